@@ -0,0 +1,124 @@
+use crate::{Campaign, CampaignWriter, ReadCampaignError, WriteCampaignError, AOE2_DE, AOE_AOK};
+use genie_scx::convert::{ConversionReport, ConvertError};
+use genie_scx::{self as scx, VersionBundle};
+use std::io::{Read, Seek, Write};
+
+/// Type for errors that could occur while converting a campaign file.
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertCampaignError {
+    /// The campaign file or a scenario inside it could not be read.
+    #[error("{}", .0)]
+    ReadError(#[from] ReadCampaignError),
+    /// A scenario could not be converted to the target version.
+    #[error("{}", .0)]
+    ConvertError(#[from] ConvertError),
+    /// A scenario could not be written in the target version.
+    #[error("{}", .0)]
+    WriteSCXError(#[from] scx::Error),
+    /// The converted campaign file could not be written.
+    #[error("{}", .0)]
+    WriteError(#[from] WriteCampaignError),
+}
+
+/// Get the file extension that scenarios for the given version use.
+fn scenario_extension(version: &VersionBundle) -> &'static str {
+    if version.is_age2_de() {
+        "aoe2scenario"
+    } else if version.is_aok() {
+        "scn"
+    } else {
+        "scx"
+    }
+}
+
+/// Replace the extension of a scenario file name.
+fn convert_filename(filename: &str, version: &VersionBundle) -> String {
+    let stem = match filename.rfind('.') {
+        Some(index) => &filename[..index],
+        None => filename,
+    };
+    format!("{}.{}", stem, scenario_extension(version))
+}
+
+impl<R> Campaign<R>
+where
+    R: Read + Seek,
+{
+    /// Convert all scenarios in this campaign to a different Age of Empires 2 version, and write
+    /// them to a new campaign file in the format of that version.
+    ///
+    /// Converting to [`VersionBundle::aoe2_de()`] creates an `.aoe2campaign` file, other versions
+    /// create a classic campaign file. The file extensions of the scenarios are changed to match
+    /// the target version. Returns the conversion report of every scenario, in order. See
+    /// [`genie_scx::convert::VersionConverter`] for details.
+    pub fn convert<W: Write>(
+        &mut self,
+        to: &VersionBundle,
+        output: W,
+    ) -> Result<(W, Vec<ConversionReport>), ConvertCampaignError> {
+        let version = if to.is_age2_de() { AOE2_DE } else { AOE_AOK };
+        let mut writer = CampaignWriter::new(self.name(), output).version(version);
+        let mut reports = vec![];
+
+        for i in 0..self.len() {
+            let mut scenario = self.by_index(i)?;
+            reports.push(scenario.convert(to.clone())?);
+
+            let mut bytes = vec![];
+            scenario.write_to_version(&mut bytes, to)?;
+            match (self.get_name(i), self.get_filename(i)) {
+                (Some(name), Some(filename)) => {
+                    writer.add_raw(name, &convert_filename(filename, to), bytes);
+                }
+                _ => return Err(WriteCampaignError::NotFoundError(i).into()),
+            }
+        }
+
+        let output = writer.flush().map_err(WriteCampaignError::from)?;
+        Ok((output, reports))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn filenames() {
+        let de = VersionBundle::aoe2_de();
+        assert_eq!(convert_filename("Tariq1.scx", &de), "Tariq1.aoe2scenario");
+        assert_eq!(
+            convert_filename("v1.2 final.scn", &de),
+            "v1.2 final.aoe2scenario"
+        );
+        assert_eq!(
+            convert_filename("A1_Tariq1.aoe2scenario", &VersionBundle::aoc()),
+            "A1_Tariq1.scx"
+        );
+        assert_eq!(
+            convert_filename("untitled", &VersionBundle::aok()),
+            "untitled.scn"
+        );
+    }
+
+    #[test]
+    fn convert_to_de() -> anyhow::Result<()> {
+        let f = File::open("./test/campaigns/DER FALL VON SACSAHUAMAN - TEIL I.cpx")?;
+        let mut cpx = Campaign::from(f)?;
+        let (output, reports) = cpx.convert(&VersionBundle::aoe2_de(), vec![])?;
+        assert_eq!(reports.len(), 1);
+
+        let mut converted = Campaign::from(Cursor::new(output))?;
+        assert_eq!(converted.version(), AOE2_DE);
+        assert_eq!(converted.name(), cpx.name());
+        assert_eq!(converted.get_name(0), cpx.get_name(0));
+        assert_eq!(
+            converted.get_filename(0),
+            Some("Der Weg nach Sacsahuamán.aoe2scenario")
+        );
+        assert!(converted.by_index(0)?.version().is_age2_de());
+        Ok(())
+    }
+}
@@ -1,7 +1,25 @@
 //! Campaign files store multiple scenario files in one easily distributable chunk.
 //!
 //! genie-cpx can read and write campaign files using the Campaign and CampaignWriter structs,
-//! respectively.
+//! respectively. [`Campaign::convert`] converts a campaign between the classic and Definitive
+//! Edition formats, converting its scenarios with genie-scx.
+//!
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use genie_cpx::Campaign;
+//! use std::fs::File;
+//!
+//! let f = File::open("test/campaigns/Rise of Egypt Learning Campaign.cpn")?;
+//! let mut campaign = Campaign::from(f)?;
+//! assert_eq!(campaign.name(), "Rise of Egypt Learning Campaign");
+//! for entry in campaign.entries() {
+//!     println!("{} ({}, {} bytes at {})", entry.name, entry.filename, entry.size, entry.offset());
+//! }
+//! assert_eq!(campaign.get_filename(0), Some("HUNTING.scn"));
+//! let scenario = campaign.by_index(0)?;
+//! println!("{:?}", scenario.version());
+//! # Ok(()) }
+//! ```
 
 #![deny(future_incompatible)]
 #![deny(nonstandard_style)]
@@ -12,9 +30,11 @@
 
 use std::io::{Read, Seek, Write};
 
+mod convert;
 mod read;
 mod write;
 
+pub use convert::ConvertCampaignError;
 pub use read::{Campaign, ReadCampaignError};
 pub use write::{CampaignWriter, WriteCampaignError};
 
@@ -64,6 +84,13 @@ pub struct ScenarioMeta {
     pub filename: String,
 }
 
+impl ScenarioMeta {
+    /// Get the offset in bytes of the scenario file within the campaign file.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
 impl<R> Campaign<R>
 where
     R: Read + Seek,
@@ -79,7 +106,21 @@ where
         output: &mut W,
         version: CPXVersion,
     ) -> Result<(), WriteCampaignError> {
-        let mut writer = CampaignWriter::new(self.name(), output).version(version);
+        let _output = self.to_writer(output)?.version(version).flush()?;
+
+        Ok(())
+    }
+
+    /// Create a campaign writer containing the scenarios of this campaign, using the same name
+    /// and version.
+    ///
+    /// The writer can be used to rename, reorder, add or remove scenarios before writing a new
+    /// campaign file.
+    pub fn to_writer<W: Write>(
+        &mut self,
+        output: W,
+    ) -> Result<CampaignWriter<W>, WriteCampaignError> {
+        let mut writer = CampaignWriter::new(self.name(), output).version(self.version());
 
         for i in 0..self.len() {
             let bytes = self
@@ -93,9 +134,7 @@ where
             }
         }
 
-        let _output = writer.flush()?;
-
-        Ok(())
+        Ok(writer)
     }
 }
 
@@ -152,4 +191,27 @@ mod tests {
         assert_eq!(written_cpx.by_index_raw(0)?, incpx.by_index_raw(0)?);
         Ok(())
     }
+
+    #[test]
+    fn edit_campaign() -> anyhow::Result<()> {
+        let instream = File::open("./test/campaigns/acam1.aoe2campaign")?;
+        let mut incpx = Campaign::from(instream)?;
+        let mut writer = incpx.to_writer(vec![])?;
+        writer.set_name("Tariq ibn Ziyad");
+        writer.move_scenario(4, 0)?;
+        writer.rename_scenario(0, "Finale")?;
+        writer.remove_scenario(1)?;
+        assert!(writer.remove_scenario(4).is_err());
+
+        let mut written_cpx = Campaign::from(Cursor::new(writer.flush()?))?;
+        assert_eq!(written_cpx.version(), AOE2_DE);
+        assert_eq!(written_cpx.name(), "Tariq ibn Ziyad");
+        assert_eq!(written_cpx.len(), 4);
+        assert_eq!(written_cpx.get_name(0), Some("Finale"));
+        assert_eq!(written_cpx.get_filename(0), incpx.get_filename(4));
+        assert_eq!(written_cpx.get_filename(1), incpx.get_filename(1));
+        assert_eq!(written_cpx.by_index_raw(0)?, incpx.by_index_raw(4)?);
+        assert_eq!(written_cpx.by_index_raw(3)?, incpx.by_index_raw(3)?);
+        Ok(())
+    }
 }
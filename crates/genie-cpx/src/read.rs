@@ -1,8 +1,7 @@
 use crate::{CPXVersion, CampaignHeader, ScenarioMeta, AOE1_DE, AOE2_DE};
 use byteorder::{ReadBytesExt, LE};
-use chardet::detect as detect_encoding;
-use encoding_rs::Encoding;
 use genie_scx::{self as scx, DLCPackage, Scenario};
+use genie_support::{decode_str_with_encoding, detect_encoding};
 use std::convert::TryFrom;
 use std::io::{self, Cursor, Read, Seek, SeekFrom};
 
@@ -26,24 +25,23 @@ pub enum ReadCampaignError {
     ParseSCXError(#[from] scx::Error),
 }
 
+impl From<ReadCampaignError> for genie_support::Error {
+    fn from(err: ReadCampaignError) -> Self {
+        match err {
+            ReadCampaignError::ParseSCXError(err) => {
+                genie_support::Error::from(err).in_file("campaign")
+            }
+            err => genie_support::Error::new("campaign", err),
+        }
+    }
+}
+
 type Result<T> = std::result::Result<T, ReadCampaignError>;
 
 /// Decode a string with unknown encoding.
 fn decode_str(bytes: &[u8]) -> Result<String> {
-    if bytes.is_empty() {
-        return Ok("".to_string());
-    }
-
-    let (encoding_name, _confidence, _language) = detect_encoding(&bytes);
-    Encoding::for_label(encoding_name.as_bytes())
-        .ok_or(ReadCampaignError::DecodeStringError)
-        .and_then(|encoding| {
-            let (decoded, _enc, failed) = encoding.decode(&bytes);
-            if failed {
-                return Err(ReadCampaignError::DecodeStringError);
-            }
-            Ok(decoded.to_string())
-        })
+    decode_str_with_encoding(bytes, detect_encoding(bytes))
+        .map_err(|_| ReadCampaignError::DecodeStringError)
 }
 
 pub fn read_fixed_str<R: Read>(input: &mut R, len: usize) -> Result<Option<String>> {
@@ -250,6 +248,11 @@ where
             .and_then(|buf| Scenario::read_from(buf).map_err(ReadCampaignError::ParseSCXError))
     }
 
+    /// Iterate over the scenarios in this campaign, in order, parsing each of them.
+    pub fn scenarios(&mut self) -> impl Iterator<Item = Result<Scenario>> + '_ {
+        (0..self.len()).map(move |index| self.by_index(index))
+    }
+
     /// Get a scenario file buffer by its file name.
     pub fn by_name_raw(&mut self, filename: &str) -> Result<Vec<u8>> {
         self.get_id(filename)
@@ -320,6 +323,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn extract_scenarios() -> anyhow::Result<()> {
+        let f = File::open("test/campaigns/Rise of Egypt Learning Campaign.cpn")?;
+        let mut c = Campaign::from(f)?;
+        // Scenario files are stored one after the other.
+        let mut offset = c.entries().next().map(|e| e.offset());
+        for entry in c.entries() {
+            assert_eq!(Some(entry.offset()), offset);
+            offset = Some(entry.offset() + entry.size);
+        }
+
+        let scenarios = c.scenarios().collect::<Result<Vec<_>>>()?;
+        assert_eq!(scenarios.len(), 12);
+        Ok(())
+    }
+
     #[test]
     fn aoe1_beta_cpn() -> anyhow::Result<()> {
         let f = File::open("test/campaigns/Rise of Egypt Learning Campaign.cpn")?;
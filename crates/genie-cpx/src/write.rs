@@ -15,10 +15,16 @@ pub enum WriteCampaignError {
     NotFoundError(usize),
 }
 
+impl From<WriteCampaignError> for genie_support::Error {
+    fn from(err: WriteCampaignError) -> Self {
+        genie_support::Error::new("campaign", err)
+    }
+}
+
 fn write_variable_str<W: Write>(output: &mut W, value: &str) -> io::Result<()> {
     output.write_u16::<LE>(0x0A60)?;
-    let len = value.as_bytes().len();
-    assert!(len < std::u16::MAX as usize);
+    let len = value.len();
+    assert!(len < u16::MAX as usize);
     output.write_u16::<LE>(len as u16)?;
     output.write_all(value.as_bytes())?;
     Ok(())
@@ -26,7 +32,7 @@ fn write_variable_str<W: Write>(output: &mut W, value: &str) -> io::Result<()> {
 
 /// Write the campaign header to the `output` stream.
 fn write_campaign_header<W: Write>(header: &CampaignHeader, output: &mut W) -> io::Result<()> {
-    assert!(header.num_scenarios < std::i32::MAX as usize);
+    assert!(header.num_scenarios < i32::MAX as usize);
 
     output.write_all(&header.version)?;
     if header.version == AOE2_DE {
@@ -63,8 +69,8 @@ fn write_campaign_header<W: Write>(header: &CampaignHeader, output: &mut W) -> i
 
 /// Write metadata for a single scenario into the `output` stream in the classic AoE/AoK format.
 fn write_scenario_meta<W: Write>(meta: &ScenarioMeta, output: &mut W) -> io::Result<()> {
-    assert!(meta.size < std::i32::MAX as usize);
-    assert!(meta.offset < std::i32::MAX as usize);
+    assert!(meta.size < i32::MAX as usize);
+    assert!(meta.offset < i32::MAX as usize);
 
     output.write_i32::<LE>(meta.size as i32)?;
     output.write_i32::<LE>(meta.offset as i32)?;
@@ -78,14 +84,16 @@ fn write_scenario_meta<W: Write>(meta: &ScenarioMeta, output: &mut W) -> io::Res
     assert!(filename_bytes.len() < 255);
     filename_bytes.extend(vec![0; 255 - filename_bytes.len()]);
     output.write_all(&filename_bytes)?;
+    // Padding to align the next entry to 4 bytes.
+    output.write_all(&[0; 2])?;
 
     Ok(())
 }
 
 /// Write metadata for a single scenario into the `output` stream, in the AoE1: DE format.
 fn write_scenario_meta_de<W: Write>(meta: &ScenarioMeta, output: &mut W) -> io::Result<()> {
-    assert!(meta.size < std::u64::MAX as usize);
-    assert!(meta.offset < std::u64::MAX as usize);
+    assert!(meta.size < u64::MAX as usize);
+    assert!(meta.offset < u64::MAX as usize);
 
     output.write_u64::<LE>(meta.size as u64)?;
     output.write_u64::<LE>(meta.offset as u64)?;
@@ -96,8 +104,8 @@ fn write_scenario_meta_de<W: Write>(meta: &ScenarioMeta, output: &mut W) -> io::
 
 /// Write metadata for a single scenario into the `output` stream, in the AoE2: DE format.
 fn write_scenario_meta_de2<W: Write>(meta: &ScenarioMeta, output: &mut W) -> io::Result<()> {
-    assert!(meta.size < std::u32::MAX as usize);
-    assert!(meta.offset < std::u32::MAX as usize);
+    assert!(meta.size < u32::MAX as usize);
+    assert!(meta.offset < u32::MAX as usize);
 
     output.write_u32::<LE>(meta.size as u32)?;
     output.write_u32::<LE>(meta.offset as u32)?;
@@ -138,6 +146,31 @@ impl CampaignEntry {
 /// A campaign file writer. Instantiate it, then add scenario files to it.
 ///
 /// This has to keep all scenario files in memory until the file is written, either on a call to `flush()` or implicitly when the struct is dropped.
+///
+/// Scenarios can be renamed, reordered and removed before the file is written. To edit an
+/// existing campaign file, load it into a writer with [`Campaign::to_writer`].
+///
+/// ```rust
+/// # fn main() -> anyhow::Result<()> {
+/// use genie_cpx::{Campaign, CampaignWriter};
+/// use std::io::Cursor;
+///
+/// let mut writer = CampaignWriter::new("Learning Campaign", vec![]);
+/// writer.add_raw("Hunting", "hunting.scn", vec![1, 2, 3]);
+/// writer.add_raw("Foraging", "foraging.scn", vec![4, 5]);
+/// writer.add_raw("Farming", "farming.scn", vec![6]);
+/// writer.move_scenario(2, 0)?;
+/// writer.rename_scenario(1, "Hunting Grounds")?;
+/// writer.remove_scenario(2)?;
+///
+/// let mut campaign = Campaign::from(Cursor::new(writer.flush()?))?;
+/// let names: Vec<_> = campaign.entries().map(|entry| &entry.name).collect();
+/// assert_eq!(names, ["Farming", "Hunting Grounds"]);
+/// assert_eq!(campaign.by_name_raw("hunting.scn")?, [1, 2, 3]);
+/// # Ok(()) }
+/// ```
+///
+/// [`Campaign::to_writer`]: crate::Campaign::to_writer
 pub struct CampaignWriter<W: Write> {
     writer: W,
     header: CampaignHeader,
@@ -188,6 +221,53 @@ impl<W: Write> CampaignWriter<W> {
         Ok(())
     }
 
+    /// Set the user-visible name of the campaign.
+    pub fn set_name(&mut self, name: &str) {
+        self.header.name = name.to_owned();
+    }
+
+    /// Get the number of scenarios in this campaign.
+    pub fn len(&self) -> usize {
+        self.scenarios.len()
+    }
+
+    /// Returns true if this campaign contains no scenario files.
+    pub fn is_empty(&self) -> bool {
+        self.scenarios.is_empty()
+    }
+
+    /// Change the user-visible name of the scenario at `index`.
+    pub fn rename_scenario(&mut self, index: usize, name: &str) -> Result<(), WriteCampaignError> {
+        let entry = self
+            .scenarios
+            .get_mut(index)
+            .ok_or(WriteCampaignError::NotFoundError(index))?;
+        entry.name = name.to_owned();
+        Ok(())
+    }
+
+    /// Move the scenario at index `from` to index `to`, shifting the scenarios in between.
+    pub fn move_scenario(&mut self, from: usize, to: usize) -> Result<(), WriteCampaignError> {
+        if from >= self.scenarios.len() {
+            return Err(WriteCampaignError::NotFoundError(from));
+        }
+        if to >= self.scenarios.len() {
+            return Err(WriteCampaignError::NotFoundError(to));
+        }
+        let entry = self.scenarios.remove(from);
+        self.scenarios.insert(to, entry);
+        Ok(())
+    }
+
+    /// Remove the scenario at `index` from this campaign.
+    pub fn remove_scenario(&mut self, index: usize) -> Result<(), WriteCampaignError> {
+        if index >= self.scenarios.len() {
+            return Err(WriteCampaignError::NotFoundError(index));
+        }
+        self.scenarios.remove(index);
+        Ok(())
+    }
+
     /// Consume the `CampaignWriter` instance, returning the inner `Write` instance.
     pub fn into_inner(self) -> W {
         self.writer
@@ -205,14 +285,14 @@ impl<W: Write> CampaignWriter<W> {
         let header_size = std::mem::size_of::<CPXVersion>()
             + std::mem::size_of::<i32>() // number of scenarios
             + 256; // campaign name
-        header_size + self.scenarios.len() * (2 * std::mem::size_of::<i32>() + 255 + 255)
+        header_size + self.scenarios.len() * (2 * std::mem::size_of::<i32>() + 255 + 255 + 2)
     }
 
     /// Get the size in bytes of all metadata for a AoE1: DE campaign file.
     fn get_meta_size_de(&self) -> usize {
         // Length of a single variable string is (4 + byte length)
         fn strlen(s: &str) -> usize {
-            s.as_bytes().len() + 4
+            s.len() + 4
         }
         let header_size = std::mem::size_of::<CPXVersion>()
             + std::mem::size_of::<u32>() // number of scenarios
@@ -226,7 +306,7 @@ impl<W: Write> CampaignWriter<W> {
     fn get_meta_size_de2(&self) -> usize {
         // Length of a single variable string is (4 + byte length)
         fn strlen(s: &str) -> usize {
-            s.as_bytes().len() + 4
+            s.len() + 4
         }
         let header_size = std::mem::size_of::<CPXVersion>()
             + 7 * std::mem::size_of::<i32>() // DLC dependencies
@@ -0,0 +1,82 @@
+//! Measures the heap memory that is retained by loaded data files, when they are read eagerly
+//! with `DatFile` and lazily with `LazyDatFile`.
+//!
+//! Run with `cargo bench -p genie-dat --bench memory`.
+
+use genie_dat::{DatFile, LazyDatFile};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// An allocator that keeps track of the number of bytes currently allocated.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of copies of each data file to keep loaded at the same time.
+const BATCH_SIZE: usize = 8;
+
+/// Load a batch of copies of a data file, and print the memory they retain and the time each load
+/// took. Returns the number of bytes retained per data file.
+fn measure<T>(
+    path: &str,
+    kind: &str,
+    load: impl Fn() -> std::io::Result<T>,
+) -> std::io::Result<usize> {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let batch = (0..BATCH_SIZE)
+        .map(|_| load())
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let elapsed = start.elapsed();
+    let retained = (ALLOCATED.load(Ordering::Relaxed) - before) / BATCH_SIZE;
+
+    println!(
+        "{} ({}): {} KiB per data file, {:?} per load",
+        path,
+        kind,
+        retained / 1024,
+        elapsed / BATCH_SIZE as u32
+    );
+    drop(batch);
+    Ok(retained)
+}
+
+fn main() -> std::io::Result<()> {
+    for path in &[
+        "fixtures/aok.dat",
+        "fixtures/aoc1.0c.dat",
+        "fixtures/hd.dat",
+    ] {
+        let bytes = std::fs::read(path)?;
+        let eager = measure(path, "eager", || DatFile::read_from(&bytes[..]))?;
+        let lazy = measure(path, "lazy", || LazyDatFile::read_from(&bytes[..]))?;
+        // The lazy reader keeps the decompressed file, and parses a table on first access.
+        measure(path, "lazy, one civilization", || {
+            let dat = LazyDatFile::read_from(&bytes[..])?;
+            dat.civilization(1)?;
+            Ok(dat)
+        })?;
+        println!(
+            "{}: lazy retains {}% of the eager memory",
+            path,
+            lazy * 100 / eager
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! Which units and techs are available to each civilization.
+//!
+//! [`Availability`] combines each civilization's tech tree effect, which disables techs and units,
+//! with the techs that enable or upgrade units, so you can look up whether a civilization can
+//! ever get a unit or research a tech. It can be exported to an [`AvailabilityTable`] for
+//! rendering tech tree charts.
+
+use crate::civ::CivilizationID;
+use crate::effect::{Command, EffectState};
+use crate::unit_type::UnitTypeID;
+use crate::DatFile;
+use genie_support::TechID;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// The kind of game object in an availability row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AvailabilityKind {
+    /// A unit or building.
+    Unit,
+    /// A tech.
+    Tech,
+}
+
+/// The availability of a single unit type or tech to each civilization.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvailabilityRow {
+    /// Whether this row describes a unit type or a tech.
+    pub kind: AvailabilityKind,
+    /// The unit type ID or tech ID.
+    pub id: u16,
+    /// The internal name of the unit type or tech.
+    pub name: String,
+    /// Whether the unit type or tech is available, indexed by civilization ID.
+    pub available: Vec<bool>,
+}
+
+/// A flat table of unit and tech availability, with one column per civilization.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvailabilityTable {
+    /// Civilization names, indexed by civilization ID.
+    pub civilizations: Vec<String>,
+    /// Unit type rows, followed by tech rows.
+    pub rows: Vec<AvailabilityRow>,
+}
+
+/// The units and techs that are available to each civilization.
+///
+/// Only unit types that can be trained or built, and techs that can be researched at a building,
+/// are included.
+#[derive(Debug, Clone)]
+pub struct Availability {
+    civilizations: Vec<String>,
+    units: Vec<AvailabilityRow>,
+    techs: Vec<AvailabilityRow>,
+    unit_index: HashMap<UnitTypeID, usize>,
+    tech_index: HashMap<TechID, usize>,
+}
+
+impl Availability {
+    /// Compute unit and tech availability for all civilizations in a data file.
+    pub fn new(dat: &DatFile) -> Self {
+        let civ_ids: Vec<CivilizationID> = (0..dat.civilizations.len())
+            .filter_map(|index| CivilizationID::try_from(index as u32).ok())
+            .collect();
+
+        let mut units = vec![];
+        let mut seen = HashSet::new();
+        for civ in &dat.civilizations {
+            for unit_type in civ.unit_types().iter().flatten() {
+                let trainable = unit_type
+                    .combat
+                    .as_ref()
+                    .is_some_and(|combat| combat.create_at_building.is_some());
+                if trainable && seen.insert(unit_type.static_.id) {
+                    units.push(AvailabilityRow {
+                        kind: AvailabilityKind::Unit,
+                        id: unit_type.static_.id.into(),
+                        name: unit_type.static_.name().to_string(),
+                        available: vec![false; civ_ids.len()],
+                    });
+                }
+            }
+        }
+        units.sort_by_key(|row| row.id);
+        let unit_index: HashMap<UnitTypeID, usize> = units
+            .iter()
+            .enumerate()
+            .map(|(index, row)| (row.id.into(), index))
+            .collect();
+
+        let mut techs = vec![];
+        let mut tech_index = HashMap::new();
+        // Tech IDs are 16 bits, so techs past that can not be referred to and are left out.
+        for (id, tech) in (0..=u16::MAX).map(TechID::from).zip(&dat.techs) {
+            if tech.location().is_none() {
+                continue;
+            }
+            tech_index.insert(id, techs.len());
+            techs.push(AvailabilityRow {
+                kind: AvailabilityKind::Tech,
+                id: id.into(),
+                name: tech.name().to_string(),
+                available: vec![false; civ_ids.len()],
+            });
+        }
+
+        for (column, &civ) in civ_ids.iter().enumerate() {
+            let disabled = dat.disabled_techs(civ);
+            let researchable: Vec<TechID> = (0..=u16::MAX)
+                .take(dat.techs.len())
+                .map(TechID::from)
+                .filter(|&tech| dat.can_research_with(civ, tech, &disabled))
+                .collect();
+
+            for tech in &researchable {
+                if let Some(&row) = tech_index.get(tech) {
+                    techs[row].available[column] = true;
+                }
+            }
+
+            for unit in enabled_units(dat, civ, &researchable) {
+                if let Some(&row) = unit_index.get(&unit) {
+                    units[row].available[column] = true;
+                }
+            }
+        }
+
+        Self {
+            civilizations: dat
+                .civilizations
+                .iter()
+                .map(|civ| civ.name().to_string())
+                .collect(),
+            units,
+            techs,
+            unit_index,
+            tech_index,
+        }
+    }
+
+    /// Get the civilization names, indexed by civilization ID.
+    pub fn civilizations(&self) -> &[String] {
+        &self.civilizations
+    }
+
+    /// Get the availability rows for unit types, ordered by ID.
+    pub fn units(&self) -> &[AvailabilityRow] {
+        &self.units
+    }
+
+    /// Get the availability rows for techs, ordered by ID.
+    pub fn techs(&self) -> &[AvailabilityRow] {
+        &self.techs
+    }
+
+    /// Check if a civilization can train or build a unit type.
+    pub fn is_unit_available(
+        &self,
+        civ: impl Into<CivilizationID>,
+        unit_type: impl Into<UnitTypeID>,
+    ) -> bool {
+        let column = usize::from(civ.into());
+        self.unit_index
+            .get(&unit_type.into())
+            .and_then(|&row| self.units[row].available.get(column))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Check if a civilization can research a tech.
+    pub fn is_tech_available(
+        &self,
+        civ: impl Into<CivilizationID>,
+        tech: impl Into<TechID>,
+    ) -> bool {
+        let column = usize::from(civ.into());
+        self.tech_index
+            .get(&tech.into())
+            .and_then(|&row| self.techs[row].available.get(column))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Export the availability data to a flat table.
+    pub fn to_table(&self) -> AvailabilityTable {
+        AvailabilityTable {
+            civilizations: self.civilizations.clone(),
+            rows: self.units.iter().chain(&self.techs).cloned().collect(),
+        }
+    }
+}
+
+/// Find the unit types that a civilization starts with, or that are enabled or upgraded to by
+/// one of its researchable techs.
+fn enabled_units(dat: &DatFile, civ: CivilizationID, techs: &[TechID]) -> HashSet<UnitTypeID> {
+    let mut enabled: HashSet<UnitTypeID> = match EffectState::new(dat, civ) {
+        Some(state) => state
+            .unit_types
+            .iter()
+            .flatten()
+            .filter(|unit_type| unit_type.static_.enabled)
+            .map(|unit_type| unit_type.static_.id)
+            .collect(),
+        None => return HashSet::new(),
+    };
+
+    let commands = techs
+        .iter()
+        .filter_map(|&tech| dat.get_tech(tech))
+        .filter_map(|tech| tech.effect_id())
+        .filter_map(|id| dat.effects.get(usize::from(id)))
+        .flat_map(|effect| &effect.commands);
+    for command in commands {
+        match command.decode() {
+            Command::EnableUnit {
+                unit,
+                enabled: true,
+            } => enabled.insert(unit),
+            Command::UpgradeUnit { to, .. } => enabled.insert(to),
+            _ => continue,
+        };
+    }
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn aoc_availability() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let availability = Availability::new(&dat);
+        assert_eq!(availability.civilizations().len(), 19);
+
+        // Britons (1), Franks (2). Longbowman (8), Throwing Axeman (281).
+        assert!(availability.is_unit_available(1, 8u16));
+        assert!(!availability.is_unit_available(2, 8u16));
+        assert!(availability.is_unit_available(2, 281u16));
+        assert!(!availability.is_unit_available(1, 281u16));
+        // Archer (4) is available to everyone.
+        assert!(availability.is_unit_available(1, 4u16));
+        assert!(availability.is_unit_available(2, 4u16));
+        // Yeomen (3) is a Briton unique tech.
+        assert!(availability.is_tech_available(1, 3u16));
+        assert!(!availability.is_tech_available(2, 3u16));
+
+        let table = availability.to_table();
+        assert_eq!(
+            table.rows.len(),
+            availability.units().len() + availability.techs().len()
+        );
+        assert!(table.rows.iter().all(|row| row.available.len() == 19));
+        Ok(())
+    }
+}
@@ -1,16 +1,22 @@
 //! Types related to civilizations.
 
-use crate::unit_type::{UnitType, UnitTypeID};
+use crate::unit_type::{UnitBaseClass, UnitClass, UnitType, UnitTypeID};
+use crate::unknown::RawBytes;
 use crate::GameVersion;
 use arrayvec::ArrayString;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use encoding_rs::WINDOWS_1252;
-use genie_support::{fallible_try_from, infallible_try_into, read_opt_u16};
-use std::convert::TryInto;
-use std::io::{Read, Result, Write};
+use genie_support::{
+    fallible_try_from, infallible_try_into, read_opt_u16, write_fixed_str_with_encoding,
+    write_opt_u16, ReadStringsExt, TryConvertExt, DEFAULT_ENCODING,
+};
+pub use genie_support::{AttributeID, ResourceID};
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::sync::{Arc, OnceLock};
 
 /// An ID identifying a civilization
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CivilizationID(u8);
 
 impl From<u8> for CivilizationID {
@@ -55,13 +61,56 @@ type CivName = ArrayString<20>;
 
 /// Information about a civilization.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Civilization {
     name: CivName,
     attributes: Vec<f32>,
     civ_effect: u16,
     bonus_effect: Option<u16>,
+    /// Secondary name, only used by Star Wars: Galactic Battlegrounds.
+    name2: Option<CivName>,
+    /// Unique unit techs, only used by Star Wars: Galactic Battlegrounds.
+    unique_unit_techs: Option<[i16; 4]>,
     culture: u8,
     unit_types: Vec<Option<UnitType>>,
+    /// Pointers to the unit types. Only the existence of a unit type is stored in the unit types
+    /// table.
+    pub unknown_unit_type_pointers: RawBytes,
+    /// Lookup tables for the unit type search functions, built on first use.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    index: OnceLock<UnitTypeIndex>,
+}
+
+/// Lookup tables for unit types, so searches do not have to scan all unit types.
+#[derive(Debug, Default, Clone)]
+struct UnitTypeIndex {
+    by_class: HashMap<UnitClass, Vec<UnitTypeID>>,
+    by_base_class: HashMap<UnitBaseClass, Vec<UnitTypeID>>,
+    by_name: HashMap<Arc<str>, UnitTypeID>,
+}
+
+impl UnitTypeIndex {
+    fn build(unit_types: &[Option<UnitType>]) -> Self {
+        let mut index = Self::default();
+        for unit_type in unit_types.iter().flatten() {
+            let id = unit_type.static_.id;
+            index
+                .by_class
+                .entry(unit_type.static_.unit_class)
+                .or_default()
+                .push(id);
+            index
+                .by_base_class
+                .entry(unit_type.unit_base_class)
+                .or_default()
+                .push(id);
+            index
+                .by_name
+                .entry(unit_type.static_.name().into())
+                .or_insert(id);
+        }
+        index
+    }
 }
 
 impl Civilization {
@@ -70,17 +119,97 @@ impl Civilization {
         self.name.as_str()
     }
 
+    /// Get the secondary name of this civilization. Only Star Wars: Galactic Battlegrounds
+    /// civilizations have a secondary name.
+    pub fn name2(&self) -> Option<&str> {
+        self.name2.as_ref().map(|name| name.as_str())
+    }
+
+    /// Get the ID of the tech effect that applies this civilization's tech tree and bonuses.
+    pub fn civ_effect(&self) -> u16 {
+        self.civ_effect
+    }
+
+    /// Get the ID of the tech effect that applies this civilization's team bonus.
+    pub fn bonus_effect(&self) -> Option<u16> {
+        self.bonus_effect
+    }
+
+    /// Get the starting values of this civilization's player attributes, such as resources.
+    pub fn attributes(&self) -> &[f32] {
+        &self.attributes
+    }
+
+    /// Get the starting value of a single player attribute.
+    pub fn attribute(&self, id: AttributeID) -> Option<f32> {
+        self.attributes.get(usize::from(id)).copied()
+    }
+
+    /// Get the unit types available to this civilization, indexed by their ID.
+    pub fn unit_types(&self) -> &[Option<UnitType>] {
+        &self.unit_types
+    }
+
+    /// Get mutable access to the unit types table of this civilization.
+    pub(crate) fn unit_types_mut(&mut self) -> &mut Vec<Option<UnitType>> {
+        self.index.take();
+        &mut self.unit_types
+    }
+
+    fn index(&self) -> &UnitTypeIndex {
+        self.index
+            .get_or_init(|| UnitTypeIndex::build(&self.unit_types))
+    }
+
+    fn unit_types_by_id<'a>(
+        &'a self,
+        ids: Option<&'a Vec<UnitTypeID>>,
+    ) -> impl Iterator<Item = &'a UnitType> {
+        ids.into_iter()
+            .flatten()
+            .filter_map(move |&id| self.get_unit_type(id))
+    }
+
+    /// Iterate over the unit types of a unit class, such as archers or buildings.
+    pub fn units_of_class(&self, class: UnitClass) -> impl Iterator<Item = &UnitType> {
+        self.unit_types_by_id(self.index().by_class.get(&class))
+    }
+
+    /// Iterate over the unit types with a base class, such as combat units or buildings.
+    ///
+    /// The base class determines which attributes a unit type has. Unit types whose base class
+    /// inherits from `base_class` are not included.
+    pub fn units_with_base_class(
+        &self,
+        base_class: UnitBaseClass,
+    ) -> impl Iterator<Item = &UnitType> {
+        self.unit_types_by_id(self.index().by_base_class.get(&base_class))
+    }
+
+    /// Find a unit type by its internal name. If several unit types have the same name, the one
+    /// with the lowest ID is returned.
+    pub fn find_by_internal_name(&self, name: &str) -> Option<&UnitType> {
+        let id = *self.index().by_name.get(name)?;
+        self.get_unit_type(id)
+    }
+
     /// Read civilization data from an input stream.
     pub fn read_from(mut input: impl Read, version: GameVersion) -> Result<Self> {
-        let mut civ = Self::default();
-        let mut bytes = [0; 20];
-        input.read_exact(&mut bytes)?;
-        let bytes = &bytes[..bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len())];
-        let (name, _encoding, _failed) = WINDOWS_1252.decode(&bytes);
-        civ.name = CivName::from(&name).unwrap();
+        let mut civ = Self {
+            name: read_civ_name(&mut input)?,
+            ..Default::default()
+        };
         let num_attributes = input.read_u16::<LE>()?;
         civ.civ_effect = input.read_u16::<LE>()?;
         civ.bonus_effect = read_opt_u16(&mut input)?;
+        if version.is_swgb() {
+            civ.name2 = Some(read_civ_name(&mut input)?);
+            let mut techs = [0; 4];
+            for tech in techs.iter_mut() {
+                *tech = input.read_i16::<LE>()?;
+            }
+            civ.unique_unit_techs = Some(techs);
+        }
 
         civ.attributes.reserve(num_attributes as usize);
         for _ in 0..num_attributes {
@@ -90,51 +219,101 @@ impl Civilization {
         civ.culture = input.read_u8()?;
 
         let num_unit_types = input.read_u16::<LE>()?;
-        let have_unit_types = {
-            let mut list = vec![];
-            for _ in 0..num_unit_types {
-                list.push(input.read_u32::<LE>()? != 0);
-            }
-            list
-        };
+        let mut unit_type_pointers = vec![0; 4 * usize::from(num_unit_types)];
+        input.read_exact(&mut unit_type_pointers)?;
+        civ.unknown_unit_type_pointers = RawBytes::new(&unit_type_pointers);
+        let have_unit_types = unit_type_pointers
+            .chunks(4)
+            .map(|pointer| pointer != [0; 4]);
         for do_read in have_unit_types {
             if !do_read {
                 civ.unit_types.push(None);
                 continue;
             }
-            civ.unit_types
-                .push(Some(UnitType::read_from(&mut input, version.as_f32())?));
+            let unit_type = if version.is_swgb() {
+                UnitType::read_from_swgb(&mut input, version.data_version())?
+            } else {
+                UnitType::read_from(&mut input, version.data_version())?
+            };
+            civ.unit_types.push(Some(unit_type));
         }
 
         Ok(civ)
     }
 
+    /// Skip over civilization data in an input stream, without reading its unit types.
+    pub(crate) fn skip(mut input: impl Read + Seek, version: GameVersion) -> Result<()> {
+        input.seek(SeekFrom::Current(20))?;
+        let num_attributes = input.read_u16::<LE>()?;
+        let mut dist = 4 + 4 * i64::from(num_attributes) + 1;
+        if version.is_swgb() {
+            dist += 20 + 8;
+        }
+        input.seek(SeekFrom::Current(dist))?;
+
+        let num_unit_types = input.read_u16::<LE>()?;
+        let mut have_unit_types = vec![];
+        for _ in 0..num_unit_types {
+            have_unit_types.push(input.read_u32::<LE>()? != 0);
+        }
+        for _ in have_unit_types.into_iter().filter(|&exists| exists) {
+            UnitType::skip(&mut input, version.data_version(), version.is_swgb())?;
+        }
+        Ok(())
+    }
+
     /// Write civilization data to an output stream.
     pub fn write_to(&self, mut output: impl Write, version: GameVersion) -> Result<()> {
-        let mut name = [0; 20];
-        (&mut name[..self.name.len()]).copy_from_slice(self.name.as_bytes());
-        output.write_all(&name)?;
-        output.write_u16::<LE>(self.attributes.len().try_into().unwrap())?;
+        self.write_header_to(&mut output, version)?;
+
+        output.write_u16::<LE>(self.unit_types.len().try_convert()?)?;
+        self.unknown_unit_type_pointers
+            .write_pointers_to(&mut output, &self.unit_types)?;
+        for unit_type in self.unit_types.iter().flatten() {
+            if version.is_swgb() {
+                unit_type.write_to_swgb(&mut output, version.data_version())?;
+            } else {
+                unit_type.write_to(&mut output, version.data_version())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the civilization's own data, without its unit types.
+    pub(crate) fn write_header_to(
+        &self,
+        mut output: impl Write,
+        version: GameVersion,
+    ) -> Result<()> {
+        write_civ_name(&mut output, &self.name)?;
+        output.write_u16::<LE>(self.attributes.len().try_convert()?)?;
         output.write_u16::<LE>(self.civ_effect)?;
-        output.write_u16::<LE>(self.bonus_effect.unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.bonus_effect)?;
+        if version.is_swgb() {
+            write_civ_name(&mut output, &self.name2.unwrap_or_default())?;
+            for tech in &self.unique_unit_techs.unwrap_or([-1; 4]) {
+                output.write_i16::<LE>(*tech)?;
+            }
+        }
         for v in self.attributes.iter() {
             output.write_f32::<LE>(*v)?;
         }
         output.write_u8(self.culture)?;
-
-        output.write_u16::<LE>(self.unit_types.len().try_into().unwrap())?;
-        for opt in &self.unit_types {
-            output.write_u32::<LE>(match opt {
-                Some(_) => 1,
-                None => 0,
-            })?;
-        }
-        for unit_type in self.unit_types.iter().flatten() {
-            unit_type.write_to(&mut output, version.as_f32())?;
-        }
         Ok(())
     }
 
+    /// Replace this civilization's own data with that of another civilization, keeping the unit
+    /// types.
+    pub(crate) fn copy_header_from(&mut self, other: &Civilization) {
+        self.name = other.name;
+        self.attributes = other.attributes.clone();
+        self.civ_effect = other.civ_effect;
+        self.bonus_effect = other.bonus_effect;
+        self.name2 = other.name2;
+        self.unique_unit_techs = other.unique_unit_techs;
+        self.culture = other.culture;
+    }
+
     /// Get a unit type by its ID.
     pub fn get_unit_type(&self, id: impl Into<UnitTypeID>) -> Option<&UnitType> {
         let id: UnitTypeID = id.into();
@@ -143,3 +322,84 @@ impl Civilization {
             .and_then(Option::as_ref)
     }
 }
+
+/// Let unit types with the same names share a single copy of those names.
+///
+/// Every civilization has its own copy of most unit types, so this keeps only one copy of each
+/// unit type name instead of one per civilization.
+pub(crate) fn share_unit_names(civilizations: &mut [Civilization]) {
+    let mut names = HashSet::new();
+    for civ in civilizations {
+        for unit_type in civ.unit_types.iter_mut().flatten() {
+            unit_type.static_.share_names(&mut names);
+        }
+    }
+}
+
+fn read_civ_name(mut input: impl Read) -> Result<CivName> {
+    let name = input.read_str(20)?.unwrap_or_default();
+    CivName::from(&name).map_err(|e| Error::new(ErrorKind::InvalidData, e.simplify()))
+}
+
+fn write_civ_name(mut output: impl Write, name: &CivName) -> Result<()> {
+    write_fixed_str_with_encoding(&mut output, name, 20, DEFAULT_ENCODING)?;
+    Ok(())
+}
+
+genie_support::impl_versioned! {
+    Civilization: GameVersion => std::io::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unit_type::UnitTypeID;
+    use crate::DatFile;
+    use std::fs::File;
+
+    #[test]
+    fn search_unit_types() -> anyhow::Result<()> {
+        use crate::unit_type::{unit_class, UnitBaseClass};
+
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        let britons = &dat.civilizations[1];
+        // Archer (4), Crossbowman (24), Arbalest (492).
+        let archers: Vec<_> = britons
+            .units_of_class(unit_class::ARCHER)
+            .map(|unit| unit.static_.id)
+            .collect();
+        assert!(archers.contains(&UnitTypeID::from(4)));
+        assert!(archers.contains(&UnitTypeID::from(24)));
+        assert!(archers.contains(&UnitTypeID::from(492)));
+        assert!(britons
+            .units_with_base_class(UnitBaseClass::Building)
+            .all(|unit| unit.building.is_some()));
+        let archer = britons.find_by_internal_name("ARCHR").unwrap();
+        assert_eq!(archer.static_.id, UnitTypeID::from(4));
+        assert!(britons.find_by_internal_name("NOT A UNIT").is_none());
+
+        // Changing the unit types rebuilds the index.
+        let britons = &mut dat.civilizations[1];
+        britons.unit_types_mut()[4]
+            .as_mut()
+            .unwrap()
+            .static_
+            .set_name("BOWMN");
+        assert!(britons.find_by_internal_name("ARCHR").is_none());
+        assert!(britons.find_by_internal_name("BOWMN").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn unit_names_are_shared() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let britons = dat.civilizations[1].get_unit_type(4).unwrap();
+        let franks = dat.civilizations[2].get_unit_type(4).unwrap();
+        assert_eq!(
+            britons.static_.name().as_ptr(),
+            franks.static_.name().as_ptr()
+        );
+        Ok(())
+    }
+}
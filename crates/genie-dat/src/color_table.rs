@@ -1,10 +1,11 @@
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use genie_support::TryConvertExt;
 pub use jascpal::PaletteIndex;
-use std::convert::TryInto;
 use std::io::{Read, Result, Write};
 
 /// Player colour data.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ColorTable {
     pub id: i32,
     /// Base palette index for this player colour.
@@ -22,16 +23,16 @@ impl ColorTable {
     /// Read a colour table from an input stream.
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let id = input.read_i32::<LE>()?;
-        let base = input.read_i32::<LE>()?.try_into().unwrap();
-        let unit_outline_color = input.read_i32::<LE>()?.try_into().unwrap();
+        let base = input.read_i32::<LE>()?.try_convert()?;
+        let unit_outline_color = input.read_i32::<LE>()?.try_convert()?;
         let unit_selection_colors = (
-            input.read_i32::<LE>()?.try_into().unwrap(),
-            input.read_i32::<LE>()?.try_into().unwrap(),
+            input.read_i32::<LE>()?.try_convert()?,
+            input.read_i32::<LE>()?.try_convert()?,
         );
         let minimap_colors = (
-            input.read_i32::<LE>()?.try_into().unwrap(),
-            input.read_i32::<LE>()?.try_into().unwrap(),
-            input.read_i32::<LE>()?.try_into().unwrap(),
+            input.read_i32::<LE>()?.try_convert()?,
+            input.read_i32::<LE>()?.try_convert()?,
+            input.read_i32::<LE>()?.try_convert()?,
         );
         let statistics_text_color = input.read_i32::<LE>()?;
 
@@ -48,14 +49,18 @@ impl ColorTable {
     /// Write this colour table to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_i32::<LE>(self.id)?;
-        output.write_i32::<LE>(self.base.try_into().unwrap())?;
-        output.write_i32::<LE>(self.unit_outline_color.try_into().unwrap())?;
-        output.write_i32::<LE>(self.unit_selection_colors.0.try_into().unwrap())?;
-        output.write_i32::<LE>(self.unit_selection_colors.1.try_into().unwrap())?;
-        output.write_i32::<LE>(self.minimap_colors.0.try_into().unwrap())?;
-        output.write_i32::<LE>(self.minimap_colors.1.try_into().unwrap())?;
-        output.write_i32::<LE>(self.minimap_colors.2.try_into().unwrap())?;
+        output.write_i32::<LE>(self.base.into())?;
+        output.write_i32::<LE>(self.unit_outline_color.into())?;
+        output.write_i32::<LE>(self.unit_selection_colors.0.into())?;
+        output.write_i32::<LE>(self.unit_selection_colors.1.into())?;
+        output.write_i32::<LE>(self.minimap_colors.0.into())?;
+        output.write_i32::<LE>(self.minimap_colors.1.into())?;
+        output.write_i32::<LE>(self.minimap_colors.2.into())?;
         output.write_i32::<LE>(self.statistics_text_color)?;
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    ColorTable => std::io::Error;
+}
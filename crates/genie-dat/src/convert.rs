@@ -0,0 +1,265 @@
+//! Conversion of data files between game versions.
+//!
+//! Age of Empires II: The Age of Kings, The Conquerors and HD Edition data files share most of
+//! their layout, but differ in the number of terrains and in a few unit type fields. Converting a
+//! data file adds defaults for fields that the target version has and the source does not, and
+//! drops fields that the target version does not support. Every value that is lost is reported.
+//!
+//! UserPatch data files use the layout of The Conquerors 1.0c, so they can be targeted by
+//! converting to [`GameVersion::AoC`].
+
+use crate::civ::CivilizationID;
+use crate::terrain::{Terrain, TerrainID};
+use crate::unit_type::{UnitType, UnitTypeID};
+use crate::{DatFile, GameVersion};
+use genie_support::SaveVersion;
+use std::fmt;
+
+/// A value that was added or lost while converting a data file to a different game version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionNote {
+    /// Disabled terrains were added to fill the terrain table of the target version.
+    AddedTerrains(usize),
+    /// An enabled terrain was dropped, because the target version supports fewer terrains.
+    DroppedTerrain(TerrainID),
+    /// A unit type field that does not exist in the target version was dropped.
+    DroppedField {
+        civilization: CivilizationID,
+        unit_type: UnitTypeID,
+        field: &'static str,
+    },
+    /// A unit type field was clamped to the range that the target version supports.
+    ClampedField {
+        civilization: CivilizationID,
+        unit_type: UnitTypeID,
+        field: &'static str,
+    },
+}
+
+impl fmt::Display for ConversionNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddedTerrains(count) => write!(f, "added {} disabled terrains", count),
+            Self::DroppedTerrain(id) => write!(f, "dropped terrain {}", u16::from(*id)),
+            Self::DroppedField {
+                civilization,
+                unit_type,
+                field,
+            } => write!(
+                f,
+                "unit type {} (civilization {}): dropped {}",
+                u16::from(*unit_type),
+                u8::from(*civilization),
+                field
+            ),
+            Self::ClampedField {
+                civilization,
+                unit_type,
+                field,
+            } => write!(
+                f,
+                "unit type {} (civilization {}): clamped {}",
+                u16::from(*unit_type),
+                u8::from(*civilization),
+                field
+            ),
+        }
+    }
+}
+
+/// The data file can not be converted to the requested game version.
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("cannot convert {from:?} data files to {to:?}")]
+pub struct UnsupportedConversionError {
+    pub from: GameVersion,
+    pub to: GameVersion,
+}
+
+/// Get the number of terrains in the terrain restriction tables and in the terrain table for a
+/// game version.
+fn terrain_counts(version: GameVersion) -> Option<(u16, u16)> {
+    match version {
+        GameVersion::AoK => Some((32, 32)),
+        // AoC hardcodes 42 terrains, but only has restriction data for 41 terrains.
+        GameVersion::AoC => Some((41, 42)),
+        GameVersion::HD => Some((100, 100)),
+        GameVersion::SWGB => None,
+    }
+}
+
+/// Drop or clamp unit type fields that do not exist in a data version.
+fn convert_unit_type(
+    unit: &mut UnitType,
+    civilization: CivilizationID,
+    version: SaveVersion,
+    notes: &mut Vec<ConversionNote>,
+) {
+    let unit_type = unit.static_.id;
+    let dropped = |field| ConversionNote::DroppedField {
+        civilization,
+        unit_type,
+        field,
+    };
+
+    if version < 11.55 && unit.static_.object_flags != 0 {
+        unit.static_.object_flags = 0;
+        notes.push(dropped("object_flags"));
+    }
+    if let Some(building) = &mut unit.building {
+        if version < 11.53 && building.snow_sprite.take().is_some() {
+            notes.push(dropped("snow_sprite"));
+        }
+    }
+    if let Some(base_combat) = &mut unit.base_combat {
+        if version < 11.52 && base_combat.base_armor > u16::from(u8::MAX) {
+            base_combat.base_armor = u8::MAX.into();
+            notes.push(ConversionNote::ClampedField {
+                civilization,
+                unit_type,
+                field: "base_armor",
+            });
+        }
+    }
+}
+
+impl DatFile {
+    /// Get the game version targeted by this data file.
+    pub fn game_version(&self) -> GameVersion {
+        self.game_version
+    }
+
+    /// Convert this data file to the layout of a different game version.
+    ///
+    /// Returns the values that were added or lost. Conversions from and to Star Wars: Galactic
+    /// Battlegrounds are not supported.
+    pub fn convert_to(
+        &mut self,
+        target: GameVersion,
+    ) -> Result<Vec<ConversionNote>, UnsupportedConversionError> {
+        let error = UnsupportedConversionError {
+            from: self.game_version,
+            to: target,
+        };
+        if self.game_version == target {
+            return Ok(vec![]);
+        }
+        if terrain_counts(self.game_version).is_none() {
+            return Err(error);
+        }
+        let (num_restriction_terrains, num_terrains) = terrain_counts(target).ok_or(error)?;
+
+        let mut notes = vec![];
+        let old_num_terrains = self.terrains.len();
+        let new_num_terrains = usize::from(num_terrains);
+        if old_num_terrains > new_num_terrains {
+            for (index, terrain) in self.terrains.iter().enumerate().skip(new_num_terrains) {
+                if terrain.enabled {
+                    notes.push(ConversionNote::DroppedTerrain(TerrainID::from(
+                        index as u16,
+                    )));
+                }
+            }
+            self.terrains.truncate(new_num_terrains);
+        } else if old_num_terrains < new_num_terrains {
+            notes.push(ConversionNote::AddedTerrains(
+                new_num_terrains - old_num_terrains,
+            ));
+            self.terrains
+                .resize_with(new_num_terrains, || Terrain::disabled(num_terrains));
+        }
+        for terrain in &mut self.terrains {
+            terrain.borders.resize(new_num_terrains, 0);
+        }
+        for table in &mut self.terrain_tables {
+            table.resize(num_restriction_terrains);
+        }
+
+        let version = target.data_version();
+        for (index, civ) in self.civilizations.iter_mut().enumerate() {
+            let civilization = CivilizationID::from(index as u8);
+            for unit in civ.unit_types_mut().iter_mut().flatten() {
+                convert_unit_type(unit, civilization, version, &mut notes);
+            }
+        }
+
+        self.game_version = target;
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn reread(dat: &DatFile) -> anyhow::Result<DatFile> {
+        let mut bytes = vec![];
+        dat.write_to(&mut bytes)?;
+        Ok(DatFile::read_from(&bytes[..])?)
+    }
+
+    #[test]
+    fn upgrade_aok_to_aoc() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aok.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+
+        let notes = dat.convert_to(GameVersion::AoC)?;
+        assert_eq!(notes, vec![ConversionNote::AddedTerrains(10)]);
+
+        let dat = reread(&dat)?;
+        assert_eq!(dat.game_version(), GameVersion::AoC);
+        assert_eq!(dat.terrains.len(), 42);
+        assert_eq!(dat.terrain_tables[0].num_terrains(), 41);
+        Ok(())
+    }
+
+    #[test]
+    fn downgrade_aoc_to_aok() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        let num_civs = dat.civilizations.len();
+
+        let notes = dat.convert_to(GameVersion::AoK)?;
+        assert!(notes.contains(&ConversionNote::DroppedTerrain(32u16.into())));
+        // Castles have a snow sprite in AoC.
+        assert!(notes.contains(&ConversionNote::DroppedField {
+            civilization: 1.into(),
+            unit_type: 82.into(),
+            field: "snow_sprite",
+        }));
+
+        let dat = reread(&dat)?;
+        assert_eq!(dat.game_version(), GameVersion::AoK);
+        assert_eq!(dat.terrains.len(), 32);
+        assert_eq!(dat.civilizations.len(), num_civs);
+        Ok(())
+    }
+
+    #[test]
+    fn downgrade_hd_to_aoc() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/hd.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+
+        let notes = dat.convert_to(GameVersion::AoC)?;
+        assert_eq!(
+            notes
+                .iter()
+                .filter(|note| matches!(note, ConversionNote::DroppedTerrain(_)))
+                .count(),
+            58
+        );
+
+        let dat = reread(&dat)?;
+        assert_eq!(dat.game_version(), GameVersion::AoC);
+        assert_eq!(dat.terrains.len(), 42);
+        Ok(())
+    }
+
+    #[test]
+    fn swgb_is_unsupported() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        assert!(dat.convert_to(GameVersion::SWGB).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Human-readable text dumps of data files.
+//!
+//! A dump lists the most important values of civilizations, unit types, techs and tech effects,
+//! one value per line, with references to other entries resolved to their names. Entries are
+//! always written in ID order, so dumps of two versions of a data file can be compared with a
+//! regular text diff.
+//!
+//! Most unit types are the same for every civilization. A unit type is listed once for each
+//! distinct version of it, followed by the civilizations that use that version.
+
+use crate::effect::{Command, ModifyMode};
+use crate::tech::{EffectCommand, Tech, TechEffect};
+use crate::unit_type::{UnitClass, UnitType, UnitTypeID, WeaponInfo};
+use crate::DatFile;
+use genie_support::{AttributeID, StringSource, TechID};
+use std::fmt::{self, Write as _};
+use std::io::{Result, Write};
+
+/// A table that can be included in a dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpTable {
+    Civilizations,
+    UnitTypes,
+    Techs,
+    Effects,
+}
+
+impl DumpTable {
+    const ALL: [DumpTable; 4] = [
+        DumpTable::Civilizations,
+        DumpTable::UnitTypes,
+        DumpTable::Techs,
+        DumpTable::Effects,
+    ];
+}
+
+/// A text dump of a data file.
+///
+/// The dump is written by its `Display` implementation, or by [`Dump::write_to`].
+#[derive(Clone, Copy)]
+pub struct Dump<'a> {
+    dat: &'a DatFile,
+    strings: Option<&'a dyn StringSource>,
+    tables: [bool; 4],
+}
+
+impl fmt::Debug for Dump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dump")
+            .field("tables", &self.tables)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DatFile {
+    /// Create a text dump of this data file. By default, the dump contains all supported tables.
+    pub fn dump(&self) -> Dump<'_> {
+        Dump {
+            dat: self,
+            strings: None,
+            tables: [true; 4],
+        }
+    }
+}
+
+impl<'a> Dump<'a> {
+    /// Include display names from a language file.
+    pub fn strings(mut self, strings: &'a dyn StringSource) -> Self {
+        self.strings = Some(strings);
+        self
+    }
+
+    /// Only include the given tables.
+    pub fn tables(mut self, tables: &[DumpTable]) -> Self {
+        for (index, table) in DumpTable::ALL.iter().enumerate() {
+            self.tables[index] = tables.contains(table);
+        }
+        self
+    }
+
+    /// Write the dump to an output stream.
+    pub fn write_to(&self, mut output: impl Write) -> Result<()> {
+        write!(output, "{}", self)
+    }
+
+    fn includes(&self, table: DumpTable) -> bool {
+        let index = DumpTable::ALL.iter().position(|t| *t == table).unwrap();
+        self.tables[index]
+    }
+
+    /// Format a reference to a unit type, including its name if it exists.
+    fn unit_ref(&self, id: UnitTypeID) -> String {
+        let unit = self
+            .dat
+            .civilizations
+            .iter()
+            .find_map(|civ| civ.get_unit_type(id));
+        match unit {
+            Some(unit) => format!("{} \"{}\"", u16::from(id), unit.static_.name()),
+            None => format!("{} (missing)", u16::from(id)),
+        }
+    }
+
+    /// Format a reference to a tech, including its name if it exists.
+    fn tech_ref(&self, id: TechID) -> String {
+        match self.dat.get_tech(id) {
+            Some(tech) => format!("{} \"{}\"", u16::from(id), tech.name()),
+            None => format!("{} (missing)", u16::from(id)),
+        }
+    }
+
+    /// Format a reference to a tech effect, including its name if it exists.
+    fn effect_ref(&self, id: u16) -> String {
+        match self.dat.effects.get(usize::from(id)) {
+            Some(effect) => format!("{} \"{}\"", id, effect.name()),
+            None => format!("{} (missing)", id),
+        }
+    }
+
+    fn write_civilizations(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, civ) in self.dat.civilizations.iter().enumerate() {
+            writeln!(f, "civilization {} \"{}\"", index, civ.name())?;
+            writeln!(f, "  tech tree: {}", self.effect_ref(civ.civ_effect()))?;
+            if let Some(bonus) = civ.bonus_effect() {
+                writeln!(f, "  team bonus: {}", self.effect_ref(bonus))?;
+            }
+            let num_units = civ.unit_types().iter().flatten().count();
+            writeln!(f, "  unit types: {}", num_units)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    fn write_unit_types(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let civs = &self.dat.civilizations;
+        let num_unit_types = civs
+            .iter()
+            .map(|civ| civ.unit_types().len())
+            .max()
+            .unwrap_or(0);
+        for id in 0..num_unit_types {
+            // Group the civilizations that have identical versions of this unit type.
+            let mut versions: Vec<(&UnitType, String, Vec<usize>)> = vec![];
+            for (civ_id, civ) in civs.iter().enumerate() {
+                let unit = match civ.unit_types().get(id).and_then(Option::as_ref) {
+                    Some(unit) => unit,
+                    None => continue,
+                };
+                let body = self.unit_body(unit)?;
+                match versions.iter_mut().find(|(_, other, _)| *other == body) {
+                    Some((_, _, civ_ids)) => civ_ids.push(civ_id),
+                    None => versions.push((unit, body, vec![civ_id])),
+                }
+            }
+            for (unit, body, civ_ids) in versions {
+                write!(f, "unit {} \"{}\"", id, unit.static_.name())?;
+                if let Some(name) = self.strings.and_then(|strings| unit.display_name(strings)) {
+                    write!(f, " ({})", name)?;
+                }
+                writeln!(f, " [civilizations {}]", format_ranges(&civ_ids))?;
+                writeln!(f, "{}", body)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unit_body(&self, unit: &UnitType) -> std::result::Result<String, fmt::Error> {
+        let static_ = &unit.static_;
+        let mut out = String::new();
+        writeln!(out, "  base class: {:?}", unit.unit_base_class)?;
+        writeln!(out, "  class: {}", static_.unit_class)?;
+        writeln!(out, "  enabled: {}", static_.enabled)?;
+        writeln!(out, "  hp: {}", static_.hp)?;
+        writeln!(out, "  line of sight: {}", static_.los)?;
+        if static_.garrison_capacity > 0 {
+            writeln!(out, "  garrison capacity: {}", static_.garrison_capacity)?;
+        }
+        if let Some(animated) = &unit.animated {
+            writeln!(out, "  speed: {}", animated.speed)?;
+        }
+        if let Some(action) = &unit.action {
+            writeln!(out, "  work rate: {}", action.work_rate)?;
+            writeln!(out, "  search radius: {}", action.search_radius)?;
+        }
+        if let Some(combat) = &unit.base_combat {
+            writeln!(out, "  attack: {}", format_weapons(&combat.weapons))?;
+            writeln!(out, "  armor: {}", format_weapons(&combat.armors))?;
+            writeln!(
+                out,
+                "  range: {}-{}",
+                combat.weapon_range_min, combat.weapon_range_max
+            )?;
+            writeln!(out, "  reload time: {}", combat.attack_speed)?;
+            writeln!(out, "  accuracy: {}", combat.base_hit_chance)?;
+            if let Some(missile) = combat.missile_id {
+                writeln!(out, "  projectile: {}", self.unit_ref(missile))?;
+            }
+        }
+        if let Some(combat) = &unit.combat {
+            let costs = combat
+                .costs
+                .iter()
+                .map(|cost| format!("{} {}", resource_name(cost.attribute_type), cost.amount))
+                .collect::<Vec<_>>();
+            writeln!(out, "  cost: {}", costs.join(", "))?;
+            writeln!(out, "  train time: {}", combat.create_time)?;
+            if let Some(building) = combat.create_at_building {
+                writeln!(out, "  train location: {}", self.unit_ref(building))?;
+            }
+        }
+        Ok(out)
+    }
+
+    fn write_techs(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (id, tech) in self.dat.techs.iter().enumerate() {
+            write!(f, "tech {} \"{}\"", id, tech.name())?;
+            if let Some(name) = self.strings.and_then(|strings| tech.display_name(strings)) {
+                write!(f, " ({})", name)?;
+            }
+            writeln!(f)?;
+            self.write_tech_body(f, tech)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    fn write_tech_body(&self, f: &mut fmt::Formatter<'_>, tech: &Tech) -> fmt::Result {
+        if let Some(civ) = tech.civilization_id() {
+            writeln!(f, "  civilization: {}", u8::from(civ))?;
+        }
+        if let Some(location) = tech.location() {
+            writeln!(f, "  location: {}", self.unit_ref(location))?;
+        }
+        for &required in tech.required_techs() {
+            writeln!(f, "  requires: {}", self.tech_ref(required))?;
+        }
+        let costs = tech
+            .costs()
+            .iter()
+            .map(|cost| format!("{} {}", resource_name(cost.effect_type), cost.amount))
+            .collect::<Vec<_>>();
+        writeln!(f, "  cost: {}", costs.join(", "))?;
+        writeln!(f, "  research time: {}", tech.research_time())?;
+        if let Some(effect) = tech.effect_id() {
+            writeln!(f, "  effect: {}", self.effect_ref(effect))?;
+        }
+        Ok(())
+    }
+
+    fn write_effects(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (id, effect) in self.dat.effects.iter().enumerate() {
+            self.write_effect(f, id, effect)?;
+        }
+        Ok(())
+    }
+
+    fn write_effect(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        id: usize,
+        effect: &TechEffect,
+    ) -> fmt::Result {
+        writeln!(f, "effect {} \"{}\"", id, effect.name())?;
+        for command in &effect.commands {
+            writeln!(f, "  {}", self.format_command(command))?;
+        }
+        writeln!(f)
+    }
+
+    fn format_command(&self, command: &EffectCommand) -> String {
+        let mode = |mode: ModifyMode| match mode {
+            ModifyMode::Set => "set",
+            ModifyMode::Add => "add",
+        };
+        let units = |unit: Option<UnitTypeID>, class: Option<UnitClass>| match (unit, class) {
+            (None, None) => "all units".to_string(),
+            (None, Some(class)) => format!("class {}", class),
+            (Some(unit), None) => format!("unit {}", self.unit_ref(unit)),
+            (Some(unit), Some(class)) => format!("unit {} in class {}", self.unit_ref(unit), class),
+        };
+        match command.decode() {
+            Command::AttributeSet {
+                unit,
+                class,
+                attribute,
+                amount,
+            } => format!(
+                "set attribute {} of {}: {}",
+                attribute,
+                units(unit, class),
+                amount
+            ),
+            Command::AttributeAdd {
+                unit,
+                class,
+                attribute,
+                amount,
+            } => format!(
+                "add attribute {} of {}: {}",
+                attribute,
+                units(unit, class),
+                amount
+            ),
+            Command::AttributeMultiply {
+                unit,
+                class,
+                attribute,
+                factor,
+            } => format!(
+                "multiply attribute {} of {}: {}",
+                attribute,
+                units(unit, class),
+                factor
+            ),
+            Command::ResourceModify {
+                resource,
+                mode: m,
+                amount,
+            } => format!("{} resource {}: {}", mode(m), u16::from(resource), amount),
+            Command::ResourceMultiply { resource, factor } => {
+                format!("multiply resource {}: {}", u16::from(resource), factor)
+            }
+            Command::EnableUnit { unit, enabled } => format!(
+                "{} unit {}",
+                if enabled { "enable" } else { "disable" },
+                self.unit_ref(unit)
+            ),
+            Command::UpgradeUnit { from, to } => format!(
+                "upgrade unit {} to {}",
+                self.unit_ref(from),
+                self.unit_ref(to)
+            ),
+            Command::ModifyCost {
+                tech,
+                resource,
+                mode: m,
+                amount,
+            } => format!(
+                "{} resource {} cost of tech {}: {}",
+                mode(m),
+                u16::from(resource),
+                self.tech_ref(tech),
+                amount
+            ),
+            Command::DisableTech { tech } => format!("disable tech {}", self.tech_ref(tech)),
+            Command::ModifyResearchTime {
+                tech,
+                mode: m,
+                amount,
+            } => format!(
+                "{} research time of tech {}: {}",
+                mode(m),
+                self.tech_ref(tech),
+                amount
+            ),
+            Command::Unknown {
+                command_type,
+                params: (a, b, c, d),
+            } => format!("command {}: {} {} {} {}", command_type, a, b, c, d),
+        }
+    }
+}
+
+impl fmt::Display for Dump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for table in DumpTable::ALL {
+            if !self.includes(table) {
+                continue;
+            }
+            match table {
+                DumpTable::Civilizations => self.write_civilizations(f)?,
+                DumpTable::UnitTypes => self.write_unit_types(f)?,
+                DumpTable::Techs => self.write_techs(f)?,
+                DumpTable::Effects => self.write_effects(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get a readable name for the resources that units and techs can cost.
+fn resource_name(id: AttributeID) -> String {
+    match id {
+        AttributeID::FOOD => "food".to_string(),
+        AttributeID::WOOD => "wood".to_string(),
+        AttributeID::STONE => "stone".to_string(),
+        AttributeID::GOLD => "gold".to_string(),
+        AttributeID::POPULATION_HEADROOM => "population".to_string(),
+        other => format!("attribute {}", u16::from(other)),
+    }
+}
+
+/// Format attack or armor values as `class=value` pairs.
+fn format_weapons(weapons: &[WeaponInfo]) -> String {
+    weapons
+        .iter()
+        .map(|weapon| format!("{}={}", u16::from(weapon.weapon_type), weapon.value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format a sorted list of numbers, combining consecutive numbers into ranges.
+fn format_ranges(numbers: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for &n in numbers {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == n => *end = n,
+            _ => ranges.push((n, n)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::{Patch, PatchAttribute, PatchOperation};
+    use std::fs::File;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(format_ranges(&[]), "");
+        assert_eq!(format_ranges(&[1]), "1");
+        assert_eq!(format_ranges(&[0, 1, 2, 4, 6, 7]), "0-2, 4, 6-7");
+    }
+
+    #[test]
+    fn dump_aoc() -> anyhow::Result<()> {
+        let dat = DatFile::read_from(File::open("fixtures/aoc1.0c.dat")?)?;
+        let dump = dat.dump().to_string();
+        assert!(dump.contains("civilization 1 \"British\"\n"));
+        assert!(dump.contains("unit 4 \"ARCHR\" [civilizations "));
+        assert!(dump.contains("tech 22 \"Loom\"\n"));
+        assert!(dump.contains("  location: 109 \"RTWC\"\n"));
+        assert_eq!(dump, dat.dump().to_string());
+
+        let techs = dat.dump().tables(&[DumpTable::Techs]).to_string();
+        assert!(techs.starts_with("tech 0 "));
+        assert!(!techs.contains("\nunit "));
+        Ok(())
+    }
+
+    #[test]
+    fn dump_shows_changes() -> anyhow::Result<()> {
+        let mut dat = DatFile::read_from(File::open("fixtures/aoc1.0c.dat")?)?;
+        let before = dat.dump().tables(&[DumpTable::Techs]).to_string();
+        dat.apply_patch(&Patch::new().tech(
+            22,
+            PatchAttribute::ResearchTime,
+            PatchOperation::Set(5.0),
+        ))?;
+        let after = dat.dump().tables(&[DumpTable::Techs]).to_string();
+
+        let changed = before
+            .lines()
+            .zip(after.lines())
+            .filter(|(a, b)| a != b)
+            .collect::<Vec<_>>();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].1, "  research time: 5");
+        Ok(())
+    }
+}
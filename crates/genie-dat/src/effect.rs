@@ -0,0 +1,1015 @@
+//! Applying tech effects to game data.
+//!
+//! Tech effects are lists of commands that change unit, tech and player attributes when they are
+//! triggered. Data files store commands as a command type and four numeric parameters; use
+//! [`EffectCommand::decode`] to get a typed [`Command`]. [`EffectState`] holds a copy of a
+//! civilization's data tables, and applies effects to it, so you can find out what a unit looks
+//! like after researching some techs.
+
+use crate::civ::CivilizationID;
+use crate::tech::{EffectCommand, Tech, TechEffect};
+use crate::unit_type::{ArmorClass, AttributeCost, UnitClass, UnitType, UnitTypeID, WeaponInfo};
+use crate::DatFile;
+use genie_support::{AttributeID, TechID};
+use smallvec::SmallVec;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+/// Effect command types.
+pub(crate) mod command {
+    pub const SET_ATTRIBUTE: u8 = 0;
+    pub const RESOURCE_MODIFIER: u8 = 1;
+    pub const ENABLE_UNIT: u8 = 2;
+    pub const UPGRADE_UNIT: u8 = 3;
+    pub const ADD_ATTRIBUTE: u8 = 4;
+    pub const MULTIPLY_ATTRIBUTE: u8 = 5;
+    pub const MULTIPLY_RESOURCE: u8 = 6;
+    pub const TECH_COST_MODIFIER: u8 = 101;
+    pub const DISABLE_TECH: u8 = 102;
+    pub const TECH_TIME_MODIFIER: u8 = 103;
+}
+
+/// Whether a command replaces a value or adds to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifyMode {
+    Set,
+    Add,
+}
+
+impl ModifyMode {
+    /// Commands with a "mode" parameter set the value if it is 0, and add to it otherwise.
+    fn from_param(mode: i16) -> Self {
+        if mode == 0 {
+            Self::Set
+        } else {
+            Self::Add
+        }
+    }
+
+    fn to_param(self) -> i16 {
+        match self {
+            Self::Set => 0,
+            Self::Add => 1,
+        }
+    }
+}
+
+/// A decoded effect command.
+///
+/// Unit type and unit class filters of `None` match all unit types or classes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Set an attribute of the matching unit types.
+    AttributeSet {
+        unit: Option<UnitTypeID>,
+        class: Option<UnitClass>,
+        attribute: i16,
+        amount: f32,
+    },
+    /// Add to an attribute of the matching unit types.
+    AttributeAdd {
+        unit: Option<UnitTypeID>,
+        class: Option<UnitClass>,
+        attribute: i16,
+        amount: f32,
+    },
+    /// Multiply an attribute of the matching unit types.
+    AttributeMultiply {
+        unit: Option<UnitTypeID>,
+        class: Option<UnitClass>,
+        attribute: i16,
+        factor: f32,
+    },
+    /// Set or add to a player attribute, such as a resource amount.
+    ResourceModify {
+        resource: AttributeID,
+        mode: ModifyMode,
+        amount: f32,
+    },
+    /// Multiply a player attribute.
+    ResourceMultiply { resource: AttributeID, factor: f32 },
+    /// Enable or disable a unit type.
+    EnableUnit { unit: UnitTypeID, enabled: bool },
+    /// Replace a unit type with another, keeping its ID.
+    UpgradeUnit { from: UnitTypeID, to: UnitTypeID },
+    /// Set or add to the cost of a tech in a resource.
+    ModifyCost {
+        tech: TechID,
+        resource: AttributeID,
+        mode: ModifyMode,
+        amount: f32,
+    },
+    /// Make a tech impossible to research.
+    DisableTech { tech: TechID },
+    /// Set or add to the research time of a tech.
+    ModifyResearchTime {
+        tech: TechID,
+        mode: ModifyMode,
+        amount: f32,
+    },
+    /// A command that is not supported, or whose parameters are out of range.
+    Unknown {
+        command_type: u8,
+        params: (i16, i16, i16, f32),
+    },
+}
+
+/// Decode a unit type or unit class filter parameter. -1 matches everything.
+fn decode_filter(param: i16) -> Option<Option<u16>> {
+    match param {
+        -1 => Some(None),
+        _ => u16::try_from(param).ok().map(Some),
+    }
+}
+
+fn encode_filter(filter: Option<u16>) -> i16 {
+    filter.map_or(-1, |id| id as i16)
+}
+
+impl EffectCommand {
+    /// Decode this command into a typed [`Command`].
+    ///
+    /// Commands with an unsupported command type or out-of-range parameters are returned as
+    /// [`Command::Unknown`].
+    pub fn decode(&self) -> Command {
+        self.try_decode().unwrap_or(Command::Unknown {
+            command_type: self.command_type,
+            params: self.params,
+        })
+    }
+
+    fn try_decode(&self) -> Option<Command> {
+        let (a, b, c, d) = self.params;
+        let id = |param: i16| u16::try_from(param).ok();
+        Some(match self.command_type {
+            command::SET_ATTRIBUTE => Command::AttributeSet {
+                unit: decode_filter(a)?.map(Into::into),
+                class: decode_filter(b)?,
+                attribute: c,
+                amount: d,
+            },
+            command::ADD_ATTRIBUTE => Command::AttributeAdd {
+                unit: decode_filter(a)?.map(Into::into),
+                class: decode_filter(b)?,
+                attribute: c,
+                amount: d,
+            },
+            command::MULTIPLY_ATTRIBUTE => Command::AttributeMultiply {
+                unit: decode_filter(a)?.map(Into::into),
+                class: decode_filter(b)?,
+                attribute: c,
+                factor: d,
+            },
+            command::RESOURCE_MODIFIER => Command::ResourceModify {
+                resource: id(a)?.into(),
+                mode: ModifyMode::from_param(b),
+                amount: d,
+            },
+            command::MULTIPLY_RESOURCE => Command::ResourceMultiply {
+                resource: id(a)?.into(),
+                factor: d,
+            },
+            command::ENABLE_UNIT => Command::EnableUnit {
+                unit: id(a)?.into(),
+                enabled: b != 0,
+            },
+            command::UPGRADE_UNIT => Command::UpgradeUnit {
+                from: id(a)?.into(),
+                to: id(b)?.into(),
+            },
+            command::TECH_COST_MODIFIER => Command::ModifyCost {
+                tech: id(a)?.into(),
+                resource: id(b)?.into(),
+                mode: ModifyMode::from_param(c),
+                amount: d,
+            },
+            command::DISABLE_TECH => Command::DisableTech {
+                tech: u16::try_from(d as i32).ok()?.into(),
+            },
+            command::TECH_TIME_MODIFIER => Command::ModifyResearchTime {
+                tech: id(a)?.into(),
+                mode: ModifyMode::from_param(c),
+                amount: d,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl Command {
+    /// Encode this command as it is stored in data files. Parameters that the command does not
+    /// use are set to -1.
+    pub fn encode(self) -> EffectCommand {
+        let (command_type, params) = match self {
+            Self::AttributeSet {
+                unit,
+                class,
+                attribute,
+                amount,
+            } => (
+                command::SET_ATTRIBUTE,
+                (
+                    encode_filter(unit.map(Into::into)),
+                    encode_filter(class),
+                    attribute,
+                    amount,
+                ),
+            ),
+            Self::AttributeAdd {
+                unit,
+                class,
+                attribute,
+                amount,
+            } => (
+                command::ADD_ATTRIBUTE,
+                (
+                    encode_filter(unit.map(Into::into)),
+                    encode_filter(class),
+                    attribute,
+                    amount,
+                ),
+            ),
+            Self::AttributeMultiply {
+                unit,
+                class,
+                attribute,
+                factor,
+            } => (
+                command::MULTIPLY_ATTRIBUTE,
+                (
+                    encode_filter(unit.map(Into::into)),
+                    encode_filter(class),
+                    attribute,
+                    factor,
+                ),
+            ),
+            Self::ResourceModify {
+                resource,
+                mode,
+                amount,
+            } => (
+                command::RESOURCE_MODIFIER,
+                (u16::from(resource) as i16, mode.to_param(), -1, amount),
+            ),
+            Self::ResourceMultiply { resource, factor } => (
+                command::MULTIPLY_RESOURCE,
+                (u16::from(resource) as i16, -1, -1, factor),
+            ),
+            Self::EnableUnit { unit, enabled } => (
+                command::ENABLE_UNIT,
+                (u16::from(unit) as i16, i16::from(enabled), -1, -1.0),
+            ),
+            Self::UpgradeUnit { from, to } => (
+                command::UPGRADE_UNIT,
+                (u16::from(from) as i16, u16::from(to) as i16, -1, -1.0),
+            ),
+            Self::ModifyCost {
+                tech,
+                resource,
+                mode,
+                amount,
+            } => (
+                command::TECH_COST_MODIFIER,
+                (
+                    u16::from(tech) as i16,
+                    u16::from(resource) as i16,
+                    mode.to_param(),
+                    amount,
+                ),
+            ),
+            Self::DisableTech { tech } => (
+                command::DISABLE_TECH,
+                (-1, -1, -1, f32::from(u16::from(tech))),
+            ),
+            Self::ModifyResearchTime { tech, mode, amount } => (
+                command::TECH_TIME_MODIFIER,
+                (u16::from(tech) as i16, -1, mode.to_param(), amount),
+            ),
+            Self::Unknown {
+                command_type,
+                params,
+            } => (command_type, params),
+        };
+        EffectCommand {
+            command_type,
+            params,
+        }
+    }
+}
+
+impl From<Command> for EffectCommand {
+    fn from(command: Command) -> Self {
+        command.encode()
+    }
+}
+
+/// How an effect command changes a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Set,
+    Add,
+    Multiply,
+}
+
+impl From<ModifyMode> for Operation {
+    fn from(mode: ModifyMode) -> Self {
+        match mode {
+            ModifyMode::Set => Self::Set,
+            ModifyMode::Add => Self::Add,
+        }
+    }
+}
+
+impl Operation {
+    pub(crate) fn apply(self, value: f32, amount: f32) -> f32 {
+        match self {
+            Self::Set => amount,
+            Self::Add => value + amount,
+            Self::Multiply => value * amount,
+        }
+    }
+
+    fn apply_u8(self, value: u8, amount: f32) -> u8 {
+        self.apply(f32::from(value), amount) as u8
+    }
+
+    fn apply_i16(self, value: i16, amount: f32) -> i16 {
+        self.apply(f32::from(value), amount) as i16
+    }
+
+    pub(crate) fn apply_u16(self, value: u16, amount: f32) -> u16 {
+        self.apply(f32::from(value), amount) as u16
+    }
+}
+
+/// A civilization's data tables, as modified by tech effects.
+#[derive(Debug, Clone)]
+pub struct EffectState {
+    civilization_id: CivilizationID,
+    /// Player attribute values, such as resource amounts.
+    pub attributes: Vec<f32>,
+    /// Unit types, indexed by their ID.
+    pub unit_types: Vec<Option<UnitType>>,
+    /// Techs, indexed by their ID.
+    pub techs: Vec<Tech>,
+    /// Techs that have been disabled by an effect.
+    pub disabled_techs: Vec<TechID>,
+}
+
+impl EffectState {
+    /// Create the starting state for a civilization. This applies the civilization's tech tree and
+    /// bonus effect, like the game does at the start of a match.
+    ///
+    /// Returns `None` if the civilization does not exist.
+    pub fn new(dat: &DatFile, civ: impl Into<CivilizationID>) -> Option<Self> {
+        let civilization_id = civ.into();
+        let civ = dat.get_civilization(civilization_id)?;
+        let mut state = Self {
+            civilization_id,
+            attributes: civ.attributes().to_vec(),
+            unit_types: civ.unit_types().to_vec(),
+            techs: dat.techs.clone(),
+            disabled_techs: vec![],
+        };
+        if let Some(effect) = dat.effects.get(usize::from(civ.civ_effect())) {
+            state.apply_effect(effect);
+        }
+        Some(state)
+    }
+
+    /// Get the ID of the civilization this state belongs to.
+    pub fn civilization_id(&self) -> CivilizationID {
+        self.civilization_id
+    }
+
+    /// Get a unit type by its ID.
+    pub fn get_unit_type(&self, id: impl Into<UnitTypeID>) -> Option<&UnitType> {
+        let id: UnitTypeID = id.into();
+        self.unit_types
+            .get(usize::from(id))
+            .and_then(Option::as_ref)
+    }
+
+    /// Get a tech by its ID.
+    pub fn get_tech(&self, id: impl Into<TechID>) -> Option<&Tech> {
+        let id: TechID = id.into();
+        self.techs.get(usize::from(id))
+    }
+
+    /// Apply the effect of a tech, as if it was researched.
+    ///
+    /// Returns `false` if the tech does not exist or has no effect.
+    pub fn research(&mut self, dat: &DatFile, tech: impl Into<TechID>) -> bool {
+        let effect = self
+            .get_tech(tech)
+            .and_then(Tech::effect_id)
+            .and_then(|id| dat.effects.get(usize::from(id)));
+        match effect {
+            Some(effect) => {
+                self.apply_effect(effect);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply all commands in a tech effect.
+    pub fn apply_effect(&mut self, effect: &TechEffect) {
+        for command in &effect.commands {
+            self.apply_command(command);
+        }
+    }
+
+    /// Apply a single effect command. Unsupported commands are ignored.
+    pub fn apply_command(&mut self, command: &EffectCommand) {
+        self.apply(command.decode());
+    }
+
+    /// Apply a decoded effect command. Unsupported commands are ignored.
+    pub fn apply(&mut self, command: Command) {
+        match command {
+            Command::AttributeSet {
+                unit,
+                class,
+                attribute,
+                amount,
+            } => self.modify_units(unit, class, attribute, Operation::Set, amount),
+            Command::AttributeAdd {
+                unit,
+                class,
+                attribute,
+                amount,
+            } => self.modify_units(unit, class, attribute, Operation::Add, amount),
+            Command::AttributeMultiply {
+                unit,
+                class,
+                attribute,
+                factor,
+            } => self.modify_units(unit, class, attribute, Operation::Multiply, factor),
+            Command::ResourceModify {
+                resource,
+                mode,
+                amount,
+            } => self.modify_attribute(resource, mode.into(), amount),
+            Command::ResourceMultiply { resource, factor } => {
+                self.modify_attribute(resource, Operation::Multiply, factor)
+            }
+            Command::EnableUnit { unit, enabled } => {
+                if let Some(unit) = self.unit_mut(unit) {
+                    unit.static_.enabled = enabled;
+                }
+            }
+            Command::UpgradeUnit { from, to } => self.upgrade_unit(from, to),
+            Command::ModifyCost {
+                tech,
+                resource,
+                mode,
+                amount,
+            } => {
+                let op = Operation::from(mode);
+                if let Some(tech) = self.tech_mut(tech) {
+                    for cost in tech.costs_mut() {
+                        if cost.effect_type == resource {
+                            cost.amount = op.apply_u16(cost.amount, amount);
+                        }
+                    }
+                }
+            }
+            Command::DisableTech { tech } => self.disabled_techs.push(tech),
+            Command::ModifyResearchTime { tech, mode, amount } => {
+                let op = Operation::from(mode);
+                if let Some(tech) = self.tech_mut(tech) {
+                    let time = op.apply_u16(tech.research_time(), amount);
+                    tech.set_research_time(time);
+                }
+            }
+            Command::Unknown { .. } => (),
+        }
+    }
+
+    fn unit_mut(&mut self, id: UnitTypeID) -> Option<&mut UnitType> {
+        self.unit_types
+            .get_mut(usize::from(id))
+            .and_then(Option::as_mut)
+    }
+
+    fn tech_mut(&mut self, id: TechID) -> Option<&mut Tech> {
+        self.techs.get_mut(usize::from(id))
+    }
+
+    fn modify_attribute(&mut self, attribute: AttributeID, op: Operation, amount: f32) {
+        if let Some(value) = self.attributes.get_mut(usize::from(attribute)) {
+            *value = op.apply(*value, amount);
+        }
+    }
+
+    /// Modify an attribute of all unit types matching the unit ID and class. `None` matches any
+    /// unit ID or class.
+    fn modify_units(
+        &mut self,
+        unit_id: Option<UnitTypeID>,
+        class: Option<UnitClass>,
+        attribute: i16,
+        op: Operation,
+        d: f32,
+    ) {
+        for unit in self.unit_types.iter_mut().flatten() {
+            let matches_id = unit_id.is_none_or(|id| id == unit.static_.id);
+            let matches_class = class.is_none_or(|class| class == unit.static_.unit_class);
+            if matches_id && matches_class {
+                modify_unit(unit, attribute, op, d);
+            }
+        }
+    }
+
+    /// Make the unit type `from` take on the attributes of the unit type `to`. The unit type keeps
+    /// its original ID.
+    fn upgrade_unit(&mut self, from: UnitTypeID, to: UnitTypeID) {
+        let upgrade = match self.unit_types.get(usize::from(to)) {
+            Some(Some(unit)) => unit.clone(),
+            _ => return,
+        };
+        if let Some(unit) = self.unit_mut(from) {
+            let id = unit.static_.id;
+            *unit = upgrade;
+            unit.static_.id = id;
+        }
+    }
+}
+
+/// Modify an attack or armour value. The amount packs the armour class in the upper byte, and the
+/// value in the lower byte.
+fn modify_weapons(weapons: &mut SmallVec<[WeaponInfo; 4]>, op: Operation, packed: f32) {
+    let packed = packed as i32;
+    let weapon_type = ArmorClass::from((packed >> 8) as u16);
+    let amount = (packed & 0xFF) as f32;
+    modify_weapon(weapons, weapon_type, op, amount);
+}
+
+/// Modify the attack or armour value for one class, adding it if the unit does not have it yet.
+pub(crate) fn modify_weapon(
+    weapons: &mut SmallVec<[WeaponInfo; 4]>,
+    weapon_type: ArmorClass,
+    op: Operation,
+    amount: f32,
+) {
+    match weapons.iter_mut().find(|w| w.weapon_type == weapon_type) {
+        Some(weapon) => weapon.value = op.apply_i16(weapon.value, amount),
+        None => weapons.push(WeaponInfo {
+            weapon_type,
+            value: op.apply_i16(0, amount),
+        }),
+    }
+}
+
+/// Modify the costs of a unit. `resource` of `None` modifies all costs.
+fn modify_costs(
+    costs: &mut [AttributeCost],
+    resource: Option<AttributeID>,
+    op: Operation,
+    amount: f32,
+) {
+    for cost in costs {
+        if resource.is_none_or(|resource| resource == cost.attribute_type) {
+            cost.amount = op.apply_i16(cost.amount, amount);
+        }
+    }
+}
+
+/// Modify a single attribute of a unit type. Attributes that the unit type does not have are
+/// ignored.
+pub(crate) fn modify_unit(unit: &mut UnitType, attribute: i16, op: Operation, d: f32) {
+    let static_ = &mut unit.static_;
+    match attribute {
+        0 => static_.hp = op.apply_u16(static_.hp, d),
+        1 => static_.los = op.apply(static_.los, d),
+        2 => static_.garrison_capacity = op.apply_u8(static_.garrison_capacity, d),
+        3 => static_.radius.0 = op.apply(static_.radius.0, d),
+        4 => static_.radius.1 = op.apply(static_.radius.1, d),
+        5 => {
+            if let Some(animated) = &mut unit.animated {
+                animated.speed = op.apply(animated.speed, d);
+            }
+        }
+        6 => {
+            if let Some(moving) = &mut unit.moving {
+                moving.turn_speed = op.apply(moving.turn_speed, d);
+            }
+        }
+        // Multiplying packed attack and armour values is not meaningful.
+        8 | 9 if op == Operation::Multiply => (),
+        8 => {
+            if let Some(combat) = &mut unit.base_combat {
+                modify_weapons(&mut combat.armors, op, d);
+            }
+        }
+        9 => {
+            if let Some(combat) = &mut unit.base_combat {
+                modify_weapons(&mut combat.weapons, op, d);
+            }
+        }
+        10 | 11 | 12 | 15 | 16 | 18 | 20 | 22 => {
+            if let Some(combat) = &mut unit.base_combat {
+                match attribute {
+                    10 => combat.attack_speed = op.apply(combat.attack_speed, d),
+                    11 => combat.base_hit_chance = op.apply_i16(combat.base_hit_chance, d),
+                    12 => combat.weapon_range_max = op.apply(combat.weapon_range_max, d),
+                    15 => combat.base_armor = op.apply_u16(combat.base_armor, d),
+                    16 => combat.missile_id = u16::try_from(d as i32).ok().map(Into::into),
+                    18 => combat.defense_terrain_bonus = u16::try_from(d as i32).ok(),
+                    20 => combat.weapon_range_min = op.apply(combat.weapon_range_min, d),
+                    22 => combat.area_effect_range = op.apply(combat.area_effect_range, d),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        13 => {
+            if let Some(action) = &mut unit.action {
+                action.work_rate = op.apply(action.work_rate, d);
+            }
+        }
+        14 => static_.attribute_max_amount = op.apply_u16(static_.attribute_max_amount, d),
+        23 => {
+            if let Some(action) = &mut unit.action {
+                action.search_radius = op.apply(action.search_radius, d);
+            }
+        }
+        100..=108 => {
+            if let Some(combat) = &mut unit.combat {
+                match attribute {
+                    100 => modify_costs(&mut combat.costs, None, op, d),
+                    101 => combat.create_time = op.apply_u16(combat.create_time, d),
+                    102 => combat.volley_fire_amount = op.apply(combat.volley_fire_amount, d),
+                    103 => modify_costs(&mut combat.costs, Some(AttributeID::FOOD), op, d),
+                    104 => modify_costs(&mut combat.costs, Some(AttributeID::WOOD), op, d),
+                    105 => modify_costs(&mut combat.costs, Some(AttributeID::GOLD), op, d),
+                    106 => modify_costs(&mut combat.costs, Some(AttributeID::STONE), op, d),
+                    107 => {
+                        combat.max_attacks_in_volley =
+                            op.apply(f32::from(combat.max_attacks_in_volley), d) as i8
+                    }
+                    108 => {
+                        if let Some(building) = &mut unit.building {
+                            building.garrison_heal_rate = op.apply(building.garrison_heal_rate, d);
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+impl DatFile {
+    /// Get the effective cost of a unit type for a civilization, after researching some techs.
+    ///
+    /// Civilization bonuses that depend on the researched techs are included. Team bonuses are
+    /// not; use [`CivView::with_team_bonus`] and [`CivView::unit_cost`] for those.
+    pub fn unit_cost(
+        &self,
+        civ: impl Into<CivilizationID>,
+        unit: impl Into<UnitTypeID>,
+        researched: &[TechID],
+    ) -> Option<Vec<AttributeCost>> {
+        self.civ_view_with(civ, researched)?.unit_cost(unit)
+    }
+
+    /// Get the effective time in seconds to train a unit type for a civilization, after
+    /// researching some techs.
+    ///
+    /// Civilization bonuses that depend on the researched techs are included. Team bonuses are
+    /// not; use [`CivView::with_team_bonus`] and [`CivView::train_time`] for those.
+    pub fn train_time(
+        &self,
+        civ: impl Into<CivilizationID>,
+        unit: impl Into<UnitTypeID>,
+        researched: &[TechID],
+    ) -> Option<f32> {
+        self.civ_view_with(civ, researched)?.train_time(unit)
+    }
+
+    fn civ_view_with(
+        &self,
+        civ: impl Into<CivilizationID>,
+        researched: &[TechID],
+    ) -> Option<CivView<'_>> {
+        let mut view = CivView::new(self, civ)?;
+        for &tech in researched {
+            view.research(tech);
+        }
+        Some(view)
+    }
+}
+
+/// The ages in Age of Empires II.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Age {
+    Dark,
+    Feudal,
+    Castle,
+    Imperial,
+}
+
+impl Age {
+    /// Get the ID of the tech that advances to this age. The Dark Age is the starting age, so it
+    /// has no tech.
+    pub fn tech_id(self) -> Option<TechID> {
+        match self {
+            Self::Dark => None,
+            Self::Feudal => Some(101.into()),
+            Self::Castle => Some(102.into()),
+            Self::Imperial => Some(103.into()),
+        }
+    }
+}
+
+/// A view of a civilization's effective unit stats, including its civilization bonuses.
+///
+/// Civilization bonuses are implemented as techs without a research location, which the game
+/// researches automatically once their requirements are met. `CivView` does the same whenever a
+/// tech is researched.
+#[derive(Debug, Clone)]
+pub struct CivView<'a> {
+    dat: &'a DatFile,
+    state: EffectState,
+    researched: HashSet<TechID>,
+}
+
+impl<'a> CivView<'a> {
+    /// Create a view of a civilization at the start of a game.
+    ///
+    /// Returns `None` if the civilization does not exist.
+    pub fn new(dat: &'a DatFile, civ: impl Into<CivilizationID>) -> Option<Self> {
+        let mut view = Self {
+            dat,
+            state: EffectState::new(dat, civ)?,
+            researched: HashSet::new(),
+        };
+        view.research_automatic_techs();
+        Some(view)
+    }
+
+    /// Create a view of a civilization after advancing to an age.
+    ///
+    /// Returns `None` if the civilization does not exist.
+    pub fn at_age(dat: &'a DatFile, civ: impl Into<CivilizationID>, age: Age) -> Option<Self> {
+        let mut view = Self::new(dat, civ)?;
+        for previous in [Age::Feudal, Age::Castle, Age::Imperial] {
+            if previous > age {
+                break;
+            }
+            if let Some(tech_id) = previous.tech_id() {
+                view.research(tech_id);
+            }
+        }
+        Some(view)
+    }
+
+    /// Apply the civilization's team bonus.
+    pub fn with_team_bonus(mut self) -> Self {
+        let effect = self
+            .dat
+            .get_civilization(self.state.civilization_id())
+            .and_then(|civ| civ.bonus_effect())
+            .and_then(|id| self.dat.effects.get(usize::from(id)));
+        if let Some(effect) = effect {
+            self.state.apply_effect(effect);
+        }
+        self
+    }
+
+    /// Research a tech, and any automatic techs that become available because of it.
+    ///
+    /// Returns `false` if the tech was already researched, or does not exist.
+    pub fn research(&mut self, tech: impl Into<TechID>) -> bool {
+        let tech = tech.into();
+        if !self.researched.insert(tech) {
+            return false;
+        }
+        let researched = self.state.research(self.dat, tech);
+        self.research_automatic_techs();
+        researched
+    }
+
+    /// Check if a tech has been researched.
+    pub fn has_researched(&self, tech: impl Into<TechID>) -> bool {
+        self.researched.contains(&tech.into())
+    }
+
+    /// Get a unit type's effective stats.
+    pub fn get_unit_type(&self, id: impl Into<UnitTypeID>) -> Option<&UnitType> {
+        self.state.get_unit_type(id)
+    }
+
+    /// Get the effective cost of a unit type, including changes by researched techs and
+    /// civilization bonuses.
+    ///
+    /// Returns `None` if the unit type does not exist or can not be created.
+    pub fn unit_cost(&self, id: impl Into<UnitTypeID>) -> Option<Vec<AttributeCost>> {
+        let unit = self.get_unit_type(id)?;
+        Some(unit.combat.as_ref()?.costs.to_vec())
+    }
+
+    /// Get the effective time in seconds that it takes to train a unit type, or to build a
+    /// building.
+    ///
+    /// Units are trained faster in buildings that work faster, for example because of a
+    /// civilization bonus.
+    ///
+    /// Returns `None` if the unit type does not exist or can not be created.
+    pub fn train_time(&self, id: impl Into<UnitTypeID>) -> Option<f32> {
+        let unit = self.get_unit_type(id)?;
+        let create_time = f32::from(unit.combat.as_ref()?.create_time);
+        let work_rate = unit
+            .trainable_at()
+            .and_then(|building| self.get_unit_type(building))
+            .filter(|building| building.is_building())
+            .and_then(|building| building.action.as_ref())
+            .map(|action| action.work_rate)
+            .filter(|&work_rate| work_rate > 0.0)
+            .unwrap_or(1.0);
+        Some(create_time / work_rate)
+    }
+
+    /// Get the underlying effect state.
+    pub fn state(&self) -> &EffectState {
+        &self.state
+    }
+
+    /// Is this tech researched automatically by this civilization, given the techs researched so
+    /// far?
+    fn is_automatic(&self, id: TechID, tech: &Tech) -> bool {
+        let civ = self.state.civilization_id();
+        tech.location().is_none()
+            && tech.effect_id().is_some()
+            && tech.civilization_id().is_none_or(|owner| owner == civ)
+            && !self.researched.contains(&id)
+            && !self.state.disabled_techs.contains(&id)
+            && tech
+                .required_techs()
+                .iter()
+                .all(|required| self.researched.contains(required))
+    }
+
+    fn research_automatic_techs(&mut self) {
+        loop {
+            let automatic: Vec<TechID> = self
+                .state
+                .techs
+                .iter()
+                .enumerate()
+                .filter_map(|(index, tech)| {
+                    let id = TechID::from(u16::try_from(index).ok()?);
+                    Some(id).filter(|&id| self.is_automatic(id, tech))
+                })
+                .collect();
+            if automatic.is_empty() {
+                break;
+            }
+            for id in automatic {
+                self.researched.insert(id);
+                self.state.research(self.dat, id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn research_fletching() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let mut britons = EffectState::new(&dat, 1).unwrap();
+        assert!(britons.disabled_techs.contains(&TechID::from(265)));
+
+        let archer = britons.get_unit_type(4).unwrap().clone();
+        assert!(britons.research(&dat, 199));
+        let upgraded = britons.get_unit_type(4).unwrap();
+
+        let pierce_attack = |unit: &UnitType| {
+            let combat = unit.base_combat.as_ref().unwrap();
+            combat
+                .weapons
+                .iter()
+                .find(|w| w.weapon_type == ArmorClass::Pierce)
+                .unwrap()
+                .value
+        };
+        assert_eq!(pierce_attack(upgraded), pierce_attack(&archer) + 1);
+        assert_eq!(upgraded.static_.los, archer.static_.los + 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_commands() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let commands = dat.effects.iter().flat_map(|effect| &effect.commands);
+        for raw in commands {
+            let command = raw.decode();
+            if let Command::Unknown { .. } = command {
+                continue;
+            }
+            assert_eq!(command.encode().decode(), command);
+        }
+
+        let raw = EffectCommand {
+            command_type: 4,
+            params: (-1, 6, 9, 769.0),
+        };
+        let command = raw.decode();
+        assert_eq!(
+            command,
+            Command::AttributeAdd {
+                unit: None,
+                class: Some(6),
+                attribute: 9,
+                amount: 769.0,
+            }
+        );
+        assert_eq!(command.encode().params, raw.params);
+        let disable = Command::DisableTech { tech: 265.into() }.encode();
+        assert_eq!(disable.command_type, 102);
+        assert_eq!(disable.params.3, 265.0);
+        assert!(matches!(
+            EffectCommand {
+                command_type: 2,
+                params: (-5, 1, -1, 0.0),
+            }
+            .decode(),
+            Command::Unknown {
+                command_type: 2,
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn costs_and_train_times() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let food_and_gold = |costs: Vec<AttributeCost>| {
+            costs
+                .into_iter()
+                .filter(|cost| cost.attribute_type != AttributeID::POPULATION_HEADROOM)
+                .map(|cost| (cost.attribute_type, cost.amount))
+                .collect::<Vec<_>>()
+        };
+
+        // Goths infantry get cheaper in later ages (Militia is 74).
+        let dark = dat.unit_cost(3, 74, &[]).unwrap();
+        assert_eq!(
+            food_and_gold(dark),
+            vec![(AttributeID::FOOD, 60), (AttributeID::GOLD, 20)]
+        );
+        let imperial = dat.unit_cost(3, 74, &[101.into(), 102.into(), 103.into()]);
+        let imperial = food_and_gold(imperial.unwrap());
+        assert!(imperial[0].1 < 60 && imperial[1].1 < 20);
+        assert_eq!(dat.unit_cost(3, 60000, &[]), None);
+
+        // The Briton team bonus makes archery ranges (87) work faster (Archer is 4).
+        assert_eq!(dat.train_time(1, 4, &[]), Some(35.0));
+        let britons = CivView::new(&dat, 1).unwrap().with_team_bonus();
+        let time = britons.train_time(4).unwrap();
+        assert!((time - 35.0 / 1.2).abs() < 0.01);
+        Ok(())
+    }
+
+    #[test]
+    fn upgrade_unit() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let mut franks = EffectState::new(&dat, 2).unwrap();
+        // Cavalier upgrades the Knight (38) to the Cavalier (283).
+        assert!(franks.research(&dat, 209));
+        let knight = franks.get_unit_type(38).unwrap();
+        let cavalier = franks.get_unit_type(283).unwrap();
+        assert_eq!(knight.static_.id, UnitTypeID::from(38));
+        assert_eq!(knight.static_.hp, cavalier.static_.hp);
+        Ok(())
+    }
+
+    #[test]
+    fn civ_view_bonuses() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let knight_hp = |civ: u8, age: Age| {
+            let view = CivView::at_age(&dat, civ, age).unwrap();
+            view.get_unit_type(38).unwrap().static_.hp
+        };
+        // Frankish cavalry has +20% HP.
+        assert_eq!(knight_hp(1, Age::Imperial), 100);
+        assert_eq!(knight_hp(2, Age::Imperial), 120);
+
+        let view = CivView::at_age(&dat, 2, Age::Castle).unwrap();
+        assert!(view.has_researched(Age::Feudal.tech_id().unwrap()));
+        assert!(!view.has_researched(Age::Imperial.tech_id().unwrap()));
+        Ok(())
+    }
+}
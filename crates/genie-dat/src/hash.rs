@@ -0,0 +1,81 @@
+use crate::unknown::without_raw_bytes;
+use crate::DatFile;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::Result;
+
+/// A hash of the contents of a data file.
+///
+/// Two data files have the same content hash if they contain the same data, even if they were
+/// compressed differently or contain different padding, pointers, or other values that the game
+/// does not use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    /// Get the SHA-256 hash bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for ContentHash {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl DatFile {
+    /// Compute a hash over the contents of this data file.
+    ///
+    /// The hash is the SHA-256 hash of the uncompressed data file, written as if the
+    /// `raw-fields` feature were disabled. It can be used to check that two players have the same
+    /// data mod.
+    pub fn content_hash(&self) -> Result<ContentHash> {
+        let mut hasher = Sha256::new();
+        without_raw_bytes(|| self.write_uncompressed(&mut hasher))?;
+        Ok(ContentHash(hasher.finalize().into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatCompression, RawBytes};
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn content_hash() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        let hash = dat.content_hash()?;
+        assert_eq!(hash.to_string().len(), 64);
+
+        // Compression does not affect the hash.
+        let mut bytes = vec![];
+        dat.write_to_with(&mut bytes, DatCompression::Uncompressed)?;
+        let uncompressed = DatFile::read_from(Cursor::new(bytes))?;
+        assert_eq!(uncompressed.content_hash()?, hash);
+
+        // Neither do uninterpreted bytes.
+        dat.unknown_fields.tile_sizes_padding = RawBytes::new(&[0xAB, 0xCD]);
+        for sprite in dat.sprites.iter_mut().flatten() {
+            sprite.unknown_name_tail = RawBytes::new(b"\0garbage");
+        }
+        assert_eq!(dat.content_hash()?, hash);
+
+        dat.techs[22].set_research_time(5);
+        assert_ne!(dat.content_hash()?, hash);
+        Ok(())
+    }
+}
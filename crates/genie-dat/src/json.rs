@@ -0,0 +1,124 @@
+//! JSON export and import of data files.
+//!
+//! Storing data files as JSON makes it possible to keep mods as readable text diffs and to edit
+//! them with generic tools. Either the entire data file or a single table can be converted.
+
+use crate::DatFile;
+use serde::Serialize;
+use std::io::{Read, Result, Write};
+
+/// A table in a data file that can be exported and imported on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatTable {
+    TerrainRestrictions,
+    TileSizes,
+    Terrains,
+    TerrainBorders,
+    RandomMaps,
+    ColorTables,
+    Sounds,
+    Sprites,
+    Effects,
+    UnitLines,
+    TaskLists,
+    Civilizations,
+    Techs,
+    TechTree,
+}
+
+fn write_json(output: impl Write, value: &impl Serialize) -> Result<()> {
+    serde_json::to_writer_pretty(output, value)?;
+    Ok(())
+}
+
+impl DatFile {
+    /// Export the entire data file as pretty-printed JSON.
+    pub fn to_json(&self, output: impl Write) -> Result<()> {
+        write_json(output, self)
+    }
+
+    /// Import a data file from JSON created by [`DatFile::to_json`].
+    pub fn from_json(input: impl Read) -> Result<Self> {
+        Ok(serde_json::from_reader(input)?)
+    }
+
+    /// Export a single table of the data file as pretty-printed JSON.
+    pub fn table_to_json(&self, table: DatTable, output: impl Write) -> Result<()> {
+        match table {
+            DatTable::TerrainRestrictions => write_json(output, &self.terrain_tables),
+            DatTable::TileSizes => write_json(output, &self.tile_sizes),
+            DatTable::Terrains => write_json(output, &self.terrains),
+            DatTable::TerrainBorders => write_json(output, &self.terrain_borders),
+            DatTable::RandomMaps => write_json(output, &self.random_maps),
+            DatTable::ColorTables => write_json(output, &self.color_tables),
+            DatTable::Sounds => write_json(output, &self.sounds),
+            DatTable::Sprites => write_json(output, &self.sprites),
+            DatTable::Effects => write_json(output, &self.effects),
+            DatTable::UnitLines => write_json(output, &self.unit_lines),
+            DatTable::TaskLists => write_json(output, &self.task_lists),
+            DatTable::Civilizations => write_json(output, &self.civilizations),
+            DatTable::Techs => write_json(output, &self.techs),
+            DatTable::TechTree => write_json(output, &self.tech_tree),
+        }
+    }
+
+    /// Replace a single table of the data file with one read from JSON created by
+    /// [`DatFile::table_to_json`].
+    pub fn import_json_table(&mut self, table: DatTable, input: impl Read) -> Result<()> {
+        match table {
+            DatTable::TerrainRestrictions => self.terrain_tables = serde_json::from_reader(input)?,
+            DatTable::TileSizes => self.tile_sizes = serde_json::from_reader(input)?,
+            DatTable::Terrains => self.terrains = serde_json::from_reader(input)?,
+            DatTable::TerrainBorders => self.terrain_borders = serde_json::from_reader(input)?,
+            DatTable::RandomMaps => self.random_maps = serde_json::from_reader(input)?,
+            DatTable::ColorTables => self.color_tables = serde_json::from_reader(input)?,
+            DatTable::Sounds => self.sounds = serde_json::from_reader(input)?,
+            DatTable::Sprites => self.sprites = serde_json::from_reader(input)?,
+            DatTable::Effects => self.effects = serde_json::from_reader(input)?,
+            DatTable::UnitLines => self.unit_lines = serde_json::from_reader(input)?,
+            DatTable::TaskLists => self.task_lists = serde_json::from_reader(input)?,
+            DatTable::Civilizations => self.civilizations = serde_json::from_reader(input)?,
+            DatTable::Techs => self.techs = serde_json::from_reader(input)?,
+            DatTable::TechTree => self.tech_tree = serde_json::from_reader(input)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn to_bytes(dat: &DatFile) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        dat.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn json_roundtrip() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+
+        let mut json = vec![];
+        dat.to_json(&mut json)?;
+        let imported = DatFile::from_json(&json[..])?;
+        assert_eq!(to_bytes(&imported)?, to_bytes(&dat)?);
+        Ok(())
+    }
+
+    #[test]
+    fn json_table_roundtrip() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+
+        let mut json = vec![];
+        dat.table_to_json(DatTable::Techs, &mut json)?;
+        let mut modded = dat.clone();
+        modded.techs.clear();
+        modded.import_json_table(DatTable::Techs, &json[..])?;
+        assert_eq!(to_bytes(&modded)?, to_bytes(&dat)?);
+        Ok(())
+    }
+}
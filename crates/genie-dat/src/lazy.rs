@@ -0,0 +1,700 @@
+//! Lazy loading of data files.
+//!
+//! Reading a [`DatFile`] parses every table up front, which takes noticeable time and memory when
+//! only a few tables are needed. A [`LazyDatFile`] decompresses the data file and scans it once to
+//! record where each table starts. Unit types are skipped during the scan without being parsed.
+//! Tables are parsed when they are first accessed.
+//!
+//! With the `rayon` feature, [`LazyDatFile::into_dat_file_parallel`] uses the recorded offsets to
+//! parse civilizations and sprites, which make up most of a data file, on multiple threads.
+
+use crate::civ::{self, Civilization, CivilizationID};
+use crate::color_table::ColorTable;
+use crate::random_map::RandomMapInfo;
+use crate::sound::Sound;
+use crate::sprite::Sprite;
+use crate::task::TaskList;
+use crate::tech::{Tech, TechEffect};
+use crate::tech_tree::TechTree;
+use crate::terrain::{Terrain, TerrainBorder, TerrainRestriction, TileSize};
+use crate::unit_type::{UnitLine, UnitType, UnitTypeID};
+use crate::unknown::{RawBytes, UnknownFields};
+use crate::{DatFile, DatReader, FileVersion, GameVersion};
+use byteorder::{ReadBytesExt, LE};
+use std::io::{Cursor, Error, ErrorKind, Read, Result};
+use std::sync::OnceLock;
+
+type Input<'a> = Cursor<&'a [u8]>;
+type Reader<T> = fn(&mut Input<'_>, &Header) -> Result<T>;
+
+/// Header values that are needed to parse the tables of a data file.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    file_version: FileVersion,
+    game_version: GameVersion,
+    swgb_header: Option<[i32; 4]>,
+    num_terrain_tables: u16,
+    num_terrains: u16,
+    num_terrains_fixed: u16,
+}
+
+/// Offsets of the tables in the decompressed data file.
+#[derive(Debug, Default, Clone)]
+struct Sections {
+    terrain_tables: u64,
+    color_tables: u64,
+    sounds: u64,
+    sprites: u64,
+    /// Offsets of the individual sprites, or `None` for empty sprite slots.
+    sprite_entries: Vec<Option<u64>>,
+    tile_sizes: u64,
+    terrains: u64,
+    terrain_borders: u64,
+    random_maps: u64,
+    effects: u64,
+    unit_lines: u64,
+    task_lists: u64,
+    civilizations: Vec<u64>,
+    techs: u64,
+    tech_tree: u64,
+}
+
+fn read_array<T>(num: usize, mut read: impl FnMut() -> Result<T>) -> Result<Vec<T>> {
+    let mut list = Vec::with_capacity(num);
+    for _ in 0..num {
+        list.push(read()?);
+    }
+    Ok(list)
+}
+
+fn read_terrain_tables(input: &mut Input<'_>, header: &Header) -> Result<Vec<TerrainRestriction>> {
+    read_array(header.num_terrain_tables.into(), || {
+        TerrainRestriction::read_from(&mut *input, header.file_version, header.num_terrains)
+    })
+}
+
+fn read_color_tables(input: &mut Input<'_>, _header: &Header) -> Result<Vec<ColorTable>> {
+    let num_color_tables = input.read_u16::<LE>()?;
+    read_array(num_color_tables.into(), || ColorTable::read_from(input))
+}
+
+fn read_sounds(input: &mut Input<'_>, header: &Header) -> Result<Vec<Sound>> {
+    let num_sounds = input.read_u16::<LE>()?;
+    read_array(num_sounds.into(), || {
+        Sound::read_from(input, header.file_version)
+    })
+}
+
+fn read_sprites(input: &mut Input<'_>, header: &Header) -> Result<Vec<Option<Sprite>>> {
+    let num_sprites = input.read_u16::<LE>()?;
+    let sprites_exist = read_array(num_sprites.into(), || {
+        input.read_u32::<LE>().map(|n| n != 0)
+    })?;
+    let mut sprites = Vec::with_capacity(sprites_exist.len());
+    for exists in sprites_exist {
+        sprites.push(if exists {
+            Some(read_sprite(input, header)?)
+        } else {
+            None
+        });
+    }
+    Ok(sprites)
+}
+
+fn read_sprite(input: &mut Input<'_>, header: &Header) -> Result<Sprite> {
+    Sprite::read_from(input, header.file_version)
+}
+
+fn read_tile_sizes(input: &mut Input<'_>, _header: &Header) -> Result<Vec<TileSize>> {
+    read_array(19, || TileSize::read_from(input))
+}
+
+fn read_terrains(input: &mut Input<'_>, header: &Header) -> Result<Vec<Terrain>> {
+    read_array(header.num_terrains_fixed.into(), || {
+        Terrain::read_from(&mut *input, header.file_version, header.num_terrains_fixed)
+    })
+}
+
+fn read_terrain_borders(input: &mut Input<'_>, header: &Header) -> Result<Vec<TerrainBorder>> {
+    read_array(16, || {
+        TerrainBorder::read_from(&mut *input, header.file_version)
+    })
+}
+
+fn read_random_maps(input: &mut Input<'_>, _header: &Header) -> Result<Vec<RandomMapInfo>> {
+    let num_random_maps = input.read_u32::<LE>()? as usize;
+    let _random_maps_pointer = input.read_u32::<LE>()?;
+    let mut random_maps = read_array(num_random_maps, || RandomMapInfo::read_from(input))?;
+    for map in random_maps.iter_mut() {
+        map.finish(&mut *input)?;
+    }
+    Ok(random_maps)
+}
+
+fn read_effects(input: &mut Input<'_>, _header: &Header) -> Result<Vec<TechEffect>> {
+    let num_effects = input.read_u32::<LE>()? as usize;
+    read_array(num_effects, || TechEffect::read_from(input))
+}
+
+fn read_unit_lines(input: &mut Input<'_>, header: &Header) -> Result<Vec<UnitLine>> {
+    if !header.file_version.is_swgb() {
+        return Ok(vec![]);
+    }
+    let num_unit_lines = input.read_u16::<LE>()?;
+    read_array(num_unit_lines.into(), || UnitLine::read_from(&mut *input))
+}
+
+fn read_task_lists(input: &mut Input<'_>, _header: &Header) -> Result<Vec<Option<TaskList>>> {
+    let num_task_lists = input.read_u32::<LE>()? as usize;
+    read_array(num_task_lists, || {
+        if input.read_u8()? != 0 {
+            TaskList::read_from(&mut *input).map(Some)
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+fn read_civilization(input: &mut Input<'_>, header: &Header) -> Result<Civilization> {
+    let player_type = input.read_i8()?;
+    if player_type != 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "unexpected civilization player type {}, expected 1",
+                player_type
+            ),
+        ));
+    }
+    Civilization::read_from(input, header.game_version)
+}
+
+fn read_techs(input: &mut Input<'_>, header: &Header) -> Result<Vec<Tech>> {
+    let num_techs = input.read_u16::<LE>()?;
+    read_array(num_techs.into(), || {
+        Tech::read_from(&mut *input, header.file_version)
+    })
+}
+
+fn read_tech_tree(input: &mut Input<'_>, _header: &Header) -> Result<TechTree> {
+    TechTree::read_from(input)
+}
+
+fn read_swgb_trailer(input: &mut Input<'_>, header: &Header) -> Result<Option<i8>> {
+    if header.file_version.is_swgb() {
+        Ok(Some(input.read_i8()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// A decompressed data file.
+#[derive(Debug)]
+struct Data {
+    bytes: Vec<u8>,
+    header: Header,
+}
+
+impl Data {
+    /// Parse a table at an offset in the data file.
+    fn parse<T>(&self, offset: u64, read: Reader<T>) -> Result<T> {
+        let mut input = Cursor::new(&self.bytes[..]);
+        input.set_position(offset);
+        read(&mut input, &self.header)
+    }
+
+    /// Get a table that may already have been parsed, parsing it if it was not.
+    fn take<T>(&self, cell: OnceLock<T>, offset: u64, read: Reader<T>) -> Result<T> {
+        match cell.into_inner() {
+            Some(table) => Ok(table),
+            None => self.parse(offset, read),
+        }
+    }
+}
+
+/// The result of scanning a data file: its header, the offsets of its tables, the values that are
+/// not part of any table, and the Star Wars: Galactic Battlegrounds trailers.
+type Scan = (Header, Sections, UnknownFields, Option<i8>, Option<i8>);
+
+/// Read the header of a decompressed data file, and find the offsets of all its tables.
+fn scan(input: &mut Input<'_>) -> Result<Scan> {
+    let mut file_version = [0u8; 8];
+    input.read_exact(&mut file_version)?;
+    let file_version = FileVersion(file_version);
+
+    let swgb_header = if file_version.is_swgb() {
+        // Duplicate of the civilization count further down.
+        let _num_civilizations = input.read_u16::<LE>()?;
+        let mut header = [0; 4];
+        for value in header.iter_mut() {
+            *value = input.read_i32::<LE>()?;
+        }
+        Some(header)
+    } else {
+        None
+    };
+
+    let num_terrain_tables = input.read_u16::<LE>()?;
+    let num_terrains = input.read_u16::<LE>()?;
+    let game_version = GameVersion::detect(file_version, num_terrains);
+    let header = Header {
+        file_version,
+        game_version,
+        swgb_header,
+        num_terrain_tables,
+        num_terrains,
+        num_terrains_fixed: game_version.fixed_num_terrains(num_terrains),
+    };
+
+    let mut unknown_fields = UnknownFields {
+        // Two lists of pointers
+        terrain_table_pointers: RawBytes::read_from(
+            &mut *input,
+            UnknownFields::terrain_table_pointers_len(num_terrain_tables),
+        )?,
+        ..Default::default()
+    };
+
+    let mut sections = Sections {
+        terrain_tables: input.position(),
+        ..Default::default()
+    };
+    read_terrain_tables(input, &header)?;
+    sections.color_tables = input.position();
+    read_color_tables(input, &header)?;
+    sections.sounds = input.position();
+    read_sounds(input, &header)?;
+    sections.sprites = input.position();
+    let num_sprites = input.read_u16::<LE>()?;
+    unknown_fields.sprite_pointers =
+        RawBytes::read_from(&mut *input, 4 * usize::from(num_sprites))?;
+    input.set_position(sections.sprites + 2);
+    let sprites_exist = read_array(num_sprites.into(), || {
+        input.read_u32::<LE>().map(|n| n != 0)
+    })?;
+    for exists in sprites_exist {
+        sections.sprite_entries.push(if exists {
+            let offset = input.position();
+            Sprite::skip(&mut *input, file_version)?;
+            Some(offset)
+        } else {
+            None
+        });
+    }
+
+    // Pointers and map size values
+    unknown_fields.map_header = RawBytes::read_from(&mut *input, UnknownFields::MAP_HEADER_LEN)?;
+    sections.tile_sizes = input.position();
+    read_tile_sizes(input, &header)?;
+    unknown_fields.tile_sizes_padding =
+        RawBytes::read_from(&mut *input, UnknownFields::TILE_SIZES_PADDING_LEN)?;
+    sections.terrains = input.position();
+    read_terrains(input, &header)?;
+    sections.terrain_borders = input.position();
+    read_terrain_borders(input, &header)?;
+
+    // Map data and pointers, see `DatFile::read_from`.
+    unknown_fields.map_state =
+        RawBytes::read_from(&mut *input, UnknownFields::map_state_len(file_version))?;
+
+    sections.random_maps = input.position();
+    // The pointer follows the random map count.
+    let mut pointer = input.clone();
+    pointer.set_position(sections.random_maps + 4);
+    unknown_fields.random_maps_pointer =
+        RawBytes::read_from(&mut pointer, UnknownFields::RANDOM_MAPS_POINTER_LEN)?;
+    read_random_maps(input, &header)?;
+    sections.effects = input.position();
+    read_effects(input, &header)?;
+    sections.unit_lines = input.position();
+    read_unit_lines(input, &header)?;
+    sections.task_lists = input.position();
+    read_task_lists(input, &header)?;
+
+    let num_civilizations = input.read_u16::<LE>()?;
+    for _ in 0..num_civilizations {
+        sections.civilizations.push(input.position());
+        let _player_type = input.read_i8()?;
+        Civilization::skip(&mut *input, game_version)?;
+    }
+    let swgb_civs_trailer = read_swgb_trailer(input, &header)?;
+
+    sections.techs = input.position();
+    read_techs(input, &header)?;
+    let swgb_techs_trailer = read_swgb_trailer(input, &header)?;
+
+    // Unit kill and razing statistics
+    unknown_fields.combat_stats =
+        RawBytes::read_from(&mut *input, UnknownFields::COMBAT_STATS_LEN)?;
+    sections.tech_tree = input.position();
+
+    Ok((
+        header,
+        sections,
+        unknown_fields,
+        swgb_civs_trailer,
+        swgb_techs_trailer,
+    ))
+}
+
+/// A data file whose tables are parsed when they are first accessed.
+///
+/// Accessors return an error if the table could not be parsed. Use [`DatFile::read_from`] to
+/// parse all tables up front instead.
+#[derive(Debug)]
+pub struct LazyDatFile {
+    data: Data,
+    sections: Sections,
+    unknown_fields: UnknownFields,
+    swgb_civs_trailer: Option<i8>,
+    swgb_techs_trailer: Option<i8>,
+    terrain_tables: OnceLock<Vec<TerrainRestriction>>,
+    tile_sizes: OnceLock<Vec<TileSize>>,
+    terrains: OnceLock<Vec<Terrain>>,
+    terrain_borders: OnceLock<Vec<TerrainBorder>>,
+    random_maps: OnceLock<Vec<RandomMapInfo>>,
+    color_tables: OnceLock<Vec<ColorTable>>,
+    sounds: OnceLock<Vec<Sound>>,
+    sprites: OnceLock<Vec<Option<Sprite>>>,
+    effects: OnceLock<Vec<TechEffect>>,
+    unit_lines: OnceLock<Vec<UnitLine>>,
+    task_lists: OnceLock<Vec<Option<TaskList>>>,
+    civilizations: Vec<OnceLock<Civilization>>,
+    techs: OnceLock<Vec<Tech>>,
+    tech_tree: OnceLock<TechTree>,
+}
+
+impl LazyDatFile {
+    /// Read a data file from a byte stream, and find the offsets of its tables.
+    ///
+    /// Like [`DatFile::read_from`], this accepts both compressed and uncompressed data files.
+    pub fn read_from(input: impl Read) -> Result<Self> {
+        let mut data = vec![];
+        DatReader::new(input)?.read_to_end(&mut data)?;
+        let (header, sections, unknown_fields, swgb_civs_trailer, swgb_techs_trailer) =
+            scan(&mut Cursor::new(&data[..]))?;
+
+        Ok(Self {
+            civilizations: sections
+                .civilizations
+                .iter()
+                .map(|_| OnceLock::new())
+                .collect(),
+            data: Data {
+                bytes: data,
+                header,
+            },
+            sections,
+            unknown_fields,
+            swgb_civs_trailer,
+            swgb_techs_trailer,
+            terrain_tables: OnceLock::new(),
+            tile_sizes: OnceLock::new(),
+            terrains: OnceLock::new(),
+            terrain_borders: OnceLock::new(),
+            random_maps: OnceLock::new(),
+            color_tables: OnceLock::new(),
+            sounds: OnceLock::new(),
+            sprites: OnceLock::new(),
+            effects: OnceLock::new(),
+            unit_lines: OnceLock::new(),
+            task_lists: OnceLock::new(),
+            techs: OnceLock::new(),
+            tech_tree: OnceLock::new(),
+        })
+    }
+
+    /// Get a table, parsing it if it was not accessed before.
+    fn load<'a, T>(&'a self, cell: &'a OnceLock<T>, offset: u64, read: Reader<T>) -> Result<&'a T> {
+        if let Some(table) = cell.get() {
+            return Ok(table);
+        }
+        let table = self.data.parse(offset, read)?;
+        Ok(cell.get_or_init(|| table))
+    }
+
+    /// Get the game version targeted by this data file.
+    pub fn game_version(&self) -> GameVersion {
+        self.data.header.game_version
+    }
+
+    /// Get the terrain restriction tables.
+    pub fn terrain_tables(&self) -> Result<&[TerrainRestriction]> {
+        self.load(
+            &self.terrain_tables,
+            self.sections.terrain_tables,
+            read_terrain_tables,
+        )
+        .map(Vec::as_slice)
+    }
+
+    /// Get the tile size data.
+    pub fn tile_sizes(&self) -> Result<&[TileSize]> {
+        self.load(&self.tile_sizes, self.sections.tile_sizes, read_tile_sizes)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the terrains.
+    pub fn terrains(&self) -> Result<&[Terrain]> {
+        self.load(&self.terrains, self.sections.terrains, read_terrains)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the terrain border data.
+    pub fn terrain_borders(&self) -> Result<&[TerrainBorder]> {
+        self.load(
+            &self.terrain_borders,
+            self.sections.terrain_borders,
+            read_terrain_borders,
+        )
+        .map(Vec::as_slice)
+    }
+
+    /// Get the random map generation data.
+    pub fn random_maps(&self) -> Result<&[RandomMapInfo]> {
+        self.load(
+            &self.random_maps,
+            self.sections.random_maps,
+            read_random_maps,
+        )
+        .map(Vec::as_slice)
+    }
+
+    /// Get the player colour data.
+    pub fn color_tables(&self) -> Result<&[ColorTable]> {
+        self.load(
+            &self.color_tables,
+            self.sections.color_tables,
+            read_color_tables,
+        )
+        .map(Vec::as_slice)
+    }
+
+    /// Get the sounds.
+    pub fn sounds(&self) -> Result<&[Sound]> {
+        self.load(&self.sounds, self.sections.sounds, read_sounds)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the sprites.
+    pub fn sprites(&self) -> Result<&[Option<Sprite>]> {
+        self.load(&self.sprites, self.sections.sprites, read_sprites)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the tech effects.
+    pub fn effects(&self) -> Result<&[TechEffect]> {
+        self.load(&self.effects, self.sections.effects, read_effects)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the unit upgrade lines. Only used by Star Wars: Galactic Battlegrounds.
+    pub fn unit_lines(&self) -> Result<&[UnitLine]> {
+        self.load(&self.unit_lines, self.sections.unit_lines, read_unit_lines)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the task lists for unit types.
+    pub fn task_lists(&self) -> Result<&[Option<TaskList>]> {
+        self.load(&self.task_lists, self.sections.task_lists, read_task_lists)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the number of civilizations.
+    pub fn num_civilizations(&self) -> usize {
+        self.civilizations.len()
+    }
+
+    /// Get a civilization by its ID. Only this civilization's data is parsed.
+    pub fn civilization(&self, id: impl Into<CivilizationID>) -> Result<Option<&Civilization>> {
+        let index = usize::from(u8::from(id.into()));
+        match self.civilizations.get(index) {
+            Some(cell) => self
+                .load(cell, self.sections.civilizations[index], read_civilization)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a unit type available to a civilization.
+    pub fn unit_type(
+        &self,
+        civ: impl Into<CivilizationID>,
+        id: impl Into<UnitTypeID>,
+    ) -> Result<Option<&UnitType>> {
+        Ok(self
+            .civilization(civ)?
+            .and_then(|civ| civ.get_unit_type(id)))
+    }
+
+    /// Get the techs.
+    pub fn techs(&self) -> Result<&[Tech]> {
+        self.load(&self.techs, self.sections.techs, read_techs)
+            .map(Vec::as_slice)
+    }
+
+    /// Get the tech tree data.
+    pub fn tech_tree(&self) -> Result<&TechTree> {
+        self.load(&self.tech_tree, self.sections.tech_tree, read_tech_tree)
+    }
+
+    /// Parse all remaining tables, and convert this into a regular data file.
+    pub fn into_dat_file(mut self) -> Result<DatFile> {
+        let (data, sections) = (&self.data, &self.sections);
+        let civilizations = std::mem::take(&mut self.civilizations)
+            .into_iter()
+            .zip(&sections.civilizations)
+            .map(|(cell, &offset)| data.take(cell, offset, read_civilization))
+            .collect::<Result<Vec<_>>>()?;
+        let sprites = data.take(
+            std::mem::take(&mut self.sprites),
+            sections.sprites,
+            read_sprites,
+        )?;
+        self.finish(civilizations, sprites)
+    }
+
+    /// Parse all remaining tables, and convert this into a regular data file.
+    ///
+    /// Civilizations and sprites are parsed on the rayon thread pool. The result is the same as
+    /// with [`LazyDatFile::into_dat_file`].
+    #[cfg(feature = "rayon")]
+    pub fn into_dat_file_parallel(mut self) -> Result<DatFile> {
+        use rayon::prelude::*;
+
+        let (data, sections) = (&self.data, &self.sections);
+        let civilizations = std::mem::take(&mut self.civilizations)
+            .into_par_iter()
+            .zip(&sections.civilizations)
+            .map(|(cell, &offset)| data.take(cell, offset, read_civilization))
+            .collect::<Result<Vec<_>>>()?;
+        let sprites = match std::mem::take(&mut self.sprites).into_inner() {
+            Some(sprites) => sprites,
+            None => sections
+                .sprite_entries
+                .par_iter()
+                .map(|entry| {
+                    entry
+                        .map(|offset| data.parse(offset, read_sprite))
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>>>()?,
+        };
+        self.finish(civilizations, sprites)
+    }
+
+    /// Parse the tables that are not passed in, and assemble the data file.
+    fn finish(
+        self,
+        mut civilizations: Vec<Civilization>,
+        sprites: Vec<Option<Sprite>>,
+    ) -> Result<DatFile> {
+        let (data, header, sections) = (&self.data, &self.data.header, &self.sections);
+        civ::share_unit_names(&mut civilizations);
+
+        Ok(DatFile {
+            file_version: header.file_version,
+            game_version: header.game_version,
+            swgb_header: header.swgb_header,
+            terrain_tables: data.take(
+                self.terrain_tables,
+                sections.terrain_tables,
+                read_terrain_tables,
+            )?,
+            tile_sizes: data.take(self.tile_sizes, sections.tile_sizes, read_tile_sizes)?,
+            terrains: data.take(self.terrains, sections.terrains, read_terrains)?,
+            terrain_borders: data.take(
+                self.terrain_borders,
+                sections.terrain_borders,
+                read_terrain_borders,
+            )?,
+            random_maps: data.take(self.random_maps, sections.random_maps, read_random_maps)?,
+            color_tables: data.take(self.color_tables, sections.color_tables, read_color_tables)?,
+            sounds: data.take(self.sounds, sections.sounds, read_sounds)?,
+            sprites,
+            effects: data.take(self.effects, sections.effects, read_effects)?,
+            unit_lines: data.take(self.unit_lines, sections.unit_lines, read_unit_lines)?,
+            task_lists: data.take(self.task_lists, sections.task_lists, read_task_lists)?,
+            civilizations,
+            swgb_civs_trailer: self.swgb_civs_trailer,
+            techs: data.take(self.techs, sections.techs, read_techs)?,
+            swgb_techs_trailer: self.swgb_techs_trailer,
+            tech_tree: data.take(self.tech_tree, sections.tech_tree, read_tech_tree)?,
+            unknown_fields: self.unknown_fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::fs::File;
+
+    fn to_bytes(dat: &DatFile) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        dat.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn lazy_tables_match_eager() -> anyhow::Result<()> {
+        for path in &["fixtures/aok.dat", "fixtures/aoc1.0c.dat"] {
+            let eager = DatFile::read_from(File::open(path)?)?;
+            let lazy = LazyDatFile::read_from(File::open(path)?)?;
+
+            assert_eq!(lazy.game_version(), eager.game_version());
+            assert_eq!(lazy.num_civilizations(), eager.civilizations.len());
+            let archer = lazy.unit_type(1, 4)?.expect("archer should exist");
+            let eager_archer = eager.civilizations[1].get_unit_type(4).unwrap();
+            assert_eq!(archer.static_.name(), eager_archer.static_.name());
+            assert_eq!(archer.static_.hp, eager_archer.static_.hp);
+            assert_eq!(lazy.techs()?.len(), eager.techs.len());
+            assert_eq!(lazy.tech_tree()?.ages.len(), eager.tech_tree.ages.len());
+            assert!(lazy.civilization(200)?.is_none());
+
+            assert_eq!(to_bytes(&lazy.into_dat_file()?)?, to_bytes(&eager)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_civilization_player_type() -> anyhow::Result<()> {
+        let lazy = LazyDatFile::read_from(File::open("fixtures/aoc1.0c.dat")?)?;
+        let mut bytes = lazy.data.bytes.clone();
+        let offset = usize::try_from(lazy.sections.civilizations[1])?;
+        bytes[offset] = 2;
+
+        let err = DatFile::read_from(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let lazy = LazyDatFile::read_from(&bytes[..])?;
+        let err = lazy.civilization(1).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(lazy.civilization(0)?.is_some());
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_matches_eager() -> anyhow::Result<()> {
+        for path in &[
+            "fixtures/aok.dat",
+            "fixtures/aoc1.0c.dat",
+            "fixtures/hd.dat",
+        ] {
+            let eager = DatFile::read_from(File::open(path)?)?;
+            let parallel = DatFile::read_from_parallel(File::open(path)?)?;
+            assert_eq!(to_bytes(&parallel)?, to_bytes(&eager)?);
+
+            // Tables that were already accessed are reused.
+            let lazy = LazyDatFile::read_from(File::open(path)?)?;
+            lazy.civilization(1)?;
+            lazy.sprites()?;
+            assert_eq!(
+                to_bytes(&lazy.into_dat_file_parallel()?)?,
+                to_bytes(&eager)?
+            );
+        }
+        Ok(())
+    }
+}
@@ -10,8 +10,18 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+pub mod availability;
 mod civ;
 mod color_table;
+pub mod convert;
+pub mod dump;
+pub mod effect;
+mod hash;
+#[cfg(feature = "json")]
+mod json;
+mod lazy;
+pub mod merge;
+pub mod patch;
 pub mod random_map;
 mod sound;
 pub mod sprite;
@@ -20,26 +30,35 @@ mod tech;
 pub mod tech_tree;
 pub mod terrain;
 pub mod unit_type;
+mod unknown;
+pub mod validate;
 
-pub use crate::civ::{Civilization, CivilizationID};
+pub use crate::civ::{AttributeID, Civilization, CivilizationID, ResourceID};
 pub use crate::color_table::{ColorTable, PaletteIndex};
+pub use crate::hash::ContentHash;
+#[cfg(feature = "json")]
+pub use crate::json::DatTable;
+pub use crate::lazy::LazyDatFile;
 use crate::random_map::RandomMapInfo;
 pub use crate::sound::{Sound, SoundID, SoundItem};
 use crate::sprite::{Sprite, SpriteID};
 pub use crate::task::{Task, TaskList};
-pub use crate::tech::{Tech, TechEffect};
-use crate::tech_tree::TechTree;
+pub use crate::tech::{EffectCommand, Tech, TechBuilder, TechEffect, TechEffectRef};
+use crate::tech_tree::{TechTree, TechTreeDependency};
 use crate::terrain::{Terrain, TerrainBorder, TerrainID, TerrainRestriction, TileSize};
+use crate::unit_type::{UnitLine, UnitType};
+pub use crate::unknown::{RawBytes, UnknownFields};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
-use genie_support::{f32_eq, ReadSkipExt, TechID};
+use genie_support::{trace_section, CountingReader, TechID, TryConvertExt};
+pub use genie_support::{GameRelease, SaveVersion};
 use std::cmp::{Ordering, PartialOrd};
-use std::convert::TryInto;
 use std::fmt;
-use std::io::{Read, Result, Write};
+use std::io::{BufReader, Chain, Cursor, Error, ErrorKind, Read, Result, Write};
 
 /// A game version targeted by a data file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameVersion {
     /// The original expansion-less Age of Empires 2: Age of Kings.
     AoK,
@@ -47,22 +66,70 @@ pub enum GameVersion {
     AoC,
     /// Age of EMpires 2: HD Edition.
     HD,
+    /// Star Wars: Galactic Battlegrounds.
+    SWGB,
 }
 
 impl GameVersion {
     /// Get the most likely internal game data version number for a given game version.
-    fn as_f32(self) -> f32 {
+    fn data_version(self) -> SaveVersion {
         use GameVersion::*;
         match self {
-            AoK => 11.5,
-            AoC => 11.97,
-            HD => 12.0,
+            AoK => SaveVersion::AOK,
+            AoC => SaveVersion::new(11.97),
+            HD => SaveVersion::new(12.0),
+            // SWGB runs on the AoC engine, and its unit data uses the same version gates.
+            SWGB => SaveVersion::new(11.97),
+        }
+    }
+
+    /// Get the Age of Empires 2 release this game version belongs to, to look up which
+    /// features it supports. Returns `None` for Star Wars: Galactic Battlegrounds.
+    pub fn release(self) -> Option<GameRelease> {
+        match self {
+            GameVersion::AoK => Some(GameRelease::AoK),
+            GameVersion::AoC => Some(GameRelease::AoC),
+            GameVersion::HD => Some(GameRelease::HD),
+            GameVersion::SWGB => None,
+        }
+    }
+
+    /// Is this Star Wars: Galactic Battlegrounds?
+    pub fn is_swgb(self) -> bool {
+        self == GameVersion::SWGB
+    }
+
+    /// Detect the game version from the file version and the number of terrains of a data file.
+    fn detect(file_version: FileVersion, num_terrains: u16) -> Self {
+        if file_version.is_swgb() {
+            GameVersion::SWGB
+        } else if file_version == FileVersion(*b"VER 5.7\0") {
+            match num_terrains {
+                32 => GameVersion::AoK,
+                41 => GameVersion::AoC,
+                100 => GameVersion::HD,
+                _ => GameVersion::AoC, // TODO support different versions
+            }
+        } else {
+            GameVersion::AoC // TODO support different versions
+        }
+    }
+
+    /// Get the number of terrains that are actually stored in a data file for this game version.
+    fn fixed_num_terrains(self, num_terrains: u16) -> u16 {
+        // AoC hardcodes to 42 terrains, but says 41 terrains in the data file.
+        // The 42nd terrain is zeroed out.
+        if self == GameVersion::AoC && num_terrains == 41 {
+            42
+        } else {
+            num_terrains
         }
     }
 }
 
 /// A data file version.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileVersion([u8; 8]);
 
 impl From<[u8; 8]> for FileVersion {
@@ -80,7 +147,7 @@ impl From<&str> for FileVersion {
     fn from(string: &str) -> Self {
         assert!(string.len() <= 8);
         let mut bytes = [0; 8];
-        (&mut bytes[..string.len()]).copy_from_slice(string.as_bytes());
+        bytes[..string.len()].copy_from_slice(string.as_bytes());
         Self::from(bytes)
     }
 }
@@ -117,34 +184,76 @@ impl FileVersion {
 
     /// Is this file built for Star Wars: Galactic Battlegrounds?
     pub fn is_swgb(self) -> bool {
-        false
+        self.0 == *b"VER 5.9\0"
     }
 
     /// Is this file built for Age of Empires II: The Conquerors?
     pub fn is_aoc(self) -> bool {
-        let data_version = self.into_data_version();
-        f32_eq!(data_version, 11.97)
+        self.0 == *b"VER 5.7\0"
     }
 
     /// Is this file built for Age of Empires II: Definitive Edition?
     pub fn is_de2(self) -> bool {
-        self >= FileVersion(*b"VER 5.8\0")
+        !self.is_swgb() && self >= FileVersion(*b"VER 5.8\0")
+    }
+}
+
+/// How a data file is compressed when it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatCompression {
+    /// Write the data file without compression. The games can not read uncompressed data files,
+    /// but they are easier to inspect when debugging.
+    Uncompressed,
+    /// Compress the data file with raw deflate, like the games do. The level ranges from 0 (no
+    /// compression) to 9 (best compression).
+    Deflate { level: u32 },
+}
+
+impl Default for DatCompression {
+    fn default() -> Self {
+        Self::Deflate { level: 6 }
     }
+}
 
-    /// Get the data version associated with this file version.
-    fn into_data_version(self) -> f32 {
-        match &self.0 {
-            b"VER 5.7\0" => 11.97,
-            _ => panic!("unknown version"),
+/// Reads the contents of a data file, decompressing it if necessary.
+pub(crate) enum DatReader<R: Read> {
+    Uncompressed(Chain<Cursor<[u8; 4]>, R>),
+    Compressed(DeflateDecoder<Chain<Cursor<[u8; 4]>, R>>),
+}
+
+impl<R: Read> DatReader<R> {
+    /// Check if a data file is compressed, and prepare to read its contents.
+    pub(crate) fn new(mut input: R) -> Result<Self> {
+        // Uncompressed data files start with the file version. This is never a valid start of a
+        // deflate stream, as 'V' would indicate a reserved block type.
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        let input = Cursor::new(magic).chain(input);
+        if &magic == b"VER " {
+            Ok(Self::Uncompressed(input))
+        } else {
+            Ok(Self::Compressed(DeflateDecoder::new(input)))
+        }
+    }
+}
+
+impl<R: Read> Read for DatReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            Self::Uncompressed(input) => input.read(buf),
+            Self::Compressed(input) => input.read(buf),
         }
     }
 }
 
 /// A data file.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DatFile {
     file_version: FileVersion,
     game_version: GameVersion,
+    /// Unknown header values in Star Wars: Galactic Battlegrounds data files.
+    swgb_header: Option<[i32; 4]>,
     /// Terrain restriction tables.
     pub terrain_tables: Vec<TerrainRestriction>,
     /// Tile size data.
@@ -153,8 +262,8 @@ pub struct DatFile {
     pub terrains: Vec<Terrain>,
     /// Terrain border data, specifying how different terrains blend.
     pub terrain_borders: Vec<TerrainBorder>,
-    /// Random map data from AoE1.
-    random_maps: Vec<RandomMapInfo>,
+    /// Random map generation data from AoE1.
+    pub random_maps: Vec<RandomMapInfo>,
     /// Data about player colours.
     pub color_tables: Vec<ColorTable>,
     /// The available sounds.
@@ -163,49 +272,67 @@ pub struct DatFile {
     pub sprites: Vec<Option<Sprite>>,
     /// Tech effect data.
     pub effects: Vec<TechEffect>,
+    /// Unit upgrade lines. Only used by Star Wars: Galactic Battlegrounds.
+    pub unit_lines: Vec<UnitLine>,
     /// Task lists for unit types.
     pub task_lists: Vec<Option<TaskList>>,
     /// The available civilizations.
     pub civilizations: Vec<Civilization>,
+    /// Unknown byte following the civilizations in Star Wars: Galactic Battlegrounds data files.
+    swgb_civs_trailer: Option<i8>,
     /// Techs or researches.
     pub techs: Vec<Tech>,
+    /// Unknown byte following the techs in Star Wars: Galactic Battlegrounds data files.
+    swgb_techs_trailer: Option<i8>,
     /// Tech tree data.
     pub tech_tree: TechTree,
+    /// Values that are not interpreted, but kept so the data file can be written back unchanged.
+    pub unknown_fields: UnknownFields,
 }
 
 impl DatFile {
-    /// Read a data file from a compressed byte stream.
+    /// Read a data file from a byte stream.
+    ///
+    /// Data files are normally compressed, but uncompressed data files, as written by some
+    /// tools, are also accepted.
+    ///
+    /// The input is buffered internally, so a `File` can be passed directly. Data may be read
+    /// past the end of the data file.
     pub fn read_from(input: impl Read) -> Result<Self> {
-        let mut input = DeflateDecoder::new(input);
+        const FILE: &str = "data file";
+        // Most fields are read one by one, so buffer the decompressed data.
+        let mut input = CountingReader::new(BufReader::new(DatReader::new(input)?));
 
         let mut file_version = [0u8; 8];
         input.read_exact(&mut file_version)?;
         let file_version = FileVersion(file_version);
 
-        let num_terrain_tables = input.read_u16::<LE>()?;
-        let num_terrains = input.read_u16::<LE>()?;
-
-        let game_version = if file_version == FileVersion(*b"VER 5.7\0") {
-            match num_terrains {
-                32 => GameVersion::AoK,
-                41 => GameVersion::AoC,
-                100 => GameVersion::HD,
-                _ => GameVersion::AoC, // TODO support different versions
+        let swgb_header = if file_version.is_swgb() {
+            // Duplicate of the civilization count further down.
+            let _num_civilizations = input.read_u16::<LE>()?;
+            let mut header = [0; 4];
+            for value in header.iter_mut() {
+                *value = input.read_i32::<LE>()?;
             }
+            Some(header)
         } else {
-            GameVersion::AoC // TODO support different versions
+            None
         };
 
-        // AoC hardcodes to 42 terrains, but says 41 terrains in the data file.
-        // The 42nd terrain is zeroed out.
-        let num_terrains_fixed = if game_version == GameVersion::AoC && num_terrains == 41 {
-            42
-        } else {
-            num_terrains
-        };
+        let num_terrain_tables = input.read_u16::<LE>()?;
+        let num_terrains = input.read_u16::<LE>()?;
 
-        // Two lists of pointers
-        input.skip(4 * u64::from(num_terrain_tables) + 4 * u64::from(num_terrain_tables))?;
+        let game_version = GameVersion::detect(file_version, num_terrains);
+        let num_terrains_fixed = game_version.fixed_num_terrains(num_terrains);
+
+        let mut unknown_fields = UnknownFields {
+            // Two lists of pointers
+            terrain_table_pointers: RawBytes::read_from(
+                &mut input,
+                UnknownFields::terrain_table_pointers_len(num_terrain_tables),
+            )?,
+            ..Default::default()
+        };
 
         fn read_array<T>(num: usize, mut read: impl FnMut() -> Result<T>) -> Result<Vec<T>> {
             let mut list = vec![];
@@ -215,130 +342,149 @@ impl DatFile {
             Ok(list)
         }
 
-        let terrain_tables = read_array(num_terrain_tables.into(), || {
-            TerrainRestriction::read_from(&mut input, file_version, num_terrains)
+        let terrain_tables = trace_section(FILE, "terrain tables", &mut input, |input| {
+            read_array(num_terrain_tables.into(), || {
+                TerrainRestriction::read_from(&mut *input, file_version, num_terrains)
+            })
         })?;
 
-        let num_color_tables = input.read_u16::<LE>()?;
-        let color_tables = read_array(num_color_tables.into(), || {
-            ColorTable::read_from(&mut input)
+        let color_tables = trace_section(FILE, "color tables", &mut input, |input| {
+            let num_color_tables = input.read_u16::<LE>()?;
+            read_array(num_color_tables.into(), || ColorTable::read_from(input))
         })?;
 
-        let num_sounds = input.read_u16::<LE>()?;
-        let sounds = read_array(num_sounds.into(), || {
-            Sound::read_from(&mut input, file_version)
+        let sounds = trace_section(FILE, "sounds", &mut input, |input| {
+            let num_sounds = input.read_u16::<LE>()?;
+            read_array(num_sounds.into(), || Sound::read_from(input, file_version))
         })?;
 
-        let num_sprites = input.read_u16::<LE>()?;
-        let sprites_exist = read_array(num_sprites.into(), || {
-            input.read_u32::<LE>().map(|n| n != 0)
+        let sprites = trace_section(FILE, "sprites", &mut input, |input| -> Result<_> {
+            let num_sprites = input.read_u16::<LE>()?;
+            // A sprite exists if its pointer is not null.
+            let mut sprite_pointers = vec![0; 4 * usize::from(num_sprites)];
+            input.read_exact(&mut sprite_pointers)?;
+            unknown_fields.sprite_pointers = RawBytes::new(&sprite_pointers);
+            let mut sprites = vec![];
+            for exists in sprite_pointers.chunks(4).map(|pointer| pointer != [0; 4]) {
+                sprites.push(if exists {
+                    Some(Sprite::read_from(&mut *input, file_version)?)
+                } else {
+                    None
+                });
+            }
+            Ok(sprites)
         })?;
-        let mut sprites = vec![];
-        for exists in sprites_exist {
-            sprites.push(if exists {
-                Some(Sprite::read_from(&mut input)?)
-            } else {
-                None
-            });
-        }
-
-        // Some raw pointer values
-        let _map_vtable_pointer = input.read_i32::<LE>()?;
-        let _tiles_pointer = input.read_i32::<LE>()?;
 
-        // Bogus stuff
-        let _map_width = input.read_i32::<LE>()?;
-        let _map_height = input.read_i32::<LE>()?;
-        let _world_width = input.read_i32::<LE>()?;
-        let _world_height = input.read_i32::<LE>()?;
+        // Pointers, and bogus map and world sizes
+        unknown_fields.map_header = RawBytes::read_from(&mut input, UnknownFields::MAP_HEADER_LEN)?;
 
         let mut tile_sizes = vec![TileSize::default(); 19];
         for val in tile_sizes.iter_mut() {
             *val = TileSize::read_from(&mut input)?;
         }
 
-        // Padding
-        input.read_i16::<LE>()?;
+        unknown_fields.tile_sizes_padding =
+            RawBytes::read_from(&mut input, UnknownFields::TILE_SIZES_PADDING_LEN)?;
 
-        let terrains = read_array(num_terrains_fixed.into(), || {
-            Terrain::read_from(&mut input, file_version, num_terrains_fixed)
+        let terrains = trace_section(FILE, "terrains", &mut input, |input| {
+            read_array(num_terrains_fixed.into(), || {
+                Terrain::read_from(&mut *input, file_version, num_terrains_fixed)
+            })
         })?;
-        let terrain_borders = read_array(16, || TerrainBorder::read_from(&mut input))?;
-
-        // Should just skip all this shit probably
-        let _map_row_offset = input.read_i32::<LE>()?;
-        let _map_min_x = input.read_f32::<LE>()?;
-        let _map_min_y = input.read_f32::<LE>()?;
-        let _map_max_x = input.read_f32::<LE>()?;
-        let _map_max_y = input.read_f32::<LE>()?;
-        let _map_max_x = input.read_f32::<LE>()?;
-        let _map_max_y = input.read_f32::<LE>()?;
-        let _additional_terrain_count = input.read_u16::<LE>()?;
-        let _borders_used = input.read_u16::<LE>()?;
-        let _max_terrain = input.read_u16::<LE>()?;
-        let _tile_width = input.read_u16::<LE>()?;
-        let _tile_height = input.read_u16::<LE>()?;
-        let _tile_half_width = input.read_u16::<LE>()?;
-        let _tile_half_height = input.read_u16::<LE>()?;
-        let _elev_height = input.read_u16::<LE>()?;
-        let _current_row = input.read_u16::<LE>()?;
-        let _current_column = input.read_u16::<LE>()?;
-        let _block_begin_row = input.read_u16::<LE>()?;
-        let _block_end_row = input.read_u16::<LE>()?;
-        let _block_begin_column = input.read_u16::<LE>()?;
-        let _block_end_column = input.read_u16::<LE>()?;
-        let _seach_map_pointer = input.read_i32::<LE>()?;
-        let _seach_map_rows_pointer = input.read_i32::<LE>()?;
-        let _any_frame_change = input.read_u8()?;
-        let _map_visible = input.read_u8()?;
-        let _map_fog_of_war = input.read_u8()?;
-
-        // Lots more pointers and stuff
-        input.skip(21 + 157 * 4)?;
+        let terrain_borders = trace_section(FILE, "terrain borders", &mut input, |input| {
+            read_array(16, || TerrainBorder::read_from(&mut *input, file_version))
+        })?;
+
+        // Map row offset, map bounds, terrain and tile size values, search map pointers, map
+        // visibility flags, and lots more pointers and stuff
+        unknown_fields.map_state =
+            RawBytes::read_from(&mut input, UnknownFields::map_state_len(file_version))?;
 
         let num_random_maps = input.read_u32::<LE>()? as usize;
-        let _random_maps_pointer = input.read_u32::<LE>()?;
+        unknown_fields.random_maps_pointer =
+            RawBytes::read_from(&mut input, UnknownFields::RANDOM_MAPS_POINTER_LEN)?;
 
-        let mut random_maps = read_array(num_random_maps, || RandomMapInfo::read_from(&mut input))?;
-        for map in random_maps.iter_mut() {
-            map.finish(&mut input)?;
-        }
+        let random_maps = trace_section(FILE, "random maps", &mut input, |input| -> Result<_> {
+            let mut random_maps = read_array(num_random_maps, || RandomMapInfo::read_from(input))?;
+            for map in random_maps.iter_mut() {
+                map.finish(input)?;
+            }
+            Ok(random_maps)
+        })?;
 
-        let num_effects = input.read_u32::<LE>()? as usize;
-        let effects = read_array(num_effects, || TechEffect::read_from(&mut input))?;
+        let effects = trace_section(FILE, "effects", &mut input, |input| {
+            let num_effects = input.read_u32::<LE>()? as usize;
+            read_array(num_effects, || TechEffect::read_from(input))
+        })?;
 
-        let num_task_lists = input.read_u32::<LE>()? as usize;
-        let task_lists = read_array(num_task_lists, || {
-            if input.read_u8()? != 0 {
-                TaskList::read_from(&mut input).map(Some)
-            } else {
-                Ok(None)
-            }
+        let unit_lines = if file_version.is_swgb() {
+            trace_section(FILE, "unit lines", &mut input, |input| {
+                let num_unit_lines = input.read_u16::<LE>()?;
+                read_array(num_unit_lines.into(), || UnitLine::read_from(&mut *input))
+            })?
+        } else {
+            vec![]
+        };
+
+        let task_lists = trace_section(FILE, "task lists", &mut input, |input| {
+            let num_task_lists = input.read_u32::<LE>()? as usize;
+            read_array(num_task_lists, || {
+                if input.read_u8()? != 0 {
+                    TaskList::read_from(&mut *input).map(Some)
+                } else {
+                    Ok(None)
+                }
+            })
         })?;
 
-        let num_civilizations = input.read_u16::<LE>()?;
-        let civilizations = read_array(num_civilizations.into(), || {
-            let player_type = input.read_i8()?;
-            assert_eq!(player_type, 1);
-            Civilization::read_from(&mut input, game_version)
+        let mut civilizations = trace_section(FILE, "civilizations", &mut input, |input| {
+            let num_civilizations = input.read_u16::<LE>()?;
+            read_array(num_civilizations.into(), || {
+                let player_type = input.read_i8()?;
+                if player_type != 1 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "unexpected civilization player type {}, expected 1",
+                            player_type
+                        ),
+                    ));
+                }
+                Civilization::read_from(&mut *input, game_version)
+            })
         })?;
+        civ::share_unit_names(&mut civilizations);
+        let swgb_civs_trailer = if file_version.is_swgb() {
+            Some(input.read_i8()?)
+        } else {
+            None
+        };
 
-        let num_techs = input.read_u16::<LE>()?;
-        let techs = read_array(num_techs.into(), || Tech::read_from(&mut input))?;
+        let techs = trace_section(FILE, "techs", &mut input, |input| {
+            let num_techs = input.read_u16::<LE>()?;
+            read_array(num_techs.into(), || {
+                Tech::read_from(&mut *input, file_version)
+            })
+        })?;
+        let swgb_techs_trailer = if file_version.is_swgb() {
+            Some(input.read_i8()?)
+        } else {
+            None
+        };
 
-        let _time_slice = input.read_u32::<LE>()?;
-        let _unit_kill_rate = input.read_u32::<LE>()?;
-        let _unit_kill_total = input.read_u32::<LE>()?;
-        let _unit_hit_point_rate = input.read_u32::<LE>()?;
-        let _unit_hit_point_total = input.read_u32::<LE>()?;
-        let _razing_kill_rate = input.read_u32::<LE>()?;
-        let _razing_kill_total = input.read_u32::<LE>()?;
+        // Time slice, unit kill rate and total, unit hit point rate and total, razing kill rate
+        // and total
+        unknown_fields.combat_stats =
+            RawBytes::read_from(&mut input, UnknownFields::COMBAT_STATS_LEN)?;
 
-        let tech_tree = TechTree::read_from(&mut input)?;
+        let tech_tree = trace_section(FILE, "tech tree", &mut input, |input| {
+            TechTree::read_from(input)
+        })?;
 
         Ok(Self {
             file_version,
             game_version,
+            swgb_header,
             terrain_tables,
             tile_sizes,
             terrains,
@@ -348,117 +494,138 @@ impl DatFile {
             sounds,
             sprites,
             effects,
+            unit_lines,
             task_lists,
             civilizations,
+            swgb_civs_trailer,
             techs,
+            swgb_techs_trailer,
             tech_tree,
+            unknown_fields,
         })
     }
 
+    /// Read a data file from a byte stream, parsing its largest tables on multiple threads.
+    ///
+    /// This finds the offsets of all tables first, and then parses civilizations and sprites on
+    /// the rayon thread pool. The result is the same as with [`DatFile::read_from`].
+    #[cfg(feature = "rayon")]
+    pub fn read_from_parallel(input: impl Read) -> Result<Self> {
+        LazyDatFile::read_from(input)?.into_dat_file_parallel()
+    }
+
+    /// Read a data file from an async stream, such as an upload in a web service.
+    ///
+    /// The whole file is read into memory without blocking the executor, and then parsed.
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader(mut input: impl tokio::io::AsyncRead + Unpin) -> Result<Self> {
+        use tokio::io::AsyncReadExt;
+
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes).await?;
+        Self::read_from(&bytes[..])
+    }
+
     /// Serialize this data file to an output stream. Compression is applied by this function.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        self.write_to_with(output, DatCompression::default())
+    }
+
+    /// Serialize this data file to an output stream, with the given compression.
+    pub fn write_to_with<W: Write>(
+        &self,
+        output: &mut W,
+        compression: DatCompression,
+    ) -> Result<()> {
+        match compression {
+            DatCompression::Uncompressed => self.write_uncompressed(output),
+            DatCompression::Deflate { level } => {
+                if level > 9 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("invalid compression level {}, expected 0-9", level),
+                    ));
+                }
+                let mut output = DeflateEncoder::new(output, Compression::new(level));
+                self.write_uncompressed(&mut output)?;
+                output.finish()?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_uncompressed(&self, mut output: impl Write) -> Result<()> {
         let num_terrains = if self.game_version == GameVersion::AoC && self.terrains.len() == 42 {
             41
         } else {
             self.terrains.len()
         };
 
-        let mut output = DeflateEncoder::new(output, Compression::default());
         output.write_all(&self.file_version.0)?;
-        output.write_u16::<LE>(self.terrain_tables.len().try_into().unwrap())?;
-        output.write_u16::<LE>(num_terrains.try_into().unwrap())?;
+        if self.file_version.is_swgb() {
+            output.write_u16::<LE>(self.civilizations.len().try_convert()?)?;
+            for value in &self.swgb_header.unwrap_or_default() {
+                output.write_i32::<LE>(*value)?;
+            }
+        }
+        output.write_u16::<LE>(self.terrain_tables.len().try_convert()?)?;
+        output.write_u16::<LE>(num_terrains.try_convert()?)?;
 
         // Two lists of pointers
-        output.write_all(&vec![
-            0u8;
-            4 * self.terrain_tables.len()
-                + 4 * self.terrain_tables.len()
-        ])?;
+        self.unknown_fields.terrain_table_pointers.write_to(
+            &mut output,
+            UnknownFields::terrain_table_pointers_len(self.terrain_tables.len().try_convert()?),
+        )?;
 
         for table in &self.terrain_tables {
-            table.write_to(
-                &mut output,
-                self.file_version,
-                num_terrains.try_into().unwrap(),
-            )?;
+            table.write_to(&mut output, self.file_version, num_terrains.try_convert()?)?;
         }
 
-        output.write_u16::<LE>(self.color_tables.len().try_into().unwrap())?;
+        output.write_u16::<LE>(self.color_tables.len().try_convert()?)?;
         for table in &self.color_tables {
             table.write_to(&mut output)?;
         }
 
-        output.write_u16::<LE>(self.sounds.len().try_into().unwrap())?;
+        output.write_u16::<LE>(self.sounds.len().try_convert()?)?;
         for sound in &self.sounds {
             sound.write_to(&mut output, self.file_version)?;
         }
 
-        output.write_u16::<LE>(self.sprites.len().try_into().unwrap())?;
-        for maybe_sprite in &self.sprites {
-            output.write_u32::<LE>(match maybe_sprite {
-                Some(_) => 1,
-                None => 0,
-            })?;
-        }
+        output.write_u16::<LE>(self.sprites.len().try_convert()?)?;
+        self.unknown_fields
+            .sprite_pointers
+            .write_pointers_to(&mut output, &self.sprites)?;
         for sprite in self.sprites.iter().flatten() {
-            sprite.write_to(&mut output)?;
+            sprite.write_to(&mut output, self.file_version)?;
         }
 
-        output.write_u32::<LE>(0)?; // map vtable pointer
-        output.write_u32::<LE>(0)?; // map tiles pointer
-        output.write_u32::<LE>(0)?; // map width
-        output.write_u32::<LE>(0)?; // map height
-        output.write_u32::<LE>(0)?; // world width
-        output.write_u32::<LE>(0)?; // world height
+        self.unknown_fields
+            .map_header
+            .write_to(&mut output, UnknownFields::MAP_HEADER_LEN)?;
 
         for size in &self.tile_sizes {
             size.write_to(&mut output)?;
         }
 
-        // Padding
-        output.write_i16::<LE>(0)?;
+        self.unknown_fields
+            .tile_sizes_padding
+            .write_to(&mut output, UnknownFields::TILE_SIZES_PADDING_LEN)?;
 
         for terrain in &self.terrains {
             terrain.write_to(&mut output, self.file_version, self.terrains.len() as u16)?;
         }
         for border in &self.terrain_borders {
-            border.write_to(&mut output)?;
+            border.write_to(&mut output, self.file_version)?;
         }
 
-        // TODO put correct values in
-        output.write_i32::<LE>(0)?;
-        output.write_f32::<LE>(0.0)?;
-        output.write_f32::<LE>(0.0)?;
-        output.write_f32::<LE>(0.0)?;
-        output.write_f32::<LE>(0.0)?;
-        output.write_f32::<LE>(0.0)?;
-        output.write_f32::<LE>(0.0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u16::<LE>(0)?;
-        output.write_i32::<LE>(0)?;
-        output.write_i32::<LE>(0)?;
-        output.write_u8(0)?;
-        output.write_u8(0)?;
-        output.write_u8(0)?;
-
-        // Lots more pointers and stuff
-        let nulls = [0; 21 + 157 * 4];
-        output.write_all(&nulls)?;
+        self.unknown_fields
+            .map_state
+            .write_to(&mut output, UnknownFields::map_state_len(self.file_version))?;
 
         output.write_u32::<LE>(self.random_maps.len() as u32)?;
-        output.write_u32::<LE>(0)?; // pointer
+        self.unknown_fields
+            .random_maps_pointer
+            .write_to(&mut output, UnknownFields::RANDOM_MAPS_POINTER_LEN)?;
 
         for map in &self.random_maps {
             map.write_to(&mut output)?;
@@ -472,6 +639,13 @@ impl DatFile {
             effect.write_to(&mut output)?;
         }
 
+        if self.file_version.is_swgb() {
+            output.write_u16::<LE>(self.unit_lines.len().try_convert()?)?;
+            for unit_line in &self.unit_lines {
+                unit_line.write_to(&mut output)?;
+            }
+        }
+
         output.write_u32::<LE>(self.task_lists.len() as u32)?;
         for task_list in &self.task_lists {
             if let Some(task_list) = task_list {
@@ -487,19 +661,21 @@ impl DatFile {
             output.write_i8(1)?; // player type
             civilization.write_to(&mut output, self.game_version)?;
         }
+        if self.file_version.is_swgb() {
+            output.write_i8(self.swgb_civs_trailer.unwrap_or(0))?;
+        }
 
         output.write_u16::<LE>(self.techs.len() as u16)?;
         for tech in &self.techs {
-            tech.write_to(&mut output)?;
+            tech.write_to(&mut output, self.file_version)?;
+        }
+        if self.file_version.is_swgb() {
+            output.write_i8(self.swgb_techs_trailer.unwrap_or(0))?;
         }
 
-        output.write_u32::<LE>(0)?;
-        output.write_u32::<LE>(0)?;
-        output.write_u32::<LE>(0)?;
-        output.write_u32::<LE>(0)?;
-        output.write_u32::<LE>(0)?;
-        output.write_u32::<LE>(0)?;
-        output.write_u32::<LE>(0)?;
+        self.unknown_fields
+            .combat_stats
+            .write_to(&mut output, UnknownFields::COMBAT_STATS_LEN)?;
 
         self.tech_tree.write_to(&mut output)?;
 
@@ -518,6 +694,16 @@ impl DatFile {
         self.terrains.get(usize::from(id))
     }
 
+    /// Get a terrain restriction by its ID.
+    pub fn get_terrain_restriction(&self, id: u16) -> Option<&TerrainRestriction> {
+        self.terrain_tables.get(usize::from(id))
+    }
+
+    /// Get the terrain restriction that applies to a unit type.
+    pub fn terrain_restriction_for(&self, unit_type: &UnitType) -> Option<&TerrainRestriction> {
+        self.get_terrain_restriction(unit_type.static_.terrain_restriction_id)
+    }
+
     /// Get the GAIA civilization.
     pub fn get_gaia(&self) -> Option<&Civilization> {
         self.get_civilization(0)
@@ -535,11 +721,77 @@ impl DatFile {
         self.sounds.get(usize::from(id))
     }
 
+    /// Get a random map type by its ID.
+    pub fn get_random_map(&self, id: i32) -> Option<&RandomMapInfo> {
+        self.random_maps.iter().find(|map| map.id() == id)
+    }
+
     /// Get a sprite by its ID.
     pub fn get_sprite(&self, id: impl Into<SpriteID>) -> Option<&Sprite> {
         let id: SpriteID = id.into();
         self.sprites.get(usize::from(id)).and_then(Option::as_ref)
     }
+
+    /// Get a mutable reference to a sprite by its ID.
+    pub fn get_sprite_mut(&mut self, id: impl Into<SpriteID>) -> Option<&mut Sprite> {
+        let id: SpriteID = id.into();
+        self.sprites
+            .get_mut(usize::from(id))
+            .and_then(Option::as_mut)
+    }
+
+    /// Get the techs that a civilization's tech tree effect disables.
+    pub fn disabled_techs(&self, civ: impl Into<CivilizationID>) -> Vec<TechID> {
+        let effect = self
+            .get_civilization(civ)
+            .and_then(|civ| self.effects.get(usize::from(civ.civ_effect())));
+        let commands = match effect {
+            Some(effect) => &effect.commands,
+            None => return vec![],
+        };
+        commands
+            .iter()
+            .filter_map(|command| match command.decode() {
+                effect::Command::DisableTech { tech } => Some(tech),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Check if a civilization can research a tech.
+    ///
+    /// A tech can not be researched if it is exclusive to another civilization, if the
+    /// civilization's tech tree disables it, or if any of the techs it depends on in the tech tree
+    /// can not be researched.
+    pub fn can_research(&self, civ: impl Into<CivilizationID>, tech: impl Into<TechID>) -> bool {
+        let civ: CivilizationID = civ.into();
+        self.can_research_with(civ, tech.into(), &self.disabled_techs(civ))
+    }
+
+    /// Check if a civilization can research a tech, given the techs that its tech tree disables.
+    pub(crate) fn can_research_with(
+        &self,
+        civ: CivilizationID,
+        tech: TechID,
+        disabled: &[TechID],
+    ) -> bool {
+        let is_available = |id: TechID| match self.get_tech(id) {
+            Some(tech) => {
+                !disabled.contains(&id) && tech.civilization_id().is_none_or(|owner| owner == civ)
+            }
+            None => false,
+        };
+
+        is_available(tech)
+            && self
+                .tech_tree
+                .all_dependencies_of(tech)
+                .into_iter()
+                .all(|dependency| match dependency {
+                    TechTreeDependency::Research(id) => is_available(id),
+                    _ => true,
+                })
+    }
 }
 
 #[cfg(test)]
@@ -547,11 +799,26 @@ mod tests {
     use super::*;
     use std::{
         collections::hash_map::DefaultHasher,
+        convert::TryFrom,
         fs::File,
         hash::{Hash, Hasher},
         io::Cursor,
     };
 
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn read_async() -> anyhow::Result<()> {
+        let file = tokio::fs::File::open("fixtures/aoc1.0c.dat").await?;
+        let dat = DatFile::from_async_reader(file).await?;
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let sync = DatFile::read_from(&mut f)?;
+        assert_eq!(dat.file_version, sync.file_version);
+        assert_eq!(dat.civilizations.len(), sync.civilizations.len());
+        assert_eq!(dat.techs.len(), sync.techs.len());
+        assert_eq!(dat.get_tech(199).unwrap().name(), "Fletching");
+        Ok(())
+    }
+
     #[test]
     fn aok() -> anyhow::Result<()> {
         let mut f = File::open("fixtures/aok.dat")?;
@@ -568,6 +835,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn tech_tree_queries() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        // Feudal Age requires the Dark Age.
+        assert_eq!(
+            dat.tech_tree.dependencies_of(101),
+            vec![TechTreeDependency::Age(1)]
+        );
+        // Britons do not get Paladin, Franks do.
+        assert!(!dat.can_research(1, 265));
+        assert!(dat.can_research(2, 265));
+        // Only Britons can research the Longbowman.
+        assert!(dat.can_research(1, 263));
+        assert!(!dat.can_research(2, 263));
+        Ok(())
+    }
+
     #[test]
     fn non_7bit_ascii_tech_name() -> anyhow::Result<()> {
         let mut f = File::open("fixtures/age-of-chivalry.dat")?;
@@ -576,6 +861,54 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn swgb_file_version() {
+        let swgb = FileVersion(*b"VER 5.9\0");
+        assert!(swgb.is_swgb());
+        assert!(!swgb.is_aoc());
+        assert!(!swgb.is_de2());
+        assert!(!FileVersion(*b"VER 5.7\0").is_swgb());
+    }
+
+    /// There is no Star Wars: Galactic Battlegrounds data file among the fixtures, so this
+    /// converts the HD Edition data file to the SWGB layout and checks that the SWGB-only parts
+    /// are read back.
+    #[test]
+    fn swgb_roundtrip() -> anyhow::Result<()> {
+        let mut dat = DatFile::read_from(File::open("fixtures/hd.dat")?)?;
+        dat.file_version = FileVersion(*b"VER 5.9\0");
+        dat.game_version = GameVersion::SWGB;
+        dat.swgb_header = Some([1, 2, 3, 4]);
+        dat.swgb_civs_trailer = Some(5);
+        dat.swgb_techs_trailer = Some(6);
+        let line = [7, 0, 5, 0, b'A', b'r', b'c', b'h', b'r', 2, 0, 4, 0, 24, 0];
+        dat.unit_lines.push(UnitLine::read_from(&line[..])?);
+
+        let mut bytes = vec![];
+        dat.write_to(&mut bytes)?;
+        let swgb = DatFile::read_from(&bytes[..])?;
+        assert!(swgb.file_version.is_swgb());
+        assert_eq!(swgb.game_version(), GameVersion::SWGB);
+        assert_eq!(swgb.swgb_header, Some([1, 2, 3, 4]));
+        assert_eq!(swgb.swgb_civs_trailer, Some(5));
+        assert_eq!(swgb.swgb_techs_trailer, Some(6));
+        assert_eq!(swgb.unit_lines.len(), 1);
+        assert_eq!(swgb.unit_lines[0].id, 7);
+        assert_eq!(swgb.unit_lines[0].name(), "Archr");
+        assert_eq!(swgb.unit_lines[0].unit_ids, vec![4.into(), 24.into()]);
+        assert_eq!(swgb.civilizations.len(), dat.civilizations.len());
+        assert_eq!(swgb.techs.len(), dat.techs.len());
+        assert_eq!(swgb.get_tech(199).unwrap().name(), "Fletching");
+        let archer = swgb.civilizations[1].get_unit_type(4).unwrap();
+        assert_eq!(archer.static_.name(), "ARCHR");
+        assert_eq!(archer.static_.name2(), Some(""));
+
+        let mut rewritten = vec![];
+        swgb.write_to(&mut rewritten)?;
+        assert_eq!(rewritten, bytes);
+        Ok(())
+    }
+
     #[test]
     fn hd_edition() -> anyhow::Result<()> {
         let mut f = File::open("fixtures/hd.dat")?;
@@ -584,6 +917,177 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn write_compression() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let mut compressed = vec![];
+        dat.write_to(&mut compressed)?;
+
+        let mut uncompressed = vec![];
+        dat.write_to_with(&mut uncompressed, DatCompression::Uncompressed)?;
+        assert!(uncompressed.starts_with(b"VER 5.7\0"));
+        assert!(uncompressed.len() > compressed.len());
+
+        let mut fast = vec![];
+        dat.write_to_with(&mut fast, DatCompression::Deflate { level: 1 })?;
+        assert!(fast.len() > compressed.len());
+
+        for bytes in &[uncompressed, fast] {
+            let mut rewritten = vec![];
+            DatFile::read_from(&bytes[..])?.write_to(&mut rewritten)?;
+            assert_eq!(rewritten, compressed);
+        }
+
+        assert!(dat
+            .write_to_with(&mut vec![], DatCompression::Deflate { level: 10 })
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn display_names() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let archer = dat.civilizations[1].get_unit_type(4).unwrap();
+        let fletching = dat.get_tech(199).unwrap();
+
+        let mut base = genie_lang::LangFile::new();
+        base.insert(archer.static_.string_id.clone(), "Archer".to_string());
+        let mut expansion = genie_lang::LangFile::new();
+        expansion.insert(archer.static_.string_id.clone(), "Bowman".to_string());
+
+        assert_eq!(archer.display_name(&base), Some("Archer"));
+        assert_eq!(
+            archer.display_name(&[&expansion, &base][..]),
+            Some("Bowman")
+        );
+        assert_eq!(archer.help_text(&base), None);
+        assert_eq!(fletching.display_name(&base), None);
+        Ok(())
+    }
+
+    #[test]
+    fn insert_with_builders() -> anyhow::Result<()> {
+        use crate::unit_type::{UnitBaseClass, UnitTypeBuilder};
+
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        let num_techs = dat.techs.len();
+
+        // Research the new tech at the castle (82), and train the new unit there.
+        let tech_id = TechBuilder::new("Hero Training")
+            .cost(AttributeID::GOLD, 100)
+            .research_time(60)
+            .location(82u16)
+            .effect(TechEffect::default())
+            .insert(&mut dat);
+        let unit_id = UnitTypeBuilder::new(UnitBaseClass::Combat)
+            .name("HERO")
+            .hp(300)
+            .train_location(82u16)
+            .requires_tech(tech_id)
+            .civilizations(vec![CivilizationID::from(1)])
+            .insert(&mut dat);
+        assert_eq!(usize::from(tech_id), num_techs);
+
+        let mut serialized = vec![];
+        dat.write_to(&mut serialized)?;
+        let dat = DatFile::read_from(Cursor::new(&serialized))?;
+
+        assert_eq!(dat.get_tech(tech_id).unwrap().name(), "Hero Training");
+        let hero = dat.civilizations[1].get_unit_type(unit_id).unwrap();
+        assert_eq!(hero.static_.name(), "HERO");
+        assert_eq!(hero.static_.hp, 300);
+        assert!(dat.civilizations[2].get_unit_type(unit_id).is_none());
+        assert_eq!(
+            dat.tech_tree.get_unit(unit_id).unwrap().requires_tech_id(),
+            Some(tech_id)
+        );
+        assert_eq!(
+            dat.tech_tree.dependencies_of(tech_id),
+            vec![TechTreeDependency::Building(82.into())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn terrain_restrictions() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        // Grass (0), Water (1).
+        let villager = dat.civilizations[1].get_unit_type(83).unwrap();
+        let land = dat.terrain_restriction_for(villager).unwrap();
+        assert!(land.can_enter(0u16));
+        assert!(!land.can_enter(1u16));
+        let fishing_ship = dat.civilizations[1].get_unit_type(13).unwrap();
+        let water = dat.terrain_restriction_for(fishing_ship).unwrap();
+        assert!(!water.can_enter(0u16));
+        assert!(water.can_enter(1u16));
+
+        let num_terrains = u16::try_from(dat.terrains.len())?;
+        let err = land
+            .write_to(vec![], dat.file_version, num_terrains + 1)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn armor_classes_and_damage() -> anyhow::Result<()> {
+        use crate::unit_type::ArmorClass;
+
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let combat = |id: u16| {
+            dat.civilizations[1]
+                .get_unit_type(id)
+                .and_then(|unit| unit.base_combat.as_ref())
+                .unwrap()
+        };
+        // Archer (4), Spearman (93), Militia (74).
+        let archer = combat(4);
+        let spearman = combat(93);
+        let militia = combat(74);
+        assert_eq!(archer.attack(ArmorClass::Pierce), Some(4));
+        assert_eq!(archer.attack(ArmorClass::Spearmen), Some(3));
+        assert_eq!(militia.armor(ArmorClass::Pierce), Some(1));
+        assert_eq!(militia.armor(ArmorClass::Spearmen), None);
+        // Pierce attack plus the bonus against spearmen.
+        assert_eq!(archer.damage_to(spearman), 7);
+        assert_eq!(archer.damage_to(militia), 3);
+        assert_eq!(militia.damage_to(archer), 4);
+        assert_eq!(ArmorClass::from(27), ArmorClass::Spearmen);
+        assert_eq!(u16::from(ArmorClass::Unknown(99)), 99);
+        Ok(())
+    }
+
+    #[test]
+    fn unit_class_helpers() -> anyhow::Result<()> {
+        use crate::unit_type::UnitBaseClass;
+
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let unit = |id: u16| dat.civilizations[1].get_unit_type(id).unwrap();
+        // Archer (4), Villager (83), Archery Range (87).
+        let (archer, villager, range) = (unit(4), unit(83), unit(87));
+        assert!(archer.is_military());
+        assert!(!archer.is_building());
+        assert_eq!(archer.trainable_at(), Some(87.into()));
+        assert!(villager.is_civilian());
+        assert!(!villager.is_military());
+        assert!(range.is_building());
+        assert!(!range.is_military());
+        assert!(range.unit_base_class.inherits_from(UnitBaseClass::Combat));
+        assert_eq!(range.unit_base_class.chain().count(), 7);
+        assert_eq!(UnitBaseClass::Tree.parent(), Some(UnitBaseClass::Static));
+        assert_eq!(
+            UnitBaseClass::Missile.partial_cmp(&UnitBaseClass::Combat),
+            None
+        );
+        Ok(())
+    }
+
     #[test]
     fn reserialize() -> anyhow::Result<()> {
         let original = std::fs::read("fixtures/aoc1.0c.dat")?;
@@ -603,4 +1107,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(feature = "raw-fields")]
+    fn lossless_roundtrip() -> anyhow::Result<()> {
+        for path in &[
+            "fixtures/aok.dat",
+            "fixtures/aoc1.0c.dat",
+            "fixtures/hd.dat",
+        ] {
+            let original = std::fs::read(path)?;
+            let mut decompressed = vec![];
+            DatReader::new(&original[..])?.read_to_end(&mut decompressed)?;
+
+            let dat = DatFile::read_from(&original[..])?;
+            let mut serialized = vec![];
+            dat.write_to_with(&mut serialized, DatCompression::Uncompressed)?;
+            assert!(serialized == decompressed, "{} did not round-trip", path);
+
+            let lazy = LazyDatFile::read_from(&original[..])?.into_dat_file()?;
+            let mut serialized = vec![];
+            lazy.write_to_with(&mut serialized, DatCompression::Uncompressed)?;
+            assert!(
+                serialized == decompressed,
+                "{} did not round-trip lazily",
+                path
+            );
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,428 @@
+//! Combine several data files into one.
+//!
+//! Mods are usually distributed as complete data files, built on top of a particular base data
+//! file. [`DatFile::merge`] overlays one or more of those mod data files onto the base, entry by
+//! entry: every sound, sprite, terrain, effect, task list, civilization, unit type and tech that a
+//! mod changed compared to the base is copied into the result. Entries that a mod added, beyond
+//! the end of a base table, are appended.
+//!
+//! When two mods change the same entry in different ways, that is a [`MergeConflict`]. The
+//! [`MergePolicy`] decides whether a conflict aborts the merge or which mod wins.
+//!
+//! Tables without IDs, such as the terrain restrictions, colour tables and random maps, are taken
+//! from the base data file. The tech tree is treated as a single entry.
+
+use crate::civ::CivilizationID;
+use crate::sound::SoundID;
+use crate::sprite::SpriteID;
+use crate::terrain::TerrainID;
+use crate::unit_type::{UnitType, UnitTypeID};
+use crate::{DatFile, FileVersion};
+use genie_support::{TechID, TryConvertExt};
+use std::io::Result;
+
+/// What to do when several mods change the same entry in different ways.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Fail with [`MergeError::Conflict`].
+    #[default]
+    Strict,
+    /// Use the change from the first mod that made one.
+    FirstWins,
+    /// Use the change from the last mod that made one, as if the mods were applied in order.
+    LastWins,
+}
+
+/// An entry in a data file that mods can change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeEntry {
+    /// A sound.
+    Sound(SoundID),
+    /// A sprite.
+    Sprite(SpriteID),
+    /// A terrain.
+    Terrain(TerrainID),
+    /// A terrain border, by its index.
+    TerrainBorder(usize),
+    /// A tech effect.
+    Effect(u16),
+    /// The task list at an index.
+    TaskList(usize),
+    /// The data of a civilization itself, such as its name and starting resources.
+    Civilization(CivilizationID),
+    /// A unit type of a civilization.
+    UnitType {
+        civilization: CivilizationID,
+        unit: UnitTypeID,
+    },
+    /// A tech.
+    Tech(TechID),
+    /// The tech tree.
+    TechTree,
+}
+
+/// An entry that several mods changed in different ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// The entry.
+    pub entry: MergeEntry,
+    /// The indices of the mods that changed this entry, in order.
+    pub overlays: Vec<usize>,
+}
+
+/// An error that occurred while merging data files.
+#[derive(Debug, thiserror::Error)]
+pub enum MergeError {
+    /// A mod was made for a different game version than the base data file.
+    #[error("mod {overlay} has file version {found:?}, expected {expected:?}")]
+    VersionMismatch {
+        overlay: usize,
+        expected: FileVersion,
+        found: FileVersion,
+    },
+    /// Several mods changed the same entry in different ways.
+    #[error("mods {:?} make conflicting changes to {:?}", .0.overlays, .0.entry)]
+    Conflict(MergeConflict),
+    /// An entry could not be serialized for comparison.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The result of merging data files.
+#[derive(Debug, Clone)]
+pub struct Merged {
+    /// The combined data file.
+    pub dat: DatFile,
+    /// The conflicts that were resolved using the merge policy.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+struct Merger<'a> {
+    base: &'a DatFile,
+    overlays: &'a [&'a DatFile],
+    policy: MergePolicy,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl<'a> Merger<'a> {
+    /// Find the value an entry should have in the merged data file. Returns `None` if no mod
+    /// changed the entry.
+    ///
+    /// Entries are compared by their serialized form.
+    fn merge_entry<T: 'a>(
+        &mut self,
+        entry: MergeEntry,
+        get: impl Fn(&'a DatFile) -> Option<&'a T>,
+        encode: impl Fn(&T) -> Result<Vec<u8>>,
+    ) -> std::result::Result<Option<&'a T>, MergeError> {
+        let base = get(self.base).map(&encode).transpose()?;
+        let mut changes = vec![];
+        for (index, overlay) in self.overlays.iter().enumerate() {
+            if let Some(value) = get(overlay) {
+                let encoded = encode(value)?;
+                if base.as_ref() != Some(&encoded) {
+                    changes.push((index, value, encoded));
+                }
+            }
+        }
+
+        let (first, last) = match (changes.first(), changes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(None),
+        };
+        if changes.iter().any(|(_, _, encoded)| *encoded != first.2) {
+            let conflict = MergeConflict {
+                entry,
+                overlays: changes.iter().map(|(index, _, _)| *index).collect(),
+            };
+            if self.policy == MergePolicy::Strict {
+                return Err(MergeError::Conflict(conflict));
+            }
+            self.conflicts.push(conflict);
+        }
+        Ok(Some(match self.policy {
+            MergePolicy::FirstWins => first.1,
+            MergePolicy::Strict | MergePolicy::LastWins => last.1,
+        }))
+    }
+
+    /// Merge a table of entries, indexed by ID, into `table`.
+    fn merge_table<T: Clone + 'a>(
+        &mut self,
+        table: &mut Vec<T>,
+        entry: impl Fn(usize) -> Result<MergeEntry>,
+        get: impl Fn(&'a DatFile) -> &'a [T],
+        encode: impl Fn(&T) -> Result<Vec<u8>>,
+    ) -> std::result::Result<(), MergeError> {
+        let len = self.overlays.iter().map(|dat| get(dat).len()).max();
+        let len = len.unwrap_or(0).max(get(self.base).len());
+        for index in 0..len {
+            let value = self.merge_entry(entry(index)?, |dat| get(dat).get(index), &encode)?;
+            match value {
+                Some(value) if index < table.len() => table[index] = value.clone(),
+                Some(value) => table.push(value.clone()),
+                None => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn encode(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    write(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn encode_option<T>(
+    value: &Option<T>,
+    write: impl FnOnce(&T, &mut Vec<u8>) -> Result<()>,
+) -> Result<Vec<u8>> {
+    match value {
+        Some(value) => encode(|bytes| {
+            bytes.push(1);
+            write(value, bytes)
+        }),
+        None => Ok(vec![0]),
+    }
+}
+
+fn to_id<T: From<u16>>(index: usize) -> Result<T> {
+    index.try_convert::<u16>().map(T::from)
+}
+
+impl DatFile {
+    /// Overlay mod data files onto this data file.
+    ///
+    /// Every entry that a mod changed compared to this data file is copied into the result.
+    /// Mods must have the same file version as this data file. See the [`merge`](crate::merge)
+    /// module for details.
+    pub fn merge(
+        &self,
+        overlays: &[&DatFile],
+        policy: MergePolicy,
+    ) -> std::result::Result<Merged, MergeError> {
+        for (overlay, dat) in overlays.iter().enumerate() {
+            if dat.file_version != self.file_version {
+                return Err(MergeError::VersionMismatch {
+                    overlay,
+                    expected: self.file_version,
+                    found: dat.file_version,
+                });
+            }
+        }
+
+        let version = self.file_version;
+        let game_version = self.game_version;
+        let mut merger = Merger {
+            base: self,
+            overlays,
+            policy,
+            conflicts: vec![],
+        };
+        let mut dat = self.clone();
+
+        merger.merge_table(
+            &mut dat.sounds,
+            |index| to_id(index).map(MergeEntry::Sound),
+            |dat| &dat.sounds,
+            |sound| encode(|bytes| sound.write_to(bytes, version)),
+        )?;
+        merger.merge_table(
+            &mut dat.sprites,
+            |index| to_id(index).map(MergeEntry::Sprite),
+            |dat| &dat.sprites,
+            |sprite| encode_option(sprite, |sprite, bytes| sprite.write_to(bytes, version)),
+        )?;
+        merger.merge_table(
+            &mut dat.terrains,
+            |index| to_id(index).map(MergeEntry::Terrain),
+            |dat| &dat.terrains,
+            |terrain| {
+                let num_terrains = terrain.borders.len().try_convert()?;
+                encode(|bytes| terrain.write_to(bytes, version, num_terrains))
+            },
+        )?;
+        merger.merge_table(
+            &mut dat.terrain_borders,
+            |index| Ok(MergeEntry::TerrainBorder(index)),
+            |dat| &dat.terrain_borders,
+            |border| encode(|bytes| border.write_to(bytes, version)),
+        )?;
+        merger.merge_table(
+            &mut dat.effects,
+            |index| index.try_convert().map(MergeEntry::Effect),
+            |dat| &dat.effects,
+            |effect| encode(|bytes| effect.write_to(bytes)),
+        )?;
+        merger.merge_table(
+            &mut dat.task_lists,
+            |index| Ok(MergeEntry::TaskList(index)),
+            |dat| &dat.task_lists,
+            |list| encode_option(list, |list, bytes| list.write_to(bytes)),
+        )?;
+        merger.merge_table(
+            &mut dat.techs,
+            |index| to_id(index).map(MergeEntry::Tech),
+            |dat| &dat.techs,
+            |tech| encode(|bytes| tech.write_to(bytes, version)),
+        )?;
+
+        let encode_unit_type = |unit_type: &Option<UnitType>| {
+            encode_option(unit_type, |unit_type, bytes| {
+                if game_version.is_swgb() {
+                    unit_type.write_to_swgb(bytes, game_version.data_version())
+                } else {
+                    unit_type.write_to(bytes, game_version.data_version())
+                }
+            })
+        };
+        let num_civs = overlays.iter().map(|dat| dat.civilizations.len()).max();
+        let num_civs = num_civs.unwrap_or(0).max(self.civilizations.len());
+        for index in 0..num_civs {
+            let civilization = CivilizationID::from(index.try_convert::<u8>()?);
+            let value = merger.merge_entry(
+                MergeEntry::Civilization(civilization),
+                |dat| dat.civilizations.get(index),
+                |civ| encode(|bytes| civ.write_header_to(bytes, game_version)),
+            )?;
+            match value {
+                Some(civ) if index < dat.civilizations.len() => {
+                    dat.civilizations[index].copy_header_from(civ);
+                }
+                Some(civ) => {
+                    let mut civ = civ.clone();
+                    civ.unit_types_mut().clear();
+                    dat.civilizations.push(civ);
+                }
+                None => (),
+            }
+
+            merger.merge_table(
+                dat.civilizations[index].unit_types_mut(),
+                |unit| {
+                    Ok(MergeEntry::UnitType {
+                        civilization,
+                        unit: to_id(unit)?,
+                    })
+                },
+                |dat| {
+                    dat.civilizations
+                        .get(index)
+                        .map_or(&[][..], |civ| civ.unit_types())
+                },
+                encode_unit_type,
+            )?;
+        }
+
+        let tech_tree = merger.merge_entry(
+            MergeEntry::TechTree,
+            |dat| Some(&dat.tech_tree),
+            |tree| encode(|bytes| tree.write_to(bytes)),
+        )?;
+        if let Some(tech_tree) = tech_tree {
+            dat.tech_tree = tech_tree.clone();
+        }
+
+        Ok(Merged {
+            dat,
+            conflicts: merger.conflicts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn read_aoc() -> anyhow::Result<DatFile> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        Ok(DatFile::read_from(&mut f)?)
+    }
+
+    fn set_archer_hp(dat: &mut DatFile, hp: u16) {
+        for civ in &mut dat.civilizations {
+            if let Some(archer) = civ.unit_types_mut()[4].as_mut() {
+                archer.static_.hp = hp;
+            }
+        }
+    }
+
+    #[test]
+    fn merge_mods() -> anyhow::Result<()> {
+        let base = read_aoc()?;
+        let mut archers = base.clone();
+        set_archer_hp(&mut archers, 40);
+        let mut loom = base.clone();
+        loom.techs[22].set_research_time(5);
+        let mut new_sound = base.clone();
+        let mut sound = new_sound.sounds[0].clone();
+        sound.id = SoundID::from(new_sound.sounds.len().try_convert::<u16>()?);
+        new_sound.sounds.push(sound);
+
+        let merged = base.merge(&[&archers, &loom, &new_sound], MergePolicy::Strict)?;
+        assert!(merged.conflicts.is_empty());
+        let dat = merged.dat;
+        assert_eq!(
+            dat.civilizations[1].get_unit_type(4).unwrap().static_.hp,
+            40
+        );
+        assert_eq!(dat.get_tech(22).unwrap().research_time(), 5);
+        assert_eq!(dat.sounds.len(), base.sounds.len() + 1);
+        // The civilization header and the rest of the file are unchanged.
+        assert_eq!(dat.civilizations[1].name(), "British");
+        assert_eq!(
+            dat.get_tech(23).unwrap().research_time(),
+            base.get_tech(23).unwrap().research_time()
+        );
+
+        // Merging with nothing gives back the base data file.
+        let mut expected = vec![];
+        base.write_to(&mut expected)?;
+        let mut actual = vec![];
+        base.merge(&[], MergePolicy::Strict)?
+            .dat
+            .write_to(&mut actual)?;
+        assert_eq!(actual, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn merge_conflicts() -> anyhow::Result<()> {
+        let base = read_aoc()?;
+        let mut first = base.clone();
+        set_archer_hp(&mut first, 40);
+        let mut second = base.clone();
+        set_archer_hp(&mut second, 50);
+        let same = first.clone();
+
+        let archer_hp = |dat: &DatFile| dat.civilizations[1].get_unit_type(4).unwrap().static_.hp;
+
+        // Identical changes do not conflict.
+        let merged = base.merge(&[&first, &same], MergePolicy::Strict)?;
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(archer_hp(&merged.dat), 40);
+
+        match base.merge(&[&first, &second], MergePolicy::Strict) {
+            Err(MergeError::Conflict(conflict)) => {
+                assert_eq!(
+                    conflict.entry,
+                    MergeEntry::UnitType {
+                        civilization: CivilizationID::from(0),
+                        unit: UnitTypeID::from(4),
+                    }
+                );
+                assert_eq!(conflict.overlays, vec![0, 1]);
+            }
+            other => panic!("expected a conflict, got {:?}", other.map(|_| ())),
+        }
+
+        let merged = base.merge(&[&first, &second], MergePolicy::FirstWins)?;
+        assert_eq!(merged.conflicts.len(), base.civilizations.len());
+        assert_eq!(archer_hp(&merged.dat), 40);
+        let merged = base.merge(&[&first, &second], MergePolicy::LastWins)?;
+        assert_eq!(archer_hp(&merged.dat), 50);
+        Ok(())
+    }
+}
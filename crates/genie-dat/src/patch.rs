@@ -0,0 +1,558 @@
+//! Text-based balance changes for data files.
+//!
+//! A [`Patch`] is a list of attribute changes, such as "add 1 pierce attack to unit 4" or "set
+//! the wood cost of tech 22 to 150". Patches are independent of the data file they are applied
+//! to, so the same balance mod can be layered on top of any base data file.
+//!
+//! With the `serde` feature, patches can be read from any serde format, such as JSON or TOML. In
+//! JSON, the changes above look like this:
+//!
+//! ```json
+//! {
+//!   "changes": [
+//!     { "unit": 4, "attribute": "attack", "class": 3, "add": 1 },
+//!     { "tech": 22, "attribute": "cost", "resource": "wood", "set": 150 }
+//!   ]
+//! }
+//! ```
+
+use crate::civ::CivilizationID;
+use crate::effect::{self, Operation};
+use crate::unit_type::{ArmorClass, UnitType, UnitTypeID};
+use crate::DatFile;
+use genie_support::{AttributeID, TechID};
+
+/// A set of attribute changes to apply to a data file.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Patch {
+    /// The changes, in the order they are applied.
+    pub changes: Vec<PatchChange>,
+}
+
+/// A single attribute change.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchChange {
+    /// The unit type or tech to change.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub target: PatchTarget,
+    /// The civilization whose unit type should be changed. Unit type changes apply to all
+    /// civilizations if this is `None`. Techs are shared by all civilizations, so this must be
+    /// `None` for tech changes.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub civilization: Option<CivilizationID>,
+    /// The attribute to change.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub attribute: PatchAttribute,
+    /// How to change the attribute.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub operation: PatchOperation,
+}
+
+/// The data table entry changed by a patch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PatchTarget {
+    Unit(UnitTypeID),
+    Tech(TechID),
+}
+
+/// The resources that units and techs can cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CostResource {
+    Food,
+    Wood,
+    Stone,
+    Gold,
+}
+
+impl From<CostResource> for AttributeID {
+    fn from(resource: CostResource) -> Self {
+        match resource {
+            CostResource::Food => AttributeID::FOOD,
+            CostResource::Wood => AttributeID::WOOD,
+            CostResource::Stone => AttributeID::STONE,
+            CostResource::Gold => AttributeID::GOLD,
+        }
+    }
+}
+
+/// An attribute that can be changed by a patch.
+///
+/// Attributes are named after the values shown in the game. [`PatchAttribute::Cost`] and
+/// [`PatchAttribute::ResearchTime`] apply to techs, `Cost` and all other attributes apply to unit
+/// types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "attribute", rename_all = "snake_case"))]
+pub enum PatchAttribute {
+    Hp,
+    LineOfSight,
+    GarrisonCapacity,
+    Speed,
+    /// The attack value for an attack class, such as 3 for pierce attack.
+    Attack {
+        class: u16,
+    },
+    /// The armor value for an armor class, such as 4 for melee armor.
+    Armor {
+        class: u16,
+    },
+    ReloadTime,
+    Accuracy,
+    Range,
+    MinRange,
+    BlastRadius,
+    WorkRate,
+    SearchRadius,
+    TrainTime,
+    /// The cost in one resource. Only existing costs can be changed.
+    Cost {
+        resource: CostResource,
+    },
+    ResearchTime,
+}
+
+impl PatchAttribute {
+    /// Get the effect attribute ID for a unit type attribute.
+    fn effect_attribute(self) -> Option<i16> {
+        use PatchAttribute::*;
+        Some(match self {
+            Hp => 0,
+            LineOfSight => 1,
+            GarrisonCapacity => 2,
+            Speed => 5,
+            Armor { .. } => 8,
+            Attack { .. } => 9,
+            ReloadTime => 10,
+            Accuracy => 11,
+            Range => 12,
+            WorkRate => 13,
+            MinRange => 20,
+            BlastRadius => 22,
+            SearchRadius => 23,
+            TrainTime => 101,
+            Cost {
+                resource: CostResource::Food,
+            } => 103,
+            Cost {
+                resource: CostResource::Wood,
+            } => 104,
+            Cost {
+                resource: CostResource::Gold,
+            } => 105,
+            Cost {
+                resource: CostResource::Stone,
+            } => 106,
+            ResearchTime => return None,
+        })
+    }
+
+    /// Check if a unit type has this attribute.
+    fn exists_on(self, unit: &UnitType) -> bool {
+        use PatchAttribute::*;
+        match self {
+            Hp | LineOfSight | GarrisonCapacity => true,
+            Speed => unit.animated.is_some(),
+            Attack { .. }
+            | Armor { .. }
+            | ReloadTime
+            | Accuracy
+            | Range
+            | MinRange
+            | BlastRadius => unit.base_combat.is_some(),
+            WorkRate | SearchRadius => unit.action.is_some(),
+            TrainTime => unit.combat.is_some(),
+            Cost { resource } => unit.combat.as_ref().is_some_and(|combat| {
+                combat
+                    .costs
+                    .iter()
+                    .any(|cost| cost.attribute_type == resource.into())
+            }),
+            ResearchTime => false,
+        }
+    }
+}
+
+/// How a patch changes an attribute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PatchOperation {
+    /// Replace the value.
+    Set(f32),
+    /// Add to the value. Use a negative amount to subtract.
+    Add(f32),
+    /// Multiply the value.
+    Multiply(f32),
+}
+
+impl PatchOperation {
+    fn split(self) -> (Operation, f32) {
+        match self {
+            Self::Set(amount) => (Operation::Set, amount),
+            Self::Add(amount) => (Operation::Add, amount),
+            Self::Multiply(amount) => (Operation::Multiply, amount),
+        }
+    }
+}
+
+/// An error that occurred while applying a patch. No changes are made if a patch fails.
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+pub enum PatchError {
+    #[error("change {index}: unit type {} does not exist", u16::from(*.id))]
+    UnknownUnitType { index: usize, id: UnitTypeID },
+    #[error("change {index}: tech {} does not exist", u16::from(*.id))]
+    UnknownTech { index: usize, id: TechID },
+    #[error("change {index}: civilization {} does not exist", u8::from(*.id))]
+    UnknownCivilization { index: usize, id: CivilizationID },
+    #[error("change {index}: {target:?} does not have the {attribute:?} attribute")]
+    MissingAttribute {
+        index: usize,
+        target: PatchTarget,
+        attribute: PatchAttribute,
+    },
+    #[error("change {index}: techs are not specific to a civilization")]
+    CivilizationForTech { index: usize },
+}
+
+impl Patch {
+    /// Create an empty patch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a change to a unit type for all civilizations.
+    pub fn unit(
+        mut self,
+        id: impl Into<UnitTypeID>,
+        attribute: PatchAttribute,
+        operation: PatchOperation,
+    ) -> Self {
+        self.changes.push(PatchChange {
+            target: PatchTarget::Unit(id.into()),
+            civilization: None,
+            attribute,
+            operation,
+        });
+        self
+    }
+
+    /// Add a change to a tech.
+    pub fn tech(
+        mut self,
+        id: impl Into<TechID>,
+        attribute: PatchAttribute,
+        operation: PatchOperation,
+    ) -> Self {
+        self.changes.push(PatchChange {
+            target: PatchTarget::Tech(id.into()),
+            civilization: None,
+            attribute,
+            operation,
+        });
+        self
+    }
+
+    /// Read a patch from JSON.
+    #[cfg(feature = "json")]
+    pub fn from_json(input: impl std::io::Read) -> std::io::Result<Self> {
+        Ok(serde_json::from_reader(input)?)
+    }
+}
+
+impl DatFile {
+    /// Apply the changes in a patch to this data file.
+    ///
+    /// All changes are checked before any of them are applied, so the data file is not modified
+    /// if the patch contains an invalid change.
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        for (index, change) in patch.changes.iter().enumerate() {
+            self.check_change(index, change)?;
+        }
+        for change in &patch.changes {
+            self.apply_change(change);
+        }
+        Ok(())
+    }
+
+    fn check_change(&self, index: usize, change: &PatchChange) -> Result<(), PatchError> {
+        let missing_attribute = PatchError::MissingAttribute {
+            index,
+            target: change.target,
+            attribute: change.attribute,
+        };
+        match change.target {
+            PatchTarget::Unit(id) => {
+                let civs = match change.civilization {
+                    Some(civ) => vec![self
+                        .get_civilization(civ)
+                        .ok_or(PatchError::UnknownCivilization { index, id: civ })?],
+                    None => self.civilizations.iter().collect(),
+                };
+                let mut units = civs
+                    .into_iter()
+                    .filter_map(|civ| civ.get_unit_type(id))
+                    .peekable();
+                if units.peek().is_none() {
+                    return Err(PatchError::UnknownUnitType { index, id });
+                }
+                if !units.all(|unit| change.attribute.exists_on(unit)) {
+                    return Err(missing_attribute);
+                }
+            }
+            PatchTarget::Tech(id) => {
+                if change.civilization.is_some() {
+                    return Err(PatchError::CivilizationForTech { index });
+                }
+                let tech = self
+                    .get_tech(id)
+                    .ok_or(PatchError::UnknownTech { index, id })?;
+                let exists = match change.attribute {
+                    PatchAttribute::ResearchTime => true,
+                    PatchAttribute::Cost { resource } => tech
+                        .costs()
+                        .iter()
+                        .any(|cost| cost.effect_type == resource.into()),
+                    _ => false,
+                };
+                if !exists {
+                    return Err(missing_attribute);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_change(&mut self, change: &PatchChange) {
+        let (op, amount) = change.operation.split();
+        match change.target {
+            PatchTarget::Unit(id) => {
+                let civs = self
+                    .civilizations
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(index, _)| {
+                        change
+                            .civilization
+                            .is_none_or(|civ| usize::from(civ) == *index)
+                    });
+                for (_, civ) in civs {
+                    let unit = civ
+                        .unit_types_mut()
+                        .get_mut(usize::from(id))
+                        .and_then(Option::as_mut);
+                    if let Some(unit) = unit {
+                        apply_unit_change(unit, change.attribute, op, amount);
+                    }
+                }
+            }
+            PatchTarget::Tech(id) => {
+                let tech = match self.techs.get_mut(usize::from(id)) {
+                    Some(tech) => tech,
+                    None => return,
+                };
+                match change.attribute {
+                    PatchAttribute::ResearchTime => {
+                        let time = op.apply_u16(tech.research_time(), amount);
+                        tech.set_research_time(time);
+                    }
+                    PatchAttribute::Cost { resource } => {
+                        for cost in tech.costs_mut() {
+                            if cost.effect_type == resource.into() {
+                                cost.amount = op.apply_u16(cost.amount, amount);
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+fn apply_unit_change(unit: &mut UnitType, attribute: PatchAttribute, op: Operation, amount: f32) {
+    // Effect commands pack attack and armor values into a single byte, which does not allow
+    // negative or large amounts, so they are changed directly.
+    match attribute {
+        PatchAttribute::Attack { class } => {
+            if let Some(combat) = &mut unit.base_combat {
+                effect::modify_weapon(&mut combat.weapons, ArmorClass::from(class), op, amount);
+            }
+        }
+        PatchAttribute::Armor { class } => {
+            if let Some(combat) = &mut unit.base_combat {
+                effect::modify_weapon(&mut combat.armors, ArmorClass::from(class), op, amount);
+            }
+        }
+        _ => {
+            if let Some(attribute) = attribute.effect_attribute() {
+                effect::modify_unit(unit, attribute, op, amount);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn archer_attack(dat: &DatFile, civ: u8) -> i16 {
+        dat.civilizations[usize::from(civ)]
+            .get_unit_type(4)
+            .unwrap()
+            .base_combat
+            .as_ref()
+            .unwrap()
+            .attack(ArmorClass::from(3))
+            .unwrap()
+    }
+
+    #[test]
+    fn apply_unit_and_tech_changes() -> anyhow::Result<()> {
+        let mut dat = DatFile::read_from(File::open("fixtures/aoc1.0c.dat")?)?;
+        let attack = archer_attack(&dat, 1);
+        let loom_gold = dat.get_tech(22).unwrap().costs()[0].amount;
+
+        let patch = Patch::new()
+            .unit(
+                4,
+                PatchAttribute::Attack { class: 3 },
+                PatchOperation::Add(1.0),
+            )
+            .tech(
+                22,
+                PatchAttribute::Cost {
+                    resource: CostResource::Gold,
+                },
+                PatchOperation::Set(150.0),
+            );
+        dat.apply_patch(&patch)?;
+
+        assert_eq!(archer_attack(&dat, 1), attack + 1);
+        assert_eq!(archer_attack(&dat, 2), attack + 1);
+        assert_ne!(loom_gold, 150);
+        assert_eq!(dat.get_tech(22).unwrap().costs()[0].amount, 150);
+        Ok(())
+    }
+
+    #[test]
+    fn civilization_specific_change() -> anyhow::Result<()> {
+        let mut dat = DatFile::read_from(File::open("fixtures/aoc1.0c.dat")?)?;
+        let hp = dat.civilizations[2].get_unit_type(4).unwrap().static_.hp;
+        let mut patch = Patch::new().unit(4, PatchAttribute::Hp, PatchOperation::Multiply(2.0));
+        patch.changes[0].civilization = Some(1.into());
+        dat.apply_patch(&patch)?;
+
+        assert_eq!(
+            dat.civilizations[1].get_unit_type(4).unwrap().static_.hp,
+            hp * 2
+        );
+        assert_eq!(
+            dat.civilizations[2].get_unit_type(4).unwrap().static_.hp,
+            hp
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_patch_changes_nothing() -> anyhow::Result<()> {
+        let mut dat = DatFile::read_from(File::open("fixtures/aoc1.0c.dat")?)?;
+        let hp = dat.civilizations[1].get_unit_type(4).unwrap().static_.hp;
+        let patch = Patch::new()
+            .unit(4, PatchAttribute::Hp, PatchOperation::Set(100.0))
+            .tech(22, PatchAttribute::Speed, PatchOperation::Set(2.0));
+        assert_eq!(
+            dat.apply_patch(&patch),
+            Err(PatchError::MissingAttribute {
+                index: 1,
+                target: PatchTarget::Tech(22.into()),
+                attribute: PatchAttribute::Speed,
+            })
+        );
+        assert_eq!(
+            dat.civilizations[1].get_unit_type(4).unwrap().static_.hp,
+            hp
+        );
+
+        let patch = Patch::new().unit(60000, PatchAttribute::Hp, PatchOperation::Set(1.0));
+        assert!(matches!(
+            dat.apply_patch(&patch),
+            Err(PatchError::UnknownUnitType { index: 0, .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn read_json_patch() -> anyhow::Result<()> {
+        let json = r#"{
+            "changes": [
+                { "unit": 4, "attribute": "attack", "class": 3, "add": 1 },
+                { "unit": 4, "civilization": 1, "attribute": "hp", "set": 40 },
+                { "tech": 22, "attribute": "cost", "resource": "wood", "set": 150 }
+            ]
+        }"#;
+        let patch = Patch::from_json(json.as_bytes())?;
+        assert_eq!(
+            patch.changes[0],
+            PatchChange {
+                target: PatchTarget::Unit(4.into()),
+                civilization: None,
+                attribute: PatchAttribute::Attack { class: 3 },
+                operation: PatchOperation::Add(1.0),
+            }
+        );
+        assert_eq!(patch.changes[1].civilization, Some(1.into()));
+        assert_eq!(
+            patch.changes[2].attribute,
+            PatchAttribute::Cost {
+                resource: CostResource::Wood
+            }
+        );
+        assert_eq!(patch.changes[2].operation, PatchOperation::Set(150.0));
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn read_toml_patch() -> anyhow::Result<()> {
+        let patch: Patch = toml::from_str(
+            r#"
+            [[changes]]
+            unit = 4
+            attribute = "armor"
+            class = 4
+            add = -1
+
+            [[changes]]
+            tech = 22
+            attribute = "research_time"
+            multiply = 0.5
+            "#,
+        )?;
+        assert_eq!(
+            patch,
+            Patch::new()
+                .unit(
+                    4,
+                    PatchAttribute::Armor { class: 4 },
+                    PatchOperation::Add(-1.0)
+                )
+                .tech(
+                    22,
+                    PatchAttribute::ResearchTime,
+                    PatchOperation::Multiply(0.5)
+                )
+        );
+        Ok(())
+    }
+}
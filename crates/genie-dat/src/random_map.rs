@@ -1,27 +1,56 @@
-//! Types related to the random map data from Age of Empires 1.
-
-#![allow(missing_docs)]
+//! Types related to the random map generation data embedded in data files.
+//!
+//! This data was used by the Age of Empires 1 map generator. Later games still ship it, but use
+//! random map scripts instead.
 
 use crate::unit_type::UnitTypeID;
+use crate::unknown::RawBytes;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use std::convert::TryInto;
+use genie_support::TryConvertExt;
 use std::io::{Read, Result, Write};
 
-#[derive(Debug, Default, Clone)]
+/// Size of the four pointers to the lands, terrains, objects and elevations of a random map.
+const POINTERS_LEN: usize = 4 * 4;
+/// Size of the padding in a random map land.
+const LAND_PADDING_LEN: usize = 7;
+
+/// A random map type.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomMapInfo {
     id: i32,
+    /// Size of the map border in tiles, for the south-west, north-west, north-east and
+    /// south-east edges.
     pub borders: (i32, i32, i32, i32),
     pub border_fade: i32,
     pub water_border: i32,
+    /// The terrain that fills the map before lands are placed.
     pub base_terrain: i32,
+    /// Percentage of the map that is covered by lands.
     pub land_percent: i32,
+    unknown_id: i32,
+    /// Lands to place on the map.
     pub lands: Vec<RandomMapLand>,
+    /// Terrain patches to place on the map.
     pub terrains: Vec<RandomMapTerrain>,
+    /// Objects to place on the map.
     pub objects: Vec<RandomMapObject>,
+    /// Hills to place on the map.
     pub elevations: Vec<RandomMapElevation>,
+    /// Pointers to the lands, terrains, objects and elevations in the random map header.
+    pub unknown_pointers: RawBytes,
+    /// Pointers to the lands, terrains, objects and elevations in the random map commands.
+    pub unknown_command_pointers: RawBytes,
 }
 
 impl RandomMapInfo {
+    /// Get the ID of this random map type.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Read the header of a random map from an input stream. The lands, terrains, objects and
+    /// elevations are read separately by [`RandomMapInfo::finish`].
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let mut info = RandomMapInfo {
             id: input.read_i32::<LE>()?,
@@ -38,51 +67,61 @@ impl RandomMapInfo {
             ..Default::default()
         };
 
-        let _some_id = input.read_i32::<LE>()?;
+        info.unknown_id = input.read_i32::<LE>()?;
+        let mut pointers = [0; POINTERS_LEN];
         let num_lands = input.read_u32::<LE>()?;
-        let _pointer = input.read_u32::<LE>()?;
+        input.read_exact(&mut pointers[0..4])?;
         let num_terrains = input.read_u32::<LE>()?;
-        let _pointer = input.read_u32::<LE>()?;
+        input.read_exact(&mut pointers[4..8])?;
         let num_objects = input.read_u32::<LE>()?;
-        let _pointer = input.read_u32::<LE>()?;
+        input.read_exact(&mut pointers[8..12])?;
         let num_elevations = input.read_u32::<LE>()?;
-        let _pointer = input.read_u32::<LE>()?;
+        input.read_exact(&mut pointers[12..16])?;
+        info.unknown_pointers = RawBytes::new(&pointers);
 
-        info.lands = vec![RandomMapLand::default(); num_lands.try_into().unwrap()];
-        info.terrains = vec![RandomMapTerrain::default(); num_terrains.try_into().unwrap()];
-        info.objects = vec![RandomMapObject::default(); num_objects.try_into().unwrap()];
-        info.elevations = vec![RandomMapElevation::default(); num_elevations.try_into().unwrap()];
+        info.lands = vec![RandomMapLand::default(); num_lands.try_convert()?];
+        info.terrains = vec![RandomMapTerrain::default(); num_terrains.try_convert()?];
+        info.objects = vec![RandomMapObject::default(); num_objects.try_convert()?];
+        info.elevations = vec![RandomMapElevation::default(); num_elevations.try_convert()?];
 
         Ok(info)
     }
 
+    /// Read the lands, terrains, objects and elevations of a random map from an input stream.
     pub fn finish<R: Read>(&mut self, input: &mut R) -> Result<()> {
+        let mut pointers = [0; POINTERS_LEN];
         // duplicate data
-        std::io::copy(&mut input.by_ref().take(44), &mut std::io::sink())?;
+        std::io::copy(&mut input.by_ref().take(40), &mut std::io::sink())?;
+        input.read_exact(&mut pointers[0..4])?;
         for land in self.lands.iter_mut() {
             *land = RandomMapLand::read_from(input)?;
         }
 
         // duplicate data
-        std::io::copy(&mut input.by_ref().take(8), &mut std::io::sink())?;
+        std::io::copy(&mut input.by_ref().take(4), &mut std::io::sink())?;
+        input.read_exact(&mut pointers[4..8])?;
         for terrain in self.terrains.iter_mut() {
             *terrain = RandomMapTerrain::read_from(input)?;
         }
 
         // duplicate data
-        std::io::copy(&mut input.by_ref().take(8), &mut std::io::sink())?;
+        std::io::copy(&mut input.by_ref().take(4), &mut std::io::sink())?;
+        input.read_exact(&mut pointers[8..12])?;
         for object in self.objects.iter_mut() {
             *object = RandomMapObject::read_from(input)?;
         }
 
         // duplicate data
-        std::io::copy(&mut input.by_ref().take(8), &mut std::io::sink())?;
+        std::io::copy(&mut input.by_ref().take(4), &mut std::io::sink())?;
+        input.read_exact(&mut pointers[12..16])?;
         for elevation in self.elevations.iter_mut() {
             *elevation = RandomMapElevation::read_from(input)?;
         }
+        self.unknown_command_pointers = RawBytes::new(&pointers);
         Ok(())
     }
 
+    /// Write the header of a random map to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_i32::<LE>(self.id)?;
         output.write_i32::<LE>(self.borders.0)?;
@@ -94,19 +133,24 @@ impl RandomMapInfo {
         output.write_i32::<LE>(self.base_terrain)?;
         output.write_i32::<LE>(self.land_percent)?;
 
-        output.write_i32::<LE>(0)?; // some id
-        output.write_u32::<LE>(self.lands.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
-        output.write_u32::<LE>(self.terrains.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
-        output.write_u32::<LE>(self.objects.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
-        output.write_u32::<LE>(self.elevations.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
+        output.write_i32::<LE>(self.unknown_id)?;
+        output.write_u32::<LE>(self.lands.len().try_convert()?)?;
+        self.unknown_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 0..4)?;
+        output.write_u32::<LE>(self.terrains.len().try_convert()?)?;
+        self.unknown_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 4..8)?;
+        output.write_u32::<LE>(self.objects.len().try_convert()?)?;
+        self.unknown_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 8..12)?;
+        output.write_u32::<LE>(self.elevations.len().try_convert()?)?;
+        self.unknown_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 12..16)?;
 
         Ok(())
     }
 
+    /// Write the lands, terrains, objects and elevations of a random map to an output stream.
     pub fn write_commands_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_i32::<LE>(self.borders.0)?;
         output.write_i32::<LE>(self.borders.1)?;
@@ -116,25 +160,29 @@ impl RandomMapInfo {
         output.write_i32::<LE>(self.water_border)?;
         output.write_i32::<LE>(self.base_terrain)?;
         output.write_i32::<LE>(self.land_percent)?;
-        output.write_u32::<LE>(0)?; // some id
+        output.write_i32::<LE>(self.unknown_id)?;
 
-        output.write_u32::<LE>(self.lands.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
+        output.write_u32::<LE>(self.lands.len().try_convert()?)?;
+        self.unknown_command_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 0..4)?;
         for land in &self.lands {
             land.write_to(output)?;
         }
-        output.write_u32::<LE>(self.terrains.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
+        output.write_u32::<LE>(self.terrains.len().try_convert()?)?;
+        self.unknown_command_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 4..8)?;
         for terrain in &self.terrains {
             terrain.write_to(output)?;
         }
-        output.write_u32::<LE>(self.objects.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
+        output.write_u32::<LE>(self.objects.len().try_convert()?)?;
+        self.unknown_command_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 8..12)?;
         for object in &self.objects {
             object.write_to(output)?;
         }
-        output.write_u32::<LE>(self.elevations.len().try_into().unwrap())?;
-        output.write_u32::<LE>(0)?; // pointer
+        output.write_u32::<LE>(self.elevations.len().try_convert()?)?;
+        self.unknown_command_pointers
+            .write_part_to(&mut *output, POINTERS_LEN, 12..16)?;
         for elevation in &self.elevations {
             elevation.write_to(output)?;
         }
@@ -142,7 +190,9 @@ impl RandomMapInfo {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// A land placed by the random map generator, such as a player's starting area.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomMapLand {
     pub id: i32,
     pub terrain_type: u8,
@@ -157,6 +207,8 @@ pub struct RandomMapLand {
     pub radius: i32,
     pub fade: i32,
     pub clumpiness_factor: i32,
+    /// Padding between the fields.
+    pub unknown_padding: RawBytes,
 }
 
 impl RandomMapLand {
@@ -166,39 +218,42 @@ impl RandomMapLand {
             terrain_type: input.read_u8()?,
             ..Default::default()
         };
-        let _padding = input.read_u16::<LE>()?;
-        let _padding = input.read_u8()?;
+        let mut padding = [0; LAND_PADDING_LEN];
+        input.read_exact(&mut padding[0..3])?;
         land.land_avoidance_tiles = input.read_i32::<LE>()?;
         land.base_square_radius = input.read_i32::<LE>()?;
         land.zone = input.read_i8()?;
         land.placement_type = input.read_i8()?;
-        let _padding = input.read_u16::<LE>()?;
+        input.read_exact(&mut padding[3..5])?;
         land.x = input.read_i32::<LE>()?;
         land.y = input.read_i32::<LE>()?;
         land.amount_of_land_used_percent = input.read_i8()?;
         land.by_player_flag = input.read_i8()?;
-        let _padding = input.read_u16::<LE>()?;
+        input.read_exact(&mut padding[5..7])?;
         land.radius = input.read_i32::<LE>()?;
         land.fade = input.read_i32::<LE>()?;
         land.clumpiness_factor = input.read_i32::<LE>()?;
+        land.unknown_padding = RawBytes::new(&padding);
         Ok(land)
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_i32::<LE>(self.id)?;
         output.write_u8(self.terrain_type)?;
-        output.write_u16::<LE>(0)?;
-        output.write_u8(0)?;
+        self.unknown_padding
+            .write_part_to(&mut *output, LAND_PADDING_LEN, 0..3)?;
         output.write_i32::<LE>(self.land_avoidance_tiles)?;
         output.write_i32::<LE>(self.base_square_radius)?;
         output.write_i8(self.zone)?;
         output.write_i8(self.placement_type)?;
-        output.write_u16::<LE>(0)?;
+        self.unknown_padding
+            .write_part_to(&mut *output, LAND_PADDING_LEN, 3..5)?;
         output.write_i32::<LE>(self.x)?;
         output.write_i32::<LE>(self.y)?;
         output.write_i8(self.amount_of_land_used_percent)?;
         output.write_i8(self.by_player_flag)?;
-        output.write_u16::<LE>(0)?;
+        self.unknown_padding
+            .write_part_to(&mut *output, LAND_PADDING_LEN, 5..7)?;
         output.write_i32::<LE>(self.radius)?;
         output.write_i32::<LE>(self.fade)?;
         output.write_i32::<LE>(self.clumpiness_factor)?;
@@ -206,7 +261,9 @@ impl RandomMapLand {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Clumps of terrain placed by the random map generator.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomMapTerrain {
     pub percent: i32,
     pub terrain_type: i32,
@@ -239,7 +296,9 @@ impl RandomMapTerrain {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Groups of objects placed by the random map generator.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomMapObject {
     pub unit_type: UnitTypeID,
     pub terrain_type: i32,
@@ -253,18 +312,20 @@ pub struct RandomMapObject {
     pub land_id: i32,
     pub min_distance_to_players: i32,
     pub max_distance_to_players: i32,
+    /// Padding following `scale_flag`.
+    pub unknown_padding: RawBytes,
 }
 
 impl RandomMapObject {
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let mut object = RandomMapObject {
-            unit_type: input.read_u32::<LE>()?.try_into().unwrap(),
+            unit_type: input.read_u32::<LE>()?.try_convert()?,
             terrain_type: input.read_i32::<LE>()?,
             group_flag: input.read_i8()?,
             scale_flag: input.read_i8()?,
             ..Default::default()
         };
-        let _padding = input.read_u16::<LE>()?;
+        object.unknown_padding = RawBytes::read_from(&mut *input, 2)?;
         object.group_size = input.read_i32::<LE>()?;
         object.group_size_variance = input.read_i32::<LE>()?;
         object.group_count = input.read_i32::<LE>()?;
@@ -277,11 +338,11 @@ impl RandomMapObject {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u32::<LE>(self.unit_type.try_into().unwrap())?;
+        output.write_u32::<LE>(self.unit_type.into())?;
         output.write_i32::<LE>(self.terrain_type)?;
         output.write_i8(self.group_flag)?;
         output.write_i8(self.scale_flag)?;
-        output.write_u16::<LE>(0)?;
+        self.unknown_padding.write_to(&mut *output, 2)?;
         output.write_i32::<LE>(self.group_size)?;
         output.write_i32::<LE>(self.group_size_variance)?;
         output.write_i32::<LE>(self.group_count)?;
@@ -294,7 +355,9 @@ impl RandomMapObject {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Hills placed by the random map generator.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomMapElevation {
     pub percent: i32,
     pub height: i32,
@@ -307,7 +370,7 @@ pub struct RandomMapElevation {
 impl RandomMapElevation {
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         Ok(RandomMapElevation {
-            percent: input.read_u32::<LE>()?.try_into().unwrap(),
+            percent: input.read_i32::<LE>()?,
             height: input.read_i32::<LE>()?,
             clumps: input.read_i32::<LE>()?,
             spacing: input.read_i32::<LE>()?,
@@ -326,3 +389,60 @@ impl RandomMapElevation {
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    RandomMapLand => std::io::Error;
+    RandomMapTerrain => std::io::Error;
+    RandomMapObject => std::io::Error;
+    RandomMapElevation => std::io::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatFile;
+    use genie_test_utils::{assert_round_trip, assert_truncation_errors};
+    use std::fs::File;
+
+    #[test]
+    fn read_random_maps() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        assert_eq!(dat.random_maps.len(), 9);
+
+        let map = dat.get_random_map(4).unwrap();
+        assert_eq!(map.borders, (9, 9, 9, 9));
+        assert_eq!(map.land_percent, 40);
+        assert_eq!(map.lands.len(), 1);
+        assert_eq!(map.terrains.len(), 4);
+        assert_eq!(map.objects.len(), 17);
+        assert!(map.elevations.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn random_map_roundtrip() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+
+        for map in &dat.random_maps {
+            let bytes = assert_round_trip(
+                map,
+                |map, output| {
+                    map.write_to(output)?;
+                    map.write_commands_to(output)
+                },
+                |input| {
+                    let mut read = RandomMapInfo::read_from(input)?;
+                    read.finish(input)?;
+                    Ok(read)
+                },
+            );
+            assert_truncation_errors(&bytes, 64, |input| {
+                let mut read = RandomMapInfo::read_from(input)?;
+                read.finish(input)
+            });
+        }
+        Ok(())
+    }
+}
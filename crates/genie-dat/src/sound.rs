@@ -1,12 +1,18 @@
-use crate::FileVersion;
+use crate::civ::CivilizationID;
+use crate::{DatFile, FileVersion};
 use arrayvec::ArrayString;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{fallible_try_from, fallible_try_into, infallible_try_into};
+use genie_drs::{DRSReader, DRSResource, ResourceType};
+use genie_support::{
+    fallible_try_from, fallible_try_into, infallible_try_into, write_fixed_str_with_encoding,
+    ReadStringsExt, TryConvertExt, DEFAULT_ENCODING,
+};
 use std::convert::TryInto;
-use std::io::{Read, Result, Write};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 
 /// An ID identifying a sound.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SoundID(u16);
 impl From<u16> for SoundID {
     fn from(n: u16) -> Self {
@@ -33,11 +39,15 @@ fallible_try_from!(SoundID, i16);
 fallible_try_from!(SoundID, i32);
 fallible_try_from!(SoundID, u32);
 
+/// Sound file names are 13 bytes, or 27 bytes in Star Wars: Galactic Battlegrounds.
+type SoundFileName = ArrayString<27>;
+
 /// A "conceptual" sound, consisting of one or a group of sound files.
 ///
 /// Items can be picked depending on the player's civilization, and depending on the probabilities
 /// for each file.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sound {
     /// Unique ID for this sound.
     pub id: SoundID,
@@ -50,27 +60,40 @@ pub struct Sound {
 }
 
 /// A single sound file.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SoundItem {
     /// Internal file name for this sound file.
-    pub filename: ArrayString<13>,
+    pub filename: SoundFileName,
     /// DRS file ID for this sound file.
     pub resource_id: i32,
     /// The probability out of 100% that this file will be used for any given playback.
     pub probability: i16,
-    /// Use this file for this civilization ID only.
+    /// Use this file for this civilization ID only. -1 means the file is used for all
+    /// civilizations that do not have their own variation.
     pub civilization: Option<i16>,
     /// File icon set (TODO what does this do?)
     pub icon_set: Option<i16>,
 }
 
 impl SoundItem {
+    /// Get the size of the file name field for a data file version.
+    fn filename_size(version: FileVersion) -> usize {
+        if version.is_swgb() {
+            27
+        } else {
+            13
+        }
+    }
+
     /// Read this sound item from an input stream.
-    pub fn read_from<R: Read>(input: &mut R, _version: FileVersion) -> Result<Self> {
+    pub fn read_from<R: Read>(input: &mut R, version: FileVersion) -> Result<Self> {
         let mut item = SoundItem::default();
-        // TODO actually use this
-        let mut filename = [0u8; 13];
-        input.read_exact(&mut filename)?;
+        let filename = input
+            .read_str(Self::filename_size(version))?
+            .unwrap_or_default();
+        item.filename = SoundFileName::from(&filename)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.simplify()))?;
         item.resource_id = input.read_i32::<LE>()?;
         item.probability = input.read_i16::<LE>()?;
         // AoK only
@@ -81,8 +104,13 @@ impl SoundItem {
     }
 
     /// Write this sound item to an input stream.
-    pub fn write_to<W: Write>(&self, output: &mut W, _version: FileVersion) -> Result<()> {
-        output.write_all(&[0; 13])?;
+    pub fn write_to<W: Write>(&self, output: &mut W, version: FileVersion) -> Result<()> {
+        write_fixed_str_with_encoding(
+            output,
+            &self.filename,
+            Self::filename_size(version),
+            DEFAULT_ENCODING,
+        )?;
         output.write_i32::<LE>(self.resource_id)?;
         output.write_i16::<LE>(self.probability)?;
         // AoK only, must both be set
@@ -92,6 +120,22 @@ impl SoundItem {
         output.write_i16::<LE>(self.icon_set.unwrap())?;
         Ok(())
     }
+
+    /// Get the DRS resource ID of this sound file, if it has one.
+    pub fn drs_id(&self) -> Option<u32> {
+        self.resource_id.try_into().ok()
+    }
+
+    /// Check if this sound file is the variation that is used for a specific civilization.
+    pub fn is_civilization_variation(&self) -> bool {
+        self.civilization.is_some_and(|civ| civ >= 0)
+    }
+
+    /// Find this sound file in a DRS archive.
+    pub fn find_in<'a>(&self, drs: &'a DRSReader) -> Option<&'a DRSResource> {
+        self.drs_id()
+            .and_then(|id| drs.get_resource(ResourceType::from("wav"), id))
+    }
 }
 
 impl Sound {
@@ -117,8 +161,12 @@ impl Sound {
     pub fn write_to<W: Write>(&self, output: &mut W, version: FileVersion) -> Result<()> {
         output.write_u16::<LE>(self.id.into())?;
         output.write_i16::<LE>(self.play_delay)?;
-        output.write_u16::<LE>(self.len().try_into().unwrap())?;
+        output.write_u16::<LE>(self.len().try_convert()?)?;
         output.write_i32::<LE>(self.cache_time)?;
+        if version.is_de2() {
+            let total_probability: i16 = self.items.iter().map(|item| item.probability).sum();
+            output.write_u16::<LE>(total_probability.try_convert()?)?;
+        }
         for item in &self.items {
             item.write_to(output, version)?;
         }
@@ -134,4 +182,123 @@ impl Sound {
     pub fn is_empty(&self) -> bool {
         self.items.is_empty()
     }
+
+    /// Get the sound files that can be played for a civilization.
+    ///
+    /// If the civilization has its own variations of this sound, only those are returned.
+    /// Otherwise, the sound files that are shared by all civilizations are returned.
+    pub fn items_for_civilization(
+        &self,
+        civ: impl Into<CivilizationID>,
+    ) -> impl Iterator<Item = &SoundItem> {
+        let civ = i16::from(u8::from(civ.into()));
+        let has_variation = self.items.iter().any(|item| item.civilization == Some(civ));
+        self.items.iter().filter(move |item| {
+            if has_variation {
+                item.civilization == Some(civ)
+            } else {
+                !item.is_civilization_variation()
+            }
+        })
+    }
+}
+
+impl DatFile {
+    /// Find sound files that are not present in any of the given DRS archives.
+    ///
+    /// Returns the ID of the sound and the sound file for each missing file. Sound files without
+    /// a DRS resource ID are ignored.
+    pub fn missing_sound_files<'a>(
+        &'a self,
+        archives: &[&DRSReader],
+    ) -> Vec<(SoundID, &'a SoundItem)> {
+        self.sounds
+            .iter()
+            .flat_map(|sound| sound.items.iter().map(move |item| (sound.id, item)))
+            .filter(|(_, item)| item.drs_id().is_some())
+            .filter(|(_, item)| archives.iter().all(|drs| item.find_in(drs).is_none()))
+            .collect()
+    }
+}
+
+genie_support::impl_versioned! {
+    SoundItem: FileVersion => std::io::Error;
+    Sound: FileVersion => std::io::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genie_drs::{DRSWriter, InMemoryStrategy};
+    use genie_test_utils::assert_versioned_round_trip;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn civilization_variations() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+        let sound = dat.get_sound(295).unwrap();
+
+        let britons: Vec<_> = sound.items_for_civilization(1).collect();
+        assert_eq!(britons.len(), 1);
+        assert_eq!(britons[0].resource_id, 6222);
+
+        let gaia: Vec<_> = sound.items_for_civilization(0).collect();
+        assert_eq!(gaia.len(), 1);
+        assert_eq!(gaia[0].resource_id, 5338);
+        Ok(())
+    }
+
+    #[test]
+    fn sound_item_filename_roundtrip() -> anyhow::Result<()> {
+        let version = FileVersion(*b"VER 5.7\0");
+        let mut item = SoundItem {
+            resource_id: 5011,
+            probability: 100,
+            civilization: Some(-1),
+            icon_set: Some(-1),
+            ..Default::default()
+        };
+        item.filename.push_str("drum1.wav");
+
+        assert_versioned_round_trip(&item, version);
+        Ok(())
+    }
+
+    #[test]
+    fn write_out_of_range_count() {
+        let sound = Sound {
+            items: vec![SoundItem::default(); usize::from(u16::MAX) + 1],
+            ..Default::default()
+        };
+        let err = sound
+            .write_to(&mut vec![], FileVersion(*b"VER 5.7\0"))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn find_missing_sound_files() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        for sound in &dat.sounds {
+            for id in sound.items.iter().filter_map(SoundItem::drs_id) {
+                if id != 6222 {
+                    writer.add("wav", id, &b"RIFF"[..])?;
+                }
+            }
+        }
+        let mut archive = writer.flush()?;
+        archive.set_position(0);
+        let drs = DRSReader::new(&mut archive)?;
+
+        let missing = dat.missing_sound_files(&[&drs]);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].0, SoundID::from(295));
+        assert_eq!(missing[0].1.resource_id, 6222);
+        Ok(())
+    }
 }
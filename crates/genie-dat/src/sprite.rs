@@ -1,16 +1,27 @@
 //! Types related to sprites and graphics.
 
 use crate::sound::SoundID;
+use crate::terrain::TerrainID;
+use crate::unknown::RawBytes;
+use crate::{DatFile, FileVersion};
 use arrayvec::ArrayVec;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use encoding_rs::UTF_8;
+use genie_drs::{DRSReader, DRSResource, ResourceType};
 pub use genie_support::SpriteID;
-use genie_support::{fallible_try_into, infallible_try_into, read_opt_u16, MapInto};
+use genie_support::{
+    decode_str_with_encoding, fallible_try_into, infallible_try_into, read_opt_i32, read_opt_u16,
+    read_opt_u8, read_records, write_opt_i16, write_opt_i32, write_opt_u16, write_opt_u8,
+    TryConvertExt,
+};
+use std::collections::BTreeSet;
 use std::convert::{TryFrom, TryInto};
-use std::io::{Read, Result, Write};
+use std::io::{Read, Result, Seek, SeekFrom, Write};
 use std::num::TryFromIntError;
 
 /// An ID identifying a string resource.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GraphicID(u32);
 
 impl From<u16> for GraphicID {
@@ -37,6 +48,7 @@ infallible_try_into!(GraphicID, u32);
 fallible_try_into!(GraphicID, i32);
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpriteDelta {
     pub sprite_id: Option<SpriteID>,
     pub offset_x: i16,
@@ -45,18 +57,33 @@ pub struct SpriteDelta {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SoundProp {
     pub sound_delay: i16,
     pub sound_id: SoundID,
+    #[allow(dead_code)]
     wwise_sound_id: Option<u32>,
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpriteAttackSound {
     pub sound_props: ArrayVec<SoundProp, 3>,
 }
 
+/// An error occurred while adding an attack sound to a sprite.
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum AddAttackSoundError {
+    /// The sprite does not have the given angle.
+    #[error("sprite has no angle {angle} (it has {num_angles} angles)")]
+    NoSuchAngle { angle: u16, num_angles: u16 },
+    /// The angle already has the maximum of 3 attack sounds.
+    #[error("angle {0} already has the maximum of 3 attack sounds")]
+    TooManySounds(u16),
+}
+
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sprite {
     pub id: SpriteID,
     pub name: String,
@@ -87,7 +114,9 @@ pub struct Sprite {
     /// ```
     pub layer: u8,
     pub color_table: u16,
-    pub transparent_selection: bool,
+    /// How the sprite is drawn when selected through other graphics. 0 and 1 are
+    /// commonly used, but other values occur.
+    pub transparent_selection: u8,
     pub bounding_box: (i16, i16, i16, i16),
     pub sound_id: Option<SoundID>,
     /// Number of frames per angle animation
@@ -109,9 +138,25 @@ pub struct Sprite {
     other_flag: i8,
     pub deltas: Vec<SpriteDelta>,
     pub attack_sounds: Vec<SpriteAttackSound>,
+    /// Bytes following the name in its fixed-size buffer.
+    pub unknown_name_tail: RawBytes,
+    /// Bytes following the file name in its fixed-size buffer.
+    pub unknown_filename_tail: RawBytes,
 }
 
 impl SpriteDelta {
+    /// Create a delta that draws another sprite at the same position, for all angles.
+    pub fn new(sprite_id: impl Into<SpriteID>) -> Self {
+        Self {
+            sprite_id: Some(sprite_id.into()),
+            display_angle: -1,
+            ..Default::default()
+        }
+    }
+
+    /// The size of a delta in a data file, in bytes.
+    const SIZE: usize = 16;
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let mut delta = SpriteDelta {
             sprite_id: read_opt_u16(&mut input)?,
@@ -128,7 +173,7 @@ impl SpriteDelta {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u16::<LE>(self.sprite_id.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut *output, self.sprite_id)?;
         // padding
         output.write_i16::<LE>(0)?;
         // pointer address to the parent sprite (overridden at load time by the game)
@@ -161,13 +206,16 @@ impl SoundProp {
     }
 
     pub fn write_empty<W: Write>(output: &mut W) -> Result<()> {
-        output.write_u16::<LE>(0)?;
+        output.write_i16::<LE>(-1)?;
         output.write_u16::<LE>(0xFFFF)?;
         Ok(())
     }
 }
 
 impl SpriteAttackSound {
+    /// The size of the attack sounds for one angle in a data file, in bytes.
+    const SIZE: usize = 12;
+
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let mut val = SpriteAttackSound::default();
         for _ in 0..val.sound_props.capacity() {
@@ -191,32 +239,32 @@ impl SpriteAttackSound {
 }
 
 impl Sprite {
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
+    /// Get the sizes of the name and file name fields for a data file version.
+    fn name_sizes(version: FileVersion) -> (usize, usize) {
+        if version.is_swgb() {
+            (25, 25)
+        } else {
+            (21, 13)
+        }
+    }
+
+    pub fn read_from(mut input: impl Read, version: FileVersion) -> Result<Self> {
         let mut sprite = Sprite::default();
-        let mut name = [0u8; 21];
+        let (name_size, filename_size) = Self::name_sizes(version);
+        let mut name = vec![0u8; name_size];
         input.read_exact(&mut name)?;
-        sprite.name =
-            String::from_utf8(name.iter().cloned().take_while(|b| *b != 0).collect()).unwrap();
-        let mut filename = [0u8; 13];
+        sprite.unknown_name_tail = RawBytes::string_tail(&name);
+        sprite.name = read_sprite_name(&name)?;
+        let mut filename = vec![0u8; filename_size];
         input.read_exact(&mut filename)?;
-        sprite.filename =
-            String::from_utf8(filename.iter().cloned().take_while(|b| *b != 0).collect()).unwrap();
-        sprite.slp_id = {
-            let num = input.read_i32::<LE>()?;
-            if num == -1 {
-                None
-            } else {
-                Some(num.try_into().unwrap())
-            }
-        };
+        sprite.unknown_filename_tail = RawBytes::string_tail(&filename);
+        sprite.filename = read_sprite_name(&filename)?;
+        sprite.slp_id = read_opt_i32(&mut input)?;
         sprite.is_loaded = input.read_u8()? != 0;
-        sprite.force_player_color = match input.read_u8()? {
-            0xFF => None,
-            id => Some(id),
-        };
+        sprite.force_player_color = read_opt_u8(&mut input)?;
         sprite.layer = input.read_u8()?;
         sprite.color_table = input.read_u16::<LE>()?;
-        sprite.transparent_selection = input.read_u8()? != 0;
+        sprite.transparent_selection = input.read_u8()?;
         sprite.bounding_box = (
             input.read_i16::<LE>()?,
             input.read_i16::<LE>()?,
@@ -236,43 +284,66 @@ impl Sprite {
         sprite.mirror_flag = input.read_i8()?;
         sprite.other_flag = input.read_i8()?;
 
-        for _ in 0..num_deltas {
-            sprite.deltas.push(SpriteDelta::read_from(&mut input)?);
-        }
+        sprite.deltas = read_records(
+            &mut input,
+            usize::from(num_deltas),
+            SpriteDelta::SIZE,
+            |record| SpriteDelta::read_from(record),
+        )?;
         if attack_sounds_used {
-            for _ in 0..sprite.num_angles {
-                sprite
-                    .attack_sounds
-                    .push(SpriteAttackSound::read_from(&mut input)?);
-            }
+            sprite.attack_sounds = read_records(
+                &mut input,
+                usize::from(sprite.num_angles),
+                SpriteAttackSound::SIZE,
+                |record| SpriteAttackSound::read_from(record),
+            )?;
         }
 
         Ok(sprite)
     }
 
-    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+    /// Skip over a sprite in an input stream, without reading its data.
+    pub(crate) fn skip(mut input: impl Read + Seek, version: FileVersion) -> Result<()> {
+        let (name_size, filename_size) = Self::name_sizes(version);
+        input.seek(SeekFrom::Current((name_size + filename_size + 18) as i64))?;
+        let num_deltas = input.read_u16::<LE>()?;
+        input.seek(SeekFrom::Current(2))?;
+        let attack_sounds_used = input.read_u8()? != 0;
+        input.seek(SeekFrom::Current(2))?;
+        let num_angles = input.read_u16::<LE>()?;
+        let mut dist = 17 + 16 * i64::from(num_deltas);
+        if attack_sounds_used {
+            dist += 12 * i64::from(num_angles);
+        }
+        input.seek(SeekFrom::Current(dist))?;
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, output: &mut W, version: FileVersion) -> Result<()> {
         if !self.attack_sounds.is_empty() {
             assert_eq!(self.attack_sounds.len(), usize::from(self.num_angles));
         }
-        let mut name = [0u8; 21];
-        (&mut name[..]).write_all(self.name.as_bytes())?;
-        let mut filename = [0u8; 13];
-        (&mut filename[..]).write_all(self.filename.as_bytes())?;
-        output.write_all(&name)?;
-        output.write_all(&filename)?;
-        output.write_i32::<LE>(self.slp_id.map(|v| v.try_into().unwrap()).unwrap_or(-1))?;
+        let (name_size, filename_size) = Self::name_sizes(version);
+        self.unknown_name_tail
+            .write_string_to(&mut *output, self.name.as_bytes(), name_size)?;
+        self.unknown_filename_tail.write_string_to(
+            &mut *output,
+            self.filename.as_bytes(),
+            filename_size,
+        )?;
+        write_opt_i32(&mut *output, self.slp_id)?;
         output.write_u8(if self.is_loaded { 1 } else { 0 })?;
-        output.write_u8(self.force_player_color.unwrap_or(0xFF))?;
+        write_opt_u8(&mut *output, self.force_player_color)?;
         output.write_u8(self.layer)?;
         output.write_u16::<LE>(self.color_table)?;
-        output.write_u8(if self.transparent_selection { 1 } else { 0 })?;
+        output.write_u8(self.transparent_selection)?;
         output.write_i16::<LE>(self.bounding_box.0)?;
         output.write_i16::<LE>(self.bounding_box.1)?;
         output.write_i16::<LE>(self.bounding_box.2)?;
         output.write_i16::<LE>(self.bounding_box.3)?;
 
-        output.write_u16::<LE>(self.deltas.len().try_into().unwrap())?;
-        output.write_i16::<LE>(self.sound_id.map(|v| v.try_into().unwrap()).unwrap_or(-1))?;
+        output.write_u16::<LE>(self.deltas.len().try_convert()?)?;
+        write_opt_i16(&mut *output, self.sound_id)?;
         output.write_u8(if self.attack_sounds.is_empty() { 0 } else { 1 })?;
         output.write_u16::<LE>(self.num_frames)?;
         output.write_u16::<LE>(self.num_angles)?;
@@ -293,3 +364,275 @@ impl Sprite {
         Ok(())
     }
 }
+
+impl Sprite {
+    /// Point this sprite at a different SLP graphic.
+    pub fn set_slp_id(&mut self, slp_id: impl Into<GraphicID>) {
+        self.slp_id = Some(slp_id.into());
+    }
+
+    /// Add a delta, drawing another sprite on top of this one. Returns the index of the new
+    /// delta.
+    pub fn add_delta(&mut self, delta: SpriteDelta) -> usize {
+        self.deltas.push(delta);
+        self.deltas.len() - 1
+    }
+
+    /// Remove the delta at the given index.
+    pub fn remove_delta(&mut self, index: usize) -> Option<SpriteDelta> {
+        if index < self.deltas.len() {
+            Some(self.deltas.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Set the number of frames in the animation for each angle.
+    pub fn set_num_frames(&mut self, num_frames: u16) {
+        self.num_frames = num_frames;
+    }
+
+    /// Set the number of angles in this sprite.
+    ///
+    /// If the sprite has attack sounds, the attack sound list is resized to match: new angles do
+    /// not have attack sounds, and the attack sounds of removed angles are dropped.
+    pub fn set_num_angles(&mut self, num_angles: u16) {
+        self.num_angles = num_angles;
+        if !self.attack_sounds.is_empty() {
+            self.attack_sounds
+                .resize_with(num_angles.into(), Default::default);
+        }
+    }
+
+    /// Get the attack sounds played at the given angle.
+    pub fn attack_sounds_for_angle(&self, angle: u16) -> Option<&SpriteAttackSound> {
+        self.attack_sounds.get(usize::from(angle))
+    }
+
+    /// Add an attack sound to the given angle.
+    ///
+    /// If the sprite did not have attack sounds yet, an empty attack sound list is created for
+    /// every angle.
+    pub fn add_attack_sound(
+        &mut self,
+        angle: u16,
+        sound_id: impl Into<SoundID>,
+        sound_delay: i16,
+    ) -> std::result::Result<(), AddAttackSoundError> {
+        if angle >= self.num_angles {
+            return Err(AddAttackSoundError::NoSuchAngle {
+                angle,
+                num_angles: self.num_angles,
+            });
+        }
+        if self.attack_sounds.is_empty() {
+            self.attack_sounds
+                .resize_with(self.num_angles.into(), Default::default);
+        }
+        let prop = SoundProp {
+            sound_delay,
+            sound_id: sound_id.into(),
+            wwise_sound_id: None,
+        };
+        self.attack_sounds[usize::from(angle)]
+            .sound_props
+            .try_push(prop)
+            .map_err(|_| AddAttackSoundError::TooManySounds(angle))
+    }
+
+    /// Remove all attack sounds from this sprite.
+    pub fn clear_attack_sounds(&mut self) {
+        self.attack_sounds.clear();
+    }
+
+    /// Find this sprite's SLP graphic in a DRS archive.
+    pub fn find_in<'a>(&self, drs: &'a DRSReader) -> Option<&'a DRSResource> {
+        self.slp_id.and_then(|id| find_graphic(drs, id))
+    }
+}
+
+fn find_graphic(drs: &DRSReader, id: GraphicID) -> Option<&DRSResource> {
+    drs.get_resource(ResourceType::from("slp"), id.0)
+}
+
+/// Decode a sprite name or file name from its fixed-size, NUL-terminated buffer.
+fn read_sprite_name(buffer: &[u8]) -> Result<String> {
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    Ok(decode_str_with_encoding(&buffer[..end], UTF_8)?)
+}
+
+/// Something in a data file that refers to an SLP graphic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicOwner {
+    /// A sprite.
+    Sprite(SpriteID),
+    /// A terrain.
+    Terrain(TerrainID),
+    /// A terrain border, by its index.
+    TerrainBorder(usize),
+}
+
+/// The result of checking the graphics referenced by a data file against DRS archives.
+#[derive(Debug, Default, Clone)]
+pub struct GraphicsReport {
+    /// Graphics referenced by the data file that are not present in any of the archives. These
+    /// show up as pink squares in game.
+    pub missing: Vec<(GraphicOwner, GraphicID)>,
+    /// SLP graphics in the archives that are not referenced by the data file, in ascending order.
+    pub orphaned: Vec<GraphicID>,
+}
+
+impl GraphicsReport {
+    /// Returns true if no graphics are missing or orphaned.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+impl DatFile {
+    /// Iterate over all SLP graphics referenced by sprites, terrains, and terrain borders.
+    pub fn graphic_references(&self) -> impl Iterator<Item = (GraphicOwner, GraphicID)> + '_ {
+        let sprites = self
+            .sprites
+            .iter()
+            .flatten()
+            .filter_map(|sprite| Some((GraphicOwner::Sprite(sprite.id), sprite.slp_id?)));
+        let terrains = self
+            .terrains
+            .iter()
+            .enumerate()
+            .filter_map(|(index, terrain)| {
+                let id = TerrainID::from(u16::try_from(index).ok()?);
+                Some((GraphicOwner::Terrain(id), terrain.slp_id?))
+            });
+        let borders = self
+            .terrain_borders
+            .iter()
+            .enumerate()
+            .filter_map(|(index, border)| {
+                Some((GraphicOwner::TerrainBorder(index), border.slp_id?))
+            });
+        sprites.chain(terrains).chain(borders)
+    }
+
+    /// Check the SLP graphics referenced by this data file against the given DRS archives.
+    ///
+    /// Only graphics archives should be passed in: interface archives contain SLPs, such as
+    /// icons, that are not referenced through sprites and would all be reported as orphaned.
+    pub fn check_graphics(&self, archives: &[&DRSReader]) -> GraphicsReport {
+        let mut referenced = BTreeSet::new();
+        let mut missing = vec![];
+        for (owner, id) in self.graphic_references() {
+            referenced.insert(id);
+            if archives.iter().all(|drs| find_graphic(drs, id).is_none()) {
+                missing.push((owner, id));
+            }
+        }
+
+        let available: BTreeSet<GraphicID> = archives
+            .iter()
+            .flat_map(|drs| drs.tables())
+            .filter(|table| table.resource_type == ResourceType::from("slp"))
+            .flat_map(|table| table.resources())
+            .map(|resource| GraphicID(resource.id))
+            .collect();
+        let orphaned = available.difference(&referenced).copied().collect();
+
+        GraphicsReport { missing, orphaned }
+    }
+}
+
+genie_support::impl_versioned! {
+    SpriteDelta => std::io::Error;
+    SoundProp => std::io::Error;
+    SpriteAttackSound => std::io::Error;
+    Sprite: FileVersion => std::io::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genie_drs::{DRSWriter, InMemoryStrategy};
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn edit_sprite() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        let version = dat.file_version;
+        let sprite = dat
+            .sprites
+            .iter_mut()
+            .flatten()
+            .find(|sprite| sprite.num_angles == 8 && sprite.attack_sounds.is_empty())
+            .unwrap();
+
+        sprite.set_slp_id(1234u32);
+        let index = sprite.add_delta(SpriteDelta::new(7));
+        assert_eq!(sprite.deltas[index].display_angle, -1);
+        sprite.add_attack_sound(2, 80, 5)?;
+        assert_eq!(sprite.attack_sounds.len(), 8);
+        sprite.add_attack_sound(2, 81, 5)?;
+        sprite.add_attack_sound(2, 82, 5)?;
+        assert!(matches!(
+            sprite.add_attack_sound(2, 83, 5),
+            Err(AddAttackSoundError::TooManySounds(2))
+        ));
+        assert!(matches!(
+            sprite.add_attack_sound(8, 83, 5),
+            Err(AddAttackSoundError::NoSuchAngle { .. })
+        ));
+
+        sprite.set_num_angles(16);
+        assert_eq!(sprite.attack_sounds.len(), 16);
+
+        let mut bytes = vec![];
+        sprite.write_to(&mut bytes, version)?;
+        let read = Sprite::read_from(Cursor::new(bytes), version)?;
+        assert_eq!(read.slp_id, Some(GraphicID::from(1234u32)));
+        assert_eq!(read.deltas.len(), sprite.deltas.len());
+        assert_eq!(read.num_angles, 16);
+        assert_eq!(read.attack_sounds.len(), 16);
+        let sounds = read.attack_sounds_for_angle(2).unwrap();
+        assert_eq!(sounds.sound_props.len(), 3);
+        assert_eq!(sounds.sound_props[0].sound_id, SoundID::from(80));
+        Ok(())
+    }
+
+    #[test]
+    fn check_graphics() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let dat = DatFile::read_from(&mut f)?;
+
+        let archer = dat.civilizations[0].get_unit_type(4).unwrap();
+        let archer = dat
+            .get_sprite(archer.static_.standing_sprite_1.unwrap())
+            .unwrap();
+        let removed = archer.slp_id.unwrap();
+
+        let referenced: BTreeSet<_> = dat.graphic_references().map(|(_, id)| id).collect();
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        for &id in referenced.iter().filter(|&&id| id != removed) {
+            writer.add("slp", id.0, &b"2.0N"[..])?;
+        }
+        writer.add("slp", 99_999, &b"2.0N"[..])?;
+        let mut archive = writer.flush()?;
+        archive.set_position(0);
+        let drs = DRSReader::new(&mut archive)?;
+
+        assert!(archer.find_in(&drs).is_none());
+        let report = dat.check_graphics(&[&drs]);
+        assert!(!report.is_empty());
+        assert!(report.missing.iter().all(|&(_, id)| id == removed));
+        assert!(report
+            .missing
+            .contains(&(GraphicOwner::Sprite(archer.id), removed)));
+        assert_eq!(report.orphaned, vec![GraphicID::from(99_999u32)]);
+
+        let report = dat.check_graphics(&[]);
+        assert_eq!(report.missing.len(), dat.graphic_references().count());
+        assert!(report.orphaned.is_empty());
+        Ok(())
+    }
+}
@@ -2,15 +2,16 @@ use crate::sound::SoundID;
 use crate::sprite::SpriteID;
 use crate::unit_type::UnitTypeID;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::read_opt_u16;
-use std::convert::TryInto;
+use genie_support::{read_opt_u16, write_opt_i16, TryConvertExt};
 use std::io::{Read, Result, Write};
 use std::ops::Deref;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TaskList(Vec<Task>);
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Task {
     id: u16,
     is_default: bool,
@@ -23,7 +24,7 @@ pub struct Task {
     work_range: f32,
     auto_search_targets: bool,
     search_wait_time: f32,
-    enable_targeting: bool,
+    enable_targeting: u8,
     combat_level: u8,
     work_flags: (u16, u16),
     owner_type: u8,
@@ -58,7 +59,7 @@ impl TaskList {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u16::<LE>(self.len().try_into().unwrap())?;
+        output.write_u16::<LE>(self.len().try_convert()?)?;
         for task in self.iter() {
             output.write_u16::<LE>(1)?;
             task.write_to(output)?;
@@ -86,7 +87,7 @@ impl Task {
             work_range: input.read_f32::<LE>()?,
             auto_search_targets: input.read_u8()? != 0,
             search_wait_time: input.read_f32::<LE>()?,
-            enable_targeting: input.read_u8()? != 0,
+            enable_targeting: input.read_u8()?,
             combat_level: input.read_u8()?,
             work_flags: (input.read_u16::<LE>()?, input.read_u16::<LE>()?),
             owner_type: input.read_u8()?,
@@ -106,11 +107,7 @@ impl Task {
         output.write_u8(if self.is_default { 1 } else { 0 })?;
         output.write_u16::<LE>(self.action_type)?;
         output.write_i16::<LE>(self.object_class)?;
-        output.write_i16::<LE>(
-            self.object_id
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut *output, self.object_id)?;
         output.write_i16::<LE>(self.terrain_id)?;
         output.write_i16::<LE>(self.attribute_types.0)?;
         output.write_i16::<LE>(self.attribute_types.1)?;
@@ -121,43 +118,24 @@ impl Task {
         output.write_f32::<LE>(self.work_range)?;
         output.write_u8(if self.auto_search_targets { 1 } else { 0 })?;
         output.write_f32::<LE>(self.search_wait_time)?;
-        output.write_u8(if self.enable_targeting { 1 } else { 0 })?;
+        output.write_u8(self.enable_targeting)?;
         output.write_u8(self.combat_level)?;
         output.write_u16::<LE>(self.work_flags.0)?;
         output.write_u16::<LE>(self.work_flags.1)?;
         output.write_u8(self.owner_type)?;
         output.write_u8(self.holding_attribute)?;
         output.write_u8(self.state_building)?;
-        output.write_i16::<LE>(
-            self.move_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.work_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.work_sprite2
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.carry_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.work_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.work_sound2
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut *output, self.move_sprite)?;
+        write_opt_i16(&mut *output, self.work_sprite)?;
+        write_opt_i16(&mut *output, self.work_sprite2)?;
+        write_opt_i16(&mut *output, self.carry_sprite)?;
+        write_opt_i16(&mut *output, self.work_sound)?;
+        write_opt_i16(&mut *output, self.work_sound2)?;
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    TaskList => std::io::Error;
+    Task => std::io::Error;
+}
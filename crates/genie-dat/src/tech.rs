@@ -1,14 +1,21 @@
 use crate::civ::CivilizationID;
 use crate::unit_type::UnitTypeID;
+use crate::unknown::RawBytes;
+use crate::{DatFile, FileVersion};
 use arrayvec::{ArrayString, ArrayVec};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use encoding_rs::WINDOWS_1252;
 pub use genie_support::TechID;
-use genie_support::{read_opt_u16, read_opt_u32, MapInto, StringKey};
-use std::io::{Read, Result, Write};
+use genie_support::{
+    decode_str_with_encoding, encode_str_with_encoding, read_opt_u16, read_opt_u32,
+    write_fixed_str_with_encoding, write_opt_u16, write_opt_u32, AttributeID, ReadStringsExt,
+    StringKey, StringSource, DEFAULT_ENCODING,
+};
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Read, Result, Write};
 
 /// An effect command specifies an attribute change when a tech effect is triggered.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EffectCommand {
     /// The command.
     pub command_type: u8,
@@ -20,6 +27,7 @@ type TechEffectName = ArrayString<31>;
 
 /// A tech effect is a group of attribute changes that are applied when the effect is triggered.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechEffect {
     /// Name for the effect.
     name: TechEffectName,
@@ -27,14 +35,16 @@ pub struct TechEffect {
     pub commands: Vec<EffectCommand>,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechEffectRef {
-    pub effect_type: u16,
+    pub effect_type: AttributeID,
     pub amount: u16,
     pub enabled: bool,
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tech {
     required_techs: ArrayVec<TechID, 6>,
     effects: ArrayVec<TechEffectRef, 3>,
@@ -44,7 +54,7 @@ pub struct Tech {
     language_dll_name: Option<StringKey>,
     language_dll_description: Option<StringKey>,
     time: u16,
-    time2: u16,
+    effect_id: Option<u16>,
     type_: u16,
     icon_id: Option<u16>,
     button_id: u8,
@@ -52,6 +62,39 @@ pub struct Tech {
     help_page_id: u32,
     hotkey: Option<u32>,
     name: String,
+    /// Secondary name, only used by Star Wars: Galactic Battlegrounds.
+    name2: Option<String>,
+    /// The required tech and cost slots and the number of required techs, as stored in the data
+    /// file. Used to write unchanged requirements back in their original form.
+    pub unknown_requirement_slots: RawBytes,
+    /// Bytes following the name, usually a NUL terminator.
+    pub unknown_name_tail: RawBytes,
+    /// Bytes following the secondary name, usually a NUL terminator.
+    pub unknown_name2_tail: RawBytes,
+}
+
+/// Size of the required tech slots, the cost slots, and the number of required techs of a tech.
+const REQUIREMENT_SLOTS_LEN: usize = 6 * 2 + 3 * 5 + 2;
+
+/// Read the required tech and cost slots of a tech, skipping unused slots.
+fn read_requirements(
+    mut input: impl Read,
+) -> Result<(ArrayVec<TechID, 6>, ArrayVec<TechEffectRef, 3>)> {
+    let mut required_techs = ArrayVec::new();
+    for _ in 0..6 {
+        // 4 on some versions
+        if let Some(tech_id) = read_opt_u16(&mut input)? {
+            required_techs.push(tech_id);
+        }
+    }
+    let mut effects = ArrayVec::new();
+    for _ in 0..3 {
+        let effect = TechEffectRef::read_from(&mut input)?;
+        if effect.effect_type != AttributeID::from(0xFFFF_u16) {
+            effects.push(effect);
+        }
+    }
+    Ok((required_techs, effects))
 }
 
 impl EffectCommand {
@@ -95,11 +138,9 @@ impl TechEffect {
 
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let mut effect = Self::default();
-        let mut bytes = [0; 31];
-        input.read_exact(&mut bytes)?;
-        let bytes = &bytes[..bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len())];
-        let (name, _encoding, _failed) = WINDOWS_1252.decode(&bytes);
-        effect.name = TechEffectName::from(&name).unwrap();
+        let name = input.read_str(31)?.unwrap_or_default();
+        effect.name = TechEffectName::from(&name)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.simplify()))?;
 
         let num_commands = input.read_u16::<LE>()?;
         for _ in 0..num_commands {
@@ -110,9 +151,7 @@ impl TechEffect {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        let mut buffer = [0; 31];
-        (&mut buffer[..self.name.len()]).copy_from_slice(self.name.as_bytes());
-        output.write_all(&buffer)?;
+        write_fixed_str_with_encoding(output, &self.name, 31, DEFAULT_ENCODING)?;
 
         output.write_u16::<LE>(self.commands.len() as u16)?;
         for effect in &self.commands {
@@ -125,14 +164,14 @@ impl TechEffect {
 impl TechEffectRef {
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         Ok(Self {
-            effect_type: input.read_u16::<LE>()?,
+            effect_type: input.read_u16::<LE>()?.into(),
             amount: input.read_u16::<LE>()?,
             enabled: input.read_u8()? != 0,
         })
     }
 
     pub fn write_to<W: Write>(self, output: &mut W) -> Result<()> {
-        output.write_u16::<LE>(self.effect_type)?;
+        output.write_u16::<LE>(self.effect_type.into())?;
         output.write_u16::<LE>(self.amount)?;
         output.write_u8(if self.enabled { 1 } else { 0 })?;
         Ok(())
@@ -145,67 +184,119 @@ impl Tech {
         self.name.as_str()
     }
 
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
+    /// Get the secondary name of this tech. Only Star Wars: Galactic Battlegrounds techs have a
+    /// secondary name.
+    pub fn name2(&self) -> Option<&str> {
+        self.name2.as_deref()
+    }
+
+    /// Look up the display name of this tech.
+    pub fn display_name<'a>(&self, strings: &'a (impl StringSource + ?Sized)) -> Option<&'a str> {
+        self.language_dll_name
+            .as_ref()
+            .and_then(|key| strings.get_string(key))
+    }
+
+    /// Look up the description of this tech.
+    pub fn description<'a>(&self, strings: &'a (impl StringSource + ?Sized)) -> Option<&'a str> {
+        self.language_dll_description
+            .as_ref()
+            .and_then(|key| strings.get_string(key))
+    }
+
+    /// Look up the help text of this tech.
+    pub fn help_text<'a>(&self, strings: &'a (impl StringSource + ?Sized)) -> Option<&'a str> {
+        self.language_dll_help
+            .as_ref()
+            .and_then(|key| strings.get_string(key))
+    }
+
+    /// Get the techs that must be researched before this tech.
+    pub fn required_techs(&self) -> &[TechID] {
+        &self.required_techs
+    }
+
+    /// Get the building where this tech is researched. Techs without a location are researched
+    /// automatically once their requirements are met.
+    pub fn location(&self) -> Option<UnitTypeID> {
+        self.location
+    }
+
+    /// Get the resource costs of this tech.
+    pub fn costs(&self) -> &[TechEffectRef] {
+        &self.effects
+    }
+
+    /// Get mutable access to the resource costs of this tech.
+    pub fn costs_mut(&mut self) -> &mut [TechEffectRef] {
+        &mut self.effects
+    }
+
+    /// Get the time it takes to research this tech.
+    pub fn research_time(&self) -> u16 {
+        self.time
+    }
+
+    /// Set the time it takes to research this tech.
+    pub fn set_research_time(&mut self, time: u16) {
+        self.time = time;
+    }
+
+    /// Get the ID of the effect that is triggered when this tech is researched.
+    pub fn effect_id(&self) -> Option<u16> {
+        self.effect_id
+    }
+
+    /// Get the civilization that can research this tech. `None` if all civilizations can
+    /// research it.
+    pub fn civilization_id(&self) -> Option<CivilizationID> {
+        self.civilization_id
+    }
+
+    pub fn read_from(mut input: impl Read, version: FileVersion) -> Result<Self> {
         let mut tech = Self::default();
-        for _ in 0..6 {
-            // 4 on some versions
-            if let Some(tech_id) = read_opt_u16(&mut input)? {
-                tech.required_techs.push(tech_id);
-            }
-        }
-        for _ in 0..3 {
-            let effect = TechEffectRef::read_from(&mut input)?;
-            if effect.effect_type != 0xFFFF {
-                tech.effects.push(effect);
-            }
-        }
-        let _num_required_techs = input.read_u16::<LE>()?;
+        let mut requirement_slots = [0; REQUIREMENT_SLOTS_LEN];
+        input.read_exact(&mut requirement_slots)?;
+        let (required_techs, effects) = read_requirements(&requirement_slots[..])?;
+        tech.required_techs = required_techs;
+        tech.effects = effects;
+        tech.unknown_requirement_slots = RawBytes::new(&requirement_slots);
         tech.civilization_id = read_opt_u16(&mut input)?;
         tech.full_tech_mode = input.read_u16::<LE>()?;
         tech.location = read_opt_u16(&mut input)?;
         tech.language_dll_name = read_opt_u16(&mut input)?;
         tech.language_dll_description = read_opt_u16(&mut input)?;
         tech.time = input.read_u16::<LE>()?;
-        tech.time2 = input.read_u16::<LE>()?;
+        tech.effect_id = read_opt_u16(&mut input)?;
         tech.type_ = input.read_u16::<LE>()?;
         tech.icon_id = read_opt_u16(&mut input)?;
         tech.button_id = input.read_u8()?;
         tech.language_dll_help = read_opt_u32(&mut input)?;
         tech.help_page_id = input.read_u32::<LE>()?;
         tech.hotkey = read_opt_u32(&mut input)?;
-        tech.name = {
-            let name_len = input.read_u16::<LE>()?;
-            let mut bytes = vec![0; name_len as usize];
-            input.read_exact(&mut bytes)?;
-            let bytes = &bytes[..bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len())];
-            let (name, _encoding, _failed) = WINDOWS_1252.decode(&bytes);
-            name.to_string()
-        };
+        (tech.name, tech.unknown_name_tail) = read_tech_name(&mut input)?;
+        if version.is_swgb() {
+            let (name2, name2_tail) = read_tech_name(&mut input)?;
+            tech.name2 = Some(name2);
+            tech.unknown_name2_tail = name2_tail;
+        }
         Ok(tech)
     }
 
-    pub fn write_to(&self, mut output: impl Write) -> Result<()> {
-        for i in 0..6 {
-            match self.required_techs.get(i) {
-                Some(&id) => output.write_u16::<LE>(id.into())?,
-                None => output.write_i16::<LE>(-1)?,
-            }
-        }
-        for i in 0..3 {
-            match self.effects.get(i) {
-                Some(effect) => effect.write_to(&mut output)?,
-                None => TechEffectRef {
-                    effect_type: 0xFFFF,
-                    amount: 0,
-                    enabled: false,
-                }
-                .write_to(&mut output)?,
-            }
+    pub fn write_to(&self, mut output: impl Write, version: FileVersion) -> Result<()> {
+        let requirement_slots = self.unknown_requirement_slots.as_slice();
+        let unchanged = requirement_slots.len() == REQUIREMENT_SLOTS_LEN && {
+            let (required_techs, effects) = read_requirements(requirement_slots)?;
+            required_techs == self.required_techs && effects == self.effects
+        };
+        if unchanged {
+            output.write_all(requirement_slots)?;
+        } else {
+            self.write_requirements_to(&mut output)?;
         }
-        output.write_u16::<LE>(self.required_techs.len() as u16)?;
-        output.write_u16::<LE>(self.civilization_id.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.civilization_id)?;
         output.write_u16::<LE>(self.full_tech_mode)?;
-        output.write_u16::<LE>(self.location.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.location)?;
         output.write_u16::<LE>(match self.language_dll_name {
             Some(StringKey::Num(id)) => id as u16,
             Some(_) => unreachable!("cannot use named string keys in dat files"),
@@ -217,9 +308,9 @@ impl Tech {
             None => 0xFFFF,
         })?;
         output.write_u16::<LE>(self.time)?;
-        output.write_u16::<LE>(self.time2)?;
+        write_opt_u16(&mut output, self.effect_id)?;
         output.write_u16::<LE>(self.type_)?;
-        output.write_u16::<LE>(self.icon_id.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.icon_id)?;
         output.write_u8(self.button_id)?;
         output.write_u32::<LE>(match self.language_dll_help {
             Some(StringKey::Num(id)) => id,
@@ -227,10 +318,174 @@ impl Tech {
             None => 0xFFFF_FFFF,
         })?;
         output.write_u32::<LE>(self.help_page_id)?;
-        output.write_u32::<LE>(self.hotkey.map_into().unwrap_or(0xFFFF_FFFF))?;
-        let (encoded, _encoding, _failed) = WINDOWS_1252.encode(&self.name);
-        output.write_u16::<LE>(encoded.len() as u16)?;
-        output.write_all(encoded.as_ref())?;
+        write_opt_u32(&mut output, self.hotkey)?;
+        write_tech_name(&mut output, &self.name, &self.unknown_name_tail)?;
+        if version.is_swgb() {
+            write_tech_name(
+                &mut output,
+                self.name2.as_deref().unwrap_or_default(),
+                &self.unknown_name2_tail,
+            )?;
+        }
         Ok(())
     }
+
+    fn write_requirements_to(&self, mut output: impl Write) -> Result<()> {
+        for i in 0..6 {
+            match self.required_techs.get(i) {
+                Some(&id) => output.write_u16::<LE>(id.into())?,
+                None => output.write_i16::<LE>(-1)?,
+            }
+        }
+        for i in 0..3 {
+            match self.effects.get(i) {
+                Some(effect) => effect.write_to(&mut output)?,
+                None => TechEffectRef {
+                    effect_type: AttributeID::from(0xFFFF_u16),
+                    amount: 0,
+                    enabled: false,
+                }
+                .write_to(&mut output)?,
+            }
+        }
+        output.write_u16::<LE>(self.required_techs.len() as u16)?;
+        Ok(())
+    }
+}
+
+/// Read a tech name, and keep the bytes following its NUL terminator.
+fn read_tech_name(mut input: impl Read) -> Result<(String, RawBytes)> {
+    let name_len = input.read_u16::<LE>()?;
+    let mut bytes = vec![0; name_len as usize];
+    input.read_exact(&mut bytes)?;
+    let end = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    let name = decode_str_with_encoding(&bytes[..end], DEFAULT_ENCODING)?;
+    Ok((name, RawBytes::new(&bytes[end..])))
+}
+
+fn write_tech_name(mut output: impl Write, name: &str, tail: &RawBytes) -> Result<()> {
+    let encoded = encode_str_with_encoding(name, DEFAULT_ENCODING)?;
+    let tail = tail.as_slice();
+    output.write_u16::<LE>((encoded.len() + tail.len()) as u16)?;
+    output.write_all(encoded.as_ref())?;
+    output.write_all(tail)?;
+    Ok(())
+}
+
+/// Builder for adding a new tech to a data file.
+///
+/// ```rust,no_run
+/// # use genie_dat::DatFile;
+/// # use genie_dat::TechBuilder;
+/// # use genie_dat::AttributeID;
+/// # fn add_tech(dat: &mut DatFile) {
+/// let tech_id = TechBuilder::new("Better Swords")
+///     .cost(AttributeID::GOLD, 100)
+///     .research_time(60)
+///     .location(103u16)
+///     .insert(dat);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TechBuilder {
+    tech: Tech,
+    effect: Option<TechEffect>,
+}
+
+impl TechBuilder {
+    /// Start building a tech with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            tech: Tech {
+                name: name.into(),
+                ..Default::default()
+            },
+            effect: None,
+        }
+    }
+
+    /// Add a tech that must be researched before this tech.
+    ///
+    /// # Panics
+    /// This function panics if the tech already requires 6 other techs.
+    pub fn required_tech(mut self, tech: impl Into<TechID>) -> Self {
+        self.tech.required_techs.push(tech.into());
+        self
+    }
+
+    /// Add a resource cost.
+    ///
+    /// # Panics
+    /// This function panics if the tech already has 3 costs.
+    pub fn cost(mut self, resource: AttributeID, amount: u16) -> Self {
+        self.tech.effects.push(TechEffectRef {
+            effect_type: resource,
+            amount,
+            enabled: true,
+        });
+        self
+    }
+
+    /// Make the tech exclusive to a civilization.
+    pub fn civilization(mut self, civ: impl Into<CivilizationID>) -> Self {
+        self.tech.civilization_id = Some(civ.into());
+        self
+    }
+
+    /// Set the building where the tech is researched. This also adds the tech to the tech tree.
+    pub fn location(mut self, building: impl Into<UnitTypeID>) -> Self {
+        self.tech.location = Some(building.into());
+        self
+    }
+
+    /// Set the time it takes to research the tech.
+    pub fn research_time(mut self, time: u16) -> Self {
+        self.tech.time = time;
+        self
+    }
+
+    /// Set the ID of an existing effect to trigger when the tech is researched.
+    pub fn effect_id(mut self, effect_id: u16) -> Self {
+        self.tech.effect_id = Some(effect_id);
+        self.effect = None;
+        self
+    }
+
+    /// Set a new effect to trigger when the tech is researched. The effect is added to the data
+    /// file when the tech is inserted.
+    pub fn effect(mut self, effect: TechEffect) -> Self {
+        self.effect = Some(effect);
+        self
+    }
+
+    /// Set the button position of the tech.
+    pub fn button(mut self, button_id: u8) -> Self {
+        self.tech.button_id = button_id;
+        self
+    }
+
+    /// Add the tech to the data file, and return its newly allocated ID.
+    ///
+    /// # Panics
+    /// This function panics if the data file already has the maximum number of techs or effects.
+    pub fn insert(self, dat: &mut DatFile) -> TechID {
+        let id = TechID::from(u16::try_from(dat.techs.len()).expect("too many techs"));
+        let mut tech = self.tech;
+        if let Some(effect) = self.effect {
+            tech.effect_id = Some(u16::try_from(dat.effects.len()).expect("too many effects"));
+            dat.effects.push(effect);
+        }
+        if let Some(building) = tech.location {
+            dat.tech_tree.add_tech(id, building);
+        }
+        dat.techs.push(tech);
+        id
+    }
+}
+
+genie_support::impl_versioned! {
+    EffectCommand => std::io::Error;
+    TechEffect => std::io::Error;
+    TechEffectRef => std::io::Error;
+    Tech: FileVersion => std::io::Error;
 }
@@ -2,14 +2,18 @@
 
 use crate::civ::CivilizationID;
 use crate::unit_type::UnitTypeID;
+use crate::unknown::RawBytes;
 use arrayvec::ArrayVec;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{read_opt_u32, TechID};
+use genie_support::{read_opt_u32, write_opt_u32, TechID};
+use std::collections::{HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Read, Result, Write};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TechTreeStatus {
+    #[default]
     None,
     /// This building/unit/technology is available to the player.
     AvailablePlayer,
@@ -21,15 +25,7 @@ pub enum TechTreeStatus {
     ResearchedCompleted,
     /// This building/unit/technology is available to the player if someone on their team is this
     /// civilization.
-    AvailableTeam {
-        civilization_id: CivilizationID,
-    },
-}
-
-impl std::default::Default for TechTreeStatus {
-    fn default() -> Self {
-        Self::None
-    }
+    AvailableTeam { civilization_id: CivilizationID },
 }
 
 #[derive(Debug, Clone, Copy, thiserror::Error)]
@@ -49,7 +45,7 @@ impl TryFrom<u8> for TechTreeStatus {
             10..=255 => Ok(Self::AvailableTeam {
                 civilization_id: (n - 10).into(),
             }),
-            n => Err(ParseTechTreeStatusError(n as u8)),
+            n => Err(ParseTechTreeStatusError(n)),
         }
     }
 }
@@ -68,8 +64,10 @@ impl From<TechTreeStatus> for u8 {
 }
 
 /// Kinds of tech tree nodes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TechTreeType {
+    #[default]
     None = 0,
     Age = 1,
     Unit = 2,
@@ -80,12 +78,6 @@ pub enum TechTreeType {
     UniqueUnit = 7,
 }
 
-impl std::default::Default for TechTreeType {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 #[error("invalid tech tree node type {} (must be 0-7)", .0)]
 pub struct ParseTechTreeTypeError(i32);
@@ -121,6 +113,7 @@ impl From<TechTreeType> for i32 {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechTree {
     pub ages: Vec<TechTreeAge>,
     pub buildings: Vec<TechTreeBuilding>,
@@ -129,7 +122,8 @@ pub struct TechTree {
     num_groups: i32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TechTreeDependency {
     /// A dependency on an age being researched.
     ///
@@ -194,18 +188,26 @@ impl TryFrom<i32> for TechTreeDependencyType {
     }
 }
 
-// FIXME: implementation of `From` is preferred since it gives you `Into<_>`
-// for free where the reverse isn't true
-impl Into<i32> for TechTreeDependencyType {
-    fn into(self) -> i32 {
-        self as i32
+impl From<TechTreeDependencyType> for i32 {
+    fn from(ty: TechTreeDependencyType) -> Self {
+        ty as i32
     }
 }
 
 #[derive(Debug, Default, Clone)]
-pub struct TechTreeDependencies(ArrayVec<TechTreeDependency, 10>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TechTreeDependencies(
+    ArrayVec<TechTreeDependency, 10>,
+    /// The dependency slots as stored in the data file, including unused slots. Used to write
+    /// unchanged dependencies back in their original form.
+    RawBytes,
+);
+
+/// Size of the dependency slots of a tech tree node.
+const DEPENDENCY_SLOTS_LEN: usize = 4 + 10 * 4 + 10 * 4;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechTreeAge {
     age_id: i32,
     status: TechTreeStatus,
@@ -224,6 +226,7 @@ pub struct TechTreeAge {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechTreeBuilding {
     building_id: UnitTypeID,
     status: TechTreeStatus,
@@ -249,6 +252,7 @@ pub struct TechTreeBuilding {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechTreeUnit {
     unit_id: UnitTypeID,
     status: TechTreeStatus,
@@ -263,6 +267,7 @@ pub struct TechTreeUnit {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechTreeTech {
     tech_id: TechID,
     status: TechTreeStatus,
@@ -313,6 +318,122 @@ impl TechTree {
         })
     }
 
+    /// Get the tech tree node for an age.
+    pub fn get_age(&self, age_id: i32) -> Option<&TechTreeAge> {
+        self.ages.iter().find(|age| age.age_id == age_id)
+    }
+
+    /// Get the tech tree node for a building.
+    pub fn get_building(&self, building_id: impl Into<UnitTypeID>) -> Option<&TechTreeBuilding> {
+        let building_id = building_id.into();
+        self.buildings
+            .iter()
+            .find(|building| building.building_id == building_id)
+    }
+
+    /// Get the tech tree node for a unit.
+    pub fn get_unit(&self, unit_id: impl Into<UnitTypeID>) -> Option<&TechTreeUnit> {
+        let unit_id = unit_id.into();
+        self.units.iter().find(|unit| unit.unit_id == unit_id)
+    }
+
+    /// Get the tech tree node for a tech.
+    pub fn get_tech(&self, tech_id: impl Into<TechID>) -> Option<&TechTreeTech> {
+        let tech_id = tech_id.into();
+        self.techs.iter().find(|tech| tech.tech_id == tech_id)
+    }
+
+    /// Add a unit node to the tech tree, and list it as a dependent of the building where it is
+    /// trained.
+    pub(crate) fn add_unit(
+        &mut self,
+        unit_id: UnitTypeID,
+        building: UnitTypeID,
+        requires_tech_id: Option<TechID>,
+    ) {
+        let mut prerequisites = TechTreeDependencies::default();
+        prerequisites.0.push(TechTreeDependency::Building(building));
+        if let Some(tech_id) = requires_tech_id {
+            prerequisites.0.push(TechTreeDependency::Research(tech_id));
+        }
+        self.units.push(TechTreeUnit {
+            unit_id,
+            status: TechTreeStatus::AvailablePlayer,
+            node_type: TechTreeType::Unit,
+            building,
+            requires_tech_id,
+            prerequisites,
+            ..Default::default()
+        });
+        if let Some(node) = self
+            .buildings
+            .iter_mut()
+            .find(|b| b.building_id == building)
+        {
+            node.dependent_units.push(unit_id);
+        }
+    }
+
+    /// Add a tech node to the tech tree, and list it as a dependent of the building where it is
+    /// researched.
+    pub(crate) fn add_tech(&mut self, tech_id: TechID, building: UnitTypeID) {
+        let mut prerequisites = TechTreeDependencies::default();
+        prerequisites.0.push(TechTreeDependency::Building(building));
+        self.techs.push(TechTreeTech {
+            tech_id,
+            status: TechTreeStatus::AvailablePlayer,
+            node_type: TechTreeType::Research,
+            building,
+            prerequisites,
+            ..Default::default()
+        });
+        if let Some(node) = self
+            .buildings
+            .iter_mut()
+            .find(|b| b.building_id == building)
+        {
+            node.dependent_techs.push(tech_id);
+        }
+    }
+
+    /// Get the prerequisites of a node in the tech tree.
+    fn prerequisites_of(&self, dependency: TechTreeDependency) -> Option<&TechTreeDependencies> {
+        match dependency {
+            TechTreeDependency::Age(id) => self.get_age(id).map(TechTreeAge::prerequisites),
+            TechTreeDependency::Building(id) => {
+                self.get_building(id).map(TechTreeBuilding::prerequisites)
+            }
+            TechTreeDependency::Unit(id) => self.get_unit(id).map(TechTreeUnit::prerequisites),
+            TechTreeDependency::Research(id) => self.get_tech(id).map(TechTreeTech::prerequisites),
+        }
+    }
+
+    /// Get the direct requirements for researching a tech. Returns an empty list if the tech is
+    /// not part of the tech tree.
+    pub fn dependencies_of(&self, tech_id: impl Into<TechID>) -> Vec<TechTreeDependency> {
+        self.get_tech(tech_id)
+            .map(|tech| tech.prerequisites().iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get all requirements for researching a tech, including the requirements of its
+    /// requirements. Each requirement is listed once, in the order they are encountered, nearest first.
+    pub fn all_dependencies_of(&self, tech_id: impl Into<TechID>) -> Vec<TechTreeDependency> {
+        let mut seen = HashSet::new();
+        let mut list = vec![];
+        let mut queue: VecDeque<_> = self.dependencies_of(tech_id).into();
+        while let Some(dependency) = queue.pop_front() {
+            if !seen.insert(dependency) {
+                continue;
+            }
+            list.push(dependency);
+            if let Some(prerequisites) = self.prerequisites_of(dependency) {
+                queue.extend(prerequisites.iter().copied());
+            }
+        }
+        list
+    }
+
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         output.write_u8(self.ages.len() as u8)?;
         output.write_u8(self.buildings.len() as u8)?;
@@ -339,6 +460,14 @@ impl TechTree {
 
 impl TechTreeDependencies {
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
+        let mut slots = [0; DEPENDENCY_SLOTS_LEN];
+        input.read_exact(&mut slots)?;
+        let mut deps = Self::read_slots(&mut &slots[..])?;
+        deps.1 = RawBytes::new(&slots);
+        Ok(deps)
+    }
+
+    fn read_slots<R: Read>(input: &mut R) -> Result<Self> {
         let mut deps = Self::default();
         let num = input.read_u8()?;
         let _padding = input.read_u8()?;
@@ -374,6 +503,10 @@ impl TechTreeDependencies {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        let slots = self.1.as_slice();
+        if slots.len() == DEPENDENCY_SLOTS_LEN && Self::read_slots(&mut &slots[..])?.0 == self.0 {
+            return output.write_all(slots);
+        }
         assert!(self.len() <= 10);
         output.write_u8(self.len() as u8)?;
         output.write_all(&[0, 0, 0])?;
@@ -423,6 +556,21 @@ where
 }
 
 impl TechTreeAge {
+    /// The ID of this age.
+    pub fn age_id(&self) -> i32 {
+        self.age_id
+    }
+
+    /// The status of this age.
+    pub fn status(&self) -> TechTreeStatus {
+        self.status
+    }
+
+    /// The requirements for reaching this age.
+    pub fn prerequisites(&self) -> &TechTreeDependencies {
+        &self.prerequisites
+    }
+
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let mut age = TechTreeAge {
             age_id: input.read_i32::<LE>()?,
@@ -473,6 +621,26 @@ impl TechTreeAge {
 }
 
 impl TechTreeBuilding {
+    /// The unit type ID of this building.
+    pub fn building_id(&self) -> UnitTypeID {
+        self.building_id
+    }
+
+    /// The status of this building.
+    pub fn status(&self) -> TechTreeStatus {
+        self.status
+    }
+
+    /// The kind of tech tree node this building is.
+    pub fn node_type(&self) -> TechTreeType {
+        self.node_type
+    }
+
+    /// The requirements for constructing this building.
+    pub fn prerequisites(&self) -> &TechTreeDependencies {
+        &self.prerequisites
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let mut building = TechTreeBuilding {
             building_id: input.read_i32::<LE>()?.try_into().map_err(invalid_data)?,
@@ -515,16 +683,48 @@ impl TechTreeBuilding {
         output.write_all(&self.total_children_by_age)?;
         output.write_all(&self.initial_children_by_age)?;
         output.write_u32::<LE>(self.node_type.into())?;
-        output.write_u32::<LE>(
-            self.depends_tech_id
-                .map(|tech_id| u16::from(tech_id) as u32)
-                .unwrap_or(0xFFFF_FFFF),
-        )?;
+        write_opt_u32(&mut output, self.depends_tech_id)?;
         Ok(())
     }
 }
 
 impl TechTreeUnit {
+    /// The unit type ID of this unit.
+    pub fn unit_id(&self) -> UnitTypeID {
+        self.unit_id
+    }
+
+    /// The status of this unit.
+    pub fn status(&self) -> TechTreeStatus {
+        self.status
+    }
+
+    /// The kind of tech tree node this unit is.
+    pub fn node_type(&self) -> TechTreeType {
+        self.node_type
+    }
+
+    /// The building where this unit is trained.
+    pub fn building(&self) -> UnitTypeID {
+        self.building
+    }
+
+    /// The tech that must be researched to make this unit available. `None` if the unit is
+    /// available without requiring any techs.
+    pub fn requires_tech_id(&self) -> Option<TechID> {
+        self.requires_tech_id
+    }
+
+    /// The units that become available by training this unit.
+    pub fn dependent_units(&self) -> &[UnitTypeID] {
+        &self.dependent_units
+    }
+
+    /// The requirements for training this unit.
+    pub fn prerequisites(&self) -> &TechTreeDependencies {
+        &self.prerequisites
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(TechTreeUnit {
             unit_id: input.read_i32::<LE>()?.try_into().map_err(invalid_data)?,
@@ -551,22 +751,54 @@ impl TechTreeUnit {
             output.write_u32::<LE>((*dependent).into())?;
         }
         output.write_i32::<LE>(self.level_no)?;
-        output.write_u32::<LE>(
-            self.requires_tech_id
-                .map(|tech_id| u16::from(tech_id) as u32)
-                .unwrap_or(0xFFFF_FFFF),
-        )?;
+        write_opt_u32(&mut output, self.requires_tech_id)?;
         output.write_u32::<LE>(self.node_type.into())?;
-        output.write_u32::<LE>(
-            self.depends_tech_id
-                .map(|tech_id| u16::from(tech_id) as u32)
-                .unwrap_or(0xFFFF_FFFF),
-        )?;
+        write_opt_u32(&mut output, self.depends_tech_id)?;
         Ok(())
     }
 }
 
 impl TechTreeTech {
+    /// The ID of this tech.
+    pub fn tech_id(&self) -> TechID {
+        self.tech_id
+    }
+
+    /// The status of this tech.
+    pub fn status(&self) -> TechTreeStatus {
+        self.status
+    }
+
+    /// The kind of tech tree node this tech is.
+    pub fn node_type(&self) -> TechTreeType {
+        self.node_type
+    }
+
+    /// The building where this tech is researched.
+    pub fn building(&self) -> UnitTypeID {
+        self.building
+    }
+
+    /// The buildings that become available by researching this tech.
+    pub fn dependent_buildings(&self) -> &[UnitTypeID] {
+        &self.dependent_buildings
+    }
+
+    /// The units that become available by researching this tech.
+    pub fn dependent_units(&self) -> &[UnitTypeID] {
+        &self.dependent_units
+    }
+
+    /// The techs that become available by researching this tech.
+    pub fn dependent_techs(&self) -> &[TechID] {
+        &self.dependent_techs
+    }
+
+    /// The requirements for researching this tech.
+    pub fn prerequisites(&self) -> &TechTreeDependencies {
+        &self.prerequisites
+    }
+
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         Ok(TechTreeTech {
             tech_id: input.read_i32::<LE>()?.try_into().map_err(invalid_data)?,
@@ -609,3 +841,12 @@ impl TechTreeTech {
 fn invalid_data<E: std::error::Error + Sized + Send + Sync + 'static>(err: E) -> io::Error {
     io::Error::new(io::ErrorKind::InvalidData, err)
 }
+
+genie_support::impl_versioned! {
+    TechTree => std::io::Error;
+    TechTreeDependencies => std::io::Error;
+    TechTreeAge => std::io::Error;
+    TechTreeBuilding => std::io::Error;
+    TechTreeUnit => std::io::Error;
+    TechTreeTech => std::io::Error;
+}
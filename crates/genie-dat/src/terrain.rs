@@ -3,17 +3,19 @@
 use crate::sound::SoundID;
 use crate::sprite::{GraphicID, SpriteID};
 use crate::unit_type::UnitTypeID;
+use crate::unknown::RawBytes;
 use crate::FileVersion;
 use arrayvec::ArrayString;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use genie_support::{
-    fallible_try_from, fallible_try_into, infallible_try_into, read_opt_u16, read_opt_u32, MapInto,
+    fallible_try_from, fallible_try_into, infallible_try_into, read_opt_u16, read_opt_u32,
+    read_opt_u8, write_opt_i16, write_opt_i32, write_opt_u8, ReadableVersioned, WritableVersioned,
 };
-use std::convert::TryInto;
-use std::io::{Read, Result, Write};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 
 /// An ID identifying a terrain.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainID(u16);
 
 impl From<u8> for TerrainID {
@@ -45,9 +47,11 @@ infallible_try_into!(TerrainID, u32);
 fallible_try_from!(TerrainID, i32);
 fallible_try_from!(TerrainID, u32);
 
-type TerrainName = ArrayString<13>;
+/// Terrain names are 13 bytes, or 17 bytes in Star Wars: Galactic Battlegrounds.
+type TerrainName = ArrayString<17>;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainPassGraphic {
     exit_tile_sprite: Option<SpriteID>,
     enter_tile_sprite: Option<SpriteID>,
@@ -57,12 +61,14 @@ pub struct TerrainPassGraphic {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainRestriction {
     passability: Vec<f32>,
     pass_graphics: Vec<TerrainPassGraphic>,
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileSize {
     pub width: i16,
     pub height: i16,
@@ -70,6 +76,7 @@ pub struct TileSize {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainAnimation {
     pub enabled: bool,
     num_frames: i16,
@@ -84,6 +91,7 @@ pub struct TerrainAnimation {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainSpriteFrame {
     pub num_frames: i16,
     pub num_facets: i16,
@@ -91,6 +99,7 @@ pub struct TerrainSpriteFrame {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainObject {
     pub object_id: UnitTypeID,
     pub density: i16,
@@ -98,6 +107,7 @@ pub struct TerrainObject {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Terrain {
     /// Is this terrain enabled?
     pub enabled: bool,
@@ -133,9 +143,21 @@ pub struct Terrain {
     cols: i16,
     pub borders: Vec<i16>,
     pub terrain_objects: Vec<TerrainObject>,
+    /// The number of terrain objects as stored in the data file. This is often 0, even if
+    /// objects are defined.
+    num_terrain_objects: u16,
+    /// Bytes following the name in its fixed-size buffer.
+    pub unknown_name_tail: RawBytes,
+    /// Bytes following the SLP name in its fixed-size buffer.
+    pub unknown_slp_name_tail: RawBytes,
+    /// Pointer to the loaded SLP graphic.
+    pub unknown_slp_pointer: RawBytes,
+    /// Padding at the end of the terrain.
+    pub unknown_padding: RawBytes,
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainBorder {
     pub enabled: bool,
     random: u8,
@@ -150,9 +172,32 @@ pub struct TerrainBorder {
     draw_tile: i8,
     pub underlay_terrain: Option<i16>,
     pub border_style: i16,
+    /// Bytes following the name in its fixed-size buffer.
+    pub unknown_name_tail: RawBytes,
+    /// Bytes following the SLP name in its fixed-size buffer.
+    pub unknown_slp_name_tail: RawBytes,
+    /// Pointer to the loaded SLP graphic.
+    pub unknown_slp_pointer: RawBytes,
+    /// Padding following `draw_tile`.
+    pub unknown_padding: RawBytes,
 }
 
 impl TerrainPassGraphic {
+    /// Sprite shown when a unit leaves a tile of this terrain.
+    pub fn exit_tile_sprite(&self) -> Option<SpriteID> {
+        self.exit_tile_sprite
+    }
+
+    /// Sprite shown when a unit enters a tile of this terrain.
+    pub fn enter_tile_sprite(&self) -> Option<SpriteID> {
+        self.enter_tile_sprite
+    }
+
+    /// Sprite shown when a unit walks on a tile of this terrain.
+    pub fn walk_tile_sprite(&self) -> Option<SpriteID> {
+        self.walk_tile_sprite
+    }
+
     pub fn read_from(mut input: impl Read, version: FileVersion) -> Result<Self> {
         let mut pass = TerrainPassGraphic {
             exit_tile_sprite: read_opt_u32(&mut input)?,
@@ -170,20 +215,71 @@ impl TerrainPassGraphic {
 
     /// Serialize this object to a binary output stream.
     pub fn write_to(&self, mut output: impl Write, version: FileVersion) -> Result<()> {
-        output.write_i32::<LE>(self.exit_tile_sprite.map_into().unwrap_or(-1))?;
-        output.write_i32::<LE>(self.enter_tile_sprite.map_into().unwrap_or(-1))?;
-        output.write_i32::<LE>(self.walk_tile_sprite.map_into().unwrap_or(-1))?;
+        write_opt_i32(&mut output, self.exit_tile_sprite)?;
+        write_opt_i32(&mut output, self.enter_tile_sprite)?;
+        write_opt_i32(&mut output, self.walk_tile_sprite)?;
         // TODO decide on correct default values for these
         if version.is_swgb() {
             output.write_f32::<LE>(self.walk_rate.unwrap_or(0.0))?;
         } else {
-            output.write_i32::<LE>(self.replication_amount.unwrap_or(-1))?;
+            write_opt_i32(&mut output, self.replication_amount)?;
         }
         Ok(())
     }
 }
 
 impl TerrainRestriction {
+    /// Get the number of terrains this restriction has values for.
+    pub fn num_terrains(&self) -> usize {
+        self.passability.len()
+    }
+
+    /// Change the number of terrains this restriction has values for. New terrains can not be
+    /// entered.
+    pub(crate) fn resize(&mut self, num_terrains: u16) {
+        self.passability.resize(num_terrains.into(), 0.0);
+        self.pass_graphics
+            .resize_with(num_terrains.into(), Default::default);
+    }
+
+    /// Get the passability multiplier for a terrain. This is the movement speed and damage
+    /// multiplier for units on this terrain; 0.0 means the terrain can not be entered.
+    ///
+    /// Returns `None` if the terrain ID is out of range.
+    pub fn passability(&self, terrain: impl Into<TerrainID>) -> Option<f32> {
+        let terrain: TerrainID = terrain.into();
+        self.passability.get(usize::from(terrain)).copied()
+    }
+
+    /// Set the passability multiplier for a terrain.
+    ///
+    /// # Panics
+    /// This function panics if the terrain ID is out of range.
+    pub fn set_passability(&mut self, terrain: impl Into<TerrainID>, value: f32) {
+        let terrain: TerrainID = terrain.into();
+        self.passability[usize::from(terrain)] = value;
+    }
+
+    /// Check if units with this restriction can enter a terrain.
+    pub fn can_enter(&self, terrain: impl Into<TerrainID>) -> bool {
+        self.passability(terrain).is_some_and(|value| value > 0.0)
+    }
+
+    /// Iterate over the terrains that units with this restriction can enter.
+    pub fn passable_terrains(&self) -> impl Iterator<Item = TerrainID> + '_ {
+        self.passability
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value > 0.0)
+            .map(|(index, _)| TerrainID(index as u16))
+    }
+
+    /// Get the sprites shown when units with this restriction move over a terrain.
+    pub fn pass_graphic(&self, terrain: impl Into<TerrainID>) -> Option<&TerrainPassGraphic> {
+        let terrain: TerrainID = terrain.into();
+        self.pass_graphics.get(usize::from(terrain))
+    }
+
     pub fn read_from(
         mut input: impl Read,
         version: FileVersion,
@@ -213,8 +309,19 @@ impl TerrainRestriction {
         version: FileVersion,
         num_terrains: u16,
     ) -> Result<()> {
-        assert_eq!(self.passability.len(), num_terrains.into());
-        assert_eq!(self.pass_graphics.len(), num_terrains.into());
+        if self.passability.len() != usize::from(num_terrains)
+            || self.pass_graphics.len() != usize::from(num_terrains)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "terrain restriction has {} passability values and {} pass graphics, expected {}",
+                    self.passability.len(),
+                    self.pass_graphics.len(),
+                    num_terrains
+                ),
+            ));
+        }
         for value in &self.passability {
             output.write_f32::<LE>(*value)?;
         }
@@ -305,6 +412,22 @@ impl Terrain {
         self.name.as_str()
     }
 
+    /// Get the internal name of the SLP graphic for this terrain.
+    pub fn slp_name(&self) -> &str {
+        self.slp_name.as_str()
+    }
+
+    /// Create a disabled terrain, used to pad the terrain table to the size that a game version
+    /// expects.
+    pub(crate) fn disabled(num_terrains: u16) -> Self {
+        Self {
+            elevation_sprites: vec![Default::default(); 19],
+            borders: vec![0; num_terrains.into()],
+            terrain_objects: vec![Default::default(); 30],
+            ..Default::default()
+        }
+    }
+
     /// Read a Terrain object from an input stream.
     pub fn read_from(
         mut input: impl Read,
@@ -316,11 +439,11 @@ impl Terrain {
             random: input.read_u8()?,
             ..Default::default()
         };
-        read_terrain_name(&mut input, &mut terrain.name)?;
-        read_terrain_name(&mut input, &mut terrain.slp_name)?;
-        // println!("{}", terrain.name);
+        terrain.unknown_name_tail = read_terrain_name(&mut input, &mut terrain.name, version)?;
+        terrain.unknown_slp_name_tail =
+            read_terrain_name(&mut input, &mut terrain.slp_name, version)?;
         terrain.slp_id = read_opt_u32(&mut input)?;
-        let _slp_pointer = input.read_i32::<LE>()?;
+        terrain.unknown_slp_pointer = RawBytes::read_from(&mut input, 4)?;
         terrain.sound_id = read_opt_u32(&mut input)?;
         if version.is_de2() {
             terrain.wwise_sound_id = read_opt_u32(&mut input)?;
@@ -334,14 +457,8 @@ impl Terrain {
         terrain.minimap_color_low = input.read_u8()?;
         terrain.minimap_color_cliff_lt = input.read_u8()?;
         terrain.minimap_color_cliff_rt = input.read_u8()?;
-        terrain.passable_terrain_id = match input.read_u8()? {
-            0xFF => None,
-            id => Some(id),
-        };
-        terrain.impassable_terrain_id = match input.read_u8()? {
-            0xFF => None,
-            id => Some(id),
-        };
+        terrain.passable_terrain_id = read_opt_u8(&mut input)?;
+        terrain.impassable_terrain_id = read_opt_u8(&mut input)?;
         terrain.animation = TerrainAnimation::read_from(&mut input)?;
         for _ in 0..19 {
             terrain
@@ -366,12 +483,12 @@ impl Terrain {
             object.placement_flag = input.read_i8()?;
         }
 
-        let _num_terrain_objects = input.read_u16::<LE>()?;
         // Why is num_terrain_objects always 0?
         // terrain_objects.truncate(num_terrain_objects as usize);
+        terrain.num_terrain_objects = input.read_u16::<LE>()?;
         terrain.terrain_objects = terrain_objects;
 
-        let _padding = input.read_u16::<LE>()?;
+        terrain.unknown_padding = RawBytes::read_from(&mut input, 2)?;
 
         Ok(terrain)
     }
@@ -380,35 +497,31 @@ impl Terrain {
     pub fn write_to<W: Write>(
         &self,
         output: &mut W,
-        _version: FileVersion,
+        version: FileVersion,
         num_terrains: u16,
     ) -> Result<()> {
         assert_eq!(self.borders.len(), num_terrains as usize);
         output.write_u8(if self.enabled { 1 } else { 0 })?;
         output.write_u8(self.random)?;
-        write_terrain_name(output, &self.name)?;
-        write_terrain_name(output, &self.slp_name)?;
-        output.write_i32::<LE>(self.slp_id.map(|id| id.try_into().unwrap()).unwrap_or(-1))?;
-        output.write_i32::<LE>(0)?; // slp pointer
-        output.write_i32::<LE>(self.sound_id.map(|id| id.try_into().unwrap()).unwrap_or(-1))?;
-        output.write_i32::<LE>(self.blend_priority.unwrap_or(-1))?;
-        output.write_i32::<LE>(self.blend_mode.unwrap_or(-1))?;
+        write_terrain_name(output, &self.name, &self.unknown_name_tail, version)?;
+        write_terrain_name(output, &self.slp_name, &self.unknown_slp_name_tail, version)?;
+        write_opt_i32(&mut *output, self.slp_id)?;
+        self.unknown_slp_pointer.write_to(&mut *output, 4)?;
+        write_opt_i32(&mut *output, self.sound_id)?;
+        write_opt_i32(&mut *output, self.blend_priority)?;
+        write_opt_i32(&mut *output, self.blend_mode)?;
         output.write_u8(self.minimap_color_high)?;
         output.write_u8(self.minimap_color_medium)?;
         output.write_u8(self.minimap_color_low)?;
         output.write_u8(self.minimap_color_cliff_lt)?;
         output.write_u8(self.minimap_color_cliff_rt)?;
-        output.write_u8(self.passable_terrain_id.unwrap_or(0xFF))?;
-        output.write_u8(self.impassable_terrain_id.unwrap_or(0xFF))?;
+        write_opt_u8(&mut *output, self.passable_terrain_id)?;
+        write_opt_u8(&mut *output, self.impassable_terrain_id)?;
         self.animation.write_to(output)?;
         for frame in &self.elevation_sprites {
             frame.write_to(output)?;
         }
-        output.write_i16::<LE>(
-            self.terrain_id_to_draw
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut *output, self.terrain_id_to_draw)?;
         output.write_i16::<LE>(self.rows)?;
         output.write_i16::<LE>(self.cols)?;
         for border in &self.borders {
@@ -436,25 +549,26 @@ impl Terrain {
                 output.write_i8(0)?;
             }
         }
-        output.write_u16::<LE>(self.terrain_objects.len() as u16)?;
+        output.write_u16::<LE>(self.num_terrain_objects)?;
 
-        output.write_u16::<LE>(0)?; // padding
+        self.unknown_padding.write_to(&mut *output, 2)?;
 
         Ok(())
     }
 }
 
 impl TerrainBorder {
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: FileVersion) -> Result<Self> {
         let mut border = TerrainBorder {
             enabled: input.read_u8()? != 0,
             random: input.read_u8()?,
             ..Default::default()
         };
-        read_terrain_name(&mut input, &mut border.name)?;
-        read_terrain_name(&mut input, &mut border.slp_name)?;
+        border.unknown_name_tail = read_terrain_name(&mut input, &mut border.name, version)?;
+        border.unknown_slp_name_tail =
+            read_terrain_name(&mut input, &mut border.slp_name, version)?;
         border.slp_id = read_opt_u32(&mut input)?;
-        let _slp_pointer = input.read_i32::<LE>()?;
+        border.unknown_slp_pointer = RawBytes::read_from(&mut input, 4)?;
         border.sound_id = read_opt_u32(&mut input)?;
         border.color = (input.read_u8()?, input.read_u8()?, input.read_u8()?);
         border.animation = TerrainAnimation::read_from(&mut input)?;
@@ -467,8 +581,7 @@ impl TerrainBorder {
         }
 
         border.draw_tile = input.read_i8()?;
-        // Padding
-        input.read_u8()?;
+        border.unknown_padding = RawBytes::read_from(&mut input, 1)?;
         border.underlay_terrain = read_opt_u16(&mut input)?;
         border.border_style = input.read_i16::<LE>()?;
 
@@ -476,14 +589,14 @@ impl TerrainBorder {
     }
 
     /// Serialize this object to a binary output stream.
-    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+    pub fn write_to<W: Write>(&self, output: &mut W, version: FileVersion) -> Result<()> {
         output.write_u8(if self.enabled { 1 } else { 0 })?;
         output.write_u8(self.random)?;
-        write_terrain_name(output, &self.name)?;
-        write_terrain_name(output, &self.slp_name)?;
-        output.write_i32::<LE>(self.slp_id.map(|id| id.try_into().unwrap()).unwrap_or(-1))?;
-        output.write_i32::<LE>(0)?; // slp pointer
-        output.write_i32::<LE>(self.sound_id.map(|id| id.try_into().unwrap()).unwrap_or(-1))?;
+        write_terrain_name(output, &self.name, &self.unknown_name_tail, version)?;
+        write_terrain_name(output, &self.slp_name, &self.unknown_slp_name_tail, version)?;
+        write_opt_i32(&mut *output, self.slp_id)?;
+        self.unknown_slp_pointer.write_to(&mut *output, 4)?;
+        write_opt_i32(&mut *output, self.sound_id)?;
         output.write_u8(self.color.0)?;
         output.write_u8(self.color.1)?;
         output.write_u8(self.color.2)?;
@@ -494,15 +607,29 @@ impl TerrainBorder {
             }
         }
         output.write_i8(self.draw_tile)?;
-        output.write_u8(0)?; // padding
-        output.write_i16::<LE>(self.underlay_terrain.unwrap_or(-1))?;
+        self.unknown_padding.write_to(&mut *output, 1)?;
+        write_opt_i16(&mut *output, self.underlay_terrain)?;
         output.write_i16::<LE>(self.border_style)?;
         Ok(())
     }
 }
 
-fn read_terrain_name<R: Read>(input: &mut R, output: &mut TerrainName) -> Result<()> {
-    let bytes = &mut [0; 13];
+/// Get the size of terrain name fields for a data file version.
+fn terrain_name_size(version: FileVersion) -> usize {
+    if version.is_swgb() {
+        17
+    } else {
+        13
+    }
+}
+
+/// Read a terrain name, returning the bytes that follow it in its fixed-size buffer.
+fn read_terrain_name<R: Read>(
+    input: &mut R,
+    output: &mut TerrainName,
+    version: FileVersion,
+) -> Result<RawBytes> {
+    let bytes = &mut vec![0; terrain_name_size(version)];
     input.read_exact(bytes)?;
     bytes
         .iter()
@@ -510,12 +637,69 @@ fn read_terrain_name<R: Read>(input: &mut R, output: &mut TerrainName) -> Result
         .take_while(|b| *b != 0)
         .map(char::from)
         .for_each(|c| output.push(c));
-    Ok(())
+    Ok(RawBytes::string_tail(bytes))
+}
+
+fn write_terrain_name<W: Write>(
+    output: &mut W,
+    name: &TerrainName,
+    tail: &RawBytes,
+    version: FileVersion,
+) -> Result<()> {
+    tail.write_string_to(output, name.as_bytes(), terrain_name_size(version))
+}
+
+genie_support::impl_versioned! {
+    TerrainPassGraphic: FileVersion => std::io::Error;
+    TileSize => std::io::Error;
+    TerrainAnimation => std::io::Error;
+    TerrainSpriteFrame => std::io::Error;
+    TerrainBorder: FileVersion => std::io::Error;
+}
+
+// Terrain restrictions and terrains also depend on the number of terrains in the data file.
+impl ReadableVersioned<(FileVersion, u16)> for TerrainRestriction {
+    type Error = std::io::Error;
+
+    fn read_versioned<R: Read>(
+        input: &mut R,
+        (version, num_terrains): (FileVersion, u16),
+    ) -> Result<Self> {
+        Self::read_from(input, version, num_terrains)
+    }
+}
+
+impl WritableVersioned<(FileVersion, u16)> for TerrainRestriction {
+    type Error = std::io::Error;
+
+    fn write_versioned<W: Write>(
+        &self,
+        output: &mut W,
+        (version, num_terrains): (FileVersion, u16),
+    ) -> Result<()> {
+        self.write_to(output, version, num_terrains)
+    }
 }
 
-fn write_terrain_name<W: Write>(output: &mut W, name: &TerrainName) -> Result<()> {
-    let bytes = &mut [0; 13];
-    (&mut bytes[..name.len()]).copy_from_slice(name.as_bytes());
-    output.write_all(bytes)?;
-    Ok(())
+impl ReadableVersioned<(FileVersion, u16)> for Terrain {
+    type Error = std::io::Error;
+
+    fn read_versioned<R: Read>(
+        input: &mut R,
+        (version, num_terrains): (FileVersion, u16),
+    ) -> Result<Self> {
+        Self::read_from(input, version, num_terrains)
+    }
+}
+
+impl WritableVersioned<(FileVersion, u16)> for Terrain {
+    type Error = std::io::Error;
+
+    fn write_versioned<W: Write>(
+        &self,
+        output: &mut W,
+        (version, num_terrains): (FileVersion, u16),
+    ) -> Result<()> {
+        self.write_to(output, version, num_terrains)
+    }
 }
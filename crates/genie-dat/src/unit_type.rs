@@ -1,16 +1,27 @@
 //! Types related to unit types.
 
+use crate::civ::CivilizationID;
 use crate::sound::SoundID;
 use crate::sprite::{GraphicID, SpriteID};
 use crate::task::TaskList;
 use crate::terrain::TerrainID;
+use crate::unknown::RawBytes;
+use crate::DatFile;
 use arrayvec::ArrayVec;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use encoding_rs::UTF_8;
 pub use genie_support::UnitTypeID;
-use genie_support::{read_opt_u16, read_opt_u32, MapInto, StringKey, TechID};
+use genie_support::{
+    decode_str_with_encoding, read_opt_i8, read_opt_u16, read_opt_u32, write_opt_i16, write_opt_i8,
+    write_opt_u16, write_opt_u32, AttributeID, MapInto, SaveVersion, StringKey, StringSource,
+    TechID, TryConvertExt,
+};
+use smallvec::SmallVec;
 use std::cmp::{Ordering, PartialOrd};
+use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
-use std::io::{self, Read, Result, Write};
+use std::io::{self, Read, Result, Seek, SeekFrom, Write};
+use std::sync::Arc;
 
 /// The base class of a unit indicates which data is available for that unit type.
 ///
@@ -27,10 +38,11 @@ use std::io::{self, Read, Result, Write};
 /// assert_eq!(UnitBaseClass::Doppelganger < UnitBaseClass::Moving, false);
 /// assert_eq!(UnitBaseClass::Doppelganger > UnitBaseClass::Moving, false);
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnitBaseClass {
-    /// The base unit type, for units that do not do anything.
+    /// The base unit type, for units that do not do anything. Also known as "eye candy".
     Static = 10,
     /// Unit type that supports animated sprites.
     Animated = 20,
@@ -53,56 +65,59 @@ pub enum UnitBaseClass {
     Tree = 90,
 }
 
-impl PartialOrd for UnitBaseClass {
-    fn partial_cmp(&self, other: &UnitBaseClass) -> Option<Ordering> {
-        if self == other {
-            return Some(Ordering::Equal);
+impl UnitBaseClass {
+    /// Get the base class that this base class inherits from, or `None` for `Static`.
+    pub fn parent(self) -> Option<Self> {
+        use UnitBaseClass::*;
+        match self {
+            Static => None,
+            Animated | Tree => Some(Static),
+            Doppelganger | Moving => Some(Animated),
+            Action => Some(Moving),
+            BaseCombat => Some(Action),
+            Missile | Combat => Some(BaseCombat),
+            Building => Some(Combat),
         }
+    }
 
-        let self_n = *self as u8;
-        let other_n = *other as u8;
+    /// Get the chain of base classes that this base class inherits from, starting at `Static` and
+    /// ending at this base class.
+    ///
+    /// ```rust
+    /// # use genie_dat::unit_type::UnitBaseClass;
+    /// let chain: Vec<_> = UnitBaseClass::Missile.chain().collect();
+    /// assert_eq!(chain, vec![
+    ///     UnitBaseClass::Static,
+    ///     UnitBaseClass::Animated,
+    ///     UnitBaseClass::Moving,
+    ///     UnitBaseClass::Action,
+    ///     UnitBaseClass::BaseCombat,
+    ///     UnitBaseClass::Missile,
+    /// ]);
+    /// ```
+    pub fn chain(self) -> impl Iterator<Item = Self> {
+        let mut chain: ArrayVec<Self, 7> =
+            std::iter::successors(Some(self), |class| class.parent()).collect();
+        chain.reverse();
+        chain.into_iter()
+    }
 
-        // handle weird leaves specially
-        match self {
-            Self::Doppelganger => {
-                if self_n > other_n {
-                    Some(Ordering::Greater)
-                } else {
-                    None
-                }
-            }
-            Self::Missile => {
-                if self_n > other_n {
-                    Some(Ordering::Greater)
-                } else {
-                    None
-                }
-            }
-            Self::Tree => match other {
-                Self::Static => Some(Ordering::Greater),
-                _ => None,
-            },
-            _ => match other {
-                Self::Doppelganger => {
-                    if self_n < other_n {
-                        Some(Ordering::Less)
-                    } else {
-                        None
-                    }
-                }
-                Self::Missile => {
-                    if self_n < other_n {
-                        Some(Ordering::Less)
-                    } else {
-                        None
-                    }
-                }
-                Self::Tree => match self {
-                    Self::Static => Some(Ordering::Less),
-                    _ => None,
-                },
-                _ => Some(self_n.cmp(&other_n)),
-            },
+    /// Check if this base class is `other`, or inherits from it.
+    pub fn inherits_from(self, other: Self) -> bool {
+        std::iter::successors(Some(self), |class| class.parent()).any(|class| class == other)
+    }
+}
+
+impl PartialOrd for UnitBaseClass {
+    fn partial_cmp(&self, other: &UnitBaseClass) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else if self.inherits_from(*other) {
+            Some(Ordering::Greater)
+        } else if other.inherits_from(*self) {
+            Some(Ordering::Less)
+        } else {
+            None
         }
     }
 }
@@ -139,8 +154,113 @@ impl From<UnitBaseClass> for u8 {
 }
 
 /// A unit class, a group identifier for runtime behaviours.
+///
+/// See [`unit_class`] for the classes used by Age of Empires II.
 pub type UnitClass = u16;
 
+/// Unit classes used by Age of Empires II.
+pub mod unit_class {
+    use super::UnitClass;
+
+    pub const ARCHER: UnitClass = 0;
+    pub const ARTIFACT: UnitClass = 1;
+    pub const TRADE_BOAT: UnitClass = 2;
+    pub const BUILDING: UnitClass = 3;
+    pub const CIVILIAN: UnitClass = 4;
+    pub const OCEAN_FISH: UnitClass = 5;
+    pub const INFANTRY: UnitClass = 6;
+    pub const BERRY_BUSH: UnitClass = 7;
+    pub const STONE_MINE: UnitClass = 8;
+    pub const PREY_ANIMAL: UnitClass = 9;
+    pub const PREDATOR_ANIMAL: UnitClass = 10;
+    pub const MISCELLANEOUS: UnitClass = 11;
+    pub const CAVALRY: UnitClass = 12;
+    pub const SIEGE_WEAPON: UnitClass = 13;
+    pub const TERRAIN: UnitClass = 14;
+    pub const TREE: UnitClass = 15;
+    pub const TREE_STUMP: UnitClass = 16;
+    pub const HEALER: UnitClass = 17;
+    pub const MONK: UnitClass = 18;
+    pub const TRADE_CART: UnitClass = 19;
+    pub const TRANSPORT_BOAT: UnitClass = 20;
+    pub const FISHING_BOAT: UnitClass = 21;
+    pub const WARSHIP: UnitClass = 22;
+    pub const CONQUISTADOR: UnitClass = 23;
+    pub const WAR_ELEPHANT: UnitClass = 24;
+    pub const HERO: UnitClass = 25;
+    pub const ELEPHANT_ARCHER: UnitClass = 26;
+    pub const WALL: UnitClass = 27;
+    pub const PHALANX: UnitClass = 28;
+    pub const DOMESTIC_ANIMAL: UnitClass = 29;
+    pub const FLAG: UnitClass = 30;
+    pub const DEEP_SEA_FISH: UnitClass = 31;
+    pub const GOLD_MINE: UnitClass = 32;
+    pub const SHORE_FISH: UnitClass = 33;
+    pub const CLIFF: UnitClass = 34;
+    pub const PETARD: UnitClass = 35;
+    pub const CAVALRY_ARCHER: UnitClass = 36;
+    pub const DOPPELGANGER: UnitClass = 37;
+    pub const BIRD: UnitClass = 38;
+    pub const GATE: UnitClass = 39;
+    pub const SALVAGE_PILE: UnitClass = 40;
+    pub const RESOURCE_PILE: UnitClass = 41;
+    pub const RELIC: UnitClass = 42;
+    pub const MONK_WITH_RELIC: UnitClass = 43;
+    pub const HAND_CANNONEER: UnitClass = 44;
+    pub const TWO_HANDED_SWORDSMAN: UnitClass = 45;
+    pub const PIKEMAN: UnitClass = 46;
+    pub const SCOUT: UnitClass = 47;
+    pub const ORE_MINE: UnitClass = 48;
+    pub const FARM: UnitClass = 49;
+    pub const SPEARMAN: UnitClass = 50;
+    pub const PACKED_UNIT: UnitClass = 51;
+    pub const TOWER: UnitClass = 52;
+    pub const BOARDING_BOAT: UnitClass = 53;
+    pub const UNPACKED_SIEGE_UNIT: UnitClass = 54;
+    pub const BALLISTA: UnitClass = 55;
+    pub const RAIDER: UnitClass = 56;
+    pub const CAVALRY_RAIDER: UnitClass = 57;
+    pub const LIVESTOCK: UnitClass = 58;
+    pub const KING: UnitClass = 59;
+    pub const MISC_BUILDING: UnitClass = 60;
+    pub const CONTROLLED_ANIMAL: UnitClass = 61;
+
+    /// Unit classes of units that fight, as opposed to villagers, trade units, animals and
+    /// buildings.
+    const MILITARY: [UnitClass; 25] = [
+        ARCHER,
+        INFANTRY,
+        CAVALRY,
+        SIEGE_WEAPON,
+        MONK,
+        WARSHIP,
+        CONQUISTADOR,
+        WAR_ELEPHANT,
+        HERO,
+        ELEPHANT_ARCHER,
+        PHALANX,
+        PETARD,
+        CAVALRY_ARCHER,
+        MONK_WITH_RELIC,
+        HAND_CANNONEER,
+        TWO_HANDED_SWORDSMAN,
+        PIKEMAN,
+        SCOUT,
+        SPEARMAN,
+        PACKED_UNIT,
+        BOARDING_BOAT,
+        UNPACKED_SIEGE_UNIT,
+        BALLISTA,
+        RAIDER,
+        CAVALRY_RAIDER,
+    ];
+
+    /// Check if a unit class is a military class.
+    pub fn is_military(class: UnitClass) -> bool {
+        MILITARY.contains(&class)
+    }
+}
+
 /// Data for a unit type.
 ///
 /// Unit types have a [base class][] identifier that indicates which data is available for that
@@ -152,6 +272,7 @@ pub type UnitClass = u16;
 ///
 /// [base class]: ./enum.UnitBaseClass.html
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnitType {
     /// The base class for this unit type.
     pub unit_base_class: UnitBaseClass,
@@ -172,14 +293,74 @@ pub struct UnitType {
     pub combat: Option<CombatUnitTypeAttributes>,
     /// Building unit type attributes, available if `self.unit_base_class >=
     /// UnitBaseClass::Building`.
-    pub building: Option<BuildingUnitTypeAttributes>,
+    pub building: Option<Box<BuildingUnitTypeAttributes>>,
 }
 
 impl UnitType {
+    /// Create a unit type with the given base class, with default values for all the attributes
+    /// that the base class supports.
+    pub fn new(unit_base_class: UnitBaseClass) -> Self {
+        let has = |class: UnitBaseClass| unit_base_class >= class;
+        Self {
+            unit_base_class,
+            static_: Default::default(),
+            animated: Some(Default::default()).filter(|_| has(UnitBaseClass::Animated)),
+            moving: Some(Default::default()).filter(|_| has(UnitBaseClass::Moving)),
+            action: Some(Default::default()).filter(|_| has(UnitBaseClass::Action)),
+            base_combat: Some(Default::default()).filter(|_| has(UnitBaseClass::BaseCombat)),
+            missile: Some(Default::default()).filter(|_| has(UnitBaseClass::Missile)),
+            combat: Some(Default::default()).filter(|_| has(UnitBaseClass::Combat)),
+            building: Some(Default::default()).filter(|_| has(UnitBaseClass::Building)),
+        }
+    }
+
+    /// Check if this unit type is a building.
+    pub fn is_building(&self) -> bool {
+        self.unit_base_class.inherits_from(UnitBaseClass::Building)
+    }
+
+    /// Check if this unit type is a military unit, such as an archer, a siege weapon or a monk.
+    /// Villagers, trade units, animals and buildings are not military units.
+    pub fn is_military(&self) -> bool {
+        !self.is_building()
+            && self.unit_base_class.inherits_from(UnitBaseClass::Combat)
+            && unit_class::is_military(self.static_.unit_class)
+    }
+
+    /// Check if this unit type is a villager.
+    pub fn is_civilian(&self) -> bool {
+        self.static_.unit_class == unit_class::CIVILIAN
+    }
+
+    /// Get the building or unit where this unit type can be trained or built. Returns `None` for
+    /// unit types that can not be created by players.
+    pub fn trainable_at(&self) -> Option<UnitTypeID> {
+        self.combat.as_ref()?.create_at_building
+    }
+
+    /// Look up the display name of this unit type.
+    pub fn display_name<'a>(&self, strings: &'a (impl StringSource + ?Sized)) -> Option<&'a str> {
+        strings.get_string(&self.static_.string_id)
+    }
+
+    /// Look up the help text of this unit type.
+    pub fn help_text<'a>(&self, strings: &'a (impl StringSource + ?Sized)) -> Option<&'a str> {
+        strings.get_string(&self.static_.help_string_id)
+    }
+
     /// Read a unit type from an input stream.
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
-        let unit_base_class = input.read_u8()?.try_into().unwrap();
-        let static_ = StaticUnitTypeAttributes::read_from(&mut input, version)?;
+    pub fn read_from(input: impl Read, version: SaveVersion) -> Result<Self> {
+        Self::read_from_inner(input, version, false)
+    }
+
+    /// Read a Star Wars: Galactic Battlegrounds unit type from an input stream.
+    pub fn read_from_swgb(input: impl Read, version: SaveVersion) -> Result<Self> {
+        Self::read_from_inner(input, version, true)
+    }
+
+    fn read_from_inner(mut input: impl Read, version: SaveVersion, swgb: bool) -> Result<Self> {
+        let unit_base_class = input.read_u8()?.try_convert()?;
+        let static_ = StaticUnitTypeAttributes::read_from_inner(&mut input, version, swgb)?;
         let mut unit = Self {
             unit_base_class,
             static_,
@@ -212,21 +393,97 @@ impl UnitType {
             unit.combat = Some(CombatUnitTypeAttributes::read_from(&mut input, version)?);
         }
         if unit_base_class >= UnitBaseClass::Building {
-            unit.building = Some(BuildingUnitTypeAttributes::read_from(&mut input, version)?);
+            unit.building = Some(Box::new(BuildingUnitTypeAttributes::read_from(
+                &mut input, version,
+            )?));
         }
         Ok(unit)
     }
 
+    /// Skip over a unit type in an input stream, without reading its data.
+    ///
+    /// This only reads the length fields, so it is much faster than reading the unit type.
+    pub(crate) fn skip(
+        mut input: impl Read + Seek,
+        version: SaveVersion,
+        swgb: bool,
+    ) -> Result<()> {
+        fn skip(mut input: impl Seek, dist: i64) -> Result<()> {
+            input.seek(SeekFrom::Current(dist))?;
+            Ok(())
+        }
+
+        let unit_base_class: UnitBaseClass = input.read_u8()?.try_convert()?;
+
+        // Static attributes
+        let name_len = input.read_u16::<LE>()?;
+        skip(&mut input, if version < 11.55 { 142 } else { 146 })?;
+        let num_damage_sprites = input.read_u8()?;
+        skip(
+            &mut input,
+            5 * i64::from(num_damage_sprites) + 6 + i64::from(name_len),
+        )?;
+        if swgb {
+            let name2_len = input.read_u16::<LE>()?;
+            skip(&mut input, i64::from(name2_len) + 3)?;
+        }
+        skip(&mut input, 4)?;
+
+        if unit_base_class >= UnitBaseClass::Animated {
+            skip(&mut input, 4)?;
+        }
+        if unit_base_class >= UnitBaseClass::Moving {
+            skip(&mut input, 37)?;
+        }
+        if unit_base_class >= UnitBaseClass::Action {
+            skip(&mut input, 20)?;
+        }
+        if unit_base_class >= UnitBaseClass::BaseCombat {
+            skip(&mut input, if version < 11.52 { 1 } else { 2 })?;
+            let num_weapons = input.read_u16::<LE>()?;
+            skip(&mut input, 4 * i64::from(num_weapons))?;
+            let num_armors = input.read_u16::<LE>()?;
+            skip(&mut input, 4 * i64::from(num_armors) + 56)?;
+        }
+        if unit_base_class >= UnitBaseClass::Missile {
+            skip(&mut input, 9)?;
+        }
+        if unit_base_class >= UnitBaseClass::Combat {
+            skip(&mut input, 65)?;
+        }
+        if unit_base_class >= UnitBaseClass::Building {
+            skip(&mut input, if version < 11.53 { 80 } else { 82 })?;
+        }
+        Ok(())
+    }
+
     /// Write this unit type to an output stream.
     ///
     /// # Panics
     /// This function panics when trying to write a unit type whose `unit_base_class` property does
     /// not match the available data attributes. For example, when `self.unit_base_class` is
     /// `UnitBaseClass::Animated`, but `self.animated` is `None`.
-    pub fn write_to(&self, mut output: impl Write, version: f32) -> Result<()> {
+    pub fn write_to(&self, output: impl Write, version: SaveVersion) -> Result<()> {
+        self.write_to_inner(output, version, false)
+    }
+
+    /// Write this unit type to an output stream in the Star Wars: Galactic Battlegrounds format.
+    ///
+    /// # Panics
+    /// This function panics in the same cases as [`UnitType::write_to`].
+    pub fn write_to_swgb(&self, output: impl Write, version: SaveVersion) -> Result<()> {
+        self.write_to_inner(output, version, true)
+    }
+
+    fn write_to_inner(
+        &self,
+        mut output: impl Write,
+        version: SaveVersion,
+        swgb: bool,
+    ) -> Result<()> {
         output.write_u8(self.unit_base_class.into())?;
 
-        self.static_.write_to(&mut output, version)?;
+        self.static_.write_to_inner(&mut output, version, swgb)?;
 
         if self.unit_base_class >= UnitBaseClass::Animated {
             self.animated
@@ -307,9 +564,10 @@ impl UnitType {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnitAttribute {
-    pub attribute_type: u16,
+    pub attribute_type: AttributeID,
     pub amount: f32,
     pub flag: u8,
 }
@@ -317,14 +575,14 @@ pub struct UnitAttribute {
 impl UnitAttribute {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(Self {
-            attribute_type: input.read_u16::<LE>()?,
+            attribute_type: input.read_u16::<LE>()?.into(),
             amount: input.read_f32::<LE>()?,
             flag: input.read_u8()?,
         })
     }
 
     pub fn write_to(self, mut output: impl Write) -> Result<()> {
-        output.write_u16::<LE>(self.attribute_type)?;
+        output.write_u16::<LE>(self.attribute_type.into())?;
         output.write_f32::<LE>(self.amount)?;
         output.write_u8(self.flag)?;
         Ok(())
@@ -338,7 +596,23 @@ impl UnitAttribute {
     }
 }
 
+/// Size of the three attribute slots of a unit type.
+const ATTRIBUTE_SLOTS_LEN: usize = 3 * 7;
+
+/// Read the three attribute slots of a unit type, skipping unused slots.
+fn read_attributes(mut input: impl Read) -> Result<ArrayVec<UnitAttribute, 3>> {
+    let mut attributes = ArrayVec::new();
+    for _ in 0..3 {
+        let attr = UnitAttribute::read_from(&mut input)?;
+        if attr.attribute_type != AttributeID::from(0xFFFF_u16) {
+            attributes.push(attr);
+        }
+    }
+    Ok(attributes)
+}
+
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DamageSprite {
     pub sprite: SpriteID,
     pub damage_percent: u16,
@@ -363,8 +637,9 @@ impl DamageSprite {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StaticUnitTypeAttributes {
-    name: String,
+    name: Arc<str>,
     pub id: UnitTypeID,
     pub string_id: StringKey,
     string_id2: Option<StringKey>,
@@ -391,8 +666,8 @@ pub struct StaticUnitTypeAttributes {
     pub tile_req: (i16, i16),
     pub center_tile_req: (i16, i16),
     pub construction_radius: (f32, f32),
-    pub elevation_flag: bool,
-    pub fog_flag: bool,
+    pub elevation_flag: u8,
+    pub fog_flag: u8,
     pub terrain_restriction_id: u16,
     pub movement_type: u8,
     pub attribute_max_amount: u16,
@@ -409,7 +684,7 @@ pub struct StaticUnitTypeAttributes {
     pub hotkey_id: u32,
     pub recyclable: bool,
     pub track_as_resource: bool,
-    pub create_doppleganger: bool,
+    pub create_doppleganger: u8,
     pub resource_group: u8,
     pub occlusion_mask: u8,
     pub obstruction_type: u8,
@@ -419,17 +694,44 @@ pub struct StaticUnitTypeAttributes {
     pub attribute_piece: u8,
     pub outline_radius: (f32, f32, f32),
     pub attributes: ArrayVec<UnitAttribute, 3>,
-    pub damage_sprites: Vec<DamageSprite>,
+    pub damage_sprites: SmallVec<[DamageSprite; 2]>,
     pub selected_sound: Option<SoundID>,
     pub death_sound: Option<SoundID>,
     pub attack_reaction: u8,
     pub convert_terrain_flag: u8,
+    /// Secondary name, only used by Star Wars: Galactic Battlegrounds.
+    name2: Option<Arc<str>>,
+    /// The unit line this unit belongs to, only used by Star Wars: Galactic Battlegrounds.
+    pub unit_line: Option<u16>,
+    /// Minimum tech level, only used by Star Wars: Galactic Battlegrounds.
+    pub min_tech_level: Option<u8>,
     pub copy_id: u16,
     pub unit_group: u16,
+    /// The three attribute slots as stored in the data file. Used to write unchanged attributes
+    /// back in their original slots.
+    pub unknown_attribute_slots: RawBytes,
+    /// Bytes following the name, usually a NUL terminator.
+    pub unknown_name_tail: RawBytes,
+    /// Bytes following the secondary name, usually a NUL terminator.
+    pub unknown_name2_tail: RawBytes,
 }
 
 impl StaticUnitTypeAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    /// Get the internal name of this unit type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the internal name of this unit type.
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into().into();
+    }
+
+    pub fn read_from(input: impl Read, version: SaveVersion) -> Result<Self> {
+        Self::read_from_inner(input, version, false)
+    }
+
+    fn read_from_inner(mut input: impl Read, version: SaveVersion, swgb: bool) -> Result<Self> {
         let mut unit_type = Self::default();
         let name_len = input.read_u16::<LE>()?;
         unit_type.id = input.read_u16::<LE>()?.into();
@@ -462,8 +764,8 @@ impl StaticUnitTypeAttributes {
         unit_type.tile_req = (input.read_i16::<LE>()?, input.read_i16::<LE>()?);
         unit_type.center_tile_req = (input.read_i16::<LE>()?, input.read_i16::<LE>()?);
         unit_type.construction_radius = (input.read_f32::<LE>()?, input.read_f32::<LE>()?);
-        unit_type.elevation_flag = input.read_u8()? != 0;
-        unit_type.fog_flag = input.read_u8()? != 0;
+        unit_type.elevation_flag = input.read_u8()?;
+        unit_type.fog_flag = input.read_u8()?;
         unit_type.terrain_restriction_id = input.read_u16::<LE>()?;
         unit_type.movement_type = input.read_u8()?;
         unit_type.attribute_max_amount = input.read_u16::<LE>()?;
@@ -480,7 +782,7 @@ impl StaticUnitTypeAttributes {
         unit_type.hotkey_id = input.read_u32::<LE>()?;
         unit_type.recyclable = input.read_u8()? != 0;
         unit_type.track_as_resource = input.read_u8()? != 0;
-        unit_type.create_doppleganger = input.read_u8()? != 0;
+        unit_type.create_doppleganger = input.read_u8()?;
         unit_type.resource_group = input.read_u8()?;
         unit_type.occlusion_mask = input.read_u8()?;
         unit_type.obstruction_type = input.read_u8()?;
@@ -497,15 +799,13 @@ impl StaticUnitTypeAttributes {
             input.read_f32::<LE>()?,
             input.read_f32::<LE>()?,
         );
-        for _ in 0..3 {
-            let attr = UnitAttribute::read_from(&mut input)?;
-            if attr.attribute_type != 0xFFFF {
-                unit_type.attributes.push(attr);
-            }
-        }
+        let mut attribute_slots = [0; ATTRIBUTE_SLOTS_LEN];
+        input.read_exact(&mut attribute_slots)?;
+        unit_type.attributes = read_attributes(&attribute_slots[..])?;
+        unit_type.unknown_attribute_slots = RawBytes::new(&attribute_slots);
         unit_type.damage_sprites = {
             let num_damage_sprites = input.read_u8()?;
-            let mut damage_sprites = vec![];
+            let mut damage_sprites = SmallVec::new();
             for _ in 0..num_damage_sprites {
                 damage_sprites.push(DamageSprite::read_from(&mut input)?);
             }
@@ -515,45 +815,68 @@ impl StaticUnitTypeAttributes {
         unit_type.death_sound = read_opt_u16(&mut input)?;
         unit_type.attack_reaction = input.read_u8()?;
         unit_type.convert_terrain_flag = input.read_u8()?;
-        unit_type.name = {
-            // TODO use not-UTF8 for the name
-            let mut bytes = vec![0; usize::from(name_len)];
-            input.read_exact(&mut bytes)?;
-            String::from_utf8(bytes.iter().cloned().take_while(|b| *b != 0).collect()).unwrap()
-        };
+        let (name, name_tail) = read_unit_name_with_tail(&mut input, name_len)?;
+        unit_type.name = name.into();
+        unit_type.unknown_name_tail = name_tail;
+        if swgb {
+            let name2_len = input.read_u16::<LE>()?;
+            let (name2, name2_tail) = read_unit_name_with_tail(&mut input, name2_len)?;
+            unit_type.name2 = Some(name2.into());
+            unit_type.unknown_name2_tail = name2_tail;
+            unit_type.unit_line = read_opt_u16(&mut input)?;
+            unit_type.min_tech_level = read_opt_i8(&mut input)?;
+        }
         unit_type.copy_id = input.read_u16::<LE>()?;
         unit_type.unit_group = input.read_u16::<LE>()?;
         Ok(unit_type)
     }
 
+    /// Get the secondary name of this unit type. Only Star Wars: Galactic Battlegrounds unit types
+    /// have a secondary name.
+    pub fn name2(&self) -> Option<&str> {
+        self.name2.as_deref()
+    }
+
+    /// Replace the names of this unit type by equal names from a set of shared names, and add its
+    /// names to the set if they are not in it yet.
+    pub(crate) fn share_names(&mut self, names: &mut HashSet<Arc<str>>) {
+        fn share(name: &mut Arc<str>, names: &mut HashSet<Arc<str>>) {
+            match names.get(name) {
+                Some(shared) => *name = Arc::clone(shared),
+                None => {
+                    names.insert(Arc::clone(name));
+                }
+            }
+        }
+
+        share(&mut self.name, names);
+        if let Some(name2) = &mut self.name2 {
+            share(name2, names);
+        }
+    }
+
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
+    pub fn write_to(&self, output: impl Write, version: SaveVersion) -> Result<()> {
+        self.write_to_inner(output, version, false)
+    }
+
+    fn write_to_inner(
+        &self,
+        mut output: impl Write,
+        version: SaveVersion,
+        swgb: bool,
+    ) -> Result<()> {
         // TODO use not-UTF8 for the name
-        output.write_u16::<LE>(self.name.len() as u16)?;
+        output
+            .write_u16::<LE>((self.name.len() + self.unknown_name_tail.as_slice().len()) as u16)?;
         output.write_u16::<LE>(self.id.into())?;
-        output.write_i16::<LE>((&self.string_id).try_into().unwrap())?;
+        output.write_i16::<LE>((&self.string_id).try_convert()?)?;
         write_opt_string_key(&mut output, &self.string_id2)?;
         output.write_u16::<LE>(self.unit_class)?;
-        output.write_i16::<LE>(
-            self.standing_sprite_1
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.standing_sprite_2
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.dying_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.undead_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.standing_sprite_1)?;
+        write_opt_i16(&mut output, self.standing_sprite_2)?;
+        write_opt_i16(&mut output, self.dying_sprite)?;
+        write_opt_i16(&mut output, self.undead_sprite)?;
         output.write_u8(self.undead_flag)?;
         output.write_u16::<LE>(self.hp)?;
         output.write_f32::<LE>(self.los)?;
@@ -561,34 +884,14 @@ impl StaticUnitTypeAttributes {
         output.write_f32::<LE>(self.radius.0)?;
         output.write_f32::<LE>(self.radius.1)?;
         output.write_f32::<LE>(self.radius.2)?;
-        output.write_i16::<LE>(
-            self.train_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.damage_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.death_spawn
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.train_sound)?;
+        write_opt_i16(&mut output, self.damage_sound)?;
+        write_opt_i16(&mut output, self.death_spawn)?;
         output.write_u8(self.sort_number)?;
         output.write_u8(if self.can_be_built_on { 1 } else { 0 })?;
-        output.write_i16::<LE>(
-            self.button_picture
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.button_picture)?;
         output.write_u8(if self.hide_in_scenario_editor { 1 } else { 0 })?;
-        output.write_i16::<LE>(
-            self.portrait_picture
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.portrait_picture)?;
         output.write_u8(if self.enabled { 1 } else { 0 })?;
         output.write_u8(if self.disabled { 1 } else { 0 })?;
         output.write_i16::<LE>(self.tile_req.0)?;
@@ -597,8 +900,8 @@ impl StaticUnitTypeAttributes {
         output.write_i16::<LE>(self.center_tile_req.1)?;
         output.write_f32::<LE>(self.construction_radius.0)?;
         output.write_f32::<LE>(self.construction_radius.1)?;
-        output.write_u8(if self.elevation_flag { 1 } else { 0 })?;
-        output.write_u8(if self.fog_flag { 1 } else { 0 })?;
+        output.write_u8(self.elevation_flag)?;
+        output.write_u8(self.fog_flag)?;
         output.write_u16::<LE>(self.terrain_restriction_id)?;
         output.write_u8(self.movement_type)?;
         output.write_u16::<LE>(self.attribute_max_amount)?;
@@ -610,45 +913,56 @@ impl StaticUnitTypeAttributes {
         output.write_u8(self.unit_level)?;
         output.write_f32::<LE>(self.multiple_attribute_mod)?;
         output.write_u8(self.map_color)?;
-        output.write_u32::<LE>((&self.help_string_id).try_into().unwrap())?;
+        output.write_u32::<LE>((&self.help_string_id).try_convert()?)?;
         output.write_u32::<LE>(self.help_page_id)?;
         output.write_u32::<LE>(self.hotkey_id)?;
         output.write_u8(if self.recyclable { 1 } else { 0 })?;
         output.write_u8(if self.track_as_resource { 1 } else { 0 })?;
-        output.write_u8(if self.create_doppleganger { 1 } else { 0 })?;
+        output.write_u8(self.create_doppleganger)?;
         output.write_u8(self.resource_group)?;
         output.write_u8(self.occlusion_mask)?;
         output.write_u8(self.obstruction_type)?;
         output.write_u8(self.selection_shape)?;
-        output.write_u32::<LE>(self.object_flags)?;
+        if version >= 11.55 {
+            output.write_u32::<LE>(self.object_flags)?;
+        }
         output.write_u8(self.civilization)?;
         output.write_u8(self.attribute_piece)?;
         output.write_f32::<LE>(self.outline_radius.0)?;
         output.write_f32::<LE>(self.outline_radius.1)?;
         output.write_f32::<LE>(self.outline_radius.2)?;
-        for index in 0..self.attributes.capacity() {
-            match self.attributes.get(index) {
-                Some(attr) => attr.write_to(&mut output)?,
-                None => UnitAttribute::write_empty(&mut output)?,
+        let attribute_slots = self.unknown_attribute_slots.as_slice();
+        if attribute_slots.len() == ATTRIBUTE_SLOTS_LEN
+            && read_attributes(attribute_slots)? == self.attributes
+        {
+            output.write_all(attribute_slots)?;
+        } else {
+            for index in 0..self.attributes.capacity() {
+                match self.attributes.get(index) {
+                    Some(attr) => attr.write_to(&mut output)?,
+                    None => UnitAttribute::write_empty(&mut output)?,
+                }
             }
         }
-        output.write_u8(self.damage_sprites.len().try_into().unwrap())?;
+        output.write_u8(self.damage_sprites.len().try_convert()?)?;
         for sprite in &self.damage_sprites {
             sprite.write_to(&mut output)?;
         }
-        output.write_i16::<LE>(
-            self.selected_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.death_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.selected_sound)?;
+        write_opt_i16(&mut output, self.death_sound)?;
         output.write_u8(self.attack_reaction)?;
         output.write_u8(self.convert_terrain_flag)?;
         output.write_all(self.name.as_bytes())?;
+        output.write_all(self.unknown_name_tail.as_slice())?;
+        if swgb {
+            let name2 = self.name2.as_deref().unwrap_or_default();
+            let name2_tail = self.unknown_name2_tail.as_slice();
+            output.write_u16::<LE>((name2.len() + name2_tail.len()) as u16)?;
+            output.write_all(name2.as_bytes())?;
+            output.write_all(name2_tail)?;
+            write_opt_u16(&mut output, self.unit_line)?;
+            write_opt_i8(&mut output, self.min_tech_level)?;
+        }
         output.write_u16::<LE>(self.copy_id)?;
         output.write_u16::<LE>(self.unit_group)?;
         Ok(())
@@ -656,25 +970,27 @@ impl StaticUnitTypeAttributes {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnimatedUnitTypeAttributes {
     pub speed: f32,
 }
 
 impl AnimatedUnitTypeAttributes {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         Ok(Self {
             speed: input.read_f32::<LE>()?,
         })
     }
 
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
         output.write_f32::<LE>(self.speed)?;
         Ok(())
     }
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MovingUnitTypeAttributes {
     pub move_sprite: Option<SpriteID>,
     pub run_sprite: Option<SpriteID>,
@@ -692,7 +1008,7 @@ pub struct MovingUnitTypeAttributes {
 }
 
 impl MovingUnitTypeAttributes {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         Ok(MovingUnitTypeAttributes {
             move_sprite: read_opt_u16(&mut input)?,
             run_sprite: read_opt_u16(&mut input)?,
@@ -711,24 +1027,12 @@ impl MovingUnitTypeAttributes {
     }
 
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
-        output.write_i16::<LE>(
-            self.move_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.run_sprite
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
+        write_opt_i16(&mut output, self.move_sprite)?;
+        write_opt_i16(&mut output, self.run_sprite)?;
         output.write_f32::<LE>(self.turn_speed)?;
         output.write_u8(self.size_class)?;
-        output.write_i16::<LE>(
-            self.trailing_unit
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.trailing_unit)?;
         output.write_u8(self.trailing_options)?;
         output.write_f32::<LE>(self.trailing_spacing)?;
         output.write_u8(self.move_algorithm)?;
@@ -742,6 +1046,7 @@ impl MovingUnitTypeAttributes {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ActionUnitTypeAttributes {
     pub default_task: Option<u16>,
     pub search_radius: f32,
@@ -758,7 +1063,7 @@ pub struct ActionUnitTypeAttributes {
 }
 
 impl ActionUnitTypeAttributes {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         Ok(ActionUnitTypeAttributes {
             default_task: read_opt_u16(&mut input)?,
             search_radius: input.read_f32::<LE>()?,
@@ -774,65 +1079,168 @@ impl ActionUnitTypeAttributes {
     }
 
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
-        output.write_i16::<LE>(
-            self.default_task
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
+        write_opt_i16(&mut output, self.default_task)?;
         output.write_f32::<LE>(self.search_radius)?;
         output.write_f32::<LE>(self.work_rate)?;
-        output.write_i16::<LE>(
-            self.drop_site
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.backup_drop_site
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.drop_site)?;
+        write_opt_i16(&mut output, self.backup_drop_site)?;
         output.write_u8(self.task_by_group)?;
-        output.write_i16::<LE>(
-            self.command_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_i16::<LE>(
-            self.move_sound
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i16(&mut output, self.command_sound)?;
+        write_opt_i16(&mut output, self.move_sound)?;
         output.write_u8(self.run_pattern)?;
         Ok(())
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+/// The class of an attack or armor value.
+///
+/// A unit only takes damage from attack classes that it has armor for. The numeric values are
+/// the ones used by Age of Empires II: The Conquerors and later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArmorClass {
+    /// Infantry units.
+    Infantry,
+    /// Turtle ships.
+    TurtleShips,
+    /// Base pierce attack and armor.
+    Pierce,
+    /// Base melee attack and armor.
+    Melee,
+    /// War elephants.
+    WarElephants,
+    /// Cavalry units. Attacks of this class are anti-cavalry bonuses, as on spearmen.
+    Cavalry,
+    /// All buildings, except for ports.
+    Buildings,
+    /// Stone defenses, such as towers and castles.
+    StoneDefense,
+    /// Foot archers.
+    Archers,
+    /// Ships, camels and saboteurs.
+    Ships,
+    /// Battering rams.
+    Rams,
+    /// Trees.
+    Trees,
+    /// Unique units.
+    UniqueUnits,
+    /// Siege weapons.
+    SiegeWeapons,
+    /// Standard buildings.
+    StandardBuildings,
+    /// Walls and gates.
+    WallsAndGates,
+    /// Boars.
+    Boars,
+    /// Monks.
+    Monks,
+    /// Castles.
+    Castle,
+    /// Spearmen.
+    Spearmen,
+    /// Cavalry archers.
+    CavalryArchers,
+    /// Eagle warriors.
+    EagleWarriors,
+    /// Camels.
+    Camels,
+    /// Some unknown value, either because of a modded data file or some new unsupported game
+    /// feature.
+    Unknown(u16),
+}
+
+impl From<u16> for ArmorClass {
+    fn from(n: u16) -> Self {
+        match n {
+            1 => ArmorClass::Infantry,
+            2 => ArmorClass::TurtleShips,
+            3 => ArmorClass::Pierce,
+            4 => ArmorClass::Melee,
+            5 => ArmorClass::WarElephants,
+            8 => ArmorClass::Cavalry,
+            11 => ArmorClass::Buildings,
+            13 => ArmorClass::StoneDefense,
+            15 => ArmorClass::Archers,
+            16 => ArmorClass::Ships,
+            17 => ArmorClass::Rams,
+            18 => ArmorClass::Trees,
+            19 => ArmorClass::UniqueUnits,
+            20 => ArmorClass::SiegeWeapons,
+            21 => ArmorClass::StandardBuildings,
+            22 => ArmorClass::WallsAndGates,
+            24 => ArmorClass::Boars,
+            25 => ArmorClass::Monks,
+            26 => ArmorClass::Castle,
+            27 => ArmorClass::Spearmen,
+            28 => ArmorClass::CavalryArchers,
+            29 => ArmorClass::EagleWarriors,
+            30 => ArmorClass::Camels,
+            n => ArmorClass::Unknown(n),
+        }
+    }
+}
+
+impl From<ArmorClass> for u16 {
+    fn from(class: ArmorClass) -> Self {
+        match class {
+            ArmorClass::Infantry => 1,
+            ArmorClass::TurtleShips => 2,
+            ArmorClass::Pierce => 3,
+            ArmorClass::Melee => 4,
+            ArmorClass::WarElephants => 5,
+            ArmorClass::Cavalry => 8,
+            ArmorClass::Buildings => 11,
+            ArmorClass::StoneDefense => 13,
+            ArmorClass::Archers => 15,
+            ArmorClass::Ships => 16,
+            ArmorClass::Rams => 17,
+            ArmorClass::Trees => 18,
+            ArmorClass::UniqueUnits => 19,
+            ArmorClass::SiegeWeapons => 20,
+            ArmorClass::StandardBuildings => 21,
+            ArmorClass::WallsAndGates => 22,
+            ArmorClass::Boars => 24,
+            ArmorClass::Monks => 25,
+            ArmorClass::Castle => 26,
+            ArmorClass::Spearmen => 27,
+            ArmorClass::CavalryArchers => 28,
+            ArmorClass::EagleWarriors => 29,
+            ArmorClass::Camels => 30,
+            ArmorClass::Unknown(n) => n,
+        }
+    }
+}
+
+/// An attack or armor value of a specific class.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WeaponInfo {
-    pub weapon_type: i16,
+    pub weapon_type: ArmorClass,
     pub value: i16,
 }
 
 impl WeaponInfo {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(Self {
-            weapon_type: input.read_i16::<LE>()?,
+            weapon_type: input.read_u16::<LE>()?.into(),
             value: input.read_i16::<LE>()?,
         })
     }
     pub fn write_to(self, mut output: impl Write) -> Result<()> {
-        output.write_i16::<LE>(self.weapon_type)?;
+        output.write_u16::<LE>(self.weapon_type.into())?;
         output.write_i16::<LE>(self.value)?;
         Ok(())
     }
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BaseCombatUnitTypeAttributes {
     pub base_armor: u16,
-    pub weapons: Vec<WeaponInfo>,
-    pub armors: Vec<WeaponInfo>,
+    pub weapons: SmallVec<[WeaponInfo; 4]>,
+    pub armors: SmallVec<[WeaponInfo; 4]>,
     pub defense_terrain_bonus: Option<u16>,
     pub weapon_range_max: f32,
     pub area_effect_range: f32,
@@ -853,7 +1261,41 @@ pub struct BaseCombatUnitTypeAttributes {
 }
 
 impl BaseCombatUnitTypeAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    /// Get this unit type's attack value for an armor class.
+    pub fn attack(&self, class: ArmorClass) -> Option<i16> {
+        self.weapons
+            .iter()
+            .find(|weapon| weapon.weapon_type == class)
+            .map(|weapon| weapon.value)
+    }
+
+    /// Get this unit type's armor value for an armor class.
+    pub fn armor(&self, class: ArmorClass) -> Option<i16> {
+        self.armors
+            .iter()
+            .find(|armor| armor.weapon_type == class)
+            .map(|armor| armor.value)
+    }
+
+    /// Calculate the damage that a single attack of this unit type does to the `target` unit
+    /// type, before terrain and elevation modifiers.
+    ///
+    /// Only attack classes that the target has armor for are counted. An attack always does at
+    /// least 1 damage.
+    pub fn damage_to(&self, target: &BaseCombatUnitTypeAttributes) -> i16 {
+        let damage: i32 = self
+            .weapons
+            .iter()
+            .filter_map(|weapon| {
+                let armor = target.armor(weapon.weapon_type)?;
+                Some(i32::from(weapon.value) - i32::from(armor))
+            })
+            .map(|damage| damage.max(0))
+            .sum();
+        damage.clamp(1, i32::from(i16::MAX)) as i16
+    }
+
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = BaseCombatUnitTypeAttributes {
             base_armor: if version < 11.52 {
                 input.read_u8()?.into()
@@ -895,9 +1337,9 @@ impl BaseCombatUnitTypeAttributes {
     }
 
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, version: f32) -> Result<()> {
+    pub fn write_to(&self, mut output: impl Write, version: SaveVersion) -> Result<()> {
         if version < 11.52 {
-            output.write_u8(self.base_armor.try_into().unwrap())?;
+            output.write_u8(self.base_armor.try_convert()?)?;
         } else {
             output.write_u16::<LE>(self.base_armor)?;
         };
@@ -909,11 +1351,11 @@ impl BaseCombatUnitTypeAttributes {
         for armor in &self.armors {
             armor.write_to(&mut output)?;
         }
-        output.write_u16::<LE>(self.defense_terrain_bonus.unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.defense_terrain_bonus)?;
         output.write_f32::<LE>(self.weapon_range_max)?;
         output.write_f32::<LE>(self.area_effect_range)?;
         output.write_f32::<LE>(self.attack_speed)?;
-        output.write_u16::<LE>(self.missile_id.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.missile_id)?;
         output.write_i16::<LE>(self.base_hit_chance)?;
         output.write_i8(self.break_off_combat)?;
         output.write_i16::<LE>(self.frame_delay)?;
@@ -923,7 +1365,7 @@ impl BaseCombatUnitTypeAttributes {
         output.write_i8(self.blast_level_offense)?;
         output.write_f32::<LE>(self.weapon_range_min)?;
         output.write_f32::<LE>(self.missed_missile_spread)?;
-        output.write_u16::<LE>(self.fight_sprite.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.fight_sprite)?;
         output.write_i16::<LE>(self.displayed_armor)?;
         output.write_i16::<LE>(self.displayed_attack)?;
         output.write_f32::<LE>(self.displayed_range)?;
@@ -933,6 +1375,7 @@ impl BaseCombatUnitTypeAttributes {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MissileUnitTypeAttributes {
     pub missile_type: u8,
     pub targetting_type: u8,
@@ -944,7 +1387,7 @@ pub struct MissileUnitTypeAttributes {
 
 impl MissileUnitTypeAttributes {
     /// Read this unit type from an input stream.
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         Ok(MissileUnitTypeAttributes {
             missile_type: input.read_u8()?,
             targetting_type: input.read_u8()?,
@@ -956,7 +1399,7 @@ impl MissileUnitTypeAttributes {
     }
 
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
         output.write_u8(self.missile_type)?;
         output.write_u8(self.targetting_type)?;
         output.write_u8(self.missile_hit_info)?;
@@ -968,10 +1411,11 @@ impl MissileUnitTypeAttributes {
 }
 
 /// Resource cost for a unit.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AttributeCost {
     /// The player attribute type to give/take.
-    pub attribute_type: i16,
+    pub attribute_type: AttributeID,
     /// The amount of that attribute that should be taken/given.
     pub amount: i16,
     /// Flag determining how and when this cost is counted.
@@ -981,25 +1425,64 @@ pub struct AttributeCost {
 }
 
 impl AttributeCost {
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
-        let cost = Self {
-            attribute_type: input.read_i16::<LE>()?,
-            amount: input.read_i16::<LE>()?,
-            flag: input.read_u8()?,
-        };
+    /// Read a cost slot from an input stream. Returns `None` if the slot is unused.
+    pub fn read_from(mut input: impl Read) -> Result<Option<Self>> {
+        let attribute_type = input.read_i16::<LE>()?;
+        let amount = input.read_i16::<LE>()?;
+        let flag = input.read_u8()?;
         let _padding = input.read_u8()?;
-        Ok(cost)
+        Ok(AttributeID::try_from(attribute_type)
+            .ok()
+            .map(|attribute_type| Self {
+                attribute_type,
+                amount,
+                flag,
+            }))
     }
+
     pub fn write_to(self, mut output: impl Write) -> Result<()> {
-        output.write_i16::<LE>(self.attribute_type)?;
+        output.write_i16::<LE>(self.attribute_type.try_convert()?)?;
         output.write_i16::<LE>(self.amount)?;
         output.write_u8(self.flag)?;
         output.write_u8(0)?;
         Ok(())
     }
+
+    fn write_empty(mut output: impl Write) -> Result<()> {
+        output.write_i16::<LE>(-1)?;
+        output.write_i16::<LE>(0)?;
+        output.write_u8(0)?;
+        output.write_u8(0)?;
+        Ok(())
+    }
+}
+
+/// Size of the three cost slots of a unit type.
+const COST_SLOTS_LEN: usize = 3 * 6;
+
+/// Interpret the garrison sprite value of a unit type. All negative values mean there is no
+/// garrison sprite.
+fn read_garrison_sprite(value: i32) -> Result<Option<SpriteID>> {
+    if value < 0 {
+        Ok(None)
+    } else {
+        value.try_convert().map(Some)
+    }
+}
+
+/// Read the three cost slots of a unit type, skipping unused slots.
+fn read_costs(mut input: impl Read) -> Result<ArrayVec<AttributeCost, 3>> {
+    let mut costs = ArrayVec::new();
+    for _ in 0..3 {
+        if let Some(cost) = AttributeCost::read_from(&mut input)? {
+            costs.push(cost);
+        }
+    }
+    Ok(costs)
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CombatUnitTypeAttributes {
     /// The costs of creating a unit of this type.
     pub costs: ArrayVec<AttributeCost, 3>,
@@ -1011,6 +1494,8 @@ pub struct CombatUnitTypeAttributes {
     pub create_button: i8,
     pub rear_attack_modifier: f32,
     pub flank_attack_modifier: f32,
+    /// The kind of creatable unit. Its meaning is not fully known.
+    pub creatable_type: u8,
     /// Is this unit a hero unit?
     ///
     /// TODO what is special about hero units? Does it just opt into the healing behaviour?
@@ -1024,33 +1509,33 @@ pub struct CombatUnitTypeAttributes {
     pub special_attack_sprite: Option<SpriteID>,
     pub special_attack_flag: i8,
     pub displayed_pierce_armor: i16,
+    /// The three cost slots as stored in the data file. Used to write unchanged costs back in
+    /// their original slots.
+    pub unknown_cost_slots: RawBytes,
+    /// The garrison sprite as stored in the data file, if it is negative but not -1.
+    pub unknown_garrison_sprite: RawBytes,
 }
 
 impl CombatUnitTypeAttributes {
     /// Read this unit type from an input stream.
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         let mut attrs = Self::default();
-        for _ in 0..3 {
-            let attr = AttributeCost::read_from(&mut input)?;
-            if attr.attribute_type >= 0 {
-                attrs.costs.push(attr);
-            }
-        }
+        let mut cost_slots = [0; COST_SLOTS_LEN];
+        input.read_exact(&mut cost_slots)?;
+        attrs.costs = read_costs(&cost_slots[..])?;
+        attrs.unknown_cost_slots = RawBytes::new(&cost_slots);
         attrs.create_time = input.read_u16::<LE>()?;
         attrs.create_at_building = read_opt_u16(&mut input)?;
         attrs.create_button = input.read_i8()?;
         attrs.rear_attack_modifier = input.read_f32::<LE>()?;
         attrs.flank_attack_modifier = input.read_f32::<LE>()?;
-        let _tribe_unit_type = input.read_u8()?;
+        attrs.creatable_type = input.read_u8()?;
         attrs.hero_flag = input.read_u8()?;
-        attrs.garrison_sprite = {
-            let n = input.read_i32::<LE>()?;
-            if n < 0 {
-                None
-            } else {
-                Some(n.try_into().unwrap())
-            }
-        };
+        let garrison_sprite = input.read_i32::<LE>()?;
+        attrs.garrison_sprite = read_garrison_sprite(garrison_sprite)?;
+        if garrison_sprite < -1 {
+            attrs.unknown_garrison_sprite = RawBytes::new(&garrison_sprite.to_le_bytes());
+        }
         attrs.volley_fire_amount = input.read_f32::<LE>()?;
         attrs.max_attacks_in_volley = input.read_i8()?;
         attrs.volley_spread = (input.read_f32::<LE>()?, input.read_f32::<LE>()?);
@@ -1063,33 +1548,39 @@ impl CombatUnitTypeAttributes {
     }
 
     /// Write this unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
-        for i in 0..3 {
-            match self.costs.get(i) {
-                Some(cost) => cost.write_to(&mut output)?,
-                None => AttributeCost {
-                    attribute_type: -1,
-                    amount: 0,
-                    flag: 0,
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
+        let cost_slots = self.unknown_cost_slots.as_slice();
+        if cost_slots.len() == COST_SLOTS_LEN && read_costs(cost_slots)? == self.costs {
+            output.write_all(cost_slots)?;
+        } else {
+            for i in 0..3 {
+                match self.costs.get(i) {
+                    Some(cost) => cost.write_to(&mut output)?,
+                    None => AttributeCost::write_empty(&mut output)?,
                 }
-                .write_to(&mut output)?,
             }
         }
         output.write_u16::<LE>(self.create_time)?;
-        output.write_u16::<LE>(self.create_at_building.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.create_at_building)?;
         output.write_i8(self.create_button)?;
         output.write_f32::<LE>(self.rear_attack_modifier)?;
         output.write_f32::<LE>(self.flank_attack_modifier)?;
-        output.write_u8(0)?;
+        output.write_u8(self.creatable_type)?;
         output.write_u8(self.hero_flag)?;
-        output.write_u32::<LE>(self.garrison_sprite.map_into().unwrap_or(0xFFFF_FFFF))?;
+        let garrison_sprite = self.unknown_garrison_sprite.as_slice();
+        match garrison_sprite.try_into().map(i32::from_le_bytes) {
+            Ok(raw) if read_garrison_sprite(raw).ok() == Some(self.garrison_sprite) => {
+                output.write_i32::<LE>(raw)?
+            }
+            _ => output.write_u32::<LE>(self.garrison_sprite.map_into().unwrap_or(0xFFFF_FFFF))?,
+        }
         output.write_f32::<LE>(self.volley_fire_amount)?;
         output.write_i8(self.max_attacks_in_volley)?;
         output.write_f32::<LE>(self.volley_spread.0)?;
         output.write_f32::<LE>(self.volley_spread.1)?;
         output.write_f32::<LE>(self.volley_start_spread_adjustment)?;
-        output.write_u32::<LE>(self.volley_missile.map_into().unwrap_or(0xFFFF_FFFF))?;
-        output.write_u32::<LE>(self.special_attack_sprite.map_into().unwrap_or(0xFFFF_FFFF))?;
+        write_opt_u32(&mut output, self.volley_missile)?;
+        write_opt_u32(&mut output, self.special_attack_sprite)?;
         output.write_i8(self.special_attack_flag)?;
         output.write_i16::<LE>(self.displayed_pierce_armor)?;
         Ok(())
@@ -1099,7 +1590,8 @@ impl CombatUnitTypeAttributes {
 /// A linked, or "Annex" building. These allow for buildings made up of multiple pieces
 /// with different behaviour, like the Town Centre with some walkable tiles and some non-walkable
 /// tiles.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinkedBuilding {
     /// Unit type ID for this linked building.
     pub unit_id: UnitTypeID,
@@ -1131,8 +1623,24 @@ impl LinkedBuilding {
     }
 }
 
+/// Size of the four linked building slots of a building.
+const LINKED_BUILDING_SLOTS_LEN: usize = 4 * 10;
+
+/// Read the four linked building slots of a building, skipping unused slots.
+fn read_linked_buildings(mut input: impl Read) -> Result<ArrayVec<LinkedBuilding, 4>> {
+    let mut linked_buildings = ArrayVec::new();
+    for _ in 0..linked_buildings.capacity() {
+        let link = LinkedBuilding::read_from(&mut input)?;
+        if link.unit_id != 0xFFFF.into() {
+            linked_buildings.push(link);
+        }
+    }
+    Ok(linked_buildings)
+}
+
 /// Unit type class for buildings.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BuildingUnitTypeAttributes {
     /// Sprite to use during construction.
     pub construction_sprite: Option<SpriteID>,
@@ -1166,11 +1674,14 @@ pub struct BuildingUnitTypeAttributes {
     pub garrison_repair_rate: f32,
     pub salvage_unit: Option<UnitTypeID>,
     pub salvage_attributes: ArrayVec<i8, 6>,
+    /// The four linked building slots as stored in the data file. Used to write unchanged
+    /// linked buildings back in their original slots.
+    pub unknown_linked_building_slots: RawBytes,
 }
 
 impl BuildingUnitTypeAttributes {
     /// Read this unit type from an input stream.
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = BuildingUnitTypeAttributes {
             construction_sprite: read_opt_u16(&mut input)?,
             snow_sprite: if version < 11.53 {
@@ -1188,12 +1699,10 @@ impl BuildingUnitTypeAttributes {
             can_burn: input.read_u8()? != 0,
             ..Default::default()
         };
-        for _ in 0..attrs.linked_buildings.capacity() {
-            let link = LinkedBuilding::read_from(&mut input)?;
-            if link.unit_id != 0xFFFF.into() {
-                attrs.linked_buildings.push(link);
-            }
-        }
+        let mut linked_building_slots = [0; LINKED_BUILDING_SLOTS_LEN];
+        input.read_exact(&mut linked_building_slots)?;
+        attrs.linked_buildings = read_linked_buildings(&linked_building_slots[..])?;
+        attrs.unknown_linked_building_slots = RawBytes::new(&linked_building_slots);
 
         attrs.construction_unit = read_opt_u16(&mut input)?;
         attrs.transform_unit = read_opt_u16(&mut input)?;
@@ -1211,33 +1720,40 @@ impl BuildingUnitTypeAttributes {
     }
 
     /// Write the unit type to an output stream.
-    pub fn write_to(&self, mut output: impl Write, version: f32) -> Result<()> {
-        output.write_u16::<LE>(self.construction_sprite.map_into().unwrap_or(0xFFFF))?;
+    pub fn write_to(&self, mut output: impl Write, version: SaveVersion) -> Result<()> {
+        write_opt_u16(&mut output, self.construction_sprite)?;
         if version >= 11.53 {
-            output.write_u16::<LE>(self.snow_sprite.map_into().unwrap_or(0xFFFF))?;
+            write_opt_u16(&mut output, self.snow_sprite)?;
         }
         output.write_u8(self.connect_flag)?;
         output.write_i16::<LE>(self.facet)?;
         output.write_u8(if self.destroy_on_build { 1 } else { 0 })?;
-        output.write_u16::<LE>(self.on_build_make_unit.map_into().unwrap_or(0xFFFF))?;
-        output.write_u16::<LE>(self.on_build_make_tile.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.on_build_make_unit)?;
+        write_opt_u16(&mut output, self.on_build_make_tile)?;
         output.write_i16::<LE>(self.on_build_make_overlay)?;
-        output.write_u16::<LE>(self.on_build_make_tech.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.on_build_make_tech)?;
         output.write_u8(if self.can_burn { 1 } else { 0 })?;
-        for i in 0..self.linked_buildings.capacity() {
-            match self.linked_buildings.get(i) {
-                Some(link) => link.write_to(&mut output)?,
-                None => LinkedBuilding::write_empty(&mut output)?,
+        let linked_building_slots = self.unknown_linked_building_slots.as_slice();
+        if linked_building_slots.len() == LINKED_BUILDING_SLOTS_LEN
+            && read_linked_buildings(linked_building_slots)? == self.linked_buildings
+        {
+            output.write_all(linked_building_slots)?;
+        } else {
+            for i in 0..self.linked_buildings.capacity() {
+                match self.linked_buildings.get(i) {
+                    Some(link) => link.write_to(&mut output)?,
+                    None => LinkedBuilding::write_empty(&mut output)?,
+                }
             }
         }
-        output.write_u16::<LE>(self.construction_unit.map_into().unwrap_or(0xFFFF))?;
-        output.write_u16::<LE>(self.transform_unit.map_into().unwrap_or(0xFFFF))?;
-        output.write_u16::<LE>(self.transform_sound.map_into().unwrap_or(0xFFFF))?;
-        output.write_u16::<LE>(self.construction_sound.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.construction_unit)?;
+        write_opt_u16(&mut output, self.transform_unit)?;
+        write_opt_u16(&mut output, self.transform_sound)?;
+        write_opt_u16(&mut output, self.construction_sound)?;
         output.write_i8(self.garrison_type)?;
         output.write_f32::<LE>(self.garrison_heal_rate)?;
         output.write_f32::<LE>(self.garrison_repair_rate)?;
-        output.write_u16::<LE>(self.salvage_unit.map_into().unwrap_or(0xFFFF))?;
+        write_opt_u16(&mut output, self.salvage_unit)?;
         for attr in &self.salvage_attributes {
             output.write_i8(*attr)?;
         }
@@ -1247,10 +1763,208 @@ impl BuildingUnitTypeAttributes {
 
 fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) -> Result<()> {
     output.write_i16::<LE>(if let Some(key) = opt_key {
-        key.try_into()
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+        key.try_into().map_err(io::Error::other)?
     } else {
         -1
     })?;
     Ok(())
 }
+
+fn read_unit_name(input: impl Read, len: u16) -> Result<String> {
+    read_unit_name_with_tail(input, len).map(|(name, _tail)| name)
+}
+
+/// Read a unit name, and keep the bytes following its NUL terminator.
+fn read_unit_name_with_tail(mut input: impl Read, len: u16) -> Result<(String, RawBytes)> {
+    // TODO use not-UTF8 for the name
+    let mut bytes = vec![0; usize::from(len)];
+    input.read_exact(&mut bytes)?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let tail = RawBytes::new(&bytes[end..]);
+    bytes.truncate(end);
+    Ok((decode_str_with_encoding(&bytes, UTF_8)?, tail))
+}
+
+/// A unit line groups unit types that upgrade into each other. Only used by Star Wars: Galactic
+/// Battlegrounds.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnitLine {
+    /// ID of this unit line.
+    pub id: u16,
+    /// Internal name of this unit line.
+    name: String,
+    /// The unit types in this line.
+    pub unit_ids: Vec<UnitTypeID>,
+}
+
+impl UnitLine {
+    /// Get the internal name of this unit line.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Read a unit line from an input stream.
+    pub fn read_from(mut input: impl Read) -> Result<Self> {
+        let id = input.read_u16::<LE>()?;
+        let name_len = input.read_u16::<LE>()?;
+        let name = read_unit_name(&mut input, name_len)?;
+        let num_units = input.read_u16::<LE>()?;
+        let mut unit_ids = Vec::with_capacity(usize::from(num_units));
+        for _ in 0..num_units {
+            unit_ids.push(input.read_u16::<LE>()?.into());
+        }
+        Ok(Self { id, name, unit_ids })
+    }
+
+    /// Write this unit line to an output stream.
+    pub fn write_to(&self, mut output: impl Write) -> Result<()> {
+        output.write_u16::<LE>(self.id)?;
+        output.write_u16::<LE>(self.name.len().try_convert()?)?;
+        output.write_all(self.name.as_bytes())?;
+        output.write_u16::<LE>(self.unit_ids.len().try_convert()?)?;
+        for id in &self.unit_ids {
+            output.write_u16::<LE>((*id).into())?;
+        }
+        Ok(())
+    }
+}
+
+/// Builder for adding a new unit type to a data file.
+///
+/// ```rust,no_run
+/// # use genie_dat::DatFile;
+/// # use genie_dat::unit_type::{UnitBaseClass, UnitTypeBuilder};
+/// # fn add_hero(dat: &mut DatFile) {
+/// let hero_id = UnitTypeBuilder::new(UnitBaseClass::Combat)
+///     .name("HERO")
+///     .hp(300)
+///     .train_location(82u16)
+///     .insert(dat);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnitTypeBuilder {
+    unit_type: UnitType,
+    civilizations: Option<Vec<CivilizationID>>,
+    train_location: Option<UnitTypeID>,
+    requires_tech: Option<TechID>,
+}
+
+impl UnitTypeBuilder {
+    /// Start building a unit type with the given base class.
+    pub fn new(unit_base_class: UnitBaseClass) -> Self {
+        let mut unit_type = UnitType::new(unit_base_class);
+        unit_type.static_.hp = 1;
+        unit_type.static_.los = 1.0;
+        unit_type.static_.radius = (0.5, 0.5, 0.0);
+        unit_type.static_.enabled = true;
+        Self::from_template(unit_type)
+    }
+
+    /// Start building a unit type that is a copy of an existing unit type.
+    pub fn from_template(unit_type: UnitType) -> Self {
+        Self {
+            unit_type,
+            civilizations: None,
+            train_location: None,
+            requires_tech: None,
+        }
+    }
+
+    /// Set the internal name of the unit type.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.unit_type.static_.set_name(name);
+        self
+    }
+
+    /// Set the hit points of the unit type.
+    pub fn hp(mut self, hp: u16) -> Self {
+        self.unit_type.static_.hp = hp;
+        self
+    }
+
+    /// Set the class of the unit type.
+    pub fn unit_class(mut self, unit_class: UnitClass) -> Self {
+        self.unit_type.static_.unit_class = unit_class;
+        self
+    }
+
+    /// Only add the unit type to these civilizations. By default, it is added to all
+    /// civilizations.
+    pub fn civilizations(mut self, civs: impl IntoIterator<Item = CivilizationID>) -> Self {
+        self.civilizations = Some(civs.into_iter().collect());
+        self
+    }
+
+    /// Set the building where this unit type is trained. This also adds the unit type to the tech
+    /// tree.
+    pub fn train_location(mut self, building: impl Into<UnitTypeID>) -> Self {
+        let building = building.into();
+        if let Some(combat) = &mut self.unit_type.combat {
+            combat.create_at_building = Some(building);
+        }
+        self.train_location = Some(building);
+        self
+    }
+
+    /// Set the tech that must be researched before the unit type can be trained.
+    pub fn requires_tech(mut self, tech: impl Into<TechID>) -> Self {
+        self.requires_tech = Some(tech.into());
+        self
+    }
+
+    /// Make arbitrary changes to the unit type.
+    pub fn with(mut self, f: impl FnOnce(&mut UnitType)) -> Self {
+        f(&mut self.unit_type);
+        self
+    }
+
+    /// Add the unit type to the data file, and return its newly allocated ID.
+    ///
+    /// # Panics
+    /// This function panics if the data file already has the maximum number of unit types.
+    pub fn insert(self, dat: &mut DatFile) -> UnitTypeID {
+        let index = dat
+            .civilizations
+            .iter()
+            .map(|civ| civ.unit_types().len())
+            .max()
+            .unwrap_or(0);
+        let id = UnitTypeID::from(u16::try_from(index).expect("too many unit types"));
+
+        let mut unit_type = self.unit_type;
+        unit_type.static_.id = id;
+        for (civ_index, civ) in dat.civilizations.iter_mut().enumerate() {
+            let included = match &self.civilizations {
+                Some(list) => list.iter().any(|&civ_id| usize::from(civ_id) == civ_index),
+                None => true,
+            };
+            let unit_types = civ.unit_types_mut();
+            unit_types.resize(index, None);
+            unit_types.push(Some(unit_type.clone()).filter(|_| included));
+        }
+
+        if let Some(building) = self.train_location {
+            dat.tech_tree.add_unit(id, building, self.requires_tech);
+        }
+        id
+    }
+}
+
+genie_support::impl_versioned! {
+    UnitType: SaveVersion => std::io::Error;
+    UnitAttribute => std::io::Error;
+    DamageSprite => std::io::Error;
+    StaticUnitTypeAttributes: SaveVersion => std::io::Error;
+    AnimatedUnitTypeAttributes: SaveVersion => std::io::Error;
+    MovingUnitTypeAttributes: SaveVersion => std::io::Error;
+    ActionUnitTypeAttributes: SaveVersion => std::io::Error;
+    WeaponInfo => std::io::Error;
+    BaseCombatUnitTypeAttributes: SaveVersion => std::io::Error;
+    MissileUnitTypeAttributes: SaveVersion => std::io::Error;
+    CombatUnitTypeAttributes: SaveVersion => std::io::Error;
+    LinkedBuilding => std::io::Error;
+    BuildingUnitTypeAttributes: SaveVersion => std::io::Error;
+    UnitLine => std::io::Error;
+}
@@ -0,0 +1,210 @@
+//! Data file values that are not interpreted by this crate.
+//!
+//! Data files contain padding, pointers left over from the game's memory layout, garbage bytes
+//! following the NUL terminator of fixed-size strings, and values whose meaning is unknown. These
+//! are only kept if the `raw-fields` feature is enabled, so that reading and writing a data file
+//! does not lose any information, and so they can be studied. Without the feature, zeroes are
+//! written instead.
+
+use crate::FileVersion;
+use byteorder::{WriteBytesExt, LE};
+#[cfg(feature = "raw-fields")]
+use std::cell::Cell;
+use std::convert::TryInto;
+use std::io::{Read, Result, Write};
+
+#[cfg(feature = "raw-fields")]
+thread_local! {
+    /// Set while stored bytes should be ignored, see [`without_raw_bytes`].
+    static IGNORE_RAW_BYTES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Call a function as if no bytes were stored in any [`RawBytes`], so that data written by it is
+/// the same whether or not the `raw-fields` feature is enabled.
+pub(crate) fn without_raw_bytes<T>(f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "raw-fields")]
+    let _restore = {
+        struct Restore(bool);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                IGNORE_RAW_BYTES.with(|ignore| ignore.set(self.0));
+            }
+        }
+        Restore(IGNORE_RAW_BYTES.with(|ignore| ignore.replace(true)))
+    };
+    f()
+}
+
+/// Bytes that are not interpreted by this crate.
+///
+/// The bytes are only stored if the `raw-fields` feature is enabled. Otherwise, this is always
+/// empty.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct RawBytes(#[cfg(feature = "raw-fields")] Vec<u8>);
+
+impl RawBytes {
+    /// Keep some bytes, if the `raw-fields` feature is enabled.
+    #[cfg_attr(not(feature = "raw-fields"), allow(unused_variables))]
+    pub fn new(bytes: &[u8]) -> Self {
+        #[cfg(feature = "raw-fields")]
+        return Self(bytes.to_vec());
+        #[cfg(not(feature = "raw-fields"))]
+        return Self();
+    }
+
+    /// Get the stored bytes.
+    pub fn as_slice(&self) -> &[u8] {
+        #[cfg(feature = "raw-fields")]
+        return if IGNORE_RAW_BYTES.with(Cell::get) {
+            &[]
+        } else {
+            &self.0
+        };
+        #[cfg(not(feature = "raw-fields"))]
+        return &[];
+    }
+
+    /// Read and keep some bytes.
+    pub(crate) fn read_from(mut input: impl Read, len: usize) -> Result<Self> {
+        let mut bytes = vec![0; len];
+        input.read_exact(&mut bytes)?;
+        Ok(Self::new(&bytes))
+    }
+
+    /// Write the stored bytes, or zeroes if they do not have the expected length.
+    pub(crate) fn write_to(&self, mut output: impl Write, len: usize) -> Result<()> {
+        let bytes = self.as_slice();
+        if bytes.len() == len {
+            output.write_all(bytes)
+        } else {
+            output.write_all(&vec![0; len])
+        }
+    }
+
+    /// Write part of the stored bytes, for values that are split up across a structure. Zeroes
+    /// are written if the stored bytes do not have the expected total length.
+    pub(crate) fn write_part_to(
+        &self,
+        mut output: impl Write,
+        total_len: usize,
+        range: std::ops::Range<usize>,
+    ) -> Result<()> {
+        let bytes = self.as_slice();
+        if bytes.len() == total_len {
+            output.write_all(&bytes[range])
+        } else {
+            output.write_all(&vec![0; range.len()])
+        }
+    }
+
+    /// Write a list of pointers that indicate which entries of a table exist. The stored
+    /// pointers are used if they agree with `entries`, and 1 or 0 is written otherwise.
+    pub(crate) fn write_pointers_to<T>(
+        &self,
+        mut output: impl Write,
+        entries: &[Option<T>],
+    ) -> Result<()> {
+        let pointers = self.as_slice();
+        let pointers = if pointers.len() == 4 * entries.len() {
+            pointers
+        } else {
+            &[]
+        };
+        for (index, entry) in entries.iter().enumerate() {
+            let pointer = pointers
+                .get(4 * index..4 * index + 4)
+                .map(|pointer| u32::from_le_bytes(pointer.try_into().unwrap()))
+                .filter(|&pointer| (pointer != 0) == entry.is_some());
+            output.write_u32::<LE>(match (pointer, entry) {
+                (Some(pointer), _) => pointer,
+                (None, Some(_)) => 1,
+                (None, None) => 0,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Keep the bytes following the NUL terminator of a fixed-size string buffer. Nothing is kept
+    /// if those bytes are all zero.
+    pub(crate) fn string_tail(buffer: &[u8]) -> Self {
+        match buffer.iter().position(|&byte| byte == 0) {
+            Some(end) if buffer[end..].iter().any(|&byte| byte != 0) => Self::new(&buffer[end..]),
+            _ => Self::default(),
+        }
+    }
+
+    /// Write a string to a fixed-size buffer, followed by the bytes kept by
+    /// [`RawBytes::string_tail`] if they fit exactly, or by zeroes otherwise.
+    pub(crate) fn write_string_to(
+        &self,
+        mut output: impl Write,
+        string: &[u8],
+        size: usize,
+    ) -> Result<()> {
+        let mut buffer = vec![0; size];
+        let len = string.len().min(size);
+        buffer[..len].copy_from_slice(&string[..len]);
+        let tail = self.as_slice();
+        if len + tail.len() == size {
+            buffer[len..].copy_from_slice(tail);
+        }
+        output.write_all(&buffer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RawBytes {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RawBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        Ok(Self::new(&bytes))
+    }
+}
+
+/// Values in the top level of a data file that are not interpreted by this crate.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnknownFields {
+    /// Two lists of pointers, each with one entry per terrain table.
+    pub terrain_table_pointers: RawBytes,
+    /// Pointers to the sprites. Only the existence of a sprite is stored in the sprite table.
+    pub sprite_pointers: RawBytes,
+    /// Map pointers and map and world sizes, preceding the tile sizes.
+    pub map_header: RawBytes,
+    /// Padding following the tile sizes.
+    pub tile_sizes_padding: RawBytes,
+    /// Map state and pointers following the terrain borders, such as map bounds, tile sizes and
+    /// visibility flags.
+    pub map_state: RawBytes,
+    /// Pointer to the random maps.
+    pub random_maps_pointer: RawBytes,
+    /// Unit kill and razing statistics preceding the tech tree.
+    pub combat_stats: RawBytes,
+}
+
+impl UnknownFields {
+    pub(crate) const MAP_HEADER_LEN: usize = 6 * 4;
+    pub(crate) const TILE_SIZES_PADDING_LEN: usize = 2;
+    pub(crate) const RANDOM_MAPS_POINTER_LEN: usize = 4;
+    pub(crate) const COMBAT_STATS_LEN: usize = 7 * 4;
+
+    pub(crate) fn terrain_table_pointers_len(num_terrain_tables: u16) -> usize {
+        8 * usize::from(num_terrain_tables)
+    }
+
+    pub(crate) fn map_state_len(version: FileVersion) -> usize {
+        let num_map_bytes = if version.is_swgb() { 25 } else { 21 };
+        67 + num_map_bytes + 157 * 4
+    }
+}
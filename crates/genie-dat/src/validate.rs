@@ -0,0 +1,323 @@
+//! Referential integrity checks for data files.
+
+use crate::civ::{Civilization, CivilizationID};
+use crate::sound::SoundID;
+use crate::sprite::SpriteID;
+use crate::unit_type::{UnitType, UnitTypeID};
+use crate::DatFile;
+use genie_support::TechID;
+use std::fmt;
+
+/// The data table entry that contains a broken reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Referrer {
+    /// A unit type in a civilization's unit type table.
+    UnitType {
+        civilization: CivilizationID,
+        unit_type: UnitTypeID,
+    },
+    /// A tech.
+    Tech(TechID),
+    /// A civilization.
+    Civilization(CivilizationID),
+    /// A sprite.
+    Sprite(SpriteID),
+}
+
+impl fmt::Display for Referrer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnitType {
+                civilization,
+                unit_type,
+            } => write!(
+                f,
+                "unit type {} (civilization {})",
+                u16::from(*unit_type),
+                u8::from(*civilization)
+            ),
+            Self::Tech(id) => write!(f, "tech {}", u16::from(*id)),
+            Self::Civilization(id) => write!(f, "civilization {}", u8::from(*id)),
+            Self::Sprite(id) => write!(f, "sprite {}", u16::from(*id)),
+        }
+    }
+}
+
+/// The missing data table entry that is referenced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    Sprite(SpriteID),
+    Sound(SoundID),
+    UnitType(UnitTypeID),
+    Tech(TechID),
+    Effect(u16),
+    TerrainRestriction(u16),
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sprite(id) => write!(f, "sprite {}", u16::from(*id)),
+            Self::Sound(id) => write!(f, "sound {}", u16::from(*id)),
+            Self::UnitType(id) => write!(f, "unit type {}", u16::from(*id)),
+            Self::Tech(id) => write!(f, "tech {}", u16::from(*id)),
+            Self::Effect(id) => write!(f, "effect {}", id),
+            Self::TerrainRestriction(id) => write!(f, "terrain restriction {}", id),
+        }
+    }
+}
+
+/// A reference from one data table entry to another entry that does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// The entry containing the reference.
+    pub referrer: Referrer,
+    /// The name of the field containing the reference.
+    pub field: &'static str,
+    /// The missing entry.
+    pub missing: Reference,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} refers to missing {}",
+            self.referrer, self.field, self.missing
+        )
+    }
+}
+
+/// Collects validation issues for a data file.
+struct Validator<'a> {
+    dat: &'a DatFile,
+    issues: Vec<ValidationIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, referrer: Referrer, field: &'static str, missing: Reference) {
+        self.issues.push(ValidationIssue {
+            referrer,
+            field,
+            missing,
+        });
+    }
+
+    fn sprite(&mut self, referrer: Referrer, field: &'static str, id: Option<SpriteID>) {
+        if let Some(id) = id {
+            if self.dat.get_sprite(id).is_none() {
+                self.report(referrer, field, Reference::Sprite(id));
+            }
+        }
+    }
+
+    fn sound(&mut self, referrer: Referrer, field: &'static str, id: Option<SoundID>) {
+        if let Some(id) = id {
+            if self.dat.get_sound(id).is_none() {
+                self.report(referrer, field, Reference::Sound(id));
+            }
+        }
+    }
+
+    fn tech(&mut self, referrer: Referrer, field: &'static str, id: Option<TechID>) {
+        if let Some(id) = id {
+            if self.dat.get_tech(id).is_none() {
+                self.report(referrer, field, Reference::Tech(id));
+            }
+        }
+    }
+
+    fn effect(&mut self, referrer: Referrer, field: &'static str, id: Option<u16>) {
+        if let Some(id) = id {
+            if self.dat.effects.get(usize::from(id)).is_none() {
+                self.report(referrer, field, Reference::Effect(id));
+            }
+        }
+    }
+
+    fn unit_type(
+        &mut self,
+        civ: &Civilization,
+        referrer: Referrer,
+        field: &'static str,
+        id: Option<UnitTypeID>,
+    ) {
+        if let Some(id) = id {
+            if civ.get_unit_type(id).is_none() {
+                self.report(referrer, field, Reference::UnitType(id));
+            }
+        }
+    }
+
+    fn check_unit_type(&mut self, civ: &Civilization, referrer: Referrer, unit: &UnitType) {
+        let static_ = &unit.static_;
+        self.sprite(referrer, "standing_sprite_1", static_.standing_sprite_1);
+        self.sprite(referrer, "standing_sprite_2", static_.standing_sprite_2);
+        self.sprite(referrer, "dying_sprite", static_.dying_sprite);
+        self.sprite(referrer, "undead_sprite", static_.undead_sprite);
+        for damage_sprite in &static_.damage_sprites {
+            // Damage sprite lists may contain unused entries.
+            let sprite = Some(damage_sprite.sprite).filter(|&id| u16::from(id) != 0xFFFF);
+            self.sprite(referrer, "damage_sprites", sprite);
+        }
+        self.sound(referrer, "train_sound", static_.train_sound);
+        self.sound(referrer, "damage_sound", static_.damage_sound);
+        self.sound(referrer, "selected_sound", static_.selected_sound);
+        self.sound(referrer, "death_sound", static_.death_sound);
+        self.unit_type(civ, referrer, "death_spawn", static_.death_spawn);
+        let restriction = static_.terrain_restriction_id;
+        if restriction != 0xFFFF && self.dat.get_terrain_restriction(restriction).is_none() {
+            self.report(
+                referrer,
+                "terrain_restriction_id",
+                Reference::TerrainRestriction(restriction),
+            );
+        }
+
+        if let Some(moving) = &unit.moving {
+            self.sprite(referrer, "move_sprite", moving.move_sprite);
+            self.sprite(referrer, "run_sprite", moving.run_sprite);
+            self.unit_type(civ, referrer, "trailing_unit", moving.trailing_unit);
+        }
+        if let Some(action) = &unit.action {
+            self.sound(referrer, "command_sound", action.command_sound);
+            self.sound(referrer, "move_sound", action.move_sound);
+            self.unit_type(civ, referrer, "drop_site", action.drop_site);
+            self.unit_type(civ, referrer, "backup_drop_site", action.backup_drop_site);
+        }
+        if let Some(base_combat) = &unit.base_combat {
+            self.sprite(referrer, "fight_sprite", base_combat.fight_sprite);
+            self.unit_type(civ, referrer, "missile_id", base_combat.missile_id);
+        }
+        if let Some(combat) = &unit.combat {
+            self.sprite(referrer, "garrison_sprite", combat.garrison_sprite);
+            self.sprite(
+                referrer,
+                "special_attack_sprite",
+                combat.special_attack_sprite,
+            );
+            self.unit_type(
+                civ,
+                referrer,
+                "create_at_building",
+                combat.create_at_building,
+            );
+            self.unit_type(civ, referrer, "volley_missile", combat.volley_missile);
+        }
+        if let Some(building) = &unit.building {
+            self.sprite(
+                referrer,
+                "construction_sprite",
+                building.construction_sprite,
+            );
+            self.sprite(referrer, "snow_sprite", building.snow_sprite);
+            self.sound(referrer, "transform_sound", building.transform_sound);
+            self.sound(referrer, "construction_sound", building.construction_sound);
+            self.tech(referrer, "on_build_make_tech", building.on_build_make_tech);
+            self.unit_type(
+                civ,
+                referrer,
+                "on_build_make_unit",
+                building.on_build_make_unit,
+            );
+            self.unit_type(
+                civ,
+                referrer,
+                "construction_unit",
+                building.construction_unit,
+            );
+            self.unit_type(civ, referrer, "transform_unit", building.transform_unit);
+            self.unit_type(civ, referrer, "salvage_unit", building.salvage_unit);
+            for link in &building.linked_buildings {
+                self.unit_type(civ, referrer, "linked_buildings", Some(link.unit_id));
+            }
+        }
+    }
+
+    fn check(mut self) -> Vec<ValidationIssue> {
+        let dat = self.dat;
+        for (index, sprite) in dat.sprites.iter().enumerate() {
+            let sprite = match sprite {
+                Some(sprite) => sprite,
+                None => continue,
+            };
+            let referrer = Referrer::Sprite(SpriteID::from(index as u16));
+            self.sound(referrer, "sound_id", sprite.sound_id);
+            for delta in &sprite.deltas {
+                self.sprite(referrer, "deltas", delta.sprite_id);
+            }
+            for attack_sound in &sprite.attack_sounds {
+                for prop in &attack_sound.sound_props {
+                    self.sound(referrer, "attack_sounds", Some(prop.sound_id));
+                }
+            }
+        }
+
+        for (index, tech) in dat.techs.iter().enumerate() {
+            let referrer = Referrer::Tech(TechID::from(index as u16));
+            for required in tech.required_techs() {
+                self.tech(referrer, "required_techs", Some(*required));
+            }
+            self.effect(referrer, "effect_id", tech.effect_id());
+        }
+
+        for (index, civ) in dat.civilizations.iter().enumerate() {
+            let civilization = CivilizationID::from(index as u8);
+            let referrer = Referrer::Civilization(civilization);
+            self.effect(referrer, "civ_effect", Some(civ.civ_effect()));
+            self.effect(referrer, "bonus_effect", civ.bonus_effect());
+
+            for unit in civ.unit_types().iter().flatten() {
+                let referrer = Referrer::UnitType {
+                    civilization,
+                    unit_type: unit.static_.id,
+                };
+                self.check_unit_type(civ, referrer, unit);
+            }
+        }
+
+        self.issues
+    }
+}
+
+impl DatFile {
+    /// Check the data file for references to sprites, sounds, unit types, techs, effects and
+    /// terrain restrictions that do not exist.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        Validator {
+            dat: self,
+            issues: vec![],
+        }
+        .check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::fs::File;
+
+    #[test]
+    fn detect_dangling_references() -> anyhow::Result<()> {
+        let mut f = File::open("fixtures/aoc1.0c.dat")?;
+        let mut dat = DatFile::read_from(&mut f)?;
+        let num_issues = dat.validate().len();
+
+        let missing_sprite = SpriteID::from(u16::try_from(dat.sprites.len())?);
+        let archer = dat.civilizations[1].unit_types_mut()[4].as_mut().unwrap();
+        archer.static_.dying_sprite = Some(missing_sprite);
+
+        let issues = dat.validate();
+        assert_eq!(issues.len(), num_issues + 1);
+        assert!(issues.contains(&ValidationIssue {
+            referrer: Referrer::UnitType {
+                civilization: 1.into(),
+                unit_type: 4.into(),
+            },
+            field: "dying_sprite",
+            missing: Reference::Sprite(missing_sprite),
+        }));
+        Ok(())
+    }
+}
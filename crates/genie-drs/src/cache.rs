@@ -0,0 +1,219 @@
+use crate::{DRSArchive, ResourceType};
+use std::any::Any;
+use std::io::{Error, Read, Seek};
+use std::sync::Arc;
+
+/// A decoded resource in the cache.
+struct CacheEntry {
+    resource_type: ResourceType,
+    id: u32,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+/// A DRS archive that keeps recently decoded resources in memory.
+///
+/// Renderers that look up the same sprites repeatedly, like minimap or scenario preview
+/// generators, can use this to avoid reading and decoding resources every time. Resources are
+/// decoded by a function passed to [`CachedArchive::get`], so any type can be cached, like SLP
+/// sprites or palettes. The same resource can be cached as different types at the same time.
+///
+/// When the cache is full, the least recently used resource is dropped.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use genie_drs::{CachedArchive, DRSArchive};
+///
+/// let mut drs = CachedArchive::new(DRSArchive::new(File::open("test.drs")?)?, 16);
+/// let decode = |data: &[u8]| -> Result<String, std::io::Error> {
+///     Ok(String::from_utf8_lossy(data).into_owned())
+/// };
+/// let first = drs.get("json".parse()?, 4, decode)?;
+/// let second = drs.get("json".parse()?, 4, decode)?;
+/// assert!(std::sync::Arc::ptr_eq(&first, &second));
+/// assert_eq!((drs.hits(), drs.misses()), (1, 1));
+/// # Ok(())
+/// # }
+/// ```
+pub struct CachedArchive<R> {
+    archive: DRSArchive<R>,
+    capacity: usize,
+    /// Cached resources, from least to most recently used.
+    entries: Vec<CacheEntry>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<R> CachedArchive<R>
+where
+    R: Read + Seek,
+{
+    /// Wrap an archive, keeping up to `capacity` decoded resources in memory.
+    pub fn new(archive: DRSArchive<R>, capacity: usize) -> Self {
+        Self {
+            archive,
+            capacity,
+            entries: Vec::with_capacity(capacity),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Get a decoded resource from the cache, or read and decode it if it is not cached.
+    ///
+    /// The `decode` function is only called if the resource is not cached as a `T` yet.
+    pub fn get<T, E>(
+        &mut self,
+        resource_type: ResourceType,
+        id: u32,
+        decode: impl FnOnce(&[u8]) -> Result<T, E>,
+    ) -> Result<Arc<T>, E>
+    where
+        T: Any + Send + Sync,
+        E: From<Error>,
+    {
+        let position = self.entries.iter().position(|entry| {
+            entry.resource_type == resource_type && entry.id == id && entry.value.is::<T>()
+        });
+        if let Some(index) = position {
+            self.hits += 1;
+            let entry = self.entries.remove(index);
+            let value = Arc::clone(&entry.value);
+            self.entries.push(entry);
+            return Ok(value.downcast().expect("type was checked"));
+        }
+
+        self.misses += 1;
+        let data = self.archive.read_resource(resource_type, id)?;
+        let value = Arc::new(decode(&data)?);
+        if self.capacity > 0 {
+            if self.entries.len() >= self.capacity {
+                self.entries.remove(0);
+            }
+            self.entries.push(CacheEntry {
+                resource_type,
+                id,
+                value: Arc::clone(&value) as Arc<dyn Any + Send + Sync>,
+            });
+        }
+        Ok(value)
+    }
+
+    /// Drop all cached values of a resource, so it is decoded again the next time it is used.
+    pub fn invalidate(&mut self, resource_type: ResourceType, id: u32) {
+        self.entries
+            .retain(|entry| entry.resource_type != resource_type || entry.id != id);
+    }
+
+    /// Drop all cached resources.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Get the number of cached resources.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no resources are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the maximum number of cached resources.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the number of lookups that were served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Get the number of lookups that had to read and decode the resource.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Get the archive, for example to read resources without caching them.
+    pub fn archive(&mut self) -> &mut DRSArchive<R> {
+        &mut self.archive
+    }
+
+    /// Get the archive back, dropping the cache.
+    pub fn into_inner(self) -> DRSArchive<R> {
+        self.archive
+    }
+}
+
+impl<R> std::fmt::Debug for CachedArchive<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedArchive")
+            .field("capacity", &self.capacity)
+            .field("len", &self.entries.len())
+            .field("hits", &self.hits)
+            .field("misses", &self.misses)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DRSWriter, InMemoryStrategy};
+    use std::io::Cursor;
+
+    fn len(data: &[u8]) -> Result<usize, Error> {
+        Ok(data.len())
+    }
+
+    fn text(data: &[u8]) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(data).into_owned())
+    }
+
+    #[test]
+    fn least_recently_used() -> anyhow::Result<()> {
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        writer.add("bina", 1, &b"one"[..])?;
+        writer.add("bina", 2, &b"two!"[..])?;
+        writer.add("bina", 3, &b"three"[..])?;
+        let archive = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+        let mut drs = CachedArchive::new(archive, 2);
+        let bina = ResourceType::from("bina");
+
+        assert_eq!(*drs.get(bina, 1, len)?, 3);
+        assert_eq!(*drs.get(bina, 2, len)?, 4);
+        assert_eq!(*drs.get(bina, 1, len)?, 3);
+        assert_eq!((drs.hits(), drs.misses()), (1, 2));
+
+        // 2 was used least recently, so it is dropped.
+        assert_eq!(*drs.get(bina, 3, len)?, 5);
+        assert_eq!(drs.len(), 2);
+        drs.get(bina, 1, |_| -> Result<usize, Error> {
+            panic!("1 should be cached")
+        })?;
+        drs.get(bina, 2, len)?;
+        assert_eq!((drs.hits(), drs.misses()), (2, 4));
+
+        // The same resource can be cached as different types.
+        assert_eq!(*drs.get(bina, 2, text)?, "two!");
+        assert_eq!(drs.len(), 2);
+        drs.invalidate(bina, 2);
+        assert!(drs.is_empty());
+
+        assert!(drs.get(bina, 4, len).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn no_capacity() -> anyhow::Result<()> {
+        let archive = DRSArchive::new(std::fs::File::open("test.drs")?)?;
+        let mut drs = CachedArchive::new(archive, 0);
+        let json = "json".parse()?;
+        let first = drs.get(json, 4, text)?;
+        let second = drs.get(json, 4, text)?;
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(drs.misses(), 2);
+        Ok(())
+    }
+}
@@ -2,6 +2,12 @@
 //! Star Wars: Galactic Battlegrounds. .drs files contain tables, each of which contain resources
 //! of a single type. Resources are identified by a numeric identifier.
 //!
+//! The header of Star Wars: Galactic Battlegrounds archives is slightly different; see
+//! [`DRSVariant`]. The variant is detected when reading, so the same code works for all games.
+//!
+//! HD Edition and Definitive Edition store most of their assets as loose files instead. The
+//! [`AssetSource`] trait reads assets from DRS archives and directory trees alike.
+//!
 //! ## Example
 //!
 //! ```rust
@@ -29,16 +35,33 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
-use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt, LE};
 use sorted_vec::SortedVec;
-use std::io::{Error, Read, Write};
+use std::fmt;
+use std::io::{Error, ErrorKind, Read, Write};
 use std::slice;
 use std::str;
 
+mod cache;
+mod mmap;
+mod patch;
 mod read;
+mod repack;
+mod source;
+mod verify;
+mod wav;
 mod write;
 
-pub use read::DRSReader;
+pub use cache::CachedArchive;
+pub use mmap::MappedArchive;
+#[cfg(feature = "mmap")]
+pub use mmap::Mmap;
+pub use patch::{DRSPatch, ResourceChange};
+pub use read::{DRSArchive, DRSReader, ResourceHandle};
+pub use repack::{RepackOptions, RepackReport};
+pub use source::{AssetDirectory, AssetSource, LayeredSource};
+pub use verify::{VerifyIssue, VerifyReport};
+pub use wav::{Wav, WavError, WavInfo};
 pub use write::{DRSWriter, InMemoryStrategy, ReserveDirectoryStrategy, Strategy as WriteStrategy};
 
 /// A DRS version string.
@@ -89,10 +112,10 @@ impl AsRef<str> for ResourceType {
     }
 }
 
-impl ToString for ResourceType {
-    fn to_string(&self) -> String {
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s: &str = self.as_ref();
-        s.to_string()
+        f.write_str(s)
     }
 }
 
@@ -129,7 +152,7 @@ impl From<&[u8]> for ResourceType {
     fn from(u: &[u8]) -> Self {
         assert!(u.len() <= 4);
         let mut bytes = [b' '; 4];
-        (&mut bytes[0..u.len()]).copy_from_slice(u);
+        bytes[0..u.len()].copy_from_slice(u);
         Self(bytes)
     }
 }
@@ -141,10 +164,104 @@ impl From<&str> for ResourceType {
     }
 }
 
+/// The well-known kinds of resources stored in DRS archives.
+///
+/// ## Examples
+/// ```rust
+/// use genie_drs::{ResourceKind, ResourceType};
+/// let slp = ResourceType::from("slp ");
+/// assert_eq!(slp.kind(), Some(ResourceKind::Slp));
+/// assert_eq!(&ResourceType::from(ResourceKind::Wav), "wav");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// SLP sprite graphics.
+    Slp,
+    /// SHP sprite graphics, used by early versions of Age of Empires.
+    Shp,
+    /// WAV sounds.
+    Wav,
+    /// Other binary files, like palettes, interface files and random map scripts.
+    Bina,
+}
+
+impl ResourceKind {
+    /// All well-known kinds of resources.
+    pub const ALL: [ResourceKind; 4] = [
+        ResourceKind::Slp,
+        ResourceKind::Shp,
+        ResourceKind::Wav,
+        ResourceKind::Bina,
+    ];
+
+    /// Get the resource type name that is used for this kind of resource.
+    pub fn resource_type(self) -> ResourceType {
+        match self {
+            ResourceKind::Slp => "slp ".into(),
+            ResourceKind::Shp => "shp ".into(),
+            ResourceKind::Wav => "wav ".into(),
+            ResourceKind::Bina => "bina".into(),
+        }
+    }
+}
+
+impl From<ResourceKind> for ResourceType {
+    fn from(kind: ResourceKind) -> Self {
+        kind.resource_type()
+    }
+}
+
+impl ResourceType {
+    /// Get the well-known kind of resources with this type name, if it is one.
+    pub fn kind(self) -> Option<ResourceKind> {
+        ResourceKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.resource_type() == self)
+    }
+}
+
+/// The layout of a DRS archive, which differs slightly between games.
+///
+/// Age of Empires 1 and 2 archives start with a 40 byte copyright banner. Star Wars: Galactic
+/// Battlegrounds archives start with a 60 byte banner instead, which moves the rest of the header
+/// and the tables 20 bytes further into the file. The variant is detected when reading an archive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DRSVariant {
+    /// Archives of Age of Empires 1 and 2.
+    #[default]
+    AgeOfEmpires,
+    /// Archives of Star Wars: Galactic Battlegrounds.
+    StarWars,
+}
+
+impl DRSVariant {
+    /// Get the size in bytes of the copyright banner.
+    fn banner_len(self) -> usize {
+        match self {
+            DRSVariant::AgeOfEmpires => 40,
+            DRSVariant::StarWars => 60,
+        }
+    }
+
+    /// Get the size in bytes of the archive header. The tables start at this offset.
+    pub fn header_size(self) -> u32 {
+        self.banner_len() as u32 + 24
+    }
+}
+
+/// Check if 4 bytes look like a DRS version string, like "1.00".
+fn is_version(bytes: &[u8]) -> bool {
+    matches!(bytes, [major, b'.', minor1, minor2]
+        if major.is_ascii_digit() && minor1.is_ascii_digit() && minor2.is_ascii_digit())
+}
+
 /// The DRS archive header.
 pub struct DRSHeader {
+    /// The layout of the archive.
+    variant: DRSVariant,
     /// A copyright message.
-    banner_msg: [u8; 40],
+    banner_msg: Vec<u8>,
     /// File version. (always "1.00")
     version: DRSVersion,
     /// File password / identifier.
@@ -157,29 +274,62 @@ pub struct DRSHeader {
 
 impl Default for DRSHeader {
     fn default() -> Self {
+        Self::new(DRSVariant::default())
+    }
+}
+
+impl DRSHeader {
+    /// Create the header that the original game uses for the given variant.
+    fn new(variant: DRSVariant) -> Self {
+        let (banner, password): (&[u8], _) = match variant {
+            DRSVariant::AgeOfEmpires => (
+                b"Copyright (c) 1997 Ensemble Studios.\x1a",
+                *b"tribe\x00\x00\x00\x00\x00\x00\x00",
+            ),
+            DRSVariant::StarWars => (
+                b"Copyright (c) 2001 LucasArts Entertainment Company LLC\x1a",
+                *b"swbg\x00\x00\x00\x00\x00\x00\x00\x00",
+            ),
+        };
+        let mut banner_msg = banner.to_vec();
+        banner_msg.resize(variant.banner_len(), 0);
         Self {
-            banner_msg: *b"Copyright (c) 1997 Ensemble Studios.\x1a\x00\x00\x00",
+            variant,
+            banner_msg,
             version: *b"1.00",
-            password: *b"tribe\x00\x00\x00\x00\x00\x00\x00",
+            password,
             num_resource_types: 0,
             directory_size: 0,
         }
     }
-}
 
-impl DRSHeader {
     #[inline]
-    /// Read a DRS archive header from a `Read`able handle.
+    /// Read a DRS archive header from a `Read`able handle, detecting the variant.
     fn from<R: Read>(source: &mut R) -> Result<DRSHeader, Error> {
-        let mut banner_msg = [0_u8; 40];
+        let mut banner_msg = vec![0_u8; DRSVariant::AgeOfEmpires.banner_len()];
         let mut version = [0_u8; 4];
-        let mut password = [0_u8; 12];
         source.read_exact(&mut banner_msg)?;
         source.read_exact(&mut version)?;
-        source.read_exact(&mut password)?;
-        let num_resource_types = source.read_u32::<LE>()?;
-        let directory_size = source.read_u32::<LE>()?;
+
+        // Star Wars archives have a longer banner, so the version is not where it is expected.
+        let mut rest = [0_u8; 20];
+        source.read_exact(&mut rest)?;
+        let variant = if !is_version(&version) && is_version(&rest[16..]) {
+            banner_msg.extend_from_slice(&version);
+            banner_msg.extend_from_slice(&rest[..16]);
+            version.copy_from_slice(&rest[16..]);
+            source.read_exact(&mut rest)?;
+            DRSVariant::StarWars
+        } else {
+            DRSVariant::AgeOfEmpires
+        };
+
+        let mut password = [0_u8; 12];
+        password.copy_from_slice(&rest[..12]);
+        let num_resource_types = LE::read_u32(&rest[12..16]);
+        let directory_size = LE::read_u32(&rest[16..20]);
         Ok(DRSHeader {
+            variant,
             banner_msg,
             version,
             password,
@@ -197,6 +347,12 @@ impl DRSHeader {
         output.write_u32::<LE>(self.directory_size)?;
         Ok(())
     }
+
+    /// Get the size in bytes of the header.
+    #[inline]
+    fn size(&self) -> u32 {
+        self.variant.header_size()
+    }
 }
 
 impl std::fmt::Debug for DRSHeader {
@@ -258,8 +414,15 @@ impl DRSTable {
     /// Read the table itself.
     #[inline]
     fn read_resources<R: Read>(&mut self, source: &mut R) -> Result<(), Error> {
-        for _ in 0..self.num_resources {
-            let resource = DRSResource::from(source)?;
+        // Read the whole table at once, instead of doing three small reads per resource.
+        let size = u64::from(self.num_resources) * DRSResource::SIZE;
+        let mut bytes = vec![];
+        source.by_ref().take(size).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != size {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        for mut record in bytes.chunks_exact(DRSResource::SIZE as usize) {
+            let resource = DRSResource::from(&mut record)?;
             let _discard = self.resource_ids.insert(resource.id);
             self.resources.push(resource);
         }
@@ -319,9 +482,7 @@ impl std::fmt::Debug for DRSTable {
         write!(
             f,
             "DRSTable {{ resource_type: '{}', offset: {}, num_resources: {} }}",
-            self.resource_type.to_string(),
-            self.offset,
-            self.num_resources
+            self.resource_type, self.offset, self.num_resources
         )
     }
 }
@@ -338,6 +499,9 @@ pub struct DRSResource {
 }
 
 impl DRSResource {
+    /// The size of the resource metadata in a table, in bytes.
+    const SIZE: u64 = 12;
+
     /// Read DRS resource metadata from a `Read`able handle.
     #[inline]
     fn from<R: Read>(source: &mut R) -> Result<DRSResource, Error> {
@@ -390,4 +554,63 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn archive() -> anyhow::Result<()> {
+        let mut file = File::open("test.drs")?;
+        let drs = DRSReader::new(&mut file)?;
+        let expected = drs.read_resource(&mut file, "js".parse()?, 2)?;
+
+        let mut archive = DRSArchive::new(File::open("test.drs")?)?;
+        assert_eq!(archive.tables().count(), 2);
+        assert_eq!(archive.get_resource("js".parse()?, 2).unwrap().size, 452);
+        let mut output = vec![];
+        assert_eq!(archive.copy_resource("js".parse()?, 2, &mut output)?, 452);
+        assert_eq!(&output[..], &expected[..]);
+        assert_eq!(archive.read_resource("js".parse()?, 2)?, expected);
+        assert!(archive.read_resource("js".parse()?, 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn typed_resources() -> anyhow::Result<()> {
+        use std::io::{Cursor, Read};
+
+        let mut drs = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        drs.add(ResourceKind::Slp, 15000, &b"slp data"[..])?;
+        drs.add(ResourceKind::Wav, 5000, &b"wav data"[..])?;
+        drs.add("bina", 50500, &b"palette"[..])?;
+        let output = drs.flush()?;
+
+        let mut archive = DRSArchive::new(Cursor::new(output.into_inner()))?;
+        let kinds: Vec<_> = archive
+            .tables()
+            .map(|table| table.resource_type.kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Some(ResourceKind::Slp),
+                Some(ResourceKind::Wav),
+                Some(ResourceKind::Bina)
+            ]
+        );
+
+        let mut slp = archive.slp(15000).unwrap();
+        assert_eq!(
+            (slp.kind(), slp.id(), slp.size()),
+            (ResourceKind::Slp, 15000, 8)
+        );
+        assert_eq!(&slp.read()?[..], b"slp data");
+        let mut content = vec![];
+        slp.reader()?.read_to_end(&mut content)?;
+        assert_eq!(content, b"slp data");
+
+        let mut output = vec![];
+        archive.bina(50500).unwrap().copy_to(&mut output)?;
+        assert_eq!(output, b"palette");
+        assert!(archive.wav(15000).is_none());
+        assert!(archive.shp(15000).is_none());
+        Ok(())
+    }
 }
@@ -0,0 +1,167 @@
+use crate::{DRSReader, DRSResource, DRSTableIterator, ResourceKind, ResourceType};
+use std::io::{Cursor, Error, ErrorKind};
+#[cfg(feature = "mmap")]
+use std::{fs::File, path::Path};
+
+#[cfg(feature = "mmap")]
+pub use memmap2::Mmap;
+
+/// A DRS archive whose contents are entirely in memory, usually a memory-mapped file.
+///
+/// Resources are borrowed directly from the archive data, without copying them. With a
+/// memory-mapped file, only the parts of the file that are used are read from disk, which makes
+/// scanning many resources much faster than with [`DRSArchive`](crate::DRSArchive).
+///
+/// Memory-mapping files requires the `mmap` feature. Other byte buffers, like a `Vec<u8>` or a
+/// `bytes::Bytes` received over the network, can be used without it.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use genie_drs::MappedArchive;
+///
+/// let drs = MappedArchive::new(std::fs::read("test.drs")?)?;
+/// let content = drs.resource("json".parse()?, 4)?;
+/// assert_eq!(content.len(), 710);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MappedArchive<D> {
+    reader: DRSReader,
+    data: D,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedArchive<Mmap> {
+    /// Memory-map the DRS archive at the given path.
+    ///
+    /// The file must not be modified while it is mapped, or the resources may change or become
+    /// unreadable.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        // Safety: the map is read-only, and like every memory map, it relies on the file not
+        // being modified by other processes while it is in use. This is documented above.
+        #[allow(unsafe_code)]
+        let map = unsafe { Mmap::map(&file)? };
+        Self::new(map)
+    }
+}
+
+impl<D> MappedArchive<D>
+where
+    D: AsRef<[u8]>,
+{
+    /// Read the table of contents of the DRS archive in the given data.
+    pub fn new(data: D) -> Result<Self, Error> {
+        let reader = DRSReader::new(&mut Cursor::new(data.as_ref()))?;
+        Ok(Self { reader, data })
+    }
+
+    /// Get the table of contents of the archive.
+    pub fn reader(&self) -> &DRSReader {
+        &self.reader
+    }
+
+    /// Iterate over the tables in this DRS archive.
+    #[inline]
+    pub fn tables(&self) -> DRSTableIterator<'_> {
+        self.reader.tables()
+    }
+
+    /// Get a resource of a given type and ID.
+    pub fn get_resource(&self, resource_type: ResourceType, id: u32) -> Option<&DRSResource> {
+        self.reader.get_resource(resource_type, id)
+    }
+
+    /// Borrow the contents of a resource.
+    pub fn resource(&self, resource_type: ResourceType, id: u32) -> Result<&[u8], Error> {
+        let resource = self
+            .get_resource(resource_type, id)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Resource not found in this archive"))?;
+        self.slice(resource)
+    }
+
+    /// Borrow the contents of a resource of a well-known kind.
+    pub fn resource_of_kind(&self, kind: ResourceKind, id: u32) -> Result<&[u8], Error> {
+        self.resource(kind.resource_type(), id)
+    }
+
+    /// Iterate over the type, ID and contents of every resource in the archive.
+    pub fn resources(&self) -> impl Iterator<Item = (ResourceType, u32, Result<&[u8], Error>)> {
+        self.tables().flat_map(move |table| {
+            table
+                .resources()
+                .map(move |resource| (table.resource_type, resource.id, self.slice(resource)))
+        })
+    }
+
+    /// Get the data of the archive back.
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+
+    fn slice(&self, resource: &DRSResource) -> Result<&[u8], Error> {
+        let start = resource.offset as usize;
+        let end = start + resource.size as usize;
+        self.data.as_ref().get(start..end).ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                "Resource is outside of the archive",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DRSWriter, InMemoryStrategy};
+
+    #[test]
+    fn borrow_resources() -> anyhow::Result<()> {
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        writer.add(ResourceKind::Slp, 1, &b"sprite"[..])?;
+        writer.add(ResourceKind::Wav, 2, &b"sound"[..])?;
+        let data = writer.flush()?.into_inner();
+
+        let drs = MappedArchive::new(&data[..])?;
+        assert_eq!(drs.resource_of_kind(ResourceKind::Slp, 1)?, b"sprite");
+        assert_eq!(drs.resource("wav".into(), 2)?, b"sound");
+        assert_eq!(
+            drs.resource("wav".into(), 1).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        let resources = drs
+            .resources()
+            .map(|(resource_type, id, data)| Ok((resource_type.to_string(), id, data?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        assert_eq!(
+            resources,
+            vec![
+                ("slp".to_string(), 1, &b"sprite"[..]),
+                ("wav".to_string(), 2, &b"sound"[..])
+            ]
+        );
+
+        let truncated = MappedArchive::new(&data[..data.len() - 1])?;
+        assert_eq!(
+            truncated.resource("wav".into(), 2).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn map_file() -> anyhow::Result<()> {
+        let drs = MappedArchive::open("test.drs")?;
+        let expected = std::fs::read("test.drs")?;
+        let resource = drs.get_resource("json".parse()?, 4).unwrap();
+        let start = resource.offset as usize;
+        assert_eq!(
+            drs.resource("json".parse()?, 4)?,
+            &expected[start..start + 710]
+        );
+        Ok(())
+    }
+}
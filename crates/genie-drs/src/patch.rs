@@ -0,0 +1,405 @@
+use crate::{DRSArchive, DRSWriter, ResourceType};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::io::{Error, ErrorKind, Read, Seek, Write};
+
+/// The signature at the start of patch files.
+const SIGNATURE: [u8; 8] = *b"DRSPATCH";
+/// The version of the patch file format.
+const VERSION: u32 = 1;
+
+const ADDED: u8 = 0;
+const CHANGED: u8 = 1;
+const REMOVED: u8 = 2;
+
+/// A difference between two DRS archives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceChange {
+    /// A resource that only exists in the new archive.
+    Added {
+        resource_type: ResourceType,
+        id: u32,
+        data: Vec<u8>,
+    },
+    /// A resource that exists in both archives, with different contents.
+    Changed {
+        resource_type: ResourceType,
+        id: u32,
+        data: Vec<u8>,
+    },
+    /// A resource that only exists in the old archive.
+    Removed {
+        resource_type: ResourceType,
+        id: u32,
+    },
+}
+
+impl ResourceChange {
+    /// Get the type of the resource.
+    pub fn resource_type(&self) -> ResourceType {
+        match self {
+            ResourceChange::Added { resource_type, .. }
+            | ResourceChange::Changed { resource_type, .. }
+            | ResourceChange::Removed { resource_type, .. } => *resource_type,
+        }
+    }
+
+    /// Get the ID of the resource.
+    pub fn id(&self) -> u32 {
+        match self {
+            ResourceChange::Added { id, .. }
+            | ResourceChange::Changed { id, .. }
+            | ResourceChange::Removed { id, .. } => *id,
+        }
+    }
+
+    /// Get the new contents of the resource, if it was added or changed.
+    pub fn data(&self) -> Option<&[u8]> {
+        match self {
+            ResourceChange::Added { data, .. } | ResourceChange::Changed { data, .. } => Some(data),
+            ResourceChange::Removed { .. } => None,
+        }
+    }
+
+    fn read_from<R: Read>(input: &mut R) -> Result<Self, Error> {
+        let change = input.read_u8()?;
+        let mut resource_type = [0; 4];
+        input.read_exact(&mut resource_type)?;
+        let resource_type = ResourceType::from(resource_type);
+        let id = input.read_u32::<LE>()?;
+        let mut read_data = || -> Result<Vec<u8>, Error> {
+            let size = input.read_u32::<LE>()?;
+            let mut data = vec![];
+            input.take(u64::from(size)).read_to_end(&mut data)?;
+            if data.len() != size as usize {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            Ok(data)
+        };
+        match change {
+            ADDED => Ok(ResourceChange::Added {
+                resource_type,
+                id,
+                data: read_data()?,
+            }),
+            CHANGED => Ok(ResourceChange::Changed {
+                resource_type,
+                id,
+                data: read_data()?,
+            }),
+            REMOVED => Ok(ResourceChange::Removed { resource_type, id }),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "Unknown kind of change in patch",
+            )),
+        }
+    }
+
+    fn write_to<W: Write>(&self, output: &mut W) -> Result<(), Error> {
+        output.write_u8(match self {
+            ResourceChange::Added { .. } => ADDED,
+            ResourceChange::Changed { .. } => CHANGED,
+            ResourceChange::Removed { .. } => REMOVED,
+        })?;
+        self.resource_type().write_to(output)?;
+        output.write_u32::<LE>(self.id())?;
+        if let Some(data) = self.data() {
+            assert!(data.len() <= u32::MAX as usize, "resource too large");
+            output.write_u32::<LE>(data.len() as u32)?;
+            output.write_all(data)?;
+        }
+        Ok(())
+    }
+}
+
+/// A patch that turns one DRS archive into another, containing only the resources that were
+/// added, changed or removed.
+///
+/// Graphics mods can be distributed as patches, instead of full archives.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::Cursor;
+/// use genie_drs::{DRSArchive, DRSPatch, DRSWriter, InMemoryStrategy};
+///
+/// let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+/// writer.add("slp", 1, &b"old sprite"[..])?;
+/// writer.add("wav", 2, &b"sound"[..])?;
+/// let mut old = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+///
+/// let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+/// writer.add("slp", 1, &b"new sprite"[..])?;
+/// writer.add("wav", 2, &b"sound"[..])?;
+/// let mut new = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+///
+/// let patch = DRSPatch::diff(&mut old, &mut new)?;
+/// assert_eq!(patch.changes().len(), 1);
+///
+/// let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+/// patch.apply(&mut old, &mut writer)?;
+/// let mut patched = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+/// assert_eq!(&patched.read_resource("slp".into(), 1)?[..], b"new sprite");
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DRSPatch {
+    changes: Vec<ResourceChange>,
+}
+
+impl DRSPatch {
+    /// Create a patch from a list of changes.
+    pub fn new(changes: Vec<ResourceChange>) -> Self {
+        Self { changes }
+    }
+
+    /// Compare two archives, and create a patch that turns the `old` archive into the `new`
+    /// archive.
+    pub fn diff<A, B>(old: &mut DRSArchive<A>, new: &mut DRSArchive<B>) -> Result<Self, Error>
+    where
+        A: Read + Seek,
+        B: Read + Seek,
+    {
+        let mut changes = vec![];
+        for (resource_type, id, size) in resources(old) {
+            match new
+                .get_resource(resource_type, id)
+                .map(|resource| resource.size)
+            {
+                Some(new_size) => {
+                    let data = new.read_resource(resource_type, id)?;
+                    if new_size != size || old.read_resource(resource_type, id)? != data {
+                        changes.push(ResourceChange::Changed {
+                            resource_type,
+                            id,
+                            data: data.into_vec(),
+                        });
+                    }
+                }
+                None => changes.push(ResourceChange::Removed { resource_type, id }),
+            }
+        }
+        for (resource_type, id, _) in resources(new) {
+            if old.get_resource(resource_type, id).is_none() {
+                changes.push(ResourceChange::Added {
+                    resource_type,
+                    id,
+                    data: new.read_resource(resource_type, id)?.into_vec(),
+                });
+            }
+        }
+        Ok(Self { changes })
+    }
+
+    /// Get the changes in this patch.
+    pub fn changes(&self) -> &[ResourceChange] {
+        &self.changes
+    }
+
+    /// Returns `true` if the patch does not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Read a patch file.
+    pub fn read_from(mut input: impl Read) -> Result<Self, Error> {
+        let mut signature = [0; 8];
+        input.read_exact(&mut signature)?;
+        if signature != SIGNATURE {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a DRS patch file"));
+        }
+        let version = input.read_u32::<LE>()?;
+        if version != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Unsupported DRS patch version",
+            ));
+        }
+        let num_changes = input.read_u32::<LE>()?;
+        let changes = (0..num_changes)
+            .map(|_| ResourceChange::read_from(&mut input))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { changes })
+    }
+
+    /// Write a patch file.
+    pub fn write_to(&self, mut output: impl Write) -> Result<(), Error> {
+        assert!(self.changes.len() <= u32::MAX as usize, "too many changes");
+        output.write_all(&SIGNATURE)?;
+        output.write_u32::<LE>(VERSION)?;
+        output.write_u32::<LE>(self.changes.len() as u32)?;
+        for change in &self.changes {
+            change.write_to(&mut output)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the patch to an archive, writing the patched archive to `output`.
+    ///
+    /// Resources that are not changed are copied from `archive` in their original order, and
+    /// added resources are written after them. This fails if the patch was made for a different
+    /// archive: if a changed or removed resource does not exist in `archive`, or an added
+    /// resource already does.
+    pub fn apply<R, W>(
+        &self,
+        archive: &mut DRSArchive<R>,
+        output: &mut DRSWriter<W>,
+    ) -> Result<(), Error>
+    where
+        R: Read + Seek,
+        W: Write + Seek,
+    {
+        for change in &self.changes {
+            let exists = archive
+                .get_resource(change.resource_type(), change.id())
+                .is_some();
+            let expected = !matches!(change, ResourceChange::Added { .. });
+            if exists != expected {
+                let kind = if expected {
+                    ErrorKind::NotFound
+                } else {
+                    ErrorKind::AlreadyExists
+                };
+                return Err(Error::new(
+                    kind,
+                    format!(
+                        "Patch does not apply to this archive: {}.{}",
+                        change.id(),
+                        change.resource_type()
+                    ),
+                ));
+            }
+        }
+
+        for (resource_type, id, _) in resources(archive) {
+            let change = self
+                .changes
+                .iter()
+                .find(|change| change.resource_type() == resource_type && change.id() == id);
+            match change {
+                Some(ResourceChange::Removed { .. }) => (),
+                Some(change) => output.add(resource_type, id, change.data().unwrap_or_default())?,
+                None => output.add(
+                    resource_type,
+                    id,
+                    archive.resource_reader(resource_type, id)?,
+                )?,
+            }
+        }
+        for change in &self.changes {
+            if let ResourceChange::Added {
+                resource_type,
+                id,
+                data,
+            } = change
+            {
+                output.add(*resource_type, *id, &data[..])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// List the type, ID and size of every resource in an archive.
+fn resources<R: Read + Seek>(archive: &DRSArchive<R>) -> Vec<(ResourceType, u32, u32)> {
+    archive
+        .tables()
+        .flat_map(|table| {
+            table
+                .resources()
+                .map(move |resource| (table.resource_type, resource.id, resource.size))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStrategy;
+    use std::io::Cursor;
+
+    fn archive(resources: &[(&str, u32, &[u8])]) -> anyhow::Result<DRSArchive<Cursor<Vec<u8>>>> {
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        for (resource_type, id, data) in resources {
+            writer.add(*resource_type, *id, *data)?;
+        }
+        Ok(DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?)
+    }
+
+    #[test]
+    fn diff_and_apply() -> anyhow::Result<()> {
+        let old_resources: &[(&str, u32, &[u8])] = &[
+            ("slp", 1, b"unchanged"),
+            ("slp", 2, b"old sprite"),
+            ("slp", 3, b"same size"),
+            ("wav", 4, b"removed"),
+        ];
+        let mut old = archive(old_resources)?;
+        let mut new = archive(&[
+            ("slp", 1, b"unchanged"),
+            ("slp", 2, b"new, larger sprite"),
+            ("slp", 3, b"SAME SIZE"),
+            ("bina", 5, b"added"),
+        ])?;
+
+        let patch = DRSPatch::diff(&mut old, &mut new)?;
+        assert_eq!(
+            patch.changes(),
+            &[
+                ResourceChange::Changed {
+                    resource_type: "slp".into(),
+                    id: 2,
+                    data: b"new, larger sprite".to_vec()
+                },
+                ResourceChange::Changed {
+                    resource_type: "slp".into(),
+                    id: 3,
+                    data: b"SAME SIZE".to_vec()
+                },
+                ResourceChange::Removed {
+                    resource_type: "wav".into(),
+                    id: 4
+                },
+                ResourceChange::Added {
+                    resource_type: "bina".into(),
+                    id: 5,
+                    data: b"added".to_vec()
+                },
+            ]
+        );
+        assert_eq!(
+            DRSPatch::diff(&mut new, &mut archive(&[])?)?
+                .changes()
+                .len(),
+            4
+        );
+        assert!(DRSPatch::diff(&mut old, &mut archive(old_resources)?)?.is_empty());
+
+        let mut bytes = vec![];
+        patch.write_to(&mut bytes)?;
+        let patch = DRSPatch::read_from(&bytes[..])?;
+
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        patch.apply(&mut old, &mut writer)?;
+        let mut patched = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+        assert!(DRSPatch::diff(&mut patched, &mut new)?.is_empty());
+        assert!(patched.get_resource("wav".into(), 4).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn apply_to_wrong_archive() -> anyhow::Result<()> {
+        let mut old = archive(&[("slp", 1, b"sprite")])?;
+        let mut new = archive(&[("slp", 1, b"sprite"), ("slp", 2, b"added")])?;
+        let patch = DRSPatch::diff(&mut old, &mut new)?;
+
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        let err = patch.apply(&mut new, &mut writer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+        let patch = DRSPatch::diff(&mut new, &mut old)?;
+        let err = patch.apply(&mut old, &mut writer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        assert!(DRSPatch::read_from(&b"DRSPATCX\x01\x00\x00\x00"[..]).is_err());
+        Ok(())
+    }
+}
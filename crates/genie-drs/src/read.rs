@@ -1,5 +1,8 @@
-use super::{DRSHeader, DRSResource, DRSTable, DRSTableIterator, ResourceType};
-use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+use super::{
+    DRSHeader, DRSResource, DRSTable, DRSTableIterator, DRSVariant, ResourceKind, ResourceType,
+};
+use crate::{Wav, WavError};
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write};
 
 /// A DRS archive reader.
 #[derive(Debug)]
@@ -53,6 +56,19 @@ impl DRSReader {
         Ok(())
     }
 
+    /// Get the archive header.
+    pub(crate) fn header(&self) -> Option<&DRSHeader> {
+        self.header.as_ref()
+    }
+
+    /// Get the layout of the archive, which depends on the game it is from.
+    pub fn variant(&self) -> DRSVariant {
+        self.header
+            .as_ref()
+            .map(|header| header.variant)
+            .unwrap_or_default()
+    }
+
     /// Get the table for the given resource type.
     pub fn get_table(&self, resource_type: ResourceType) -> Option<&DRSTable> {
         self.tables
@@ -114,3 +130,185 @@ impl DRSReader {
         self.tables.iter()
     }
 }
+
+/// A DRS archive reader that owns its file handle.
+///
+/// Only the table of contents is kept in memory. Resources are read from the handle when they are
+/// requested, so large archives can be processed without loading them entirely.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use genie_drs::DRSArchive;
+///
+/// let mut drs = DRSArchive::new(File::open("test.drs")?)?;
+/// let mut output = vec![];
+/// let size = drs.copy_resource("json".parse()?, 4, &mut output)?;
+/// assert_eq!(size, 710);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DRSArchive<R> {
+    reader: DRSReader,
+    handle: R,
+}
+
+impl<R> DRSArchive<R>
+where
+    R: Read + Seek,
+{
+    /// Read the table of contents of the DRS archive in the given handle.
+    pub fn new(mut handle: R) -> Result<Self, Error> {
+        let reader = DRSReader::new(&mut handle)?;
+        Ok(Self { reader, handle })
+    }
+
+    /// Get the table of contents of the archive.
+    pub fn reader(&self) -> &DRSReader {
+        &self.reader
+    }
+
+    /// Get the layout of the archive, which depends on the game it is from.
+    pub fn variant(&self) -> DRSVariant {
+        self.reader.variant()
+    }
+
+    /// Iterate over the tables in this DRS archive.
+    #[inline]
+    pub fn tables(&self) -> DRSTableIterator<'_> {
+        self.reader.tables()
+    }
+
+    /// Get a resource of a given type and ID.
+    pub fn get_resource(&self, resource_type: ResourceType, id: u32) -> Option<&DRSResource> {
+        self.reader.get_resource(resource_type, id)
+    }
+
+    /// Get a `Read`er for the given resource, that reads directly from the file handle.
+    pub fn resource_reader(
+        &mut self,
+        resource_type: ResourceType,
+        id: u32,
+    ) -> Result<impl Read + '_, Error> {
+        self.reader
+            .get_resource_reader(&mut self.handle, resource_type, id)
+    }
+
+    /// Read a file from the DRS archive into memory.
+    pub fn read_resource(
+        &mut self,
+        resource_type: ResourceType,
+        id: u32,
+    ) -> Result<Box<[u8]>, Error> {
+        self.reader
+            .read_resource(&mut self.handle, resource_type, id)
+    }
+
+    /// Copy a file from the DRS archive to an output stream, without loading it into memory.
+    /// Returns the number of bytes that were copied.
+    pub fn copy_resource(
+        &mut self,
+        resource_type: ResourceType,
+        id: u32,
+        output: &mut impl Write,
+    ) -> Result<u64, Error> {
+        let mut reader = self.resource_reader(resource_type, id)?;
+        io::copy(&mut reader, output)
+    }
+
+    /// Get a handle to a resource of a well-known kind.
+    pub fn resource(&mut self, kind: ResourceKind, id: u32) -> Option<ResourceHandle<'_, R>> {
+        let size = self.get_resource(kind.resource_type(), id)?.size;
+        Some(ResourceHandle {
+            archive: self,
+            kind,
+            id,
+            size,
+        })
+    }
+
+    /// Get a handle to an SLP graphic.
+    pub fn slp(&mut self, id: u32) -> Option<ResourceHandle<'_, R>> {
+        self.resource(ResourceKind::Slp, id)
+    }
+
+    /// Get a handle to an SHP graphic.
+    pub fn shp(&mut self, id: u32) -> Option<ResourceHandle<'_, R>> {
+        self.resource(ResourceKind::Shp, id)
+    }
+
+    /// Get a handle to a WAV sound.
+    pub fn wav(&mut self, id: u32) -> Option<ResourceHandle<'_, R>> {
+        self.resource(ResourceKind::Wav, id)
+    }
+
+    /// Get a handle to a binary file.
+    pub fn bina(&mut self, id: u32) -> Option<ResourceHandle<'_, R>> {
+        self.resource(ResourceKind::Bina, id)
+    }
+
+    /// Get the size of the archive file in bytes.
+    pub(crate) fn archive_len(&mut self) -> Result<u64, Error> {
+        self.handle.seek(SeekFrom::End(0))
+    }
+
+    /// Get the file handle back.
+    pub fn into_inner(self) -> R {
+        self.handle
+    }
+}
+
+/// A resource of a well-known kind in a [`DRSArchive`], returned by methods like
+/// [`DRSArchive::slp`].
+#[derive(Debug)]
+pub struct ResourceHandle<'a, R> {
+    archive: &'a mut DRSArchive<R>,
+    kind: ResourceKind,
+    id: u32,
+    size: u32,
+}
+
+impl<'a, R> ResourceHandle<'a, R>
+where
+    R: Read + Seek,
+{
+    /// Get the kind of this resource.
+    pub fn kind(&self) -> ResourceKind {
+        self.kind
+    }
+
+    /// Get the ID of this resource.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Get the size of this resource in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Get a `Read`er for this resource, that reads directly from the file handle.
+    pub fn reader(&mut self) -> Result<impl Read + '_, Error> {
+        self.archive
+            .resource_reader(self.kind.resource_type(), self.id)
+    }
+
+    /// Read this resource into memory.
+    pub fn read(&mut self) -> Result<Box<[u8]>, Error> {
+        self.archive
+            .read_resource(self.kind.resource_type(), self.id)
+    }
+
+    /// Read this resource as a WAV sound.
+    pub fn read_wav(&mut self) -> Result<Wav, WavError> {
+        Wav::from_bytes(self.read()?.into_vec())
+    }
+
+    /// Copy this resource to an output stream, without loading it into memory. Returns the
+    /// number of bytes that were copied.
+    pub fn copy_to(&mut self, output: &mut impl Write) -> Result<u64, Error> {
+        self.archive
+            .copy_resource(self.kind.resource_type(), self.id, output)
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{DRSArchive, DRSWriter, InMemoryStrategy, ResourceType};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{Error, Read, Seek, Write};
+
+/// A function that re-encodes a resource, given its type, ID and contents.
+type RecompressFn<'a> = dyn FnMut(ResourceType, u32, &[u8]) -> Result<Option<Vec<u8>>, Error> + 'a;
+
+/// Options for rebuilding a DRS archive with [`RepackOptions::repack`].
+///
+/// Repacking copies every resource to a new archive, storing resources with identical contents
+/// only once. A recompression function can be given to re-encode resources more compactly, for
+/// example to re-encode SLP sprites with a better encoder.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::{fs::File, io::Cursor};
+/// use genie_drs::{DRSArchive, RepackOptions};
+///
+/// let mut drs = DRSArchive::new(File::open("test.drs")?)?;
+/// let (output, report) = RepackOptions::new().repack(&mut drs, Cursor::new(vec![]))?;
+/// assert_eq!(report.repacked_size, output.into_inner().len() as u64);
+/// println!("saved {} bytes", report.saved());
+/// # Ok(())
+/// # }
+/// ```
+pub struct RepackOptions<'a> {
+    deduplicate: bool,
+    recompress: Option<Box<RecompressFn<'a>>>,
+}
+
+impl Default for RepackOptions<'_> {
+    fn default() -> Self {
+        Self {
+            deduplicate: true,
+            recompress: None,
+        }
+    }
+}
+
+impl fmt::Debug for RepackOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RepackOptions")
+            .field("deduplicate", &self.deduplicate)
+            .field("recompress", &self.recompress.is_some())
+            .finish()
+    }
+}
+
+impl<'a> RepackOptions<'a> {
+    /// Create the default options: deduplicate resources, and keep their contents as they are.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether to store resources with identical contents only once.
+    pub fn deduplicate(mut self, deduplicate: bool) -> Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// Set a function that re-encodes resources. It is called with the type, ID and contents of
+    /// every resource, and returns the new contents, or `None` to keep the resource as it is.
+    /// The new contents are only used if they are smaller.
+    pub fn recompress(
+        mut self,
+        recompress: impl FnMut(ResourceType, u32, &[u8]) -> Result<Option<Vec<u8>>, Error> + 'a,
+    ) -> Self {
+        self.recompress = Some(Box::new(recompress));
+        self
+    }
+
+    /// Copy all resources in `archive` to a new archive in `output`, for the same game.
+    ///
+    /// The new archive is created in memory before it is written.
+    pub fn repack<R, W>(
+        mut self,
+        archive: &mut DRSArchive<R>,
+        output: W,
+    ) -> Result<(W, RepackReport), Error>
+    where
+        R: Read + Seek,
+        W: Write + Seek,
+    {
+        let resources: Vec<_> = archive
+            .tables()
+            .flat_map(|table| {
+                table
+                    .resources()
+                    .map(move |resource| (table.resource_type, resource.id))
+            })
+            .collect();
+        let mut report = RepackReport {
+            resources: resources.len(),
+            original_size: original_size(archive),
+            ..Default::default()
+        };
+
+        let strategy = InMemoryStrategy::default().deduplicate(self.deduplicate);
+        let mut writer = DRSWriter::with_variant(output, strategy, archive.variant())?;
+        let mut seen = HashSet::new();
+        for (resource_type, id) in resources {
+            let mut data = archive.read_resource(resource_type, id)?.into_vec();
+            if let Some(recompress) = &mut self.recompress {
+                match recompress(resource_type, id, &data)? {
+                    Some(smaller) if smaller.len() < data.len() => {
+                        data = smaller;
+                        report.recompressed += 1;
+                    }
+                    _ => (),
+                }
+            }
+            if self.deduplicate && !seen.insert(data.clone()) {
+                report.duplicates += 1;
+            }
+            writer.add(resource_type, id, &data[..])?;
+        }
+
+        let mut output = writer.flush()?;
+        report.repacked_size = output.stream_position()?;
+        Ok((output, report))
+    }
+}
+
+/// Compute the size of an archive from its table of contents, counting resources that share
+/// their data only once.
+fn original_size<R>(archive: &DRSArchive<R>) -> u64
+where
+    R: Read + Seek,
+{
+    let mut size = u64::from(archive.variant().header_size());
+    let mut data = HashSet::new();
+    for table in archive.tables() {
+        size += 12;
+        for resource in table.resources() {
+            size += 12;
+            if data.insert((resource.offset, resource.size)) {
+                size += u64::from(resource.size);
+            }
+        }
+    }
+    size
+}
+
+/// The results of repacking an archive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RepackReport {
+    /// The number of resources in the archive.
+    pub resources: usize,
+    /// The number of resources whose contents were already stored for another resource.
+    pub duplicates: usize,
+    /// The number of resources that were re-encoded.
+    pub recompressed: usize,
+    /// The size in bytes of the original archive.
+    pub original_size: u64,
+    /// The size in bytes of the repacked archive.
+    pub repacked_size: u64,
+}
+
+impl RepackReport {
+    /// Get the number of bytes that were saved by repacking.
+    pub fn saved(&self) -> u64 {
+        self.original_size.saturating_sub(self.repacked_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn archive(resources: &[(&str, u32, &[u8])]) -> anyhow::Result<DRSArchive<Cursor<Vec<u8>>>> {
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        for &(resource_type, id, data) in resources {
+            writer.add(resource_type, id, data)?;
+        }
+        Ok(DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?)
+    }
+
+    #[test]
+    fn deduplicate() -> anyhow::Result<()> {
+        let mut drs = archive(&[
+            ("slp", 1, b"sprite"),
+            ("slp", 2, b"sprite"),
+            ("wav", 1, b"sound"),
+            ("bina", 3, b"sprite"),
+        ])?;
+        let (output, report) = RepackOptions::new().repack(&mut drs, Cursor::new(vec![]))?;
+        assert_eq!((report.resources, report.duplicates), (4, 2));
+        assert_eq!(
+            report.original_size,
+            drs.into_inner().into_inner().len() as u64
+        );
+        assert_eq!(report.saved(), 12);
+
+        let data = output.into_inner();
+        assert_eq!(report.repacked_size, data.len() as u64);
+        let mut repacked = DRSArchive::new(Cursor::new(data))?;
+        for &(resource_type, id) in &[("slp", 1), ("slp", 2), ("bina", 3)] {
+            assert_eq!(
+                &repacked.read_resource(resource_type.into(), id)?[..],
+                b"sprite"
+            );
+        }
+        assert_eq!(&repacked.read_resource("wav".into(), 1)?[..], b"sound");
+
+        // Shared data is counted once, so storing every copy makes the archive larger.
+        let (_, report) = RepackOptions::new()
+            .deduplicate(false)
+            .repack(&mut repacked, Cursor::new(vec![]))?;
+        assert_eq!(report.duplicates, 0);
+        assert_eq!(report.repacked_size - report.original_size, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn recompress() -> anyhow::Result<()> {
+        let mut drs = archive(&[("txt", 1, b"aaaaaaaa"), ("txt", 2, b"ab")])?;
+        let (output, report) = RepackOptions::new()
+            .recompress(|_, _, data| Ok(Some(data[..2].to_vec())))
+            .repack(&mut drs, Cursor::new(vec![]))?;
+        assert_eq!((report.recompressed, report.saved()), (1, 6));
+
+        let mut repacked = DRSArchive::new(Cursor::new(output.into_inner()))?;
+        assert_eq!(&repacked.read_resource("txt".into(), 1)?[..], b"aa");
+        assert_eq!(&repacked.read_resource("txt".into(), 2)?[..], b"ab");
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{DRSArchive, MappedArchive, ResourceType};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Read, Seek};
+use std::path::{Path, PathBuf};
+
+fn not_found() -> Error {
+    Error::new(ErrorKind::NotFound, "Resource not found in this source")
+}
+
+/// Get the resource type and ID of a file named like `123.slp`.
+fn file_resource(name: &str) -> Option<(ResourceType, u32)> {
+    let (id, ext) = name.rsplit_once('.')?;
+    let id = id.parse().ok()?;
+    let resource_type = ext.to_ascii_lowercase().parse().ok()?;
+    Some((resource_type, id))
+}
+
+/// A place to read game assets from.
+///
+/// The original games store their assets in DRS archives, while HD Edition and Definitive Edition
+/// use loose files in directory trees. Code that reads sprites, sounds or palettes can use this
+/// trait to support every edition.
+///
+/// Assets are found by their resource type and ID, or by their file name. Files named like
+/// `123.slp` refer to the resource with ID 123 in the `slp` table, so they can be found in DRS
+/// archives as well. Other names, like the sprite names that Definitive Edition uses, can only be
+/// found in directories.
+pub trait AssetSource {
+    /// Check if this source contains a resource.
+    fn contains(&self, resource_type: ResourceType, id: u32) -> bool;
+
+    /// Read a resource. Returns an error of kind [`ErrorKind::NotFound`] if this source does not
+    /// contain the resource.
+    fn read(&mut self, resource_type: ResourceType, id: u32) -> Result<Vec<u8>, Error>;
+
+    /// Check if this source contains a file.
+    fn contains_file(&self, name: &str) -> bool {
+        match file_resource(name) {
+            Some((resource_type, id)) => self.contains(resource_type, id),
+            None => false,
+        }
+    }
+
+    /// Read a file. Returns an error of kind [`ErrorKind::NotFound`] if this source does not
+    /// contain the file.
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        let (resource_type, id) = file_resource(name).ok_or_else(not_found)?;
+        self.read(resource_type, id)
+    }
+}
+
+impl<S> AssetSource for Box<S>
+where
+    S: AssetSource + ?Sized,
+{
+    fn contains(&self, resource_type: ResourceType, id: u32) -> bool {
+        (**self).contains(resource_type, id)
+    }
+
+    fn read(&mut self, resource_type: ResourceType, id: u32) -> Result<Vec<u8>, Error> {
+        (**self).read(resource_type, id)
+    }
+
+    fn contains_file(&self, name: &str) -> bool {
+        (**self).contains_file(name)
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        (**self).read_file(name)
+    }
+}
+
+impl<R> AssetSource for DRSArchive<R>
+where
+    R: Read + Seek,
+{
+    fn contains(&self, resource_type: ResourceType, id: u32) -> bool {
+        self.get_resource(resource_type, id).is_some()
+    }
+
+    fn read(&mut self, resource_type: ResourceType, id: u32) -> Result<Vec<u8>, Error> {
+        Ok(self.read_resource(resource_type, id)?.into_vec())
+    }
+}
+
+impl<D> AssetSource for MappedArchive<D>
+where
+    D: AsRef<[u8]>,
+{
+    fn contains(&self, resource_type: ResourceType, id: u32) -> bool {
+        self.get_resource(resource_type, id).is_some()
+    }
+
+    fn read(&mut self, resource_type: ResourceType, id: u32) -> Result<Vec<u8>, Error> {
+        Ok(self.resource(resource_type, id)?.to_vec())
+    }
+}
+
+/// Loose asset files in a directory tree, like the `resources` directory of HD Edition and
+/// Definitive Edition.
+///
+/// Files are found by their name, in any subdirectory. Names are not case sensitive. If several
+/// files have the same name, the first one that is found is used, visiting the files in a
+/// directory before its subdirectories, in alphabetical order.
+#[derive(Debug, Clone)]
+pub struct AssetDirectory {
+    root: PathBuf,
+    /// Paths of the files, by their lowercase name.
+    files: HashMap<String, PathBuf>,
+}
+
+impl AssetDirectory {
+    /// List the files in the directory tree at `root`.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, Error> {
+        let root = root.as_ref().to_path_buf();
+        let mut files = HashMap::new();
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = fs::read_dir(&dir)?.collect::<Result<Vec<_>, _>>()?;
+            entries.sort_by_key(|entry| entry.file_name());
+            let mut subdirs = vec![];
+            for entry in entries {
+                let path = entry.path();
+                if entry.file_type()?.is_dir() {
+                    subdirs.push(path);
+                } else if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                    files.entry(name.to_lowercase()).or_insert(path);
+                }
+            }
+            // Directories are popped from the end, so push them in reverse.
+            dirs.extend(subdirs.into_iter().rev());
+        }
+        Ok(Self { root, files })
+    }
+
+    /// Get the root of the directory tree.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the path of a file.
+    pub fn path(&self, name: &str) -> Option<&Path> {
+        self.files.get(&name.to_lowercase()).map(PathBuf::as_path)
+    }
+
+    /// Get the number of files in the directory tree.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if the directory tree contains no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl AssetSource for AssetDirectory {
+    fn contains(&self, resource_type: ResourceType, id: u32) -> bool {
+        self.contains_file(&format!("{}.{}", id, resource_type))
+    }
+
+    fn read(&mut self, resource_type: ResourceType, id: u32) -> Result<Vec<u8>, Error> {
+        self.read_file(&format!("{}.{}", id, resource_type))
+    }
+
+    fn contains_file(&self, name: &str) -> bool {
+        self.path(name).is_some()
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        fs::read(self.path(name).ok_or_else(not_found)?)
+    }
+}
+
+/// Several asset sources, searched in order.
+///
+/// This can be used to read mods on top of the base game, or the loose files of HD Edition on top
+/// of its DRS archives.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use genie_drs::{AssetSource, DRSArchive, LayeredSource};
+///
+/// let mut assets = LayeredSource::new()
+///     .with(DRSArchive::new(File::open("test.drs")?)?);
+/// assert!(assets.contains_file("4.json"));
+/// assert_eq!(assets.read("json".parse()?, 4)?.len(), 710);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct LayeredSource<'a> {
+    sources: Vec<Box<dyn AssetSource + 'a>>,
+}
+
+impl<'a> LayeredSource<'a> {
+    /// Create an empty list of sources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source, that is searched after the sources that were added before it.
+    pub fn with(mut self, source: impl AssetSource + 'a) -> Self {
+        self.push(source);
+        self
+    }
+
+    /// Add a source, that is searched after the sources that were added before it.
+    pub fn push(&mut self, source: impl AssetSource + 'a) {
+        self.sources.push(Box::new(source));
+    }
+
+    /// Get the number of sources.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns `true` if there are no sources.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+impl std::fmt::Debug for LayeredSource<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayeredSource")
+            .field("sources", &self.sources.len())
+            .finish()
+    }
+}
+
+impl AssetSource for LayeredSource<'_> {
+    fn contains(&self, resource_type: ResourceType, id: u32) -> bool {
+        self.sources
+            .iter()
+            .any(|source| source.contains(resource_type, id))
+    }
+
+    fn read(&mut self, resource_type: ResourceType, id: u32) -> Result<Vec<u8>, Error> {
+        let source = self
+            .sources
+            .iter_mut()
+            .find(|source| source.contains(resource_type, id))
+            .ok_or_else(not_found)?;
+        source.read(resource_type, id)
+    }
+
+    fn contains_file(&self, name: &str) -> bool {
+        self.sources.iter().any(|source| source.contains_file(name))
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        let source = self
+            .sources
+            .iter_mut()
+            .find(|source| source.contains_file(name))
+            .ok_or_else(not_found)?;
+        source.read_file(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DRSWriter, InMemoryStrategy};
+    use std::io::Cursor;
+
+    #[test]
+    fn file_names() {
+        assert_eq!(file_resource("123.slp"), Some(("slp".into(), 123)));
+        assert_eq!(file_resource("50500.BINA"), Some(("bina".into(), 50500)));
+        assert_eq!(file_resource("u_arc_archer_idleA_x1.smx"), None);
+        assert_eq!(file_resource("1.toolong"), None);
+    }
+
+    #[test]
+    fn layered_sources() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("genie-drs-source-{}", std::process::id()));
+        fs::create_dir_all(dir.join("graphics"))?;
+        fs::write(dir.join("graphics").join("1.slp"), b"loose sprite")?;
+        fs::write(dir.join("u_arc_archer_x1.SMX"), b"named sprite")?;
+        let directory = AssetDirectory::new(&dir)?;
+        assert_eq!(directory.len(), 2);
+
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        writer.add("slp", 1, &b"archived sprite"[..])?;
+        writer.add("slp", 2, &b"other sprite"[..])?;
+        let archive = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+
+        let mut assets = LayeredSource::new().with(directory).with(archive);
+        let slp = ResourceType::from("slp");
+        assert_eq!(assets.read(slp, 1)?, b"loose sprite");
+        assert_eq!(assets.read(slp, 2)?, b"other sprite");
+        assert_eq!(assets.read_file("2.slp")?, b"other sprite");
+        assert_eq!(assets.read_file("U_ARC_ARCHER_X1.smx")?, b"named sprite");
+        assert!(!assets.contains(slp, 3));
+        assert_eq!(
+            assets.read_file("missing.smx").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{DRSArchive, ResourceKind, ResourceType, Wav, WavError};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{Error, Read, Seek};
+
+/// A problem found while verifying an archive.
+#[derive(Debug)]
+pub enum VerifyIssue {
+    /// The directory size in the header is smaller than the tables and resource entries.
+    DirectorySize { expected: u32, actual: u32 },
+    /// A table does not point to its resource entries.
+    TableOffset {
+        resource_type: ResourceType,
+        expected: u32,
+        actual: u32,
+    },
+    /// A table contains the same resource ID more than once.
+    DuplicateId {
+        resource_type: ResourceType,
+        id: u32,
+    },
+    /// A resource starts inside the header or the tables.
+    ResourceInDirectory {
+        resource_type: ResourceType,
+        id: u32,
+        offset: u32,
+    },
+    /// A resource extends past the end of the archive.
+    ResourceOutOfBounds {
+        resource_type: ResourceType,
+        id: u32,
+        offset: u32,
+        size: u32,
+    },
+    /// Two resources partially overlap. Resources that share exactly the same data are allowed.
+    OverlappingResources {
+        first: (ResourceType, u32),
+        second: (ResourceType, u32),
+    },
+    /// A WAV sound has a missing or broken header.
+    InvalidWav { id: u32, error: WavError },
+    /// An SLP sprite does not start with an SLP header.
+    InvalidSlp { id: u32 },
+}
+
+impl fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyIssue::DirectorySize { expected, actual } => write!(
+                f,
+                "directory size is {}, but should be at least {}",
+                actual, expected
+            ),
+            VerifyIssue::TableOffset {
+                resource_type,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "table `{}` is at offset {}, but should be at {}",
+                resource_type, actual, expected
+            ),
+            VerifyIssue::DuplicateId { resource_type, id } => {
+                write!(
+                    f,
+                    "table `{}` contains {} more than once",
+                    resource_type, id
+                )
+            }
+            VerifyIssue::ResourceInDirectory {
+                resource_type,
+                id,
+                offset,
+            } => write!(
+                f,
+                "{}.{} starts inside the tables, at offset {}",
+                id, resource_type, offset
+            ),
+            VerifyIssue::ResourceOutOfBounds {
+                resource_type,
+                id,
+                offset,
+                size,
+            } => write!(
+                f,
+                "{}.{} at offset {} with size {} extends past the end of the archive",
+                id, resource_type, offset, size
+            ),
+            VerifyIssue::OverlappingResources { first, second } => write!(
+                f,
+                "{}.{} overlaps {}.{}",
+                first.1, first.0, second.1, second.0
+            ),
+            VerifyIssue::InvalidWav { id, error } => write!(f, "{}.wav: {}", id, error),
+            VerifyIssue::InvalidSlp { id } => write!(f, "{}.slp: not an SLP sprite", id),
+        }
+    }
+}
+
+/// The results of verifying an archive with [`DRSArchive::verify`].
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// The number of resources in the archive.
+    pub resources: usize,
+    /// The problems that were found.
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// Returns `true` if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A resource entry, with its location in the archive.
+struct Entry {
+    resource_type: ResourceType,
+    id: u32,
+    offset: u32,
+    size: u32,
+}
+
+impl Entry {
+    fn end(&self) -> u64 {
+        u64::from(self.offset) + u64::from(self.size)
+    }
+}
+
+/// Check that a resource starts with an SLP version, like `2.0N`.
+fn is_slp(data: &[u8]) -> bool {
+    data.len() >= 32 && data[0].is_ascii_digit() && data[1] == b'.' && data[2].is_ascii_digit()
+}
+
+impl<R> DRSArchive<R>
+where
+    R: Read + Seek,
+{
+    /// Check the archive for problems that can crash the game, like resources outside of the
+    /// archive, overlapping resources, and broken WAV and SLP headers.
+    ///
+    /// Only I/O errors are returned as errors. Problems with the archive are listed in the
+    /// report.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::fs::File;
+    /// use genie_drs::DRSArchive;
+    ///
+    /// let mut drs = DRSArchive::new(File::open("test.drs")?)?;
+    /// let report = drs.verify()?;
+    /// for issue in &report.issues {
+    ///     println!("{}", issue);
+    /// }
+    /// assert!(report.is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify(&mut self) -> Result<VerifyReport, Error> {
+        let mut report = VerifyReport::default();
+        let archive_len = self.archive_len()?;
+        let header_size = self.variant().header_size();
+        let directory_size = self
+            .reader()
+            .header()
+            .map_or(0, |header| header.directory_size);
+
+        let mut entries = vec![];
+        let num_tables = self.tables().len() as u32;
+        let mut table_offset = header_size + 12 * num_tables;
+        for table in self.tables() {
+            if table.offset != table_offset {
+                report.issues.push(VerifyIssue::TableOffset {
+                    resource_type: table.resource_type,
+                    expected: table_offset,
+                    actual: table.offset,
+                });
+            }
+            table_offset += 12 * table.len() as u32;
+
+            let mut ids = HashSet::new();
+            for resource in table.resources() {
+                if !ids.insert(resource.id) {
+                    report.issues.push(VerifyIssue::DuplicateId {
+                        resource_type: table.resource_type,
+                        id: resource.id,
+                    });
+                }
+                entries.push(Entry {
+                    resource_type: table.resource_type,
+                    id: resource.id,
+                    offset: resource.offset,
+                    size: resource.size,
+                });
+            }
+        }
+        report.resources = entries.len();
+
+        let directory_end = table_offset;
+        if directory_size < directory_end {
+            report.issues.push(VerifyIssue::DirectorySize {
+                expected: directory_end,
+                actual: directory_size,
+            });
+        }
+
+        let mut valid = vec![];
+        for entry in entries {
+            if entry.offset < directory_end {
+                report.issues.push(VerifyIssue::ResourceInDirectory {
+                    resource_type: entry.resource_type,
+                    id: entry.id,
+                    offset: entry.offset,
+                });
+            } else if entry.end() > archive_len {
+                report.issues.push(VerifyIssue::ResourceOutOfBounds {
+                    resource_type: entry.resource_type,
+                    id: entry.id,
+                    offset: entry.offset,
+                    size: entry.size,
+                });
+            } else {
+                valid.push(entry);
+            }
+        }
+
+        // Compare each resource to the one that reaches furthest among the resources before it.
+        valid.sort_by_key(|entry| (entry.offset, entry.size));
+        let mut furthest: Option<&Entry> = None;
+        for entry in &valid {
+            if let Some(previous) = furthest {
+                let shared = previous.offset == entry.offset && previous.size == entry.size;
+                if !shared && entry.size > 0 && u64::from(entry.offset) < previous.end() {
+                    report.issues.push(VerifyIssue::OverlappingResources {
+                        first: (previous.resource_type, previous.id),
+                        second: (entry.resource_type, entry.id),
+                    });
+                }
+            }
+            match furthest {
+                Some(previous) if previous.end() >= entry.end() => (),
+                _ => furthest = Some(entry),
+            }
+        }
+
+        for entry in &valid {
+            if entry.resource_type == ResourceKind::Wav.resource_type() {
+                let data = self.read_resource(entry.resource_type, entry.id)?;
+                let result = Wav::from_bytes(data.into_vec()).and_then(|wav| wav.validate());
+                match result {
+                    Ok(()) => (),
+                    Err(WavError::IoError(error)) => return Err(error),
+                    Err(error) => report.issues.push(VerifyIssue::InvalidWav {
+                        id: entry.id,
+                        error,
+                    }),
+                }
+            } else if entry.resource_type == ResourceKind::Slp.resource_type() {
+                let data = self.read_resource(entry.resource_type, entry.id)?;
+                if !is_slp(&data) {
+                    report.issues.push(VerifyIssue::InvalidSlp { id: entry.id });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DRSWriter, InMemoryStrategy};
+    use byteorder::{ByteOrder, LE};
+    use std::io::Cursor;
+
+    fn wav() -> Vec<u8> {
+        let mut data = b"RIFF\x2c\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        data.extend_from_slice(&[0x40, 0x1f, 0, 0, 0x40, 0x1f, 0, 0, 1, 0, 8, 0]);
+        data.extend_from_slice(b"data\x08\0\0\0");
+        data.extend_from_slice(&[0x80; 8]);
+        data
+    }
+
+    /// Create an archive with a sprite, a sound and a text file, and change some of its bytes.
+    fn archive(edit: impl FnOnce(&mut [u8])) -> anyhow::Result<DRSArchive<Cursor<Vec<u8>>>> {
+        let mut slp = b"2.0N".to_vec();
+        slp.resize(32, 0);
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        writer.add(ResourceKind::Slp, 1, &slp[..])?;
+        writer.add(ResourceKind::Wav, 2, &wav()[..])?;
+        writer.add("txt", 3, &b"text"[..])?;
+        let mut data = writer.flush()?.into_inner();
+        edit(&mut data);
+        Ok(DRSArchive::new(Cursor::new(data))?)
+    }
+
+    // The tables start at 64, and the resource entries at 100, in the order they were added.
+    const SLP_ENTRY: usize = 100;
+    const WAV_ENTRY: usize = 112;
+    const TXT_ENTRY: usize = 124;
+
+    #[test]
+    fn valid_archive() -> anyhow::Result<()> {
+        let report = archive(|_| ())?.verify()?;
+        assert_eq!(report.resources, 3);
+        assert!(report.is_ok(), "{:?}", report.issues);
+        assert!(DRSArchive::new(std::fs::File::open("test.drs")?)?
+            .verify()?
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn broken_archive() -> anyhow::Result<()> {
+        let report = archive(|data| {
+            // Move the second table.
+            LE::write_u32(&mut data[64 + 12 + 4..], 0);
+            // Make the text file end past the end of the archive.
+            LE::write_u32(&mut data[TXT_ENTRY + 8..], 100);
+        })?
+        .verify()?;
+        assert!(matches!(
+            report.issues[..],
+            [
+                VerifyIssue::TableOffset {
+                    expected: 112,
+                    actual: 0,
+                    ..
+                },
+                VerifyIssue::ResourceOutOfBounds {
+                    id: 3,
+                    size: 100,
+                    ..
+                },
+            ]
+        ));
+
+        let report = archive(|data| {
+            // Move the sound into the middle of the sprite, and break the sprite's header.
+            let slp_offset = LE::read_u32(&data[SLP_ENTRY + 4..]);
+            LE::write_u32(&mut data[WAV_ENTRY + 4..], slp_offset + 4);
+            data[slp_offset as usize] = b'X';
+            // Point the text file at the table entries.
+            LE::write_u32(&mut data[TXT_ENTRY + 4..], 90);
+        })?
+        .verify()?;
+        assert!(matches!(
+            report.issues[..],
+            [
+                VerifyIssue::ResourceInDirectory {
+                    id: 3,
+                    offset: 90,
+                    ..
+                },
+                VerifyIssue::OverlappingResources {
+                    first: (_, 1),
+                    second: (_, 2)
+                },
+                VerifyIssue::InvalidSlp { id: 1 },
+                VerifyIssue::InvalidWav {
+                    id: 2,
+                    error: WavError::InvalidSignatureError
+                },
+            ]
+        ));
+        assert_eq!(
+            report.issues[0].to_string(),
+            "3.txt starts inside the tables, at offset 90"
+        );
+        Ok(())
+    }
+}
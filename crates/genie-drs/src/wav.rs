@@ -0,0 +1,303 @@
+use byteorder::{ByteOrder, LE};
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// An error occurred while reading a WAV sound.
+#[derive(Debug, thiserror::Error)]
+pub enum WavError {
+    /// The resource does not start with a RIFF WAVE header.
+    #[error("not a RIFF WAVE file")]
+    InvalidSignatureError,
+    /// A required chunk is missing.
+    #[error("missing the `{}` chunk", .0)]
+    MissingChunkError(&'static str),
+    /// The `fmt ` chunk is too short to describe the sound format.
+    #[error("the `fmt ` chunk is too short")]
+    InvalidFormatError,
+    /// The size in the RIFF header does not match the size of the file.
+    #[error("RIFF size is {}, but should be {}", .0, .1)]
+    RiffSizeError(u32, u32),
+    /// The `data` chunk claims to be larger than the rest of the file.
+    #[error("data chunk size is {}, but only {} bytes are available", .0, .1)]
+    DataSizeError(u32, u32),
+    /// An I/O error occurred while reading the resource.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+/// The format of a WAV sound, read from its `fmt ` and `data` chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavInfo {
+    /// The encoding of the samples: 1 for PCM, 2 for Microsoft ADPCM.
+    pub format_tag: u16,
+    /// The number of channels.
+    pub channels: u16,
+    /// The number of samples per second.
+    pub sample_rate: u32,
+    /// The number of bytes per second.
+    pub byte_rate: u32,
+    /// The size in bytes of a block of samples for all channels.
+    pub block_align: u16,
+    /// The number of bits per sample.
+    pub bits_per_sample: u16,
+    /// The size in bytes of the sample data.
+    pub data_len: u32,
+}
+
+impl WavInfo {
+    /// Get the length of the sound.
+    pub fn duration(&self) -> Duration {
+        let byte_rate = match self.byte_rate {
+            0 => u64::from(self.sample_rate) * u64::from(self.block_align),
+            byte_rate => u64::from(byte_rate),
+        };
+        if byte_rate == 0 {
+            return Duration::default();
+        }
+        Duration::from_nanos(u64::from(self.data_len) * 1_000_000_000 / byte_rate)
+    }
+}
+
+/// Locations of the parts of a WAV file that may need to be fixed.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    /// Offset of the size field of the `data` chunk.
+    data_size_offset: usize,
+    /// The size of the `data` chunk according to the header.
+    declared_data_len: u32,
+}
+
+/// A WAV sound resource.
+///
+/// The sounds in DRS archives are RIFF WAVE files, but some of them have incorrect sizes in their
+/// headers, which strict audio decoders reject. Reading a sound only requires the `fmt ` and
+/// `data` chunks to be present; [`Wav::validate`] checks the sizes, and [`Wav::fix_header`]
+/// corrects them.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::fs::File;
+/// use genie_drs::DRSArchive;
+///
+/// let mut archive = DRSArchive::new(File::open("sounds.drs")?)?;
+/// let mut sound = archive.wav(5000).unwrap().read_wav()?;
+/// println!("{}Hz, {:?}", sound.info().sample_rate, sound.info().duration());
+/// if sound.fix_header() {
+///     println!("fixed the header");
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wav {
+    data: Vec<u8>,
+    info: WavInfo,
+    data_offset: usize,
+}
+
+impl Wav {
+    /// Parse a WAV sound from its bytes.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, WavError> {
+        let (info, data_offset, _) = parse(&data)?;
+        Ok(Self {
+            data,
+            info,
+            data_offset,
+        })
+    }
+
+    /// Get the format of the sound.
+    pub fn info(&self) -> &WavInfo {
+        &self.info
+    }
+
+    /// Get the sample data.
+    pub fn samples(&self) -> &[u8] {
+        &self.data[self.data_offset..self.data_offset + self.info.data_len as usize]
+    }
+
+    /// Get the bytes of the WAV file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the bytes of the WAV file, to write it elsewhere.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Check that the sizes in the RIFF header and the `data` chunk match the size of the file.
+    pub fn validate(&self) -> Result<(), WavError> {
+        let (_, _, layout) = parse(&self.data)?;
+        let riff_size = LE::read_u32(&self.data[4..8]);
+        let expected = riff_size_of(&self.data);
+        if riff_size != expected {
+            return Err(WavError::RiffSizeError(riff_size, expected));
+        }
+        if layout.declared_data_len != self.info.data_len {
+            return Err(WavError::DataSizeError(
+                layout.declared_data_len,
+                self.info.data_len,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Correct the sizes in the RIFF header and the `data` chunk, so the file passes
+    /// [`Wav::validate`]. Returns `true` if anything was changed.
+    pub fn fix_header(&mut self) -> bool {
+        let layout = match parse(&self.data) {
+            Ok((_, _, layout)) => layout,
+            Err(_) => return false,
+        };
+        let mut changed = false;
+        let riff_size = riff_size_of(&self.data);
+        if LE::read_u32(&self.data[4..8]) != riff_size {
+            LE::write_u32(&mut self.data[4..8], riff_size);
+            changed = true;
+        }
+        if layout.declared_data_len != self.info.data_len {
+            let offset = layout.data_size_offset;
+            LE::write_u32(&mut self.data[offset..offset + 4], self.info.data_len);
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Get the correct RIFF size of a file.
+fn riff_size_of(data: &[u8]) -> u32 {
+    u32::try_from(data.len() - 8).unwrap_or(u32::MAX)
+}
+
+/// Read the chunks of a WAV file. Sizes that point past the end of the file are clamped.
+fn parse(data: &[u8]) -> Result<(WavInfo, usize, Layout), WavError> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(WavError::InvalidSignatureError);
+    }
+
+    let mut format = None;
+    let mut samples = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() && (format.is_none() || samples.is_none()) {
+        let chunk_id = &data[pos..pos + 4];
+        let declared = LE::read_u32(&data[pos + 4..pos + 8]);
+        let start = pos + 8;
+        let len = (declared as usize).min(data.len() - start);
+        match chunk_id {
+            b"fmt " => {
+                if len < 16 {
+                    return Err(WavError::InvalidFormatError);
+                }
+                format = Some(&data[start..start + 16]);
+            }
+            b"data" => {
+                let layout = Layout {
+                    data_size_offset: pos + 4,
+                    declared_data_len: declared,
+                };
+                samples = Some((start, len as u32, layout));
+            }
+            _ => (),
+        }
+        // Chunks are padded to an even size.
+        pos = start + len + (len & 1);
+    }
+
+    let format = format.ok_or(WavError::MissingChunkError("fmt "))?;
+    let (data_offset, data_len, layout) = samples.ok_or(WavError::MissingChunkError("data"))?;
+    let info = WavInfo {
+        format_tag: LE::read_u16(&format[0..2]),
+        channels: LE::read_u16(&format[2..4]),
+        sample_rate: LE::read_u32(&format[4..8]),
+        byte_rate: LE::read_u32(&format[8..12]),
+        block_align: LE::read_u16(&format[12..14]),
+        bits_per_sample: LE::read_u16(&format[14..16]),
+        data_len,
+    };
+    Ok((info, data_offset, layout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a mono 8-bit PCM sound at 8000Hz, with the given header sizes.
+    fn wav(riff_size: u32, data_size: u32, samples: &[u8]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&riff_size.to_le_bytes());
+        data.extend_from_slice(b"WAVEfmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 1, 0]);
+        data.extend_from_slice(&8000u32.to_le_bytes());
+        data.extend_from_slice(&8000u32.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 8, 0]);
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&data_size.to_le_bytes());
+        data.extend_from_slice(samples);
+        data
+    }
+
+    #[test]
+    fn read_info() -> anyhow::Result<()> {
+        let sound = Wav::from_bytes(wav(4036, 4000, &[0x80; 4000]))?;
+        sound.validate()?;
+        let info = sound.info();
+        assert_eq!((info.format_tag, info.channels), (1, 1));
+        assert_eq!((info.sample_rate, info.bits_per_sample), (8000, 8));
+        assert_eq!(info.duration(), Duration::from_millis(500));
+        assert_eq!(sound.samples().len(), 4000);
+
+        assert!(matches!(
+            Wav::from_bytes(b"RIFF\0\0\0\0AVI ".to_vec()),
+            Err(WavError::InvalidSignatureError)
+        ));
+        let mut no_data = wav(36, 0, &[]);
+        no_data.truncate(36);
+        assert!(matches!(
+            Wav::from_bytes(no_data),
+            Err(WavError::MissingChunkError("data"))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn fix_header() -> anyhow::Result<()> {
+        let mut sound = Wav::from_bytes(wav(0, 100, &[0x80; 10]))?;
+        assert_eq!(sound.info().data_len, 10);
+        assert!(matches!(
+            sound.validate(),
+            Err(WavError::RiffSizeError(0, 46))
+        ));
+        assert!(sound.fix_header());
+        sound.validate()?;
+        assert!(!sound.fix_header());
+        assert_eq!(sound.into_bytes(), wav(46, 10, &[0x80; 10]));
+
+        let sound = Wav::from_bytes(wav(46, 100, &[0x80; 10]))?;
+        assert!(matches!(
+            sound.validate(),
+            Err(WavError::DataSizeError(100, 10))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn read_resource() -> anyhow::Result<()> {
+        use crate::{DRSArchive, DRSWriter, InMemoryStrategy, ResourceKind};
+        use std::io::Cursor;
+
+        let mut writer = DRSWriter::new(Cursor::new(vec![]), InMemoryStrategy::default())?;
+        writer.add(ResourceKind::Wav, 5000, &wav(44, 8, &[0x80; 8])[..])?;
+        writer.add(ResourceKind::Wav, 5001, &b"not a sound"[..])?;
+        let mut archive = DRSArchive::new(Cursor::new(writer.flush()?.into_inner()))?;
+
+        let sound = archive.wav(5000).unwrap().read_wav()?;
+        assert_eq!(sound.info().duration(), Duration::from_millis(1));
+        assert!(matches!(
+            archive.wav(5001).unwrap().read_wav(),
+            Err(WavError::InvalidSignatureError)
+        ));
+        Ok(())
+    }
+}
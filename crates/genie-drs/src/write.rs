@@ -1,5 +1,7 @@
-use crate::{DRSHeader, DRSResource, DRSTable, ResourceType};
+use crate::{DRSHeader, DRSResource, DRSTable, DRSVariant, ResourceType};
 use byteorder::{WriteBytesExt, LE};
+use std::collections::hash_map::{DefaultHasher, HashMap};
+use std::hash::{Hash, Hasher};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 
 /// Strategy to use when writing files to the archive.
@@ -33,7 +35,38 @@ where
 /// This works best for archives with small files.
 #[derive(Default)]
 pub struct InMemoryStrategy {
-    resources: Vec<(ResourceType, Vec<u8>)>,
+    /// The table and index into `data` of each resource, in the order they were added.
+    resources: Vec<(ResourceType, usize)>,
+    data: Vec<Vec<u8>>,
+    deduplicate: bool,
+    /// Indices into `data`, by the hash of the bytes.
+    hashes: HashMap<u64, Vec<usize>>,
+}
+
+impl InMemoryStrategy {
+    /// Set whether to store resources with identical contents only once.
+    ///
+    /// The table entries of the duplicates all point to the same data, which the game supports.
+    pub fn deduplicate(mut self, deduplicate: bool) -> Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// Find the index of identical data that was added before.
+    fn find_duplicate(&mut self, bytes: &[u8]) -> Option<usize> {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let candidates = self.hashes.entry(hasher.finish()).or_default();
+        let data = &self.data;
+        let found = candidates
+            .iter()
+            .copied()
+            .find(|&index| data[index] == bytes);
+        if found.is_none() {
+            candidates.push(data.len());
+        }
+        found
+    }
 }
 
 impl<W> Strategy<W> for InMemoryStrategy
@@ -53,52 +86,64 @@ where
     ) -> Result<DRSResource, io::Error> {
         let mut bytes = vec![];
         data.read_to_end(&mut bytes)?;
-        assert!(bytes.len() < u32::max_value() as usize, "file too large");
+        assert!(bytes.len() < u32::MAX as usize, "file too large");
         resource.size = bytes.len() as u32;
-        self.resources.push((table, bytes));
+        let duplicate = if self.deduplicate {
+            self.find_duplicate(&bytes)
+        } else {
+            None
+        };
+        let index = duplicate.unwrap_or_else(|| {
+            self.data.push(bytes);
+            self.data.len() - 1
+        });
+        self.resources.push((table, index));
         Ok(resource)
     }
 
     fn close(&mut self, drs: &mut InnerDRSWriter<W>) -> Result<(), io::Error> {
         assert!(
-            self.resources.len() < u32::max_value() as usize,
+            self.resources.len() < u32::MAX as usize,
             "too many resources"
         );
 
         let num_tables = drs.tables.len();
         let num_resources = drs.tables.iter().fold(0, |acc, t| acc + t.len());
-        drs.header.directory_size = 64 + 12 * (num_tables + num_resources) as u32;
+        drs.header.directory_size = drs.header.size() + 12 * (num_tables + num_resources) as u32;
         drs.write_header()?;
 
         // Assign table offsets
-        let mut table_offset = 64 + 12 * (drs.tables.len() as u32);
+        let mut table_offset = drs.header.size() + 12 * (drs.tables.len() as u32);
         let mut file_offset = drs.header.directory_size;
+        let mut data_offsets = vec![None; self.data.len()];
+        let mut data_order = vec![];
         for table in drs.tables.iter_mut() {
             table.offset = table_offset;
             table_offset += 12 * (table.len() as u32);
 
-            // Assign file offsets
+            // Assign file offsets. Deduplicated resources share the offset of the first copy.
+            let resource_type = table.resource_type;
+            let mut indices = self
+                .resources
+                .iter()
+                .filter(|(t, _)| resource_type == *t)
+                .map(|&(_, index)| index);
             for res in table.resources.iter_mut() {
-                res.offset = file_offset;
-                file_offset += res.size;
+                let index = indices.next().expect("genie-drs bug: mismatch between InMemoryStrategy resources and DRSWriter table data");
+                res.offset = *data_offsets[index].get_or_insert_with(|| {
+                    let offset = file_offset;
+                    file_offset += res.size;
+                    data_order.push(index);
+                    offset
+                });
             }
         }
 
         // Write out all the table data
         drs.write_tables()?;
 
-        for table in &drs.tables {
-            let mut data = self.resources.iter().filter_map(|(t, bytes)| {
-                if table.resource_type == *t {
-                    Some(bytes)
-                } else {
-                    None
-                }
-            });
-            for _ in &table.resources {
-                let bytes = data.next().expect("genie-drs bug: mismatch between InMemoryStrategy resources and DRSWriter table data");
-                drs.output.write_all(&bytes)?;
-            }
+        for index in data_order {
+            drs.output.write_all(&self.data[index])?;
         }
 
         Ok(())
@@ -109,6 +154,7 @@ where
 pub struct ReserveDirectoryStrategy {
     reserved_tables: u32,
     file_space_left: u32,
+    /// Offset where the next file is written. This is only known once the header size is known.
     write_offset: u32,
 }
 
@@ -123,7 +169,7 @@ impl ReserveDirectoryStrategy {
         Self {
             reserved_tables,
             file_space_left: reserved_files,
-            write_offset: 64 + 12 * (reserved_tables + reserved_files),
+            write_offset: 0,
         }
     }
 }
@@ -133,11 +179,13 @@ where
     W: Write + Seek,
 {
     fn open(&mut self, drs: &mut InnerDRSWriter<W>) -> Result<(), io::Error> {
+        let reserved_size = 12 * (self.reserved_tables + self.file_space_left);
+        self.write_offset = drs.header.size() + reserved_size;
         drs.header.directory_size = self.write_offset;
         drs.write_header()?;
 
         // Write 0 bytes for reserved space at the top of the file.
-        let reserved_block = vec![0; self.write_offset as usize - 64];
+        let reserved_block = vec![0; reserved_size as usize];
         drs.output.write_all(&reserved_block)?;
         Ok(())
     }
@@ -153,7 +201,7 @@ where
         self.file_space_left -= 1;
 
         let len = std::io::copy(data, &mut drs.output)?;
-        assert!(len < u64::from(u32::max_value()), "file too large");
+        assert!(len < u64::from(u32::MAX), "file too large");
         resource.offset = self.write_offset;
         resource.size = len as u32;
         self.write_offset += resource.size;
@@ -167,12 +215,13 @@ where
         );
 
         // Update the resource type count
-        drs.output.seek(SeekFrom::Start(56))?;
+        drs.output
+            .seek(SeekFrom::Start(u64::from(drs.header.size() - 8)))?;
         drs.output.write_u32::<LE>(drs.header.num_resource_types)?;
         drs.output.seek(SeekFrom::Current(4))?;
 
         // Assign table offsets
-        let mut table_offset = 64 + 12 * (drs.tables.len() as u32);
+        let mut table_offset = drs.header.size() + 12 * (drs.tables.len() as u32);
         for table in drs.tables.iter_mut() {
             table.offset = table_offset;
             table_offset += 12 * (table.len() as u32);
@@ -257,9 +306,18 @@ impl<W> DRSWriter<W>
 where
     W: Write + Seek,
 {
-    /// Create a writer with the given strategy.
+    /// Create a writer with the given strategy, for an Age of Empires archive.
     pub fn new(output: W, strategy: impl Strategy<W> + 'static) -> io::Result<Self> {
-        let header = DRSHeader::default();
+        Self::with_variant(output, strategy, DRSVariant::default())
+    }
+
+    /// Create a writer with the given strategy, for an archive of the given variant.
+    pub fn with_variant(
+        output: W,
+        strategy: impl Strategy<W> + 'static,
+        variant: DRSVariant,
+    ) -> io::Result<Self> {
+        let header = DRSHeader::new(variant);
 
         let mut writer = Self {
             inner: InnerDRSWriter {
@@ -314,7 +372,7 @@ where
     /// Finish any writes that still need to happen and return the file handle.
     pub fn flush(mut self) -> io::Result<W> {
         assert!(
-            self.inner.tables.len() < u32::max_value() as usize,
+            self.inner.tables.len() < u32::MAX as usize,
             "too many tables"
         );
         self.inner.header.num_resource_types = self.inner.tables.len() as u32;
@@ -351,6 +409,44 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn star_wars_variant() -> anyhow::Result<()> {
+        use crate::DRSReader;
+
+        let mut drs = DRSWriter::with_variant(
+            Cursor::new(vec![]),
+            InMemoryStrategy::default(),
+            DRSVariant::StarWars,
+        )?;
+        drs.add("txt", 1, "example test file".as_bytes())?;
+        let memory = drs.flush()?.into_inner();
+
+        let mut drs = DRSWriter::with_variant(
+            Cursor::new(vec![]),
+            ReserveDirectoryStrategy::new(1, 1),
+            DRSVariant::StarWars,
+        )?;
+        drs.add("txt", 1, "example test file".as_bytes())?;
+        let reserve = drs.flush()?.into_inner();
+        assert_eq!(memory, reserve);
+
+        // The same archive as ONE_FILE, with a 20 byte longer banner.
+        assert!(memory.starts_with(b"Copyright (c) 2001 LucasArts"));
+        assert_eq!(&memory[60..64], b"1.00");
+        assert_eq!(memory.len(), ONE_FILE.len() + 20);
+        assert_eq!(&memory[memory.len() - 17..], b"example test file");
+
+        let mut input = Cursor::new(memory);
+        let archive = DRSReader::new(&mut input)?;
+        assert_eq!(archive.variant(), DRSVariant::StarWars);
+        let content = archive.read_resource(&mut input, "txt".into(), 1)?;
+        assert_eq!(&content[..], b"example test file");
+
+        let archive = DRSReader::new(&mut Cursor::new(ONE_FILE))?;
+        assert_eq!(archive.variant(), DRSVariant::AgeOfEmpires);
+        Ok(())
+    }
+
     #[test]
     fn one_file_memory() -> anyhow::Result<()> {
         let output = Cursor::new(vec![]);
@@ -0,0 +1,201 @@
+use crate::{GroupIndexError, Hotkey, HotkeyIndexError, HotkeyInfo, IndexError};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Identifies the action of a hotkey: the index of its group, and its index in the group.
+///
+/// Use the `HotkeyGroupId` enum and the hotkey enums like `VillagerHotkeys` to refer to known
+/// commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HotkeyCommand {
+    /// The index of the hotkey group.
+    pub group: usize,
+    /// The index of the hotkey in its group.
+    pub index: usize,
+}
+
+impl HotkeyCommand {
+    /// Create a command from a group index and a hotkey index.
+    ///
+    /// ```
+    /// use genie_hki::{HotkeyCommand, HotkeyGroupId, VillagerHotkeys};
+    ///
+    /// let command = HotkeyCommand::new(
+    ///     HotkeyGroupId::Villager as usize,
+    ///     VillagerHotkeys::BuildMill as usize,
+    /// );
+    /// assert_eq!(command.to_string(), "3:1");
+    /// ```
+    pub fn new(group: usize, index: usize) -> Self {
+        Self { group, index }
+    }
+}
+
+impl fmt::Display for HotkeyCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.group, self.index)
+    }
+}
+
+/// A keycode together with the modifier keys that must be held.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyBinding {
+    /// Keycode that activates the hotkey. 0 if the hotkey is unbound.
+    pub key: i32,
+    /// Whether the Ctrl key needs to be held.
+    pub ctrl: bool,
+    /// Whether the Alt key needs to be held.
+    pub alt: bool,
+    /// Whether the Shift key needs to be held.
+    pub shift: bool,
+}
+
+impl KeyBinding {
+    /// Create a binding for a keycode without modifier keys.
+    pub fn new(key: i32) -> Self {
+        Self {
+            key,
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if no key is bound.
+    pub fn is_unbound(&self) -> bool {
+        self.key == 0
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}",
+            if self.ctrl { "Ctrl-" } else { "" },
+            if self.alt { "Alt-" } else { "" },
+            if self.shift { "Shift-" } else { "" },
+            self.key
+        )
+    }
+}
+
+impl Hotkey {
+    /// Returns the key and modifier keys that activate this hotkey.
+    pub fn binding(&self) -> KeyBinding {
+        KeyBinding {
+            key: self.key,
+            ctrl: self.ctrl,
+            alt: self.alt,
+            shift: self.shift,
+        }
+    }
+
+    /// Returns a hotkey equivalent to this one but activated by `binding`.
+    pub fn with_binding(self, binding: KeyBinding) -> Self {
+        self.key(binding.key)
+            .ctrl(binding.ctrl)
+            .alt(binding.alt)
+            .shift(binding.shift)
+    }
+}
+
+impl HotkeyInfo {
+    /// Returns the key bindings of all hotkeys that are used in this file, by command.
+    ///
+    /// Unused hotkey slots, which have a string ID of -1, are skipped.
+    ///
+    /// ```rust
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::fs::File;
+    /// use genie_hki::{HotkeyCommand, HotkeyGroupId, HotkeyInfo, KeyBinding, UnitCommandHotkeys};
+    /// let mut f = File::open("test/files/aoc1.hki")?;
+    /// let info = HotkeyInfo::from(&mut f)?;
+    /// let bindings = info.bindings();
+    /// let build_economic = HotkeyCommand::new(
+    ///     HotkeyGroupId::UnitCommands as usize,
+    ///     UnitCommandHotkeys::BuildEconomic as usize,
+    /// );
+    /// assert_eq!(bindings[&build_economic], KeyBinding::new(66));
+    /// # Ok(()) }
+    /// ```
+    pub fn bindings(&self) -> BTreeMap<HotkeyCommand, KeyBinding> {
+        self.iter()
+            .enumerate()
+            .flat_map(|(group, hotkeys)| {
+                hotkeys
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, hotkey)| hotkey.string_id != -1)
+                    .map(move |(index, hotkey)| {
+                        (HotkeyCommand::new(group, index), hotkey.binding())
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the key binding of a single command, if the command exists in this file.
+    pub fn binding(&self, command: HotkeyCommand) -> Option<KeyBinding> {
+        self.group(command.group)
+            .and_then(|group| group.hotkey(command.index))
+            .map(Hotkey::binding)
+    }
+
+    /// Changes the key binding of a command. Returns an error if the command does not exist in
+    /// this file.
+    pub fn set_binding(
+        &mut self,
+        command: HotkeyCommand,
+        binding: KeyBinding,
+    ) -> Result<(), IndexError> {
+        let num_groups = self.num_groups();
+        let group = self
+            .group_mut(command.group)
+            .ok_or_else(|| GroupIndexError::new(command.group, num_groups))?;
+        let num_hotkeys = group.num_hotkeys();
+        let hotkey = group
+            .hotkey_mut(command.index)
+            .ok_or_else(|| HotkeyIndexError::new(command.index, num_hotkeys))?;
+        *hotkey = hotkey.with_binding(binding);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HotkeyGroupId, VillagerHotkeys};
+    use std::fs::File;
+
+    #[test]
+    fn edit_bindings() -> anyhow::Result<()> {
+        let mut f = File::open("test/files/hd0.hki")?;
+        let mut info = HotkeyInfo::from(&mut f)?;
+        let build_house = HotkeyCommand::new(
+            HotkeyGroupId::Villager as usize,
+            VillagerHotkeys::BuildHouse as usize,
+        );
+        let binding = KeyBinding {
+            key: 72,
+            shift: true,
+            ..Default::default()
+        };
+        assert_ne!(info.binding(build_house), Some(binding));
+        info.set_binding(build_house, binding)?;
+        assert_eq!(binding.to_string(), "Shift-72");
+
+        let mut data = vec![];
+        info.write_to(&mut data)?;
+        let written = HotkeyInfo::from(&mut &data[..])?;
+        assert_eq!(written.bindings()[&build_house], binding);
+        assert_eq!(written.bindings(), info.bindings());
+
+        assert!(info
+            .set_binding(HotkeyCommand::new(99, 0), binding)
+            .is_err());
+        assert!(info
+            .set_binding(HotkeyCommand::new(0, 99), binding)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{HotkeyCommand, HotkeyInfo};
+
+/// Lists the hotkeys that could not be carried over by [`HotkeyInfo::convert_to`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConversionReport {
+    defaults: Vec<HotkeyCommand>,
+    unmapped: Vec<HotkeyCommand>,
+}
+
+impl ConversionReport {
+    /// Check if every hotkey in the original profile was carried over.
+    pub fn is_complete(&self) -> bool {
+        self.unmapped.is_empty()
+    }
+
+    /// Get the commands in the converted profile that do not exist in the original profile, and
+    /// use the binding from the template.
+    pub fn defaults(&self) -> &[HotkeyCommand] {
+        &self.defaults
+    }
+
+    /// Get the commands in the original profile that do not exist in the target version.
+    pub fn unmapped(&self) -> &[HotkeyCommand] {
+        &self.unmapped
+    }
+}
+
+/// Get the index of every used hotkey in a group, with the number of hotkeys before it that have
+/// the same string ID.
+fn occurrences(string_ids: &[i32]) -> Vec<(usize, i32, usize)> {
+    string_ids
+        .iter()
+        .enumerate()
+        .filter(|(_, string_id)| **string_id != -1)
+        .map(|(index, string_id)| {
+            let nth = string_ids[..index]
+                .iter()
+                .filter(|other| *other == string_id)
+                .count();
+            (index, *string_id, nth)
+        })
+        .collect()
+}
+
+impl HotkeyInfo {
+    /// Convert this hotkey profile to a different game version.
+    ///
+    /// `template` is a profile for the target version, usually its default hotkeys. The
+    /// converted profile has the version, groups and hotkey slots of the template. Slots are
+    /// matched by their group and string ID, because game versions insert new hotkeys in the
+    /// middle of groups. Slots that only exist in the template keep the template's binding, and
+    /// hotkeys that do not exist in the target version are dropped; both are listed in the
+    /// report.
+    ///
+    /// ```rust
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::fs::File;
+    /// use genie_hki::HotkeyInfo;
+    /// let aoc = HotkeyInfo::from(&mut File::open("test/files/aoc1.hki")?)?;
+    /// let hd = HotkeyInfo::from(&mut File::open("test/files/hd0.hki")?)?;
+    /// let (converted, report) = aoc.convert_to(&hd);
+    /// assert_eq!(converted.version(), 3.0);
+    /// assert_eq!(converted.num_groups(), hd.num_groups());
+    /// println!("{} new hotkeys use the HD defaults", report.defaults().len());
+    /// # Ok(()) }
+    /// ```
+    pub fn convert_to(&self, template: &HotkeyInfo) -> (HotkeyInfo, ConversionReport) {
+        let mut converted = template.clone();
+        let mut report = ConversionReport::default();
+
+        let num_groups = self.num_groups().max(template.num_groups());
+        for group in 0..num_groups {
+            let string_ids = |info: &HotkeyInfo| -> Vec<i32> {
+                info.group(group)
+                    .map(|group| group.iter().map(|hotkey| hotkey.string_id).collect())
+                    .unwrap_or_default()
+            };
+            let source = occurrences(&string_ids(self));
+            let target = occurrences(&string_ids(template));
+
+            for &(index, string_id, nth) in &target {
+                let found = source
+                    .iter()
+                    .find(|(_, other_id, other_nth)| *other_id == string_id && *other_nth == nth)
+                    .and_then(|(source_index, _, _)| self.group(group)?.hotkey(*source_index));
+                match (found, converted.group_mut(group)) {
+                    (Some(original), Some(converted_group)) => {
+                        if let Some(hotkey) = converted_group.hotkey_mut(index) {
+                            *hotkey = hotkey.with_binding(original.binding());
+                        }
+                    }
+                    _ => report.defaults.push(HotkeyCommand::new(group, index)),
+                }
+            }
+
+            for &(index, string_id, nth) in &source {
+                if !target
+                    .iter()
+                    .any(|(_, other_id, other_nth)| *other_id == string_id && *other_nth == nth)
+                {
+                    report.unmapped.push(HotkeyCommand::new(group, index));
+                }
+            }
+        }
+
+        (converted, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn read(name: &str) -> anyhow::Result<HotkeyInfo> {
+        let mut f = File::open(format!("test/files/{}", name))?;
+        Ok(HotkeyInfo::from(&mut f)?)
+    }
+
+    #[test]
+    fn convert_aoc_to_userpatch() -> anyhow::Result<()> {
+        let aoc = read("aoc1.hki")?;
+        let userpatch = read("aoc2.hki")?;
+        let (converted, report) = aoc.convert_to(&userpatch);
+        assert!(report.is_complete());
+        // The Mill group and 9 hotkeys in existing groups are new.
+        assert_eq!(converted.num_groups(), 15);
+        assert_eq!(report.defaults().len(), 9);
+        assert!(report.defaults().contains(&HotkeyCommand::new(1, 63)));
+
+        let house = HotkeyCommand::new(3, 0);
+        assert_eq!(converted.binding(house), aoc.binding(house));
+
+        // Converting back drops the new hotkeys.
+        let (back, report) = converted.convert_to(&aoc);
+        assert_eq!(report.unmapped().len(), 9);
+        assert!(report.defaults().is_empty());
+        assert_eq!(back, aoc);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{HotkeyCommand, HotkeyInfo, KeyBinding};
+
+/// A command whose key binding differs between two hotkey profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BindingChange {
+    /// The command.
+    pub command: HotkeyCommand,
+    /// The binding in the original profile.
+    pub old: KeyBinding,
+    /// The binding in the changed profile.
+    pub new: KeyBinding,
+}
+
+impl HotkeyInfo {
+    /// Returns the commands whose key bindings differ between this profile and `other`, ordered
+    /// by command.
+    ///
+    /// Only commands that are used in both profiles are compared. To compare profiles for
+    /// different game versions, convert one of them first with [`HotkeyInfo::convert_to`].
+    ///
+    /// ```rust
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::fs::File;
+    /// use genie_hki::{HotkeyCommand, HotkeyInfo, KeyBinding};
+    /// let base = HotkeyInfo::from(&mut File::open("test/files/aoc1.hki")?)?;
+    /// let mut custom = base.clone();
+    /// custom.set_binding(HotkeyCommand::new(3, 0), KeyBinding::new(72))?;
+    ///
+    /// let changes = base.diff(&custom);
+    /// assert_eq!(changes.len(), 1);
+    /// assert_eq!(changes[0].new, KeyBinding::new(72));
+    /// # Ok(()) }
+    /// ```
+    pub fn diff(&self, other: &HotkeyInfo) -> Vec<BindingChange> {
+        let other = other.bindings();
+        self.bindings()
+            .into_iter()
+            .filter_map(|(command, old)| match other.get(&command) {
+                Some(&new) if new != old => Some(BindingChange { command, old, new }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Applies a partial set of key bindings on top of this profile, such as a shared hotkey
+    /// pack or the changes returned by [`HotkeyInfo::diff`].
+    ///
+    /// Bindings for commands that do not exist in this profile are skipped. Returns the
+    /// skipped commands.
+    ///
+    /// ```rust
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::fs::File;
+    /// use genie_hki::{HotkeyCommand, HotkeyInfo, KeyBinding};
+    /// let mut profile = HotkeyInfo::from(&mut File::open("test/files/aoc1.hki")?)?;
+    /// let pack = vec![
+    ///     (HotkeyCommand::new(3, 0), KeyBinding::new(72)),
+    ///     (HotkeyCommand::new(99, 0), KeyBinding::new(73)),
+    /// ];
+    /// let skipped = profile.merge(pack);
+    /// assert_eq!(skipped, [HotkeyCommand::new(99, 0)]);
+    /// assert_eq!(profile.binding(HotkeyCommand::new(3, 0)), Some(KeyBinding::new(72)));
+    /// # Ok(()) }
+    /// ```
+    pub fn merge(
+        &mut self,
+        bindings: impl IntoIterator<Item = (HotkeyCommand, KeyBinding)>,
+    ) -> Vec<HotkeyCommand> {
+        bindings
+            .into_iter()
+            .filter(|&(command, binding)| self.set_binding(command, binding).is_err())
+            .map(|(command, _)| command)
+            .collect()
+    }
+}
+
+impl From<BindingChange> for (HotkeyCommand, KeyBinding) {
+    fn from(change: BindingChange) -> Self {
+        (change.command, change.new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn diff_and_merge() -> anyhow::Result<()> {
+        let aoc = HotkeyInfo::from(&mut File::open("test/files/aoc2.hki")?)?;
+        let wk = HotkeyInfo::from(&mut File::open("test/files/wk.hki")?)?;
+        let changes = aoc.diff(&wk);
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|change| change.old != change.new));
+        assert!(wk.diff(&wk).is_empty());
+
+        // Applying the changes to the base profile gives the same bindings as the other one.
+        let mut merged = aoc.clone();
+        let skipped = merged.merge(changes.iter().copied().map(Into::into));
+        assert!(skipped.is_empty());
+        assert!(merged.diff(&wk).is_empty());
+        assert!(wk.diff(&merged).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+//! JSON export and import of hotkey files.
+//!
+//! Storing hotkeys as JSON lets external tools, such as web-based hotkey editors, read and edit
+//! the key bindings without parsing the compressed binary format.
+
+use crate::HotkeyInfo;
+use std::io::{Read, Result, Write};
+
+impl HotkeyInfo {
+    /// Export the hotkeys as pretty-printed JSON.
+    pub fn to_json(&self, output: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(output, self)?;
+        Ok(())
+    }
+
+    /// Import hotkeys from JSON created by [`HotkeyInfo::to_json`].
+    pub fn from_json(input: impl Read) -> Result<Self> {
+        Ok(serde_json::from_reader(input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn json_roundtrip() -> anyhow::Result<()> {
+        for name in &["aoc1.hki", "hd0.hki", "wk.hki"] {
+            let mut f = File::open(format!("test/files/{}", name))?;
+            let info = HotkeyInfo::from(&mut f)?;
+
+            let mut json = vec![];
+            info.to_json(&mut json)?;
+            assert_eq!(HotkeyInfo::from_json(&json[..])?, info, "{}", name);
+        }
+        Ok(())
+    }
+}
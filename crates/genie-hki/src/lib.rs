@@ -4,6 +4,17 @@
 //! hotkeys. Hotkeys have a string ID, a keycode, and flags
 //! for Ctrl/Alt/Shift modifiers. The index of the hotkey in its
 //! group determines the action that will be taken when it is activated.
+//!
+//! Version 1.0 files are used by The Conquerors, UserPatch and WololoKingdoms, and version 3.0
+//! files by HD Edition. Both versions use the same layout, but later game versions add hotkeys
+//! to some groups, and add groups. [`HotkeyInfo::convert_to`] converts a profile between them.
+//!
+//! [`HotkeyInfo::bindings`] lists the key bindings of every command, and
+//! [`HotkeyInfo::set_binding`] changes them. [`HotkeyInfo::diff`] lists the changed bindings
+//! between two profiles, and [`HotkeyInfo::merge`] applies a partial set of bindings, to share
+//! hotkey packs that only change some hotkeys. With the `serde` feature, all types can be
+//! serialized, and the `json` feature adds `HotkeyInfo::to_json` and
+//! `HotkeyInfo::from_json`.
 
 #![deny(future_incompatible)]
 #![deny(nonstandard_style)]
@@ -12,6 +23,15 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+mod bindings;
+mod convert;
+mod diff;
+#[cfg(feature = "json")]
+mod json;
+
+pub use bindings::{HotkeyCommand, KeyBinding};
+pub use convert::ConversionReport;
+pub use diff::BindingChange;
 use genie_lang::{LangFile, StringKey};
 
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
@@ -595,6 +615,7 @@ impl HotkeyIndexError {
 
 /// The information about a single hotkey.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hotkey {
     /// Keycode that activates this hotkey.
     ///
@@ -819,6 +840,7 @@ impl Hotkey {
 ///
 /// Different groups may have different numbers of hotkeys.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HotkeyGroup {
     /// The hotkeys in this group, ordered by the order they appear in a
     /// hotkey file.
@@ -926,7 +948,7 @@ impl HotkeyGroup {
         let hotkeys: Vec<String> = self
             .hotkeys
             .iter()
-            .map(|hki| hki.get_string_from_lang(&lang_file))
+            .map(|hki| hki.get_string_from_lang(lang_file))
             .collect();
         format!("{}{}", group_name, hotkeys.join("\n  "))
     }
@@ -970,6 +992,7 @@ impl<'a> IntoIterator for &'a mut HotkeyGroup {
 
 /// Represents a HKI file containing hotkey settings.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HotkeyInfo {
     /// The file version.
     version: f32,
@@ -1147,7 +1170,7 @@ impl HotkeyInfo {
             .groups
             .iter()
             .zip(him.iter())
-            .map(|(grp, sk)| grp.get_string_from_lang(&lang_file, sk))
+            .map(|(grp, sk)| grp.get_string_from_lang(lang_file, sk))
             .collect();
         format!("Version: {}\n{}", self.version, groups.join("\n"))
     }
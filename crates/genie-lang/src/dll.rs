@@ -0,0 +1,197 @@
+//! Writes resource-only DLLs containing string tables.
+//!
+//! The DLL is a 32-bit PE image with a single `.rsrc` section and no code, like the ones that
+//! resource compilers create with `/NOENTRY`. The resource section contains one directory tree:
+//! string tables (type 6), then one block of 16 strings per ID, then a single language-neutral
+//! entry pointing to the block data.
+
+use byteorder::{WriteBytesExt, LE};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+/// The PE resource type of string tables.
+const RT_STRING: u32 = 6;
+/// The alignment of sections in the file.
+const FILE_ALIGNMENT: u32 = 0x200;
+/// The alignment of sections in memory.
+const SECTION_ALIGNMENT: u32 = 0x1000;
+/// The address of the resource section in memory.
+const RSRC_RVA: u32 = SECTION_ALIGNMENT;
+/// Size of the optional header of 32-bit PE images, including the 16 data directories.
+const OPTIONAL_HEADER_SIZE: u16 = 224;
+
+/// Round `value` up to a multiple of `alignment`, which must be a power of two.
+fn align(value: u32, alignment: u32) -> u32 {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+fn invalid_input(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Group strings into the blocks of 16 strings that string tables are made of, by block ID.
+fn string_blocks<'a>(
+    strings: impl IntoIterator<Item = (u32, &'a str)>,
+) -> io::Result<BTreeMap<u16, [Vec<u16>; 16]>> {
+    let mut blocks: BTreeMap<u16, [Vec<u16>; 16]> = BTreeMap::new();
+    for (id, string) in strings {
+        let block_id = u16::try_from(id / 16 + 1)
+            .map_err(|_| invalid_input("string ID is too large for a string table"))?;
+        let units: Vec<u16> = string.encode_utf16().collect();
+        if units.len() > usize::from(u16::MAX) {
+            return Err(invalid_input("string is too long for a string table"));
+        }
+        blocks.entry(block_id).or_default()[(id % 16) as usize] = units;
+    }
+    Ok(blocks)
+}
+
+/// Build the contents of the resource section.
+fn resource_section(blocks: &BTreeMap<u16, [Vec<u16>; 16]>) -> io::Result<Vec<u8>> {
+    let num_blocks = blocks.len() as u32;
+    let type_dir = 16 + 8;
+    let language_dirs = type_dir + 16 + 8 * num_blocks;
+    let data_entries = language_dirs + 24 * num_blocks;
+    let mut data_offset = data_entries + 16 * num_blocks;
+
+    let mut section = vec![];
+    let write_dir = |section: &mut Vec<u8>, num_ids: u16| -> io::Result<()> {
+        section.write_u32::<LE>(0)?; // Characteristics
+        section.write_u32::<LE>(0)?; // TimeDateStamp
+        section.write_u16::<LE>(0)?; // MajorVersion
+        section.write_u16::<LE>(0)?; // MinorVersion
+        section.write_u16::<LE>(0)?; // NumberOfNamedEntries
+        section.write_u16::<LE>(num_ids)
+    };
+    // Subdirectory offsets have the high bit set.
+    let subdir = |offset: u32| offset | 0x8000_0000;
+
+    write_dir(&mut section, 1)?;
+    section.write_u32::<LE>(RT_STRING)?;
+    section.write_u32::<LE>(subdir(type_dir))?;
+
+    write_dir(&mut section, blocks.len() as u16)?;
+    for (index, block_id) in blocks.keys().enumerate() {
+        section.write_u32::<LE>(u32::from(*block_id))?;
+        section.write_u32::<LE>(subdir(language_dirs + 24 * index as u32))?;
+    }
+
+    for index in 0..num_blocks {
+        write_dir(&mut section, 1)?;
+        section.write_u32::<LE>(0)?; // LANG_NEUTRAL
+        section.write_u32::<LE>(data_entries + 16 * index)?;
+    }
+
+    let data: Vec<Vec<u8>> = blocks
+        .values()
+        .map(|strings| {
+            let mut data = vec![];
+            for units in strings {
+                data.write_u16::<LE>(units.len() as u16)?;
+                for unit in units {
+                    data.write_u16::<LE>(*unit)?;
+                }
+            }
+            Ok(data)
+        })
+        .collect::<io::Result<_>>()?;
+    for block in &data {
+        section.write_u32::<LE>(RSRC_RVA + data_offset)?;
+        section.write_u32::<LE>(block.len() as u32)?;
+        section.write_u32::<LE>(0)?; // CodePage
+        section.write_u32::<LE>(0)?; // Reserved
+        data_offset = align(data_offset + block.len() as u32, 4);
+    }
+    for block in &data {
+        section.extend_from_slice(block);
+        section.resize(align(section.len() as u32, 4) as usize, 0);
+    }
+    Ok(section)
+}
+
+/// Write a resource-only DLL containing the given strings.
+pub(crate) fn write_dll<'a, W: Write>(
+    output: &mut W,
+    strings: impl IntoIterator<Item = (u32, &'a str)>,
+) -> io::Result<()> {
+    let section = resource_section(&string_blocks(strings)?)?;
+    let section_size = section.len() as u32;
+    let raw_size = align(section_size, FILE_ALIGNMENT);
+
+    let mut headers = vec![];
+    // DOS header, only containing the signature and the offset of the PE header.
+    headers.extend_from_slice(b"MZ");
+    headers.resize(0x3C, 0);
+    headers.write_u32::<LE>(0x40)?;
+
+    // COFF header.
+    headers.extend_from_slice(b"PE\0\0");
+    headers.write_u16::<LE>(0x14C)?; // Machine: i386
+    headers.write_u16::<LE>(1)?; // NumberOfSections
+    headers.write_u32::<LE>(0)?; // TimeDateStamp
+    headers.write_u32::<LE>(0)?; // PointerToSymbolTable
+    headers.write_u32::<LE>(0)?; // NumberOfSymbols
+    headers.write_u16::<LE>(OPTIONAL_HEADER_SIZE)?;
+    headers.write_u16::<LE>(0x2102)?; // Characteristics: executable, 32-bit, DLL
+
+    // Optional header.
+    headers.write_u16::<LE>(0x10B)?; // Magic: PE32
+    headers.write_u8(0)?; // MajorLinkerVersion
+    headers.write_u8(0)?; // MinorLinkerVersion
+    headers.write_u32::<LE>(0)?; // SizeOfCode
+    headers.write_u32::<LE>(raw_size)?; // SizeOfInitializedData
+    headers.write_u32::<LE>(0)?; // SizeOfUninitializedData
+    headers.write_u32::<LE>(0)?; // AddressOfEntryPoint: none
+    headers.write_u32::<LE>(RSRC_RVA)?; // BaseOfCode
+    headers.write_u32::<LE>(RSRC_RVA)?; // BaseOfData
+    headers.write_u32::<LE>(0x1000_0000)?; // ImageBase
+    headers.write_u32::<LE>(SECTION_ALIGNMENT)?;
+    headers.write_u32::<LE>(FILE_ALIGNMENT)?;
+    headers.write_u16::<LE>(5)?; // MajorOperatingSystemVersion
+    headers.write_u16::<LE>(1)?; // MinorOperatingSystemVersion
+    headers.write_u16::<LE>(0)?; // MajorImageVersion
+    headers.write_u16::<LE>(0)?; // MinorImageVersion
+    headers.write_u16::<LE>(5)?; // MajorSubsystemVersion
+    headers.write_u16::<LE>(1)?; // MinorSubsystemVersion
+    headers.write_u32::<LE>(0)?; // Win32VersionValue
+    headers.write_u32::<LE>(RSRC_RVA + align(section_size, SECTION_ALIGNMENT))?; // SizeOfImage
+    headers.write_u32::<LE>(FILE_ALIGNMENT)?; // SizeOfHeaders
+    headers.write_u32::<LE>(0)?; // CheckSum
+    headers.write_u16::<LE>(2)?; // Subsystem: Windows GUI
+    headers.write_u16::<LE>(0x0540)?; // DllCharacteristics: dynamic base, NX compatible, no SEH
+    headers.write_u32::<LE>(0x10_0000)?; // SizeOfStackReserve
+    headers.write_u32::<LE>(0x1000)?; // SizeOfStackCommit
+    headers.write_u32::<LE>(0x10_0000)?; // SizeOfHeapReserve
+    headers.write_u32::<LE>(0x1000)?; // SizeOfHeapCommit
+    headers.write_u32::<LE>(0)?; // LoaderFlags
+    headers.write_u32::<LE>(16)?; // NumberOfRvaAndSizes
+    for index in 0..16 {
+        // Only the resource directory is present.
+        let (address, size) = if index == 2 {
+            (RSRC_RVA, section_size)
+        } else {
+            (0, 0)
+        };
+        headers.write_u32::<LE>(address)?;
+        headers.write_u32::<LE>(size)?;
+    }
+
+    // Section table.
+    headers.extend_from_slice(b".rsrc\0\0\0");
+    headers.write_u32::<LE>(section_size)?; // VirtualSize
+    headers.write_u32::<LE>(RSRC_RVA)?; // VirtualAddress
+    headers.write_u32::<LE>(raw_size)?; // SizeOfRawData
+    headers.write_u32::<LE>(FILE_ALIGNMENT)?; // PointerToRawData
+    headers.write_u32::<LE>(0)?; // PointerToRelocations
+    headers.write_u32::<LE>(0)?; // PointerToLinenumbers
+    headers.write_u16::<LE>(0)?; // NumberOfRelocations
+    headers.write_u16::<LE>(0)?; // NumberOfLinenumbers
+    headers.write_u32::<LE>(0x4000_0040)?; // Characteristics: initialized data, readable
+
+    headers.resize(FILE_ALIGNMENT as usize, 0);
+    output.write_all(&headers)?;
+    output.write_all(&section)?;
+    output.write_all(&vec![0; (raw_size - section_size) as usize])?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use crate::{LangFile, StringKey, StringSource};
+use std::collections::BTreeMap;
+
+/// A stack of language files, where strings in later files replace strings in earlier ones.
+///
+/// The game looks up strings in the language files of its expansions first, and falls back to
+/// the base game when they do not contain a string. Mods can replace strings in the same way by
+/// adding another layer on top.
+///
+/// ```rust
+/// use genie_lang::{LangEnv, LangFile, StringKey, StringSource};
+///
+/// let mut base = LangFile::new();
+/// base.insert(StringKey::from(5083u32), String::from("Archer"));
+/// base.insert(StringKey::from(5084u32), String::from("Crossbowman"));
+/// let mut x1 = LangFile::new();
+/// x1.insert(StringKey::from(5084u32), String::from("Arbalest"));
+/// let mut mod_strings = LangFile::new();
+/// mod_strings.insert(StringKey::from("MOD_TITLE"), String::from("Archers Only"));
+///
+/// let env = LangEnv::new().with(base).with(x1).with(mod_strings);
+/// assert_eq!(env.get_string(&StringKey::from(5083u32)), Some("Archer"));
+/// assert_eq!(env.get_string(&StringKey::from(5084u32)), Some("Arbalest"));
+/// assert_eq!(env.layer_of(&StringKey::from(5084u32)), Some(1));
+/// assert_eq!(env.get_string(&StringKey::from(5085u32)), None);
+///
+/// let strings: Vec<_> = env.iter().map(|(_, string)| string).collect();
+/// assert_eq!(strings, ["Archer", "Arbalest", "Archers Only"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LangEnv {
+    /// The language files, from the lowest to the highest priority.
+    layers: Vec<LangFile>,
+}
+
+impl LangEnv {
+    /// Creates an empty stack of language files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a language file on top of the stack, so its strings replace the strings of the
+    /// files that were added before it.
+    pub fn with(mut self, layer: LangFile) -> Self {
+        self.push(layer);
+        self
+    }
+
+    /// Adds a language file on top of the stack, so its strings replace the strings of the
+    /// files that were added before it.
+    pub fn push(&mut self, layer: LangFile) {
+        self.layers.push(layer);
+    }
+
+    /// Returns the language files, from the lowest to the highest priority.
+    pub fn layers(&self) -> &[LangFile] {
+        &self.layers
+    }
+
+    /// Returns the number of language files.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns `true` if there are no language files.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Returns the index of the language file that the string for `key` is resolved from.
+    pub fn layer_of(&self, key: &StringKey) -> Option<usize> {
+        self.layers
+            .iter()
+            .rposition(|layer| layer.contains_key(key))
+    }
+
+    /// An iterator visiting the strings that are used for every key, sorted by key.
+    ///
+    /// Keys that occur in several language files are visited once, with the string from the
+    /// file with the highest priority.
+    pub fn iter(&self) -> impl Iterator<Item = (&StringKey, &str)> {
+        let mut strings = BTreeMap::new();
+        for layer in &self.layers {
+            strings.extend(layer.iter().map(|(key, string)| (key, string.as_str())));
+        }
+        strings.into_iter()
+    }
+
+    /// Merges the language files into one, containing the strings that are used for every key.
+    ///
+    /// This can be used to export the strings that a game or mod shows to a single file.
+    pub fn flatten(&self) -> LangFile {
+        self.iter()
+            .map(|(key, string)| (key.clone(), string.to_string()))
+            .collect()
+    }
+}
+
+impl StringSource for LangEnv {
+    fn get_string(&self, key: &StringKey) -> Option<&str> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get_string(key))
+    }
+}
@@ -2,10 +2,14 @@
 //! All three major language file types used by Age of Empires versions are
 //! supported: DLLs, INI files, and HD Edition's key-value format.
 //!
-//! DLLs are used by the original games.
+//! DLLs are used by the original games. [`LangFile::read_dlls`] reads all language DLLs of an
+//! installation at once, and [`LangFile::write_to_dll`] writes a new DLL for localization mods.
 //! INI files are used for Voobly mods, and can be used with a standard
 //! AoC installation through the aoc-language-ini mod.
 //!
+//! Several language files can be stacked in a [`LangEnv`], which looks up strings in mod or
+//! expansion files before falling back to the base game, like the game does.
+//!
 //! ## DLLs
 //! ```rust
 //! # fn main() -> anyhow::Result<()> {
@@ -49,6 +53,18 @@
 //! # Ok(()) }
 //! ```
 //!
+//! INI files that are valid UTF-8 are read as UTF-8, and other INI files are
+//! read as Windows codepage 1252:
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use genie_lang::{LangFileType::Ini, StringKey};
+//! let utf8 = Ini.read_from("4201=Schwäne".as_bytes())?;
+//! let cp1252 = Ini.read_from(&b"4201=Schw\xE4ne"[..])?;
+//! assert_eq!(utf8.get(&StringKey::from(4201u32)), Some(&String::from("Schwäne")));
+//! assert_eq!(cp1252, utf8);
+//! # Ok(()) }
+//! ```
+//!
 //! ## HD key-value files
 //! ```rust
 //! # fn main() -> anyhow::Result<()> {
@@ -71,6 +87,27 @@
 //! # Ok(()) }
 //! ```
 //!
+//! Definitive Edition files start with a byte order mark, and contain
+//! comments. Values can contain escaped backslashes, line breaks and quotes:
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use genie_lang::{LangFileType::KeyValue, StringKey, StringSource};
+//! use std::io::Cursor;
+//! let text = concat!("\u{feff}", r#"// Campaign strings
+//! 10 "C:\\Games\\new folder" // a path
+//! 11 "Line one\nLine two"
+//! "#);
+//! let lang_file = KeyValue.read_from(Cursor::new(text))?;
+//! assert_eq!(lang_file.len(), 2);
+//! assert_eq!(lang_file.get_string(&StringKey::from(10u32)), Some(r"C:\Games\new folder"));
+//! assert_eq!(lang_file.get_string(&StringKey::from(11u32)), Some("Line one\nLine two"));
+//!
+//! let mut out = vec![];
+//! KeyValue.write_to(&lang_file, &mut out)?;
+//! assert_eq!(KeyValue.read_from(Cursor::new(out))?, lang_file);
+//! # Ok(()) }
+//! ```
+//!
 //! ## Creating a file from scratch
 //! ```rust
 //! # fn main() -> anyhow::Result<()> {
@@ -88,15 +125,10 @@
 //! lang_file.insert(StringKey::from("LOBBYBROWSER_DATMOD_TITLE_FORMAT"), String::from(r#"DatMod: "%s""#));
 //! let mut out = vec![];
 //! lang_file.write_to_keyval(&mut out)?;
-//! let result_string = str::from_utf8(&out)?;
-//! println!("{}", result_string);
-//! assert!(
-//!     result_string == r#"46604 "Kill the traitor, Kushluk.\n\nPrevent the tent of Genghis Khan (Wonder) from being destroyed."
+//! assert_eq!(
+//!     str::from_utf8(&out)?,
+//!     r#"46604 "Kill the traitor, Kushluk.\n\nPrevent the tent of Genghis Khan (Wonder) from being destroyed."
 //! LOBBYBROWSER_DATMOD_TITLE_FORMAT "DatMod: \"%s\""
-//! "#
-//!     ||
-//!     result_string == r#"LOBBYBROWSER_DATMOD_TITLE_FORMAT "DatMod: \"%s\""
-//! 46604 "Kill the traitor, Kushluk.\n\nPrevent the tent of Genghis Khan (Wonder) from being destroyed."
 //! "#);
 //! # Ok(()) }
 //! ```
@@ -108,10 +140,14 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+mod dll;
+mod env;
+
 use byteorder::{ReadBytesExt, LE};
-use encoding_rs::{UTF_16LE, WINDOWS_1252};
-use encoding_rs_io::DecodeReaderBytesBuilder;
-pub use genie_support::{StringKey, TryFromStringKeyError};
+use encoding_rs::UTF_16LE;
+pub use env::LangEnv;
+use genie_support::{decode_str_with_encoding, detect_encoding};
+pub use genie_support::{StringKey, StringSource, TryFromStringKeyError};
 use pelite::{
     pe32::{Pe, PeFile},
     resources::Name,
@@ -119,10 +155,12 @@ use pelite::{
 use std::collections::hash_map::{Drain, Entry, IntoIter, Iter, IterMut, Keys, Values, ValuesMut};
 use std::collections::HashMap;
 use std::fmt;
+use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader, Error as IoError, Read, Write};
 use std::iter::FromIterator;
 use std::num::ParseIntError;
 use std::ops::Index;
+use std::path::Path;
 use std::str::FromStr;
 
 /// Errors that may occur when loading a language file.
@@ -145,6 +183,12 @@ pub enum LoadError {
     ParseIntError(#[from] ParseIntError),
 }
 
+impl From<LoadError> for genie_support::Error {
+    fn from(err: LoadError) -> Self {
+        genie_support::Error::new("language file", err)
+    }
+}
+
 /// An error when parsing a string to a language file.
 ///
 /// The field contains the string that could not be parsed.
@@ -177,6 +221,17 @@ impl LangFileType {
         from_method(&mut lang_file, r)?;
         Ok(lang_file)
     }
+
+    /// Writes a language file to an output writer in this format.
+    /// Returns an `io::Error` if an error occurs while writing.
+    pub fn write_to<W: Write>(&self, lang_file: &LangFile, output: &mut W) -> io::Result<()> {
+        use LangFileType::{Dll, Ini, KeyValue};
+        match self {
+            Dll => lang_file.write_to_dll(output),
+            Ini => lang_file.write_to_ini(output),
+            KeyValue => lang_file.write_to_keyval(output),
+        }
+    }
 }
 
 impl FromStr for LangFileType {
@@ -186,12 +241,16 @@ impl FromStr for LangFileType {
         match &s.to_lowercase()[..] {
             "dll" => Ok(Dll),
             "ini" => Ok(Ini),
-            "key-value" => Ok(KeyValue),
+            "key-value" | "txt" => Ok(KeyValue),
             _ => Err(ParseLangFileTypeError(String::from(s))),
         }
     }
 }
 
+/// The names of the language DLLs of Age of Empires 2, in the order that they override each
+/// other: the base game, The Conquerors, and the 1.0c patch.
+pub const LANGUAGE_DLLS: [&str; 3] = ["language.dll", "language_x1.dll", "language_x1_p1.dll"];
+
 /// A mapping of `StringKey` key to `String` values.
 ///
 /// May be read from or written to one of the three file formats for Aoe2
@@ -212,7 +271,7 @@ impl LangFile {
         self.load_pe_file(pe)
     }
 
-    /// TODO specify
+    /// Loads the strings from the string table resources (type 6) of a PE file.
     fn load_pe_file(&mut self, pe: PeFile<'_>) -> Result<(), LoadError> {
         for root_dir_entry in pe.resources()?.root()?.entries() {
             if let Ok(Name::Id(6)) = root_dir_entry.name() {
@@ -224,16 +283,16 @@ impl LangFile {
         Ok(())
     }
 
-    /// TODO specify
+    /// Loads the string table blocks in a resource directory. Each block contains 16 strings, and
+    /// block `n` starts at string ID `(n - 1) * 16`.
     fn load_pe_directory(
         &mut self,
         directory: pelite::resources::Directory<'_>,
     ) -> Result<(), LoadError> {
         for entry in directory.entries() {
-            let base_index = if let Name::Id(n) = entry.name()? {
-                (n - 1) * 16
-            } else {
-                continue;
+            let base_index = match entry.name()? {
+                Name::Id(n) if n > 0 => (n - 1) * 16,
+                _ => continue,
             };
             if let Some(subdir) = entry.entry()?.dir() {
                 for data_entry in subdir.entries() {
@@ -246,7 +305,8 @@ impl LangFile {
         Ok(())
     }
 
-    /// TODO specify
+    /// Loads a block of strings, starting at string ID `index`. Each string is stored as its
+    /// length in UTF-16 code units, followed by the UTF-16 text. Empty strings are skipped.
     fn load_pe_data(&mut self, mut index: u32, data: &[u8]) -> Result<(), LoadError> {
         use std::io::{Cursor, Seek, SeekFrom};
         let mut cursor = Cursor::new(data);
@@ -257,9 +317,14 @@ impl LangFile {
                 continue;
             }
             let start = cursor.position() as usize;
-            let (string, _enc, failed) = UTF_16LE.decode(&data[start..start + len]);
-            if !failed {
-                self.0.insert(StringKey::from(index), string.to_string());
+            let bytes = data.get(start..start + len).ok_or_else(|| {
+                IoError::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "string extends past the end of the string table",
+                )
+            })?;
+            if let Ok(string) = decode_str_with_encoding(bytes, UTF_16LE) {
+                self.0.insert(StringKey::from(index), string);
             }
             cursor.seek(SeekFrom::Current(len as i64))?;
             index += 1;
@@ -267,22 +332,62 @@ impl LangFile {
         Ok(())
     }
 
+    /// Reads the language DLLs of an original game installation, such as its root directory.
+    ///
+    /// The DLLs are read in the order listed in [`LANGUAGE_DLLS`], so the strings of the
+    /// expansions replace the strings of the base game, like they do in the game. File names are
+    /// not case sensitive, and DLLs that do not exist are skipped. An error of kind
+    /// [`io::ErrorKind::NotFound`] is returned if the directory contains none of the DLLs.
+    ///
+    /// ```rust
+    /// # fn main() -> anyhow::Result<()> {
+    /// use genie_lang::{LangFile, StringKey};
+    /// use std::convert::TryFrom;
+    /// let lang_file = LangFile::read_dlls("./test/dlls")?;
+    /// assert!(lang_file.contains_key(&StringKey::try_from(30177).unwrap()));
+    /// assert!(LangFile::read_dlls("./src").is_err());
+    /// # Ok(()) }
+    /// ```
+    pub fn read_dlls(dir: impl AsRef<Path>) -> Result<LangFile, LoadError> {
+        let mut paths = HashMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                paths.insert(name.to_lowercase(), path);
+            }
+        }
+
+        let mut lang_file = LangFile::new();
+        let mut found = false;
+        for name in &LANGUAGE_DLLS {
+            if let Some(path) = paths.get(*name) {
+                lang_file.read_dll(File::open(path)?)?;
+                found = true;
+            }
+        }
+        if !found {
+            return Err(IoError::new(io::ErrorKind::NotFound, "no language DLLs found").into());
+        }
+        Ok(lang_file)
+    }
+
     /// Reads a language file from a .INI file, like the ones used by Voobly and
     /// the aoc-language-ini mod.
     ///
     /// This function eagerly loads all the strings into memory.
-    /// At this time, the encoding of the language.ini file is assumed to be
-    /// Windows codepage 1252.
+    /// The encoding of the language.ini file is detected with
+    /// [`genie_support::detect_encoding`]: files that are not valid UTF-8 are
+    /// read as Windows codepage 1252.
     ///
     /// Returns `Err(e)` where `e` is a `LoadError` if an error occurs while
     /// loading the file.
-    fn read_ini(&mut self, input: impl Read) -> Result<(), LoadError> {
-        let input = DecodeReaderBytesBuilder::new()
-            .encoding(Some(WINDOWS_1252))
-            .build(input);
-        let input = BufReader::new(input);
-        for line in input.lines() {
-            self.load_ini_line(&line?)?;
+    fn read_ini(&mut self, mut input: impl Read) -> Result<(), LoadError> {
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes)?;
+        let text =
+            decode_str_with_encoding(&bytes, detect_encoding(&bytes)).map_err(IoError::from)?;
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            self.load_ini_line(line)?;
         }
         Ok(())
     }
@@ -315,24 +420,33 @@ impl LangFile {
         Ok(())
     }
 
-    /// Reads a language file from an HD Edition-style key-value file.
+    /// Reads a language file from an HD Edition-style key-value file, like
+    /// the `key-value-strings-utf8.txt` files of HD Edition and Definitive
+    /// Edition.
     ///
     /// This function loads eagerly all the strings into memory.
     fn read_keyval(&mut self, input: impl Read) -> Result<(), LoadError> {
         let input = BufReader::new(input);
-        for line in input.lines() {
-            self.load_keyval_line(&line?)?;
+        for (index, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = if index == 0 {
+                line.trim_start_matches('\u{feff}')
+            } else {
+                &line
+            };
+            self.load_keyval_line(line)?;
         }
         Ok(())
     }
 
     /// Parses an HD Edition string line.
     ///
+    /// Lines contain a key and a quoted value, which may contain escape
+    /// sequences. Anything after the closing quote is ignored, as are empty
+    /// lines, lines starting with `//`, and lines without a quoted value.
+    ///
     /// The key value pair stored in the line is inserted to `self`, if parsed
     /// successfully.
-    /// A `LoadError` is returned if an error occurs while parsing.
-    ///
-    /// This is incomplete, unquoting and un-escaping is not yet done.
     fn load_keyval_line(&mut self, line: &str) -> Result<(), LoadError> {
         let line = line.trim();
         if line.starts_with("//") || line.is_empty() {
@@ -346,7 +460,6 @@ impl LangFile {
             .collect();
         let string_key = StringKey::from(id);
 
-        // TODO unquoting and un-escaping
         let mut iter = iter.skip_while(|&c| char::is_whitespace(c));
         let value = if let Some('"') = iter.next() {
             unescape(iter, true)
@@ -357,24 +470,68 @@ impl LangFile {
         Ok(())
     }
 
+    /// Returns the key-value pairs in this language file sorted by key, with
+    /// numeric keys first, so written files do not change between runs.
+    fn sorted(&self) -> impl Iterator<Item = (&StringKey, &String)> {
+        let mut strings: Vec<_> = self.0.iter().collect();
+        strings.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        strings.into_iter()
+    }
+
     /// Writes this language file to an output writer using the ini format.
     pub fn write_to_ini<W: Write>(&self, output: &mut W) -> io::Result<()> {
         // TODO warning if there are string ids
-        for (id, string) in self.iter().filter(|(id, _)| id.is_numeric()) {
+        for (id, string) in self.sorted().filter(|(id, _)| id.is_numeric()) {
             output.write_all(format!("{}={}\n", id, escape(string, false)).as_bytes())?;
         }
         Ok(())
     }
 
     /// Writes this language file to an output writer using the key-value
-    /// format.
+    /// format of HD Edition and Definitive Edition.
+    ///
+    /// Values are quoted and escaped, so they can contain line breaks and
+    /// quotes.
     pub fn write_to_keyval<W: Write>(&self, output: &mut W) -> io::Result<()> {
-        for (id, string) in self.iter() {
+        for (id, string) in self.sorted() {
             output.write_all(format!("{} \"{}\"\n", id, escape(string, true)).as_bytes())?;
         }
         Ok(())
     }
 
+    /// Writes this language file to an output writer as a resource-only DLL
+    /// with string tables, which the original games can load in place of
+    /// their language DLLs.
+    ///
+    /// Only numeric keys below 1048560 can be stored in DLLs; named keys are
+    /// skipped. To patch an existing DLL, read it, change the strings, and
+    /// write it again. Other resources in the original DLL, like its version
+    /// information, are not kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use genie_lang::{LangFileType::Dll, StringKey};
+    /// use std::{fs::File, io::Cursor};
+    ///
+    /// let mut lang_file = Dll.read_from(File::open("./test/dlls/language_x1_p1.dll")?)?;
+    /// lang_file.insert(StringKey::from(30177u32), String::from("Turbo!"));
+    /// let mut dll = vec![];
+    /// lang_file.write_to_dll(&mut dll)?;
+    ///
+    /// let patched = Dll.read_from(Cursor::new(dll))?;
+    /// assert_eq!(patched, lang_file);
+    /// # Ok(()) }
+    /// ```
+    pub fn write_to_dll<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let strings = self.iter().filter_map(|(id, string)| match id {
+            StringKey::Num(id) => Some((*id, string.as_str())),
+            StringKey::Name(_) => None,
+        });
+        dll::write_dll(output, strings)
+    }
+
     /// Creates an empty language file.
     ///
     /// # Examples
@@ -772,36 +929,51 @@ impl Index<&StringKey> for LangFile {
     }
 }
 
-// TODO specify
+impl StringSource for LangFile {
+    fn get_string(&self, key: &StringKey) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+/// Replaces escape sequences like `\n` with the characters they represent.
+///
+/// If `quoted` is true, the input is the text after an opening quote, and it
+/// is read up to the closing quote. Escaped quotes are allowed in that case.
+/// Backslashes that do not start a known escape sequence are kept.
 fn unescape(escaped: impl Iterator<Item = char>, quoted: bool) -> String {
     let mut unescaped = String::new();
-    let mut prev = 'x'; // Innocuous character
+    let mut escaping = false;
     for c in escaped {
-        // NOTE this does not support escapes like "\\n", which should
-        // print out "\n" literally, instead we get "\" followed by a newline.
-        // Could be solved by making `prev` an Option
-        match (prev, c) {
-            ('\\', '\\') => unescaped.push('\\'),
-            ('\\', 'n') => unescaped.push('\n'),
-            ('\\', 'r') => unescaped.push('\r'),
-            ('\\', 't') => unescaped.push('\t'),
-            ('\\', '"') if quoted => unescaped.push('"'),
-            (_, '"') if quoted => break,
-            (_, '\\') => {} // Might be escape, wait for one more
-            ('\\', c) => {
-                // Previous character was escape,
-                // but this is not part of a sequence
-                unescaped.push('\\');
-                unescaped.push(c);
+        if escaping {
+            match c {
+                '\\' => unescaped.push('\\'),
+                'n' => unescaped.push('\n'),
+                'r' => unescaped.push('\r'),
+                't' => unescaped.push('\t'),
+                '"' if quoted => unescaped.push('"'),
+                c => {
+                    // Not an escape sequence, keep the backslash.
+                    unescaped.push('\\');
+                    unescaped.push(c);
+                }
+            }
+            escaping = false;
+        } else {
+            match c {
+                '\\' => escaping = true,
+                '"' if quoted => break,
+                c => unescaped.push(c),
             }
-            (_, c) => unescaped.push(c),
         }
-        prev = c;
+    }
+    if escaping {
+        unescaped.push('\\');
     }
     unescaped
 }
 
-// TODO specify
+/// Escapes backslashes, line breaks and tabs, and quotes if `quoted` is
+/// true, so the string can be read back with `unescape`.
 fn escape(source: &str, quoted: bool) -> String {
     let mut escaped = String::new();
     for c in source.chars() {
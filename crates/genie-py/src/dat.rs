@@ -0,0 +1,75 @@
+use crate::read_source;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// A data file (empires2.dat, empires2_x1_p1.dat).
+#[pyclass(module = "genie_rs", unsendable)]
+pub struct DatFile {
+    inner: genie_dat::DatFile,
+}
+
+#[pymethods]
+impl DatFile {
+    /// Open a data file from a path or from `bytes`.
+    #[new]
+    fn new(source: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let bytes = read_source(source)?;
+        let inner = genie_dat::DatFile::read_from(&bytes[..])?;
+        Ok(Self { inner })
+    }
+
+    /// List the names of the civilizations. Civilization 0 is Gaia.
+    fn civilizations(&self) -> Vec<&str> {
+        self.inner
+            .civilizations
+            .iter()
+            .map(|civ| civ.name())
+            .collect()
+    }
+
+    /// List the unit types of a civilization as dicts with "id" and "name" keys.
+    #[pyo3(signature = (civilization = 0))]
+    fn unit_types<'py>(
+        &self,
+        py: Python<'py>,
+        civilization: u8,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let civ = match self.inner.get_civilization(civilization) {
+            Some(civ) => civ,
+            None => return Ok(vec![]),
+        };
+        civ.unit_types()
+            .iter()
+            .flatten()
+            .map(|unit_type| {
+                let dict = PyDict::new(py);
+                dict.set_item("id", u16::from(unit_type.static_.id))?;
+                dict.set_item("name", unit_type.static_.name())?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// List the techs as dicts with "id" and "name" keys.
+    fn techs<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.inner
+            .techs
+            .iter()
+            .enumerate()
+            .map(|(id, tech)| {
+                let dict = PyDict::new(py);
+                dict.set_item("id", id)?;
+                dict.set_item("name", tech.name())?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<DatFile {} civilizations, {} techs>",
+            self.inner.civilizations.len(),
+            self.inner.techs.len()
+        )
+    }
+}
@@ -0,0 +1,85 @@
+//! Python bindings for the genie-rs recorded game, scenario and data file readers.
+//!
+//! The bindings are built as the `genie_rs` Python module with [maturin](https://www.maturin.rs),
+//! which enables the `extension-module` feature.
+
+#![deny(future_incompatible)]
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(unsafe_code)]
+#![warn(unused)]
+
+mod dat;
+mod rec;
+mod scx;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+pub use dat::DatFile;
+pub use rec::RecordedGame;
+pub use scx::Scenario;
+
+/// Read the contents of a file from a Python `bytes` object, or from a path.
+fn read_source(source: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+    if let Ok(bytes) = source.extract::<Vec<u8>>() {
+        return Ok(bytes);
+    }
+    let path = source.extract::<PathBuf>()?;
+    Ok(std::fs::read(path)?)
+}
+
+/// Convert a parse error to a Python `ValueError`.
+fn value_error(err: impl std::error::Error) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// The `genie_rs` Python module.
+#[pymodule]
+fn genie_rs(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<RecordedGame>()?;
+    module.add_class::<Scenario>()?;
+    module.add_class::<DatFile>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    #[test]
+    fn python_api() -> anyhow::Result<()> {
+        Python::initialize();
+        Python::attach(|py| -> PyResult<()> {
+            let module = PyModule::new(py, "genie_rs")?;
+            genie_rs(&module)?;
+            let locals = PyDict::new(py);
+            locals.set_item("genie_rs", module)?;
+            py.run(
+                pyo3::ffi::c_str!(
+                    "
+rec = genie_rs.RecordedGame('../genie-rec/test/missyou_finally_vs_11.mgx')
+assert rec.release == 'The Conquerors', rec.release
+assert len(rec.players()) == 3, rec.players()
+assert rec.duration() > 0
+
+scen = genie_rs.Scenario(open('../genie-scx/test/scenarios/Age of Heroes b1-3-5.scx', 'rb').read())
+assert len(scen.objects()) > 0
+converted = genie_rs.Scenario(scen.to_bytes('hd'))
+assert converted.release == 'HD Edition', converted.release
+try:
+    scen.to_bytes('aoe3')
+    assert False
+except ValueError:
+    pass
+"
+                ),
+                None,
+                Some(&locals),
+            )
+        })?;
+        Ok(())
+    }
+}
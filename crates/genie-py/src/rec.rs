@@ -0,0 +1,136 @@
+use crate::{read_source, value_error, Scenario};
+use genie_rec::actions::{Action, Command};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::io::Cursor;
+
+/// A recorded game file (.mgl, .mgx, .mgz).
+///
+/// The file is read into memory when it is opened. The header and the actions are parsed every
+/// time they are requested.
+#[pyclass(module = "genie_rs")]
+pub struct RecordedGame {
+    inner: genie_rec::RecordedGame<Cursor<Vec<u8>>>,
+}
+
+/// Get the name of a command type, like "Move".
+fn command_name(command: &Command) -> String {
+    let debug = format!("{:?}", command);
+    match debug.find('(') {
+        Some(end) => debug[..end].to_string(),
+        None => debug,
+    }
+}
+
+/// Convert an action to a Python dict with a "type" key and the fields of the action.
+fn action_to_dict<'py>(py: Python<'py>, action: &Action) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    match action {
+        Action::Command(command) => {
+            dict.set_item("type", "command")?;
+            dict.set_item("command", command_name(command))?;
+        }
+        Action::Time(time) => {
+            dict.set_item("type", "time")?;
+            dict.set_item("time", time.time)?;
+        }
+        Action::Sync(sync) => {
+            dict.set_item("type", "sync")?;
+            dict.set_item("checksum", sync.checksum)?;
+        }
+        Action::ViewLock(view_lock) => {
+            dict.set_item("type", "view_lock")?;
+            dict.set_item("x", view_lock.x)?;
+            dict.set_item("y", view_lock.y)?;
+            dict.set_item("player", u8::from(view_lock.player))?;
+        }
+        Action::Chat(chat) => {
+            dict.set_item("type", "chat")?;
+            dict.set_item("message", chat.message())?;
+        }
+    }
+    Ok(dict)
+}
+
+#[pymethods]
+impl RecordedGame {
+    /// Open a recorded game from a path or from `bytes`.
+    #[new]
+    fn new(source: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let bytes = read_source(source)?;
+        let inner = genie_rec::RecordedGame::new(Cursor::new(bytes)).map_err(value_error)?;
+        Ok(Self { inner })
+    }
+
+    /// The game version string, like "VER 9.4".
+    #[getter]
+    fn game_version(&self) -> String {
+        self.inner
+            .game_version()
+            .to_string()
+            .trim_end_matches('\0')
+            .to_string()
+    }
+
+    /// The save version number, like 11.76.
+    #[getter]
+    fn save_version(&self) -> f32 {
+        self.inner.save_version().as_f32()
+    }
+
+    /// The name of the game release that recorded this game, or None if it is not known.
+    #[getter]
+    fn release(&self) -> Option<&'static str> {
+        self.inner.release().map(|release| release.name())
+    }
+
+    /// List the players as dicts with "name" and "civilization_id" keys, including Gaia.
+    fn players<'py>(&mut self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let header = self.inner.header().map_err(value_error)?;
+        header
+            .players()
+            .map(|player| {
+                let dict = PyDict::new(py);
+                dict.set_item("name", player.name())?;
+                dict.set_item("civilization_id", u8::from(player.civilization_id))?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// List all actions as dicts. The "type" key is one of "command", "time", "sync",
+    /// "view_lock" or "chat".
+    fn actions<'py>(&mut self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let actions = self.inner.actions().map_err(value_error)?;
+        actions
+            .map(|action| action_to_dict(py, &action.map_err(value_error)?))
+            .collect()
+    }
+
+    /// The duration of the game in milliseconds.
+    fn duration(&mut self) -> PyResult<u64> {
+        let mut duration = 0;
+        for action in self.inner.actions().map_err(value_error)? {
+            if let Action::Time(time) = action.map_err(value_error)? {
+                duration += u64::from(time.time);
+            }
+        }
+        Ok(duration)
+    }
+
+    /// Recover the map and the objects of each player as a scenario.
+    #[pyo3(name = "to_scenario")]
+    fn header_scenario(&mut self) -> PyResult<Scenario> {
+        let header = self.inner.header().map_err(value_error)?;
+        let scenario = header.to_scenario().map_err(value_error)?;
+        Ok(Scenario::from(scenario))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<RecordedGame {} {}>",
+            self.game_version(),
+            self.inner.save_version()
+        )
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{read_source, value_error};
+use genie_scx::VersionBundle;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::path::PathBuf;
+
+/// A scenario file (.scn, .scx, .aoe2scenario).
+#[pyclass(module = "genie_rs", unsendable)]
+pub struct Scenario {
+    inner: genie_scx::Scenario,
+}
+
+impl From<genie_scx::Scenario> for Scenario {
+    fn from(inner: genie_scx::Scenario) -> Self {
+        Self { inner }
+    }
+}
+
+/// Find the version bundle for a target version name, like "aoc" or "hd".
+fn version_bundle(name: &str) -> PyResult<VersionBundle> {
+    match name {
+        "aoe" => Ok(VersionBundle::aoe()),
+        "aok" => Ok(VersionBundle::aok()),
+        "aoc" => Ok(VersionBundle::aoc()),
+        "up14" => Ok(VersionBundle::userpatch_14()),
+        "up15" => Ok(VersionBundle::userpatch_15()),
+        "hd" => Ok(VersionBundle::hd_edition()),
+        "de" => Ok(VersionBundle::aoe2_de()),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown scenario version {:?}, expected one of aoe, aok, aoc, up14, up15, hd, de",
+            name
+        ))),
+    }
+}
+
+impl Scenario {
+    fn serialize(&self, version: Option<&str>) -> PyResult<Vec<u8>> {
+        let mut bytes = vec![];
+        match version {
+            Some(name) => self
+                .inner
+                .write_to_version(&mut bytes, &version_bundle(name)?),
+            None => self.inner.write_to(&mut bytes),
+        }
+        .map_err(value_error)?;
+        Ok(bytes)
+    }
+}
+
+#[pymethods]
+impl Scenario {
+    /// Open a scenario from a path or from `bytes`.
+    #[new]
+    fn new(source: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let bytes = read_source(source)?;
+        let inner = genie_scx::Scenario::read_from(&bytes[..]).map_err(value_error)?;
+        Ok(Self { inner })
+    }
+
+    /// The file name stored inside the scenario.
+    #[getter]
+    fn filename(&self) -> &str {
+        self.inner.filename()
+    }
+
+    /// The scenario description, or None if it has none.
+    #[getter]
+    fn description(&self) -> Option<&str> {
+        self.inner.description()
+    }
+
+    /// The scenario format version, like "1.21".
+    #[getter]
+    fn format_version(&self) -> String {
+        self.inner.format_version().to_string()
+    }
+
+    /// The name of the game release that this scenario was made for, or None if it is not known.
+    #[getter]
+    fn release(&self) -> Option<&'static str> {
+        self.inner.version().release().map(|release| release.name())
+    }
+
+    /// The width of the map in tiles.
+    #[getter]
+    fn width(&self) -> u32 {
+        self.inner.map().width()
+    }
+
+    /// The height of the map in tiles.
+    #[getter]
+    fn height(&self) -> u32 {
+        self.inner.map().height()
+    }
+
+    /// List the placed objects as dicts with "id", "player", "unit_type", "x", "y" and "angle"
+    /// keys. Player 0 is Gaia.
+    fn objects<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let mut objects = vec![];
+        let mut player = 0;
+        while let Some(list) = self.inner.player_objects(player) {
+            for object in list {
+                let dict = PyDict::new(py);
+                dict.set_item("id", object.id)?;
+                dict.set_item("player", player)?;
+                dict.set_item("unit_type", u16::from(object.object_type))?;
+                dict.set_item("x", object.position.0)?;
+                dict.set_item("y", object.position.1)?;
+                dict.set_item("angle", object.angle)?;
+                objects.push(dict);
+            }
+            player += 1;
+        }
+        Ok(objects)
+    }
+
+    /// Serialize the scenario. `version` is one of "aoe", "aok", "aoc", "up14", "up15", "hd"
+    /// or "de"; the scenario keeps its own version if it is None.
+    #[pyo3(signature = (version = None))]
+    fn to_bytes<'py>(
+        &self,
+        py: Python<'py>,
+        version: Option<&str>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        Ok(PyBytes::new(py, &self.serialize(version)?))
+    }
+
+    /// Save the scenario to a file, optionally converting it to a different version like
+    /// `to_bytes`.
+    #[pyo3(signature = (path, version = None))]
+    fn save(&self, path: PathBuf, version: Option<&str>) -> PyResult<()> {
+        std::fs::write(path, self.serialize(version)?)?;
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<Scenario {:?} {}>",
+            self.inner.filename(),
+            self.inner.format_version()
+        )
+    }
+}
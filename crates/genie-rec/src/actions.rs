@@ -1,11 +1,15 @@
 //! Player actions executed during a game.
 
-use crate::{ObjectID, PlayerID, Result};
+use crate::{expect_value, Error, ObjectID, PlayerID, Result};
 use arrayvec::ArrayVec;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{f32_neq, read_opt_u32, ReadSkipExt, ReadStringsExt, TechID, UnitTypeID};
-use std::convert::TryInto;
-use std::io::{Read, Write};
+use genie_support::{
+    decode_str_with_encoding, f32_neq, read_opt_i8, read_opt_u16, read_opt_u32, read_opt_u8,
+    write_opt_i32, write_opt_u16, write_opt_u32, write_opt_u8, AttributeID, ReadSkipExt,
+    ReadStringsExt, TechID, TryConvertExt, UnitTypeID, DEFAULT_ENCODING,
+};
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
 
 /// A location with an X and Y coordinate.
 pub type Location2 = (f32, f32);
@@ -32,7 +36,7 @@ impl ViewLock {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let x = input.read_f32::<LE>()?;
         let y = input.read_f32::<LE>()?;
-        let player = input.read_i32::<LE>()?.try_into().unwrap();
+        let player = input.read_i32::<LE>()?.try_convert()?;
         Ok(Self { x, y, player })
     }
 
@@ -40,7 +44,7 @@ impl ViewLock {
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_f32::<LE>(self.x)?;
         output.write_f32::<LE>(self.y)?;
-        output.write_i32::<LE>(self.player.try_into().unwrap())?;
+        output.write_i32::<LE>(self.player.try_convert()?)?;
         Ok(())
     }
 }
@@ -70,7 +74,7 @@ impl ObjectsList {
         if count < 0xFF {
             let mut list = vec![];
             for _ in 0..count {
-                list.push(input.read_i32::<LE>()?.try_into().unwrap());
+                list.push(input.read_i32::<LE>()?.try_convert()?);
             }
             Ok(ObjectsList::List(list))
         } else {
@@ -136,12 +140,8 @@ impl OrderCommand {
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_u8(self.player_id.into())?;
         output.write_all(&[0, 0])?;
-        output.write_i32::<LE>(
-            self.target_id
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
-        output.write_u32::<LE>(self.objects.len().try_into().unwrap())?;
+        write_opt_i32(&mut *output, self.target_id)?;
+        output.write_u32::<LE>(self.objects.len().try_convert()?)?;
         output.write_f32::<LE>(self.location.0)?;
         output.write_f32::<LE>(self.location.1)?;
         self.objects.write_to(output)?;
@@ -167,7 +167,7 @@ impl StopCommand {
 
     /// Write this Stop command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_i8(self.objects.len().try_into().unwrap())?;
+        output.write_i8(self.objects.len().try_convert()?)?;
         self.objects.write_to(output)?;
         Ok(())
     }
@@ -200,8 +200,8 @@ impl WorkCommand {
     /// Write this Work command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_all(&[0, 0, 0])?;
-        output.write_i32::<LE>(self.target_id.map(|u| u32::from(u) as i32).unwrap_or(-1))?;
-        output.write_i8(self.objects.len().try_into().unwrap())?;
+        write_opt_i32(&mut *output, self.target_id)?;
+        output.write_i8(self.objects.len().try_convert()?)?;
         output.write_all(&[0, 0, 0])?;
         output.write_f32::<LE>(self.location.0)?;
         output.write_f32::<LE>(self.location.1)?;
@@ -242,8 +242,8 @@ impl MoveCommand {
     /// Write this Move command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_all(&[0, 0, 0])?;
-        output.write_i32::<LE>(self.target_id.map(|u| u32::from(u) as i32).unwrap_or(-1))?;
-        output.write_i8(self.objects.len().try_into().unwrap())?;
+        write_opt_i32(&mut *output, self.target_id)?;
+        output.write_i8(self.objects.len().try_convert()?)?;
         output.write_all(&[0, 0, 0])?;
         output.write_f32::<LE>(self.location.0)?;
         output.write_f32::<LE>(self.location.1)?;
@@ -301,7 +301,7 @@ pub struct AddResourceCommand {
     /// The player this command applies to.
     pub player_id: PlayerID,
     /// The resource to add.
-    pub resource: u8,
+    pub resource: AttributeID,
     /// The amount to add to this resource. May be negative for subtracting.
     pub amount: f32,
 }
@@ -310,7 +310,7 @@ impl AddResourceCommand {
     /// Read an AddResource command from an input stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let player_id = input.read_u8()?.into();
-        let resource = input.read_u8()?;
+        let resource = input.read_u8()?.into();
         let _padding = input.read_u8()?;
         let amount = input.read_f32::<LE>()?;
         Ok(Self {
@@ -323,14 +323,13 @@ impl AddResourceCommand {
     /// Write this AddResource command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_u8(self.player_id.into())?;
-        output.write_u8(self.resource)?;
+        output.write_u8(self.resource.try_convert()?)?;
         output.write_u8(0)?;
         output.write_f32::<LE>(self.amount)?;
         Ok(())
     }
 }
 
-///
 #[derive(Debug, Default, Clone)]
 pub struct AIOrderCommand {
     pub player_id: PlayerID,
@@ -357,10 +356,7 @@ impl AIOrderCommand {
         command.order_priority = input.read_i8()?;
         let _padding = input.read_u8()?;
         command.target_id = read_opt_u32(&mut input)?;
-        command.target_player_id = match input.read_i8()? {
-            -1 => None,
-            id => Some(id.try_into().unwrap()),
-        };
+        command.target_player_id = read_opt_i8(&mut input)?;
         input.skip(3)?;
         command.target_location = (
             input.read_f32::<LE>()?,
@@ -380,7 +376,7 @@ impl AIOrderCommand {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_i8(self.objects.len().try_into().unwrap())?;
+        output.write_i8(self.objects.len().try_convert()?)?;
         output.write_u8(self.player_id.into())?;
         output.write_u8(self.issuer.into())?;
         match &self.objects {
@@ -392,11 +388,8 @@ impl AIOrderCommand {
         output.write_u16::<LE>(self.order_type)?;
         output.write_i8(self.order_priority)?;
         output.write_u8(0)?;
-        output.write_i32::<LE>(match self.target_id {
-            Some(id) => id.try_into().unwrap(),
-            None => -1,
-        })?;
-        output.write_u8(self.player_id.into())?;
+        write_opt_i32(&mut *output, self.target_id)?;
+        write_opt_u8(&mut *output, self.target_player_id)?;
         output.write_all(&[0, 0, 0])?;
         output.write_f32::<LE>(self.target_location.0)?;
         output.write_f32::<LE>(self.target_location.1)?;
@@ -467,7 +460,7 @@ impl GroupWaypointCommand {
 
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         output.write_u8(self.player_id.into())?;
-        output.write_u8(self.objects.len().try_into().unwrap())?;
+        output.write_u8(self.objects.len().try_convert()?)?;
         output.write_u8(self.location.0)?;
         output.write_u8(self.location.1)?;
         self.objects.write_to(&mut output)?;
@@ -495,7 +488,7 @@ impl UnitAIStateCommand {
 
     /// Write this UnitAIState command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u8(self.objects.len().try_into().unwrap())?;
+        output.write_u8(self.objects.len().try_convert()?)?;
         output.write_i8(self.state)?;
         self.objects.write_to(output)?;
         Ok(())
@@ -524,13 +517,9 @@ impl GuardCommand {
 
     /// Write a Guard command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u8(self.objects.len().try_into().unwrap())?;
+        output.write_u8(self.objects.len().try_convert()?)?;
         output.write_all(&[0, 0])?;
-        output.write_i32::<LE>(
-            self.target_id
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i32(&mut *output, self.target_id)?;
         self.objects.write_to(output)?;
         Ok(())
     }
@@ -558,13 +547,9 @@ impl FollowCommand {
 
     /// Write a Follow command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u8(self.objects.len().try_into().unwrap())?;
+        output.write_u8(self.objects.len().try_convert()?)?;
         output.write_all(&[0, 0])?;
-        output.write_i32::<LE>(
-            self.target_id
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i32(&mut *output, self.target_id)?;
         self.objects.write_to(output)?;
         Ok(())
     }
@@ -592,17 +577,19 @@ impl PatrolCommand {
         for w in raw_waypoints.iter_mut() {
             w.1 = input.read_f32::<LE>()?;
         }
-        command
-            .waypoints
-            .try_extend_from_slice(&raw_waypoints[0..usize::from(waypoint_count)])
-            .unwrap();
+        let waypoints = raw_waypoints
+            .get(..usize::from(waypoint_count))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "too many patrol waypoints")
+            })?;
+        command.waypoints = waypoints.iter().copied().collect();
         command.objects = ObjectsList::read_from(input, i32::from(selected_count))?;
         Ok(command)
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_i8(self.objects.len().try_into().unwrap())?;
-        output.write_u8(self.waypoints.len().try_into().unwrap())?;
+        output.write_i8(self.objects.len().try_convert()?)?;
+        output.write_u8(self.waypoints.len().try_convert()?)?;
         output.write_u8(0)?;
         for i in 0..10 {
             output.write_f32::<LE>(self.waypoints.get(i).cloned().unwrap_or_default().0)?;
@@ -638,7 +625,7 @@ impl FormFormationCommand {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_i8(self.objects.len().try_into().unwrap())?;
+        output.write_i8(self.objects.len().try_convert()?)?;
         output.write_u8(self.player_id.into())?;
         output.write_u8(0)?;
         output.write_i32::<LE>(self.formation_type)?;
@@ -673,12 +660,16 @@ pub struct UserPatchAICommand {
 
 impl UserPatchAICommand {
     pub fn read_from(mut input: impl Read, size: u32) -> Result<Self> {
-        let num_params = (size - 4) / 4;
-        assert!(
-            num_params < 4,
-            "UserPatchAICommand needs more room for {} params",
-            num_params
-        );
+        let num_params = size
+            .checked_sub(4)
+            .map(|params_size| params_size / 4)
+            .filter(|&num_params| num_params < 4)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "UserPatchAICommand has an unexpected size",
+                )
+            })?;
         let ai_action = input.read_u8()?;
         let player_id = input.read_u8()?.into();
         let _padding = input.read_u8()?;
@@ -734,10 +725,7 @@ impl MakeCommand {
         output.write_u8(self.player_id.into())?;
         output.write_u8(0)?;
         output.write_u16::<LE>(self.unit_type_id.into())?;
-        output.write_i32::<LE>(match self.target_id {
-            None => -1,
-            Some(id) => id.try_into().unwrap(),
-        })?;
+        write_opt_i32(&mut *output, self.target_id)?;
         Ok(())
     }
 }
@@ -777,10 +765,7 @@ impl ResearchCommand {
         output.write_u8(self.player_id.into())?;
         output.write_u8(0)?;
         output.write_u16::<LE>(self.tech_id.into())?;
-        output.write_i32::<LE>(match self.target_id {
-            None => -1,
-            Some(id) => id.try_into().unwrap(),
-        })?;
+        write_opt_i32(&mut *output, self.target_id)?;
         Ok(())
     }
 }
@@ -831,7 +816,7 @@ pub enum GameCommand {
     /// Not used in game, but implemented.
     Inventory {
         player_id: PlayerID,
-        attribute_id: i16,
+        attribute_id: AttributeID,
         amount: f32,
     },
     /// Not implemented in game.
@@ -919,54 +904,54 @@ impl GameCommand {
         use GameCommand::*;
         match game_command {
             0x01 => Ok(SetGameSpeed {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
                 speed: var3,
             }),
             0x02 => Ok(Inventory {
-                player_id: var1.try_into().unwrap(),
-                attribute_id: var2,
+                player_id: var1.try_convert()?,
+                attribute_id: var2.try_convert()?,
                 amount: var3,
             }),
             0x03 => Ok(UpgradeTown {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
             }),
             0x04 => Ok(QuickBuild {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
             }),
             0x05 => Ok(AlliedVictory {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
                 status: var2 != 0,
             }),
             0x06 => Ok(Cheat {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
                 cheat_id: var2,
             }),
             0x07 => Ok(SharedLos {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
             }),
             0x0a => Ok(Spies {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
             }),
             0x0b => Ok(SetStrategicNumber {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
                 strategic_number: var2,
-                value: var4.try_into().unwrap(),
+                value: var4.try_convert()?,
             }),
             0x0c => Ok(Unknown0x0c {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
             }),
             0x0d => Ok(AddFarmReseedQueue {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
                 amount: var2,
             }),
             0x0e => Ok(RemoveFarmReseedQueue {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
                 amount: var2,
             }),
             0x10 => Ok(FarmReseedAutoQueue {
-                player_id: var1.try_into().unwrap(),
+                player_id: var1.try_convert()?,
             }),
-            _ => panic!("unimplemented game command {:#x}", game_command),
+            _ => Err(Error::UnsupportedGameCommand(game_command)),
         }
     }
 }
@@ -990,20 +975,20 @@ impl BuildWallCommand {
         let _padding = input.read_u8()?;
         let unit_type_id = input.read_u16::<LE>()?.into();
         let _padding = input.read_u16::<LE>()?;
-        assert_eq!(
-            input.read_u32::<LE>()?,
-            0xFFFF_FFFF,
-            "check out what this is for"
-        );
+        expect_value("wall command", input.read_u32::<LE>()?, 0xFFFF_FFFF)?;
         let builders = if selected_count == -1 {
             ObjectsList::SameAsLast
         } else {
-            let mut list = vec![0; selected_count.try_into().unwrap()];
+            let mut list = vec![0; selected_count.try_convert()?];
             input.read_i32_into::<LE>(&mut list)?;
             if selected_count == 1 && list[0] == -1 {
                 list.clear();
             }
-            ObjectsList::List(list.into_iter().map(|id| id.try_into().unwrap()).collect())
+            ObjectsList::List(
+                list.into_iter()
+                    .map(|id| id.try_convert())
+                    .collect::<io::Result<_>>()?,
+            )
         };
         Ok(Self {
             player_id,
@@ -1028,7 +1013,7 @@ impl CancelBuildCommand {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         input.skip(3)?;
         let building_id = input.read_u32::<LE>()?.into();
-        let player_id = input.read_u32::<LE>()?.try_into().unwrap();
+        let player_id = input.read_u32::<LE>()?.try_convert()?;
         Ok(Self {
             player_id,
             building_id,
@@ -1038,7 +1023,7 @@ impl CancelBuildCommand {
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
         output.write_all(&[0, 0, 0])?;
         output.write_u32::<LE>(self.building_id.into())?;
-        output.write_u32::<LE>(self.player_id.try_into().unwrap())?;
+        output.write_u32::<LE>(self.player_id.try_convert()?)?;
         Ok(())
     }
 }
@@ -1059,13 +1044,13 @@ impl AttackGroundCommand {
         let selected_count = i32::from(input.read_i8()?);
         input.skip(2)?;
         command.location = (input.read_f32::<LE>()?, input.read_f32::<LE>()?);
-        command.objects = ObjectsList::read_from(input, selected_count as i32)?;
+        command.objects = ObjectsList::read_from(input, selected_count)?;
         Ok(command)
     }
 
     /// Write this AttackGround command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_i8(self.objects.len().try_into().unwrap())?;
+        output.write_i8(self.objects.len().try_convert()?)?;
         output.write_all(&[0, 0])?;
         output.write_f32::<LE>(self.location.0)?;
         output.write_f32::<LE>(self.location.1)?;
@@ -1096,13 +1081,9 @@ impl RepairCommand {
 
     /// Write a Repair command to an output stream.
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u8(self.repairers.len().try_into().unwrap())?;
+        output.write_u8(self.repairers.len().try_convert()?)?;
         output.write_all(&[0, 0])?;
-        output.write_i32::<LE>(
-            self.target_id
-                .map(|id| id.try_into().unwrap())
-                .unwrap_or(-1),
-        )?;
+        write_opt_i32(&mut *output, self.target_id)?;
         self.repairers.write_to(output)?;
         Ok(())
     }
@@ -1150,11 +1131,7 @@ impl FlareCommand {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let mut command = Self::default();
         input.skip(3)?;
-        assert_eq!(
-            input.read_i32::<LE>()?,
-            -1,
-            "found flare with unexpected unit id"
-        );
+        expect_value("flare command unit ID", input.read_i32::<LE>()?, -1)?;
         for receive in command.recipients.iter_mut() {
             *receive = input.read_u8()? != 0;
         }
@@ -1167,7 +1144,6 @@ impl FlareCommand {
     }
 }
 
-///
 #[derive(Debug, Default, Clone)]
 pub struct UnitOrderCommand {
     pub target_id: Option<ObjectID>,
@@ -1185,10 +1161,7 @@ impl UnitOrderCommand {
         let _padding = input.read_u16::<LE>()?;
         command.target_id = read_opt_u32(&mut input)?;
         command.action = input.read_i8()?;
-        command.param = match input.read_i8()? {
-            -1 => None,
-            param => Some(param as u8),
-        };
+        command.param = read_opt_u8(&mut input)?;
         let _padding = input.read_u16::<LE>()?;
         let x = input.read_f32::<LE>()?;
         let y = input.read_f32::<LE>()?;
@@ -1203,7 +1176,6 @@ impl UnitOrderCommand {
     }
 }
 
-///
 #[derive(Debug, Default, Clone)]
 pub struct QueueCommand {
     /// The ID of the building where this unit is being queued.
@@ -1233,7 +1205,6 @@ impl QueueCommand {
     }
 }
 
-///
 #[derive(Debug, Default, Clone)]
 pub struct SetGatherPointCommand {
     /// The IDs of the buildings whose gather points are being set.
@@ -1252,10 +1223,7 @@ impl SetGatherPointCommand {
         let selected_count = i32::from(input.read_i8()?);
         input.skip(2)?;
         command.target_id = read_opt_u32(&mut input)?;
-        command.target_type_id = match input.read_u16::<LE>()? {
-            0xFFFF => None,
-            id => Some(id.try_into().unwrap()),
-        };
+        command.target_type_id = read_opt_u16(&mut input)?;
         input.skip(2)?;
         command.location = Some((input.read_f32::<LE>()?, input.read_f32::<LE>()?));
         command.buildings = ObjectsList::read_from(input, selected_count)?;
@@ -1263,10 +1231,10 @@ impl SetGatherPointCommand {
     }
 
     pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
-        output.write_u8(self.buildings.len().try_into().unwrap())?;
+        output.write_u8(self.buildings.len().try_convert()?)?;
         output.write_all(&[0, 0])?;
-        output.write_u32::<LE>(self.target_id.map(|id| id.into()).unwrap_or(0xFFFF_FFFF))?;
-        output.write_u16::<LE>(self.target_type_id.map(|id| id.into()).unwrap_or(0xFFFF))?;
+        write_opt_u32(&mut *output, self.target_id)?;
+        write_opt_u16(&mut *output, self.target_type_id)?;
         output.write_all(&[0, 0])?;
         match self.location {
             Some((x, y)) => {
@@ -1291,7 +1259,7 @@ macro_rules! buy_sell_impl {
             pub fn read_from(mut input: impl Read) -> Result<Self> {
                 Ok(Self {
                     player_id: input.read_u8()?.into(),
-                    resource: input.read_u8()?,
+                    resource: input.read_u8()?.into(),
                     amount: input.read_i8()?,
                     market_id: input.read_u32::<LE>()?.into(),
                 })
@@ -1299,7 +1267,7 @@ macro_rules! buy_sell_impl {
 
             pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
                 output.write_u8(self.player_id.into())?;
-                output.write_u8(self.resource)?;
+                output.write_u8(self.resource.try_convert()?)?;
                 output.write_i8(self.amount)?;
                 output.write_u32::<LE>(self.market_id.into())?;
                 Ok(())
@@ -1314,7 +1282,7 @@ pub struct SellResourceCommand {
     /// The ID of the player issuing this command.
     pub player_id: PlayerID,
     /// The resource being sold.
-    pub resource: u8,
+    pub resource: AttributeID,
     /// The amount being sold, in 100s. Typically this is 1 for selling 100 of a resource, or 5 for
     /// selling 500 (with Shift-click).
     pub amount: i8,
@@ -1330,7 +1298,7 @@ pub struct BuyResourceCommand {
     /// The ID of the player issuing this command.
     pub player_id: PlayerID,
     /// The resource being bought.
-    pub resource: u8,
+    pub resource: AttributeID,
     /// The amount being bought, in 100s. Typically this is 1 for buying 100 of a resource, or 5 for
     /// buying 500 (with Shift-click).
     pub amount: i8,
@@ -1409,8 +1377,13 @@ pub enum Command {
 impl Command {
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
         let len = input.read_u32::<LE>()?;
+        let command = Self::read_payload(input.by_ref().take(len.into()), len);
+        let _world_time = input.read_u32::<LE>()?;
+        command
+    }
 
-        let mut cursor = input.by_ref().take(len.into());
+    /// Read a command from its `len` bytes of data, starting with the command type.
+    fn read_payload(mut cursor: impl Read, len: u32) -> Result<Self> {
         let command = match cursor.read_u8()? {
             0x00 => OrderCommand::read_from(&mut cursor).map(Command::Order),
             0x01 => StopCommand::read_from(&mut cursor).map(Command::Stop),
@@ -1444,12 +1417,10 @@ impl Command {
             0x7b => BuyResourceCommand::read_from(&mut cursor).map(Command::BuyResource),
             0x7f => Unknown7FCommand::read_from(&mut cursor).map(Command::Unknown7F),
             0x80 => BackToWorkCommand::read_from(&mut cursor).map(Command::BackToWork),
-            id => panic!("unsupported command type {:#x}", id),
+            id => Err(Error::UnsupportedCommandType(id)),
         };
         // Consume any excess bytes.
         std::io::copy(&mut cursor, &mut std::io::sink())?;
-
-        let _world_time = input.read_u32::<LE>()?;
         command
     }
 }
@@ -1527,7 +1498,7 @@ impl Meta {
     fn read_from_inner(mut input: impl Read) -> Result<Self> {
         let checksum_interval = input.read_u32::<LE>()?;
         let is_multiplayer = input.read_u32::<LE>()? != 0;
-        let local_player_id = input.read_u32::<LE>()?.try_into().unwrap();
+        let local_player_id = input.read_u32::<LE>()?.try_convert()?;
         let header_position = input.read_u32::<LE>()?;
         let use_sequence_numbers = input.read_u32::<LE>()? != 0;
         Ok(Self {
@@ -1558,7 +1529,13 @@ impl Meta {
     /// Conquerors and all subsequent versions.
     pub fn read_from_mgx(mut input: impl Read) -> Result<Self> {
         let log_version = input.read_u32::<LE>()?;
-        assert!(matches!(log_version, 3 | 4 | 5));
+        if !matches!(log_version, 3..=5) {
+            return Err(Error::UnexpectedValue {
+                context: "log version",
+                expected: 5,
+                found: log_version.into(),
+            });
+        }
         let mut meta = Self::read_from_inner(&mut input)?;
         meta.log_version = Some(log_version);
         if log_version == 5 {
@@ -1573,14 +1550,20 @@ impl Meta {
 }
 
 /// A chat message sent during the game.
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Chat {
     message: String,
 }
 
 impl Chat {
+    /// Get the text of this chat message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
     pub fn read_from<R: Read>(input: &mut R) -> Result<Self> {
-        assert_eq!(input.read_i32::<LE>()?, -1);
+        expect_value("chat message", input.read_i32::<LE>()?, -1)?;
         let message = input.read_u32_length_prefixed_str()?.unwrap_or_default();
         Ok(Self { message })
     }
@@ -1595,3 +1578,102 @@ pub enum Action {
     ViewLock(ViewLock),
     Chat(Chat),
 }
+
+/// A command in a recorded game body, borrowed from the body data.
+///
+/// Only the command type is read up front. Use [`BorrowedCommand::parse`] to read the rest of the
+/// command when it is needed.
+#[derive(Debug, Clone, Copy)]
+pub struct BorrowedCommand<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> BorrowedCommand<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Get the type of the command, like `0x03` for a move command.
+    pub fn command_type(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Get the data of the command, starting with the command type.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Read the command.
+    pub fn parse(&self) -> Result<Command> {
+        Command::read_payload(self.data, self.data.len().try_convert()?)
+    }
+}
+
+/// A chat message in a recorded game body, borrowed from the body data.
+#[derive(Debug, Clone, Copy)]
+pub struct BorrowedChat<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BorrowedChat<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        // Messages are null-terminated, like other strings.
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        Self {
+            bytes: &bytes[..end],
+        }
+    }
+
+    /// Get the encoded text of this chat message.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the text of this chat message. Plain ASCII messages are borrowed, others are decoded.
+    pub fn message(&self) -> Result<Cow<'a, str>> {
+        match std::str::from_utf8(self.bytes) {
+            // ASCII text is the same in every encoding that recorded games use.
+            Ok(message) if self.bytes.is_ascii() => Ok(Cow::Borrowed(message)),
+            _ => Ok(Cow::Owned(decode_str_with_encoding(
+                self.bytes,
+                DEFAULT_ENCODING,
+            )?)),
+        }
+    }
+
+    /// Decode the chat message.
+    pub fn to_chat(&self) -> Result<Chat> {
+        Ok(Chat {
+            message: self.message()?.into_owned(),
+        })
+    }
+}
+
+/// An action in a recorded game body, borrowed from the body data.
+///
+/// Commands and chat messages, which vary in size, are not copied out of the body. Use
+/// [`BorrowedAction::to_action`] to read the whole action.
+#[derive(Debug, Clone)]
+pub enum BorrowedAction<'a> {
+    Command(BorrowedCommand<'a>),
+    Time(Time),
+    Sync(Sync),
+    ViewLock(ViewLock),
+    Chat(BorrowedChat<'a>),
+}
+
+impl BorrowedAction<'_> {
+    /// Read the whole action.
+    pub fn to_action(&self) -> Result<Action> {
+        Ok(match self {
+            Self::Command(command) => Action::Command(command.parse()?),
+            Self::Time(time) => Action::Time(time.clone()),
+            Self::Sync(sync) => Action::Sync(sync.clone()),
+            Self::ViewLock(view_lock) => Action::ViewLock(view_lock.clone()),
+            Self::Chat(chat) => Action::Chat(chat.to_chat()?),
+        })
+    }
+}
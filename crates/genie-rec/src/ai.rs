@@ -1,10 +1,11 @@
 //! Read and write player AI state.
 
 use crate::unit::Waypoint;
-use crate::{ObjectID, PlayerID, Result};
+use crate::{Error, ObjectID, PlayerID, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{read_opt_u16, read_opt_u32, ReadSkipExt, ReadStringsExt, UnitTypeID};
-use std::convert::TryInto;
+use genie_support::{
+    read_opt_i8, read_opt_u16, read_opt_u32, ReadSkipExt, ReadStringsExt, SaveVersion, UnitTypeID,
+};
 use std::io::{Read, Write};
 
 /// The main AI module.
@@ -43,7 +44,7 @@ pub struct BuildItem {
 }
 
 impl BuildItem {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut item = BuildItem {
             name: input.read_u32_length_prefixed_str()?,
             type_id: input.read_u32::<LE>()?,
@@ -94,7 +95,7 @@ pub struct BuildAI {
 }
 
 impl BuildAI {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut ai = Self::default();
         let build_list_len = input.read_u32::<LE>()?;
         ai.build_list_name = input.read_u32_length_prefixed_str()?;
@@ -138,7 +139,7 @@ pub struct ConstructionItem {
 }
 
 impl ConstructionItem {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         let mut item = ConstructionItem {
             name: input.read_u32_length_prefixed_str()?,
             type_id: input.read_u32::<LE>()?,
@@ -169,7 +170,7 @@ pub struct ConstructionAI {
 }
 
 impl ConstructionAI {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut ai = Self::default();
         let num_lots = input.read_u32::<LE>()?;
         ai.plan_name = input.read_u32_length_prefixed_str()?;
@@ -244,7 +245,7 @@ pub struct ImportantObjectMemory {
 }
 
 impl ImportantObjectMemory {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         Ok(ImportantObjectMemory {
             id: read_opt_u32(&mut input)?,
             unit_type_id: read_opt_u16(&mut input)?,
@@ -298,7 +299,7 @@ pub struct WallLine {
 }
 
 impl WallLine {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut line = Self::default();
         if version >= 10.78 {
             line.line_type = input.read_u32::<LE>()?;
@@ -335,7 +336,7 @@ pub struct PerimeterWall {
 }
 
 impl PerimeterWall {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut wall = PerimeterWall {
             enabled: if version >= 11.22 {
                 input.read_u32::<LE>()? != 0
@@ -396,14 +397,8 @@ impl AttackMemory {
             min_y: input.read_u8()?,
             max_x: input.read_u8()?,
             max_y: input.read_u8()?,
-            attacking_owner: match input.read_i8()? {
-                -1 => None,
-                id => Some(id.try_into().unwrap()),
-            },
-            target_owner: match input.read_i8()? {
-                -1 => None,
-                id => Some(id.try_into().unwrap()),
-            },
+            attacking_owner: read_opt_i8(&mut input)?,
+            target_owner: read_opt_i8(&mut input)?,
             ..Default::default()
         };
         input.skip(1)?;
@@ -431,7 +426,7 @@ pub struct ResourceMemory {
 }
 
 impl ResourceMemory {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut mem = ResourceMemory {
             id: input.read_u32::<LE>()?.into(),
             location: (input.read_u8()?, input.read_u8()?),
@@ -518,7 +513,7 @@ pub struct InformationAI {
 
 impl InformationAI {
     #[allow(clippy::cognitive_complexity)]
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut ai = Self::default();
         for _ in 0..4096 {
             let _garbage = input.read_u32::<LE>()?;
@@ -748,7 +743,7 @@ impl InformationAI {
             );
         }
 
-        todo!()
+        Err(Error::Unsupported("Reading information AI data"))
     }
 }
 
@@ -788,7 +783,7 @@ pub struct StrategyAI {
 }
 
 impl StrategyAI {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         Ok(StrategyAI {
             current_victory_condition: input.read_u32::<LE>()?,
             target_id: input.read_u32::<LE>()?,
@@ -812,7 +807,7 @@ impl StrategyAI {
     }
 
     pub fn write_to(&self, _output: impl Write) -> Result<()> {
-        todo!()
+        Err(Error::Unsupported("Writing strategy AI data"))
     }
 }
 
@@ -842,7 +837,7 @@ pub struct TacticalAI {
 }
 
 impl TacticalAI {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         let mut ai = TacticalAI {
             civilians: read_id_list(&mut input)?,
             civilian_explorers: read_id_list(&mut input)?,
@@ -864,11 +859,11 @@ impl TacticalAI {
         ai.artifacts = read_id_list(&mut input)?;
         ai.trade_carts = read_id_list(&mut input)?;
 
-        todo!()
+        Err(Error::Unsupported("Reading tactical AI data"))
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing tactical AI data"))
     }
 }
 
@@ -885,7 +880,7 @@ pub struct PlayerAI {
 }
 
 impl PlayerAI {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let main_ai = MainAI::read_from(&mut input)?;
         let build_ai = BuildAI::read_from(&mut input, version)?;
         let construction_ai = ConstructionAI::read_from(&mut input, version)?;
@@ -907,8 +902,8 @@ impl PlayerAI {
         })
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing player AI data"))
     }
 }
 
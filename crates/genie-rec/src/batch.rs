@@ -0,0 +1,183 @@
+//! Process many recorded games in parallel.
+//!
+//! Statistics over large numbers of recorded games all have the same shape: find the files, parse
+//! each of them, extract a few values, and collect them while skipping the files that could not be
+//! read. [`process_dir`] does this on the rayon thread pool.
+//!
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use genie_rec::batch::process_dir;
+//!
+//! let results = process_dir("test", |rec| -> genie_rec::Result<_> {
+//!     Ok(rec.header()?.players().count())
+//! })?;
+//! for (path, result) in results {
+//!     match result {
+//!         Ok(num_players) => println!("{}: {} players", path.display(), num_players),
+//!         Err(err) => println!("{}: {}", path.display(), err),
+//!     }
+//! }
+//! # Ok(()) }
+//! ```
+
+use crate::{Error, RecordedGame};
+use rayon::prelude::*;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+/// File extensions of recorded games, in lower case.
+pub const EXTENSIONS: &[&str] = &["mgl", "mgx", "mgz", "aoe2record"];
+
+/// A recorded game that was read into memory for batch processing.
+pub type InMemoryGame = RecordedGame<Cursor<Vec<u8>>>;
+
+/// The result of processing one recorded game in a batch.
+pub type BatchResult<T, E> = std::result::Result<T, BatchError<E>>;
+
+/// Why a recorded game in a batch could not be processed.
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError<E> {
+    /// The file could not be read, or is not a recorded game.
+    #[error("could not read recorded game: {0}")]
+    Read(#[source] Error),
+    /// The extractor returned an error.
+    #[error(transparent)]
+    Extract(E),
+}
+
+/// Find all recorded games in a directory and its subdirectories, sorted by path.
+pub fn find_recorded_games(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                walk(&path, paths)?;
+            } else if is_recorded_game(&path) {
+                paths.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut paths = vec![];
+    walk(dir.as_ref(), &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Check if a path has the file extension of a recorded game.
+fn is_recorded_game(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+/// Run `extract` on all recorded games in a directory and its subdirectories, in parallel.
+///
+/// Returns the result for each file, sorted by path. A file that fails to parse, or that makes
+/// `extract` fail, does not affect the other files. Only failing to list the directory is
+/// an error.
+pub fn process_dir<T, E, F>(
+    dir: impl AsRef<Path>,
+    extract: F,
+) -> io::Result<Vec<(PathBuf, BatchResult<T, E>)>>
+where
+    T: Send,
+    E: Send,
+    F: Fn(&mut InMemoryGame) -> std::result::Result<T, E> + Sync,
+{
+    Ok(process_files(find_recorded_games(dir)?, extract))
+}
+
+/// Run `extract` on the given recorded games, in parallel.
+///
+/// Each file is read into memory before parsing, so `extract` can use
+/// [`RecordedGame::borrowed_actions`]. The results are in the same order as `paths`.
+pub fn process_files<T, E, F>(paths: Vec<PathBuf>, extract: F) -> Vec<(PathBuf, BatchResult<T, E>)>
+where
+    T: Send,
+    E: Send,
+    F: Fn(&mut InMemoryGame) -> std::result::Result<T, E> + Sync,
+{
+    paths
+        .into_par_iter()
+        .map(|path| {
+            let result = process_file(&path, &extract);
+            (path, result)
+        })
+        .collect()
+}
+
+fn process_file<T, E>(
+    path: &Path,
+    extract: impl Fn(&mut InMemoryGame) -> std::result::Result<T, E>,
+) -> BatchResult<T, E> {
+    let bytes = fs::read(path).map_err(|err| BatchError::Read(err.into()))?;
+    let mut rec = RecordedGame::new(Cursor::new(bytes)).map_err(BatchError::Read)?;
+    extract(&mut rec).map_err(BatchError::Extract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SaveVersion;
+
+    #[test]
+    fn process_test_files() -> anyhow::Result<()> {
+        let results = process_dir("test", |rec| -> crate::Result<_> {
+            let version = rec.save_version();
+            rec.header()?;
+            Ok(version)
+        })?;
+        let names: Vec<_> = results
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "aok.mgl",
+                "missyou_finally_vs_11.mgx",
+                "rec.20181208-195117.mgz"
+            ]
+        );
+        assert!(matches!(results[1].1, Ok(SaveVersion::AOC)));
+        // AI data parsing is incomplete, so this file fails, but the others are not affected.
+        assert!(matches!(
+            results[2].1,
+            Err(BatchError::Extract(Error::UnexpectedValue { .. }))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn isolate_errors() {
+        let results = process_files(
+            vec![
+                PathBuf::from("test/missing.mgx"),
+                PathBuf::from("Cargo.toml"),
+                PathBuf::from("test/aok.mgl"),
+            ],
+            |rec| {
+                if rec.save_version().is_mgx() {
+                    Ok(())
+                } else {
+                    Err("not an mgx file")
+                }
+            },
+        );
+        assert!(matches!(results[0].1, Err(BatchError::Read(_))));
+        assert!(matches!(results[1].1, Err(BatchError::Read(_))));
+        assert!(matches!(
+            results[2].1,
+            Err(BatchError::Extract("not an mgx file"))
+        ));
+    }
+}
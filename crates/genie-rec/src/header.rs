@@ -3,10 +3,10 @@ use crate::player::Player;
 use crate::string_table::StringTable;
 use crate::{GameVersion, Result};
 use byteorder::{ReadBytesExt, LE};
-use genie_scx::TribeScen;
-use genie_support::ReadSkipExt;
+use genie_scx::{Scenario, TribeScen, VersionBundle};
 pub use genie_support::SpriteID;
-use std::convert::TryInto;
+use genie_support::{trace_section, CountingReader, ReadSkipExt, SaveVersion, TryConvertExt};
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug};
 use std::io::Read;
 
@@ -30,6 +30,7 @@ impl AICommand {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct AIListRule {
     in_use: bool,
@@ -64,6 +65,7 @@ impl AIListRule {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct AIList {
     in_use: bool,
@@ -89,6 +91,7 @@ impl AIList {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct AIGroupTable {
     max_groups: u16,
@@ -157,12 +160,12 @@ impl AIFactState {
         let version = input.read_f32::<LE>()?;
         let death_match = input.read_u32::<LE>()? != 0;
         let regicide = input.read_u32::<LE>()? != 0;
-        let map_size = input.read_u32::<LE>()?.try_into().unwrap();
-        let map_type = input.read_u32::<LE>()?.try_into().unwrap();
-        let starting_resources = input.read_u32::<LE>()?.try_into().unwrap();
-        let starting_age = input.read_u32::<LE>()?.try_into().unwrap();
+        let map_size = input.read_u32::<LE>()?.try_convert()?;
+        let map_type = input.read_u32::<LE>()?.try_convert()?;
+        let starting_resources = input.read_u32::<LE>()?.try_convert()?;
+        let starting_age = input.read_u32::<LE>()?.try_convert()?;
         let cheats_enabled = input.read_u32::<LE>()? != 0;
-        let difficulty = input.read_u32::<LE>()?.try_into().unwrap();
+        let difficulty = input.read_u32::<LE>()?.try_convert()?;
         let mut timers = [[0; 10]; 8];
         let mut shared_goals = [0; 256];
         let mut signals = [0; 256];
@@ -234,10 +237,11 @@ impl AIScripts {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default)]
 pub struct Header {
     game_version: GameVersion,
-    save_version: f32,
+    save_version: SaveVersion,
     ai_scripts: Option<AIScripts>,
     map: Map,
     particle_system: ParticleSystem,
@@ -250,16 +254,69 @@ impl Header {
         self.players.iter()
     }
 
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
+    /// Get the scenario data embedded in the recorded game.
+    pub fn scenario(&self) -> &TribeScen {
+        &self.scenario
+    }
+
+    /// Recover the map and the objects of each player as a standalone scenario, which can be
+    /// written to a .scx file with [`Scenario::write_to`].
+    ///
+    /// The scenario uses the AoC format if the embedded scenario data is from AoC or later, and
+    /// the AoK format otherwise. Objects get new IDs; garrisoned objects stay garrisoned.
+    pub fn to_scenario(&self) -> Result<Scenario> {
+        let version = if self.scenario.version() >= 1.22 {
+            VersionBundle::aoc()
+        } else {
+            VersionBundle::aok()
+        };
+
+        let mut map = genie_scx::Map::new(self.map.width, self.map.height);
+        for (tile, rec_tile) in map.tiles_mut().zip(&self.map.tiles) {
+            tile.terrain = rec_tile.original_terrain.unwrap_or(rec_tile.terrain);
+            tile.elevation = rec_tile.elevation as i8;
+        }
+
+        let mut scen = Scenario::from_tribe_scen(self.scenario.clone(), map, version);
+        let mut object_ids = BTreeMap::new();
+        let mut garrisons = vec![];
+        for (player_id, player) in self.players.iter().enumerate() {
+            for unit in player.units.iter().chain(&player.sleeping_units) {
+                let attrs = &unit.static_;
+                let id = scen.add_object(player_id, attrs.unit_type_id, attrs.position)?;
+                object_ids.insert(attrs.id, id);
+                if let Some(container) = attrs.garrisoned_in_id {
+                    garrisons.push((id, container));
+                }
+            }
+        }
+        for (id, container) in garrisons {
+            if let Some(container) = object_ids.get(&container) {
+                scen.garrison(id, *container)?;
+            }
+        }
+
+        Ok(scen)
+    }
+
+    /// Read the decompressed header data of a recorded game.
+    ///
+    /// Most fields are read one by one, so `input` should be buffered, for example with a
+    /// `BufReader`. [`RecordedGame::header`](crate::RecordedGame::header) does this.
+    pub fn read_from(input: impl Read) -> Result<Self> {
+        const FILE: &str = "recorded game header";
+        let mut input = CountingReader::new(input);
         let mut header = Header {
             game_version: GameVersion::read_from(&mut input)?,
-            save_version: input.read_f32::<LE>()?,
+            save_version: input.read_f32::<LE>()?.into(),
             ..Default::default()
         };
 
         let includes_ai = input.read_u32::<LE>()? != 0;
         if includes_ai {
-            header.ai_scripts = Some(AIScripts::read_from(&mut input)?);
+            header.ai_scripts = Some(trace_section(FILE, "ai scripts", &mut input, |input| {
+                AIScripts::read_from(input)
+            })?);
         }
 
         let _old_time = input.read_u32::<LE>()?;
@@ -276,7 +333,7 @@ impl Header {
         let _random_seed2 = input.read_u32::<LE>()?;
         let _current_player = input.read_u16::<LE>()?;
         let num_players = input.read_u16::<LE>()?;
-        if header.save_version >= 11.76 {
+        if header.save_version.is_mgx() {
             let _aegis_enabled = input.read_u8()? != 0;
             let _cheats_enabled = input.read_u8()? != 0;
         }
@@ -293,29 +350,37 @@ impl Header {
         let mut player_time_delta = [0; 9];
         input.read_u32_into::<LE>(&mut player_time_delta[..])?;
 
-        header.map = Map::read_from(&mut input)?;
+        header.map = trace_section(FILE, "map", &mut input, |input| Map::read_from(input))?;
 
         // TODO is there another num_players here for restored games?
 
-        header.particle_system = ParticleSystem::read_from(&mut input)?;
+        header.particle_system = trace_section(FILE, "particles", &mut input, |input| {
+            ParticleSystem::read_from(input)
+        })?;
 
         if header.save_version >= 11.07 {
             let _identifier = input.read_u32::<LE>()?;
         }
 
-        header.players.reserve(num_players.try_into().unwrap());
-        for _ in 0..num_players {
-            header.players.push(Player::read_from(
-                &mut input,
-                header.save_version,
-                num_players as u8,
-            )?);
-        }
-        for player in &mut header.players {
-            player.read_info(&mut input, header.save_version)?;
-        }
+        let save_version = header.save_version;
+        header.players = trace_section(FILE, "players", &mut input, |input| -> Result<_> {
+            let mut players = Vec::with_capacity(num_players.into());
+            for _ in 0..num_players {
+                players.push(Player::read_from(
+                    &mut *input,
+                    save_version,
+                    num_players as u8,
+                )?);
+            }
+            for player in &mut players {
+                player.read_info(&mut *input, save_version)?;
+            }
+            Ok(players)
+        })?;
 
-        header.scenario = TribeScen::read_from(&mut input)?;
+        header.scenario = trace_section(FILE, "scenario", &mut input, |input| {
+            TribeScen::read_from(input)
+        })?;
 
         let _difficulty = if header.save_version >= 7.16 {
             Some(input.read_u32::<LE>()?)
@@ -370,6 +435,7 @@ impl Header {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 struct Particle {
     pub start: u32,
@@ -397,6 +463,7 @@ impl Particle {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 struct ParticleSystem {
     pub world_time: u32,
@@ -407,7 +474,7 @@ impl ParticleSystem {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let world_time = input.read_u32::<LE>()?;
         let num_particles = input.read_u32::<LE>()?;
-        let mut particles = Vec::with_capacity(num_particles.try_into().unwrap());
+        let mut particles = Vec::with_capacity(num_particles.try_convert()?);
         for _ in 0..num_particles {
             particles.push(Particle::read_from(&mut input)?);
         }
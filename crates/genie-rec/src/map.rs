@@ -1,7 +1,6 @@
-use crate::Result;
+use crate::{Error, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::ReadSkipExt;
-use std::convert::TryInto;
+use genie_support::{ReadSkipExt, TryConvertExt};
 use std::io::{Read, Write};
 
 /// Data about a map tile.
@@ -78,11 +77,11 @@ impl MapZone {
         let mut zone = Self::default();
         input.read_i8_into(&mut zone.info)?;
         input.read_i32_into::<LE>(&mut zone.tiles)?;
-        zone.zone_map = vec![0; (map_size.0 * map_size.1).try_into().unwrap()];
+        zone.zone_map = vec![0; (map_size.0 * map_size.1).try_convert()?];
         input.read_i8_into(&mut zone.zone_map)?;
 
         let num_rules = input.read_u32::<LE>()?;
-        zone.passability_rules = vec![0.0; num_rules.try_into().unwrap()];
+        zone.passability_rules = vec![0.0; num_rules.try_convert()?];
         input.read_f32_into::<LE>(&mut zone.passability_rules)?;
 
         zone.num_zones = input.read_u32::<LE>()?;
@@ -99,7 +98,7 @@ impl MapZone {
         for val in &self.zone_map {
             output.write_i8(*val)?;
         }
-        output.write_u32::<LE>(self.passability_rules.len().try_into().unwrap())?;
+        output.write_u32::<LE>(self.passability_rules.len().try_convert()?)?;
         for val in &self.passability_rules {
             output.write_f32::<LE>(*val)?;
         }
@@ -128,7 +127,6 @@ impl MapZone {
     }
 }
 
-///
 #[derive(Debug, Default, Clone)]
 pub struct VisibilityMap {
     /// Width of the visibility map.
@@ -143,7 +141,7 @@ impl VisibilityMap {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let width = input.read_u32::<LE>()?;
         let height = input.read_u32::<LE>()?;
-        let mut visibility = vec![0; (width * height).try_into().unwrap()];
+        let mut visibility = vec![0; (width * height).try_convert()?];
         input.read_u32_into::<LE>(&mut visibility)?;
         Ok(Self {
             width,
@@ -190,19 +188,19 @@ impl Map {
             ..Default::default()
         };
         let num_zones = input.read_u32::<LE>()?;
-        map.zones = Vec::with_capacity(num_zones.try_into().unwrap());
+        map.zones = Vec::with_capacity(num_zones.try_convert()?);
         for _ in 0..num_zones {
             map.zones
                 .push(MapZone::read_from(&mut input, (map.width, map.height))?);
         }
         map.all_visible = input.read_u8()? != 0;
         map.fog_of_war = input.read_u8()? != 0;
-        map.tiles = Vec::with_capacity((map.width * map.height).try_into().unwrap());
+        map.tiles = Vec::with_capacity((map.width * map.height).try_convert()?);
         for _ in 0..(map.width * map.height) {
             map.tiles.push(Tile::read_from(&mut input)?);
         }
 
-        let _umv = {
+        {
             let data_count = input.read_u32::<LE>()?;
             let _capacity = input.read_u32::<LE>()?;
             input.skip(u64::from(data_count) * 4)?;
@@ -219,6 +217,6 @@ impl Map {
 
     /// Write map data to an output stream.
     pub fn write_to(&self, _output: impl Write) -> Result<()> {
-        unimplemented!()
+        Err(Error::Unsupported("Writing map data"))
     }
 }
@@ -1,12 +1,12 @@
 use crate::ai::PlayerAI;
 use crate::unit::Unit;
 use crate::unit_type::CompactUnitType;
-use crate::{ObjectID, PlayerID, Result};
+use crate::{expect_value, ObjectID, PlayerID, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use genie_dat::tech_tree::TechTree;
 use genie_dat::CivilizationID;
 use genie_scx::VictoryConditions;
-use genie_support::{read_opt_u32, ReadStringsExt};
+use genie_support::{read_opt_u32, read_records, ReadStringsExt, SaveVersion, TryConvertExt};
 use std::convert::TryInto;
 use std::io::{Read, Write};
 
@@ -47,13 +47,13 @@ impl Player {
     }
 
     #[allow(clippy::cognitive_complexity)]
-    pub fn read_from(mut input: impl Read, version: f32, num_players: u8) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion, num_players: u8) -> Result<Self> {
         let mut player = Player {
             player_type: input.read_u8()?,
             ..Default::default()
         };
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
         player.relations = vec![0; usize::from(num_players)];
         input.read_exact(&mut player.relations)?;
@@ -64,16 +64,16 @@ impl Player {
             .read_u16_length_prefixed_str()?
             .unwrap_or_else(String::new);
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 22);
+            expect_value("player data separator", input.read_u8()?, 22)?;
         }
         let num_attributes = input.read_u32::<LE>()?;
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 33);
+            expect_value("player data separator", input.read_u8()?, 33)?;
         }
-        player.attributes = vec![0.0; num_attributes.try_into().unwrap()];
+        player.attributes = vec![0.0; num_attributes.try_convert()?];
         input.read_f32_into::<LE>(&mut player.attributes)?;
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
         player.initial_view = (input.read_f32::<LE>()?, input.read_f32::<LE>()?);
         if version >= 11.62 {
@@ -90,11 +90,11 @@ impl Player {
         player.game_status = input.read_u8()?;
         player.resigned = input.read_u8()? != 0;
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
         let _color = input.read_u8()?;
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
         let _pathing_attempt_cap = input.read_u32::<LE>()?;
         let _pathing_delay_cap = input.read_u32::<LE>()?;
@@ -177,7 +177,7 @@ impl Player {
             let _selection = if num_selections > 0 {
                 let object_id: ObjectID = input.read_u32::<LE>()?.into();
                 let object_properties = input.read_u32::<LE>()?;
-                let mut selected_ids = vec![ObjectID(0); num_selections.try_into().unwrap()];
+                let mut selected_ids = vec![ObjectID(0); num_selections.try_convert()?];
                 for id in selected_ids.iter_mut() {
                     *id = input.read_u32::<LE>()?.into();
                 }
@@ -188,8 +188,8 @@ impl Player {
         }
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         let _ty = input.read_u8()?;
@@ -211,7 +211,7 @@ impl Player {
         let _update_time = input.read_f32::<LE>()?;
 
         // if is userpatch
-        if genie_support::f32_eq!(version, 11.97) {
+        if version == 11.97 {
             player.userpatch_data = Some(UserPatchData::read_from(&mut input)?);
         }
 
@@ -226,7 +226,7 @@ impl Player {
         }
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         // diplomacy
@@ -246,7 +246,7 @@ impl Player {
         }
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         // off-map trade
@@ -261,7 +261,7 @@ impl Player {
         }
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         // market trading
@@ -333,7 +333,7 @@ impl Player {
         };
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         let _player_ai = if player.player_type == 3 && input.read_u32::<LE>()? == 1 {
@@ -343,7 +343,7 @@ impl Player {
         };
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         player.gaia = if player.player_type == 2 {
@@ -353,17 +353,17 @@ impl Player {
         };
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         let num_unit_types = input.read_u32::<LE>()?;
-        let mut available_unit_types = vec![false; num_unit_types.try_into().unwrap()];
+        let mut available_unit_types = vec![false; num_unit_types.try_convert()?];
         for available in available_unit_types.iter_mut() {
             *available = input.read_u32::<LE>()? != 0;
         }
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         player.unit_types.reserve(available_unit_types.len());
@@ -372,25 +372,25 @@ impl Player {
                 None
             } else {
                 if version >= 10.55 {
-                    assert_eq!(input.read_u8()?, 22);
+                    expect_value("player data separator", input.read_u8()?, 22)?;
                 }
                 let ty = CompactUnitType::read_from(&mut input, version)?;
                 if version >= 10.55 {
-                    assert_eq!(input.read_u8()?, 33);
+                    expect_value("player data separator", input.read_u8()?, 33)?;
                 }
                 Some(ty)
             });
         }
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         player.visible_map = VisibleMap::read_from(&mut input, version)?;
         player.visible_resources = VisibleResources::read_from(&mut input)?;
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         player.units = {
@@ -404,7 +404,7 @@ impl Player {
         };
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         player.sleeping_units = {
@@ -418,7 +418,7 @@ impl Player {
         };
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         player.doppelganger_units = {
@@ -432,13 +432,13 @@ impl Player {
         };
 
         if version >= 10.55 {
-            assert_eq!(input.read_u8()?, 11);
+            expect_value("player data separator", input.read_u8()?, 11)?;
         }
 
         Ok(player)
     }
 
-    pub fn read_info(&mut self, input: impl Read, _version: f32) -> Result<()> {
+    pub fn read_info(&mut self, input: impl Read, _version: SaveVersion) -> Result<()> {
         self.victory = VictoryConditions::read_from(input, true)?;
         Ok(())
     }
@@ -454,7 +454,7 @@ pub struct VisibleMap {
 }
 
 impl VisibleMap {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut map = VisibleMap {
             width: input.read_u32::<LE>()?,
             height: input.read_u32::<LE>()?,
@@ -463,8 +463,8 @@ impl VisibleMap {
         if version >= 6.70 {
             map.explored_tiles_count = input.read_u32::<LE>()?;
         }
-        map.player_id = input.read_u16::<LE>()?.try_into().unwrap();
-        map.tiles = vec![0; (map.width * map.height).try_into().unwrap()];
+        map.player_id = input.read_u16::<LE>()?.try_convert()?;
+        map.tiles = vec![0; (map.width * map.height).try_convert()?];
         input.read_i8_into(&mut map.tiles)?;
         Ok(map)
     }
@@ -479,6 +479,9 @@ pub struct VisibleResource {
 }
 
 impl VisibleResource {
+    /// The size of a visible resource in a recorded game, in bytes.
+    const SIZE: usize = 8;
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(VisibleResource {
             object_id: input.read_u32::<LE>()?.into(),
@@ -489,6 +492,7 @@ impl VisibleResource {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct VisibleResources {
     lists: Vec<Vec<VisibleResource>>,
@@ -504,16 +508,18 @@ impl VisibleResources {
         }
         let mut lists = Vec::with_capacity(sizes.len());
         for size in sizes {
-            let mut list = Vec::with_capacity(size.try_into().unwrap());
-            for _ in 0..size {
-                list.push(VisibleResource::read_from(&mut input)?);
-            }
-            lists.push(list);
+            lists.push(read_records(
+                &mut input,
+                size.try_convert()?,
+                VisibleResource::SIZE,
+                |record| VisibleResource::read_from(record),
+            )?);
         }
         Ok(Self { lists })
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct GaiaData {
     update_time: u32,
@@ -613,6 +619,7 @@ impl GaiaWolfInfo {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 struct DiplomacyOffer {
     sequence: u8,
@@ -664,12 +671,12 @@ pub struct HistoryInfo {
 }
 
 impl HistoryInfo {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let _padding = input.read_u8()?;
         let num_entries = input.read_u32::<LE>()?;
         let _num_events = input.read_u32::<LE>()?;
         let entries_capacity = input.read_u32::<LE>()?;
-        let mut entries = Vec::with_capacity(entries_capacity.try_into().unwrap());
+        let mut entries = Vec::with_capacity(entries_capacity.try_convert()?);
         for _ in 0..num_entries {
             entries.push(HistoryEntry::read_from(&mut input, version)?);
         }
@@ -677,10 +684,12 @@ impl HistoryInfo {
         let _padding = input.read_u8()?;
 
         let num_events = input.read_u32::<LE>()?;
-        let mut events = Vec::with_capacity(num_events.try_into().unwrap());
-        for _ in 0..num_events {
-            events.push(HistoryEvent::read_from(&mut input)?);
-        }
+        let events = read_records(
+            &mut input,
+            num_events.try_convert()?,
+            HistoryEvent::SIZE,
+            |record| HistoryEvent::read_from(record),
+        )?;
 
         let _razings = input.read_i32::<LE>()?;
         let _hit_points_razed = input.read_i32::<LE>()?;
@@ -728,6 +737,9 @@ pub struct HistoryEvent {
 }
 
 impl HistoryEvent {
+    /// The size of a history event in a recorded game, in bytes.
+    const SIZE: usize = 21;
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(HistoryEvent {
             event_type: input.read_i8()?,
@@ -749,7 +761,7 @@ pub struct HistoryEntry {
 }
 
 impl HistoryEntry {
-    pub fn read_from(mut input: impl Read, _version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, _version: SaveVersion) -> Result<Self> {
         let civilian_population = input.read_u16::<LE>()?;
         let military_population = input.read_u16::<LE>()?;
         Ok(HistoryEntry {
@@ -768,6 +780,9 @@ pub struct TechState {
 }
 
 impl TechState {
+    /// The size of a tech state in a recorded game, in bytes.
+    const SIZE: usize = 14;
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(TechState {
             progress: input.read_f32::<LE>()?,
@@ -790,10 +805,12 @@ pub struct PlayerTech {
 impl PlayerTech {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let num_techs = input.read_u16::<LE>()?;
-        let mut tech_states = Vec::with_capacity(usize::from(num_techs));
-        for _ in 0..num_techs {
-            tech_states.push(TechState::read_from(&mut input)?);
-        }
+        let tech_states = read_records(
+            &mut input,
+            usize::from(num_techs),
+            TechState::SIZE,
+            |record| TechState::read_from(record),
+        )?;
         Ok(Self { tech_states })
     }
 }
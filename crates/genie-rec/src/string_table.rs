@@ -1,6 +1,7 @@
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use std::io::{Read, Write};
+use genie_support::{decode_str_with_encoding, encode_str_with_encoding, DEFAULT_ENCODING};
+use std::io::{self, Read, Write};
 
 #[derive(Debug, Clone)]
 pub struct StringTable {
@@ -26,7 +27,7 @@ impl StringTable {
             let length = input.read_u32::<LE>()?;
             let mut bytes = vec![0; length as usize];
             input.read_exact(&mut bytes)?;
-            strings.push(String::from_utf8(bytes).unwrap());
+            strings.push(decode_str_with_encoding(&bytes, DEFAULT_ENCODING)?);
         }
 
         Ok(StringTable {
@@ -41,10 +42,11 @@ impl StringTable {
         handle.write_u32::<LE>(0)?;
 
         for string in &self.strings {
-            let len = string.len();
-            assert!(len < u32::max_value() as usize);
-            handle.write_u32::<LE>(len as u32)?;
-            handle.write_all(string.as_bytes())?;
+            let bytes =
+                encode_str_with_encoding(string, DEFAULT_ENCODING).map_err(io::Error::from)?;
+            assert!(bytes.len() < u32::MAX as usize);
+            handle.write_u32::<LE>(bytes.len() as u32)?;
+            handle.write_all(&bytes)?;
         }
 
         Ok(())
@@ -56,7 +58,7 @@ impl StringTable {
 
     pub fn num_strings(&self) -> u16 {
         let len = self.strings.len();
-        assert!(len < u16::max_value() as usize);
+        assert!(len < u16::MAX as usize);
         len as u16
     }
 
@@ -1,6 +1,6 @@
 use crate::unit_action::UnitAction;
 use crate::unit_type::UnitBaseClass;
-use crate::Result;
+use crate::{Error, Result};
 use crate::{ObjectID, PlayerID};
 use arrayvec::ArrayVec;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
@@ -8,9 +8,10 @@ pub use genie_dat::sprite::SpriteID;
 pub use genie_dat::terrain::TerrainID;
 pub use genie_dat::unit_type::AttributeCost;
 use genie_dat::unit_type::UnitType;
-use genie_support::{read_opt_u32, ReadSkipExt};
+use genie_support::{
+    read_opt_i8, read_opt_u16, read_opt_u32, AttributeID, ReadSkipExt, SaveVersion, TryConvertExt,
+};
 pub use genie_support::{StringKey, UnitTypeID};
-use std::convert::TryInto;
 use std::io::{Read, Write};
 
 #[derive(Debug, Clone)]
@@ -27,12 +28,12 @@ pub struct Unit {
 }
 
 impl Unit {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Option<Self>> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Option<Self>> {
         let raw_class = input.read_u8()?;
         if raw_class == 0 {
             return Ok(None);
         }
-        let unit_base_class = raw_class.try_into().unwrap();
+        let unit_base_class = raw_class.try_convert()?;
         let static_ = StaticUnitAttributes::read_from(&mut input, version)?;
         let mut unit = Self {
             unit_base_class,
@@ -69,7 +70,7 @@ impl Unit {
         Ok(Some(unit))
     }
 
-    pub fn write_to(&self, mut output: impl Write, version: f32) -> Result<()> {
+    pub fn write_to(&self, mut output: impl Write, version: SaveVersion) -> Result<()> {
         let raw_class = self.unit_base_class as u8;
         output.write_u8(raw_class)?;
         self.static_.write_to(&mut output, version)?;
@@ -200,7 +201,7 @@ impl SpriteList {
         Ok(Self { sprites })
     }
 
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
         for sprite in &self.sprites {
             sprite.write_to(&mut output)?;
         }
@@ -226,7 +227,7 @@ pub struct StaticUnitAttributes {
     pub screen_offset: (u16, u16),
     pub shadow_offset: (u16, u16),
     pub selected_group: Option<u8>,
-    pub attribute_type_held: u16,
+    pub attribute_type_held: AttributeID,
     pub attribute_amount_held: f32,
     pub worker_count: u8,
     pub current_damage: u8,
@@ -239,7 +240,7 @@ pub struct StaticUnitAttributes {
 }
 
 impl StaticUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = StaticUnitAttributes {
             owner_id: input.read_u8()?.into(),
             unit_type_id: input.read_u16::<LE>()?.into(),
@@ -262,12 +263,9 @@ impl StaticUnitAttributes {
             ..Default::default()
         };
         if version < 11.58 {
-            attrs.selected_group = match input.read_i8()? {
-                -1 => None,
-                id => Some(id.try_into().unwrap()),
-            };
+            attrs.selected_group = read_opt_i8(&mut input)?;
         }
-        attrs.attribute_type_held = input.read_u16::<LE>()?;
+        attrs.attribute_type_held = input.read_u16::<LE>()?.into();
         attrs.attribute_amount_held = input.read_f32::<LE>()?;
         attrs.worker_count = input.read_u8()?;
         attrs.current_damage = input.read_u8()?;
@@ -275,7 +273,7 @@ impl StaticUnitAttributes {
         attrs.under_attack = input.read_u8()? != 0;
         attrs.pathing_group_members = {
             let num_members = input.read_u32::<LE>()?;
-            let mut members = vec![ObjectID(0); num_members.try_into().unwrap()];
+            let mut members = vec![ObjectID(0); num_members.try_convert()?];
             for m in members.iter_mut() {
                 *m = input.read_u32::<LE>()?.into();
             }
@@ -289,11 +287,11 @@ impl StaticUnitAttributes {
         Ok(attrs)
     }
 
-    pub fn write_to(&self, mut output: impl Write, _version: f32) -> Result<()> {
+    pub fn write_to(&self, mut output: impl Write, _version: SaveVersion) -> Result<()> {
         output.write_u8(self.owner_id.into())?;
         output.write_u16::<LE>(self.unit_type_id.into())?;
         output.write_u16::<LE>(self.sprite_id.into())?;
-        todo!()
+        Err(Error::Unsupported("Writing static unit attributes"))
     }
 }
 
@@ -332,7 +330,7 @@ pub struct PathData {
 }
 
 impl PathData {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut path = PathData {
             id: input.read_u32::<LE>()?,
             linked_path_type: input.read_u32::<LE>()?,
@@ -356,8 +354,8 @@ impl PathData {
         Ok(path)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing path data"))
     }
 }
 
@@ -421,7 +419,7 @@ pub struct MovingUnitAttributes {
 }
 
 impl MovingUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = MovingUnitAttributes {
             trail_remainder: input.read_u32::<LE>()?,
             velocity: (
@@ -497,7 +495,7 @@ impl MovingUnitAttributes {
     }
 
     pub fn write_to(&self, _output: impl Write) -> Result<()> {
-        todo!()
+        Err(Error::Unsupported("Writing moving unit attributes"))
     }
 }
 
@@ -510,7 +508,7 @@ pub struct ActionUnitAttributes {
 }
 
 impl ActionUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = ActionUnitAttributes {
             waiting: input.read_u8()? != 0,
             ..Default::default()
@@ -525,8 +523,8 @@ impl ActionUnitAttributes {
         Ok(attrs)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing action unit attributes"))
     }
 }
 
@@ -543,7 +541,7 @@ pub struct BaseCombatUnitAttributes {
 }
 
 impl BaseCombatUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = Self::default();
         if version >= 9.05 {
             attrs.formation_id = input.read_u8()?;
@@ -564,8 +562,8 @@ impl BaseCombatUnitAttributes {
         Ok(attrs)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing base combat unit attributes"))
     }
 }
 
@@ -577,7 +575,7 @@ pub struct MissileUnitAttributes {
 }
 
 impl MissileUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         Ok(MissileUnitAttributes {
             max_range: input.read_f32::<LE>()?,
             fired_from_id: input.read_u32::<LE>()?.into(),
@@ -591,11 +589,12 @@ impl MissileUnitAttributes {
         })
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing missile unit attributes"))
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct UnitAIOrder {
     issuer: u32,
@@ -614,7 +613,7 @@ impl UnitAIOrder {
             order_type: input.read_u32::<LE>()?,
             priority: input.read_u32::<LE>()?,
             target_id: input.read_u32::<LE>()?.into(),
-            target_player: input.read_u32::<LE>()?.try_into().unwrap(),
+            target_player: input.read_u32::<LE>()?.try_convert()?,
             target_location: (
                 input.read_f32::<LE>()?,
                 input.read_f32::<LE>()?,
@@ -624,8 +623,8 @@ impl UnitAIOrder {
         })
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing unit AI order data"))
     }
 }
 
@@ -651,11 +650,12 @@ impl UnitAINotification {
         })
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing unit AI notification data"))
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct UnitAIOrderHistory {
     order: u32,
@@ -668,7 +668,7 @@ pub struct UnitAIOrderHistory {
 }
 
 impl UnitAIOrderHistory {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut order = UnitAIOrderHistory {
             order: input.read_u32::<LE>()?,
             action: input.read_u32::<LE>()?,
@@ -692,8 +692,8 @@ impl UnitAIOrderHistory {
         Ok(order)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing unit AI order history data"))
     }
 }
 
@@ -713,8 +713,8 @@ impl UnitAIRetargetEntry {
         })
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing unit AI retarget entry data"))
     }
 }
 
@@ -740,8 +740,8 @@ impl Waypoint {
         Ok(waypoint)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing waypoint data"))
     }
 }
 
@@ -750,14 +750,15 @@ pub struct PatrolPath {}
 
 impl PatrolPath {
     pub fn read_from(_input: impl Read) -> Result<Self> {
-        todo!()
+        Err(Error::Unsupported("Reading patrol path data"))
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing patrol path data"))
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct UnitAI {
     mood: Option<u32>,
@@ -802,17 +803,14 @@ pub struct UnitAI {
 }
 
 impl UnitAI {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut ai = UnitAI {
             mood: read_opt_u32(&mut input)?,
             current_order: read_opt_u32(&mut input)?,
             current_order_priority: read_opt_u32(&mut input)?,
             current_action: read_opt_u32(&mut input)?,
             current_target: read_opt_u32(&mut input)?,
-            current_target_type: match input.read_u16::<LE>()? {
-                0xFFFF => None,
-                id => Some(id.try_into().unwrap()),
-            },
+            current_target_type: read_opt_u16(&mut input)?,
             ..Default::default()
         };
         input.skip(2)?;
@@ -910,8 +908,8 @@ impl UnitAI {
         Ok(ai)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing unit AI data"))
     }
 }
 
@@ -938,7 +936,7 @@ pub struct CombatUnitAttributes {
 }
 
 impl CombatUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = CombatUnitAttributes {
             next_volley: input.read_u8()?,
             using_special_attack_animation: input.read_u8()?,
@@ -1005,8 +1003,8 @@ impl CombatUnitAttributes {
         Ok(attrs)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing combat unit attributes"))
     }
 }
 
@@ -1084,7 +1082,7 @@ pub struct BuildingUnitAttributes {
 }
 
 impl BuildingUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = BuildingUnitAttributes {
             built: input.read_u8()? != 0,
             build_points: input.read_f32::<LE>()?,
@@ -1108,8 +1106,8 @@ impl BuildingUnitAttributes {
                     (false, _, _) => None,
                     (true, -1, -1) => Some(location),
                     (true, id, unit_type_id) => Some(GatherPoint::Object {
-                        id: id.try_into().unwrap(),
-                        unit_type: unit_type_id.try_into().unwrap(),
+                        id: id.try_convert()?,
+                        unit_type: unit_type_id.try_convert()?,
                     }),
                 }
             },
@@ -1125,7 +1123,7 @@ impl BuildingUnitAttributes {
             for _ in 0..4 {
                 let id = input.read_i32::<LE>()?;
                 if id != -1 {
-                    children.push(id.try_into().unwrap());
+                    children.push(id.try_convert()?);
                 }
             }
             children
@@ -1174,7 +1172,7 @@ impl BuildingUnitAttributes {
         Ok(attrs)
     }
 
-    pub fn write_to(&self, _output: impl Write, _version: f32) -> Result<()> {
-        todo!()
+    pub fn write_to(&self, _output: impl Write, _version: SaveVersion) -> Result<()> {
+        Err(Error::Unsupported("Writing building unit attributes"))
     }
 }
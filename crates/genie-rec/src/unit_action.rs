@@ -1,9 +1,9 @@
 use crate::ObjectID;
-use crate::Result;
+use crate::{Error, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 pub use genie_dat::sprite::SpriteID;
 pub use genie_support::UnitTypeID;
-use genie_support::{read_opt_u16, read_opt_u32};
+use genie_support::{read_opt_u16, read_opt_u32, SaveVersion};
 use std::io::{Read, Write};
 
 #[derive(Debug, Clone)]
@@ -22,14 +22,18 @@ pub struct UnitAction {
 }
 
 impl UnitAction {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let action_type = input.read_u16::<LE>()?;
         Self::read_from_inner(&mut input, action_type, version)
     }
 
     // `dyn` because this is a recursive function; taking &mut from a `impl Read` here
     // would cause infinite recursion in the types.
-    fn read_from_inner(mut input: &mut dyn Read, action_type: u16, version: f32) -> Result<Self> {
+    fn read_from_inner(
+        mut input: &mut dyn Read,
+        action_type: u16,
+        version: SaveVersion,
+    ) -> Result<Self> {
         // TODO this is different between AoC 1.0 and AoC 1.0c. This version check is a guess
         // and may not actually be when it changed. May have to become more specific in the
         // future!
@@ -70,7 +74,7 @@ impl UnitAction {
         })
     }
 
-    pub fn read_list_from(mut input: impl Read, version: f32) -> Result<Vec<Self>> {
+    pub fn read_list_from(mut input: impl Read, version: SaveVersion) -> Result<Vec<Self>> {
         let mut list = vec![];
         loop {
             let action_type = input.read_u16::<LE>()?;
@@ -108,7 +112,7 @@ impl ActionType {
             13 => Self::Guard,
             21 => Self::Make(ActionMake::read_from(input)?),
             107 => Self::Artifact,
-            _ => unimplemented!("action type {} not yet implemented", action_type),
+            _ => return Err(Error::UnsupportedUnitActionType(action_type)),
         };
         Ok(data)
     }
@@ -148,6 +152,7 @@ impl ActionEnter {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct ActionAttack {
     range: f32,
@@ -1,9 +1,9 @@
-use crate::Result;
+use crate::{Error, Result};
 use arrayvec::ArrayVec;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 pub use genie_dat::unit_type::{AttributeCost, ParseUnitBaseClassError, UnitBaseClass};
+use genie_support::{read_opt_i16, SaveVersion, TryConvertExt};
 pub use genie_support::{StringKey, UnitTypeID};
-use std::convert::TryInto;
 use std::io::{Read, Write};
 
 #[derive(Debug, Clone)]
@@ -20,8 +20,8 @@ pub struct CompactUnitType {
 }
 
 impl CompactUnitType {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
-        let unit_base_class = input.read_u8()?.try_into().unwrap();
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
+        let unit_base_class = input.read_u8()?.try_convert()?;
         let static_ = StaticUnitAttributes::read_from(&mut input)?;
         let mut unit = Self {
             unit_base_class,
@@ -59,6 +59,7 @@ impl CompactUnitType {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct StaticUnitAttributes {
     id: UnitTypeID,
@@ -98,10 +99,7 @@ impl StaticUnitAttributes {
         let hidden_in_editor = input.read_i8()?;
         // UserPatch data
         let hidden_flags = if hidden_in_editor == -16 {
-            attrs.death_object_id = match input.read_i16::<LE>()? {
-                -1 => None,
-                id => Some(id.try_into().unwrap()),
-            };
+            attrs.death_object_id = read_opt_i16(&mut input)?;
             attrs.string_id = Some(input.read_u16::<LE>()?.into());
             attrs.description_id = Some(input.read_u16::<LE>()?.into());
             attrs.flags = Some(input.read_u32::<LE>()?);
@@ -212,7 +210,7 @@ pub struct BaseCombatUnitAttributes {
 }
 
 impl BaseCombatUnitAttributes {
-    pub fn read_from(mut input: impl Read, version: f32) -> Result<Self> {
+    pub fn read_from(mut input: impl Read, version: SaveVersion) -> Result<Self> {
         let mut attrs = BaseCombatUnitAttributes {
             base_armor: if version >= 11.52 {
                 input.read_u16::<LE>()?
@@ -232,14 +230,8 @@ impl BaseCombatUnitAttributes {
         attrs.attack_speed = input.read_f32::<LE>()?;
         attrs.weapon_range_max = input.read_f32::<LE>()?;
         attrs.base_hit_chance = input.read_u16::<LE>()?;
-        attrs.projectile_object_id = match input.read_i16::<LE>()? {
-            -1 => None,
-            id => Some(id.try_into().unwrap()),
-        };
-        attrs.defense_terrain_bonus = match input.read_i16::<LE>()? {
-            -1 => None,
-            id => Some(id.try_into().unwrap()),
-        };
+        attrs.projectile_object_id = read_opt_i16(&mut input)?;
+        attrs.defense_terrain_bonus = read_opt_i16(&mut input)?;
         attrs.weapon_range_max_2 = input.read_f32::<LE>()?;
         attrs.area_of_effect = input.read_f32::<LE>()?;
         attrs.weapon_range_min = input.read_f32::<LE>()?;
@@ -247,7 +239,7 @@ impl BaseCombatUnitAttributes {
     }
 
     pub fn write_to(&self, _output: impl Write) -> Result<()> {
-        todo!()
+        Err(Error::Unsupported("Writing base combat unit attributes"))
     }
 }
 
@@ -290,24 +282,20 @@ impl CombatUnitAttributes {
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let mut attrs = Self::default();
         for _ in 0..3 {
-            let attr = AttributeCost::read_from(&mut input)?;
-            if attr.attribute_type >= 0 {
-                attrs.costs.push(attr);
+            if let Some(cost) = AttributeCost::read_from(&mut input)? {
+                attrs.costs.push(cost);
             }
         }
         let create_time = input.read_u16::<LE>()?;
         // UserPatch data
-        if create_time == u16::max_value() - 15 {
+        if create_time == u16::MAX - 15 {
             attrs.original_pierce_armor = Some(input.read_u16::<LE>()?);
             attrs.original_armor = Some(input.read_u16::<LE>()?);
             attrs.original_weapon = Some(input.read_u16::<LE>()?);
             attrs.original_weapon_range = Some(input.read_f32::<LE>()?);
             attrs.area_effect_level = Some(input.read_u8()?);
             attrs.frame_delay = Some(input.read_u16::<LE>()?);
-            attrs.create_at_building = match input.read_i16::<LE>()? {
-                -1 => None,
-                id => Some(id.try_into().unwrap()),
-            };
+            attrs.create_at_building = read_opt_i16(&mut input)?;
             attrs.create_button = Some(input.read_i8()?);
             attrs.rear_attack_modifier = Some(input.read_f32::<LE>()?);
             attrs.hero_flag = Some(input.read_u8()?);
@@ -321,7 +309,7 @@ impl CombatUnitAttributes {
     }
 
     pub fn write_to(&self, _output: impl Write) -> Result<()> {
-        todo!()
+        Err(Error::Unsupported("Writing combat unit attributes"))
     }
 }
 
@@ -352,7 +340,7 @@ impl BuildingUnitAttributes {
     }
 
     pub fn write_to(&self, _output: impl Write) -> Result<()> {
-        todo!()
+        Err(Error::Unsupported("Writing building unit attributes"))
     }
 }
 
@@ -366,7 +354,9 @@ mod tests {
         assert!(UnitBaseClass::Static < UnitBaseClass::Animated);
         assert!(UnitBaseClass::Static < UnitBaseClass::Doppelganger);
         assert!(UnitBaseClass::Animated < UnitBaseClass::Doppelganger);
-        assert!(!(UnitBaseClass::Moving < UnitBaseClass::Doppelganger));
-        assert!(!(UnitBaseClass::Moving > UnitBaseClass::Doppelganger));
+        assert_eq!(
+            UnitBaseClass::Moving.partial_cmp(&UnitBaseClass::Doppelganger),
+            None
+        );
     }
 }
@@ -1,3 +1,4 @@
+use crate::sections::skip_bytes;
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use genie_support::{write_i32_str, DecodeStringError, ReadStringError, ReadStringsExt};
@@ -7,65 +8,40 @@ use std::io::{Read, Write};
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum AIErrorCode {
-    ///
     ConstantAlreadyDefined = 0,
-    ///
     FileOpenFailed = 1,
-    ///
     FileReadFailed = 2,
-    ///
     InvalidIdentifier = 3,
-    ///
     InvalidKeyword = 4,
-    ///
     InvalidPreprocessorDirective = 5,
-    ///
     ListFull = 6,
-    ///
     MissingArrow = 7,
-    ///
     MissingClosingParenthesis = 8,
-    ///
     MissingClosingQuote = 9,
-    ///
     MissingEndIf = 10,
-    ///
     MissingFileName = 11,
-    ///
     MissingIdentifier = 12,
-    ///
     MissingKeyword = 13,
-    ///
     MissingLHS = 14,
-    ///
     MissingOpeningParenthesis = 15,
-    ///
     MissingPreprocessorSymbol = 16,
-    ///
     MissingRHS = 17,
-    ///
     NoRules = 18,
-    ///
     PreprocessorNestingTooDeep = 19,
-    ///
     RuleTooLong = 20,
-    ///
     StringTableFull = 21,
-    ///
     UndocumentedError = 22,
-    ///
     UnexpectedElse = 23,
-    ///
     UnexpectedEndIf = 24,
-    ///
     UnexpectedError = 25,
-    ///
     UnexpectedEOF = 26,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AIErrorInfo {
     filename: String,
     line_number: i32,
@@ -111,14 +87,13 @@ impl AIErrorInfo {
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         // TODO support non UTF8 encoding
         let mut filename_bytes = [0; 257];
-        (&mut filename_bytes[..self.filename.len()]).copy_from_slice(self.filename.as_bytes());
+        filename_bytes[..self.filename.len()].copy_from_slice(self.filename.as_bytes());
         output.write_all(&filename_bytes)?;
 
         output.write_i32::<LE>(self.line_number)?;
 
         let mut description_bytes = [0; 128];
-        (&mut description_bytes[..self.description.len()])
-            .copy_from_slice(self.description.as_bytes());
+        description_bytes[..self.description.len()].copy_from_slice(self.description.as_bytes());
         output.write_all(&description_bytes)?;
 
         output.write_u32::<LE>(self.error_code.into())?;
@@ -127,13 +102,33 @@ impl AIErrorInfo {
     }
 }
 
-#[derive(Debug, Clone)]
+/// An AI file embedded in the scenario.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AIFile {
     filename: String,
     content: String,
 }
 
 impl AIFile {
+    /// Create an AI file.
+    pub fn new(filename: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            filename: filename.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Get the file name.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Get the file content.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
     /// Read an embedded AI file from an input stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let filename = input
@@ -146,6 +141,17 @@ impl AIFile {
         Ok(Self { filename, content })
     }
 
+    /// Read past an embedded AI file in an input stream, without keeping it in memory.
+    pub(crate) fn skip(mut input: impl Read) -> Result<()> {
+        for _ in 0..2 {
+            match input.read_u32::<LE>()? {
+                0xFFFF_FFFF => (),
+                length => skip_bytes(&mut input, u64::from(length))?,
+            }
+        }
+        Ok(())
+    }
+
     /// Write this embedded AI file to an output stream.
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         write_i32_str(&mut output, &self.filename)?;
@@ -155,13 +161,23 @@ impl AIFile {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AIInfo {
     error: Option<AIErrorInfo>,
-    files: Vec<AIFile>,
+    pub(crate) files: Vec<AIFile>,
 }
 
 impl AIInfo {
-    pub fn read_from(mut input: impl Read) -> Result<Option<Self>> {
+    /// Get mutable references to the names and contents of the embedded AI files.
+    pub(crate) fn strings_mut(&mut self) -> Vec<&mut String> {
+        self.files
+            .iter_mut()
+            .flat_map(|file| vec![&mut file.filename, &mut file.content])
+            .collect()
+    }
+
+    /// Read AI information from an input stream, optionally skipping the embedded AI files.
+    pub fn read_from(mut input: impl Read, skip_files: bool) -> Result<Option<Self>> {
         let has_ai_files = input.read_u32::<LE>()? != 0;
         let has_error = input.read_u32::<LE>()? != 0;
 
@@ -178,7 +194,11 @@ impl AIInfo {
         let num_ai_files = input.read_u32::<LE>()?;
         let mut files = vec![];
         for _ in 0..num_ai_files {
-            files.push(AIFile::read_from(&mut input)?);
+            if skip_files {
+                AIFile::skip(&mut input)?;
+            } else {
+                files.push(AIFile::read_from(&mut input)?);
+            }
         }
 
         Ok(Some(Self { error, files }))
@@ -204,3 +224,8 @@ impl AIInfo {
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    AIErrorInfo => crate::Error;
+    AIFile => crate::Error;
+}
@@ -1,12 +1,15 @@
 //! Handles bitmap files embedded in the scenario file.
 
+use crate::sections::skip_bytes;
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use rgb::RGBA8;
-use std::io::{Read, Write};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
 
 /// Bitmap header info.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitmapInfo {
     size: u32,
     width: i32,
@@ -51,7 +54,6 @@ impl BitmapInfo {
         Ok(bitmap)
     }
 
-    #[allow(unused)]
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         assert_eq!(self.colors.len(), 256);
 
@@ -78,7 +80,11 @@ impl BitmapInfo {
 }
 
 /// A Genie-style bitmap file: a typical BMP with some metadata.
-#[derive(Debug)]
+///
+/// Only 8-bit bitmaps with a 256 colour palette are supported, like the ones the scenario editor
+/// embeds for the instructions screen.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bitmap {
     own_memory: u32,
     width: u32,
@@ -89,6 +95,132 @@ pub struct Bitmap {
 }
 
 impl Bitmap {
+    /// Create a bitmap from palette indices.
+    ///
+    /// `pixels` contains one palette index for each pixel, row by row from the top left. The
+    /// palette can have at most 256 colours; the alpha channel is not used.
+    ///
+    /// Returns an [`io::ErrorKind::InvalidInput`] error if the number of pixels does not match
+    /// the size, if the bitmap is empty or too large to store, or if the palette has more than
+    /// 256 colours.
+    pub fn from_indexed(width: u32, height: u32, palette: &[RGBA8], pixels: &[u8]) -> Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if palette.len() > 256 {
+            return Err(invalid(format!(
+                "palette has {} colours, expected at most 256",
+                palette.len()
+            ))
+            .into());
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid(format!("{}x{} bitmap is empty", width, height)).into());
+        }
+        let too_large = || invalid(format!("{}x{} bitmap is too large", width, height));
+        let info_width = i32::try_from(width).map_err(|_| too_large())?;
+        let info_height = i32::try_from(height).map_err(|_| too_large())?;
+        let stride = Self::stride_for(width);
+        let size_image =
+            u32::try_from(stride as u64 * u64::from(height)).map_err(|_| too_large())?;
+
+        if pixels.len() != width as usize * height as usize {
+            return Err(invalid(format!(
+                "got {} pixels for a {}x{} bitmap",
+                pixels.len(),
+                width,
+                height
+            ))
+            .into());
+        }
+
+        let mut data = vec![0; size_image as usize];
+        // Rows are stored bottom to top.
+        for (row, target) in pixels
+            .chunks_exact(width as usize)
+            .zip(data.chunks_exact_mut(stride).rev())
+        {
+            target[..row.len()].copy_from_slice(row);
+        }
+
+        let mut colors = palette.to_vec();
+        colors.resize(256, RGBA8::default());
+
+        Ok(Self {
+            own_memory: 1,
+            width,
+            height,
+            orientation: 0xFFFF,
+            info: BitmapInfo {
+                size: 40,
+                width: info_width,
+                height: info_height,
+                planes: 1,
+                bit_count: 8,
+                compression: 0,
+                size_image,
+                xpels_per_meter: 0,
+                ypels_per_meter: 0,
+                clr_used: 256,
+                clr_important: 0,
+                colors,
+            },
+            pixels: data,
+        })
+    }
+
+    /// The number of bytes in a row of pixels, including padding.
+    fn stride_for(width: u32) -> usize {
+        ((width + 3) & !3) as usize
+    }
+
+    /// Get the width of the bitmap in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the bitmap in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the colour palette.
+    pub fn palette(&self) -> &[RGBA8] {
+        &self.info.colors
+    }
+
+    /// Get the palette index of the pixel at the given coordinates, counted from the top left.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u8> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        // A positive height means that rows are stored bottom to top.
+        let row = if self.info.height > 0 {
+            self.height - 1 - y
+        } else {
+            y
+        };
+        self.pixels
+            .get(row as usize * Self::stride_for(self.width) + x as usize)
+            .copied()
+    }
+
+    /// Convert the bitmap to RGBA pixels, row by row from the top left. All pixels are opaque.
+    pub fn to_rgba(&self) -> Vec<RGBA8> {
+        let mut rgba = Vec::with_capacity((self.width * self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = self.pixel(x, y).unwrap_or(0);
+                let color = self
+                    .info
+                    .colors
+                    .get(usize::from(index))
+                    .copied()
+                    .unwrap_or_default();
+                rgba.push(RGBA8 { a: 255, ..color });
+            }
+        }
+        rgba
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Option<Self>> {
         let own_memory = input.read_u32::<LE>()?;
         let width = input.read_u32::<LE>()?;
@@ -113,7 +245,22 @@ impl Bitmap {
         }
     }
 
-    #[allow(unused)]
+    /// Read past a bitmap in an input stream, without keeping its pixels in memory.
+    pub(crate) fn skip(mut input: impl Read) -> Result<()> {
+        let _own_memory = input.read_u32::<LE>()?;
+        let width = input.read_u32::<LE>()?;
+        let height = input.read_u32::<LE>()?;
+        let _orientation = input.read_u16::<LE>()?;
+
+        if width > 0 && height > 0 {
+            // 40 bytes of header info and a 256 colour palette.
+            let info_size = 40 + 256 * 4;
+            let aligned_width = u64::from(height) * u64::from((width + 3) & !3);
+            skip_bytes(&mut input, info_size + aligned_width)?;
+        }
+        Ok(())
+    }
+
     pub fn write_to(&self, mut output: impl Write) -> Result<()> {
         output.write_u32::<LE>(self.own_memory)?;
         output.write_u32::<LE>(self.width)?;
@@ -133,3 +280,69 @@ impl Bitmap {
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    BitmapInfo => crate::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scenario;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn from_indexed() -> anyhow::Result<()> {
+        let palette = [
+            RGBA8::new(0, 0, 0, 0),
+            RGBA8::new(255, 0, 0, 0),
+            RGBA8::new(0, 0, 255, 0),
+        ];
+        let pixels = [0, 1, 2, 2, 1, 0];
+        let bitmap = Bitmap::from_indexed(3, 2, &palette, &pixels)?;
+        assert_eq!(bitmap.pixel(1, 0), Some(1));
+        assert_eq!(bitmap.pixel(2, 0), Some(2));
+        assert_eq!(bitmap.pixel(3, 0), None);
+        assert_eq!(bitmap.to_rgba()[1], RGBA8::new(255, 0, 0, 255));
+
+        let mut out = vec![];
+        bitmap.write_to(&mut out)?;
+        assert_eq!(Bitmap::read_from(Cursor::new(out))?, Some(bitmap));
+
+        for result in &[
+            Bitmap::from_indexed(3, 3, &palette, &pixels),
+            Bitmap::from_indexed(3, 2, &[RGBA8::default(); 257], &pixels),
+            Bitmap::from_indexed(0, 2, &palette, &[]),
+            Bitmap::from_indexed(u32::MAX, 1, &palette, &[]),
+        ] {
+            assert!(matches!(
+                result,
+                Err(crate::Error::IoError(err)) if err.kind() == io::ErrorKind::InvalidInput
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn instructions_bitmap() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Jeremiah Johnson (Update).scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let bitmap = scen.instructions_bitmap().unwrap().clone();
+        assert_eq!((bitmap.width(), bitmap.height()), (628, 278));
+        assert_eq!(bitmap.to_rgba().len(), 628 * 278);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let mut scen = Scenario::read_from(Cursor::new(out))?;
+        assert_eq!(scen.instructions_bitmap(), Some(&bitmap));
+
+        let replacement = Bitmap::from_indexed(1, 1, &[RGBA8::new(1, 2, 3, 0)], &[0])?;
+        scen.set_instructions_bitmap(Some(replacement.clone()));
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        assert_eq!(scen.instructions_bitmap(), Some(&replacement));
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! Renumbering the objects placed in a scenario.
+
+use crate::Scenario;
+use std::collections::HashMap;
+
+impl Scenario {
+    /// Renumber the placed objects with consecutive IDs starting at 0, keeping their order by ID,
+    /// and reset the next object ID to follow the highest one. Returns a map from the old IDs to
+    /// the new IDs.
+    ///
+    /// Objects that share an ID with an earlier object get IDs of their own; references to the
+    /// shared ID keep pointing to the first object. References to the objects in triggers and
+    /// garrisons are updated. References to objects that are not placed are kept as they are.
+    pub fn compact_object_ids(&mut self) -> HashMap<i32, i32> {
+        let mut objects: Vec<_> = self
+            .format
+            .player_objects
+            .iter()
+            .enumerate()
+            .flat_map(|(player, objects)| {
+                objects
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, object)| (object.id, player, index))
+            })
+            .collect();
+        objects.sort_by_key(|(id, _, _)| *id);
+
+        let num_objects = objects.len() as i32;
+        let mut new_ids = HashMap::new();
+        for (new_id, (old_id, player, index)) in objects.into_iter().enumerate() {
+            let new_id = new_id as i32;
+            new_ids.entry(old_id).or_insert(new_id);
+            self.format.player_objects[player][index].id = new_id;
+        }
+        let remap = |id: i32| new_ids.get(&id).copied().unwrap_or(id);
+
+        for object in self.objects_mut() {
+            object.garrisoned_in = object.garrisoned_in.map(remap);
+        }
+        if let Some(triggers) = &mut self.format.triggers {
+            for trigger in triggers.triggers_unordered_mut() {
+                for condition in trigger.conditions_unordered_mut() {
+                    condition.set_primary_object(remap(condition.primary_object()));
+                    condition.set_secondary_object(remap(condition.secondary_object()));
+                }
+                for effect in trigger.effects_unordered_mut() {
+                    for object in effect.objects_mut() {
+                        *object = remap(*object);
+                    }
+                    effect.set_object_id(remap(effect.object_id()));
+                }
+            }
+        }
+
+        self.format.next_object_id = num_objects;
+        new_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Effect, ObjectSelection, Trigger};
+    use std::fs::File;
+
+    #[test]
+    fn compact_ids() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let first = scen.add_object(1, 4.into(), (10.5, 10.5, 0.0))?;
+        scen.format.next_object_id = first + 1000;
+        let archer = scen.add_object(1, 4.into(), (11.5, 10.5, 0.0))?;
+        let ram = scen.add_object(1, 35.into(), (12.5, 10.5, 0.0))?;
+        scen.garrison(archer, ram)?;
+        let duplicate = scen.object(archer).unwrap().clone();
+        scen.format.player_objects[2].push(duplicate);
+        let triggers = scen.triggers_mut().unwrap();
+        let kill = triggers.add_trigger(Trigger::new("Kill").effect(Effect::KillObject {
+            objects: ObjectSelection {
+                player: 1,
+                objects: vec![ram],
+                ..Default::default()
+            },
+        })?)?;
+        let num_objects = scen.objects().count();
+
+        let new_ids = scen.compact_object_ids();
+        let mut ids: Vec<_> = scen.objects().map(|object| object.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..num_objects as i32).collect::<Vec<_>>());
+        assert_eq!(scen.next_object_id(), num_objects as i32);
+        // The duplicate archer is numbered between the archer and the ram.
+        assert_eq!(new_ids[&ram], new_ids[&archer] + 2);
+        assert_eq!(
+            scen.object(new_ids[&archer]).unwrap().garrisoned_in,
+            Some(new_ids[&ram])
+        );
+        let trigger = scen
+            .triggers()
+            .unwrap()
+            .triggers_unordered()
+            .nth(kill as usize)
+            .unwrap();
+        assert_eq!(
+            trigger.effects().next().unwrap().objects(),
+            &[new_ids[&ram]]
+        );
+        Ok(())
+    }
+}
@@ -3,11 +3,13 @@
 //! This module implements conversions between different scenario formats and game versions.
 mod aoc_to_wk;
 mod hd_to_wk;
+mod version;
 
 use crate::{Scenario, ScenarioObject};
 
 pub use aoc_to_wk::AoCToWK;
 pub use hd_to_wk::HDToWK;
+pub use version::{ConversionReport, Incompatibility, VersionConverter};
 
 /// Error indicating scenario conversion failure.
 #[derive(Debug, thiserror::Error)]
@@ -30,14 +32,9 @@ pub enum ConvertError {
 /// use genie_scx::convert::AutoToWK;
 /// AutoToWK::default().convert(&mut scenario)?
 /// ```
+#[derive(Default)]
 pub struct AutoToWK {}
 
-impl Default for AutoToWK {
-    fn default() -> Self {
-        AutoToWK {}
-    }
-}
-
 /// Check if a scenario object is likely a WololoKingdoms one.
 fn is_wk_object(object: &ScenarioObject) -> bool {
     // Stormy Dog is the highest ID in AoC 1.0c.
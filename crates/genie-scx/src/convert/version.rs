@@ -0,0 +1,356 @@
+use super::ConvertError;
+use crate::{Scenario, ScenarioObject, Tile, Trigger, UnitTypeID, VersionBundle};
+use genie_support::GameRelease;
+use nohash_hasher::IntMap;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+/// A scenario feature that can not be represented in the target version of a conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// A unit type ID that does not exist in the target version, used by a placed object or a
+    /// trigger.
+    UnitType(UnitTypeID),
+    /// A terrain ID that does not exist in the target version.
+    Terrain(u8),
+    /// A trigger condition type that does not exist in the target version.
+    Condition {
+        /// The ID of the trigger.
+        trigger: usize,
+        /// The index of the condition in the trigger, unordered.
+        condition: usize,
+        /// The condition type.
+        condition_type: i32,
+    },
+    /// A trigger effect type that does not exist in the target version.
+    Effect {
+        /// The ID of the trigger.
+        trigger: usize,
+        /// The index of the effect in the trigger, unordered.
+        effect: usize,
+        /// The effect type.
+        effect_type: i32,
+    },
+    /// The scenario uses trigger variables, which are not saved in the target version.
+    TriggerVariables,
+}
+
+/// Lists the scenario features that could not be converted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConversionReport {
+    issues: Vec<Incompatibility>,
+}
+
+impl ConversionReport {
+    /// Check if the scenario was converted without any problems.
+    pub fn is_compatible(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Get the features that could not be converted.
+    pub fn issues(&self) -> &[Incompatibility] {
+        &self.issues
+    }
+}
+
+/// Convert a scenario between AoK, AoC, HD Edition, and Definitive Edition.
+///
+/// Unit type and terrain IDs are mapped to their equivalents in the target version, and the
+/// scenario will be saved in the target version's format. Features that do not exist in the
+/// target version are listed in the [`ConversionReport`]. They are left in the scenario, so they
+/// can be fixed by hand or by adding a mapping with [`VersionConverter::map_object`] or
+/// [`VersionConverter::map_terrain`].
+///
+/// Unit type and terrain IDs are only checked when converting to AoK or AoC; HD Edition and the
+/// Definitive Edition keep adding new IDs.
+///
+/// ## Usage
+///
+/// ```rust,ignore
+/// use genie_scx::convert::VersionConverter;
+/// use genie_scx::VersionBundle;
+/// let report = VersionConverter::new(VersionBundle::aoc()).convert(&mut scenario)?;
+/// for issue in report.issues() {
+///     println!("{:?}", issue);
+/// }
+/// ```
+pub struct VersionConverter {
+    target: VersionBundle,
+    object_ids_map: IntMap<i32, UnitTypeID>,
+    terrain_ids_map: IntMap<u8, u8>,
+}
+
+impl VersionConverter {
+    /// Create a converter to the given version.
+    pub fn new(target: VersionBundle) -> Self {
+        let has_hd_units = matches!(target.release(), Some(GameRelease::HD | GameRelease::DE));
+        let object_ids_map = if !has_hd_units {
+            [
+                (1103, 529), // Fire Galley, Fire Ship
+                (1104, 527), // Demolition Raft, Demolition Ship
+                (947, 280),  // Cutting Mangonel, Mangonel
+                (948, 550),  // Cutting Onager, Onager
+            ]
+            .iter()
+            .map(|(a, b)| (*a, UnitTypeID::from(*b)))
+            .collect()
+        } else {
+            IntMap::default()
+        };
+
+        Self {
+            target,
+            object_ids_map,
+            terrain_ids_map: IntMap::default(),
+        }
+    }
+
+    /// Replace a unit type ID during the conversion.
+    pub fn map_object(mut self, from: UnitTypeID, to: UnitTypeID) -> Self {
+        self.object_ids_map.insert(from.into(), to);
+        self
+    }
+
+    /// Replace a terrain ID during the conversion.
+    pub fn map_terrain(mut self, from: u8, to: u8) -> Self {
+        self.terrain_ids_map.insert(from, to);
+        self
+    }
+
+    /// Map a raw unit type ID, as stored in triggers, recording it if it does not exist in the
+    /// target game. Negative values mean "no unit type" and are left alone.
+    fn convert_unit_type(
+        &self,
+        game: GameRelease,
+        id: i32,
+        unsupported: &mut BTreeSet<u16>,
+    ) -> i32 {
+        let id = match self.object_ids_map.get(&id) {
+            Some(new_type) => i32::from(*new_type),
+            None => id,
+        };
+        if game.max_unit_type().is_some_and(|max| id > max) {
+            if let Ok(id) = u16::try_from(id) {
+                unsupported.insert(id);
+            }
+        }
+        id
+    }
+
+    fn convert_object(
+        &self,
+        game: GameRelease,
+        object: &mut ScenarioObject,
+        unsupported: &mut BTreeSet<u16>,
+    ) {
+        let id = self.convert_unit_type(game, object.object_type.into(), unsupported);
+        if let Ok(id) = UnitTypeID::try_from(id) {
+            object.object_type = id;
+        }
+    }
+
+    fn convert_terrain(&self, game: GameRelease, tile: &mut Tile, unsupported: &mut BTreeSet<u8>) {
+        if let Some(new_type) = self.terrain_ids_map.get(&tile.terrain) {
+            tile.terrain = *new_type;
+        }
+        if game.max_terrain().is_some_and(|max| tile.terrain > max) {
+            unsupported.insert(tile.terrain);
+        }
+    }
+
+    fn convert_trigger(
+        &self,
+        game: GameRelease,
+        id: usize,
+        trigger: &mut Trigger,
+        unsupported: &mut BTreeSet<u16>,
+        report: &mut ConversionReport,
+    ) {
+        for (index, cond) in trigger.conditions_unordered_mut().enumerate() {
+            let unit_type = self.convert_unit_type(game, cond.raw_unit_type(), unsupported);
+            cond.set_raw_unit_type(unit_type);
+            let object_type = self.convert_unit_type(game, cond.raw_object_type(), unsupported);
+            cond.set_raw_object_type(object_type);
+
+            let condition_type = cond.condition_type();
+            if game
+                .max_condition_type()
+                .is_some_and(|max| condition_type > max)
+            {
+                report.issues.push(Incompatibility::Condition {
+                    trigger: id,
+                    condition: index,
+                    condition_type,
+                });
+            }
+        }
+
+        for (index, effect) in trigger.effects_unordered_mut().enumerate() {
+            let unit_type = self.convert_unit_type(game, effect.raw_unit_type(), unsupported);
+            effect.set_raw_unit_type(unit_type);
+            let object_type = self.convert_unit_type(game, effect.raw_object_type(), unsupported);
+            effect.set_raw_object_type(object_type);
+
+            let effect_type = effect.effect_type();
+            if game.max_effect_type().is_some_and(|max| effect_type > max) {
+                report.issues.push(Incompatibility::Effect {
+                    trigger: id,
+                    effect: index,
+                    effect_type,
+                });
+            }
+        }
+    }
+
+    /// Convert a scenario to the target version in-place.
+    ///
+    /// Returns an error if the scenario or the target version is not an Age of Empires 2
+    /// version.
+    pub fn convert(&self, scen: &mut Scenario) -> Result<ConversionReport, ConvertError> {
+        scen.version()
+            .release()
+            .ok_or(ConvertError::InvalidVersion)?;
+        let game = self.target.release().ok_or(ConvertError::InvalidVersion)?;
+
+        let mut report = ConversionReport::default();
+        let mut unsupported_units = BTreeSet::new();
+        let mut unsupported_terrains = BTreeSet::new();
+
+        for object in scen.objects_mut() {
+            self.convert_object(game, object, &mut unsupported_units);
+        }
+
+        for tile in scen.map_mut().tiles_mut() {
+            self.convert_terrain(game, tile, &mut unsupported_terrains);
+        }
+
+        let target_triggers = self.target.triggers.unwrap_or(1.6);
+        if let Some(trigger_system) = scen.triggers_mut() {
+            if target_triggers < 2.2 && trigger_system.has_variables() {
+                report.issues.push(Incompatibility::TriggerVariables);
+            }
+            for (id, trigger) in trigger_system.triggers_unordered_mut().enumerate() {
+                self.convert_trigger(game, id, trigger, &mut unsupported_units, &mut report);
+            }
+        }
+
+        report.issues.extend(
+            unsupported_units
+                .into_iter()
+                .map(|id| Incompatibility::UnitType(id.into())),
+        );
+        report.issues.extend(
+            unsupported_terrains
+                .into_iter()
+                .map(Incompatibility::Terrain),
+        );
+
+        scen.version = self.target.clone();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, Location, ObjectSelection};
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn aoc_to_de() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let report = scen.convert(VersionBundle::aoe2_de())?;
+        assert!(report.is_compatible(), "{:?}", report);
+        assert!(scen.version().is_age2_de());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen2 = Scenario::read_from(Cursor::new(out))?;
+        assert_eq!(scen2.format_version(), VersionBundle::aoe2_de().format);
+        Ok(())
+    }
+
+    #[test]
+    fn report_incompatibilities() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.objects_mut().next().unwrap().object_type = UnitTypeID::from(1103);
+        scen.map_mut().tile_mut(0, 0).unwrap().terrain = 50;
+        scen.map_mut().tile_mut(1, 0).unwrap().terrain = 32;
+        let trigger = Trigger::new("AoC only")
+            .condition(Condition::UnitsGarrisoned {
+                object: 1,
+                amount: 2,
+            })?
+            .effect(Effect::CreateObject {
+                player: 1,
+                unit_type: UnitTypeID::from(1104),
+                location: Location { x: 1, y: 1 },
+            })?
+            .effect(Effect::StopUnit {
+                objects: ObjectSelection::default(),
+            })?;
+        let id = scen.triggers_mut().unwrap().add_trigger(trigger)? as usize;
+
+        let report = VersionConverter::new(VersionBundle::aok())
+            .map_terrain(32, 0)
+            .convert(&mut scen)?;
+        assert!(scen.version().is_aok());
+        // The scenario already uses many AoC trigger effects, only check the new trigger.
+        let issues: Vec<_> = report
+            .issues()
+            .iter()
+            .filter(|issue| match issue {
+                Incompatibility::Condition { trigger, .. }
+                | Incompatibility::Effect { trigger, .. } => *trigger == id,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                Incompatibility::Condition {
+                    trigger: id,
+                    condition: 0,
+                    condition_type: 18,
+                },
+                Incompatibility::Effect {
+                    trigger: id,
+                    effect: 1,
+                    effect_type: 29,
+                },
+                // The original scenario uses AoC terrains.
+                Incompatibility::Terrain(39),
+                Incompatibility::Terrain(40),
+                Incompatibility::Terrain(50),
+            ]
+        );
+
+        // Fire Galley and Demolition Raft are replaced by their AoC equivalents.
+        assert_eq!(
+            scen.objects().next().unwrap().object_type,
+            UnitTypeID::from(529)
+        );
+        let trigger = scen.triggers().unwrap().triggers().last().unwrap();
+        assert_eq!(
+            trigger.effects().next().unwrap().unit_type(),
+            Some(UnitTypeID::from(527))
+        );
+        assert_eq!(scen.map().tile(1, 0).unwrap().terrain, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_versions() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert!(matches!(
+            scen.convert(VersionBundle::ror()),
+            Err(ConvertError::InvalidVersion)
+        ));
+        Ok(())
+    }
+}
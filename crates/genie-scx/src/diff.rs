@@ -0,0 +1,229 @@
+//! Comparing scenarios, for reviewing scenario revisions.
+
+use crate::{Scenario, ScenarioObject};
+use std::collections::BTreeMap;
+
+/// A difference between two scenarios, found by [`Scenario::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenarioChange {
+    /// A trigger was added. Contains the ID of the trigger in the new scenario.
+    TriggerAdded(usize),
+    /// A trigger was removed. Contains the ID of the trigger in the old scenario.
+    TriggerRemoved(usize),
+    /// The name, settings, conditions or effects of a trigger changed.
+    TriggerModified(usize),
+    /// An object was placed. Contains the ID of the object.
+    ObjectAdded(i32),
+    /// An object was removed. Contains the ID of the object.
+    ObjectRemoved(i32),
+    /// An object was moved.
+    ObjectMoved {
+        /// The ID of the object.
+        id: i32,
+        /// The old position.
+        from: (f32, f32, f32),
+        /// The new position.
+        to: (f32, f32, f32),
+    },
+    /// The type, owner, angle, state or garrison of an object changed.
+    ObjectModified(i32),
+    /// The map size changed. Terrain is not compared when this happens.
+    MapResized {
+        /// The old width and height.
+        from: (u32, u32),
+        /// The new width and height.
+        to: (u32, u32),
+    },
+    /// The terrain of a tile changed.
+    TerrainChanged {
+        x: u32,
+        y: u32,
+        /// The old terrain ID.
+        from: u8,
+        /// The new terrain ID.
+        to: u8,
+    },
+    /// The elevation of a tile changed.
+    ElevationChanged {
+        x: u32,
+        y: u32,
+        /// The old elevation level.
+        from: i8,
+        /// The new elevation level.
+        to: i8,
+    },
+}
+
+/// Lists the differences between two scenarios.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScenarioDiff {
+    changes: Vec<ScenarioChange>,
+}
+
+impl ScenarioDiff {
+    /// Check if the scenarios have the same triggers, objects and terrain.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Get the differences that were found, triggers first, then objects, then terrain.
+    pub fn changes(&self) -> &[ScenarioChange] {
+        &self.changes
+    }
+}
+
+/// Index the objects placed in a scenario by their ID, together with their owner.
+fn objects_by_id(scen: &Scenario) -> BTreeMap<i32, (usize, &ScenarioObject)> {
+    scen.format
+        .player_objects
+        .iter()
+        .enumerate()
+        .flat_map(|(player, objects)| {
+            objects
+                .iter()
+                .map(move |object| (object.id, (player, object)))
+        })
+        .collect()
+}
+
+impl Scenario {
+    /// Compare this scenario to a newer revision, and report the triggers, objects and terrain
+    /// that were added, removed or changed.
+    ///
+    /// Triggers are matched by their ID and objects by their object ID, so reordering triggers
+    /// does not count as a change, but deleting a trigger other than the last one shows up as
+    /// changes to all the triggers after it.
+    pub fn diff(&self, other: &Scenario) -> ScenarioDiff {
+        let mut changes = vec![];
+
+        let old_triggers: Vec<_> = self
+            .triggers()
+            .into_iter()
+            .flat_map(|t| t.triggers_unordered())
+            .collect();
+        let new_triggers: Vec<_> = other
+            .triggers()
+            .into_iter()
+            .flat_map(|t| t.triggers_unordered())
+            .collect();
+        for id in 0..old_triggers.len().max(new_triggers.len()) {
+            match (old_triggers.get(id), new_triggers.get(id)) {
+                (Some(old), Some(new)) if old != new => {
+                    changes.push(ScenarioChange::TriggerModified(id))
+                }
+                (Some(_), None) => changes.push(ScenarioChange::TriggerRemoved(id)),
+                (None, Some(_)) => changes.push(ScenarioChange::TriggerAdded(id)),
+                _ => (),
+            }
+        }
+
+        let old_objects = objects_by_id(self);
+        let new_objects = objects_by_id(other);
+        for (id, (old_player, old)) in &old_objects {
+            match new_objects.get(id) {
+                None => changes.push(ScenarioChange::ObjectRemoved(*id)),
+                Some((new_player, new)) => {
+                    if old.position != new.position {
+                        changes.push(ScenarioChange::ObjectMoved {
+                            id: *id,
+                            from: old.position,
+                            to: new.position,
+                        });
+                    }
+                    let moved = ScenarioObject {
+                        position: new.position,
+                        ..(*old).clone()
+                    };
+                    if old_player != new_player || &moved != *new {
+                        changes.push(ScenarioChange::ObjectModified(*id));
+                    }
+                }
+            }
+        }
+        changes.extend(
+            new_objects
+                .keys()
+                .filter(|id| !old_objects.contains_key(id))
+                .map(|id| ScenarioChange::ObjectAdded(*id)),
+        );
+
+        let (old_map, new_map) = (self.map(), other.map());
+        let old_size = (old_map.width(), old_map.height());
+        let new_size = (new_map.width(), new_map.height());
+        if old_size != new_size {
+            changes.push(ScenarioChange::MapResized {
+                from: old_size,
+                to: new_size,
+            });
+        } else {
+            for (index, (old, new)) in old_map.tiles().zip(new_map.tiles()).enumerate() {
+                let (x, y) = (index as u32 % old_size.0, index as u32 / old_size.0);
+                if old.terrain != new.terrain {
+                    changes.push(ScenarioChange::TerrainChanged {
+                        x,
+                        y,
+                        from: old.terrain,
+                        to: new.terrain,
+                    });
+                }
+                if old.elevation != new.elevation {
+                    changes.push(ScenarioChange::ElevationChanged {
+                        x,
+                        y,
+                        from: old.elevation,
+                        to: new.elevation,
+                    });
+                }
+            }
+        }
+
+        ScenarioDiff { changes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Effect, Trigger};
+    use std::fs::File;
+
+    #[test]
+    fn diff_revisions() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let old = Scenario::read_from(&mut f)?;
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        let moved = old.objects().next().unwrap().id;
+        let removed = old.objects().nth(1).unwrap().id;
+        new.move_object(moved, (1.5, 2.5, 0.0))?;
+        new.remove_object(removed)?;
+        let added = new.add_object(1, 4.into(), (20.5, 20.5, 0.0))?;
+        new.map_mut().tile_mut(3, 2).unwrap().terrain = 50;
+        let triggers = new.triggers_mut().unwrap();
+        let trigger =
+            triggers.add_trigger(Trigger::new("New").effect(Effect::ActivateTrigger(0))?)?;
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.changes(),
+            &[
+                ScenarioChange::TriggerAdded(trigger as usize),
+                ScenarioChange::ObjectMoved {
+                    id: moved,
+                    from: old.object(moved).unwrap().position,
+                    to: (1.5, 2.5, 0.0),
+                },
+                ScenarioChange::ObjectRemoved(removed),
+                ScenarioChange::ObjectAdded(added),
+                ScenarioChange::TerrainChanged {
+                    x: 3,
+                    y: 2,
+                    from: old.map().tile(3, 2).unwrap().terrain,
+                    to: 50,
+                },
+            ][..]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Converting scenario text between the code page it is stored in and UTF-8.
+//!
+//! Scenario files do not say which code page their text uses: it depends on the language of the
+//! game that saved them. Text is read as WINDOWS-1252 by default, which garbles Chinese or Russian
+//! text but keeps the original bytes, so it can be converted once the real encoding is known.
+
+use crate::{Result, Scenario};
+use genie_support::{
+    decode_str_with_encoding, encode_str_with_encoding, Encoding, DEFAULT_ENCODING,
+};
+use std::io::{Read, Write};
+
+impl Scenario {
+    /// Get mutable references to all the strings in the scenario.
+    fn strings_mut(&mut self) -> Vec<&mut String> {
+        let format = &mut self.format;
+        let mut strings = format.tribe_scen.strings_mut();
+        strings.extend(format.header.description.as_mut());
+        strings.extend(format.header.author_name.as_mut());
+        strings.extend(
+            format
+                .scenario_players
+                .iter_mut()
+                .filter_map(|player| player.name.as_mut()),
+        );
+        if let Some(triggers) = &mut format.triggers {
+            strings.extend(triggers.strings_mut());
+        }
+        if let Some(ai_info) = &mut format.ai_info {
+            strings.extend(ai_info.strings_mut());
+        }
+        strings
+    }
+
+    /// Reinterpret strings that were read as WINDOWS-1252 as text in the given encoding.
+    fn decode_text(&mut self, encoding: &'static Encoding) -> Result<()> {
+        for string in self.strings_mut() {
+            let bytes = encode_str_with_encoding(string, DEFAULT_ENCODING)?;
+            *string = decode_str_with_encoding(&bytes, encoding)?;
+        }
+        Ok(())
+    }
+
+    /// Convert strings to the bytes of the given encoding, stored as WINDOWS-1252 text so they
+    /// are written unchanged.
+    fn encode_text(&mut self, encoding: &'static Encoding) -> Result<()> {
+        for string in self.strings_mut() {
+            let bytes = encode_str_with_encoding(string, encoding)?;
+            *string = decode_str_with_encoding(&bytes, DEFAULT_ENCODING)?;
+        }
+        Ok(())
+    }
+
+    /// Read a scenario file whose text is stored in the given encoding, such as GBK for Chinese
+    /// scenarios or WINDOWS-1251 for Russian scenarios.
+    ///
+    /// Fails with [`Error::DecodeStringError`] if some text is not valid in that encoding.
+    pub fn read_from_encoding(input: impl Read, encoding: &'static Encoding) -> Result<Self> {
+        let mut scen = Self::read_from(input)?;
+        scen.decode_text(encoding)?;
+        Ok(scen)
+    }
+
+    /// Write the scenario file, storing its text in the given encoding.
+    ///
+    /// Fails with [`Error::EncodeStringError`] if some text can not be represented in that
+    /// encoding.
+    pub fn write_to_encoding(&self, output: impl Write, encoding: &'static Encoding) -> Result<()> {
+        let mut scen = self.clone();
+        scen.encode_text(encoding)?;
+        scen.write_to(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, MessageKind};
+    use encoding_rs::{GBK, WINDOWS_1251};
+    use std::fs::File;
+
+    #[test]
+    fn windows_1252_keeps_bytes() {
+        let bytes: Vec<u8> = (1..=255).collect();
+        let text = decode_str_with_encoding(&bytes, DEFAULT_ENCODING).unwrap();
+        let encoded = encode_str_with_encoding(&text, DEFAULT_ENCODING).unwrap();
+        assert_eq!(&encoded[..], &bytes[..]);
+    }
+
+    #[test]
+    fn roundtrip_encodings() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_message(MessageKind::Instructions, Some("守住城堡".to_string()));
+        scen.set_message(MessageKind::Hints, Some("Защитите замок".to_string()));
+        assert!(matches!(
+            scen.write_to_encoding(&mut vec![], WINDOWS_1251),
+            Err(Error::EncodeStringError(_))
+        ));
+
+        scen.set_message(MessageKind::Hints, Some("保护国王".to_string()));
+        let mut bytes = vec![];
+        scen.write_to_encoding(&mut bytes, GBK)?;
+        let read = Scenario::read_from_encoding(&bytes[..], GBK)?;
+        assert_eq!(read.message(MessageKind::Instructions), Some("守住城堡"));
+        assert_eq!(read.message(MessageKind::Hints), Some("保护国王"));
+
+        let garbled = Scenario::read_from(&bytes[..])?;
+        assert_ne!(garbled.message(MessageKind::Hints), Some("保护国王"));
+        let mut written = vec![];
+        garbled.write_to(&mut written)?;
+        assert_eq!(written, bytes);
+        Ok(())
+    }
+}
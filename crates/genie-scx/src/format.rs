@@ -6,7 +6,9 @@ use crate::ai::AIInfo;
 use crate::bitmap::Bitmap;
 use crate::header::SCXHeader;
 use crate::map::Map;
+use crate::messages::{CinematicKind, MessageKind};
 use crate::player::*;
+use crate::sections::{skip_bytes, ScenarioSection};
 use crate::triggers::TriggerSystem;
 use crate::types::*;
 use crate::victory::*;
@@ -14,11 +16,26 @@ use crate::{Error, Result, VersionBundle};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use genie_support::{
-    f32_eq, read_opt_u32, write_opt_str, write_str, ReadStringsExt, StringKey, UnitTypeID,
+    f32_eq, read_opt_i32, read_opt_u32, trace_section, write_opt_i32, write_opt_str, write_str,
+    CountingReader, ReadStringsExt, StringKey, UnitTypeID,
 };
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Read, Write};
 
+/// The kind of file reported in tracing spans.
+const FILE: &str = "scenario";
+
+/// Get the data version that determines the layout of the scenario data.
+///
+/// Star Wars: Galactic Battlegrounds runs on the AoC engine, and its scenario data is laid out
+/// like AoC's, but it uses higher data version numbers that overlap with Definitive Edition ones.
+fn layout_version(format: Option<SCXVersion>, data_version: f32) -> f32 {
+    match format {
+        Some(format) if is_swgb_version(format, data_version) => 1.22,
+        _ => data_version,
+    }
+}
+
 // pub enum LostInformation {
 //     DisabledTechs(i32, i32),
 //     DisabledUnits(i32, i32),
@@ -27,7 +44,8 @@ use std::io::{self, Read, Write};
 // }
 
 /// An object placed in the scenario.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScenarioObject {
     /// Position (x, y, z) of this object.
     pub position: (f32, f32, f32),
@@ -65,12 +83,8 @@ impl ScenarioObject {
         let garrisoned_in = if version < SCXVersion(*b"1.13") {
             None
         } else {
-            Some(input.read_i32::<LE>()?)
+            read_opt_i32(&mut input)?
         }
-        .and_then(|id| match id {
-            -1 => None,
-            id => Some(id),
-        })
         .and_then(|id| match id {
             // 0 means -1 in "recent" versions
             0 if version > SCXVersion(*b"1.12") => None,
@@ -110,14 +124,15 @@ impl ScenarioObject {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct RGEScen {
     /// Data version.
     pub(crate) version: f32,
     /// Names for each player.
-    player_names: Vec<Option<String>>,
+    pub(crate) player_names: Vec<Option<String>>,
     /// Name IDs for each player.
     player_string_table: Vec<Option<StringKey>>,
-    player_base_properties: Vec<PlayerBaseProperties>,
+    pub(crate) player_base_properties: Vec<PlayerBaseProperties>,
     victory_conquest: bool,
     /// File name of this scenario.
     pub(crate) name: String,
@@ -136,18 +151,146 @@ pub(crate) struct RGEScen {
     pregame_cinematic: Option<String>,
     victory_cinematic: Option<String>,
     loss_cinematic: Option<String>,
-    mission_bmp: Option<String>,
-    player_build_lists: Vec<Option<String>>,
-    player_city_plans: Vec<Option<String>>,
-    player_ai_rules: Vec<Option<String>>,
-    player_files: Vec<PlayerFiles>,
+    pub(crate) mission_bmp: Option<String>,
+    /// The picture shown on the instructions screen.
+    pub(crate) mission_picture: Option<Bitmap>,
+    pub(crate) player_build_lists: Vec<Option<String>>,
+    pub(crate) player_city_plans: Vec<Option<String>>,
+    pub(crate) player_ai_rules: Vec<Option<String>>,
+    pub(crate) player_files: Vec<PlayerFiles>,
     ai_rules_types: Vec<i8>,
 }
 
 impl RGEScen {
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
-        let version = input.read_f32::<LE>()?;
-        log::debug!("RGEScen version {}", version);
+    /// Get mutable references to all the strings in the scenario data.
+    pub(crate) fn strings_mut(&mut self) -> Vec<&mut String> {
+        let messages = vec![
+            &mut self.description,
+            &mut self.hints,
+            &mut self.win_message,
+            &mut self.loss_message,
+            &mut self.history,
+            &mut self.scout,
+            &mut self.pregame_cinematic,
+            &mut self.victory_cinematic,
+            &mut self.loss_cinematic,
+            &mut self.mission_bmp,
+        ];
+        let player_files = self.player_files.iter_mut().flat_map(|files| {
+            vec![
+                &mut files.build_list,
+                &mut files.city_plan,
+                &mut files.ai_rules,
+            ]
+        });
+        let mut strings = vec![&mut self.name];
+        strings.extend(
+            self.player_names
+                .iter_mut()
+                .chain(messages)
+                .chain(self.player_build_lists.iter_mut())
+                .chain(self.player_city_plans.iter_mut())
+                .chain(self.player_ai_rules.iter_mut())
+                .chain(player_files)
+                .filter_map(Option::as_mut),
+        );
+        strings
+    }
+
+    /// Get the names of each player's embedded files of a kind.
+    pub(crate) fn player_file_names(&self, kind: PlayerFileKind) -> &[Option<String>] {
+        match kind {
+            PlayerFileKind::AIRules => &self.player_ai_rules,
+            PlayerFileKind::BuildList => &self.player_build_lists,
+            PlayerFileKind::CityPlan => &self.player_city_plans,
+        }
+    }
+
+    /// Get the names of each player's embedded files of a kind mutably.
+    pub(crate) fn player_file_names_mut(&mut self, kind: PlayerFileKind) -> &mut [Option<String>] {
+        match kind {
+            PlayerFileKind::AIRules => &mut self.player_ai_rules,
+            PlayerFileKind::BuildList => &mut self.player_build_lists,
+            PlayerFileKind::CityPlan => &mut self.player_city_plans,
+        }
+    }
+
+    /// Get the text of a message.
+    pub(crate) fn message(&self, kind: MessageKind) -> Option<&str> {
+        match kind {
+            MessageKind::Instructions => self.description.as_deref(),
+            MessageKind::Hints => self.hints.as_deref(),
+            MessageKind::Victory => self.win_message.as_deref(),
+            MessageKind::Loss => self.loss_message.as_deref(),
+            MessageKind::History => self.history.as_deref(),
+            MessageKind::Scouts => self.scout.as_deref(),
+        }
+    }
+
+    /// Get the text of a message mutably.
+    pub(crate) fn message_mut(&mut self, kind: MessageKind) -> &mut Option<String> {
+        match kind {
+            MessageKind::Instructions => &mut self.description,
+            MessageKind::Hints => &mut self.hints,
+            MessageKind::Victory => &mut self.win_message,
+            MessageKind::Loss => &mut self.loss_message,
+            MessageKind::History => &mut self.history,
+            MessageKind::Scouts => &mut self.scout,
+        }
+    }
+
+    /// Get the string table ID of a message.
+    pub(crate) fn message_string_table(&self, kind: MessageKind) -> Option<&StringKey> {
+        match kind {
+            MessageKind::Instructions => self.description_string_table.as_ref(),
+            MessageKind::Hints => self.hints_string_table.as_ref(),
+            MessageKind::Victory => self.win_message_string_table.as_ref(),
+            MessageKind::Loss => self.loss_message_string_table.as_ref(),
+            MessageKind::History => self.history_string_table.as_ref(),
+            MessageKind::Scouts => self.scout_string_table.as_ref(),
+        }
+    }
+
+    /// Get the string table ID of a message mutably.
+    pub(crate) fn message_string_table_mut(&mut self, kind: MessageKind) -> &mut Option<StringKey> {
+        match kind {
+            MessageKind::Instructions => &mut self.description_string_table,
+            MessageKind::Hints => &mut self.hints_string_table,
+            MessageKind::Victory => &mut self.win_message_string_table,
+            MessageKind::Loss => &mut self.loss_message_string_table,
+            MessageKind::History => &mut self.history_string_table,
+            MessageKind::Scouts => &mut self.scout_string_table,
+        }
+    }
+
+    /// Get the file name of a cinematic.
+    pub(crate) fn cinematic(&self, kind: CinematicKind) -> Option<&str> {
+        match kind {
+            CinematicKind::Pregame => self.pregame_cinematic.as_deref(),
+            CinematicKind::Victory => self.victory_cinematic.as_deref(),
+            CinematicKind::Loss => self.loss_cinematic.as_deref(),
+        }
+    }
+
+    /// Get the file name of a cinematic mutably.
+    pub(crate) fn cinematic_mut(&mut self, kind: CinematicKind) -> &mut Option<String> {
+        match kind {
+            CinematicKind::Pregame => &mut self.pregame_cinematic,
+            CinematicKind::Victory => &mut self.victory_cinematic,
+            CinematicKind::Loss => &mut self.loss_cinematic,
+        }
+    }
+
+    /// Read scenario data from an input stream, reading past the given sections without keeping
+    /// them in memory.
+    pub fn read_from(
+        mut input: impl Read,
+        format: Option<SCXVersion>,
+        skip: &[ScenarioSection],
+    ) -> Result<Self> {
+        let data_version = input.read_f32::<LE>()?;
+        log::debug!("RGEScen version {}", data_version);
+        let version = layout_version(format, data_version);
         let mut player_names = vec![None; 16];
         if version > 1.13 {
             for name in player_names.iter_mut() {
@@ -253,10 +396,13 @@ impl RGEScen {
             None
         };
 
-        let _mission_picture = if version >= 1.10 {
-            Bitmap::read_from(&mut input)?
-        } else {
+        let mission_picture = if version < 1.10 {
+            None
+        } else if skip.contains(&ScenarioSection::InstructionsBitmap) {
+            Bitmap::skip(&mut input)?;
             None
+        } else {
+            Bitmap::read_from(&mut input)?
         };
 
         let mut player_build_lists = vec![None; 16];
@@ -286,6 +432,11 @@ impl RGEScen {
                 0
             };
 
+            if skip.contains(&ScenarioSection::PlayerFiles) {
+                let length = build_list_length + city_plan_length + ai_rules_length;
+                skip_bytes(&mut input, length as u64)?;
+                continue;
+            }
             files.build_list = input.read_str(build_list_length)?;
             files.city_plan = input.read_str(city_plan_length)?;
             files.ai_rules = input.read_str(ai_rules_length)?;
@@ -304,7 +455,7 @@ impl RGEScen {
         }
 
         Ok(RGEScen {
-            version,
+            version: data_version,
             player_names,
             player_string_table,
             player_base_properties,
@@ -326,6 +477,7 @@ impl RGEScen {
             victory_cinematic,
             loss_cinematic,
             mission_bmp,
+            mission_picture,
             player_build_lists,
             player_city_plans,
             player_ai_rules,
@@ -334,8 +486,14 @@ impl RGEScen {
         })
     }
 
-    pub fn write_to(&self, mut output: impl Write, version: f32) -> Result<()> {
+    pub fn write_to(
+        &self,
+        mut output: impl Write,
+        format: Option<SCXVersion>,
+        version: f32,
+    ) -> Result<()> {
         output.write_f32::<LE>(version)?;
+        let version = layout_version(format, version);
 
         if version > 1.13 {
             assert_eq!(self.player_names.len(), 16);
@@ -411,16 +569,19 @@ impl RGEScen {
         write_opt_str(&mut output, &self.victory_cinematic)?;
         write_opt_str(&mut output, &self.loss_cinematic)?;
         if version >= 1.09 {
-            // mission_bmp
-            write_opt_str(&mut output, &None)?;
+            write_opt_str(&mut output, &self.mission_bmp)?;
         }
 
         if version >= 1.10 {
-            // mission_picture
-            output.write_u32::<LE>(0)?;
-            output.write_u32::<LE>(0)?;
-            output.write_u32::<LE>(0)?;
-            output.write_u16::<LE>(1)?;
+            match &self.mission_picture {
+                Some(picture) => picture.write_to(&mut output)?,
+                None => {
+                    output.write_u32::<LE>(0)?;
+                    output.write_u32::<LE>(0)?;
+                    output.write_u32::<LE>(0)?;
+                    output.write_u16::<LE>(1)?;
+                }
+            }
         }
 
         assert_eq!(self.player_build_lists.len(), 16);
@@ -490,6 +651,7 @@ impl RGEScen {
 ///
 /// The game saves this structure in scenario files, and also in saved and recorded game files.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TribeScen {
     /// "Engine" data.
     ///
@@ -500,20 +662,20 @@ pub struct TribeScen {
     /// Starting resources for players.
     player_start_resources: Vec<PlayerStartResources>,
     /// Victory settings.
-    victory: VictoryInfo,
+    pub(crate) victory: VictoryInfo,
     /// Whether all victory conditions need to be met for victory to occur.
-    victory_all_flag: bool,
+    pub(crate) victory_all_flag: bool,
     /// Type of victory condition to use in multiplayer games.
-    mp_victory_type: i32,
+    pub(crate) mp_victory_type: i32,
     /// Required score to attain multiplayer victory.
-    victory_score: i32,
+    pub(crate) victory_score: i32,
     /// Time at which the highest-scoring player will win the multiplayer match.
-    victory_time: i32,
+    pub(crate) victory_time: i32,
     /// Initial diplomacy stances between players.
-    diplomacy: Vec<Vec<DiplomaticStance>>,
+    pub(crate) diplomacy: Vec<Vec<DiplomaticStance>>,
     legacy_victory_info: Vec<Vec<LegacyVictoryInfo>>,
     /// Whether Allied Victory is enabled for each player.
-    allied_victory: Vec<i32>,
+    pub(crate) allied_victory: Vec<i32>,
     teams_locked: bool,
     can_change_teams: bool,
     random_start_locations: bool,
@@ -540,11 +702,13 @@ pub struct TribeScen {
     ///
     /// According to [AoE2ScenarioParser][].
     /// [AoE2ScenarioParser]: https://github.com/KSneijders/AoE2ScenarioParser/blob/8e3abd422164961aa5c7857350475088790804f8/AoE2ScenarioParser/pieces/options.py
+    #[allow(dead_code)]
     combat_mode: i32,
     /// (What exactly?)
     ///
     /// According to [AoE2ScenarioParser][].
     /// [AoE2ScenarioParser]: https://github.com/KSneijders/AoE2ScenarioParser/blob/8e3abd422164961aa5c7857350475088790804f8/AoE2ScenarioParser/pieces/options.py
+    #[allow(dead_code)]
     naval_mode: i32,
     /// Whether "All Techs" is enabled.
     all_techs: bool,
@@ -570,6 +734,14 @@ pub struct TribeScen {
 }
 
 impl TribeScen {
+    /// Get mutable references to all the strings in the scenario data.
+    pub(crate) fn strings_mut(&mut self) -> Vec<&mut String> {
+        let mut strings = self.base.strings_mut();
+        strings.extend(self.water_definition.as_mut());
+        strings.extend(self.color_mood.as_mut());
+        strings
+    }
+
     #[deprecated = "Use TribeScen::read_from instead"]
     #[doc(hidden)]
     pub fn from(input: impl Read) -> Result<Self> {
@@ -577,9 +749,19 @@ impl TribeScen {
     }
 
     /// Read scenario data from an input stream.
-    pub fn read_from(mut input: impl Read) -> Result<Self> {
-        let mut base = RGEScen::read_from(&mut input)?;
-        let version = base.version;
+    pub fn read_from(input: impl Read) -> Result<Self> {
+        Self::read_from_format(input, None, &[])
+    }
+
+    /// Read scenario data from an input stream, for a scenario file with the given format
+    /// version, skipping the given sections.
+    pub(crate) fn read_from_format(
+        mut input: impl Read,
+        format: Option<SCXVersion>,
+        skip: &[ScenarioSection],
+    ) -> Result<Self> {
+        let mut base = RGEScen::read_from(&mut input, format, skip)?;
+        let version = layout_version(format, base.version);
 
         let mut player_start_resources = vec![PlayerStartResources::default(); 16];
 
@@ -853,8 +1035,21 @@ impl TribeScen {
     }
 
     /// Write scenario data to an output stream.
-    pub fn write_to(&self, mut output: impl Write, version: f32, num_triggers: u32) -> Result<()> {
-        self.base.write_to(&mut output, version)?;
+    pub fn write_to(&self, output: impl Write, version: f32, num_triggers: u32) -> Result<()> {
+        self.write_to_format(output, None, version, num_triggers)
+    }
+
+    /// Write scenario data to an output stream, for a scenario file with the given format
+    /// version.
+    pub(crate) fn write_to_format(
+        &self,
+        mut output: impl Write,
+        format: Option<SCXVersion>,
+        version: f32,
+        num_triggers: u32,
+    ) -> Result<()> {
+        self.base.write_to(&mut output, format, version)?;
+        let version = layout_version(format, version);
 
         if version <= 1.13 {
             assert_eq!(self.base.player_names.len(), 16);
@@ -1061,7 +1256,7 @@ impl TribeScen {
         }
 
         if version >= 1.21 {
-            output.write_i32::<LE>(self.map_type.unwrap_or(-1))?;
+            write_opt_i32(&mut output, self.map_type)?;
         }
 
         if version >= 1.24 {
@@ -1100,6 +1295,7 @@ impl TribeScen {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SCXFormat {
     /// Version of the SCX format.
     pub(crate) version: SCXVersion,
@@ -1120,38 +1316,124 @@ pub struct SCXFormat {
     /// Triggers (only in AoK and up).
     pub(crate) triggers: Option<TriggerSystem>,
     /// AI information (AoK and up).
-    ai_info: Option<AIInfo>,
+    pub(crate) ai_info: Option<AIInfo>,
+    /// Sections that were not read.
+    pub(crate) skipped: Vec<ScenarioSection>,
 }
 
 impl SCXFormat {
+    /// Create a scenario from embedded scenario data and a map, without objects.
+    pub(crate) fn from_tribe_scen(
+        tribe_scen: TribeScen,
+        map: Map,
+        version: &VersionBundle,
+    ) -> Self {
+        let base = &tribe_scen.base;
+        let scenario_players = (1..=8)
+            .map(|player| {
+                let mut data = ScenarioPlayerData::new(player, version.victory);
+                data.name = base.player_names[player - 1].clone();
+                data
+            })
+            .collect();
+        let header = SCXHeader {
+            version: version.header,
+            timestamp: 0,
+            description: tribe_scen.description().map(str::to_string),
+            author_name: None,
+            any_sp_victory: false,
+            active_player_count: base.player_base_properties[..8]
+                .iter()
+                .filter(|properties| properties.active != 0)
+                .count() as u32,
+            dlc_options: version.dlc_options.map(|_| Default::default()),
+        };
+
+        Self {
+            version: version.format,
+            header,
+            next_object_id: 0,
+            tribe_scen,
+            map,
+            world_players: vec![WorldPlayerData::default(); 8],
+            player_objects: vec![vec![]; 9],
+            scenario_players,
+            triggers: version.triggers.map(|_| TriggerSystem::default()),
+            ai_info: None,
+            skipped: vec![],
+        }
+    }
+
     /// Extract version bundle information from a parsed SCX file.
     pub fn version(&self) -> VersionBundle {
         VersionBundle {
             format: self.version,
             header: self.header.version,
             data: self.tribe_scen.version(),
+            victory: self
+                .scenario_players
+                .first()
+                .map_or(2.0, |player| player.victory.version),
             triggers: self.triggers.as_ref().map(|triggers| triggers.version()),
             map: self.map.version(),
             ..VersionBundle::aoc()
         }
     }
 
-    fn load_inner(version: SCXVersion, player_version: f32, mut input: impl Read) -> Result<Self> {
-        let header = SCXHeader::read_from(&mut input, version)?;
+    fn load_inner(
+        version: SCXVersion,
+        player_version: f32,
+        input: impl Read,
+        compression: SCXCompression,
+        skip: &[ScenarioSection],
+    ) -> Result<Self> {
+        let mut input = CountingReader::new(input);
+        let header = trace_section(FILE, "header", &mut input, |input| {
+            SCXHeader::read_from(input, version)
+        })?;
+
+        // Most fields are read one by one, so buffer the decompressed data. Uncompressed input is
+        // left alone, so that nothing is read past the end of an embedded scenario.
+        match compression {
+            SCXCompression::Uncompressed => {
+                Self::load_data(version, player_version, header, input, skip)
+            }
+            SCXCompression::Deflate { .. } => Self::load_data(
+                version,
+                player_version,
+                header,
+                io::BufReader::new(DeflateDecoder::new(input)),
+                skip,
+            ),
+        }
+    }
 
-        let mut input = DeflateDecoder::new(&mut input);
+    fn load_data(
+        version: SCXVersion,
+        player_version: f32,
+        header: SCXHeader,
+        input: impl Read,
+        skip: &[ScenarioSection],
+    ) -> Result<Self> {
+        let mut input = CountingReader::new(input);
         let next_object_id = input.read_i32::<LE>()?;
 
-        let tribe_scen = TribeScen::read_from(&mut input)?;
+        let tribe_scen = trace_section(FILE, "scenario data", &mut input, |input| {
+            TribeScen::read_from_format(input, Some(version), skip)
+        })?;
 
-        let map = Map::read_from(&mut input)?;
+        let map = trace_section(FILE, "map", &mut input, |input| Map::read_from(input))?;
 
         let num_players = input.read_u32::<LE>()?;
         log::debug!("number of players: {}", num_players);
-        let mut world_players = Vec::with_capacity(num_players as usize);
-        for _ in 1..num_players {
-            world_players.push(WorldPlayerData::read_from(&mut input, player_version)?);
-        }
+        let world_players =
+            trace_section(FILE, "world players", &mut input, |input| -> Result<_> {
+                let mut world_players = Vec::with_capacity(num_players as usize);
+                for _ in 1..num_players {
+                    world_players.push(WorldPlayerData::read_from(&mut *input, player_version)?);
+                }
+                Ok(world_players)
+            })?;
 
         fn read_scenario_players(
             mut input: impl Read,
@@ -1185,24 +1467,38 @@ impl SCXFormat {
         }
 
         // The order is flipped … thanks DE
+        let read_players = |input: &mut CountingReader<_>| {
+            trace_section(FILE, "scenario players", input, |input| {
+                read_scenario_players(input, player_version)
+            })
+        };
+        let read_objects = |input: &mut CountingReader<_>| {
+            trace_section(FILE, "objects", input, |input| {
+                read_player_objects(input, num_players, version)
+            })
+        };
         let (scenario_players, player_objects) = if version >= SCXVersion(*b"1.36") {
-            let players = read_scenario_players(&mut input, player_version)?;
-            let objects = read_player_objects(&mut input, num_players, version)?;
+            let players = read_players(&mut input)?;
+            let objects = read_objects(&mut input)?;
             (players, objects)
         } else {
-            let objects = read_player_objects(&mut input, num_players, version)?;
-            let players = read_scenario_players(&mut input, player_version)?;
+            let objects = read_objects(&mut input)?;
+            let players = read_players(&mut input)?;
             (players, objects)
         };
 
         let triggers = if version < SCXVersion(*b"1.14") {
             None
         } else {
-            Some(TriggerSystem::read_from(&mut input)?)
+            Some(trace_section(FILE, "triggers", &mut input, |input| {
+                TriggerSystem::read_from(input)
+            })?)
         };
 
         let ai_info = if version > SCXVersion(*b"1.17") && version < SCXVersion(*b"2.00") {
-            AIInfo::read_from(&mut input)?
+            trace_section(FILE, "ai", &mut input, |input| {
+                AIInfo::read_from(input, skip.contains(&ScenarioSection::AIFiles))
+            })?
         } else {
             None
         };
@@ -1218,15 +1514,33 @@ impl SCXFormat {
             scenario_players,
             triggers,
             ai_info,
+            skipped: skip.to_vec(),
         })
     }
 
-    pub fn load_scenario(mut input: impl Read) -> Result<Self> {
+    pub fn load_scenario(input: impl Read) -> Result<Self> {
+        Self::load_scenario_with(input, SCXCompression::default())
+    }
+
+    /// Read a scenario whose data is compressed in the given way.
+    pub(crate) fn load_scenario_with(
+        input: impl Read,
+        compression: SCXCompression,
+    ) -> Result<Self> {
+        Self::load_scenario_skipping(input, compression, &[])
+    }
+
+    /// Read a scenario whose data is compressed in the given way, skipping the given sections.
+    pub(crate) fn load_scenario_skipping(
+        mut input: impl Read,
+        compression: SCXCompression,
+        skip: &[ScenarioSection],
+    ) -> Result<Self> {
         let mut format_version = [0; 4];
         input.read_exact(&mut format_version)?;
         let format_version = SCXVersion(format_version);
         if let Some(player_version) = format_version.to_player_version() {
-            Self::load_inner(format_version, player_version, input)
+            Self::load_inner(format_version, player_version, input, compression, skip)
         } else {
             Err(Error::UnsupportedFormatVersionError(format_version))
         }
@@ -1259,26 +1573,67 @@ impl SCXFormat {
         Ok(())
     }
 
-    pub fn write_to(&self, mut output: impl Write, version: &VersionBundle) -> Result<()> {
+    pub fn write_to(&self, output: impl Write, version: &VersionBundle) -> Result<()> {
+        self.write_to_with(output, version, SCXCompression::default())
+    }
+
+    /// Write the scenario, compressing its data in the given way.
+    pub(crate) fn write_to_with(
+        &self,
+        mut output: impl Write,
+        version: &VersionBundle,
+        compression: SCXCompression,
+    ) -> Result<()> {
+        if let Some(section) = self.skipped.first() {
+            return Err(Error::SkippedSectionError(*section));
+        }
+
         let player_version = match version.format.to_player_version() {
             Some(v) => v,
             None => return Err(Error::UnsupportedFormatVersionError(version.format)),
         };
 
-        output.write_all(version.format.as_bytes())?;
-        self.header
-            .write_to(&mut output, version.format, version.header)?;
-
-        let mut output = DeflateEncoder::new(output, Compression::default());
-        output.write_i32::<LE>(self.next_object_id)?;
-
         let num_triggers = self
             .triggers
             .as_ref()
             .map(|trigger_system| trigger_system.num_triggers())
             .unwrap_or(0);
-        self.tribe_scen
-            .write_to(&mut output, version.data, num_triggers)?;
+
+        output.write_all(version.format.as_bytes())?;
+        self.header.write_with_trigger_count(
+            &mut output,
+            version.format,
+            version.header,
+            num_triggers,
+        )?;
+
+        match compression {
+            SCXCompression::Uncompressed => {
+                self.write_data(output, version, player_version, num_triggers)
+            }
+            SCXCompression::Deflate { level } => {
+                let mut output = DeflateEncoder::new(output, deflate_compression(level)?);
+                self.write_data(&mut output, version, player_version, num_triggers)?;
+                output.finish()?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_data(
+        &self,
+        mut output: impl Write,
+        version: &VersionBundle,
+        player_version: f32,
+        num_triggers: u32,
+    ) -> Result<()> {
+        output.write_i32::<LE>(self.next_object_id)?;
+        self.tribe_scen.write_to_format(
+            &mut output,
+            Some(version.format),
+            version.data,
+            num_triggers,
+        )?;
         self.map.write_to(&mut output, version.map)?;
 
         output.write_i32::<LE>(self.player_objects.len() as i32)?;
@@ -1312,8 +1667,6 @@ impl SCXFormat {
             ai_info.write_to(&mut output)?;
         }
 
-        output.finish()?;
-
         Ok(())
     }
 
@@ -1339,6 +1692,18 @@ impl SCXFormat {
     }
 }
 
+/// Get the deflate compression for a level from 0 to 9.
+pub(crate) fn deflate_compression(level: u32) -> Result<Compression> {
+    if level > 9 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid compression level {}, expected 0-9", level),
+        )
+        .into());
+    }
+    Ok(Compression::new(level))
+}
+
 fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) -> Result<()> {
     output.write_u32::<LE>(if let Some(key) = opt_key {
         key.try_into()
@@ -1352,7 +1717,7 @@ fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) ->
 #[cfg(test)]
 mod tests {
     use super::SCXFormat;
-    use crate::{Result, VersionBundle};
+    use crate::{Error, Result, SCXVersion, VersionBundle};
     use std::fs::File;
     use std::io::{Cursor, ErrorKind, Read};
 
@@ -1486,6 +1851,43 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn aoe1_scn_to_ror_and_back() -> Result<()> {
+        let mut f = File::open("test/scenarios/Bronze Age Art of War.scn")?;
+        let format = SCXFormat::load_scenario(&mut f)?;
+        assert_consumed(f);
+        assert!(format.version().is_aoe1());
+
+        let ror = save_and_load(&format, VersionBundle::ror())?;
+        assert_eq!(ror.version(), VersionBundle::ror());
+        let aoe = save_and_load(&ror, VersionBundle::aoe())?;
+        assert_eq!(aoe.version(), VersionBundle::aoe());
+        assert_eq!(
+            format.hash(),
+            aoe.hash(),
+            "should produce exactly the same scenario"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aoc_to_swgb() -> Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let format = SCXFormat::load_scenario(&mut f)?;
+        let swgb = save_and_load(&format, VersionBundle::swgb_cc())?;
+        assert_eq!(swgb.version(), VersionBundle::swgb_cc());
+        assert!(swgb.version().is_swgb());
+        assert!(!swgb.version().is_age2_de());
+
+        let aoc = save_and_load(&swgb, format.version())?;
+        assert_eq!(
+            format.hash(),
+            aoc.hash(),
+            "should produce exactly the same scenario"
+        );
+        Ok(())
+    }
+
     /// Source: http://aok.heavengames.com/blacksmith/showfile.php?fileid=1271
     #[test]
     fn oldest_aok_scn_on_aokheaven() {
@@ -1509,6 +1911,52 @@ mod tests {
             .expect("failed to write");
     }
 
+    #[test]
+    fn aoc_to_de2() -> Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let format = SCXFormat::load_scenario(&mut f)?;
+        let format2 = save_and_load(&format, VersionBundle::aoe2_de())?;
+        let version = format2.version();
+        let expected = VersionBundle::aoe2_de();
+        assert_eq!(version.format, expected.format);
+        assert_eq!(version.header, expected.header);
+        assert_eq!(version.data, expected.data);
+        assert_eq!(version.triggers, expected.triggers);
+        assert_eq!(version.map, expected.map);
+
+        let num_triggers = format.triggers.as_ref().unwrap().num_triggers();
+        assert!(num_triggers > 0);
+        assert_eq!(
+            format2.triggers.as_ref().unwrap().num_triggers(),
+            num_triggers
+        );
+        assert_eq!(format2.player_objects.len(), format.player_objects.len());
+
+        // Reading and writing the converted scenario again does not change it.
+        let mut first = vec![];
+        format2.write_to(&mut first, &format2.version())?;
+        let format3 = SCXFormat::load_scenario(&first[..])?;
+        let mut second = vec![];
+        format3.write_to(&mut second, &format3.version())?;
+        assert!(first == second, "DE scenario did not round-trip");
+        Ok(())
+    }
+
+    #[test]
+    fn write_unsupported_de_version() -> Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let format = SCXFormat::load_scenario(&mut f)?;
+        let version = VersionBundle {
+            format: SCXVersion(*b"1.40"),
+            ..format.version()
+        };
+        assert!(matches!(
+            format.write_to(&mut vec![], &version),
+            Err(Error::UnsupportedFormatVersionError(SCXVersion(v))) if &v == b"1.40"
+        ));
+        Ok(())
+    }
+
     #[test]
     fn hd_aoe2scenario() {
         let mut f = File::open("test/scenarios/Year_of_the_Pig.aoe2scenario").unwrap();
@@ -0,0 +1,243 @@
+//! Generators for common trigger patterns, that expand into sets of triggers.
+
+use crate::{Condition, Effect, Result, Trigger, TriggerSystem};
+
+/// An objective that is completed when all of its goals are met, created with
+/// [`TriggerSystem::add_objective`].
+///
+/// ```
+/// use genie_scx::{Condition, Effect, Objective};
+///
+/// let objective = Objective::new("Defend the castle", "Survive and keep the castle.", 1)
+///     .goal("Survive for 10 minutes.", Condition::Timer(600))
+///     .goal("Keep the castle.", Condition::OwnObjects {
+///         player: 1,
+///         amount: 1,
+///         filter: Default::default(),
+///     })
+///     .effect(Effect::DeclareVictory { player: 1 });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Objective {
+    name: String,
+    description: String,
+    order: i32,
+    goals: Vec<(String, Condition)>,
+    effects: Vec<Effect>,
+}
+
+impl Objective {
+    /// Create an objective without goals. It is shown in the objectives list with the given
+    /// description, sorted by `order`.
+    pub fn new(name: impl Into<String>, description: impl Into<String>, order: i32) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            order,
+            goals: vec![],
+            effects: vec![],
+        }
+    }
+
+    /// Add a goal, shown below the objective in the objectives list with the given description.
+    pub fn goal(mut self, description: impl Into<String>, condition: Condition) -> Self {
+        self.goals.push((description.into(), condition));
+        self
+    }
+
+    /// Add an effect that fires when all goals are met.
+    pub fn effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+}
+
+/// A sequence of effects that fire one after another, created with
+/// [`TriggerSystem::add_cinematic`].
+///
+/// ```
+/// use genie_scx::{Cinematic, Effect, Location};
+///
+/// let cinematic = Cinematic::new("Intro")
+///     .step(0, vec![Effect::ChangeView { player: 1, location: Location { x: 10, y: 10 } }])
+///     .step(5, vec![Effect::ChangeView { player: 1, location: Location { x: 60, y: 40 } }]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cinematic {
+    name: String,
+    enabled: bool,
+    steps: Vec<(i32, Vec<Effect>)>,
+}
+
+impl Cinematic {
+    /// Create a cinematic without steps, that starts when the scenario starts.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            enabled: true,
+            steps: vec![],
+        }
+    }
+
+    /// Set whether the cinematic starts when the scenario starts. A cinematic that does not can
+    /// be started by activating its first trigger.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Add a step that fires `delay` seconds after the previous step, or after the cinematic
+    /// starts.
+    pub fn step(mut self, delay: i32, effects: impl IntoIterator<Item = Effect>) -> Self {
+        self.steps.push((delay, effects.into_iter().collect()));
+        self
+    }
+}
+
+impl TriggerSystem {
+    /// Add a looping trigger that fires the given effects every `interval` seconds. Returns the
+    /// ID of the trigger.
+    pub fn add_looping_timer(
+        &mut self,
+        name: impl Into<String>,
+        interval: i32,
+        effects: impl IntoIterator<Item = Effect>,
+    ) -> Result<i32> {
+        let mut trigger = Trigger::new(name)
+            .looping(true)
+            .condition(Condition::Timer(interval))?;
+        for effect in effects {
+            trigger.add_effect(effect)?;
+        }
+        self.add_trigger(trigger)
+    }
+
+    /// Add the triggers for an objective. Returns the IDs of the new triggers: first the
+    /// objective itself, then one trigger for each goal.
+    ///
+    /// The objective trigger checks all goals at once and fires the objective's effects. Each
+    /// goal also gets a trigger of its own, which marks the goal as done in the objectives list
+    /// as soon as it is met.
+    pub fn add_objective(&mut self, objective: Objective) -> Result<Vec<i32>> {
+        let mut trigger =
+            Trigger::new(objective.name.as_str()).objective(objective.description, objective.order);
+        for (_, condition) in &objective.goals {
+            trigger.add_condition(condition.clone())?;
+        }
+        for effect in objective.effects {
+            trigger.add_effect(effect)?;
+        }
+
+        let mut ids = vec![self.add_trigger(trigger)?];
+        for (index, (description, condition)) in objective.goals.into_iter().enumerate() {
+            let order = objective.order + 1 + index as i32;
+            let goal = Trigger::new(format!("{} ({})", objective.name, index + 1))
+                .objective(description, order)
+                .condition(condition)?;
+            ids.push(self.add_trigger(goal)?);
+        }
+        Ok(ids)
+    }
+
+    /// Add the triggers for a cinematic. Returns the IDs of the new triggers, one for each step.
+    ///
+    /// Each step is a trigger that waits for its delay, fires its effects, and activates the
+    /// trigger for the next step.
+    pub fn add_cinematic(&mut self, cinematic: Cinematic) -> Result<Vec<i32>> {
+        let first = self.num_triggers() as i32;
+        let num_steps = cinematic.steps.len();
+        let mut ids = vec![];
+        for (index, (delay, effects)) in cinematic.steps.into_iter().enumerate() {
+            let mut trigger = Trigger::new(format!("{} ({})", cinematic.name, index + 1))
+                .enabled(index == 0 && cinematic.enabled);
+            if delay > 0 {
+                trigger.add_condition(Condition::Timer(delay))?;
+            }
+            for effect in effects {
+                trigger.add_effect(effect)?;
+            }
+            if index + 1 < num_steps {
+                trigger.add_effect(Effect::ActivateTrigger(first + index as i32 + 1))?;
+            }
+            ids.push(self.add_trigger(trigger)?);
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Location, Scenario};
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn generate_triggers() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let triggers = scen.triggers_mut().unwrap();
+
+        let timer = triggers.add_looping_timer(
+            "Tribute",
+            60,
+            vec![Effect::SendTribute {
+                player: 0,
+                target_player: 1,
+                resource: 0,
+                amount: 100,
+            }],
+        )?;
+        let objective = triggers.add_objective(
+            Objective::new("Escape", "Escape with your army.", 1)
+                .goal("Find the ship.", Condition::ObjectVisible { object: 100 })
+                .goal("Survive.", Condition::Timer(300))
+                .effect(Effect::DeclareVictory { player: 1 }),
+        )?;
+        let view = |x| Effect::ChangeView {
+            player: 1,
+            location: Location { x, y: 10 },
+        };
+        let cinematic = triggers.add_cinematic(
+            Cinematic::new("Intro")
+                .step(0, vec![view(10)])
+                .step(5, vec![view(20)])
+                .step(5, vec![view(30)]),
+        )?;
+        assert_eq!(objective.len(), 3);
+        assert_eq!(cinematic.len(), 3);
+
+        let mut bytes = vec![];
+        scen.write_to(&mut bytes)?;
+        let scen = Scenario::read_from(Cursor::new(bytes))?;
+        let triggers: Vec<_> = scen.triggers().unwrap().triggers_unordered().collect();
+
+        let timer = triggers[timer as usize];
+        assert!(timer.is_looping());
+        assert_eq!(
+            timer.conditions().next().unwrap().decode(),
+            Condition::Timer(60)
+        );
+
+        assert_eq!(triggers[objective[0] as usize].conditions().count(), 2);
+        assert_eq!(
+            triggers[objective[2] as usize]
+                .conditions()
+                .next()
+                .unwrap()
+                .decode(),
+            Condition::Timer(300)
+        );
+
+        let steps: Vec<_> = cinematic.iter().map(|id| triggers[*id as usize]).collect();
+        assert!(steps[0].is_enabled() && !steps[1].is_enabled() && !steps[2].is_enabled());
+        assert_eq!(steps[0].conditions().count(), 0);
+        let effects: Vec<_> = steps[1].effects().map(|effect| effect.decode()).collect();
+        assert_eq!(
+            effects,
+            vec![view(20), Effect::ActivateTrigger(cinematic[2])]
+        );
+        assert_eq!(steps[2].effects().count(), 1);
+        Ok(())
+    }
+}
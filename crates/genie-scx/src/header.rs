@@ -1,11 +1,14 @@
 use crate::types::{DLCPackage, DataSet, SCXVersion};
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{write_opt_i32_str, ReadStringsExt};
+use genie_support::{
+    encode_str_with_encoding, write_opt_i32_str, ReadStringsExt, DEFAULT_ENCODING,
+};
 use std::convert::TryFrom;
 use std::io::{Read, Write};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DLCOptions {
     /// Version of the DLC options structure.
     pub version: i32,
@@ -69,6 +72,7 @@ impl DLCOptions {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SCXHeader {
     /// Version of the header.
     ///
@@ -135,11 +139,27 @@ impl SCXHeader {
     }
 
     /// Serialize an SCX header to a byte stream.
+    ///
+    /// Header versions 5 and up contain the number of triggers in the scenario, which is written
+    /// as 0 by this method. [`Scenario::write_to`](crate::Scenario::write_to) writes the actual
+    /// number.
     pub fn write_to(
         &self,
         output: impl Write,
         format_version: SCXVersion,
         version: u32,
+    ) -> Result<()> {
+        self.write_with_trigger_count(output, format_version, version, 0)
+    }
+
+    /// Serialize an SCX header to a byte stream, for a scenario with the given number of
+    /// triggers.
+    pub(crate) fn write_with_trigger_count(
+        &self,
+        output: impl Write,
+        format_version: SCXVersion,
+        version: u32,
+        num_triggers: u32,
     ) -> Result<()> {
         let mut intermediate = vec![];
 
@@ -151,18 +171,19 @@ impl SCXHeader {
 
         let mut description_bytes = vec![];
         if let Some(ref description) = self.description {
-            description_bytes.write_all(description.as_bytes())?;
+            let bytes = encode_str_with_encoding(description, DEFAULT_ENCODING)?;
+            description_bytes.write_all(&bytes)?;
         }
         description_bytes.push(0);
         if format_version == *b"3.13" {
             assert!(
-                description_bytes.len() <= std::u16::MAX as usize,
+                description_bytes.len() <= u16::MAX as usize,
                 "description length must fit in u16"
             );
             intermediate.write_u16::<LE>(description_bytes.len() as u16)?;
         } else {
             assert!(
-                description_bytes.len() <= std::u32::MAX as usize,
+                description_bytes.len() <= u32::MAX as usize,
                 "description length must fit in u32"
             );
             intermediate.write_u32::<LE>(description_bytes.len() as u32)?;
@@ -183,8 +204,7 @@ impl SCXHeader {
 
         if version >= 5 {
             write_opt_i32_str(&mut intermediate, &self.author_name)?;
-            // TODO should be number of triggers
-            intermediate.write_u32::<LE>(0)?;
+            intermediate.write_u32::<LE>(num_triggers)?;
         }
 
         // Make `output` mutable here so we don't accidentally use it above.
@@ -195,7 +215,11 @@ impl SCXHeader {
         Ok(())
     }
 
-    /// Update the timestamp.
+    /// Update the timestamp to the current time.
+    ///
+    /// This is not available on `wasm32-unknown-unknown`, which has no system clock. Set
+    /// `timestamp` directly instead, for example from `Date.now()` in JavaScript.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     pub fn touch(&mut self) -> std::result::Result<(), std::time::SystemTimeError> {
         let system_time = std::time::SystemTime::now();
         let duration = system_time.duration_since(std::time::UNIX_EPOCH)?;
@@ -203,3 +227,7 @@ impl SCXHeader {
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    DLCOptions => crate::Error;
+}
@@ -0,0 +1,50 @@
+//! JSON export and import of scenarios.
+//!
+//! Storing scenarios as JSON lets external tools, such as web-based trigger editors, read and
+//! edit the triggers, players, objects and map of a scenario without parsing the binary format.
+
+use crate::Scenario;
+use std::io::{Read, Result, Write};
+
+impl Scenario {
+    /// Export the scenario as pretty-printed JSON.
+    pub fn to_json(&self, output: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(output, self)?;
+        Ok(())
+    }
+
+    /// Import a scenario from JSON created by [`Scenario::to_json`].
+    pub fn from_json(input: impl Read) -> Result<Self> {
+        Ok(serde_json::from_reader(input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn to_bytes(scen: &Scenario) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        scen.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn json_roundtrip() -> anyhow::Result<()> {
+        for name in &[
+            "Age of Heroes b1-3-5.scx",
+            "Jeremiah Johnson (Update).scx",
+            "Dawn of a New Age.scn",
+        ] {
+            let mut f = File::open(format!("test/scenarios/{}", name))?;
+            let scen = Scenario::read_from(&mut f)?;
+
+            let mut json = vec![];
+            scen.to_json(&mut json)?;
+            let imported = Scenario::from_json(&json[..])?;
+            assert_eq!(to_bytes(&imported)?, to_bytes(&scen)?, "{}", name);
+        }
+        Ok(())
+    }
+}
@@ -12,28 +12,58 @@
 
 mod ai;
 mod bitmap;
+mod compact;
 pub mod convert;
+mod diff;
+mod encoding;
 mod format;
+mod generate;
 mod header;
+#[cfg(feature = "json")]
+mod json;
 mod map;
+mod messages;
+mod minimap;
 mod player;
+mod raw;
+mod sections;
+mod summary;
+mod text;
+mod transplant;
 mod triggers;
 mod types;
+mod validate;
 mod victory;
 
 use format::SCXFormat;
 use genie_support::{ReadStringError, WriteStringError};
+use std::collections::HashSet;
+use std::convert::{Infallible, TryFrom};
 use std::io::{self, Read, Write};
 
+pub use ai::AIFile;
+pub use bitmap::Bitmap;
+pub use diff::{ScenarioChange, ScenarioDiff};
 pub use format::{ScenarioObject, TribeScen};
-pub use genie_support::{DecodeStringError, EncodeStringError};
-pub use genie_support::{StringKey, UnitTypeID};
+pub use generate::{Cinematic, Objective};
+pub use genie_support::{DecodeStringError, EncodeStringError, Encoding};
+pub use genie_support::{GameRelease, StringKey, StringSource, UnitTypeID};
 pub use header::{DLCOptions, SCXHeader};
 pub use map::{Map, Tile};
-pub use player::{ScenarioPlayerData, WorldPlayerData};
-pub use triggers::{Trigger, TriggerCondition, TriggerEffect, TriggerSystem};
+pub use messages::{CinematicKind, MessageKind};
+pub use minimap::{Minimap, MinimapColors};
+pub use player::{PlayerFile, PlayerFileKind, ScenarioPlayerData, WorldPlayerData};
+pub use raw::RawScenario;
+pub use sections::ScenarioSection;
+pub use summary::ScenarioSummary;
+pub use transplant::CopiedRegion;
+pub use triggers::{
+    Area, Condition, ConditionType, Effect, EffectType, Location, ObjectFilter, ObjectSelection,
+    Trigger, TriggerCondition, TriggerEffect, TriggerSystem,
+};
 pub use types::*;
-pub use victory::{VictoryConditions, VictoryEntry, VictoryPointEntry, VictoryState};
+pub use validate::{TriggerPart, ValidationIssue, ValidationReport};
+pub use victory::{VictoryConditions, VictoryEntry, VictoryInfo, VictoryPointEntry, VictoryState};
 
 /// Error type for SCX methods, containing all types of errors that may occur while reading or
 /// writing scenario files.
@@ -65,6 +95,26 @@ pub enum Error {
     /// disabling buildings.
     #[error("requested version does not support disabling buildings")]
     CannotDisableBuildingsError,
+    /// Attempted to place an object for a player that does not exist in the scenario.
+    #[error("player {} does not exist", .0)]
+    InvalidPlayerError(usize),
+    /// Attempted to change an object that does not exist in the scenario.
+    #[error("object {} does not exist", .0)]
+    UnknownObjectError(i32),
+    /// Attempted to copy a trigger that does not exist in the scenario.
+    #[error("trigger {} does not exist", .0)]
+    UnknownTriggerError(usize),
+    /// Attempted to write a scenario that was read without one of its sections.
+    #[error("cannot write a scenario without its {:?} section", .0)]
+    SkippedSectionError(ScenarioSection),
+    /// Attempted to add a trigger, condition, effect or selected object beyond the `i32::MAX`
+    /// items that a scenario file can store.
+    #[error("too many trigger items: got {}, but scenarios support up to {}", .0, i32::MAX)]
+    TooManyTriggerItemsError(usize),
+    /// Attempted to garrison an object into itself, into an object that is garrisoned in it, or
+    /// into objects that are garrisoned in each other.
+    #[error("cannot garrison object {} into object {}", .0, .1)]
+    InvalidGarrisonError(i32, i32),
     /// Failed to decode a string from the scenario file, probably because of a wrong encoding.
     #[error(transparent)]
     DecodeStringError(#[from] DecodeStringError),
@@ -94,6 +144,12 @@ pub enum Error {
     IoError(#[from] io::Error),
 }
 
+impl From<Infallible> for Error {
+    fn from(err: Infallible) -> Error {
+        match err {}
+    }
+}
+
 impl From<ReadStringError> for Error {
     fn from(err: ReadStringError) -> Error {
         match err {
@@ -112,11 +168,36 @@ impl From<WriteStringError> for Error {
     }
 }
 
+impl From<Error> for genie_support::Error {
+    fn from(err: Error) -> Self {
+        let version = match &err {
+            // Unsupported versions may not be valid UTF-8.
+            Error::UnsupportedFormatVersionError(version) => {
+                Some(String::from_utf8_lossy(version.as_bytes()).into_owned())
+            }
+            _ => None,
+        };
+        let section = match &err {
+            Error::SkippedSectionError(section) => Some(format!("{:?}", section)),
+            _ => None,
+        };
+        let mut converted = genie_support::Error::new("scenario", err);
+        if let Some(version) = version {
+            converted = converted.with_version(version);
+        }
+        if let Some(section) = section {
+            converted = converted.in_section(section);
+        }
+        converted
+    }
+}
+
 /// Result type for SCX methods.
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// A Scenario file.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Scenario {
     format: SCXFormat,
     version: VersionBundle,
@@ -124,11 +205,46 @@ pub struct Scenario {
 
 impl Scenario {
     /// Read a scenario file.
+    ///
+    /// Decompressed scenario data is buffered internally, but the input itself is not, so wrap a
+    /// `File` in a `BufReader`.
     pub fn read_from(input: impl Read) -> Result<Self> {
         let format = SCXFormat::load_scenario(input)?;
-        let version = format.version();
+        Ok(Self::from_format(format))
+    }
 
-        Ok(Self { format, version })
+    /// Read a scenario file whose data is compressed in the given way, such as an uncompressed
+    /// scenario written by [`Scenario::write_to_with`].
+    pub fn read_from_with(input: impl Read, compression: SCXCompression) -> Result<Self> {
+        let format = SCXFormat::load_scenario_with(input, compression)?;
+        Ok(Self::from_format(format))
+    }
+
+    /// Read a scenario file from an async stream, such as an upload in a web service.
+    ///
+    /// The whole file is read into memory without blocking the executor, and then parsed.
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader(mut input: impl tokio::io::AsyncRead + Unpin) -> Result<Self> {
+        use tokio::io::AsyncReadExt;
+
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes).await?;
+        Self::read_from(&bytes[..])
+    }
+
+    /// Create a scenario from the scenario data embedded in a saved or recorded game, together
+    /// with the map. The scenario does not contain any objects yet.
+    ///
+    /// The scenario is written in the format of the given game versions. Player starting
+    /// resources, views and colours are reset to their defaults, and all players are enemies.
+    pub fn from_tribe_scen(tribe_scen: TribeScen, map: Map, version: VersionBundle) -> Self {
+        let format = SCXFormat::from_tribe_scen(tribe_scen, map, &version);
+        Self { format, version }
+    }
+
+    pub(crate) fn from_format(format: SCXFormat) -> Self {
+        let version = format.version();
+        Self { format, version }
     }
 
     /// Read a scenario file.
@@ -145,10 +261,40 @@ impl Scenario {
     }
 
     /// Write the scenario file to an output stream, targeting specific game versions.
+    ///
+    /// This can convert scenarios between game versions. For example, pass
+    /// [`VersionBundle::aoe2_de()`] to save an Age of Conquerors scenario in the Definitive
+    /// Edition format.
     pub fn write_to_version(&self, output: impl Write, version: &VersionBundle) -> Result<()> {
         self.format.write_to(output, version)
     }
 
+    /// Write the scenario file to an output stream, targeting specific game versions and
+    /// compressing the data in the given way.
+    ///
+    /// Uncompressed scenarios can not be opened by the games, but are easier to inspect when
+    /// debugging.
+    pub fn write_to_with(
+        &self,
+        output: impl Write,
+        version: &VersionBundle,
+        compression: SCXCompression,
+    ) -> Result<()> {
+        self.format.write_to_with(output, version, compression)
+    }
+
+    /// Convert this scenario to a different Age of Empires 2 version.
+    ///
+    /// Shorthand for `VersionConverter::new(to).convert(scen)`. After the conversion,
+    /// [`Scenario::write_to`] saves the scenario in the target version's format. See
+    /// [`convert::VersionConverter`] for details.
+    pub fn convert(
+        &mut self,
+        to: VersionBundle,
+    ) -> std::result::Result<convert::ConversionReport, convert::ConvertError> {
+        convert::VersionConverter::new(to).convert(self)
+    }
+
     /// Get the format version of this SCX file.
     #[inline]
     pub fn format_version(&self) -> SCXVersion {
@@ -195,7 +341,7 @@ impl Scenario {
     #[inline]
     pub fn requires_dlc(&self, dlc: DLCPackage) -> bool {
         match &self.header().dlc_options {
-            Some(options) => options.dependencies.iter().any(|dep| *dep == dlc),
+            Some(options) => options.dependencies.contains(&dlc),
             None => false,
         }
     }
@@ -214,8 +360,7 @@ impl Scenario {
         self.format
             .player_objects
             .iter()
-            .map(|list| list.iter())
-            .flatten()
+            .flat_map(|list| list.iter())
     }
 
     /// Iterate mutably over all the objects placed in the scenario.
@@ -224,8 +369,7 @@ impl Scenario {
         self.format
             .player_objects
             .iter_mut()
-            .map(|list| list.iter_mut())
-            .flatten()
+            .flat_map(|list| list.iter_mut())
     }
 
     pub fn world_players(&self) -> &[WorldPlayerData] {
@@ -259,4 +403,629 @@ impl Scenario {
     pub fn triggers_mut(&mut self) -> Option<&mut TriggerSystem> {
         self.format.triggers.as_mut()
     }
+
+    /// Get the victory setting.
+    pub fn victory_mode(&self) -> VictoryMode {
+        self.format.tribe_scen.mp_victory_type.into()
+    }
+
+    /// Set the victory setting.
+    pub fn set_victory_mode(&mut self, mode: VictoryMode) {
+        self.format.tribe_scen.mp_victory_type = mode.into();
+    }
+
+    /// Get the score needed to win with [`VictoryMode::Score`].
+    pub fn victory_score(&self) -> i32 {
+        self.format.tribe_scen.victory_score
+    }
+
+    /// Set the score needed to win with [`VictoryMode::Score`].
+    pub fn set_victory_score(&mut self, score: i32) {
+        self.format.tribe_scen.victory_score = score;
+    }
+
+    /// Get the time limit used by [`VictoryMode::TimeLimit`], as stored in the scenario file.
+    pub fn victory_time(&self) -> i32 {
+        self.format.tribe_scen.victory_time
+    }
+
+    /// Set the time limit used by [`VictoryMode::TimeLimit`], as stored in the scenario file.
+    pub fn set_victory_time(&mut self, time: i32) {
+        self.format.tribe_scen.victory_time = time;
+    }
+
+    /// Get the custom victory conditions, used by [`VictoryMode::Custom`].
+    pub fn custom_victory(&self) -> &VictoryInfo {
+        &self.format.tribe_scen.victory
+    }
+
+    /// Get the custom victory conditions mutably.
+    pub fn custom_victory_mut(&mut self) -> &mut VictoryInfo {
+        &mut self.format.tribe_scen.victory
+    }
+
+    /// Check if all custom victory conditions must be met to win, instead of any one of them.
+    pub fn requires_all_victory_conditions(&self) -> bool {
+        self.format.tribe_scen.victory_all_flag
+    }
+
+    /// Set whether all custom victory conditions must be met to win, instead of any one of them.
+    pub fn set_requires_all_victory_conditions(&mut self, all: bool) {
+        self.format.tribe_scen.victory_all_flag = all;
+    }
+
+    /// Get the index of a player in the per-player settings arrays. Players are numbered from 1
+    /// to 16.
+    fn player_index(player: usize) -> Result<usize> {
+        match player {
+            1..=16 => Ok(player - 1),
+            _ => Err(Error::InvalidPlayerError(player)),
+        }
+    }
+
+    /// Get the starting diplomatic stance of a player toward another player. Players are
+    /// numbered from 1 to 16.
+    pub fn diplomacy(&self, player: usize, other: usize) -> Option<DiplomaticStance> {
+        let player = Self::player_index(player).ok()?;
+        let other = Self::player_index(other).ok()?;
+        Some(self.format.tribe_scen.diplomacy[player][other])
+    }
+
+    /// Set the starting diplomatic stance of a player toward another player. Players are
+    /// numbered from 1 to 16.
+    ///
+    /// Diplomacy is not symmetric: use `set_diplomacy(2, 1, stance)` as well to change player 2's
+    /// stance toward player 1.
+    pub fn set_diplomacy(
+        &mut self,
+        player: usize,
+        other: usize,
+        stance: DiplomaticStance,
+    ) -> Result<()> {
+        let player_index = Self::player_index(player)?;
+        let other_index = Self::player_index(other)?;
+        self.format.tribe_scen.diplomacy[player_index][other_index] = stance;
+        // The player data has another copy, which also includes GAIA at index 0.
+        if let Some(relation) = self
+            .format
+            .scenario_players
+            .get_mut(player_index)
+            .and_then(|data| data.relations.get_mut(other))
+        {
+            *relation = i32::from(stance) as i8;
+        }
+        Ok(())
+    }
+
+    /// Check if a player has Allied Victory enabled. Players are numbered from 1 to 16.
+    pub fn allied_victory(&self, player: usize) -> Option<bool> {
+        let player = Self::player_index(player).ok()?;
+        Some(self.format.tribe_scen.allied_victory[player] != 0)
+    }
+
+    /// Enable or disable Allied Victory for a player. Players are numbered from 1 to 16.
+    pub fn set_allied_victory(&mut self, player: usize, enabled: bool) -> Result<()> {
+        let player = Self::player_index(player)?;
+        self.format.tribe_scen.allied_victory[player] = i32::from(enabled);
+        if let Some(data) = self.format.scenario_players.get_mut(player) {
+            data.allied_victory = enabled;
+        }
+        Ok(())
+    }
+
+    /// Get the picture shown on the instructions screen, if there is one.
+    pub fn instructions_bitmap(&self) -> Option<&Bitmap> {
+        self.format.tribe_scen.base.mission_picture.as_ref()
+    }
+
+    /// Set or remove the picture shown on the instructions screen.
+    pub fn set_instructions_bitmap(&mut self, bitmap: Option<Bitmap>) {
+        self.format.tribe_scen.base.mission_picture = bitmap;
+    }
+
+    /// Iterate over the files embedded for each player, such as AI scripts.
+    pub fn player_files(&self) -> impl Iterator<Item = PlayerFile<'_>> {
+        (1..=16).flat_map(move |player| {
+            PlayerFileKind::ALL
+                .iter()
+                .filter_map(move |kind| self.player_file(player, *kind))
+        })
+    }
+
+    /// Get a file embedded for a player. Players are numbered from 1 to 16.
+    pub fn player_file(&self, player: usize, kind: PlayerFileKind) -> Option<PlayerFile<'_>> {
+        let index = Self::player_index(player).ok()?;
+        let base = &self.format.tribe_scen.base;
+        let content = base.player_files[index].get(kind)?;
+        let name = base.player_file_names(kind)[index].as_deref();
+        Some(PlayerFile {
+            player,
+            kind,
+            name,
+            content,
+        })
+    }
+
+    /// Embed a file for a player, replacing the existing file of the same kind. Players are
+    /// numbered from 1 to 16.
+    pub fn set_player_file(
+        &mut self,
+        player: usize,
+        kind: PlayerFileKind,
+        name: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Result<()> {
+        let index = Self::player_index(player)?;
+        let base = &mut self.format.tribe_scen.base;
+        *base.player_files[index].get_mut(kind) = Some(content.into());
+        base.player_file_names_mut(kind)[index] = Some(name.into());
+        Ok(())
+    }
+
+    /// Remove a file embedded for a player. Players are numbered from 1 to 16.
+    pub fn remove_player_file(&mut self, player: usize, kind: PlayerFileKind) -> Result<()> {
+        let index = Self::player_index(player)?;
+        let base = &mut self.format.tribe_scen.base;
+        *base.player_files[index].get_mut(kind) = None;
+        base.player_file_names_mut(kind)[index] = None;
+        Ok(())
+    }
+
+    /// Get the AI files embedded in the scenario (AoK and up).
+    pub fn ai_files(&self) -> &[AIFile] {
+        match &self.format.ai_info {
+            Some(ai_info) => &ai_info.files,
+            None => &[],
+        }
+    }
+
+    /// Embed an AI file in the scenario, replacing the file with the same name if there is one.
+    ///
+    /// AI files are only saved in AoK and up.
+    pub fn set_ai_file(&mut self, file: AIFile) {
+        let files = &mut self
+            .format
+            .ai_info
+            .get_or_insert_with(Default::default)
+            .files;
+        match files
+            .iter_mut()
+            .find(|existing| existing.filename() == file.filename())
+        {
+            Some(existing) => *existing = file,
+            None => files.push(file),
+        }
+    }
+
+    /// Remove an embedded AI file by name, and return it.
+    pub fn remove_ai_file(&mut self, filename: &str) -> Option<AIFile> {
+        let files = &mut self.format.ai_info.as_mut()?.files;
+        let index = files.iter().position(|file| file.filename() == filename)?;
+        Some(files.remove(index))
+    }
+
+    /// Get the objects placed for a player. Player 0 is GAIA.
+    pub fn player_objects(&self, player: usize) -> Option<&[ScenarioObject]> {
+        self.format.player_objects.get(player).map(Vec::as_slice)
+    }
+
+    /// Find a placed object by its ID.
+    pub fn object(&self, id: i32) -> Option<&ScenarioObject> {
+        self.objects().find(|object| object.id == id)
+    }
+
+    /// Find a placed object by its ID, mutably.
+    pub fn object_mut(&mut self, id: i32) -> Option<&mut ScenarioObject> {
+        self.objects_mut().find(|object| object.id == id)
+    }
+
+    /// Get the ID that the next added object will have.
+    ///
+    /// This is never lower than the highest ID of any placed object, even if the stored value in
+    /// the scenario file is.
+    pub fn next_object_id(&self) -> i32 {
+        let after_highest = self.objects().map(|object| object.id + 1).max();
+        self.format.next_object_id.max(after_highest.unwrap_or(0))
+    }
+
+    /// Add an object to a player's list without checking that the player exists.
+    fn push_object(
+        &mut self,
+        player: usize,
+        object_type: UnitTypeID,
+        position: (f32, f32, f32),
+    ) -> i32 {
+        let id = self.next_object_id();
+        self.format.next_object_id = id + 1;
+        self.format.player_objects[player].push(ScenarioObject {
+            position,
+            id,
+            object_type,
+            state: 2,
+            angle: 0.0,
+            frame: 0,
+            garrisoned_in: None,
+        });
+        id
+    }
+
+    /// Place an object for a player. Player 0 is GAIA. Returns the ID of the new object.
+    ///
+    /// The position is in tiles; the center of the tile at (x, y) is (x + 0.5, y + 0.5). The third
+    /// coordinate is the elevation.
+    pub fn add_object(
+        &mut self,
+        player: usize,
+        object_type: UnitTypeID,
+        position: (f32, f32, f32),
+    ) -> Result<i32> {
+        if player >= self.format.player_objects.len() {
+            return Err(Error::InvalidPlayerError(player));
+        }
+        Ok(self.push_object(player, object_type, position))
+    }
+
+    /// Move an object. Objects garrisoned in it, and objects garrisoned in those, are moved along
+    /// with it.
+    pub fn move_object(&mut self, id: i32, position: (f32, f32, f32)) -> Result<()> {
+        if self.object(id).is_none() {
+            return Err(Error::UnknownObjectError(id));
+        }
+        let mut moved = HashSet::new();
+        moved.insert(id);
+        // Objects read from a file may be garrisoned in each other, so stop when no new objects
+        // are found instead of following each chain.
+        loop {
+            let num_moved = moved.len();
+            for object in self.objects() {
+                if object
+                    .garrisoned_in
+                    .is_some_and(|container| moved.contains(&container))
+                {
+                    moved.insert(object.id);
+                }
+            }
+            if moved.len() == num_moved {
+                break;
+            }
+        }
+        for object in self.objects_mut() {
+            if moved.contains(&object.id) {
+                object.position = position;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the angle, in radians, that an object is facing.
+    pub fn rotate_object(&mut self, id: i32, angle: f32) -> Result<()> {
+        let object = self.object_mut(id).ok_or(Error::UnknownObjectError(id))?;
+        object.angle = angle;
+        Ok(())
+    }
+
+    /// Remove an object from the scenario, and return it.
+    ///
+    /// Objects that were garrisoned in it are ungarrisoned, and stay where the removed object was.
+    pub fn remove_object(&mut self, id: i32) -> Result<ScenarioObject> {
+        let (player, index) = self
+            .format
+            .player_objects
+            .iter()
+            .enumerate()
+            .find_map(|(player, list)| {
+                let index = list.iter().position(|object| object.id == id)?;
+                Some((player, index))
+            })
+            .ok_or(Error::UnknownObjectError(id))?;
+        let removed = self.format.player_objects[player].remove(index);
+        for object in self.objects_mut() {
+            if object.garrisoned_in == Some(id) {
+                object.garrisoned_in = None;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Garrison an object into another object, such as a unit into a building or a transport ship.
+    ///
+    /// The garrisoned object is moved to the position of the object it is garrisoned in. This does
+    /// not check if the object can actually be garrisoned, or if there is room for it.
+    pub fn garrison(&mut self, id: i32, container: i32) -> Result<()> {
+        if self.object(id).is_none() {
+            return Err(Error::UnknownObjectError(id));
+        }
+        let mut position = None;
+        let mut visited = HashSet::new();
+        let mut next = Some(container);
+        while let Some(current) = next {
+            // Objects read from a file may already be garrisoned in each other.
+            if current == id || !visited.insert(current) {
+                return Err(Error::InvalidGarrisonError(id, container));
+            }
+            let object = self
+                .object(current)
+                .ok_or(Error::UnknownObjectError(current))?;
+            position.get_or_insert(object.position);
+            next = object.garrisoned_in;
+        }
+
+        let object = self.object_mut(id).unwrap();
+        object.garrisoned_in = Some(container);
+        object.position = position.unwrap();
+        Ok(())
+    }
+
+    /// Take an object out of the object it is garrisoned in. The object stays at the same
+    /// position.
+    pub fn ungarrison(&mut self, id: i32) -> Result<()> {
+        let object = self.object_mut(id).ok_or(Error::UnknownObjectError(id))?;
+        object.garrisoned_in = None;
+        Ok(())
+    }
+
+    /// Place a line of cliffs from one tile to another. Returns the IDs of the new cliff
+    /// objects.
+    ///
+    /// Cliffs are GAIA objects that are 3×3 tiles large, so a cliff piece is placed every 3 tiles
+    /// along the line. Every piece uses the first cliff graphic; change the `angle` and `frame`
+    /// of the new objects to pick other cliff graphics.
+    pub fn add_cliff(&mut self, from: Location, to: Location) -> Vec<i32> {
+        /// The unit type ID of the cliff pieces that the scenario editor places.
+        const CLIFF: u16 = 264;
+
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = dx.abs().max(dy.abs());
+        let num_pieces = length / 3 + 1;
+
+        if self.format.player_objects.is_empty() {
+            self.format.player_objects.push(vec![]);
+        }
+
+        let mut ids = vec![];
+        for piece in 0..num_pieces {
+            let (x, y) = if length == 0 {
+                (from.x, from.y)
+            } else {
+                (
+                    from.x + dx * piece * 3 / length,
+                    from.y + dy * piece * 3 / length,
+                )
+            };
+            let elevation = match (u32::try_from(x), u32::try_from(y)) {
+                (Ok(x), Ok(y)) => self.format.map.tile(x, y).map_or(0, |tile| tile.elevation),
+                _ => 0,
+            };
+
+            let position = (x as f32 + 0.5, y as f32 + 0.5, f32::from(elevation));
+            ids.push(self.push_object(0, UnitTypeID::from(CLIFF), position));
+        }
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn convert_error() {
+        let err = Scenario::read_from(&b"9.99\0\0\0\0"[..]).unwrap_err();
+        let err = genie_support::Error::from(err);
+        assert_eq!(err.context().file_kind(), Some("scenario"));
+        assert_eq!(err.context().version(), Some("9.99"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn read_async() -> anyhow::Result<()> {
+        let file = tokio::fs::File::open("test/scenarios/Age of Heroes b1-3-5.scx").await?;
+        let scen = Scenario::from_async_reader(file).await?;
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        assert_eq!(
+            scen.objects().count(),
+            Scenario::read_from(&mut f)?.objects().count()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_embedded_uncompressed() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let mut bytes = vec![];
+        scen.write_to_with(&mut bytes, scen.version(), SCXCompression::Uncompressed)?;
+        bytes.extend_from_slice(b"trailer");
+
+        let mut input = Cursor::new(bytes);
+        let read = Scenario::read_from_with(&mut input, SCXCompression::Uncompressed)?;
+        assert_eq!(read.objects().count(), scen.objects().count());
+        let mut rest = vec![];
+        input.read_to_end(&mut rest)?;
+        assert_eq!(rest, b"trailer");
+        Ok(())
+    }
+
+    #[test]
+    fn place_objects() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let highest_id = scen.objects().map(|object| object.id).max().unwrap();
+        assert!(scen.next_object_id() > highest_id);
+
+        let castle = scen.add_object(1, UnitTypeID::from(82), (20.5, 20.5, 0.0))?;
+        let archer = scen.add_object(1, UnitTypeID::from(4), (30.5, 30.5, 0.0))?;
+        assert_eq!(archer, castle + 1);
+        assert!(matches!(
+            scen.add_object(100, UnitTypeID::from(4), (0.0, 0.0, 0.0)),
+            Err(Error::InvalidPlayerError(100))
+        ));
+
+        scen.rotate_object(archer, std::f32::consts::PI)?;
+        scen.garrison(archer, castle)?;
+        assert_eq!(scen.object(archer).unwrap().position, (20.5, 20.5, 0.0));
+        assert!(matches!(
+            scen.garrison(castle, archer),
+            Err(Error::InvalidGarrisonError(_, _))
+        ));
+
+        scen.move_object(castle, (40.5, 40.5, 1.0))?;
+        assert_eq!(scen.object(archer).unwrap().position, (40.5, 40.5, 1.0));
+
+        let ram = scen.add_object(1, UnitTypeID::from(35), (40.5, 40.5, 1.0))?;
+        let villager = scen.add_object(1, UnitTypeID::from(83), (40.5, 40.5, 1.0))?;
+        scen.garrison(ram, castle)?;
+        scen.garrison(villager, ram)?;
+        scen.move_object(castle, (45.5, 45.5, 0.0))?;
+        for id in &[archer, ram, villager] {
+            assert_eq!(scen.object(*id).unwrap().position, (45.5, 45.5, 0.0));
+        }
+        scen.move_object(ram, (50.5, 50.5, 0.0))?;
+        assert_eq!(scen.object(villager).unwrap().position, (50.5, 50.5, 0.0));
+        assert_eq!(scen.object(castle).unwrap().position, (45.5, 45.5, 0.0));
+        scen.remove_object(villager)?;
+        scen.remove_object(ram)?;
+
+        let monk = scen.add_object(1, UnitTypeID::from(125), (10.5, 10.5, 0.0))?;
+        scen.object_mut(castle).unwrap().garrisoned_in = Some(archer);
+        assert!(matches!(
+            scen.garrison(monk, castle),
+            Err(Error::InvalidGarrisonError(_, _))
+        ));
+        scen.move_object(castle, (40.5, 40.5, 1.0))?;
+        assert_eq!(scen.object(archer).unwrap().position, (40.5, 40.5, 1.0));
+        scen.ungarrison(castle)?;
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let mut scen = Scenario::read_from(Cursor::new(out))?;
+        let placed = scen.player_objects(1).unwrap();
+        let archer_object = placed.iter().find(|object| object.id == archer).unwrap();
+        assert_eq!(archer_object.garrisoned_in, Some(castle));
+        assert_eq!(archer_object.angle, std::f32::consts::PI);
+
+        let removed = scen.remove_object(castle)?;
+        assert_eq!(removed.object_type, UnitTypeID::from(82));
+        assert!(scen.object(castle).is_none());
+        assert_eq!(scen.object(archer).unwrap().garrisoned_in, None);
+        assert!(matches!(
+            scen.remove_object(castle),
+            Err(Error::UnknownObjectError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn victory_and_diplomacy() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.victory_mode(), VictoryMode::Standard);
+        assert!(scen.custom_victory().conquest());
+        assert_eq!(scen.diplomacy(1, 2), Some(DiplomaticStance::Ally));
+        assert_eq!(scen.diplomacy(1, 4), Some(DiplomaticStance::Enemy));
+        assert_eq!(scen.diplomacy(0, 1), None);
+        assert_eq!(scen.allied_victory(1), Some(false));
+
+        scen.set_victory_mode(VictoryMode::Custom);
+        scen.custom_victory_mut().set_relics(5);
+        scen.set_requires_all_victory_conditions(true);
+        scen.set_diplomacy(1, 4, DiplomaticStance::Neutral)?;
+        scen.set_allied_victory(2, true)?;
+        assert!(matches!(
+            scen.set_allied_victory(17, true),
+            Err(Error::InvalidPlayerError(17))
+        ));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        assert_eq!(scen.victory_mode(), VictoryMode::Custom);
+        assert_eq!(scen.custom_victory().relics(), 5);
+        assert!(scen.requires_all_victory_conditions());
+        assert_eq!(scen.diplomacy(1, 4), Some(DiplomaticStance::Neutral));
+        assert_eq!(scen.scenario_players()[0].relations[4], 1);
+        assert_eq!(scen.allied_victory(2), Some(true));
+        assert!(scen.scenario_players()[1].allied_victory);
+        Ok(())
+    }
+
+    #[test]
+    fn replace_player_files() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/El advenimiento de los hunos_.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let ai = scen.player_file(2, PlayerFileKind::AIRules).unwrap();
+        assert_eq!(ai.name, Some("Huns2"));
+        assert!(ai.content.len() > 1000);
+        assert!(scen.player_file(1, PlayerFileKind::AIRules).is_none());
+        let num_files = scen.player_files().count();
+
+        let script = "(defrule (true) => (chat-to-all \"hi\") (disable-self))";
+        scen.set_player_file(1, PlayerFileKind::AIRules, "Greeter", script)?;
+        scen.remove_player_file(2, PlayerFileKind::BuildList)?;
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        let ai = scen.player_file(1, PlayerFileKind::AIRules).unwrap();
+        assert_eq!(ai.name, Some("Greeter"));
+        assert_eq!(ai.content, script);
+        assert!(scen.player_file(2, PlayerFileKind::BuildList).is_none());
+        assert_eq!(scen.player_files().count(), num_files);
+        Ok(())
+    }
+
+    #[test]
+    fn replace_ai_files() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/layertest.aoe2scenario")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let names: Vec<_> = scen.ai_files().iter().map(AIFile::filename).collect();
+        assert_eq!(
+            names,
+            vec!["const.per2", "testHarnessResourceHandling.per2"]
+        );
+
+        scen.set_ai_file(AIFile::new("const.per2", "(defconst x 1)"));
+        scen.set_ai_file(AIFile::new("new.per", "(defconst y 2)"));
+        assert!(scen
+            .remove_ai_file("testHarnessResourceHandling.per2")
+            .is_some());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        assert_eq!(
+            scen.ai_files(),
+            &[
+                AIFile::new("const.per2", "(defconst x 1)"),
+                AIFile::new("new.per", "(defconst y 2)"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn add_cliff() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let num_objects = scen.objects().count();
+        let ids = scen.add_cliff(Location { x: 10, y: 10 }, Location { x: 10, y: 19 });
+        assert_eq!(ids.len(), 4);
+        assert_eq!(scen.objects().count(), num_objects + 4);
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        let positions: Vec<_> = scen
+            .objects()
+            .filter(|object| ids.contains(&object.id))
+            .map(|object| (object.position.0, object.position.1))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![(10.5, 10.5), (10.5, 13.5), (10.5, 16.5), (10.5, 19.5)]
+        );
+        Ok(())
+    }
 }
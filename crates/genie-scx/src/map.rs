@@ -1,10 +1,12 @@
-use crate::Result;
+use crate::{Area, Location, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::read_opt_u16;
+use genie_support::{read_opt_u16, write_opt_u16};
+use std::convert::TryFrom;
 use std::io::{self, Read, Write};
 
 /// A map tile.
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tile {
     /// The terrain.
     pub terrain: u8,
@@ -42,8 +44,8 @@ impl Tile {
         output.write_i8(self.zone)?;
 
         if version >= 1 {
-            output.write_u16::<LE>(self.mask_type.unwrap_or(0xFFFF))?;
-            output.write_u16::<LE>(self.layered_terrain.unwrap_or(0xFFFF))?;
+            write_opt_u16(&mut output, self.mask_type)?;
+            write_opt_u16(&mut output, self.layered_terrain)?;
         }
 
         Ok(())
@@ -52,6 +54,7 @@ impl Tile {
 
 /// Describes the terrain in a map.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Map {
     /// Version of the map data format.
     version: u32,
@@ -124,13 +127,10 @@ impl Map {
         log::debug!("Map size: {}×{}", map.width, map.height);
 
         if map.width > 500 || map.height > 500 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "Unexpected map size {}×{}, this is likely a genie-scx bug.",
-                    map.width, map.height
-                ),
-            )
+            return Err(io::Error::other(format!(
+                "Unexpected map size {}×{}, this is likely a genie-scx bug.",
+                map.width, map.height
+            ))
             .into());
         }
 
@@ -185,6 +185,9 @@ impl Map {
     ///
     /// If the coordinates are out of bounds, returns None.
     pub fn tile(&self, x: u32, y: u32) -> Option<&Tile> {
+        if x >= self.width {
+            return None;
+        }
         self.tiles.get((y * self.width + x) as usize)
     }
 
@@ -192,6 +195,9 @@ impl Map {
     ///
     /// If the coordinates are out of bounds, returns None.
     pub fn tile_mut(&mut self, x: u32, y: u32) -> Option<&mut Tile> {
+        if x >= self.width {
+            return None;
+        }
         self.tiles.get_mut((y * self.width + x) as usize)
     }
 
@@ -234,4 +240,197 @@ impl Map {
     pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Tile]> {
         self.tiles.chunks_exact_mut(self.width as usize)
     }
+
+    /// Get the index of the tile at the given coordinates, if it is on the map.
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let x = u32::try_from(x).ok().filter(|x| *x < self.width)?;
+        let y = u32::try_from(y).ok().filter(|y| *y < self.height)?;
+        Some((y * self.width + x) as usize)
+    }
+
+    /// Get the indices of all the tiles in an area. The parts of the area that are outside of
+    /// the map are ignored.
+    fn area_indices(&self, area: Area) -> Vec<usize> {
+        let (x1, x2) = (area.x1.min(area.x2), area.x1.max(area.x2));
+        let (y1, y2) = (area.y1.min(area.y2), area.y1.max(area.y2));
+        (y1..=y2)
+            .flat_map(|y| (x1..=x2).map(move |x| (x, y)))
+            .filter_map(|(x, y)| self.index(x, y))
+            .collect()
+    }
+
+    /// Set the terrain type of all the tiles in an area. Both corners of the area are included.
+    pub fn set_terrain(&mut self, area: Area, terrain_type: u8) {
+        for index in self.area_indices(area) {
+            self.tiles[index].terrain = terrain_type;
+        }
+    }
+
+    /// Paint a terrain type with a round brush.
+    ///
+    /// All tiles whose center is at most `radius` tiles away from the center of the `center` tile
+    /// are changed. A radius of 0 only changes the `center` tile.
+    pub fn paint_terrain(&mut self, center: Location, radius: u32, terrain_type: u8) {
+        let r = radius as i32;
+        let area = Area {
+            x1: center.x - r,
+            y1: center.y - r,
+            x2: center.x + r,
+            y2: center.y + r,
+        };
+        let width = self.width as i32;
+        for index in self.area_indices(area) {
+            let dx = index as i32 % width - center.x;
+            let dy = index as i32 / width - center.y;
+            if dx * dx + dy * dy <= r * r {
+                self.tiles[index].terrain = terrain_type;
+            }
+        }
+    }
+
+    /// Replace the terrain type of the tile at `start`, and of all the tiles of the same terrain
+    /// type that are connected to it, like the bucket tool in a paint program.
+    ///
+    /// Tiles are connected if they share an edge. Does nothing if `start` is outside of the map.
+    pub fn flood_fill(&mut self, start: Location, terrain_type: u8) {
+        let start_index = match self.index(start.x, start.y) {
+            Some(index) => index,
+            None => return,
+        };
+        let replace = self.tiles[start_index].terrain;
+        if replace == terrain_type {
+            return;
+        }
+
+        let mut stack = vec![(start.x, start.y)];
+        while let Some((x, y)) = stack.pop() {
+            let index = match self.index(x, y) {
+                Some(index) if self.tiles[index].terrain == replace => index,
+                _ => continue,
+            };
+            self.tiles[index].terrain = terrain_type;
+            stack.extend([(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+        }
+    }
+
+    /// Set the elevation of all the tiles in an area. Both corners of the area are included.
+    ///
+    /// This can create steep height differences with the surrounding tiles; use
+    /// [`Map::smooth_elevation`] to turn them into slopes.
+    pub fn set_elevation(&mut self, area: Area, elevation: i8) {
+        for index in self.area_indices(area) {
+            self.tiles[index].elevation = elevation;
+        }
+    }
+
+    /// Raise tiles so that neighbouring tiles, including diagonal neighbours, differ in elevation
+    /// by at most one level.
+    ///
+    /// Hills keep their height and get slopes around them, like hills drawn in the scenario
+    /// editor.
+    pub fn smooth_elevation(&mut self) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for y in 0..height {
+                for x in 0..width {
+                    let index = (y * width + x) as usize;
+                    let highest_neighbour = (-1..=1)
+                        .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+                        .filter_map(|(nx, ny)| self.index(nx, ny))
+                        .map(|neighbour| self.tiles[neighbour].elevation)
+                        .max()
+                        .unwrap_or(0);
+                    let minimum = highest_neighbour.saturating_sub(1);
+                    if self.tiles[index].elevation < minimum {
+                        self.tiles[index].elevation = minimum;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+genie_support::impl_versioned! {
+    Tile: u32 => crate::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terrains(map: &Map) -> Vec<Vec<u8>> {
+        map.rows()
+            .map(|row| row.iter().map(|tile| tile.terrain).collect())
+            .collect()
+    }
+
+    #[test]
+    fn set_and_paint_terrain() {
+        let mut map = Map::new(5, 5);
+        map.set_terrain(
+            Area {
+                x1: 3,
+                y1: 1,
+                x2: 8,
+                y2: 0,
+            },
+            1,
+        );
+        map.paint_terrain(Location { x: 1, y: 3 }, 1, 2);
+        assert_eq!(
+            terrains(&map),
+            vec![
+                vec![0, 0, 0, 1, 1],
+                vec![0, 0, 0, 1, 1],
+                vec![0, 2, 0, 0, 0],
+                vec![2, 2, 2, 0, 0],
+                vec![0, 2, 0, 0, 0],
+            ]
+        );
+        assert!(map.tile(5, 0).is_none());
+    }
+
+    #[test]
+    fn flood_fill() {
+        let mut map = Map::new(4, 4);
+        map.set_terrain(
+            Area {
+                x1: 2,
+                y1: 0,
+                x2: 2,
+                y2: 3,
+            },
+            1,
+        );
+        map.flood_fill(Location { x: 0, y: 0 }, 2);
+        assert_eq!(
+            terrains(&map),
+            vec![
+                vec![2, 2, 1, 0],
+                vec![2, 2, 1, 0],
+                vec![2, 2, 1, 0],
+                vec![2, 2, 1, 0],
+            ]
+        );
+    }
+
+    #[test]
+    fn smooth_elevation() {
+        let mut map = Map::new(7, 1);
+        map.set_elevation(
+            Area {
+                x1: 1,
+                y1: 0,
+                x2: 1,
+                y2: 0,
+            },
+            3,
+        );
+        map.smooth_elevation();
+        let elevations: Vec<i8> = map.tiles().map(|tile| tile.elevation).collect();
+        assert_eq!(elevations, vec![2, 3, 2, 1, 0, 0, 0]);
+    }
 }
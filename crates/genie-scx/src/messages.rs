@@ -0,0 +1,148 @@
+//! Scenario messages and cinematics.
+
+use crate::Scenario;
+use genie_support::StringKey;
+
+/// The messages shown to players on the scenario instructions screen and at the end of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageKind {
+    /// The scenario instructions, also known as the description.
+    Instructions,
+    /// The hints tab.
+    Hints,
+    /// The message shown when the player wins.
+    Victory,
+    /// The message shown when the player loses.
+    Loss,
+    /// The history tab.
+    History,
+    /// The scouts tab (AoC and up).
+    Scouts,
+}
+
+impl MessageKind {
+    /// All kinds of messages.
+    pub const ALL: [MessageKind; 6] = [
+        MessageKind::Instructions,
+        MessageKind::Hints,
+        MessageKind::Victory,
+        MessageKind::Loss,
+        MessageKind::History,
+        MessageKind::Scouts,
+    ];
+}
+
+/// The cinematics that can be played for a scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CinematicKind {
+    /// Played before the scenario starts.
+    Pregame,
+    /// Played when the player wins.
+    Victory,
+    /// Played when the player loses.
+    Loss,
+}
+
+impl CinematicKind {
+    /// All kinds of cinematics.
+    pub const ALL: [CinematicKind; 3] = [
+        CinematicKind::Pregame,
+        CinematicKind::Victory,
+        CinematicKind::Loss,
+    ];
+}
+
+impl Scenario {
+    /// Get the text of a message.
+    pub fn message(&self, kind: MessageKind) -> Option<&str> {
+        self.format.tribe_scen.base.message(kind)
+    }
+
+    /// Set or remove the text of a message.
+    ///
+    /// Only the instructions are stored in AoE1 scenarios before format 1.11, and the scouts
+    /// message is only stored in AoC and up; other messages are dropped when writing older
+    /// versions.
+    pub fn set_message(&mut self, kind: MessageKind, text: Option<String>) {
+        *self.format.tribe_scen.base.message_mut(kind) = text;
+    }
+
+    /// Get the string table ID of a message. When it is set, the game shows the string from its
+    /// language file instead of the text stored in the scenario.
+    pub fn message_string_id(&self, kind: MessageKind) -> Option<&StringKey> {
+        self.format.tribe_scen.base.message_string_table(kind)
+    }
+
+    /// Set or remove the string table ID of a message.
+    ///
+    /// String table IDs are stored in AoK and up. Only numeric IDs can be written.
+    pub fn set_message_string_id(&mut self, kind: MessageKind, id: Option<StringKey>) {
+        *self.format.tribe_scen.base.message_string_table_mut(kind) = id;
+    }
+
+    /// Get the file name of a cinematic.
+    pub fn cinematic(&self, kind: CinematicKind) -> Option<&str> {
+        self.format.tribe_scen.base.cinematic(kind)
+    }
+
+    /// Set or remove the file name of a cinematic.
+    pub fn set_cinematic(&mut self, kind: CinematicKind, filename: Option<String>) {
+        *self.format.tribe_scen.base.cinematic_mut(kind) = filename;
+    }
+
+    /// Get the file name of the picture shown on the instructions screen.
+    pub fn instructions_bitmap_name(&self) -> Option<&str> {
+        self.format.tribe_scen.base.mission_bmp.as_deref()
+    }
+
+    /// Set or remove the file name of the picture shown on the instructions screen.
+    pub fn set_instructions_bitmap_name(&mut self, filename: Option<String>) {
+        self.format.tribe_scen.base.mission_bmp = filename;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn set_messages_and_cinematics() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        assert_eq!(scen.message(MessageKind::Instructions), scen.description());
+
+        for kind in MessageKind::ALL.iter().copied() {
+            scen.set_message(kind, Some(format!("{:?} message", kind)));
+        }
+        scen.set_message_string_id(MessageKind::Hints, Some(StringKey::from(9123_u32)));
+        scen.set_message_string_id(MessageKind::Loss, None);
+        for kind in CinematicKind::ALL.iter().copied() {
+            scen.set_cinematic(kind, Some(format!("{:?}.avi", kind)));
+        }
+        scen.set_instructions_bitmap_name(Some("map.bmp".to_string()));
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        for kind in MessageKind::ALL.iter().copied() {
+            assert_eq!(
+                scen.message(kind),
+                Some(format!("{:?} message", kind).as_str())
+            );
+        }
+        assert_eq!(
+            scen.message_string_id(MessageKind::Hints),
+            Some(&StringKey::from(9123_u32))
+        );
+        assert_eq!(scen.message_string_id(MessageKind::Loss), None);
+        assert_eq!(scen.cinematic(CinematicKind::Pregame), Some("Pregame.avi"));
+        assert_eq!(scen.cinematic(CinematicKind::Victory), Some("Victory.avi"));
+        assert_eq!(scen.cinematic(CinematicKind::Loss), Some("Loss.avi"));
+        assert_eq!(scen.instructions_bitmap_name(), Some("map.bmp"));
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+//! Minimap rendering, for scenario previews.
+
+use crate::Scenario;
+use rgb::{RGB8, RGBA8};
+
+/// The palette indices to use when rendering a minimap.
+///
+/// The game's minimap colours are stored in the data file: use the `minimap_color_medium` value
+/// of each terrain, and the minimap colour of each player colour, together with the game's
+/// palette.
+#[derive(Debug, Clone)]
+pub struct MinimapColors<'a> {
+    palette: &'a [RGB8],
+    terrains: Vec<Option<u8>>,
+    players: Vec<Option<u8>>,
+}
+
+impl<'a> MinimapColors<'a> {
+    /// Create a colour set using the given palette, such as the colours of a jascpal `Palette`.
+    /// No terrains or players have a colour yet.
+    pub fn new(palette: &'a [RGB8]) -> Self {
+        Self {
+            palette,
+            terrains: vec![],
+            players: vec![],
+        }
+    }
+
+    /// Draw tiles with the given terrain using a palette index.
+    pub fn terrain(mut self, terrain: u8, index: u8) -> Self {
+        let terrain = usize::from(terrain);
+        if self.terrains.len() <= terrain {
+            self.terrains.resize(terrain + 1, None);
+        }
+        self.terrains[terrain] = Some(index);
+        self
+    }
+
+    /// Draw objects owned by a player using a palette index. Player 0 is GAIA.
+    pub fn player(mut self, player: usize, index: u8) -> Self {
+        if self.players.len() <= player {
+            self.players.resize(player + 1, None);
+        }
+        self.players[player] = Some(index);
+        self
+    }
+
+    fn color(&self, index: Option<u8>) -> Option<RGBA8> {
+        let color = self.palette.get(usize::from(index?))?;
+        Some(RGBA8::new(color.r, color.g, color.b, 255))
+    }
+
+    fn terrain_color(&self, terrain: u8) -> Option<RGBA8> {
+        self.color(self.terrains.get(usize::from(terrain)).copied().flatten())
+    }
+
+    fn player_color(&self, player: usize) -> Option<RGBA8> {
+        self.color(self.players.get(player).copied().flatten())
+    }
+}
+
+/// A rendered minimap, with one pixel for each tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Minimap {
+    width: u32,
+    height: u32,
+    pixels: Vec<RGBA8>,
+}
+
+impl Minimap {
+    /// Get the width of the minimap in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the minimap in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the colour of the pixel at the given coordinates.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<RGBA8> {
+        if x >= self.width {
+            return None;
+        }
+        self.pixels.get((y * self.width + x) as usize).copied()
+    }
+
+    /// Get the pixels, row by row from the top left.
+    pub fn pixels(&self) -> &[RGBA8] {
+        &self.pixels
+    }
+
+    /// Take the pixels, row by row from the top left.
+    pub fn into_pixels(self) -> Vec<RGBA8> {
+        self.pixels
+    }
+}
+
+impl Scenario {
+    /// Render the map and the objects placed for each player into a top-down minimap, with one
+    /// pixel for each tile.
+    ///
+    /// Tiles with a terrain that has no colour are transparent, and objects owned by a player
+    /// without a colour are not drawn.
+    pub fn render_minimap(&self, colors: &MinimapColors<'_>) -> Minimap {
+        let map = self.map();
+        let (width, height) = (map.width(), map.height());
+        let mut pixels: Vec<RGBA8> = map
+            .tiles()
+            .map(|tile| colors.terrain_color(tile.terrain).unwrap_or_default())
+            .collect();
+
+        for (player, objects) in self.format.player_objects.iter().enumerate() {
+            let color = match colors.player_color(player) {
+                Some(color) => color,
+                None => continue,
+            };
+            for object in objects {
+                let (x, y, _) = object.position;
+                if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+                    continue;
+                }
+                pixels[y as usize * width as usize + x as usize] = color;
+            }
+        }
+
+        Minimap {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn render() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let placed: Vec<i32> = scen
+            .player_objects(1)
+            .unwrap()
+            .iter()
+            .map(|o| o.id)
+            .collect();
+        for id in placed {
+            scen.remove_object(id)?;
+        }
+        scen.map_mut().tile_mut(0, 0).unwrap().terrain = 0;
+        scen.map_mut().tile_mut(1, 0).unwrap().terrain = 1;
+        scen.add_object(1, 4.into(), (1.5, 0.5, 0.0))?;
+
+        let palette = [
+            RGB8::new(0, 0, 0),
+            RGB8::new(0, 128, 0),
+            RGB8::new(0, 0, 255),
+        ];
+        let colors = MinimapColors::new(&palette).terrain(0, 1).player(1, 2);
+        let minimap = scen.render_minimap(&colors);
+        assert_eq!(minimap.width(), scen.map().width());
+        assert_eq!(
+            minimap.pixels().len(),
+            (minimap.width() * minimap.height()) as usize
+        );
+        assert_eq!(minimap.pixel(0, 0), Some(RGBA8::new(0, 128, 0, 255)));
+        assert_eq!(minimap.pixel(1, 0), Some(RGBA8::new(0, 0, 255, 255)));
+        Ok(())
+    }
+}
@@ -1,10 +1,11 @@
 use crate::victory::VictoryConditions;
 use crate::Result;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{write_opt_str, ReadStringsExt};
+use genie_support::{write_opt_i32, write_opt_str, ReadStringsExt};
 use std::io::{Read, Write};
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayerBaseProperties {
     pub(crate) posture: i32,
     pub(crate) player_type: i32,
@@ -13,6 +14,7 @@ pub struct PlayerBaseProperties {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayerFiles {
     /// Obsolete.
     pub(crate) build_list: Option<String>,
@@ -22,7 +24,62 @@ pub struct PlayerFiles {
     pub(crate) ai_rules: Option<String>,
 }
 
+impl PlayerFiles {
+    /// Get the content of a file.
+    pub(crate) fn get(&self, kind: PlayerFileKind) -> Option<&str> {
+        match kind {
+            PlayerFileKind::AIRules => self.ai_rules.as_deref(),
+            PlayerFileKind::BuildList => self.build_list.as_deref(),
+            PlayerFileKind::CityPlan => self.city_plan.as_deref(),
+        }
+    }
+
+    /// Get the content of a file mutably.
+    pub(crate) fn get_mut(&mut self, kind: PlayerFileKind) -> &mut Option<String> {
+        match kind {
+            PlayerFileKind::AIRules => &mut self.ai_rules,
+            PlayerFileKind::BuildList => &mut self.build_list,
+            PlayerFileKind::CityPlan => &mut self.city_plan,
+        }
+    }
+}
+
+/// The kinds of files that can be embedded in a scenario for each player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlayerFileKind {
+    /// The AI script (.per file). In AoE1, this is the .ai file.
+    AIRules,
+    /// The build list, used by AoE1 AIs.
+    BuildList,
+    /// The city plan, used by AoE1 AIs.
+    CityPlan,
+}
+
+impl PlayerFileKind {
+    /// All kinds of player files.
+    pub const ALL: [PlayerFileKind; 3] = [
+        PlayerFileKind::AIRules,
+        PlayerFileKind::BuildList,
+        PlayerFileKind::CityPlan,
+    ];
+}
+
+/// A file embedded in a scenario for a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerFile<'a> {
+    /// The player number, from 1 to 16.
+    pub player: usize,
+    /// The kind of file.
+    pub kind: PlayerFileKind,
+    /// The file name, if one was stored.
+    pub name: Option<&'a str>,
+    /// The file content.
+    pub content: &'a str,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayerStartResources {
     pub(crate) gold: i32,
     pub(crate) wood: i32,
@@ -89,6 +146,7 @@ impl PlayerStartResources {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScenarioPlayerData {
     pub name: Option<String>,
     pub view: (f32, f32),
@@ -101,6 +159,28 @@ pub struct ScenarioPlayerData {
 }
 
 impl ScenarioPlayerData {
+    /// Create player data for a player, numbered from 1 to 8, who is at war with everyone else.
+    pub(crate) fn new(player: usize, victory_version: f32) -> Self {
+        let relations = (0..9).map(|other| if other == player { 0 } else { 3 });
+        let unit_diplomacy = (0..9).map(|other| match other {
+            0 => 0,
+            other if other == player => 1,
+            _ => 4,
+        });
+        let mut victory = VictoryConditions::default();
+        victory.version = victory_version;
+        Self {
+            name: None,
+            view: (-1.0, -1.0),
+            location: (-1, -1),
+            allied_victory: false,
+            relations: relations.collect(),
+            unit_diplomacy: unit_diplomacy.collect(),
+            color: Some(player as i32 - 1),
+            victory,
+        }
+    }
+
     /// Get the default player name.
     pub fn name(&self) -> Option<&str> {
         self.name.as_ref().map(|string| string.as_ref())
@@ -204,7 +284,7 @@ impl ScenarioPlayerData {
         }
 
         if version >= 1.13 {
-            output.write_i32::<LE>(self.color.unwrap_or(-1))?;
+            write_opt_i32(&mut output, self.color)?;
         }
 
         self.victory.write_to(
@@ -222,6 +302,7 @@ impl ScenarioPlayerData {
 
 /// Initial player attributes.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WorldPlayerData {
     /// Initial food count.
     pub(crate) food: f32,
@@ -313,3 +394,8 @@ impl WorldPlayerData {
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    PlayerStartResources: f32 => crate::Error;
+    WorldPlayerData: f32 => crate::Error;
+}
@@ -0,0 +1,140 @@
+//! Access to the raw, decompressed data of scenario files.
+
+use crate::format::{deflate_compression, SCXFormat};
+use crate::{Result, SCXCompression, SCXHeader, SCXVersion, Scenario};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+use std::io::{Cursor, Read, Write};
+
+/// The parts of a scenario file, with the data decompressed but not parsed.
+///
+/// This is an escape hatch for debugging scenarios that can not be read with
+/// [`Scenario::read_from`]: the decompressed data can be inspected or patched, and then parsed or
+/// written back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawScenario {
+    format: SCXVersion,
+    header: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl RawScenario {
+    /// Read a scenario file without parsing its data.
+    pub fn read_from(input: impl Read) -> Result<Self> {
+        Self::read_from_with(input, SCXCompression::default())
+    }
+
+    /// Read a scenario file whose data is compressed in the given way, without parsing its data.
+    pub fn read_from_with(mut input: impl Read, compression: SCXCompression) -> Result<Self> {
+        let mut format = [0; 4];
+        input.read_exact(&mut format)?;
+        let header_size = input.read_u32::<LE>()?;
+        let mut header = vec![0; header_size as usize];
+        input.read_exact(&mut header)?;
+
+        let mut data = vec![];
+        match compression {
+            SCXCompression::Uncompressed => input.read_to_end(&mut data)?,
+            SCXCompression::Deflate { .. } => DeflateDecoder::new(input).read_to_end(&mut data)?,
+        };
+
+        Ok(Self {
+            format: SCXVersion(format),
+            header,
+            data,
+        })
+    }
+
+    /// Get the format version of the scenario file.
+    pub fn format_version(&self) -> SCXVersion {
+        self.format
+    }
+
+    /// Parse the header of the scenario file.
+    pub fn header(&self) -> Result<SCXHeader> {
+        let mut input = Cursor::new(vec![]);
+        input.write_u32::<LE>(self.header.len() as u32)?;
+        input.write_all(&self.header)?;
+        input.set_position(0);
+        SCXHeader::read_from(input, self.format)
+    }
+
+    /// Get the bytes of the header, without its size prefix.
+    pub fn header_bytes(&self) -> &[u8] {
+        &self.header
+    }
+
+    /// Get the decompressed scenario data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the decompressed scenario data mutably, to patch it by hand.
+    pub fn data_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.data
+    }
+
+    /// Parse the scenario data.
+    pub fn parse(&self) -> Result<Scenario> {
+        let mut file = vec![];
+        self.write_to(&mut file, SCXCompression::Uncompressed)?;
+        let format = SCXFormat::load_scenario_with(&file[..], SCXCompression::Uncompressed)?;
+        Ok(Scenario::from_format(format))
+    }
+
+    /// Write the scenario file, compressing its data in the given way.
+    pub fn write_to(&self, mut output: impl Write, compression: SCXCompression) -> Result<()> {
+        output.write_all(&self.format.0)?;
+        output.write_u32::<LE>(self.header.len() as u32)?;
+        output.write_all(&self.header)?;
+        match compression {
+            SCXCompression::Uncompressed => output.write_all(&self.data)?,
+            SCXCompression::Deflate { level } => {
+                let mut output = DeflateEncoder::new(output, deflate_compression(level)?);
+                output.write_all(&self.data)?;
+                output.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn uncompressed_roundtrip() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+
+        let mut compressed = vec![];
+        scen.write_to(&mut compressed)?;
+        let mut uncompressed = vec![];
+        scen.write_to_with(
+            &mut uncompressed,
+            scen.version(),
+            SCXCompression::Uncompressed,
+        )?;
+        assert!(uncompressed.len() > compressed.len());
+
+        let raw = RawScenario::read_from(&compressed[..])?;
+        assert_eq!(
+            raw,
+            RawScenario::read_from_with(&uncompressed[..], SCXCompression::Uncompressed)?
+        );
+        assert_eq!(raw.format_version(), scen.format_version());
+        assert_eq!(raw.header()?.version, scen.header_version());
+
+        let scen2 = Scenario::read_from_with(&uncompressed[..], SCXCompression::Uncompressed)?;
+        let mut compressed2 = vec![];
+        scen2.write_to(&mut compressed2)?;
+        assert_eq!(compressed, compressed2);
+
+        let mut compressed3 = vec![];
+        raw.parse()?.write_to(&mut compressed3)?;
+        assert_eq!(compressed, compressed3);
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Reading scenarios without their largest sections.
+//!
+//! Scenarios can embed large AI scripts and pictures. These sections can be skipped while reading,
+//! and loaded separately when they are needed.
+//!
+//! This works for every format version that [`Scenario::read_from`] can read, up to Definitive
+//! Edition format 1.37. Later Definitive Edition formats are rejected with
+//! [`Error::UnsupportedFormatVersionError`](crate::Error::UnsupportedFormatVersionError) before
+//! any section is read; skipping sections in them needs a reader for those formats first.
+
+use crate::format::SCXFormat;
+use crate::{Result, SCXCompression, Scenario};
+use std::io::{self, Read};
+
+/// A large section of a scenario file, that can be skipped while reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScenarioSection {
+    /// The picture shown on the instructions screen.
+    InstructionsBitmap,
+    /// The AI scripts, build lists and city plans embedded for each player.
+    PlayerFiles,
+    /// The AI files embedded in the scenario (AoK and up).
+    AIFiles,
+}
+
+impl ScenarioSection {
+    /// All sections that can be skipped.
+    pub const ALL: [ScenarioSection; 3] = [
+        ScenarioSection::InstructionsBitmap,
+        ScenarioSection::PlayerFiles,
+        ScenarioSection::AIFiles,
+    ];
+}
+
+/// Read and discard `len` bytes from an input stream.
+pub(crate) fn skip_bytes(input: impl Read, len: u64) -> Result<()> {
+    let skipped = io::copy(&mut input.take(len), &mut io::sink())?;
+    if skipped < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
+impl Scenario {
+    /// Read a scenario file whose data is compressed in the given way, reading past the given
+    /// sections without keeping them in memory.
+    ///
+    /// A scenario that was read without some of its sections can not be written until they are
+    /// loaded with [`Scenario::load_sections`].
+    pub fn read_from_skipping(
+        input: impl Read,
+        compression: SCXCompression,
+        skip: &[ScenarioSection],
+    ) -> Result<Self> {
+        let format = SCXFormat::load_scenario_skipping(input, compression, skip)?;
+        Ok(Self::from_format(format))
+    }
+
+    /// Get the sections that were skipped while reading this scenario, and that have not been
+    /// loaded since.
+    pub fn skipped_sections(&self) -> &[ScenarioSection] {
+        &self.format.skipped
+    }
+
+    /// Load sections that were skipped while reading this scenario, by reading the scenario file
+    /// again. The input must contain the same scenario file that this scenario was read from.
+    ///
+    /// Only the requested sections are kept in memory; other skipped sections stay skipped.
+    pub fn load_sections(
+        &mut self,
+        input: impl Read,
+        compression: SCXCompression,
+        sections: &[ScenarioSection],
+    ) -> Result<()> {
+        let skip: Vec<_> = ScenarioSection::ALL
+            .iter()
+            .copied()
+            .filter(|section| !sections.contains(section))
+            .collect();
+        let mut other = SCXFormat::load_scenario_skipping(input, compression, &skip)?;
+
+        for section in sections {
+            let (base, other_base) = (&mut self.format.tribe_scen.base, &mut other.tribe_scen.base);
+            match section {
+                ScenarioSection::InstructionsBitmap => {
+                    base.mission_picture = other_base.mission_picture.take();
+                }
+                ScenarioSection::PlayerFiles => {
+                    base.player_files = std::mem::take(&mut other_base.player_files);
+                }
+                ScenarioSection::AIFiles => self.format.ai_info = other.ai_info.take(),
+            }
+            self.format.skipped.retain(|skipped| skipped != section);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AIFile, Error, PlayerFileKind};
+    use std::fs::File;
+
+    #[test]
+    fn skip_and_load_sections() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        scen.set_player_file(
+            1,
+            PlayerFileKind::AIRules,
+            "test",
+            "(defrule (true) => (disable-self))",
+        )?;
+        scen.set_ai_file(AIFile::new(
+            "test.per",
+            "(defrule (true) => (disable-self))",
+        ));
+        let mut bytes = vec![];
+        scen.write_to(&mut bytes)?;
+
+        let compression = SCXCompression::default();
+        let mut lean =
+            Scenario::read_from_skipping(&bytes[..], compression, &ScenarioSection::ALL)?;
+        assert_eq!(lean.skipped_sections(), &ScenarioSection::ALL[..]);
+        assert!(lean.player_files().next().is_none());
+        assert!(lean.ai_files().is_empty());
+        assert_eq!(lean.objects().count(), scen.objects().count());
+        assert!(matches!(
+            lean.write_to(&mut vec![]),
+            Err(Error::SkippedSectionError(
+                ScenarioSection::InstructionsBitmap
+            ))
+        ));
+
+        lean.load_sections(&bytes[..], compression, &[ScenarioSection::AIFiles])?;
+        assert_eq!(lean.ai_files(), scen.ai_files());
+        assert!(lean.player_files().next().is_none());
+
+        lean.load_sections(
+            &bytes[..],
+            compression,
+            &[
+                ScenarioSection::InstructionsBitmap,
+                ScenarioSection::PlayerFiles,
+            ],
+        )?;
+        assert!(lean.skipped_sections().is_empty());
+        let mut written = vec![];
+        lean.write_to(&mut written)?;
+        assert_eq!(written, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn skip_sections_in_de_scenarios() -> anyhow::Result<()> {
+        for name in &[
+            "Year_of_the_Pig.aoe2scenario",
+            "Hotkey Trainer Buildings.aoe2scenario",
+            "layertest.aoe2scenario",
+        ] {
+            let bytes = std::fs::read(format!("test/scenarios/{}", name))?;
+            let scen = Scenario::read_from(&bytes[..])?;
+            let mut expected = vec![];
+            scen.write_to(&mut expected)?;
+
+            let compression = SCXCompression::default();
+            let mut lean =
+                Scenario::read_from_skipping(&bytes[..], compression, &ScenarioSection::ALL)?;
+            assert_eq!(lean.objects().count(), scen.objects().count());
+            lean.load_sections(&bytes[..], compression, &ScenarioSection::ALL)?;
+            let mut written = vec![];
+            lean.write_to(&mut written)?;
+            assert_eq!(written, expected, "{}", name);
+        }
+
+        let newer = Scenario::read_from_skipping(
+            &b"1.40\0\0\0\0"[..],
+            SCXCompression::default(),
+            &ScenarioSection::ALL,
+        );
+        assert!(matches!(
+            newer,
+            Err(crate::Error::UnsupportedFormatVersionError(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+//! Statistics about a scenario, for showing in scenario listings.
+
+use crate::{SCXVersion, Scenario};
+use std::collections::BTreeMap;
+
+/// Statistics about a scenario, collected by [`Scenario::summary`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScenarioSummary {
+    format_version: SCXVersion,
+    map_size: (u32, u32),
+    num_triggers: usize,
+    num_conditions: usize,
+    num_effects: usize,
+    objects_per_player: Vec<usize>,
+    terrains: BTreeMap<u8, usize>,
+}
+
+impl ScenarioSummary {
+    /// Get the format version of the scenario file.
+    pub fn format_version(&self) -> SCXVersion {
+        self.format_version
+    }
+
+    /// Get the width and height of the map in tiles.
+    pub fn map_size(&self) -> (u32, u32) {
+        self.map_size
+    }
+
+    /// Get the number of triggers.
+    pub fn num_triggers(&self) -> usize {
+        self.num_triggers
+    }
+
+    /// Get the number of trigger conditions, across all triggers.
+    pub fn num_conditions(&self) -> usize {
+        self.num_conditions
+    }
+
+    /// Get the number of trigger effects, across all triggers.
+    pub fn num_effects(&self) -> usize {
+        self.num_effects
+    }
+
+    /// Get the number of objects placed for each player. Player 0 is GAIA.
+    pub fn objects_per_player(&self) -> &[usize] {
+        &self.objects_per_player
+    }
+
+    /// Get the number of objects placed for all players, including GAIA.
+    pub fn num_objects(&self) -> usize {
+        self.objects_per_player.iter().sum()
+    }
+
+    /// Iterate over the terrain IDs used on the map, with the number of tiles using each terrain,
+    /// ordered by terrain ID.
+    pub fn terrains(&self) -> impl Iterator<Item = (u8, usize)> + '_ {
+        self.terrains
+            .iter()
+            .map(|(terrain, count)| (*terrain, *count))
+    }
+
+    /// Get the average number of objects per tile. Returns 0 for empty maps.
+    pub fn object_density(&self) -> f32 {
+        let tiles = self.map_size.0 as f32 * self.map_size.1 as f32;
+        if tiles == 0.0 {
+            return 0.0;
+        }
+        self.num_objects() as f32 / tiles
+    }
+}
+
+impl Scenario {
+    /// Collect statistics about this scenario: trigger and object counts, the map size and the
+    /// terrains used on it.
+    ///
+    /// This only counts the data that was already read, so it is cheap to call.
+    pub fn summary(&self) -> ScenarioSummary {
+        let mut summary = ScenarioSummary {
+            format_version: self.format_version(),
+            map_size: (self.map().width(), self.map().height()),
+            num_triggers: 0,
+            num_conditions: 0,
+            num_effects: 0,
+            objects_per_player: self
+                .format
+                .player_objects
+                .iter()
+                .map(|objects| objects.len())
+                .collect(),
+            terrains: BTreeMap::new(),
+        };
+
+        for trigger in self
+            .triggers()
+            .into_iter()
+            .flat_map(|t| t.triggers_unordered())
+        {
+            summary.num_triggers += 1;
+            summary.num_conditions += trigger.conditions_unordered().count();
+            summary.num_effects += trigger.effects_unordered().count();
+        }
+
+        for tile in self.map().tiles() {
+            *summary.terrains.entry(tile.terrain).or_default() += 1;
+        }
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn summarize() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let summary = scen.summary();
+
+        let (width, height) = summary.map_size();
+        assert_eq!((width, height), (scen.map().width(), scen.map().height()));
+        assert_eq!(summary.format_version(), scen.format_version());
+        assert_eq!(
+            summary.num_triggers(),
+            scen.triggers().unwrap().num_triggers() as usize
+        );
+        assert!(summary.num_effects() > 0);
+        assert_eq!(summary.num_objects(), scen.objects().count());
+        assert_eq!(
+            summary.terrains().map(|(_, count)| count).sum::<usize>(),
+            (width * height) as usize
+        );
+        assert_eq!(
+            summary.object_density(),
+            summary.num_objects() as f32 / (width * height) as f32
+        );
+        Ok(())
+    }
+}
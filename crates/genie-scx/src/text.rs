@@ -0,0 +1,157 @@
+//! Player-visible text, for translating scenarios.
+
+use crate::{MessageKind, Scenario};
+use genie_support::{StringKey, StringSource};
+use std::collections::HashMap;
+
+/// Keys in the text table for each kind of message, in the order of `MessageKind::ALL`.
+const MESSAGE_KEYS: [&str; 6] = [
+    "instructions",
+    "hints",
+    "victory",
+    "loss",
+    "history",
+    "scouts",
+];
+
+fn player_name_key(player: usize) -> String {
+    format!("player.{}.name", player)
+}
+
+fn objective_key(trigger: usize) -> String {
+    format!("trigger.{}.description", trigger)
+}
+
+fn short_objective_key(trigger: usize) -> String {
+    format!("trigger.{}.short_description", trigger)
+}
+
+fn effect_key(trigger: usize, effect: usize) -> String {
+    format!("trigger.{}.effect.{}", trigger, effect)
+}
+
+impl Scenario {
+    /// Call a function with the key and value of every player-visible string.
+    fn for_each_text(&self, mut f: impl FnMut(String, Option<&str>)) {
+        let base = &self.format.tribe_scen.base;
+        for (key, kind) in MESSAGE_KEYS.iter().zip(MessageKind::ALL.iter()) {
+            f(key.to_string(), base.message(*kind));
+        }
+        for (index, name) in base.player_names.iter().enumerate() {
+            f(player_name_key(index + 1), name.as_deref());
+        }
+
+        for (id, trigger) in self
+            .triggers()
+            .into_iter()
+            .flat_map(|t| t.triggers_unordered())
+            .enumerate()
+        {
+            f(objective_key(id), trigger.description.as_deref());
+            f(
+                short_objective_key(id),
+                trigger.short_description.as_deref(),
+            );
+            for (index, effect) in trigger.effects_unordered().enumerate() {
+                f(effect_key(id, index), effect.chat_text.as_deref());
+            }
+        }
+    }
+
+    /// Call a function with the key and a mutable reference to every player-visible string.
+    fn for_each_text_mut(&mut self, mut f: impl FnMut(String, &mut Option<String>)) {
+        let base = &mut self.format.tribe_scen.base;
+        for (key, kind) in MESSAGE_KEYS.iter().zip(MessageKind::ALL.iter()) {
+            f(key.to_string(), base.message_mut(*kind));
+        }
+        for (index, name) in base.player_names.iter_mut().enumerate() {
+            f(player_name_key(index + 1), name);
+        }
+
+        if let Some(triggers) = self.triggers_mut() {
+            for (id, trigger) in triggers.triggers_unordered_mut().enumerate() {
+                f(objective_key(id), &mut trigger.description);
+                f(short_objective_key(id), &mut trigger.short_description);
+                for (index, effect) in trigger.effects_unordered_mut().enumerate() {
+                    f(effect_key(id, index), &mut effect.chat_text);
+                }
+            }
+        }
+    }
+
+    /// Collect all player-visible text in the scenario into a table, for translation.
+    ///
+    /// This includes the instructions, hints, victory, loss, history and scouts messages, player
+    /// names, trigger objectives, and the text of trigger effects such as chat messages. Keys are
+    /// named, like `instructions`, `player.1.name` or `trigger.3.effect.0`; empty strings are
+    /// left out. Trigger and effect numbers are their unordered indices.
+    ///
+    /// The table can be written to an HD Edition key-value language file with genie-lang, and the
+    /// translated strings put back with [`Scenario::apply_text`].
+    pub fn localizable_text(&self) -> HashMap<StringKey, String> {
+        let mut table = HashMap::new();
+        self.for_each_text(|key, text| {
+            if let Some(text) = text.filter(|text| !text.is_empty()) {
+                table.insert(StringKey::from(key), text.to_string());
+            }
+        });
+        table
+    }
+
+    /// Replace player-visible text with strings from a table, such as a translated language
+    /// file. The keys are the same as the ones used by [`Scenario::localizable_text`]. Strings
+    /// that are not in the table are left unchanged.
+    ///
+    /// Returns the number of strings that were replaced.
+    pub fn apply_text(&mut self, strings: &impl StringSource) -> usize {
+        let mut replaced = 0;
+        self.for_each_text_mut(|key, text| {
+            if let Some(string) = strings.get_string(&StringKey::from(key)) {
+                *text = Some(string.to_string());
+                replaced += 1;
+            }
+        });
+
+        // The player data has another copy of the player names.
+        for (index, player) in self.format.scenario_players.iter_mut().enumerate() {
+            let key = StringKey::from(player_name_key(index + 1));
+            if let Some(name) = strings.get_string(&key) {
+                player.set_name(name);
+            }
+        }
+        replaced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn translate() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let table = scen.localizable_text();
+        let instructions = StringKey::from("instructions");
+        assert_eq!(
+            table.get(&instructions).map(String::as_str),
+            scen.description()
+        );
+        assert!(table.keys().any(|key| key.to_string().contains(".effect.")));
+
+        let translated: HashMap<StringKey, String> = table
+            .into_iter()
+            .map(|(key, text)| (key, text.to_uppercase()))
+            .collect();
+        let replaced = scen.apply_text(&translated);
+        assert_eq!(replaced, translated.len());
+
+        let mut out = vec![];
+        scen.write_to(&mut out)?;
+        let scen = Scenario::read_from(Cursor::new(out))?;
+        assert_eq!(scen.localizable_text(), translated);
+        Ok(())
+    }
+}
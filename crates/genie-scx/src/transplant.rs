@@ -0,0 +1,254 @@
+//! Copying map regions and triggers from one scenario into another.
+
+use crate::{Area, Error, Location, Result, Scenario, ScenarioObject, TriggerSystem};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Describes a map region copied by [`Scenario::copy_region`], so that triggers copied along
+/// with it can refer to the copied objects and tiles.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CopiedRegion {
+    objects: HashMap<i32, i32>,
+    offset: (i32, i32),
+}
+
+impl CopiedRegion {
+    /// Get the ID of the copy of an object, given the ID of the original object.
+    pub fn object(&self, id: i32) -> Option<i32> {
+        self.objects.get(&id).copied()
+    }
+
+    /// Get the number of objects that were copied.
+    pub fn num_objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Get the distance in tiles between the copied region and the original region.
+    pub fn offset(&self) -> (i32, i32) {
+        self.offset
+    }
+
+    fn shift_location(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        if x < 0 || y < 0 {
+            return (x, y);
+        }
+        (x + self.offset.0, y + self.offset.1)
+    }
+
+    fn shift_area(&self, (x1, y1, x2, y2): (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+        let (x1, y1) = self.shift_location((x1, y1));
+        let (x2, y2) = self.shift_location((x2, y2));
+        (x1, y1, x2, y2)
+    }
+
+    fn remap_object(&self, id: i32) -> i32 {
+        self.object(id).unwrap_or(id)
+    }
+}
+
+impl Scenario {
+    /// Copy the terrain and the objects in a rectangular area of another scenario into this
+    /// scenario, with the top left corner of the area at the given location.
+    ///
+    /// Tiles and objects that would end up outside this scenario's map are skipped. Copied
+    /// objects get new IDs, which are listed in the returned [`CopiedRegion`].
+    pub fn copy_region(
+        &mut self,
+        source: &Scenario,
+        area: Area,
+        to: Location,
+    ) -> Result<CopiedRegion> {
+        let (x1, x2) = (area.x1.min(area.x2), area.x1.max(area.x2));
+        let (y1, y2) = (area.y1.min(area.y2), area.y1.max(area.y2));
+        let offset = (to.x - x1, to.y - y1);
+        let (width, height) = (self.map().width() as f32, self.map().height() as f32);
+
+        let mut copies = vec![];
+        for (player, objects) in source.format.player_objects.iter().enumerate() {
+            for object in objects {
+                let (x, y, z) = object.position;
+                let (tile_x, tile_y) = (x.floor() as i32, y.floor() as i32);
+                if tile_x < x1 || tile_x > x2 || tile_y < y1 || tile_y > y2 {
+                    continue;
+                }
+                let position = (x + offset.0 as f32, y + offset.1 as f32, z);
+                if !(0.0..width).contains(&position.0) || !(0.0..height).contains(&position.1) {
+                    continue;
+                }
+                if player >= self.format.player_objects.len() {
+                    return Err(Error::InvalidPlayerError(player));
+                }
+                copies.push((player, position, object));
+            }
+        }
+
+        let mut region = CopiedRegion {
+            objects: HashMap::new(),
+            offset,
+        };
+        let mut next_id = self.next_object_id();
+        for (_, _, object) in &copies {
+            region.objects.insert(object.id, next_id);
+            next_id += 1;
+        }
+        for (player, position, object) in copies {
+            self.format.player_objects[player].push(ScenarioObject {
+                position,
+                id: region.remap_object(object.id),
+                garrisoned_in: object.garrisoned_in.and_then(|id| region.object(id)),
+                ..object.clone()
+            });
+        }
+        self.format.next_object_id = next_id;
+
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let tile = match (u32::try_from(x), u32::try_from(y)) {
+                    (Ok(x), Ok(y)) => source.map().tile(x, y),
+                    _ => None,
+                };
+                let target = (u32::try_from(x + offset.0), u32::try_from(y + offset.1));
+                if let (Some(tile), (Ok(x), Ok(y))) = (tile, target) {
+                    if let Some(target) = self.map_mut().tile_mut(x, y) {
+                        *target = *tile;
+                    }
+                }
+            }
+        }
+
+        Ok(region)
+    }
+
+    /// Copy triggers from another scenario into this scenario. Returns the IDs of the new
+    /// triggers, in the same order as `ids`.
+    ///
+    /// References between the copied triggers are updated to the new trigger IDs, and references
+    /// to triggers that were not copied are cleared. When the triggers belong to a region copied
+    /// with [`Scenario::copy_region`], pass the region to update references to the copied
+    /// objects and move the trigger locations and areas along with it; references to other
+    /// objects are kept as they are.
+    pub fn copy_triggers(
+        &mut self,
+        source: &Scenario,
+        ids: &[usize],
+        region: Option<&CopiedRegion>,
+    ) -> Result<Vec<usize>> {
+        let source_triggers: Vec<_> = source
+            .triggers()
+            .into_iter()
+            .flat_map(|t| t.triggers_unordered())
+            .collect();
+        let mut triggers = ids
+            .iter()
+            .map(|id| {
+                source_triggers
+                    .get(*id)
+                    .map(|trigger| (*trigger).clone())
+                    .ok_or(Error::UnknownTriggerError(*id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let system = self
+            .format
+            .triggers
+            .get_or_insert_with(TriggerSystem::default);
+        let first = system.num_triggers() as usize;
+        let new_ids: HashMap<i32, i32> = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id as i32, (first + index) as i32))
+            .collect();
+        let remap_trigger = |id: i32| match id {
+            -1 => -1,
+            id => new_ids.get(&id).copied().unwrap_or(-1),
+        };
+
+        for trigger in &mut triggers {
+            for condition in trigger.conditions_unordered_mut() {
+                condition.set_trigger_id(remap_trigger(condition.trigger_id()));
+                if let Some(region) = region {
+                    condition.set_primary_object(region.remap_object(condition.primary_object()));
+                    condition
+                        .set_secondary_object(region.remap_object(condition.secondary_object()));
+                    condition.set_area(region.shift_area(condition.area()));
+                }
+            }
+            for effect in trigger.effects_unordered_mut() {
+                effect.set_trigger_id(remap_trigger(effect.trigger_id()));
+                if let Some(region) = region {
+                    for object in effect.objects_mut() {
+                        *object = region.remap_object(*object);
+                    }
+                    effect.set_object_id(region.remap_object(effect.object_id()));
+                    effect.set_location(region.shift_location(effect.location()));
+                    effect.set_area(region.shift_area(effect.area()));
+                }
+            }
+        }
+
+        triggers
+            .into_iter()
+            .map(|trigger| Ok(system.add_trigger(trigger)? as usize))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Effect, ObjectSelection, Trigger};
+    use std::fs::File;
+
+    #[test]
+    fn copy_region_and_triggers() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut source = Scenario::read_from(&mut f)?;
+        let mut f = File::open("test/scenarios/Jeremiah Johnson (Update).scx")?;
+        let mut target = Scenario::read_from(&mut f)?;
+
+        source.map_mut().tile_mut(10, 11).unwrap().terrain = 40;
+        let archer = source.add_object(1, 4.into(), (10.5, 11.5, 0.0))?;
+        let triggers = source.triggers_mut().unwrap();
+        let first = triggers.num_triggers() as i32;
+        let kill = Trigger::new("Kill")
+            .effect(Effect::KillObject {
+                objects: ObjectSelection {
+                    player: 1,
+                    objects: vec![archer],
+                    ..Default::default()
+                },
+            })?
+            .effect(Effect::ActivateTrigger(first + 1))?;
+        let kill = triggers.add_trigger(kill)? as usize;
+        let next =
+            triggers.add_trigger(Trigger::new("Next").effect(Effect::ActivateTrigger(0))?)?;
+
+        let num_objects = target.objects().count();
+        let area = Area {
+            x1: 10,
+            y1: 11,
+            x2: 12,
+            y2: 13,
+        };
+        let region = target.copy_region(&source, area, Location { x: 2, y: 3 })?;
+        assert_eq!(region.offset(), (-8, -8));
+        assert_eq!(target.objects().count(), num_objects + region.num_objects());
+        assert_eq!(target.map().tile(2, 3).unwrap().terrain, 40);
+        let copy = region.object(archer).unwrap();
+        assert_eq!(target.object(copy).unwrap().position, (2.5, 3.5, 0.0));
+
+        let ids = target.copy_triggers(&source, &[kill, next as usize], Some(&region))?;
+        let triggers: Vec<_> = target.triggers().unwrap().triggers_unordered().collect();
+        let effects: Vec<_> = triggers[ids[0]].effects_unordered().collect();
+        assert_eq!(effects[0].objects(), &[copy]);
+        assert_eq!(effects[1].trigger_id(), ids[1] as i32);
+        let effects: Vec<_> = triggers[ids[1]].effects_unordered().collect();
+        assert_eq!(effects[0].trigger_id(), -1);
+
+        assert!(matches!(
+            target.copy_triggers(&source, &[9999], None),
+            Err(Error::UnknownTriggerError(9999))
+        ));
+        Ok(())
+    }
+}
@@ -1,12 +1,16 @@
-use crate::Result;
+use crate::types::DiplomaticStance;
 use crate::UnitTypeID;
+use crate::{Error, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use genie_support::{read_opt_u32, write_i32_str, write_opt_i32_str, ReadStringsExt, StringKey};
-use std::convert::TryInto;
+use genie_support::{
+    read_opt_u32, write_i32_str, write_opt_i32_str, ReadStringsExt, StringKey, TryConvertExt,
+};
+use std::convert::{TryFrom, TryInto};
 use std::io::{Read, Write};
 
 /// A trigger condition, describing when a trigger can fire.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TriggerCondition {
     condition_type: i32,
     properties: Vec<i32>,
@@ -104,13 +108,15 @@ impl TriggerCondition {
     }
 
     /// Get the "unit type" value for this trigger condition.
-    pub fn unit_type(&self) -> UnitTypeID {
-        self.properties[4].try_into().unwrap()
+    ///
+    /// Returns `None` if no unit type is set, or if the raw value is not a valid unit type ID.
+    pub fn unit_type(&self) -> Option<UnitTypeID> {
+        self.properties[4].try_into().ok()
     }
 
     /// Set the "unit type" value for this trigger condition.
     pub fn set_unit_type(&mut self, unit_type: UnitTypeID) {
-        self.properties[4] = unit_type.try_into().unwrap();
+        self.properties[4] = unit_type.into();
     }
 
     /// Get the "player ID" value for this trigger condition.
@@ -185,9 +191,21 @@ impl TriggerCondition {
         self.properties[13] = unit_group;
     }
 
+    /// Get the raw "Object Type" value for this trigger condition.
+    pub fn raw_object_type(&self) -> i32 {
+        self.properties[14]
+    }
+
+    /// Set the raw "Object Type" value for this trigger condition.
+    pub fn set_raw_object_type(&mut self, object_type: i32) {
+        self.properties[14] = object_type;
+    }
+
     /// Get the "Object Type" value for this trigger condition.
-    pub fn object_type(&self) -> UnitTypeID {
-        self.properties[14].try_into().unwrap()
+    ///
+    /// Returns `None` if no unit type is set, or if the raw value is not a valid unit type ID.
+    pub fn object_type(&self) -> Option<UnitTypeID> {
+        self.properties[14].try_into().ok()
     }
 
     /// Set the "Object Type" value for this trigger condition.
@@ -217,11 +235,12 @@ impl TriggerCondition {
 }
 
 /// A trigger effect, describing the response when a trigger fires.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TriggerEffect {
     effect_type: i32,
     properties: Vec<i32>,
-    chat_text: Option<String>,
+    pub(crate) chat_text: Option<String>,
     audio_file: Option<String>,
     objects: Vec<i32>,
 }
@@ -346,9 +365,21 @@ impl TriggerEffect {
         self.properties[5] = object_id;
     }
 
+    /// Get the raw "Unit Type" value for this trigger effect.
+    pub fn raw_unit_type(&self) -> i32 {
+        self.properties[6]
+    }
+
+    /// Set the raw "Unit Type" value for this trigger effect.
+    pub fn set_raw_unit_type(&mut self, unit_type: i32) {
+        self.properties[6] = unit_type;
+    }
+
     /// Get the "Unit Type" value for this trigger effect.
-    pub fn unit_type(&self) -> UnitTypeID {
-        self.properties[6].try_into().unwrap()
+    ///
+    /// Returns `None` if no unit type is set, or if the raw value is not a valid unit type ID.
+    pub fn unit_type(&self) -> Option<UnitTypeID> {
+        self.properties[6].try_into().ok()
     }
 
     /// Set the "Unit Type" value for this trigger effect.
@@ -465,9 +496,21 @@ impl TriggerEffect {
         self.properties[20] = object_group;
     }
 
+    /// Get the raw "Object Type" value for this trigger effect.
+    pub fn raw_object_type(&self) -> i32 {
+        self.properties[21]
+    }
+
+    /// Set the raw "Object Type" value for this trigger effect.
+    pub fn set_raw_object_type(&mut self, object_type: i32) {
+        self.properties[21] = object_type;
+    }
+
     /// Get the "Object Type" value for this trigger effect.
-    pub fn object_type(&self) -> UnitTypeID {
-        self.properties[21].try_into().unwrap()
+    ///
+    /// Returns `None` if no unit type is set, or if the raw value is not a valid unit type ID.
+    pub fn object_type(&self) -> Option<UnitTypeID> {
+        self.properties[21].try_into().ok()
     }
 
     /// Set the "Object Type" value for this trigger effect.
@@ -497,7 +540,8 @@ impl TriggerEffect {
 }
 
 /// A trigger, describing automatic interactive behaviours in a scenario.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Trigger {
     enabled: bool,
     looping: bool,
@@ -505,9 +549,9 @@ pub struct Trigger {
     is_objective: bool,
     objective_order: i32,
     start_time: u32,
-    description: Option<String>,
+    pub(crate) description: Option<String>,
     short_description_id: Option<StringKey>,
-    short_description: Option<String>,
+    pub(crate) short_description: Option<String>,
     display_short_description: bool,
     short_description_state: u8,
     mute_objective: bool,
@@ -520,6 +564,129 @@ pub struct Trigger {
 }
 
 impl Trigger {
+    /// Create an enabled trigger without conditions or effects.
+    ///
+    /// ```
+    /// use genie_scx::{Condition, Effect, Trigger};
+    ///
+    /// # fn main() -> genie_scx::Result<()> {
+    /// let trigger = Trigger::new("Welcome")
+    ///     .condition(Condition::Timer(30))?
+    ///     .effect(Effect::SendChat {
+    ///         player: 1,
+    ///         message: "Welcome!".to_string(),
+    ///         sound: None,
+    ///     })?;
+    /// assert_eq!(trigger.conditions().count(), 1);
+    /// # Ok(()) }
+    /// ```
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            enabled: true,
+            looping: false,
+            name_id: -1,
+            is_objective: false,
+            objective_order: 0,
+            start_time: 0,
+            description: None,
+            short_description_id: None,
+            short_description: None,
+            display_short_description: false,
+            short_description_state: 0,
+            mute_objective: false,
+            name: Some(name.into()),
+            effects: vec![],
+            effect_order: vec![],
+            conditions: vec![],
+            condition_order: vec![],
+            make_header: false,
+        }
+    }
+
+    /// Set whether this trigger is enabled when the scenario starts.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set whether this trigger fires again every time its conditions are met.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Show this trigger as an objective, with the given description.
+    pub fn objective(mut self, description: impl Into<String>, order: i32) -> Self {
+        self.is_objective = true;
+        self.objective_order = order;
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Add a condition to this trigger.
+    ///
+    /// Returns [`Error::TooManyTriggerItemsError`] if the trigger already has `i32::MAX`
+    /// conditions.
+    pub fn condition(mut self, condition: impl Into<TriggerCondition>) -> Result<Self> {
+        self.add_condition(condition)?;
+        Ok(self)
+    }
+
+    /// Add an effect to this trigger.
+    ///
+    /// Returns [`Error::TooManyTriggerItemsError`] if the trigger already has `i32::MAX` effects,
+    /// or if the effect selects more than `i32::MAX` objects.
+    pub fn effect<E>(mut self, effect: E) -> Result<Self>
+    where
+        E: TryInto<TriggerEffect>,
+        Error: From<E::Error>,
+    {
+        self.add_effect(effect)?;
+        Ok(self)
+    }
+
+    /// Get the name of this trigger.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Check if this trigger is enabled when the scenario starts.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Check if this trigger fires again every time its conditions are met.
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Append a condition to this trigger.
+    ///
+    /// Returns [`Error::TooManyTriggerItemsError`] if the trigger already has `i32::MAX`
+    /// conditions.
+    pub fn add_condition(&mut self, condition: impl Into<TriggerCondition>) -> Result<()> {
+        let id = item_id(self.conditions.len())?;
+        self.condition_order.push(id);
+        self.conditions.push(condition.into());
+        Ok(())
+    }
+
+    /// Append an effect to this trigger.
+    ///
+    /// Returns [`Error::TooManyTriggerItemsError`] if the trigger already has `i32::MAX` effects,
+    /// or if the effect selects more than `i32::MAX` objects.
+    pub fn add_effect<E>(&mut self, effect: E) -> Result<()>
+    where
+        E: TryInto<TriggerEffect>,
+        Error: From<E::Error>,
+    {
+        let id = item_id(self.effects.len())?;
+        let effect = effect.try_into()?;
+        self.effect_order.push(id);
+        self.effects.push(effect);
+        Ok(())
+    }
+
     /// Read a trigger from an input stream, with the given trigger system version.
     pub fn read_from(mut input: impl Read, version: f64) -> Result<Self> {
         let enabled = input.read_i32::<LE>()? != 0;
@@ -646,6 +813,11 @@ impl Trigger {
     }
 
     /// Get the conditions in this trigger, unordered.
+    pub fn conditions_unordered(&self) -> impl Iterator<Item = &TriggerCondition> {
+        self.conditions.iter()
+    }
+
+    /// Get the conditions in this trigger, mutably and unordered.
     pub fn conditions_unordered_mut(&mut self) -> impl Iterator<Item = &mut TriggerCondition> {
         self.conditions.iter_mut()
     }
@@ -658,6 +830,11 @@ impl Trigger {
     }
 
     /// Get the effects in this trigger, unordered.
+    pub fn effects_unordered(&self) -> impl Iterator<Item = &TriggerEffect> {
+        self.effects.iter()
+    }
+
+    /// Get the effects in this trigger, mutably and unordered.
     pub fn effects_unordered_mut(&mut self) -> impl Iterator<Item = &mut TriggerEffect> {
         self.effects.iter_mut()
     }
@@ -665,6 +842,7 @@ impl Trigger {
 
 /// The trigger system maintains an ordered list  of triggers.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TriggerSystem {
     version: f64,
     objectives_state: i8,
@@ -690,6 +868,25 @@ impl Default for TriggerSystem {
 }
 
 impl TriggerSystem {
+    /// Get mutable references to all the strings in the trigger system: variable names, trigger
+    /// names and descriptions, and effect text and sound file names.
+    pub(crate) fn strings_mut(&mut self) -> Vec<&mut String> {
+        let mut strings: Vec<_> = self.variable_names.iter_mut().collect();
+        for trigger in &mut self.triggers {
+            let texts = vec![
+                &mut trigger.name,
+                &mut trigger.description,
+                &mut trigger.short_description,
+            ];
+            let effects = trigger
+                .effects
+                .iter_mut()
+                .flat_map(|effect| vec![&mut effect.chat_text, &mut effect.audio_file]);
+            strings.extend(texts.into_iter().chain(effects).filter_map(Option::as_mut));
+        }
+        strings
+    }
+
     /// Read a trigger system from an input stream.
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         let version = input.read_f64::<LE>()?;
@@ -760,7 +957,7 @@ impl TriggerSystem {
         if version >= 1.5 {
             output.write_i8(self.objectives_state)?;
         }
-        output.write_u32::<LE>(self.triggers.len().try_into().unwrap())?;
+        output.write_u32::<LE>(self.triggers.len().try_convert()?)?;
         for trigger in &self.triggers {
             trigger.write_to(&mut output, version)?;
         }
@@ -793,7 +990,7 @@ impl TriggerSystem {
             output.write_u32::<LE>(num_custom_names as u32)?;
             for (index, name) in custom_names {
                 output.write_u32::<LE>(index as u32)?;
-                write_i32_str(&mut output, &name)?;
+                write_i32_str(&mut output, name)?;
             }
         }
         Ok(())
@@ -804,6 +1001,13 @@ impl TriggerSystem {
         self.version
     }
 
+    /// Check if any trigger variables have a name or a nonzero starting value. Trigger variables
+    /// are only saved by trigger system version 2.2 and up.
+    pub(crate) fn has_variables(&self) -> bool {
+        self.variable_values.iter().any(|value| *value != 0)
+            || self.variable_names.iter().any(|name| !name.is_empty())
+    }
+
     /// Get the number of triggers in the trigger system.
     pub fn num_triggers(&self) -> u32 {
         self.triggers.len() as u32
@@ -816,10 +1020,32 @@ impl TriggerSystem {
             .map(move |index| &self.triggers[*index as usize])
     }
 
+    /// Iterate over all triggers, unordered. The index of a trigger in this iterator is its ID.
+    pub fn triggers_unordered(&self) -> impl Iterator<Item = &Trigger> {
+        self.triggers.iter()
+    }
+
     /// Iterate over all triggers, mutably and unordered.
     pub fn triggers_unordered_mut(&mut self) -> impl Iterator<Item = &mut Trigger> {
         self.triggers.iter_mut()
     }
+
+    /// Append a trigger. Returns the ID of the trigger, for use with trigger conditions and
+    /// effects that refer to other triggers.
+    ///
+    /// Returns [`Error::TooManyTriggerItemsError`] if the scenario already has `i32::MAX`
+    /// triggers.
+    pub fn add_trigger(&mut self, trigger: Trigger) -> Result<i32> {
+        let id = item_id(self.triggers.len())?;
+        self.triggers.push(trigger);
+        self.trigger_order.push(id);
+        Ok(id)
+    }
+}
+
+/// Get the ID for a trigger, condition, effect or object appended to a list of `len` items.
+fn item_id(len: usize) -> Result<i32> {
+    i32::try_from(len).map_err(|_| Error::TooManyTriggerItemsError(len))
 }
 
 fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) -> Result<()> {
@@ -832,3 +1058,1230 @@ fn write_opt_string_key(mut output: impl Write, opt_key: &Option<StringKey>) ->
     })?;
     Ok(())
 }
+
+/// A rectangular area on the map.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Area {
+    pub x1: i32,
+    pub y1: i32,
+    pub x2: i32,
+    pub y2: i32,
+}
+
+impl Area {
+    fn from_tuple((x1, y1, x2, y2): (i32, i32, i32, i32)) -> Self {
+        Self { x1, y1, x2, y2 }
+    }
+
+    fn to_tuple(self) -> (i32, i32, i32, i32) {
+        (self.x1, self.y1, self.x2, self.y2)
+    }
+
+    fn from_opt_tuple(area: (i32, i32, i32, i32)) -> Option<Self> {
+        Some(Self::from_tuple(area)).filter(|area| *area != Self::none())
+    }
+
+    fn none() -> Self {
+        Self::from_tuple((-1, -1, -1, -1))
+    }
+}
+
+/// A tile location on the map.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Restricts the objects that a trigger condition or effect applies to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectFilter {
+    /// Only objects of this unit type.
+    pub unit_type: Option<UnitTypeID>,
+    /// Only objects in this unit class.
+    pub group: Option<i32>,
+    /// Only objects of this object type, such as buildings or civilians.
+    pub object_type: Option<i32>,
+    /// Only objects inside this area.
+    pub area: Option<Area>,
+}
+
+/// The objects that a trigger effect applies to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectSelection {
+    /// The player whose objects are affected.
+    pub player: i32,
+    /// Specific objects, by their IDs.
+    pub objects: Vec<i32>,
+    /// Apply to all of the player's objects matching this filter.
+    pub filter: ObjectFilter,
+}
+
+/// The type of a trigger condition, without its parameters.
+///
+/// Types that this crate does not know about are kept as `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConditionType {
+    /// An object is brought into an area.
+    BringObjectToArea,
+    /// An object is brought next to another object.
+    BringObjectToObject,
+    /// A player owns at least a number of objects.
+    OwnObjects,
+    /// A player owns at most a number of objects.
+    OwnFewerObjects,
+    /// A player has at least a number of objects inside an area.
+    ObjectsInArea,
+    /// An object was destroyed.
+    DestroyObject,
+    /// A player captured an object.
+    CaptureObject,
+    /// A player has accumulated an amount of a resource.
+    AccumulateAttribute,
+    /// A player researched a tech.
+    ResearchTechnology,
+    /// A number of seconds has passed.
+    Timer,
+    /// The user selected an object.
+    ObjectSelected,
+    /// An AI script sent a signal.
+    AISignal,
+    /// A player was defeated.
+    PlayerDefeated,
+    /// An object is targeting another object.
+    ObjectHasTarget,
+    /// An object is visible to the user.
+    ObjectVisible,
+    /// An object is not visible to the user.
+    ObjectNotVisible,
+    /// A player is researching a tech.
+    ResearchingTechnology,
+    /// Units are garrisoned in an object.
+    UnitsGarrisoned,
+    /// The game is played at a difficulty level.
+    DifficultyLevel,
+    /// A condition type that is not known to this crate, with its raw type ID.
+    Unknown(i32),
+}
+
+impl From<i32> for ConditionType {
+    fn from(n: i32) -> Self {
+        match n {
+            1 => ConditionType::BringObjectToArea,
+            2 => ConditionType::BringObjectToObject,
+            3 => ConditionType::OwnObjects,
+            4 => ConditionType::OwnFewerObjects,
+            5 => ConditionType::ObjectsInArea,
+            6 => ConditionType::DestroyObject,
+            7 => ConditionType::CaptureObject,
+            8 => ConditionType::AccumulateAttribute,
+            9 => ConditionType::ResearchTechnology,
+            10 => ConditionType::Timer,
+            11 => ConditionType::ObjectSelected,
+            12 => ConditionType::AISignal,
+            13 => ConditionType::PlayerDefeated,
+            14 => ConditionType::ObjectHasTarget,
+            15 => ConditionType::ObjectVisible,
+            16 => ConditionType::ObjectNotVisible,
+            17 => ConditionType::ResearchingTechnology,
+            18 => ConditionType::UnitsGarrisoned,
+            19 => ConditionType::DifficultyLevel,
+            n => ConditionType::Unknown(n),
+        }
+    }
+}
+
+impl From<ConditionType> for i32 {
+    fn from(ty: ConditionType) -> i32 {
+        match ty {
+            ConditionType::BringObjectToArea => 1,
+            ConditionType::BringObjectToObject => 2,
+            ConditionType::OwnObjects => 3,
+            ConditionType::OwnFewerObjects => 4,
+            ConditionType::ObjectsInArea => 5,
+            ConditionType::DestroyObject => 6,
+            ConditionType::CaptureObject => 7,
+            ConditionType::AccumulateAttribute => 8,
+            ConditionType::ResearchTechnology => 9,
+            ConditionType::Timer => 10,
+            ConditionType::ObjectSelected => 11,
+            ConditionType::AISignal => 12,
+            ConditionType::PlayerDefeated => 13,
+            ConditionType::ObjectHasTarget => 14,
+            ConditionType::ObjectVisible => 15,
+            ConditionType::ObjectNotVisible => 16,
+            ConditionType::ResearchingTechnology => 17,
+            ConditionType::UnitsGarrisoned => 18,
+            ConditionType::DifficultyLevel => 19,
+            ConditionType::Unknown(n) => n,
+        }
+    }
+}
+
+/// The type of a trigger effect, without its parameters.
+///
+/// Types that this crate does not know about are kept as `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EffectType {
+    /// Change the diplomatic stance of a player.
+    ChangeDiplomacy,
+    /// Research a tech for a player.
+    ResearchTechnology,
+    /// Send a chat message.
+    SendChat,
+    /// Play a sound.
+    PlaySound,
+    /// Give resources from one player to another.
+    SendTribute,
+    /// Unlock gates.
+    UnlockGate,
+    /// Lock gates.
+    LockGate,
+    /// Enable a trigger.
+    ActivateTrigger,
+    /// Disable a trigger.
+    DeactivateTrigger,
+    /// Set an AI script goal.
+    AIScriptGoal,
+    /// Create an object.
+    CreateObject,
+    /// Order objects to move or to interact with an object.
+    TaskObject,
+    /// Make a player win the game.
+    DeclareVictory,
+    /// Kill objects.
+    KillObject,
+    /// Remove objects from the game.
+    RemoveObject,
+    /// Move a player's camera.
+    ChangeView,
+    /// Unload units from transports.
+    Unload,
+    /// Give objects to a different player.
+    ChangeOwnership,
+    /// Make objects patrol.
+    Patrol,
+    /// Show an instructions message.
+    DisplayInstructions,
+    /// Remove an instructions message.
+    ClearInstructions,
+    /// Make objects stand still.
+    FreezeUnit,
+    /// Enable the advanced buttons in the user interface.
+    UseAdvancedButtons,
+    /// Damage objects.
+    DamageObject,
+    /// Place a building foundation.
+    PlaceFoundation,
+    /// Rename objects.
+    ChangeObjectName,
+    /// Change the hit points of objects.
+    ChangeObjectHp,
+    /// Change the attack of objects.
+    ChangeObjectAttack,
+    /// Stop objects.
+    StopUnit,
+    /// A effect type that is not known to this crate, with its raw type ID.
+    Unknown(i32),
+}
+
+impl From<i32> for EffectType {
+    fn from(n: i32) -> Self {
+        match n {
+            1 => EffectType::ChangeDiplomacy,
+            2 => EffectType::ResearchTechnology,
+            3 => EffectType::SendChat,
+            4 => EffectType::PlaySound,
+            5 => EffectType::SendTribute,
+            6 => EffectType::UnlockGate,
+            7 => EffectType::LockGate,
+            8 => EffectType::ActivateTrigger,
+            9 => EffectType::DeactivateTrigger,
+            10 => EffectType::AIScriptGoal,
+            11 => EffectType::CreateObject,
+            12 => EffectType::TaskObject,
+            13 => EffectType::DeclareVictory,
+            14 => EffectType::KillObject,
+            15 => EffectType::RemoveObject,
+            16 => EffectType::ChangeView,
+            17 => EffectType::Unload,
+            18 => EffectType::ChangeOwnership,
+            19 => EffectType::Patrol,
+            20 => EffectType::DisplayInstructions,
+            21 => EffectType::ClearInstructions,
+            22 => EffectType::FreezeUnit,
+            23 => EffectType::UseAdvancedButtons,
+            24 => EffectType::DamageObject,
+            25 => EffectType::PlaceFoundation,
+            26 => EffectType::ChangeObjectName,
+            27 => EffectType::ChangeObjectHp,
+            28 => EffectType::ChangeObjectAttack,
+            29 => EffectType::StopUnit,
+            n => EffectType::Unknown(n),
+        }
+    }
+}
+
+impl From<EffectType> for i32 {
+    fn from(ty: EffectType) -> i32 {
+        match ty {
+            EffectType::ChangeDiplomacy => 1,
+            EffectType::ResearchTechnology => 2,
+            EffectType::SendChat => 3,
+            EffectType::PlaySound => 4,
+            EffectType::SendTribute => 5,
+            EffectType::UnlockGate => 6,
+            EffectType::LockGate => 7,
+            EffectType::ActivateTrigger => 8,
+            EffectType::DeactivateTrigger => 9,
+            EffectType::AIScriptGoal => 10,
+            EffectType::CreateObject => 11,
+            EffectType::TaskObject => 12,
+            EffectType::DeclareVictory => 13,
+            EffectType::KillObject => 14,
+            EffectType::RemoveObject => 15,
+            EffectType::ChangeView => 16,
+            EffectType::Unload => 17,
+            EffectType::ChangeOwnership => 18,
+            EffectType::Patrol => 19,
+            EffectType::DisplayInstructions => 20,
+            EffectType::ClearInstructions => 21,
+            EffectType::FreezeUnit => 22,
+            EffectType::UseAdvancedButtons => 23,
+            EffectType::DamageObject => 24,
+            EffectType::PlaceFoundation => 25,
+            EffectType::ChangeObjectName => 26,
+            EffectType::ChangeObjectHp => 27,
+            EffectType::ChangeObjectAttack => 28,
+            EffectType::StopUnit => 29,
+            EffectType::Unknown(n) => n,
+        }
+    }
+}
+
+/// A trigger condition, decoded from the raw [`TriggerCondition`] data.
+///
+/// Conditions that this crate does not know about are kept as `Unknown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Condition {
+    /// An object is inside an area.
+    BringObjectToArea { object: i32, area: Area },
+    /// An object is next to another object.
+    BringObjectToObject { object: i32, target: i32 },
+    /// A player owns at least `amount` objects.
+    OwnObjects {
+        player: i32,
+        amount: i32,
+        filter: ObjectFilter,
+    },
+    /// A player owns at most `amount` objects.
+    OwnFewerObjects {
+        player: i32,
+        amount: i32,
+        filter: ObjectFilter,
+    },
+    /// A player has at least `amount` objects inside an area.
+    ObjectsInArea {
+        player: i32,
+        amount: i32,
+        filter: ObjectFilter,
+    },
+    /// An object was destroyed.
+    DestroyObject { object: i32 },
+    /// A player captured an object.
+    CaptureObject { object: i32, player: i32 },
+    /// A player has accumulated at least `amount` of a resource.
+    AccumulateAttribute {
+        player: i32,
+        resource: i32,
+        amount: i32,
+    },
+    /// A player researched a tech.
+    ResearchTechnology { player: i32, tech: i32 },
+    /// A number of seconds has passed since the trigger was activated.
+    Timer(i32),
+    /// The user selected an object.
+    ObjectSelected { object: i32 },
+    /// An AI script sent a signal.
+    AISignal(i32),
+    /// A player was defeated.
+    PlayerDefeated { player: i32 },
+    /// An object is targeting another object.
+    ObjectHasTarget {
+        object: i32,
+        target: i32,
+        filter: ObjectFilter,
+    },
+    /// An object is visible to the user.
+    ObjectVisible { object: i32 },
+    /// An object is not visible to the user.
+    ObjectNotVisible { object: i32 },
+    /// A player is researching a tech.
+    ResearchingTechnology { player: i32, tech: i32 },
+    /// At least `amount` units are garrisoned in an object.
+    UnitsGarrisoned { object: i32, amount: i32 },
+    /// The game is played at a difficulty level.
+    DifficultyLevel(i32),
+    /// A condition type that is not known to this crate.
+    Unknown(TriggerCondition),
+}
+
+impl TriggerCondition {
+    /// Create an empty trigger condition of the given type. All properties are unset.
+    fn with_type(condition_type: i32) -> Self {
+        Self {
+            condition_type,
+            properties: vec![-1; 18],
+        }
+    }
+
+    /// Get the type of this trigger condition.
+    pub fn condition_type(&self) -> i32 {
+        self.condition_type
+    }
+
+    /// Get the type of this trigger condition as a [`ConditionType`].
+    pub fn kind(&self) -> ConditionType {
+        ConditionType::from(self.condition_type)
+    }
+
+    fn property(&self, index: usize) -> i32 {
+        self.properties.get(index).copied().unwrap_or(-1)
+    }
+
+    fn filter(&self) -> ObjectFilter {
+        ObjectFilter {
+            unit_type: UnitTypeID::try_from(self.property(4)).ok(),
+            group: Some(self.property(13)).filter(|&group| group != -1),
+            object_type: Some(self.property(14)).filter(|&ty| ty != -1),
+            area: Area::from_opt_tuple(self.area()),
+        }
+    }
+
+    fn set_filter(&mut self, filter: &ObjectFilter) {
+        self.properties[4] = filter.unit_type.map_or(-1, i32::from);
+        self.properties[13] = filter.group.unwrap_or(-1);
+        self.properties[14] = filter.object_type.unwrap_or(-1);
+        self.set_area(filter.area.unwrap_or_else(Area::none).to_tuple());
+    }
+
+    /// Decode this trigger condition into a [`Condition`].
+    pub fn decode(&self) -> Condition {
+        let object = self.property(2);
+        let target = self.property(3);
+        let player = self.property(5);
+        let amount = self.property(0);
+        let tech = self.property(6);
+        match self.kind() {
+            ConditionType::BringObjectToArea => Condition::BringObjectToArea {
+                object,
+                area: Area::from_tuple(self.area()),
+            },
+            ConditionType::BringObjectToObject => Condition::BringObjectToObject { object, target },
+            ConditionType::OwnObjects => Condition::OwnObjects {
+                player,
+                amount,
+                filter: self.filter(),
+            },
+            ConditionType::OwnFewerObjects => Condition::OwnFewerObjects {
+                player,
+                amount,
+                filter: self.filter(),
+            },
+            ConditionType::ObjectsInArea => Condition::ObjectsInArea {
+                player,
+                amount,
+                filter: self.filter(),
+            },
+            ConditionType::DestroyObject => Condition::DestroyObject { object },
+            ConditionType::CaptureObject => Condition::CaptureObject { object, player },
+            ConditionType::AccumulateAttribute => Condition::AccumulateAttribute {
+                player,
+                resource: self.property(1),
+                amount,
+            },
+            ConditionType::ResearchTechnology => Condition::ResearchTechnology { player, tech },
+            ConditionType::Timer => Condition::Timer(self.property(7)),
+            ConditionType::ObjectSelected => Condition::ObjectSelected { object },
+            ConditionType::AISignal => Condition::AISignal(self.property(15)),
+            ConditionType::PlayerDefeated => Condition::PlayerDefeated { player },
+            ConditionType::ObjectHasTarget => Condition::ObjectHasTarget {
+                object,
+                target,
+                filter: self.filter(),
+            },
+            ConditionType::ObjectVisible => Condition::ObjectVisible { object },
+            ConditionType::ObjectNotVisible => Condition::ObjectNotVisible { object },
+            ConditionType::ResearchingTechnology => {
+                Condition::ResearchingTechnology { player, tech }
+            }
+            ConditionType::UnitsGarrisoned => Condition::UnitsGarrisoned { object, amount },
+            ConditionType::DifficultyLevel => Condition::DifficultyLevel(amount),
+            ConditionType::Unknown(_) => Condition::Unknown(self.clone()),
+        }
+    }
+}
+
+impl Condition {
+    /// Get the raw type ID of this condition.
+    pub fn condition_type(&self) -> i32 {
+        self.kind().into()
+    }
+
+    /// Get the type of this condition.
+    pub fn kind(&self) -> ConditionType {
+        match self {
+            Self::BringObjectToArea { .. } => ConditionType::BringObjectToArea,
+            Self::BringObjectToObject { .. } => ConditionType::BringObjectToObject,
+            Self::OwnObjects { .. } => ConditionType::OwnObjects,
+            Self::OwnFewerObjects { .. } => ConditionType::OwnFewerObjects,
+            Self::ObjectsInArea { .. } => ConditionType::ObjectsInArea,
+            Self::DestroyObject { .. } => ConditionType::DestroyObject,
+            Self::CaptureObject { .. } => ConditionType::CaptureObject,
+            Self::AccumulateAttribute { .. } => ConditionType::AccumulateAttribute,
+            Self::ResearchTechnology { .. } => ConditionType::ResearchTechnology,
+            Self::Timer(_) => ConditionType::Timer,
+            Self::ObjectSelected { .. } => ConditionType::ObjectSelected,
+            Self::AISignal(_) => ConditionType::AISignal,
+            Self::PlayerDefeated { .. } => ConditionType::PlayerDefeated,
+            Self::ObjectHasTarget { .. } => ConditionType::ObjectHasTarget,
+            Self::ObjectVisible { .. } => ConditionType::ObjectVisible,
+            Self::ObjectNotVisible { .. } => ConditionType::ObjectNotVisible,
+            Self::ResearchingTechnology { .. } => ConditionType::ResearchingTechnology,
+            Self::UnitsGarrisoned { .. } => ConditionType::UnitsGarrisoned,
+            Self::DifficultyLevel(_) => ConditionType::DifficultyLevel,
+            Self::Unknown(condition) => condition.kind(),
+        }
+    }
+
+    /// Encode this condition as a [`TriggerCondition`]. Properties that are not used by the
+    /// condition are unset.
+    pub fn encode(&self) -> TriggerCondition {
+        let mut condition = TriggerCondition::with_type(self.condition_type());
+        match self {
+            Self::Unknown(condition) => return condition.clone(),
+            Self::BringObjectToArea { object, area } => {
+                condition.set_primary_object(*object);
+                condition.set_area(area.to_tuple());
+            }
+            Self::BringObjectToObject { object, target } => {
+                condition.set_primary_object(*object);
+                condition.set_secondary_object(*target);
+            }
+            Self::OwnObjects {
+                player,
+                amount,
+                filter,
+            }
+            | Self::OwnFewerObjects {
+                player,
+                amount,
+                filter,
+            }
+            | Self::ObjectsInArea {
+                player,
+                amount,
+                filter,
+            } => {
+                condition.set_player_id(*player);
+                condition.set_amount(*amount);
+                condition.set_filter(filter);
+            }
+            Self::DestroyObject { object }
+            | Self::ObjectSelected { object }
+            | Self::ObjectVisible { object }
+            | Self::ObjectNotVisible { object } => condition.set_primary_object(*object),
+            Self::CaptureObject { object, player } => {
+                condition.set_primary_object(*object);
+                condition.set_player_id(*player);
+            }
+            Self::AccumulateAttribute {
+                player,
+                resource,
+                amount,
+            } => {
+                condition.set_player_id(*player);
+                condition.set_resource(*resource);
+                condition.set_amount(*amount);
+            }
+            Self::ResearchTechnology { player, tech }
+            | Self::ResearchingTechnology { player, tech } => {
+                condition.set_player_id(*player);
+                condition.set_technology_id(*tech);
+            }
+            Self::Timer(timer) => condition.set_timer(*timer),
+            Self::AISignal(signal) => condition.set_ai_signal(*signal),
+            Self::PlayerDefeated { player } => condition.set_player_id(*player),
+            Self::ObjectHasTarget {
+                object,
+                target,
+                filter,
+            } => {
+                condition.set_primary_object(*object);
+                condition.set_secondary_object(*target);
+                condition.set_filter(filter);
+            }
+            Self::UnitsGarrisoned { object, amount } => {
+                condition.set_primary_object(*object);
+                condition.set_amount(*amount);
+            }
+            Self::DifficultyLevel(level) => condition.set_amount(*level),
+        }
+        condition
+    }
+}
+
+impl From<Condition> for TriggerCondition {
+    fn from(condition: Condition) -> Self {
+        condition.encode()
+    }
+}
+
+/// A trigger effect, decoded from the raw [`TriggerEffect`] data.
+///
+/// Effects that this crate does not know about are kept as `Unknown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Effect {
+    /// Change the diplomatic stance of a player toward another player.
+    ChangeDiplomacy {
+        player: i32,
+        target_player: i32,
+        stance: DiplomaticStance,
+    },
+    /// Research a tech for a player.
+    ResearchTechnology { player: i32, tech: i32 },
+    /// Show a chat message to a player.
+    SendChat {
+        player: i32,
+        message: String,
+        sound: Option<String>,
+    },
+    /// Play a sound for a player.
+    PlaySound { player: i32, sound: String },
+    /// Give resources from one player to another.
+    SendTribute {
+        player: i32,
+        target_player: i32,
+        resource: i32,
+        amount: i32,
+    },
+    /// Unlock gates.
+    UnlockGate { objects: ObjectSelection },
+    /// Lock gates.
+    LockGate { objects: ObjectSelection },
+    /// Enable a trigger, by its ID.
+    ActivateTrigger(i32),
+    /// Disable a trigger, by its ID.
+    DeactivateTrigger(i32),
+    /// Set an AI script goal.
+    AIScriptGoal(i32),
+    /// Create an object for a player.
+    CreateObject {
+        player: i32,
+        unit_type: UnitTypeID,
+        location: Location,
+    },
+    /// Order objects to move to a location, or to interact with an object.
+    TaskObject {
+        objects: ObjectSelection,
+        location: Location,
+        target: Option<i32>,
+    },
+    /// Make a player win the game.
+    DeclareVictory { player: i32 },
+    /// Kill objects.
+    KillObject { objects: ObjectSelection },
+    /// Remove objects from the game.
+    RemoveObject { objects: ObjectSelection },
+    /// Move a player's camera.
+    ChangeView { player: i32, location: Location },
+    /// Unload units from transports at a location.
+    Unload {
+        objects: ObjectSelection,
+        location: Location,
+    },
+    /// Give objects to a different player.
+    ChangeOwnership {
+        objects: ObjectSelection,
+        target_player: i32,
+    },
+    /// Make objects patrol to a location.
+    Patrol {
+        objects: ObjectSelection,
+        location: Location,
+    },
+    /// Show an instructions message to a player.
+    DisplayInstructions {
+        player: i32,
+        message: String,
+        /// How long to display the message, in seconds.
+        time: i32,
+        /// The instructions panel line.
+        line: i32,
+        sound: Option<String>,
+    },
+    /// Remove an instructions message.
+    ClearInstructions { line: i32 },
+    /// Make objects stand still.
+    FreezeUnit { objects: ObjectSelection },
+    /// Enable the advanced buttons in the user interface.
+    UseAdvancedButtons,
+    /// Damage objects.
+    DamageObject {
+        objects: ObjectSelection,
+        amount: i32,
+    },
+    /// Place a building foundation.
+    PlaceFoundation {
+        player: i32,
+        unit_type: UnitTypeID,
+        location: Location,
+    },
+    /// Rename objects.
+    ChangeObjectName {
+        objects: ObjectSelection,
+        name: String,
+    },
+    /// Change the hit points of objects.
+    ChangeObjectHp {
+        objects: ObjectSelection,
+        amount: i32,
+    },
+    /// Change the attack of objects.
+    ChangeObjectAttack {
+        objects: ObjectSelection,
+        amount: i32,
+    },
+    /// Stop objects.
+    StopUnit { objects: ObjectSelection },
+    /// An effect type that is not known to this crate.
+    Unknown(TriggerEffect),
+}
+
+impl TriggerEffect {
+    /// Create an empty trigger effect of the given type. All properties are unset.
+    fn with_type(effect_type: i32) -> Self {
+        Self {
+            effect_type,
+            properties: vec![-1; 24],
+            chat_text: None,
+            audio_file: None,
+            objects: vec![],
+        }
+    }
+
+    /// Get the type of this trigger effect.
+    pub fn effect_type(&self) -> i32 {
+        self.effect_type
+    }
+
+    /// Get the type of this trigger effect as an [`EffectType`].
+    pub fn kind(&self) -> EffectType {
+        EffectType::from(self.effect_type)
+    }
+
+    /// Get the chat or instructions text of this trigger effect.
+    pub fn chat_text(&self) -> Option<&str> {
+        self.chat_text.as_deref()
+    }
+
+    /// Get the sound file name of this trigger effect.
+    pub fn audio_file(&self) -> Option<&str> {
+        self.audio_file.as_deref()
+    }
+
+    /// Get the IDs of the objects this trigger effect applies to.
+    pub fn objects(&self) -> &[i32] {
+        &self.objects
+    }
+
+    /// Get the IDs of the objects this trigger effect applies to, mutably.
+    pub(crate) fn objects_mut(&mut self) -> &mut [i32] {
+        &mut self.objects
+    }
+
+    fn property(&self, index: usize) -> i32 {
+        self.properties.get(index).copied().unwrap_or(-1)
+    }
+
+    fn location_value(&self) -> Location {
+        let (x, y) = self.location();
+        Location { x, y }
+    }
+
+    fn selection(&self) -> ObjectSelection {
+        ObjectSelection {
+            player: self.property(7),
+            objects: self.objects.clone(),
+            filter: ObjectFilter {
+                unit_type: UnitTypeID::try_from(self.property(6)).ok(),
+                group: Some(self.property(20)).filter(|&group| group != -1),
+                object_type: Some(self.property(21)).filter(|&ty| ty != -1),
+                area: Area::from_opt_tuple(self.area()),
+            },
+        }
+    }
+
+    fn set_selection(&mut self, selection: &ObjectSelection) -> Result<()> {
+        self.properties[7] = selection.player;
+        self.objects = selection.objects.clone();
+        self.properties[4] = item_id(self.objects.len())?;
+        self.properties[6] = selection.filter.unit_type.map_or(-1, i32::from);
+        self.properties[20] = selection.filter.group.unwrap_or(-1);
+        self.properties[21] = selection.filter.object_type.unwrap_or(-1);
+        self.set_area(selection.filter.area.unwrap_or_else(Area::none).to_tuple());
+        Ok(())
+    }
+
+    /// Decode this trigger effect into an [`Effect`].
+    pub fn decode(&self) -> Effect {
+        let player = self.property(7);
+        let target_player = self.property(8);
+        let amount = self.property(1);
+        let text = || self.chat_text.clone().unwrap_or_default();
+        let unit_type = UnitTypeID::try_from(self.property(6)).ok();
+        match (self.kind(), unit_type) {
+            (EffectType::ChangeDiplomacy, _) => {
+                match DiplomaticStance::try_from(self.property(3)) {
+                    Ok(stance) => Effect::ChangeDiplomacy {
+                        player,
+                        target_player,
+                        stance,
+                    },
+                    Err(_) => Effect::Unknown(self.clone()),
+                }
+            }
+            (EffectType::ResearchTechnology, _) => Effect::ResearchTechnology {
+                player,
+                tech: self.property(9),
+            },
+            (EffectType::SendChat, _) => Effect::SendChat {
+                player,
+                message: text(),
+                sound: self.audio_file.clone(),
+            },
+            (EffectType::PlaySound, _) => Effect::PlaySound {
+                player,
+                sound: self.audio_file.clone().unwrap_or_default(),
+            },
+            (EffectType::SendTribute, _) => Effect::SendTribute {
+                player,
+                target_player,
+                resource: self.property(2),
+                amount,
+            },
+            (EffectType::UnlockGate, _) => Effect::UnlockGate {
+                objects: self.selection(),
+            },
+            (EffectType::LockGate, _) => Effect::LockGate {
+                objects: self.selection(),
+            },
+            (EffectType::ActivateTrigger, _) => Effect::ActivateTrigger(self.property(13)),
+            (EffectType::DeactivateTrigger, _) => Effect::DeactivateTrigger(self.property(13)),
+            (EffectType::AIScriptGoal, _) => Effect::AIScriptGoal(self.property(0)),
+            (EffectType::CreateObject, Some(unit_type)) => Effect::CreateObject {
+                player,
+                unit_type,
+                location: self.location_value(),
+            },
+            (EffectType::TaskObject, _) => Effect::TaskObject {
+                objects: self.selection(),
+                location: self.location_value(),
+                target: Some(self.property(5)).filter(|&id| id != -1),
+            },
+            (EffectType::DeclareVictory, _) => Effect::DeclareVictory { player },
+            (EffectType::KillObject, _) => Effect::KillObject {
+                objects: self.selection(),
+            },
+            (EffectType::RemoveObject, _) => Effect::RemoveObject {
+                objects: self.selection(),
+            },
+            (EffectType::ChangeView, _) => Effect::ChangeView {
+                player,
+                location: self.location_value(),
+            },
+            (EffectType::Unload, _) => Effect::Unload {
+                objects: self.selection(),
+                location: self.location_value(),
+            },
+            (EffectType::ChangeOwnership, _) => Effect::ChangeOwnership {
+                objects: self.selection(),
+                target_player,
+            },
+            (EffectType::Patrol, _) => Effect::Patrol {
+                objects: self.selection(),
+                location: self.location_value(),
+            },
+            (EffectType::DisplayInstructions, _) => Effect::DisplayInstructions {
+                player,
+                message: text(),
+                time: self.property(12),
+                line: self.property(22),
+                sound: self.audio_file.clone(),
+            },
+            (EffectType::ClearInstructions, _) => Effect::ClearInstructions {
+                line: self.property(22),
+            },
+            (EffectType::FreezeUnit, _) => Effect::FreezeUnit {
+                objects: self.selection(),
+            },
+            (EffectType::UseAdvancedButtons, _) => Effect::UseAdvancedButtons,
+            (EffectType::DamageObject, _) => Effect::DamageObject {
+                objects: self.selection(),
+                amount,
+            },
+            (EffectType::PlaceFoundation, Some(unit_type)) => Effect::PlaceFoundation {
+                player,
+                unit_type,
+                location: self.location_value(),
+            },
+            (EffectType::ChangeObjectName, _) => Effect::ChangeObjectName {
+                objects: self.selection(),
+                name: text(),
+            },
+            (EffectType::ChangeObjectHp, _) => Effect::ChangeObjectHp {
+                objects: self.selection(),
+                amount,
+            },
+            (EffectType::ChangeObjectAttack, _) => Effect::ChangeObjectAttack {
+                objects: self.selection(),
+                amount,
+            },
+            (EffectType::StopUnit, _) => Effect::StopUnit {
+                objects: self.selection(),
+            },
+            _ => Effect::Unknown(self.clone()),
+        }
+    }
+}
+
+impl Effect {
+    /// Get the raw type ID of this effect.
+    pub fn effect_type(&self) -> i32 {
+        self.kind().into()
+    }
+
+    /// Get the type of this effect.
+    pub fn kind(&self) -> EffectType {
+        match self {
+            Self::ChangeDiplomacy { .. } => EffectType::ChangeDiplomacy,
+            Self::ResearchTechnology { .. } => EffectType::ResearchTechnology,
+            Self::SendChat { .. } => EffectType::SendChat,
+            Self::PlaySound { .. } => EffectType::PlaySound,
+            Self::SendTribute { .. } => EffectType::SendTribute,
+            Self::UnlockGate { .. } => EffectType::UnlockGate,
+            Self::LockGate { .. } => EffectType::LockGate,
+            Self::ActivateTrigger(_) => EffectType::ActivateTrigger,
+            Self::DeactivateTrigger(_) => EffectType::DeactivateTrigger,
+            Self::AIScriptGoal(_) => EffectType::AIScriptGoal,
+            Self::CreateObject { .. } => EffectType::CreateObject,
+            Self::TaskObject { .. } => EffectType::TaskObject,
+            Self::DeclareVictory { .. } => EffectType::DeclareVictory,
+            Self::KillObject { .. } => EffectType::KillObject,
+            Self::RemoveObject { .. } => EffectType::RemoveObject,
+            Self::ChangeView { .. } => EffectType::ChangeView,
+            Self::Unload { .. } => EffectType::Unload,
+            Self::ChangeOwnership { .. } => EffectType::ChangeOwnership,
+            Self::Patrol { .. } => EffectType::Patrol,
+            Self::DisplayInstructions { .. } => EffectType::DisplayInstructions,
+            Self::ClearInstructions { .. } => EffectType::ClearInstructions,
+            Self::FreezeUnit { .. } => EffectType::FreezeUnit,
+            Self::UseAdvancedButtons => EffectType::UseAdvancedButtons,
+            Self::DamageObject { .. } => EffectType::DamageObject,
+            Self::PlaceFoundation { .. } => EffectType::PlaceFoundation,
+            Self::ChangeObjectName { .. } => EffectType::ChangeObjectName,
+            Self::ChangeObjectHp { .. } => EffectType::ChangeObjectHp,
+            Self::ChangeObjectAttack { .. } => EffectType::ChangeObjectAttack,
+            Self::StopUnit { .. } => EffectType::StopUnit,
+            Self::Unknown(effect) => effect.kind(),
+        }
+    }
+
+    /// Encode this effect as a [`TriggerEffect`]. Properties that are not used by the effect are
+    /// unset.
+    ///
+    /// Returns [`Error::TooManyTriggerItemsError`] if the effect selects more than `i32::MAX`
+    /// objects.
+    pub fn encode(&self) -> Result<TriggerEffect> {
+        let mut effect = TriggerEffect::with_type(self.effect_type());
+        effect.set_num_objects(0);
+        match self {
+            Self::Unknown(effect) => return Ok(effect.clone()),
+            Self::ChangeDiplomacy {
+                player,
+                target_player,
+                stance,
+            } => {
+                effect.set_source_player_id(*player);
+                effect.set_target_player_id(*target_player);
+                effect.set_diplomacy((*stance).into());
+            }
+            Self::ResearchTechnology { player, tech } => {
+                effect.set_source_player_id(*player);
+                effect.set_technology_id(*tech);
+            }
+            Self::SendChat {
+                player,
+                message,
+                sound,
+            } => {
+                effect.set_source_player_id(*player);
+                effect.chat_text = Some(message.clone());
+                effect.audio_file = sound.clone();
+            }
+            Self::PlaySound { player, sound } => {
+                effect.set_source_player_id(*player);
+                effect.audio_file = Some(sound.clone());
+            }
+            Self::SendTribute {
+                player,
+                target_player,
+                resource,
+                amount,
+            } => {
+                effect.set_source_player_id(*player);
+                effect.set_target_player_id(*target_player);
+                effect.set_resource(*resource);
+                effect.set_amount(*amount);
+            }
+            Self::UnlockGate { objects }
+            | Self::LockGate { objects }
+            | Self::KillObject { objects }
+            | Self::RemoveObject { objects }
+            | Self::FreezeUnit { objects }
+            | Self::StopUnit { objects } => effect.set_selection(objects)?,
+            Self::ActivateTrigger(trigger) | Self::DeactivateTrigger(trigger) => {
+                effect.set_trigger_id(*trigger);
+            }
+            Self::AIScriptGoal(goal) => effect.set_ai_goal(*goal),
+            Self::CreateObject {
+                player,
+                unit_type,
+                location,
+            }
+            | Self::PlaceFoundation {
+                player,
+                unit_type,
+                location,
+            } => {
+                effect.set_source_player_id(*player);
+                effect.set_unit_type(*unit_type);
+                effect.set_location((location.x, location.y));
+            }
+            Self::TaskObject {
+                objects,
+                location,
+                target,
+            } => {
+                effect.set_selection(objects)?;
+                effect.set_location((location.x, location.y));
+                effect.set_object_id(target.unwrap_or(-1));
+            }
+            Self::DeclareVictory { player } => effect.set_source_player_id(*player),
+            Self::ChangeView { player, location } => {
+                effect.set_source_player_id(*player);
+                effect.set_location((location.x, location.y));
+            }
+            Self::Unload { objects, location } | Self::Patrol { objects, location } => {
+                effect.set_selection(objects)?;
+                effect.set_location((location.x, location.y));
+            }
+            Self::ChangeOwnership {
+                objects,
+                target_player,
+            } => {
+                effect.set_selection(objects)?;
+                effect.set_target_player_id(*target_player);
+            }
+            Self::DisplayInstructions {
+                player,
+                message,
+                time,
+                line,
+                sound,
+            } => {
+                effect.set_source_player_id(*player);
+                effect.chat_text = Some(message.clone());
+                effect.set_timer(*time);
+                effect.set_line_id(*line);
+                effect.audio_file = sound.clone();
+            }
+            Self::ClearInstructions { line } => effect.set_line_id(*line),
+            Self::UseAdvancedButtons => (),
+            Self::DamageObject { objects, amount }
+            | Self::ChangeObjectHp { objects, amount }
+            | Self::ChangeObjectAttack { objects, amount } => {
+                effect.set_selection(objects)?;
+                effect.set_amount(*amount);
+            }
+            Self::ChangeObjectName { objects, name } => {
+                effect.set_selection(objects)?;
+                effect.chat_text = Some(name.clone());
+            }
+        }
+        Ok(effect)
+    }
+}
+
+impl TryFrom<Effect> for TriggerEffect {
+    type Error = Error;
+
+    fn try_from(effect: Effect) -> Result<Self> {
+        effect.encode()
+    }
+}
+
+genie_support::impl_versioned! {
+    TriggerCondition: f64 => crate::Error;
+    TriggerEffect: f64 => crate::Error;
+    Trigger: f64 => crate::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scenario;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn decode_triggers() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let scen = Scenario::read_from(&mut f)?;
+        let triggers = scen.triggers().unwrap();
+        assert!(triggers.num_triggers() > 0);
+        let mut known = 0;
+        for trigger in triggers.triggers() {
+            for condition in trigger.conditions() {
+                let decoded = condition.decode();
+                assert_eq!(decoded.encode().decode(), decoded);
+                assert_eq!(decoded.condition_type(), condition.condition_type());
+                assert_eq!(decoded.kind(), condition.kind());
+            }
+            for effect in trigger.effects() {
+                let decoded = effect.decode();
+                assert_eq!(decoded.encode()?.decode(), decoded);
+                assert_eq!(decoded.effect_type(), effect.effect_type());
+                assert_eq!(decoded.kind(), effect.kind());
+                if !matches!(decoded, Effect::Unknown(_)) {
+                    known += 1;
+                }
+            }
+        }
+        assert!(known > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn type_ids() {
+        for n in -1..40 {
+            assert_eq!(i32::from(ConditionType::from(n)), n);
+            assert_eq!(i32::from(EffectType::from(n)), n);
+        }
+        assert_eq!(ConditionType::from(10), ConditionType::Timer);
+        assert_eq!(ConditionType::from(20), ConditionType::Unknown(20));
+        assert_eq!(EffectType::from(29), EffectType::StopUnit);
+        assert_eq!(Condition::Timer(30).kind(), ConditionType::Timer);
+        assert_eq!(
+            Effect::ActivateTrigger(0).encode().unwrap().kind(),
+            EffectType::ActivateTrigger
+        );
+    }
+
+    #[test]
+    fn too_many_items() {
+        assert_eq!(item_id(3).unwrap(), 3);
+        let len = i32::MAX as usize + 1;
+        assert!(matches!(
+            item_id(len),
+            Err(Error::TooManyTriggerItemsError(n)) if n == len
+        ));
+    }
+
+    #[test]
+    fn build_trigger() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+
+        let conditions = vec![
+            Condition::Timer(30),
+            Condition::OwnObjects {
+                player: 1,
+                amount: 10,
+                filter: ObjectFilter {
+                    unit_type: Some(UnitTypeID::from(83)),
+                    ..Default::default()
+                },
+            },
+        ];
+        let effects = vec![
+            Effect::SendChat {
+                player: 1,
+                message: "Reinforcements have arrived".to_string(),
+                sound: None,
+            },
+            Effect::CreateObject {
+                player: 1,
+                unit_type: UnitTypeID::from(4),
+                location: Location { x: 10, y: 12 },
+            },
+            Effect::KillObject {
+                objects: ObjectSelection {
+                    player: 2,
+                    objects: vec![100, 101],
+                    filter: ObjectFilter {
+                        area: Some(Area {
+                            x1: 0,
+                            y1: 0,
+                            x2: 20,
+                            y2: 20,
+                        }),
+                        ..Default::default()
+                    },
+                },
+            },
+        ];
+        let mut trigger = Trigger::new("Reinforcements").looping(true);
+        for condition in &conditions {
+            trigger = trigger.condition(condition.clone())?;
+        }
+        for effect in &effects {
+            trigger = trigger.effect(effect.clone())?;
+        }
+        let triggers = scen.triggers_mut().unwrap();
+        let id = triggers.add_trigger(trigger)?;
+        assert_eq!(id as u32, triggers.num_triggers() - 1);
+
+        let mut bytes = vec![];
+        scen.write_to(&mut bytes)?;
+        let scen = Scenario::read_from(Cursor::new(bytes))?;
+        let trigger = scen
+            .triggers()
+            .unwrap()
+            .triggers()
+            .find(|trigger| trigger.name() == Some("Reinforcements"))
+            .unwrap();
+        assert!(trigger.is_enabled());
+        assert!(trigger.is_looping());
+        let read_conditions: Vec<_> = trigger.conditions().map(TriggerCondition::decode).collect();
+        assert_eq!(read_conditions, conditions);
+        let read_effects: Vec<_> = trigger.effects().map(TriggerEffect::decode).collect();
+        assert_eq!(read_effects, effects);
+
+        // Unset unit types are read as -1.
+        let send_chat = trigger.effects().next().unwrap();
+        assert_eq!(send_chat.raw_unit_type(), -1);
+        assert_eq!(send_chat.unit_type(), None);
+        let create_object = trigger.effects().nth(1).unwrap();
+        assert_eq!(create_object.unit_type(), Some(UnitTypeID::from(4)));
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 //! Contains pure types, no IO.
 //!
 //! Most of these are more descriptive wrappers around integers.
+use genie_support::{f32_eq, GameRelease};
 use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt::{self, Debug, Display};
@@ -45,6 +46,28 @@ impl Display for SCXVersion {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for SCXVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SCXVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        <[u8; 4]>::try_from(version.as_bytes())
+            .map(Self)
+            .map_err(|_| {
+                serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Str(&version),
+                    &"a format version like \"1.21\"",
+                )
+            })
+    }
+}
+
 impl PartialEq<[u8; 4]> for SCXVersion {
     fn eq(&self, other: &[u8; 4]) -> bool {
         other[0] == self.0[0] && other[1] == b'.' && other[2] == self.0[2] && other[3] == self.0[3]
@@ -84,6 +107,7 @@ pub struct ParseDiplomaticStanceError(i32);
 
 /// A player's diplomatic stance toward another player.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiplomaticStance {
     /// The other player is an ally.
     Ally = 0,
@@ -123,6 +147,7 @@ pub struct ParseDataSetError(i32);
 
 /// The data set used by a scenario, HD Edition only.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataSet {
     /// The "base" data set, containing Age of Kings and the Age of Conquerors expansion.
     BaseGame,
@@ -157,6 +182,7 @@ pub struct ParseDLCPackageError(i32);
 
 /// An HD Edition DLC identifier.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DLCPackage {
     /// The Age of Kings base game.
     AgeOfKings,
@@ -218,6 +244,7 @@ pub struct ParseStartingAgeError {
 
 /// The starting age.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StartingAge {
     /// Use the game default.
     Default = -1,
@@ -290,6 +317,7 @@ impl StartingAge {
 
 /// Known types of victory conditions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VictoryCondition {
     /// Capture an object.
     Capture,
@@ -360,8 +388,80 @@ impl From<VictoryCondition> for u8 {
     }
 }
 
+/// The victory setting of a scenario, shown as "Global Victory" in the scenario editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VictoryMode {
+    /// Win by conquest, by collecting all relics, or by building a wonder.
+    Standard,
+    /// Win by destroying all other players.
+    Conquest,
+    /// The player with the highest score wins when the time limit is reached.
+    TimeLimit,
+    /// The first player to reach the required score wins.
+    Score,
+    /// Win by meeting the custom victory conditions.
+    Custom,
+    /// Some unknown value, either because of a corrupt scenario file or some new unsupported
+    /// game feature.
+    Other(i32),
+}
+
+impl From<i32> for VictoryMode {
+    fn from(n: i32) -> Self {
+        match n {
+            0 => VictoryMode::Standard,
+            1 => VictoryMode::Conquest,
+            2 => VictoryMode::TimeLimit,
+            3 => VictoryMode::Score,
+            4 => VictoryMode::Custom,
+            n => VictoryMode::Other(n),
+        }
+    }
+}
+
+impl From<VictoryMode> for i32 {
+    fn from(mode: VictoryMode) -> Self {
+        match mode {
+            VictoryMode::Standard => 0,
+            VictoryMode::Conquest => 1,
+            VictoryMode::TimeLimit => 2,
+            VictoryMode::Score => 3,
+            VictoryMode::Custom => 4,
+            VictoryMode::Other(n) => n,
+        }
+    }
+}
+
+/// Check if a format version and data version are used by Star Wars: Galactic Battlegrounds.
+///
+/// SWGB uses the AoC format version with data version 1.30, and the Clone Campaigns expansion uses
+/// data version 1.32. Later Definitive Edition releases use those data versions too, but with a
+/// newer format version.
+pub(crate) fn is_swgb_version(format: SCXVersion, data_version: f32) -> bool {
+    format == *b"1.21" && (f32_eq!(data_version, 1.30) || f32_eq!(data_version, 1.32))
+}
+
+/// How the data in a scenario file is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SCXCompression {
+    /// The data is not compressed. The games can not read uncompressed scenarios, but they are
+    /// easier to inspect when debugging.
+    Uncompressed,
+    /// The data is compressed with raw deflate, like the games do. When writing, the level ranges
+    /// from 0 (no compression) to 9 (best compression); it is ignored when reading.
+    Deflate { level: u32 },
+}
+
+impl Default for SCXCompression {
+    fn default() -> Self {
+        Self::Deflate { level: 6 }
+    }
+}
+
 /// All the versions an SCX file uses in a single struct.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VersionBundle {
     /// The version of the 'container' file format.
     pub format: SCXVersion,
@@ -384,7 +484,10 @@ pub struct VersionBundle {
 impl VersionBundle {
     /// A version bundle with the parameters AoE1 uses by default.
     pub fn aoe() -> Self {
-        unimplemented!()
+        Self {
+            format: SCXVersion(*b"1.10"),
+            ..Self::ror()
+        }
     }
 
     /// A version bundle with the parameters AoE1: Rise of Rome uses by default.
@@ -395,7 +498,7 @@ impl VersionBundle {
             dlc_options: None,
             data: 1.15,
             picture: 1,
-            victory: 2.0,
+            victory: 1.0,
             triggers: None,
             map: 0,
         }
@@ -453,6 +556,23 @@ impl VersionBundle {
         }
     }
 
+    /// A version bundle with the parameters Star Wars: Galactic Battlegrounds uses by default.
+    pub fn swgb() -> Self {
+        Self {
+            data: 1.30,
+            ..Self::aoc()
+        }
+    }
+
+    /// A version bundle with the parameters Star Wars: Galactic Battlegrounds: Clone Campaigns
+    /// uses by default.
+    pub fn swgb_cc() -> Self {
+        Self {
+            data: 1.32,
+            ..Self::aoc()
+        }
+    }
+
     /// A version bundle with parameters Age of Empires 2: Definitive Edition uses by default.
     ///
     /// This will be updated along with DE2 patches.
@@ -469,6 +589,11 @@ impl VersionBundle {
         }
     }
 
+    /// Returns whether this version is for an AoE1 or Rise of Rome scenario.
+    pub fn is_aoe1(&self) -> bool {
+        self.format <= SCXVersion(*b"1.11")
+    }
+
     /// Returns whether this version is (likely) for an AoK scenario.
     pub fn is_aok(&self) -> bool {
         matches!(self.format.as_bytes(), b"1.18" | b"1.19" | b"1.20")
@@ -481,11 +606,39 @@ impl VersionBundle {
 
     /// Returns whether this version is (likely) for an HD Edition scenario.
     pub fn is_hd_edition(&self) -> bool {
-        self.format == *b"1.21" || self.format == *b"1.22" && self.data > 1.22
+        !self.is_swgb() && (self.format == *b"1.21" || self.format == *b"1.22" && self.data > 1.22)
     }
 
     /// Returns whether this version is (likely) for an AoE2: Definitive Edition scenario.
     pub fn is_age2_de(&self) -> bool {
-        self.data >= 1.28
+        !self.is_swgb() && self.data >= 1.28
+    }
+
+    /// Returns whether this version is for a Star Wars: Galactic Battlegrounds scenario, with or
+    /// without the Clone Campaigns expansion.
+    pub fn is_swgb(&self) -> bool {
+        is_swgb_version(self.format, self.data)
+    }
+
+    /// Returns the Age of Empires 2 release that this version (likely) belongs to, to look up
+    /// which features the scenario can use. Returns `None` for AoE1 and Star Wars: Galactic
+    /// Battlegrounds scenarios, which have an entirely different set of units and terrains.
+    ///
+    /// AoC and UserPatch scenarios can not be told apart, so AoC scenarios return
+    /// [`GameRelease::UserPatch15`], which supports every AoC feature.
+    pub fn release(&self) -> Option<GameRelease> {
+        if self.is_aoe1() || self.is_swgb() {
+            None
+        } else if self.is_age2_de() {
+            Some(GameRelease::DE)
+        } else if self.is_aok() {
+            Some(GameRelease::AoK)
+        } else if self.is_aoc() {
+            Some(GameRelease::UserPatch15)
+        } else if self.is_hd_edition() {
+            Some(GameRelease::HD)
+        } else {
+            None
+        }
     }
 }
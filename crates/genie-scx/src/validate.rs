@@ -0,0 +1,365 @@
+//! Consistency checks for scenarios.
+
+use crate::{Scenario, TriggerSystem};
+use genie_support::GameRelease;
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryFrom;
+
+/// A trigger condition or effect, by its index in the trigger, unordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerPart {
+    Condition(usize),
+    Effect(usize),
+}
+
+/// A problem found in a scenario by [`Scenario::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// A trigger condition or effect refers to an object that is not placed in the scenario,
+    /// for example because it was deleted.
+    MissingObject {
+        /// The ID of the trigger.
+        trigger: usize,
+        /// The condition or effect containing the reference.
+        part: TriggerPart,
+        /// The ID of the missing object.
+        object: i32,
+    },
+    /// A trigger condition or effect refers to a player that is not active in the scenario.
+    MissingPlayer {
+        /// The ID of the trigger.
+        trigger: usize,
+        /// The condition or effect containing the reference.
+        part: TriggerPart,
+        /// The number of the missing player.
+        player: i32,
+    },
+    /// A trigger effect activates or deactivates a trigger that does not exist.
+    MissingTrigger {
+        /// The ID of the trigger.
+        trigger: usize,
+        /// The index of the effect in the trigger, unordered.
+        effect: usize,
+        /// The ID of the missing trigger.
+        target: i32,
+    },
+    /// An object is placed outside the map.
+    ObjectOutOfBounds {
+        /// The ID of the object.
+        object: i32,
+        /// The position of the object.
+        position: (f32, f32, f32),
+    },
+    /// Triggers that activate each other in a cycle. Contains the IDs of the triggers in the
+    /// cycle, in ascending order.
+    TriggerCycle(Vec<usize>),
+    /// A terrain ID that does not exist in the scenario's game version.
+    Terrain(u8),
+}
+
+/// Lists the problems found in a scenario.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Check if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Get the problems that were found.
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+}
+
+/// The "Activate Trigger" effect type.
+const ACTIVATE_TRIGGER: i32 = 8;
+/// The "Deactivate Trigger" effect type.
+const DEACTIVATE_TRIGGER: i32 = 9;
+
+/// Collects validation issues for a scenario.
+struct Validator<'a> {
+    scen: &'a Scenario,
+    object_ids: HashSet<i32>,
+    issues: Vec<ValidationIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn object(&mut self, trigger: usize, part: TriggerPart, object: i32) {
+        if object != -1 && !self.object_ids.contains(&object) {
+            self.issues.push(ValidationIssue::MissingObject {
+                trigger,
+                part,
+                object,
+            });
+        }
+    }
+
+    fn player(&mut self, trigger: usize, part: TriggerPart, player: i32) {
+        let properties = &self.scen.format.tribe_scen.base.player_base_properties;
+        // -1 is unset, and GAIA (0) always exists.
+        let exists = match player {
+            -1 | 0 => true,
+            1..=16 => properties[player as usize - 1].active != 0,
+            _ => false,
+        };
+        if !exists {
+            self.issues.push(ValidationIssue::MissingPlayer {
+                trigger,
+                part,
+                player,
+            });
+        }
+    }
+
+    fn check_triggers(&mut self, triggers: &TriggerSystem) {
+        let num_triggers = triggers.num_triggers() as usize;
+        for (id, trigger) in triggers.triggers_unordered().enumerate() {
+            for (index, cond) in trigger.conditions_unordered().enumerate() {
+                let part = TriggerPart::Condition(index);
+                self.object(id, part, cond.primary_object());
+                self.object(id, part, cond.secondary_object());
+                self.player(id, part, cond.player_id());
+            }
+
+            for (index, effect) in trigger.effects_unordered().enumerate() {
+                let part = TriggerPart::Effect(index);
+                for object in effect.objects() {
+                    self.object(id, part, *object);
+                }
+                self.object(id, part, effect.object_id());
+                self.player(id, part, effect.source_player_id());
+                self.player(id, part, effect.target_player_id());
+
+                let target = effect.trigger_id();
+                let is_trigger_effect =
+                    matches!(effect.effect_type(), ACTIVATE_TRIGGER | DEACTIVATE_TRIGGER);
+                if is_trigger_effect && (target < 0 || target as usize >= num_triggers) {
+                    self.issues.push(ValidationIssue::MissingTrigger {
+                        trigger: id,
+                        effect: index,
+                        target,
+                    });
+                }
+            }
+        }
+
+        self.check_trigger_cycles(triggers);
+    }
+
+    /// Find groups of triggers that activate each other, using Tarjan's strongly connected
+    /// components algorithm.
+    fn check_trigger_cycles(&mut self, triggers: &TriggerSystem) {
+        let num_triggers = triggers.num_triggers() as usize;
+        let activates: Vec<Vec<usize>> = triggers
+            .triggers_unordered()
+            .map(|trigger| {
+                trigger
+                    .effects_unordered()
+                    .filter(|effect| effect.effect_type() == ACTIVATE_TRIGGER)
+                    .filter_map(|effect| usize::try_from(effect.trigger_id()).ok())
+                    .filter(|target| *target < num_triggers)
+                    .collect()
+            })
+            .collect();
+
+        struct Tarjan<'g> {
+            activates: &'g [Vec<usize>],
+            index: Vec<Option<usize>>,
+            lowlink: Vec<usize>,
+            on_stack: Vec<bool>,
+            stack: Vec<usize>,
+            next_index: usize,
+            cycles: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_> {
+            fn visit(&mut self, node: usize) {
+                self.index[node] = Some(self.next_index);
+                self.lowlink[node] = self.next_index;
+                self.next_index += 1;
+                self.stack.push(node);
+                self.on_stack[node] = true;
+
+                for &next in &self.activates[node] {
+                    match self.index[next] {
+                        None => {
+                            self.visit(next);
+                            self.lowlink[node] = self.lowlink[node].min(self.lowlink[next]);
+                        }
+                        Some(index) if self.on_stack[next] => {
+                            self.lowlink[node] = self.lowlink[node].min(index);
+                        }
+                        Some(_) => (),
+                    }
+                }
+
+                if Some(self.lowlink[node]) == self.index[node] {
+                    let mut component = vec![];
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    // A single trigger is only a cycle if it activates itself.
+                    if component.len() > 1 || self.activates[node].contains(&node) {
+                        component.sort_unstable();
+                        self.cycles.push(component);
+                    }
+                }
+            }
+        }
+
+        let mut tarjan = Tarjan {
+            activates: &activates,
+            index: vec![None; num_triggers],
+            lowlink: vec![0; num_triggers],
+            on_stack: vec![false; num_triggers],
+            stack: vec![],
+            next_index: 0,
+            cycles: vec![],
+        };
+        for node in 0..num_triggers {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.cycles.sort();
+        self.issues
+            .extend(tarjan.cycles.into_iter().map(ValidationIssue::TriggerCycle));
+    }
+
+    fn check_objects(&mut self) {
+        let map = self.scen.map();
+        let (width, height) = (map.width() as f32, map.height() as f32);
+        for object in self.scen.objects() {
+            let (x, y, _) = object.position;
+            if !(0.0..width).contains(&x) || !(0.0..height).contains(&y) {
+                self.issues.push(ValidationIssue::ObjectOutOfBounds {
+                    object: object.id,
+                    position: object.position,
+                });
+            }
+        }
+    }
+
+    fn check_terrain(&mut self) {
+        let max_terrain = self
+            .scen
+            .version()
+            .release()
+            .and_then(GameRelease::max_terrain);
+        if let Some(max) = max_terrain {
+            let invalid: BTreeSet<u8> = self
+                .scen
+                .map()
+                .tiles()
+                .map(|tile| tile.terrain)
+                .filter(|terrain| *terrain > max)
+                .collect();
+            self.issues
+                .extend(invalid.into_iter().map(ValidationIssue::Terrain));
+        }
+    }
+
+    fn check(mut self) -> ValidationReport {
+        if let Some(triggers) = self.scen.triggers() {
+            self.check_triggers(triggers);
+        }
+        self.check_objects();
+        self.check_terrain();
+        ValidationReport {
+            issues: self.issues,
+        }
+    }
+}
+
+impl Scenario {
+    /// Check the scenario for triggers that refer to missing objects, players or triggers,
+    /// triggers that activate each other in a cycle, objects placed outside the map, and terrain
+    /// IDs that do not exist in the scenario's game version.
+    ///
+    /// Terrain IDs are only checked for Age of Kings and The Conquerors scenarios.
+    pub fn validate(&self) -> ValidationReport {
+        Validator {
+            scen: self,
+            object_ids: self.objects().map(|object| object.id).collect(),
+            issues: vec![],
+        }
+        .check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Effect, Location, ObjectSelection, Trigger};
+    use std::fs::File;
+
+    #[test]
+    fn detect_issues() -> anyhow::Result<()> {
+        let mut f = File::open("test/scenarios/Age of Heroes b1-3-5.scx")?;
+        let mut scen = Scenario::read_from(&mut f)?;
+        let num_issues = scen.validate().issues().len();
+
+        let archer = scen.add_object(1, 4.into(), (20.5, 20.5, 0.0))?;
+        let outside = scen.add_object(1, 4.into(), (-1.0, 20.5, 0.0))?;
+        scen.map_mut().tile_mut(0, 0).unwrap().terrain = 50;
+
+        let triggers = scen.triggers_mut().unwrap();
+        let first = triggers.num_triggers() as i32;
+        let kill = Trigger::new("Kill").effect(Effect::KillObject {
+            objects: ObjectSelection {
+                player: 1,
+                objects: vec![archer],
+                ..Default::default()
+            },
+        })?;
+        let kill = triggers.add_trigger(kill.effect(Effect::ActivateTrigger(first + 1))?)? as usize;
+        let view = Trigger::new("View")
+            .effect(Effect::ChangeView {
+                player: 9,
+                location: Location { x: 0, y: 0 },
+            })?
+            .effect(Effect::ActivateTrigger(first))?
+            .effect(Effect::DeactivateTrigger(first + 10))?;
+        let view = triggers.add_trigger(view)? as usize;
+        scen.remove_object(archer)?;
+
+        let report = scen.validate();
+        assert!(!report.is_valid());
+        let expected = [
+            ValidationIssue::MissingObject {
+                trigger: kill,
+                part: TriggerPart::Effect(0),
+                object: archer,
+            },
+            ValidationIssue::MissingPlayer {
+                trigger: view,
+                part: TriggerPart::Effect(0),
+                player: 9,
+            },
+            ValidationIssue::MissingTrigger {
+                trigger: view,
+                effect: 2,
+                target: first + 10,
+            },
+            ValidationIssue::TriggerCycle(vec![kill, view]),
+            ValidationIssue::ObjectOutOfBounds {
+                object: outside,
+                position: (-1.0, 20.5, 0.0),
+            },
+            ValidationIssue::Terrain(50),
+        ];
+        assert_eq!(report.issues().len(), num_issues + expected.len());
+        for issue in &expected {
+            assert!(report.issues().contains(issue), "missing {:?}", issue);
+        }
+        Ok(())
+    }
+}
@@ -9,6 +9,7 @@ use std::io::{Read, Write};
 ///
 /// This was replaced by VictoryConditions in AoE2.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LegacyVictoryInfo {
     pub object_type: i32,
     pub all_flag: bool,
@@ -80,6 +81,7 @@ impl LegacyVictoryInfo {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VictoryEntry {
     command: VictoryCondition,
     object_type: i32,
@@ -152,6 +154,7 @@ impl VictoryEntry {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VictoryPointEntry {
     command: i8,
     state: i8,
@@ -218,10 +221,12 @@ impl VictoryPointEntry {
 }
 
 /// Current achieved-ness state of a victory condition.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum VictoryState {
     /// The condition is not yet achieved, but may be achieved in the future.
+    #[default]
     NotAchieved = 0,
     /// The condition can no longer be achieved.
     ///
@@ -236,14 +241,9 @@ pub enum VictoryState {
     Disabled = 3,
 }
 
-impl Default for VictoryState {
-    fn default() -> Self {
-        Self::NotAchieved
-    }
-}
-
 /// Tracks victory conditions for the scenario, used in single player.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VictoryConditions {
     /// Version of the victory condition data.
     pub version: f32,
@@ -319,7 +319,7 @@ impl VictoryConditions {
             output.write_f32::<LE>(v)?;
         }
 
-        let version = version.unwrap_or(std::f32::MIN);
+        let version = version.unwrap_or(f32::MIN);
 
         output.write_i32::<LE>(self.entries.len() as i32)?;
         output.write_u8(self.victory.into())?;
@@ -346,7 +346,11 @@ impl VictoryConditions {
     }
 }
 
+/// Custom victory conditions, used when the victory mode is [`VictoryMode::Custom`].
+///
+/// [`VictoryMode::Custom`]: crate::VictoryMode::Custom
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VictoryInfo {
     /// Is conquest victory enabled?
     pub(crate) conquest: bool,
@@ -362,6 +366,72 @@ pub struct VictoryInfo {
 }
 
 impl VictoryInfo {
+    /// Check if destroying all other players is a victory condition.
+    pub fn conquest(&self) -> bool {
+        self.conquest
+    }
+
+    /// Set whether destroying all other players is a victory condition.
+    pub fn set_conquest(&mut self, conquest: bool) {
+        self.conquest = conquest;
+    }
+
+    /// Get the number of relics that must be collected, or 0 if this is not a victory condition.
+    pub fn relics(&self) -> i32 {
+        self.relics
+    }
+
+    /// Set the number of relics that must be collected, or 0 to disable this victory condition.
+    pub fn set_relics(&mut self, relics: i32) {
+        self.relics = relics;
+    }
+
+    /// Get the percentage of the map that must be explored, or 0 if this is not a victory
+    /// condition.
+    pub fn exploration(&self) -> i32 {
+        self.exploration
+    }
+
+    /// Set the percentage of the map that must be explored, or 0 to disable this victory
+    /// condition.
+    pub fn set_exploration(&mut self, exploration: i32) {
+        self.exploration = exploration;
+    }
+
+    /// Get the number of ruins that must be captured (AoE1), or 0 if this is not a victory
+    /// condition.
+    pub fn ruins(&self) -> i32 {
+        self.ruins
+    }
+
+    /// Set the number of ruins that must be captured (AoE1), or 0 to disable this victory
+    /// condition.
+    pub fn set_ruins(&mut self, ruins: i32) {
+        self.ruins = ruins;
+    }
+
+    /// Get the number of discoveries that must be made (AoE1), or 0 if this is not a victory
+    /// condition.
+    pub fn discoveries(&self) -> i32 {
+        self.discoveries
+    }
+
+    /// Set the number of discoveries that must be made (AoE1), or 0 to disable this victory
+    /// condition.
+    pub fn set_discoveries(&mut self, discoveries: i32) {
+        self.discoveries = discoveries;
+    }
+
+    /// Get the amount of gold that must be collected, or 0 if this is not a victory condition.
+    pub fn gold(&self) -> i32 {
+        self.gold
+    }
+
+    /// Set the amount of gold that must be collected, or 0 to disable this victory condition.
+    pub fn set_gold(&mut self, gold: i32) {
+        self.gold = gold;
+    }
+
     pub fn read_from(mut input: impl Read) -> Result<Self> {
         Ok(Self {
             conquest: input.read_i32::<LE>()? != 0,
@@ -384,3 +454,10 @@ impl VictoryInfo {
         Ok(())
     }
 }
+
+genie_support::impl_versioned! {
+    LegacyVictoryInfo => crate::Error;
+    VictoryEntry => crate::Error;
+    VictoryPointEntry: f32 => crate::Error;
+    VictoryInfo => crate::Error;
+}
@@ -0,0 +1,517 @@
+//! Decoding the drawing commands of SLP frames to pixels.
+
+use crate::player::player_color_index;
+use crate::{Error, Frame, Result, EMPTY_ROW};
+use rgb::{RGB8, RGBA8};
+
+/// A decoded pixel of an SLP frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pixel {
+    /// A pixel that is not drawn.
+    #[default]
+    Transparent,
+    /// A pixel with a palette colour.
+    Color(u8),
+    /// A pixel in the colour of the player that owns the unit. The value is the offset within
+    /// the player's colours.
+    PlayerColor(u8),
+    /// A pixel that darkens the pixel behind it.
+    Shadow,
+    /// A pixel that is only drawn when the unit is behind a building, in the player's colour.
+    PlayerOutline,
+    /// A pixel that is only drawn when the unit is behind a building, in black.
+    Outline,
+}
+
+impl Pixel {
+    /// Get the colour of this pixel, looking up palette indices in `palette`.
+    pub fn to_rgba(self, palette: &[RGB8], options: &DecodeOptions) -> Result<RGBA8> {
+        let lookup = |index: usize| {
+            palette
+                .get(index)
+                .map(|color| RGBA8::new(color.r, color.g, color.b, 255))
+                .ok_or(Error::InvalidPaletteIndexError(index))
+        };
+        match self {
+            Pixel::Transparent => Ok(RGBA8::default()),
+            Pixel::Color(index) => lookup(index.into()),
+            Pixel::PlayerColor(index) => lookup(options.player_color_index(index)),
+            Pixel::Shadow => Ok(options.shadow),
+            Pixel::PlayerOutline if options.outlines => lookup(options.player_color_index(0)),
+            Pixel::Outline if options.outlines => Ok(RGBA8::new(0, 0, 0, 255)),
+            Pixel::PlayerOutline | Pixel::Outline => Ok(RGBA8::default()),
+        }
+    }
+}
+
+/// Options for turning decoded pixels into colours.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    player: u8,
+    shadow: RGBA8,
+    outlines: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            player: 1,
+            shadow: RGBA8::new(0, 0, 0, 128),
+            outlines: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Create the default options: draw player colours for player 1, draw shadows in
+    /// half-transparent black, and do not draw outlines.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the player whose colours are used for player colour pixels, from 1 to 8. In the
+    /// standard palettes, the colours of player `n` start at palette index `16 * n`; see the
+    /// [`player`](crate::player) module.
+    pub fn player(mut self, player: u8) -> Self {
+        self.player = player;
+        self
+    }
+
+    /// Set the colour used for shadow pixels.
+    pub fn shadow(mut self, shadow: RGBA8) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    /// Set whether to draw the outline pixels, that the game only shows when the unit is behind a
+    /// building.
+    pub fn outlines(mut self, outlines: bool) -> Self {
+        self.outlines = outlines;
+        self
+    }
+
+    fn player_color_index(&self, shade: u8) -> usize {
+        player_color_index(self.player, shade)
+    }
+}
+
+/// A frame decoded to separate layers.
+///
+/// The game does not draw shadows and outlines like other pixels: shadows darken whatever is
+/// behind the unit, and outlines are only shown where the unit is hidden behind a building or a
+/// tree. Keeping them apart from the colour pixels lets renderers composite them at draw time.
+/// [`Layers::flatten`] merges them back into a single image, like [`Frame::decode_rgba`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layers {
+    width: u32,
+    height: u32,
+    color: Vec<RGBA8>,
+    shadow: Vec<bool>,
+    outline: Vec<RGBA8>,
+}
+
+impl Layers {
+    /// Get the width of the layers in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the layers in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the palette and player colour pixels, row by row from the top left. Shadow and outline
+    /// pixels are transparent.
+    pub fn color(&self) -> &[RGBA8] {
+        &self.color
+    }
+
+    /// Get the shadow mask: `true` for pixels that darken the pixel behind them.
+    pub fn shadow(&self) -> &[bool] {
+        &self.shadow
+    }
+
+    /// Get the outline pixels, that are only drawn when the unit is behind a building. Player
+    /// outlines are in the player's colour and other outlines are black; all other pixels are
+    /// transparent.
+    pub fn outline(&self) -> &[RGBA8] {
+        &self.outline
+    }
+
+    /// Merge the layers into a single image, using the shadow colour and outline setting in
+    /// `options`. The result is the same as [`Frame::decode_rgba`] with the same options.
+    pub fn flatten(&self, options: &DecodeOptions) -> Vec<RGBA8> {
+        self.color
+            .iter()
+            .zip(&self.shadow)
+            .zip(&self.outline)
+            .map(|((&color, &shadow), &outline)| {
+                if shadow {
+                    options.shadow
+                } else if options.outlines && outline.a != 0 {
+                    outline
+                } else {
+                    color
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reads drawing commands from the file data.
+struct Commands<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Commands<'_> {
+    fn next_byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or(Error::InvalidOffsetError(self.pos))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Read the pixel count of a command that stores it in the upper 4 bits, or in the next byte
+    /// if those are 0.
+    fn small_count(&mut self, command: u8) -> Result<usize> {
+        match command >> 4 {
+            0 => self.next_byte().map(usize::from),
+            count => Ok(count.into()),
+        }
+    }
+
+    /// Read the pixel count of a command that stores the upper 4 bits of it in the command, and
+    /// the lower 8 bits in the next byte.
+    fn large_count(&mut self, command: u8) -> Result<usize> {
+        Ok(usize::from(command & 0xF0) << 4 | usize::from(self.next_byte()?))
+    }
+}
+
+/// Writes decoded pixels into a row.
+struct Row<'a> {
+    pixels: &'a mut [Pixel],
+    x: usize,
+    y: u32,
+}
+
+impl Row<'_> {
+    fn fill(&mut self, pixel: Pixel, count: usize) -> Result<()> {
+        let end = self.x + count;
+        if end > self.pixels.len() {
+            return Err(Error::RowOverflowError(self.y));
+        }
+        for target in &mut self.pixels[self.x..end] {
+            *target = pixel;
+        }
+        self.x = end;
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        commands: &mut Commands<'_>,
+        count: usize,
+        pixel: impl Fn(u8) -> Pixel,
+    ) -> Result<()> {
+        for _ in 0..count {
+            self.fill(pixel(commands.next_byte()?), 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or(Error::InvalidOffsetError(offset))
+}
+
+fn read_u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(Error::InvalidOffsetError(offset))
+}
+
+fn decode_row(commands: &mut Commands<'_>, row: &mut Row<'_>) -> Result<()> {
+    loop {
+        let command = commands.next_byte()?;
+        match command & 0x0F {
+            // Lesser block copy and skip use the upper 6 bits for the count.
+            0x00 | 0x04 | 0x08 | 0x0C => {
+                row.copy(commands, usize::from(command >> 2), Pixel::Color)?;
+            }
+            0x01 | 0x05 | 0x09 | 0x0D => {
+                row.fill(Pixel::Transparent, usize::from(command >> 2))?;
+            }
+            0x02 => {
+                let count = commands.large_count(command)?;
+                row.copy(commands, count, Pixel::Color)?;
+            }
+            0x03 => {
+                let count = commands.large_count(command)?;
+                row.fill(Pixel::Transparent, count)?;
+            }
+            0x06 => {
+                let count = commands.small_count(command)?;
+                row.copy(commands, count, Pixel::PlayerColor)?;
+            }
+            0x07 => {
+                let count = commands.small_count(command)?;
+                row.fill(Pixel::Color(commands.next_byte()?), count)?;
+            }
+            0x0A => {
+                let count = commands.small_count(command)?;
+                row.fill(Pixel::PlayerColor(commands.next_byte()?), count)?;
+            }
+            0x0B => {
+                let count = commands.small_count(command)?;
+                row.fill(Pixel::Shadow, count)?;
+            }
+            0x0E => match command >> 4 {
+                // Rendering hints for mirrored frames, which do not draw anything.
+                0x0..=0x3 => (),
+                0x4 => row.fill(Pixel::PlayerOutline, 1)?,
+                0x5 => {
+                    let count = commands.next_byte()?.into();
+                    row.fill(Pixel::PlayerOutline, count)?;
+                }
+                0x6 => row.fill(Pixel::Outline, 1)?,
+                0x7 => {
+                    let count = commands.next_byte()?.into();
+                    row.fill(Pixel::Outline, count)?;
+                }
+                _ => return Err(Error::UnknownCommandError(command, row.y)),
+            },
+            0x0F => return Ok(()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Frame<'_> {
+    /// Decode the frame to pixels, row by row from the top left.
+    pub fn pixels(&self) -> Result<Vec<Pixel>> {
+        let width = self.width() as usize;
+        let mut pixels = vec![Pixel::Transparent; width * self.height() as usize];
+        if width == 0 {
+            return Ok(pixels);
+        }
+
+        let outline_table = self.info.outline_table_offset as usize;
+        let command_table = self.info.command_table_offset as usize;
+        for (y, row) in pixels.chunks_mut(width).enumerate() {
+            let left = read_u16_at(self.data, outline_table + y * 4)?;
+            if left & EMPTY_ROW != 0 {
+                continue;
+            }
+            let mut commands = Commands {
+                data: self.data,
+                pos: read_u32_at(self.data, command_table + y * 4)? as usize,
+            };
+            let mut row = Row {
+                pixels: row,
+                x: 0,
+                y: y as u32,
+            };
+            row.fill(Pixel::Transparent, left.into())?;
+            decode_row(&mut commands, &mut row)?;
+        }
+        Ok(pixels)
+    }
+
+    /// Decode the frame to RGBA colours, row by row from the top left, looking up palette indices
+    /// in `palette`.
+    pub fn decode_rgba(&self, palette: &[RGB8], options: &DecodeOptions) -> Result<Vec<RGBA8>> {
+        self.pixels()?
+            .into_iter()
+            .map(|pixel| pixel.to_rgba(palette, options))
+            .collect()
+    }
+
+    /// Decode the frame to separate colour, shadow and outline layers, looking up palette indices
+    /// in `palette`. Only the player setting in `options` is used.
+    pub fn decode_layers(&self, palette: &[RGB8], options: &DecodeOptions) -> Result<Layers> {
+        let pixels = self.pixels()?;
+        let mut layers = Layers {
+            width: self.width(),
+            height: self.height(),
+            color: Vec::with_capacity(pixels.len()),
+            shadow: Vec::with_capacity(pixels.len()),
+            outline: Vec::with_capacity(pixels.len()),
+        };
+        let outline_options = options.clone().outlines(true);
+        for pixel in pixels {
+            let (color, outline) = match pixel {
+                Pixel::Shadow => (RGBA8::default(), RGBA8::default()),
+                Pixel::PlayerOutline | Pixel::Outline => {
+                    (RGBA8::default(), pixel.to_rgba(palette, &outline_options)?)
+                }
+                _ => (pixel.to_rgba(palette, options)?, RGBA8::default()),
+            };
+            layers.color.push(color);
+            layers.shadow.push(pixel == Pixel::Shadow);
+            layers.outline.push(outline);
+        }
+        Ok(layers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SLP;
+
+    /// Build an SLP file with a single frame from its outline table and row commands.
+    fn build_slp(width: u32, outlines: &[(u16, u16)], rows: &[&[u8]]) -> Vec<u8> {
+        let height = rows.len() as u32;
+        let outline_table = 64;
+        let command_table = outline_table + 4 * height;
+        let mut data = vec![];
+        data.extend_from_slice(b"2.0N");
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(b"test\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        for value in &[command_table, outline_table, 0, 0, width, height, 2, 3] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for (left, right) in outlines {
+            data.extend_from_slice(&left.to_le_bytes());
+            data.extend_from_slice(&right.to_le_bytes());
+        }
+        let mut offset = command_table + 4 * height;
+        for row in rows {
+            data.extend_from_slice(&offset.to_le_bytes());
+            offset += row.len() as u32;
+        }
+        for row in rows {
+            data.extend_from_slice(row);
+        }
+        data
+    }
+
+    #[test]
+    fn decode_commands() -> anyhow::Result<()> {
+        use Pixel::*;
+        let data = build_slp(
+            6,
+            &[(1, 0), (EMPTY_ROW, 0), (0, 0), (0, 0)],
+            &[
+                &[0x08, 10, 11, 0x27, 12, 0x0F],
+                &[0x0F],
+                &[0x05, 0x16, 3, 0x2A, 4, 0x1B, 0x4E, 0x0F],
+                &[0x0E, 0x7E, 2, 0x02, 3, 1, 2, 3, 0x03, 1, 0x0F],
+            ],
+        );
+        let slp = SLP::from_bytes(data)?;
+        assert_eq!(slp.version(), crate::SLPVersion::V2_0N);
+        assert_eq!(slp.comment(), b"test");
+        assert_eq!(slp.num_frames(), 1);
+
+        let frame = slp.frame(0).unwrap();
+        assert_eq!((frame.width(), frame.height()), (6, 4));
+        assert_eq!(frame.hotspot(), (2, 3));
+        #[rustfmt::skip]
+        assert_eq!(frame.pixels()?, vec![
+            Transparent, Color(10), Color(11), Color(12), Color(12), Transparent,
+            Transparent, Transparent, Transparent, Transparent, Transparent, Transparent,
+            Transparent, PlayerColor(3), PlayerColor(4), PlayerColor(4), Shadow, PlayerOutline,
+            Outline, Outline, Color(1), Color(2), Color(3), Transparent,
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_rgba() -> anyhow::Result<()> {
+        let data = build_slp(4, &[(0, 0)], &[&[0x04, 7, 0x16, 1, 0x1B, 0x4E, 0x0F]]);
+        let slp = SLP::from_bytes(data)?;
+        let frame = slp.frame(0).unwrap();
+        let palette: Vec<_> = (0..=255).map(|i| RGB8::new(i, i, i)).collect();
+
+        let rgba = frame.decode_rgba(&palette, &DecodeOptions::new().player(2))?;
+        assert_eq!(
+            rgba,
+            vec![
+                RGBA8::new(7, 7, 7, 255),
+                RGBA8::new(33, 33, 33, 255),
+                RGBA8::new(0, 0, 0, 128),
+                RGBA8::new(0, 0, 0, 0),
+            ]
+        );
+
+        let options = DecodeOptions::new()
+            .outlines(true)
+            .shadow(RGBA8::new(1, 2, 3, 4));
+        let rgba = frame.decode_rgba(&palette, &options)?;
+        assert_eq!(rgba[2], RGBA8::new(1, 2, 3, 4));
+        assert_eq!(rgba[3], RGBA8::new(16, 16, 16, 255));
+
+        assert!(matches!(
+            frame.decode_rgba(&palette[..8], &options),
+            Err(Error::InvalidPaletteIndexError(17))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn decode_layers() -> anyhow::Result<()> {
+        let data = build_slp(4, &[(0, 0)], &[&[0x04, 7, 0x16, 1, 0x1B, 0x4E, 0x0F]]);
+        let slp = SLP::from_bytes(data)?;
+        let frame = slp.frame(0).unwrap();
+        let palette: Vec<_> = (0..=255).map(|i| RGB8::new(i, i, i)).collect();
+
+        let layers = frame.decode_layers(&palette, &DecodeOptions::new().player(2))?;
+        let none = RGBA8::default();
+        assert_eq!((layers.width(), layers.height()), (4, 1));
+        assert_eq!(
+            layers.color(),
+            &[
+                RGBA8::new(7, 7, 7, 255),
+                RGBA8::new(33, 33, 33, 255),
+                none,
+                none
+            ][..]
+        );
+        assert_eq!(layers.shadow(), &[false, false, true, false][..]);
+        assert_eq!(
+            layers.outline(),
+            &[none, none, none, RGBA8::new(32, 32, 32, 255)][..]
+        );
+
+        for options in &[
+            DecodeOptions::new().player(2),
+            DecodeOptions::new()
+                .player(2)
+                .outlines(true)
+                .shadow(RGBA8::new(1, 2, 3, 4)),
+        ] {
+            assert_eq!(
+                layers.flatten(options),
+                frame.decode_rgba(&palette, options)?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_rows() -> anyhow::Result<()> {
+        let slp = SLP::from_bytes(build_slp(2, &[(0, 0)], &[&[0x0C, 1, 2, 3, 0x0F]]))?;
+        assert!(matches!(
+            slp.frame(0).unwrap().pixels(),
+            Err(Error::RowOverflowError(0))
+        ));
+        let slp = SLP::from_bytes(build_slp(2, &[(0, 0)], &[&[0x8E, 0x0F]]))?;
+        assert!(matches!(
+            slp.frame(0).unwrap().pixels(),
+            Err(Error::UnknownCommandError(0x8E, 0))
+        ));
+        let slp = SLP::from_bytes(build_slp(2, &[(0, 0)], &[&[0x08, 1]]))?;
+        assert!(matches!(
+            slp.frame(0).unwrap().pixels(),
+            Err(Error::InvalidOffsetError(_))
+        ));
+        Ok(())
+    }
+}
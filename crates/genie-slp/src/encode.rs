@@ -0,0 +1,436 @@
+//! Encoding paletted images to SLP frames.
+
+use crate::player::PLAYER_COLOR_STRIDE;
+use crate::{Error, Frame, FrameInfo, Pixel, Result, SLPVersion, EMPTY_ROW, SLP};
+use std::io::Write;
+
+/// Size of the file header.
+const HEADER_SIZE: usize = 32;
+
+/// Size of a frame information entry.
+const FRAME_INFO_SIZE: usize = 32;
+
+/// A frame to be encoded into an SLP file.
+///
+/// ```rust
+/// use genie_slp::{IndexedFrame, SLP};
+///
+/// // A 3×2 image where palette index 0 is transparent, and the bottom row is in player colours.
+/// let frame = IndexedFrame::from_indices(3, 2, vec![0, 42, 0, 16, 17, 18], 0)?
+///     .with_hotspot(1, 2)
+///     .player_color_mask(&[false, false, false, true, true, true])?;
+/// let slp = SLP::from_frames(&[frame]);
+/// assert_eq!(slp.frame(0).unwrap().hotspot(), (1, 2));
+/// # Ok::<(), genie_slp::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFrame {
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    pixels: Vec<Pixel>,
+}
+
+impl IndexedFrame {
+    /// Create a fully transparent frame.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            hotspot: (0, 0),
+            pixels: vec![Pixel::Transparent; width as usize * height as usize],
+        }
+    }
+
+    /// Create a frame from decoded pixels, row by row from the top left.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Pixel>) -> Result<Self> {
+        let expected = width as usize * height as usize;
+        if pixels.len() != expected {
+            return Err(Error::FrameSizeError(expected, pixels.len()));
+        }
+        Ok(Self {
+            width,
+            height,
+            hotspot: (0, 0),
+            pixels,
+        })
+    }
+
+    /// Create a frame from palette indices, row by row from the top left. Pixels with the
+    /// `transparent` palette index are not drawn.
+    pub fn from_indices(
+        width: u32,
+        height: u32,
+        indices: Vec<u8>,
+        transparent: u8,
+    ) -> Result<Self> {
+        let pixels = indices
+            .into_iter()
+            .map(|index| {
+                if index == transparent {
+                    Pixel::Transparent
+                } else {
+                    Pixel::Color(index)
+                }
+            })
+            .collect();
+        Self::from_pixels(width, height, pixels)
+    }
+
+    /// Set the position of the anchor point of the frame, relative to its top left corner.
+    pub fn with_hotspot(mut self, x: i32, y: i32) -> Self {
+        self.set_hotspot(x, y);
+        self
+    }
+
+    /// Draw the pixels that are set in `mask` in the colour of the player that owns the unit.
+    ///
+    /// The palette index of each such pixel is taken as a colour of any player: in the standard
+    /// palettes, every player has 16 palette entries, so the index modulo 16 is the offset within
+    /// the player's colours.
+    pub fn player_color_mask(mut self, mask: &[bool]) -> Result<Self> {
+        if mask.len() != self.pixels.len() {
+            return Err(Error::FrameSizeError(self.pixels.len(), mask.len()));
+        }
+        for (pixel, is_player_color) in self.pixels.iter_mut().zip(mask) {
+            if let (Pixel::Color(index), true) = (*pixel, is_player_color) {
+                *pixel = Pixel::PlayerColor(index % PLAYER_COLOR_STRIDE);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Get the width of the frame in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the frame in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the position of the anchor point of the frame, relative to its top left corner.
+    pub fn hotspot(&self) -> (i32, i32) {
+        self.hotspot
+    }
+
+    /// Set the position of the anchor point of the frame, relative to its top left corner.
+    pub fn set_hotspot(&mut self, x: i32, y: i32) {
+        self.hotspot = (x, y);
+    }
+
+    /// Get the pixels of the frame, row by row from the top left.
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    /// Get the pixels of the frame mutably, row by row from the top left.
+    pub fn pixels_mut(&mut self) -> &mut [Pixel] {
+        &mut self.pixels
+    }
+
+    /// Set a single pixel.
+    ///
+    /// Returns [`Error::PixelOutOfBoundsError`] if the position is outside of the frame.
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: Pixel) -> Result<()> {
+        if x >= self.width || y >= self.height {
+            return Err(Error::PixelOutOfBoundsError(x, y));
+        }
+        self.pixels[(y * self.width + x) as usize] = pixel;
+        Ok(())
+    }
+}
+
+impl Frame<'_> {
+    /// Decode the frame to an [`IndexedFrame`], that can be edited and encoded again.
+    pub fn to_indexed(&self) -> Result<IndexedFrame> {
+        let frame = IndexedFrame::from_pixels(self.width(), self.height(), self.pixels()?)?;
+        let (x, y) = self.hotspot();
+        Ok(frame.with_hotspot(x, y))
+    }
+}
+
+/// Write a command that stores the pixel count in the upper 4 bits, or in the next byte.
+fn small_command(out: &mut Vec<u8>, command: u8, mut count: usize, data: Option<u8>) {
+    while count > 0 {
+        let n = count.min(255);
+        if n < 16 {
+            out.push((n as u8) << 4 | command);
+        } else {
+            out.extend_from_slice(&[command, n as u8]);
+        }
+        out.extend(data);
+        count -= n;
+    }
+}
+
+/// Write a lesser or greater command, picking the smallest one that fits the pixel count.
+fn sized_command(out: &mut Vec<u8>, lesser: u8, greater: u8, count: usize, data: &[u8]) {
+    let mut start = 0;
+    while start < count {
+        let n = (count - start).min(4095);
+        if n < 64 {
+            out.push((n as u8) << 2 | lesser);
+        } else {
+            out.extend_from_slice(&[((n >> 8) as u8) << 4 | greater, n as u8]);
+        }
+        if !data.is_empty() {
+            out.extend_from_slice(&data[start..start + n]);
+        }
+        start += n;
+    }
+}
+
+/// Write an outline command, for a single pixel or a span of pixels.
+fn outline_command(out: &mut Vec<u8>, single: u8, span: u8, mut count: usize) {
+    while count > 0 {
+        let n = count.min(255);
+        if n == 1 {
+            out.push(single);
+        } else {
+            out.extend_from_slice(&[span, n as u8]);
+        }
+        count -= n;
+    }
+}
+
+/// Get the number of pixels at the start of `pixels` that are the same as the first one.
+fn run_length(pixels: &[Pixel]) -> usize {
+    pixels
+        .iter()
+        .take_while(|pixel| **pixel == pixels[0])
+        .count()
+}
+
+/// Write the commands that draw a row, not including the transparent pixels at its ends.
+fn encode_row(pixels: &[Pixel], out: &mut Vec<u8>) {
+    // Runs of the same colour that are at least this long are drawn with fill commands.
+    const MIN_FILL: usize = 3;
+
+    let mut x = 0;
+    while x < pixels.len() {
+        let run = run_length(&pixels[x..]);
+        match pixels[x] {
+            Pixel::Transparent => sized_command(out, 0x01, 0x03, run, &[]),
+            Pixel::Shadow => small_command(out, 0x0B, run, None),
+            Pixel::PlayerOutline => outline_command(out, 0x4E, 0x5E, run),
+            Pixel::Outline => outline_command(out, 0x6E, 0x7E, run),
+            Pixel::Color(index) if run >= MIN_FILL => small_command(out, 0x07, run, Some(index)),
+            Pixel::PlayerColor(index) if run >= MIN_FILL => {
+                small_command(out, 0x0A, run, Some(index))
+            }
+            Pixel::Color(_) | Pixel::PlayerColor(_) => {
+                // Copy pixels of the same kind up to the next run that can be filled.
+                let is_player_color = matches!(pixels[x], Pixel::PlayerColor(_));
+                let mut data = vec![];
+                for pixel in &pixels[x..] {
+                    let index = match (*pixel, is_player_color) {
+                        (Pixel::Color(index), false) | (Pixel::PlayerColor(index), true) => index,
+                        _ => break,
+                    };
+                    let end = x + data.len();
+                    if run_length(&pixels[end..]) >= MIN_FILL {
+                        break;
+                    }
+                    data.push(index);
+                }
+                if is_player_color {
+                    for chunk in data.chunks(255) {
+                        small_command(out, 0x06, chunk.len(), None);
+                        out.extend_from_slice(chunk);
+                    }
+                } else {
+                    sized_command(out, 0x00, 0x02, data.len(), &data);
+                }
+                x += data.len();
+                continue;
+            }
+        }
+        x += run;
+    }
+}
+
+/// Encode a frame. Returns the outline table and the commands for each row.
+fn encode_frame(frame: &IndexedFrame) -> (Vec<(u16, u16)>, Vec<Vec<u8>>) {
+    let width = frame.width as usize;
+    let mut outlines = vec![];
+    let mut rows = vec![];
+    for row in frame
+        .pixels
+        .chunks(width.max(1))
+        .take(frame.height as usize)
+    {
+        let left = row.iter().take_while(|p| **p == Pixel::Transparent).count();
+        let mut commands = vec![];
+        if left == row.len() {
+            outlines.push((EMPTY_ROW, EMPTY_ROW));
+        } else {
+            let right = row
+                .iter()
+                .rev()
+                .take_while(|p| **p == Pixel::Transparent)
+                .count();
+            outlines.push((left as u16, right as u16));
+            encode_row(&row[left..row.len() - right], &mut commands);
+        }
+        commands.push(0x0F);
+        rows.push(commands);
+    }
+    (outlines, rows)
+}
+
+impl SLP {
+    /// Encode frames into an SLP file.
+    pub fn from_frames(frames: &[IndexedFrame]) -> Self {
+        let mut infos = vec![];
+        let mut body = vec![];
+        let body_offset = HEADER_SIZE + FRAME_INFO_SIZE * frames.len();
+        for frame in frames {
+            let (outlines, rows) = encode_frame(frame);
+            let outline_table_offset = body_offset + body.len();
+            let command_table_offset = outline_table_offset + 4 * outlines.len();
+            for (left, right) in &outlines {
+                body.extend_from_slice(&left.to_le_bytes());
+                body.extend_from_slice(&right.to_le_bytes());
+            }
+            let mut row_offset = command_table_offset + 4 * rows.len();
+            for row in &rows {
+                body.extend_from_slice(&(row_offset as u32).to_le_bytes());
+                row_offset += row.len();
+            }
+            for row in &rows {
+                body.extend_from_slice(row);
+            }
+            infos.push(FrameInfo {
+                command_table_offset: command_table_offset as u32,
+                outline_table_offset: outline_table_offset as u32,
+                palette_offset: 0,
+                properties: 0,
+                width: frame.width,
+                height: frame.height,
+                hotspot: frame.hotspot,
+            });
+        }
+
+        let mut data = Vec::with_capacity(body_offset + body.len());
+        data.extend_from_slice(&SLPVersion::V2_0N.0);
+        data.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+        for info in &infos {
+            info.write_to(&mut data);
+        }
+        data.extend_from_slice(&body);
+
+        Self {
+            version: SLPVersion::V2_0N,
+            comment: [0; 24],
+            frames: infos,
+            data,
+        }
+    }
+
+    /// Write the SLP file.
+    pub fn write_to(&self, mut output: impl Write) -> Result<()> {
+        output.write_all(&self.data)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Pixel::*;
+
+    fn roundtrip(frame: &IndexedFrame) -> anyhow::Result<IndexedFrame> {
+        let mut bytes = vec![];
+        SLP::from_frames(std::slice::from_ref(frame)).write_to(&mut bytes)?;
+        let slp = SLP::from_bytes(bytes)?;
+        Ok(slp.frame(0).unwrap().to_indexed()?)
+    }
+
+    #[test]
+    fn encode_pixels() -> anyhow::Result<()> {
+        #[rustfmt::skip]
+        let pixels = vec![
+            Transparent, Color(10), Color(11), Color(12), Color(12), Color(12), Transparent,
+            Transparent, Transparent, Transparent, Transparent, Transparent, Transparent, Transparent,
+            PlayerColor(3), PlayerColor(4), PlayerColor(4), PlayerColor(4), Shadow, Shadow, PlayerOutline,
+            Outline, Outline, Color(1), Transparent, Transparent, PlayerColor(2), Color(3),
+        ];
+        let frame = IndexedFrame::from_pixels(7, 4, pixels)?.with_hotspot(3, -2);
+        assert_eq!(roundtrip(&frame)?, frame);
+        Ok(())
+    }
+
+    #[test]
+    fn encode_long_runs() -> anyhow::Result<()> {
+        let width = 5000;
+        let mut frame = IndexedFrame::new(width, 3);
+        for x in 0..width {
+            frame.set_pixel(x, 0, Color((x % 7) as u8))?;
+            frame.set_pixel(x, 1, if x < 4500 { Shadow } else { Outline })?;
+            let pixel = match x % 600 {
+                0..=99 => Transparent,
+                100..=399 => PlayerColor((x % 5) as u8),
+                _ => PlayerOutline,
+            };
+            frame.set_pixel(x, 2, pixel)?;
+        }
+        assert_eq!(roundtrip(&frame)?, frame);
+        assert!(matches!(
+            frame.set_pixel(width, 0, Shadow),
+            Err(Error::PixelOutOfBoundsError(x, 0)) if x == width
+        ));
+        assert!(matches!(
+            frame.set_pixel(0, 3, Shadow),
+            Err(Error::PixelOutOfBoundsError(0, 3))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn encode_indices() -> anyhow::Result<()> {
+        let frame = IndexedFrame::from_indices(3, 2, vec![0, 42, 0, 16, 17, 18], 0)?
+            .with_hotspot(1, 2)
+            .player_color_mask(&[false, true, false, true, true, false])?;
+        assert_eq!(
+            frame.pixels(),
+            &[
+                Transparent,
+                PlayerColor(10),
+                Transparent,
+                PlayerColor(0),
+                PlayerColor(1),
+                Color(18)
+            ]
+        );
+        assert_eq!(roundtrip(&frame)?, frame);
+        assert!(matches!(
+            IndexedFrame::from_indices(3, 2, vec![0; 5], 0),
+            Err(Error::FrameSizeError(6, 5))
+        ));
+        assert!(matches!(
+            frame.player_color_mask(&[true]),
+            Err(Error::FrameSizeError(6, 1))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn multiple_frames() -> anyhow::Result<()> {
+        let frames = vec![
+            IndexedFrame::from_indices(2, 2, vec![1, 2, 3, 4], 0)?,
+            IndexedFrame::new(0, 0),
+            IndexedFrame::from_indices(1, 3, vec![5, 0, 6], 0)?.with_hotspot(0, 3),
+        ];
+        let slp = SLP::from_frames(&frames);
+        assert_eq!(slp.num_frames(), 3);
+        let decoded = slp
+            .frames()
+            .map(|frame| frame.to_indexed())
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(decoded, frames);
+        Ok(())
+    }
+}
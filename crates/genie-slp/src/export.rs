@@ -0,0 +1,305 @@
+//! Exporting the frames of a sprite as images, for documentation and mod previews.
+//!
+//! Frames of an SLP file can have different sizes, and are aligned by their anchor points when
+//! the game draws them. [`Animation`] decodes the frames onto canvases of the same size, with the
+//! anchor points of all frames at the same position, so they can be written as a PNG sequence,
+//! an animated PNG, or a GIF.
+
+use crate::{DecodeOptions, Error, Frame, Result, SLP};
+use rgb::{ComponentBytes, RGB8, RGBA8};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Options for exporting frames as images.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    decode: DecodeOptions,
+    shadows: bool,
+    frame_delay: u16,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            decode: DecodeOptions::default(),
+            shadows: true,
+            frame_delay: 100,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Create the default options: decode with the default [`DecodeOptions`], draw shadows, and
+    /// show each frame for 100 milliseconds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the options used to decode the frames, like the player colour.
+    pub fn decode(mut self, decode: DecodeOptions) -> Self {
+        self.decode = decode;
+        self
+    }
+
+    /// Set whether to draw the shadow pixels. If set, they are drawn in the shadow colour of the
+    /// decode options.
+    pub fn shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Set how long each frame is shown in animated images, in milliseconds.
+    pub fn frame_delay(mut self, milliseconds: u16) -> Self {
+        self.frame_delay = milliseconds;
+        self
+    }
+}
+
+/// Frames of a sprite, decoded to RGBA canvases that are aligned by their anchor points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Animation {
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    frame_delay: u16,
+    frames: Vec<Vec<RGBA8>>,
+}
+
+impl Animation {
+    /// Decode all frames of an SLP file.
+    pub fn from_slp(slp: &SLP, palette: &[RGB8], options: &ExportOptions) -> Result<Self> {
+        Self::from_frames(slp.frames(), palette, options)
+    }
+
+    /// Decode the given frames, for example the frames of one direction of a unit.
+    pub fn from_frames<'a>(
+        frames: impl IntoIterator<Item = Frame<'a>>,
+        palette: &[RGB8],
+        options: &ExportOptions,
+    ) -> Result<Self> {
+        let decode = if options.shadows {
+            options.decode.clone()
+        } else {
+            options.decode.clone().shadow(RGBA8::default())
+        };
+        let frames = frames.into_iter().collect::<Vec<_>>();
+
+        // The canvas spans all frames, relative to the shared anchor point.
+        let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+        for frame in &frames {
+            let (x, y) = frame.hotspot();
+            left = left.min(-x);
+            top = top.min(-y);
+            right = right.max(frame.width() as i32 - x);
+            bottom = bottom.max(frame.height() as i32 - y);
+        }
+        let width = (right - left) as u32;
+        let height = (bottom - top) as u32;
+
+        let canvases = frames
+            .iter()
+            .map(|frame| {
+                let rgba = frame.decode_rgba(palette, &decode)?;
+                let mut canvas = vec![RGBA8::default(); width as usize * height as usize];
+                let (x, y) = frame.hotspot();
+                let offset_x = (-x - left) as usize;
+                let offset_y = (-y - top) as usize;
+                let frame_width = frame.width().max(1) as usize;
+                for (row, pixels) in rgba.chunks(frame_width).enumerate() {
+                    let start = (offset_y + row) * width as usize + offset_x;
+                    canvas[start..start + pixels.len()].copy_from_slice(pixels);
+                }
+                Ok(canvas)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            width,
+            height,
+            hotspot: (-left, -top),
+            frame_delay: options.frame_delay,
+            frames: canvases,
+        })
+    }
+
+    /// Get the width of the canvases in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the canvases in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the position of the shared anchor point, relative to the top left corner of the
+    /// canvases.
+    pub fn hotspot(&self) -> (i32, i32) {
+        self.hotspot
+    }
+
+    /// Get the decoded frames, row by row from the top left.
+    pub fn frames(&self) -> &[Vec<RGBA8>] {
+        &self.frames
+    }
+
+    fn png_encoder<W: Write>(&self, output: W) -> png::Encoder<'static, W> {
+        let mut encoder = png::Encoder::new(output, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+    }
+
+    /// Write a single frame as a PNG image.
+    ///
+    /// # Panics
+    /// This function panics if the frame index is out of range.
+    pub fn write_png(&self, index: usize, output: impl Write) -> Result<()> {
+        let mut writer = self.png_encoder(output).write_header()?;
+        writer.write_image_data(self.frames[index].as_bytes())?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Write every frame as a PNG image in the directory `dir`, named `{prefix}{index}.png` with
+    /// the index padded to 3 digits. Returns the paths of the written files.
+    pub fn write_png_sequence(&self, dir: impl AsRef<Path>, prefix: &str) -> Result<Vec<PathBuf>> {
+        (0..self.frames.len())
+            .map(|index| {
+                let path = dir.as_ref().join(format!("{}{:03}.png", prefix, index));
+                let mut output = BufWriter::new(File::create(&path)?);
+                self.write_png(index, &mut output)?;
+                output.flush()?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Write all frames as an animated PNG that loops forever.
+    pub fn write_apng(&self, output: impl Write) -> Result<()> {
+        if self.frames.is_empty() {
+            return Err(Error::NoFramesError);
+        }
+        let mut encoder = self.png_encoder(output);
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+        encoder.set_frame_delay(self.frame_delay, 1000)?;
+        encoder.set_blend_op(png::BlendOp::Source)?;
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            writer.write_image_data(frame.as_bytes())?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Write all frames as an animated GIF that loops forever.
+    ///
+    /// GIF images do not support partial transparency, so half-transparent pixels, like shadows,
+    /// are drawn opaque. Frames with more than 256 colours are quantized.
+    pub fn write_gif(&self, output: impl Write) -> Result<()> {
+        if self.frames.is_empty() {
+            return Err(Error::NoFramesError);
+        }
+        let too_large = || Error::ImageTooLargeError(self.width, self.height);
+        let width = u16::try_from(self.width).map_err(|_| too_large())?;
+        let height = u16::try_from(self.height).map_err(|_| too_large())?;
+
+        let mut encoder = gif::Encoder::new(output, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for frame in &self.frames {
+            let mut pixels = frame.as_bytes().to_vec();
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+            frame.delay = self.frame_delay / 10;
+            frame.dispose = gif::DisposalMethod::Background;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IndexedFrame, Pixel};
+
+    fn palette() -> Vec<RGB8> {
+        (0..=255).map(|i| RGB8::new(i, i, 255 - i)).collect()
+    }
+
+    fn sprite() -> anyhow::Result<SLP> {
+        use Pixel::*;
+        let first = IndexedFrame::from_pixels(2, 1, vec![Color(1), Shadow])?.with_hotspot(0, 0);
+        let second = IndexedFrame::from_pixels(1, 2, vec![Color(2), Color(3)])?.with_hotspot(1, 1);
+        Ok(SLP::from_frames(&[first, second]))
+    }
+
+    #[test]
+    fn align_frames() -> anyhow::Result<()> {
+        let slp = sprite()?;
+        let anim = Animation::from_slp(&slp, &palette(), &ExportOptions::new())?;
+        assert_eq!((anim.width(), anim.height()), (3, 2));
+        assert_eq!(anim.hotspot(), (1, 1));
+        let none = RGBA8::default();
+        let color = |i| RGBA8::new(i, i, 255 - i, 255);
+        #[rustfmt::skip]
+        assert_eq!(anim.frames(), &[
+            vec![none, none, none, none, color(1), RGBA8::new(0, 0, 0, 128)],
+            vec![color(2), none, none, color(3), none, none],
+        ]);
+
+        let anim = Animation::from_slp(&slp, &palette(), &ExportOptions::new().shadows(false))?;
+        assert_eq!(anim.frames()[0][5], none);
+        Ok(())
+    }
+
+    #[test]
+    fn write_images() -> anyhow::Result<()> {
+        let slp = sprite()?;
+        let anim = Animation::from_slp(&slp, &palette(), &ExportOptions::new())?;
+
+        let mut png = vec![];
+        anim.write_png(1, &mut png)?;
+        let mut reader = png::Decoder::new(&png[..]).read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels)?;
+        assert_eq!(pixels, anim.frames()[1].as_bytes());
+
+        let mut apng = vec![];
+        anim.write_apng(&mut apng)?;
+        let reader = png::Decoder::new(&apng[..]).read_info()?;
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 2);
+
+        let mut gif = vec![];
+        anim.write_gif(&mut gif)?;
+        let mut decoder = gif::DecodeOptions::new().read_info(&gif[..])?;
+        let mut num_frames = 0;
+        while decoder.read_next_frame()?.is_some() {
+            num_frames += 1;
+        }
+        assert_eq!(num_frames, 2);
+
+        let empty = Animation::from_frames(None, &palette(), &ExportOptions::new())?;
+        assert!(matches!(
+            empty.write_apng(vec![]),
+            Err(Error::NoFramesError)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn write_sequence() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("genie-slp-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let anim = Animation::from_slp(&sprite()?, &palette(), &ExportOptions::new())?;
+        let paths = anim.write_png_sequence(&dir, "archer_")?;
+        assert_eq!(
+            paths,
+            vec![dir.join("archer_000.png"), dir.join("archer_001.png")]
+        );
+        assert!(paths.iter().all(|path| path.is_file()));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+//! .slp is the sprite file format for the Genie Engine, used by Age of Empires 1/2 and Star Wars:
+//! Galactic Battlegrounds. An .slp file contains one or more frames, each of which is a paletted
+//! image stored as a list of drawing commands per row.
+//!
+//! Besides normal palette colours, frames can contain pixels in the colour of the player that
+//! owns the unit, shadow pixels, and outline pixels that the game draws when the unit is behind a
+//! building. [`Frame::pixels`] decodes a frame to these kinds of pixels, and
+//! [`Frame::decode_rgba`] turns them into RGBA colours using a palette. [`Frame::decode_layers`]
+//! keeps shadows and outlines in separate [`Layers`], so they can be composited at draw time.
+//! [`SLP::from_frames`] encodes paletted images, created with [`IndexedFrame`], into a new SLP
+//! file. With the `export` feature, which is enabled by default, the [`export`] module writes the
+//! frames of a sprite as PNG images, animated PNGs or GIFs. The [`terrain`] module renders maps
+//! using terrain sprites.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use genie_slp::{DecodeOptions, SLP};
+//! use std::fs::File;
+//!
+//! let slp = SLP::read_from(File::open("archer.slp")?)?;
+//! let palette = vec![rgb::RGB8::new(0, 0, 0); 256];
+//! for frame in slp.frames() {
+//!     let rgba = frame.decode_rgba(&palette, &DecodeOptions::new().player(2))?;
+//!     assert_eq!(rgba.len(), (frame.width() * frame.height()) as usize);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#![deny(future_incompatible)]
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(unsafe_code)]
+#![warn(unused)]
+#![allow(missing_docs)]
+
+use byteorder::{ReadBytesExt, LE};
+use std::fmt;
+use std::io::{Cursor, Read};
+
+mod decode;
+mod encode;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod player;
+pub mod terrain;
+
+pub use decode::{DecodeOptions, Layers, Pixel};
+pub use encode::IndexedFrame;
+
+/// Outline table value for rows that do not contain any pixels.
+const EMPTY_ROW: u16 = 0x8000;
+
+/// Errors that may occur while reading or decoding an SLP file.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// An offset in the file points outside of the file.
+    #[error("offset {} is outside of the file", .0)]
+    InvalidOffsetError(usize),
+    /// A row contains a drawing command that is not known.
+    #[error("unknown drawing command {:#04x} in row {}", .0, .1)]
+    UnknownCommandError(u8, u32),
+    /// A row draws more pixels than the frame is wide.
+    #[error("row {} is wider than the frame", .0)]
+    RowOverflowError(u32),
+    /// The number of pixels does not match the size of the frame.
+    #[error("expected {} pixels, got {}", .0, .1)]
+    FrameSizeError(usize, usize),
+    /// A pixel position is outside of the frame.
+    #[error("pixel ({}, {}) is outside of the frame", .0, .1)]
+    PixelOutOfBoundsError(u32, u32),
+    /// A pixel refers to a colour that is not in the palette.
+    #[error("palette index {} is out of range", .0)]
+    InvalidPaletteIndexError(usize),
+    /// An animation without frames was exported.
+    #[error("there are no frames to export")]
+    NoFramesError,
+    /// The frames are too large for the image format they are exported to.
+    #[error("image of {}×{} pixels is too large", .0, .1)]
+    ImageTooLargeError(u32, u32),
+    /// An error occurred while encoding a PNG image.
+    #[cfg(feature = "export")]
+    #[error(transparent)]
+    PngError(#[from] png::EncodingError),
+    /// An error occurred while encoding a GIF image.
+    #[cfg(feature = "export")]
+    #[error(transparent)]
+    GifError(#[from] gif::EncodingError),
+    /// An I/O error occurred.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The format version of an SLP file, like "2.0N".
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SLPVersion(pub [u8; 4]);
+
+impl SLPVersion {
+    /// The version used by Age of Empires 1/2 and Star Wars: Galactic Battlegrounds.
+    pub const V2_0N: Self = SLPVersion(*b"2.0N");
+}
+
+impl Default for SLPVersion {
+    fn default() -> Self {
+        Self::V2_0N
+    }
+}
+
+impl fmt::Debug for SLPVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl fmt::Display for SLPVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+/// The size and position of a frame, and where to find its drawing commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Offset of the table with the offsets of the drawing commands for each row.
+    pub command_table_offset: u32,
+    /// Offset of the table with the transparent pixels at the start and end of each row.
+    pub outline_table_offset: u32,
+    /// Offset of the palette. Unused by the game.
+    pub palette_offset: u32,
+    /// Frame properties. Unused by the game.
+    pub properties: u32,
+    /// Width of the frame in pixels.
+    pub width: u32,
+    /// Height of the frame in pixels.
+    pub height: u32,
+    /// Position of the anchor point of the frame, relative to its top left corner.
+    pub hotspot: (i32, i32),
+}
+
+impl FrameInfo {
+    fn read_from(mut input: impl Read) -> Result<Self> {
+        Ok(Self {
+            command_table_offset: input.read_u32::<LE>()?,
+            outline_table_offset: input.read_u32::<LE>()?,
+            palette_offset: input.read_u32::<LE>()?,
+            properties: input.read_u32::<LE>()?,
+            width: input.read_u32::<LE>()?,
+            height: input.read_u32::<LE>()?,
+            hotspot: (input.read_i32::<LE>()?, input.read_i32::<LE>()?),
+        })
+    }
+
+    fn write_to(&self, output: &mut Vec<u8>) {
+        let values = [
+            self.command_table_offset,
+            self.outline_table_offset,
+            self.palette_offset,
+            self.properties,
+            self.width,
+            self.height,
+        ];
+        for value in &values {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+        output.extend_from_slice(&self.hotspot.0.to_le_bytes());
+        output.extend_from_slice(&self.hotspot.1.to_le_bytes());
+    }
+}
+
+/// An SLP sprite file. Read existing files with [`SLP::read_from`], or encode new ones with
+/// [`SLP::from_frames`].
+#[derive(Debug, Clone)]
+pub struct SLP {
+    version: SLPVersion,
+    comment: [u8; 24],
+    frames: Vec<FrameInfo>,
+    data: Vec<u8>,
+}
+
+impl SLP {
+    /// Read an SLP file.
+    pub fn read_from(mut input: impl Read) -> Result<Self> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        Self::from_bytes(data)
+    }
+
+    /// Parse an SLP file from its bytes.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let mut input = Cursor::new(&data[..]);
+        let mut version = [0; 4];
+        input.read_exact(&mut version)?;
+        let num_frames = input.read_u32::<LE>()?;
+        let mut comment = [0; 24];
+        input.read_exact(&mut comment)?;
+        let frames = (0..num_frames)
+            .map(|_| FrameInfo::read_from(&mut input))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            version: SLPVersion(version),
+            comment,
+            frames,
+            data,
+        })
+    }
+
+    /// Get the format version of the file.
+    pub fn version(&self) -> SLPVersion {
+        self.version
+    }
+
+    /// Get the comment stored in the file header, without trailing NUL bytes.
+    pub fn comment(&self) -> &[u8] {
+        let len = self
+            .comment
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(self.comment.len());
+        &self.comment[..len]
+    }
+
+    /// Get the number of frames in the file.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Get a frame by its index.
+    pub fn frame(&self, index: usize) -> Option<Frame<'_>> {
+        self.frames.get(index).map(|info| Frame {
+            info,
+            data: &self.data,
+        })
+    }
+
+    /// Iterate over the frames in the file.
+    pub fn frames(&self) -> impl Iterator<Item = Frame<'_>> {
+        let data = &self.data[..];
+        self.frames.iter().map(move |info| Frame { info, data })
+    }
+}
+
+/// A frame in an SLP file.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    info: &'a FrameInfo,
+    data: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Get the raw frame information from the file header.
+    pub fn info(&self) -> &'a FrameInfo {
+        self.info
+    }
+
+    /// Get the width of the frame in pixels.
+    pub fn width(&self) -> u32 {
+        self.info.width
+    }
+
+    /// Get the height of the frame in pixels.
+    pub fn height(&self) -> u32 {
+        self.info.height
+    }
+
+    /// Get the position of the anchor point of the frame, relative to its top left corner. The
+    /// anchor point is drawn at the position of the unit on the map.
+    pub fn hotspot(&self) -> (i32, i32) {
+        self.info.hotspot
+    }
+}
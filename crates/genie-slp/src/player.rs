@@ -0,0 +1,188 @@
+//! Recolouring sprites for a player, using the player colour ranges of the standard palettes.
+//!
+//! In the standard palettes, the colours of player `n` are 8 shades starting at palette index
+//! `16 * n`, for players 1 to 8. Player colour pixels in SLP frames store the shade, which is
+//! looked up in the range of the player that owns the unit.
+
+use crate::{IndexedFrame, Pixel};
+use rgb::{RGB8, RGBA8};
+use std::convert::TryFrom;
+
+/// Number of palette entries between the colours of consecutive players.
+pub const PLAYER_COLOR_STRIDE: u8 = 16;
+
+/// Number of shades of each player's colour.
+pub const NUM_PLAYER_SHADES: u8 = 8;
+
+/// Highest player number that has colours in the standard palettes.
+pub const MAX_PLAYER: u8 = 8;
+
+/// Get the palette index of a shade of a player's colour.
+pub fn player_color_index(player: u8, shade: u8) -> usize {
+    usize::from(shade) + usize::from(PLAYER_COLOR_STRIDE) * usize::from(player)
+}
+
+/// Find the player and shade that a palette index belongs to, if it is in a player colour range.
+///
+/// ```rust
+/// use genie_slp::player::player_color_of_index;
+///
+/// assert_eq!(player_color_of_index(16), Some((1, 0)));
+/// assert_eq!(player_color_of_index(37), Some((2, 5)));
+/// assert_eq!(player_color_of_index(44), None);
+/// ```
+pub fn player_color_of_index(index: u8) -> Option<(u8, u8)> {
+    let player = index / PLAYER_COLOR_STRIDE;
+    let shade = index % PLAYER_COLOR_STRIDE;
+    if (1..=MAX_PLAYER).contains(&player) && shade < NUM_PLAYER_SHADES {
+        Some((player, shade))
+    } else {
+        None
+    }
+}
+
+/// Recolour RGBA pixels that were decoded for player `from`, so they show the colours of player
+/// `to` instead. Pixels that have the exact colour of one of the shades of player `from` in
+/// `palette` are replaced with the same shade of player `to`.
+///
+/// This works on sprites that have already been decoded, like previews that were rendered once.
+/// When decoding, [`DecodeOptions::player`](crate::DecodeOptions::player) is more accurate,
+/// because it does not touch normal pixels that happen to have a player's colour.
+pub fn recolor_rgba(pixels: &mut [RGBA8], palette: &[RGB8], from: u8, to: u8) {
+    let shades: Vec<_> = (0..NUM_PLAYER_SHADES)
+        .filter_map(|shade| {
+            let from = palette.get(player_color_index(from, shade))?;
+            let to = palette.get(player_color_index(to, shade))?;
+            Some((*from, *to))
+        })
+        .collect();
+    for pixel in pixels.iter_mut().filter(|pixel| pixel.a > 0) {
+        if let Some((_, to)) = shades
+            .iter()
+            .find(|(from, _)| *from == RGB8::new(pixel.r, pixel.g, pixel.b))
+        {
+            *pixel = RGBA8::new(to.r, to.g, to.b, pixel.a);
+        }
+    }
+}
+
+impl Pixel {
+    /// Turn a player colour pixel into the palette colour of the given player. Other pixels, and
+    /// shades that are outside of the palette, are kept.
+    pub fn for_player(self, player: u8) -> Self {
+        match self {
+            Pixel::PlayerColor(shade) => u8::try_from(player_color_index(player, shade))
+                .map(Pixel::Color)
+                .unwrap_or(self),
+            _ => self,
+        }
+    }
+
+    /// Turn a palette colour of player `from` into the same shade of player `to`. Other pixels
+    /// are kept.
+    pub fn recolor(self, from: u8, to: u8) -> Self {
+        match self {
+            Pixel::Color(index) => match player_color_of_index(index) {
+                Some((player, shade)) if player == from => Pixel::PlayerColor(shade).for_player(to),
+                _ => self,
+            },
+            _ => self,
+        }
+    }
+}
+
+impl IndexedFrame {
+    /// Draw the player colour pixels in the palette colours of the given player, for example to
+    /// show a unit preview in a fixed colour.
+    pub fn set_player(&mut self, player: u8) {
+        for pixel in self.pixels_mut() {
+            *pixel = pixel.for_player(player);
+        }
+    }
+
+    /// Replace the palette colours of player `from` with the same shades of player `to`.
+    pub fn recolor_player(&mut self, from: u8, to: u8) {
+        for pixel in self.pixels_mut() {
+            *pixel = pixel.recolor(from, to);
+        }
+    }
+
+    /// Turn the palette colours of the given player into player colour pixels, so the frame is
+    /// drawn in the colour of whichever player owns the unit. This is the inverse of
+    /// [`IndexedFrame::set_player`].
+    pub fn extract_player_colors(&mut self, player: u8) {
+        for pixel in self.pixels_mut() {
+            if let Pixel::Color(index) = *pixel {
+                match player_color_of_index(index) {
+                    Some((owner, shade)) if owner == player => *pixel = Pixel::PlayerColor(shade),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecodeOptions, SLP};
+    use Pixel::*;
+
+    #[test]
+    fn color_ranges() {
+        assert_eq!(player_color_index(1, 0), 16);
+        assert_eq!(player_color_index(8, 7), 135);
+        for player in 1..=MAX_PLAYER {
+            for shade in 0..NUM_PLAYER_SHADES {
+                let index = player_color_index(player, shade) as u8;
+                assert_eq!(player_color_of_index(index), Some((player, shade)));
+            }
+        }
+        assert_eq!(player_color_of_index(15), None);
+        assert_eq!(player_color_of_index(24), None);
+        assert_eq!(player_color_of_index(144), None);
+    }
+
+    #[test]
+    fn recolor_frames() -> anyhow::Result<()> {
+        let pixels = vec![Color(4), PlayerColor(2), Color(19), Color(35), Shadow];
+        let mut frame = IndexedFrame::from_pixels(5, 1, pixels)?;
+
+        let mut red = frame.clone();
+        red.set_player(2);
+        assert_eq!(
+            red.pixels(),
+            &[Color(4), Color(34), Color(19), Color(35), Shadow]
+        );
+        red.recolor_player(2, 3);
+        assert_eq!(
+            red.pixels(),
+            &[Color(4), Color(50), Color(19), Color(51), Shadow]
+        );
+        red.extract_player_colors(3);
+        assert_eq!(
+            red.pixels(),
+            &[Color(4), PlayerColor(2), Color(19), PlayerColor(3), Shadow]
+        );
+
+        frame.extract_player_colors(1);
+        assert_eq!(frame.pixels()[2], PlayerColor(3));
+        assert_eq!(PlayerColor(200).for_player(8), PlayerColor(200));
+        Ok(())
+    }
+
+    #[test]
+    fn recolor_decoded() -> anyhow::Result<()> {
+        let frame = IndexedFrame::from_pixels(3, 1, vec![PlayerColor(1), Color(7), Shadow])?;
+        let slp = SLP::from_frames(&[frame]);
+        let palette: Vec<_> = (0..=255).map(|i| RGB8::new(i, 255 - i, 0)).collect();
+
+        let frame = slp.frame(0).unwrap();
+        let mut blue = frame.decode_rgba(&palette, &DecodeOptions::new().player(1))?;
+        let red = frame.decode_rgba(&palette, &DecodeOptions::new().player(2))?;
+        assert_ne!(blue, red);
+        recolor_rgba(&mut blue, &palette, 1, 2);
+        assert_eq!(blue, red);
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+//! Rendering maps with terrain sprites.
+//!
+//! Terrain SLPs contain a square pattern of diamond-shaped tiles: a terrain with 100 frames is
+//! a 10×10 pattern, and the tile at map position `(x, y)` uses frame `(y % 10) * 10 + x % 10`.
+//! [`TerrainRenderer`] draws a [`TerrainGrid`] of tiles in the game's isometric projection,
+//! raising tiles by their elevation and shading slopes with a lightmask.
+
+use crate::{DecodeOptions, Result, SLP};
+use rgb::{RGB8, RGBA8};
+use std::collections::hash_map::{Entry, HashMap};
+
+/// A tile to render.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TerrainTile {
+    /// The terrain ID.
+    pub terrain: u8,
+    /// The elevation level.
+    pub elevation: i8,
+}
+
+impl TerrainTile {
+    /// Create a tile with the given terrain and elevation.
+    pub fn new(terrain: u8, elevation: i8) -> Self {
+        Self { terrain, elevation }
+    }
+}
+
+/// A map of terrain tiles to render.
+///
+/// Maps read from scenarios or recorded games can be converted with [`TerrainGrid::from_fn`]:
+///
+/// ```rust,ignore
+/// let map = scenario.map();
+/// let grid = TerrainGrid::from_fn(map.width(), map.height(), |x, y| {
+///     let tile = map.tile(x, y).unwrap();
+///     TerrainTile::new(tile.terrain, tile.elevation)
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainGrid {
+    width: u32,
+    height: u32,
+    tiles: Vec<TerrainTile>,
+}
+
+impl TerrainGrid {
+    /// Create a map of the given size, filled with flat tiles of terrain 0.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::from_fn(width, height, |_, _| TerrainTile::default())
+    }
+
+    /// Create a map of the given size, calling `tile` for each position.
+    pub fn from_fn(width: u32, height: u32, mut tile: impl FnMut(u32, u32) -> TerrainTile) -> Self {
+        let tiles = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| tile(x, y))
+            .collect();
+        Self {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// Get the width of the map in tiles.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the map in tiles.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the tile at the given position.
+    pub fn tile(&self, x: u32, y: u32) -> Option<&TerrainTile> {
+        if x >= self.width {
+            return None;
+        }
+        self.tiles.get((y * self.width + x) as usize)
+    }
+
+    /// Get a mutable reference to the tile at the given position.
+    pub fn tile_mut(&mut self, x: u32, y: u32) -> Option<&mut TerrainTile> {
+        if x >= self.width {
+            return None;
+        }
+        self.tiles.get_mut((y * self.width + x) as usize)
+    }
+
+    /// Get the elevation of a tile, or of the nearest tile on the edge of the map for positions
+    /// outside it.
+    fn elevation(&self, x: i64, y: i64) -> i32 {
+        let x = x.max(0).min(i64::from(self.width) - 1) as u32;
+        let y = y.max(0).min(i64::from(self.height) - 1) as u32;
+        self.tile(x, y).map_or(0, |tile| i32::from(tile.elevation))
+    }
+}
+
+/// A rendered map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<RGBA8>,
+}
+
+impl TerrainImage {
+    /// Get the width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the colour of the pixel at the given coordinates.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<RGBA8> {
+        if x >= self.width {
+            return None;
+        }
+        self.pixels.get((y * self.width + x) as usize).copied()
+    }
+
+    /// Get the pixels, row by row from the top left.
+    pub fn pixels(&self) -> &[RGBA8] {
+        &self.pixels
+    }
+
+    /// Take the pixels, row by row from the top left.
+    pub fn into_pixels(self) -> Vec<RGBA8> {
+        self.pixels
+    }
+
+    /// Write the image as a PNG.
+    #[cfg(feature = "export")]
+    pub fn write_png(&self, output: impl std::io::Write) -> Result<()> {
+        use rgb::ComponentBytes;
+
+        let mut encoder = png::Encoder::new(output, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_bytes())?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Draws maps using terrain sprites.
+///
+/// ```rust,ignore
+/// let renderer = TerrainRenderer::new(palette.colors())
+///     .terrain(0, &grass)
+///     .terrain(1, &water);
+/// let image = renderer.render(&grid)?;
+/// image.write_png(File::create("map.png")?)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct TerrainRenderer<'a> {
+    palette: &'a [RGB8],
+    terrains: Vec<Option<&'a SLP>>,
+    tile_size: (u32, u32),
+    elevation_height: u32,
+    lightmask: bool,
+}
+
+impl<'a> TerrainRenderer<'a> {
+    /// Create a renderer using the given palette, with the tile size of Age of Empires 2. No
+    /// terrains have a sprite yet.
+    pub fn new(palette: &'a [RGB8]) -> Self {
+        Self {
+            palette,
+            terrains: vec![],
+            tile_size: (96, 48),
+            elevation_height: 24,
+            lightmask: true,
+        }
+    }
+
+    /// Draw tiles with the given terrain using an SLP.
+    pub fn terrain(mut self, terrain: u8, slp: &'a SLP) -> Self {
+        let terrain = usize::from(terrain);
+        if self.terrains.len() <= terrain {
+            self.terrains.resize(terrain + 1, None);
+        }
+        self.terrains[terrain] = Some(slp);
+        self
+    }
+
+    /// Set the size of the diamond that a tile covers on screen, in pixels. Tiles are placed this
+    /// far apart. Age of Empires 1 and 2 use 64×32 and 96×48 tiles.
+    pub fn tile_size(mut self, width: u32, height: u32) -> Self {
+        self.tile_size = (width, height);
+        self
+    }
+
+    /// Set how many pixels tiles are raised for each elevation level.
+    pub fn elevation_height(mut self, height: u32) -> Self {
+        self.elevation_height = height;
+        self
+    }
+
+    /// Set whether to shade slopes with a lightmask, so hills are lit from the top left.
+    pub fn lightmask(mut self, lightmask: bool) -> Self {
+        self.lightmask = lightmask;
+        self
+    }
+
+    /// Get the brightness of a tile in 1/8ths, based on the slope of the terrain around it.
+    fn light(&self, grid: &TerrainGrid, x: u32, y: u32) -> i32 {
+        if !self.lightmask {
+            return 8;
+        }
+        let (x, y) = (i64::from(x), i64::from(y));
+        let slope = grid.elevation(x + 1, y) + grid.elevation(x, y + 1)
+            - grid.elevation(x - 1, y)
+            - grid.elevation(x, y - 1);
+        (8 + slope).clamp(4, 12)
+    }
+
+    /// Render the map. Tiles with a terrain that has no sprite are transparent.
+    pub fn render(&self, grid: &TerrainGrid) -> Result<TerrainImage> {
+        let (tile_width, tile_height) = (self.tile_size.0 as i64, self.tile_size.1 as i64);
+        let (map_width, map_height) = (i64::from(grid.width), i64::from(grid.height));
+        let max_elevation = grid.tiles.iter().map(|tile| tile.elevation).max();
+        let min_elevation = grid.tiles.iter().map(|tile| tile.elevation).min();
+        let elevation_range = i64::from(max_elevation.unwrap_or(0).max(0))
+            - i64::from(min_elevation.unwrap_or(0).min(0));
+
+        let width = (map_width + map_height) * tile_width / 2;
+        let height = (map_width + map_height) * tile_height / 2
+            + elevation_range * i64::from(self.elevation_height);
+        let mut pixels = vec![RGBA8::default(); (width * height) as usize];
+        let origin_x = (map_height - 1) * tile_width / 2;
+        let origin_y =
+            i64::from(max_elevation.unwrap_or(0).max(0)) * i64::from(self.elevation_height);
+
+        let mut decoded = HashMap::new();
+        // Draw from back to front, so raised tiles cover the tiles behind them.
+        for diagonal in 0..(map_width + map_height).max(1) - 1 {
+            for x in 0..=diagonal {
+                let y = diagonal - x;
+                if x >= map_width || y >= map_height {
+                    continue;
+                }
+                let (x, y) = (x as u32, y as u32);
+                let tile = grid.tile(x, y).expect("position is on the map");
+                let slp = match self.terrains.get(usize::from(tile.terrain)) {
+                    Some(Some(slp)) => slp,
+                    _ => continue,
+                };
+                let pattern = (slp.num_frames() as f64).sqrt() as u32;
+                if pattern == 0 {
+                    continue;
+                }
+                let index = ((y % pattern) * pattern + x % pattern) as usize;
+                let frame = slp.frame(index).expect("frame index is within the pattern");
+                let rgba = match decoded.entry((tile.terrain, index)) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(frame.decode_rgba(self.palette, &DecodeOptions::new())?)
+                    }
+                };
+                let light = self.light(grid, x, y);
+
+                let (hotspot_x, hotspot_y) = frame.hotspot();
+                let left = origin_x + (i64::from(x) - i64::from(y)) * tile_width / 2
+                    - i64::from(hotspot_x);
+                let top = origin_y + (i64::from(x) + i64::from(y)) * tile_height / 2
+                    - i64::from(tile.elevation) * i64::from(self.elevation_height)
+                    - i64::from(hotspot_y);
+                let frame_width = frame.width().max(1) as usize;
+                for (row, colors) in rgba.chunks(frame_width).enumerate() {
+                    let py = top + row as i64;
+                    if py < 0 || py >= height {
+                        continue;
+                    }
+                    for (column, color) in colors.iter().enumerate() {
+                        let px = left + column as i64;
+                        if color.a == 0 || px < 0 || px >= width {
+                            continue;
+                        }
+                        let shade = |c: u8| (i32::from(c) * light / 8).min(255) as u8;
+                        pixels[(py * width + px) as usize] =
+                            RGBA8::new(shade(color.r), shade(color.g), shade(color.b), color.a);
+                    }
+                }
+            }
+        }
+
+        Ok(TerrainImage {
+            width: width as u32,
+            height: height as u32,
+            pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedFrame;
+
+    /// Create a terrain with a 2×2 pattern of 4×2 diamonds, using palette indices `first..first+4`.
+    fn terrain(first: u8) -> anyhow::Result<SLP> {
+        let frames = (first..first + 4)
+            .map(|index| {
+                #[rustfmt::skip]
+                let indices = vec![
+                    0, index, index, 0,
+                    index, index, index, index,
+                    0, index, index, 0,
+                ];
+                IndexedFrame::from_indices(4, 3, indices, 0)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut data = vec![];
+        SLP::from_frames(&frames).write_to(&mut data)?;
+        Ok(SLP::from_bytes(data)?)
+    }
+
+    #[test]
+    fn render_grid() -> anyhow::Result<()> {
+        let palette: Vec<_> = (0..=255).map(|i| RGB8::new(i, i, i)).collect();
+        let grass = terrain(10)?;
+        let renderer = TerrainRenderer::new(&palette)
+            .terrain(1, &grass)
+            .tile_size(4, 2)
+            .elevation_height(1);
+
+        let mut grid = TerrainGrid::from_fn(2, 2, |_, _| TerrainTile::new(1, 0));
+        let image = renderer.render(&grid)?;
+        assert_eq!((image.width(), image.height()), (8, 4));
+        let gray = |i| Some(RGBA8::new(i, i, i, 255));
+        // Tile (0, 0) is at the top, tile (1, 1) at the bottom, using the 2×2 pattern.
+        assert_eq!(image.pixel(3, 0), gray(10));
+        assert_eq!(image.pixel(1, 2), gray(12));
+        assert_eq!(image.pixel(5, 2), gray(11));
+        assert_eq!(image.pixel(3, 3), gray(13));
+        assert_eq!(image.pixel(0, 0), Some(RGBA8::default()));
+
+        // A raised tile makes the image taller, and its slopes are shaded.
+        grid.tile_mut(1, 1).unwrap().elevation = 1;
+        let image = renderer.render(&grid)?;
+        assert_eq!((image.width(), image.height()), (8, 5));
+        assert_eq!(image.pixel(3, 1), gray(10));
+        assert_eq!(image.pixel(5, 2), gray(11 * 9 / 8));
+        assert_eq!(image.pixel(3, 3), gray(13 * 10 / 8));
+        let image = renderer.clone().lightmask(false).render(&grid)?;
+        assert_eq!(image.pixel(3, 3), gray(13));
+
+        // Terrains without a sprite are not drawn.
+        grid.tile_mut(0, 0).unwrap().terrain = 2;
+        let image = renderer.lightmask(false).render(&grid)?;
+        assert_eq!(image.pixel(3, 1), Some(RGBA8::default()));
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! The drawing commands shared by the layers of SMX and SMP files.
+//!
+//! Each command is a single byte: the lower 2 bits are the kind of command, and the upper 6 bits
+//! are the number of pixels it applies to, minus 1.
+
+use crate::{Error, Layer, Result};
+use byteorder::{ReadBytesExt, LE};
+use std::io::Read;
+
+/// Leave pixels transparent.
+pub(crate) const SKIP: u8 = 0b00;
+/// Draw pixels.
+pub(crate) const DRAW: u8 = 0b01;
+/// Draw pixels in the player's colour.
+pub(crate) const DRAW_PLAYER_COLOR: u8 = 0b10;
+/// End the row.
+pub(crate) const END_OF_ROW: u8 = 0b11;
+
+/// Outline table value for rows that do not contain any pixels.
+pub(crate) const EMPTY_ROW: u16 = 0xFFFF;
+
+/// Read the outline table: the number of transparent pixels at the start and end of each row.
+pub(crate) fn read_outline_table(mut input: impl Read, height: u32) -> Result<Vec<(u16, u16)>> {
+    (0..height)
+        .map(|_| Ok((input.read_u16::<LE>()?, input.read_u16::<LE>()?)))
+        .collect()
+}
+
+/// Decode the commands of a row into `row`, after `left` transparent pixels. `draw` reads the
+/// data for a pixel drawn with the given kind of command, if the layer stores data along with its
+/// commands.
+pub(crate) fn decode_row<R: Read, P>(
+    commands: &mut R,
+    row: &mut [P],
+    left: u16,
+    y: u32,
+    mut draw: impl FnMut(&mut R, u8) -> Result<P>,
+) -> Result<()> {
+    let mut x = usize::from(left);
+    loop {
+        let command = commands.read_u8()?;
+        let kind = command & 0b11;
+        if kind == END_OF_ROW {
+            return Ok(());
+        }
+        let count = usize::from(command >> 2) + 1;
+        if x + count > row.len() {
+            return Err(Error::RowOverflowError(y));
+        }
+        if kind != SKIP {
+            for pixel in &mut row[x..x + count] {
+                *pixel = draw(commands, kind)?;
+            }
+        }
+        x += count;
+    }
+}
+
+/// Encode the rows of a layer, returning the outline table. `kind` returns the kind of command
+/// that draws a pixel, and `draw` writes the data for a drawn pixel, if the layer stores data
+/// along with its commands. Rows without pixels do not get any commands.
+pub(crate) fn encode_rows<P>(
+    layer: &Layer<P>,
+    kind: impl Fn(&P) -> u8,
+    commands: &mut Vec<u8>,
+    mut draw: impl FnMut(&mut Vec<u8>, &P),
+) -> Vec<(u16, u16)> {
+    let mut outlines = vec![];
+    for row in layer.rows() {
+        let left = row.iter().take_while(|p| kind(p) == SKIP).count();
+        if left == row.len() {
+            outlines.push((EMPTY_ROW, EMPTY_ROW));
+            continue;
+        }
+        let right = row.iter().rev().take_while(|p| kind(p) == SKIP).count();
+        outlines.push((left as u16, right as u16));
+
+        let mut x = left;
+        while x < row.len() - right {
+            let command = kind(&row[x]);
+            let count = row[x..row.len() - right]
+                .iter()
+                .take(64)
+                .take_while(|p| kind(p) == command)
+                .count();
+            commands.push(((count - 1) as u8) << 2 | command);
+            if command != SKIP {
+                for pixel in &row[x..x + count] {
+                    draw(commands, pixel);
+                }
+            }
+            x += count;
+        }
+        commands.push(END_OF_ROW);
+    }
+    outlines
+}
@@ -0,0 +1,186 @@
+//! Converting frames between the SMX layers and single SLP images.
+
+use crate::{Frame, GraphicPixel, Layer};
+use genie_slp::{IndexedFrame, Pixel};
+
+/// Shadow intensity used for the shadow pixels of SLP frames.
+const SLP_SHADOW_INTENSITY: u8 = 255;
+
+/// Create a layer with the size and anchor point of an SLP frame.
+fn layer<P>(frame: &IndexedFrame, pixels: Vec<P>) -> Layer<P> {
+    Layer {
+        width: frame.width(),
+        height: frame.height(),
+        hotspot: frame.hotspot(),
+        pixels,
+    }
+}
+
+impl Frame {
+    /// Merge the layers into a single SLP frame, large enough to hold all layers.
+    ///
+    /// Graphic pixels are drawn over outline pixels, which are drawn over shadow pixels. Palette
+    /// sections are dropped, because SLP frames only use a single palette of 256 colours.
+    pub fn to_indexed(&self) -> IndexedFrame {
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+        let mut include = |width: u32, height: u32, (x, y): (i32, i32)| {
+            let (left, top, right, bottom) = (-x, -y, width as i32 - x, height as i32 - y);
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                None => (left, top, right, bottom),
+            });
+        };
+        if let Some(layer) = &self.graphic {
+            include(layer.width, layer.height, layer.hotspot);
+        }
+        if let Some(layer) = &self.shadow {
+            include(layer.width, layer.height, layer.hotspot);
+        }
+        if let Some(layer) = &self.outline {
+            include(layer.width, layer.height, layer.hotspot);
+        }
+        let (left, top, right, bottom) = match bounds {
+            Some(bounds) => bounds,
+            None => return IndexedFrame::new(0, 0),
+        };
+
+        let mut frame = IndexedFrame::new((right - left) as u32, (bottom - top) as u32)
+            .with_hotspot(-left, -top);
+        let width = frame.width() as i32;
+        for (i, pixel) in frame.pixels_mut().iter_mut().enumerate() {
+            let x = i as i32 % width + left;
+            let y = i as i32 / width + top;
+            let graphic = self
+                .graphic
+                .as_ref()
+                .and_then(|layer| layer.pixel_at_anchor(x, y));
+            let outline = self
+                .outline
+                .as_ref()
+                .and_then(|layer| layer.pixel_at_anchor(x, y));
+            let shadow = self
+                .shadow
+                .as_ref()
+                .and_then(|layer| layer.pixel_at_anchor(x, y));
+            *pixel = match (graphic, outline, shadow) {
+                (Some(GraphicPixel::Color { index, .. }), _, _) => Pixel::Color(*index),
+                (Some(GraphicPixel::PlayerColor { index, .. }), _, _) => Pixel::PlayerColor(*index),
+                (_, Some(true), _) => Pixel::PlayerOutline,
+                (_, _, Some(intensity)) if *intensity > 0 => Pixel::Shadow,
+                _ => Pixel::Transparent,
+            };
+        }
+        frame
+    }
+
+    /// Split an SLP frame into layers, using the given palette number.
+    ///
+    /// Colours are placed in palette section 0, and shadows are drawn at full intensity. The
+    /// shadow and outline layers are only added if the frame contains shadow or outline pixels.
+    pub fn from_indexed(frame: &IndexedFrame, palette: u8) -> Self {
+        let graphic = frame
+            .pixels()
+            .iter()
+            .map(|pixel| match *pixel {
+                Pixel::Color(index) => GraphicPixel::Color { index, section: 0 },
+                Pixel::PlayerColor(index) => GraphicPixel::PlayerColor { index, section: 0 },
+                _ => GraphicPixel::Transparent,
+            })
+            .collect();
+        let shadow: Vec<_> = frame
+            .pixels()
+            .iter()
+            .map(|pixel| match pixel {
+                Pixel::Shadow => SLP_SHADOW_INTENSITY,
+                _ => 0,
+            })
+            .collect();
+        let outline: Vec<_> = frame
+            .pixels()
+            .iter()
+            .map(|pixel| matches!(pixel, Pixel::PlayerOutline | Pixel::Outline))
+            .collect();
+
+        let mut result = Self::new(palette).with_graphic(layer(frame, graphic));
+        if shadow.iter().any(|intensity| *intensity > 0) {
+            result = result.with_shadow(layer(frame, shadow));
+        }
+        if outline.iter().any(|drawn| *drawn) {
+            result = result.with_outline(layer(frame, outline));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SMX;
+    use genie_slp::SLP;
+
+    #[test]
+    fn merge_layers() -> anyhow::Result<()> {
+        let graphic = Layer::from_pixels(
+            2,
+            1,
+            vec![
+                GraphicPixel::Color {
+                    index: 4,
+                    section: 2,
+                },
+                GraphicPixel::PlayerColor {
+                    index: 1,
+                    section: 0,
+                },
+            ],
+        )?
+        .with_hotspot(1, 0);
+        let shadow = Layer::from_pixels(2, 2, vec![0, 0, 9, 9])?.with_hotspot(0, 0);
+        let outline = Layer::from_pixels(1, 1, vec![true])?.with_hotspot(-1, 0);
+        let frame = Frame::new(0)
+            .with_graphic(graphic)
+            .with_shadow(shadow)
+            .with_outline(outline);
+
+        let indexed = frame.to_indexed();
+        assert_eq!((indexed.width(), indexed.height()), (3, 2));
+        assert_eq!(indexed.hotspot(), (1, 0));
+        use Pixel::*;
+        #[rustfmt::skip]
+        assert_eq!(indexed.pixels(), &[
+            Color(4), PlayerColor(1), PlayerOutline,
+            Transparent, Shadow, Shadow,
+        ]);
+        assert_eq!(Frame::new(0).to_indexed(), IndexedFrame::new(0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn slp_to_smx() -> anyhow::Result<()> {
+        use Pixel::*;
+        #[rustfmt::skip]
+        let pixels = vec![
+            Transparent, Color(10), Color(11), Shadow,
+            PlayerColor(2), PlayerColor(2), Outline, Transparent,
+        ];
+        let indexed = IndexedFrame::from_pixels(4, 2, pixels)?.with_hotspot(2, 1);
+        let slp = SLP::from_frames(&[indexed]);
+        let frames = slp
+            .frames()
+            .map(|frame| Ok(Frame::from_indexed(&frame.to_indexed()?, 7)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert!(frames[0].shadow().is_some());
+        assert!(frames[0].outline().is_some());
+
+        let mut bytes = vec![];
+        SMX::from_frames(frames).write_to(&mut bytes)?;
+        let smx = SMX::read_from(&bytes[..])?;
+        let frame = &smx.frames()[0];
+        assert_eq!(frame.palette(), 7);
+
+        let mut expected = slp.frame(0).unwrap().to_indexed()?;
+        expected.set_pixel(2, 1, PlayerOutline)?;
+        assert_eq!(frame.to_indexed(), expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! .smx and .smp are the sprite file formats of Age of Empires 2: Definitive Edition, which
+//! replace the .slp files of earlier versions. .smp files are the uncompressed sources of the
+//! sprites, and .smx files are the compressed versions that the game loads.
+//!
+//! Each frame of a sprite has up to three layers, each with their own size and anchor point: the
+//! graphic itself, its shadow, and the outline that the game draws when the unit is behind a
+//! building. [`Frame::to_indexed`] merges the layers into a [`genie_slp::IndexedFrame`], and
+//! [`Frame::from_indexed`] splits one into layers, so sprites can be converted between the SLP
+//! and SMX formats.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use genie_smx::{Frame, GraphicPixel, Layer, SMX};
+//!
+//! let graphic = Layer::from_pixels(2, 1, vec![
+//!     GraphicPixel::Color { index: 42, section: 1 },
+//!     GraphicPixel::PlayerColor { index: 3, section: 0 },
+//! ])?.with_hotspot(1, 1);
+//! let frame = Frame::new(21).with_graphic(graphic);
+//!
+//! let mut bytes = vec![];
+//! SMX::from_frames(vec![frame.clone()]).write_to(&mut bytes)?;
+//! let smx = SMX::read_from(&bytes[..])?;
+//! assert_eq!(smx.frames(), &[frame]);
+//! # Ok(())
+//! # }
+//! ```
+
+#![deny(future_incompatible)]
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(unsafe_code)]
+#![warn(unused)]
+#![allow(missing_docs)]
+
+mod commands;
+mod convert;
+mod smp;
+mod smx;
+
+pub use smp::SMP;
+pub use smx::SMX;
+
+/// Errors that may occur while reading or writing an SMX or SMP file.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The file does not start with the expected signature.
+    #[error("unexpected file signature {:?}", .0)]
+    InvalidSignatureError([u8; 4]),
+    /// A row draws more pixels than the layer is wide.
+    #[error("row {} is wider than the layer", .0)]
+    RowOverflowError(u32),
+    /// The number of pixels does not match the size of the layer.
+    #[error("expected {} pixels, got {}", .0, .1)]
+    LayerSizeError(usize, usize),
+    /// A layer is too large to be stored in an SMX file.
+    #[error("layer of {}×{} pixels is too large", .0, .1)]
+    LayerTooLargeError(u32, u32),
+    /// An I/O error occurred.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A pixel of the graphic layer of a frame.
+///
+/// Definitive Edition palettes are split into sections of 256 colours, so a colour is identified
+/// by its index within a section, and the section.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphicPixel {
+    /// A pixel that is not drawn.
+    #[default]
+    Transparent,
+    /// A pixel with a palette colour.
+    Color { index: u8, section: u8 },
+    /// A pixel in the colour of the player that owns the unit, looked up in the player colour
+    /// palette.
+    PlayerColor { index: u8, section: u8 },
+}
+
+impl GraphicPixel {
+    /// Get the index of the colour in the palette, counting across all sections.
+    pub fn palette_index(self) -> Option<u16> {
+        match self {
+            GraphicPixel::Transparent => None,
+            GraphicPixel::Color { index, section }
+            | GraphicPixel::PlayerColor { index, section } => {
+                Some(u16::from(section) << 8 | u16::from(index))
+            }
+        }
+    }
+}
+
+/// A layer of a frame: an image with its own size and anchor point.
+///
+/// The pixels of the graphic layer are [`GraphicPixel`]s, the pixels of the shadow layer are the
+/// intensity of the shadow, with 0 for no shadow, and the pixels of the outline layer are
+/// whether the outline is drawn there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer<P> {
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    pixels: Vec<P>,
+}
+
+impl<P: Clone + Default> Layer<P> {
+    /// Create a layer where no pixels are drawn.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            hotspot: (0, 0),
+            pixels: vec![P::default(); width as usize * height as usize],
+        }
+    }
+}
+
+impl<P> Layer<P> {
+    /// Create a layer from its pixels, row by row from the top left.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<P>) -> Result<Self> {
+        let expected = width as usize * height as usize;
+        if pixels.len() != expected {
+            return Err(Error::LayerSizeError(expected, pixels.len()));
+        }
+        Ok(Self {
+            width,
+            height,
+            hotspot: (0, 0),
+            pixels,
+        })
+    }
+
+    /// Set the position of the anchor point of the layer, relative to its top left corner.
+    pub fn with_hotspot(mut self, x: i32, y: i32) -> Self {
+        self.hotspot = (x, y);
+        self
+    }
+
+    /// Get the width of the layer in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the layer in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the position of the anchor point of the layer, relative to its top left corner. The
+    /// anchor points of all layers in a frame are drawn at the same position.
+    pub fn hotspot(&self) -> (i32, i32) {
+        self.hotspot
+    }
+
+    /// Get the pixels of the layer, row by row from the top left.
+    pub fn pixels(&self) -> &[P] {
+        &self.pixels
+    }
+
+    /// Get the pixels of the layer mutably, row by row from the top left.
+    pub fn pixels_mut(&mut self) -> &mut [P] {
+        &mut self.pixels
+    }
+
+    /// Get a pixel by its position relative to the anchor point.
+    fn pixel_at_anchor(&self, x: i32, y: i32) -> Option<&P> {
+        let x = x + self.hotspot.0;
+        let y = y + self.hotspot.1;
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        self.pixels
+            .get(y as usize * self.width as usize + x as usize)
+    }
+
+    /// Iterate over the rows of the layer.
+    fn rows(&self) -> impl Iterator<Item = &[P]> {
+        self.pixels
+            .chunks(self.width.max(1) as usize)
+            .take(self.height as usize)
+    }
+}
+
+/// A frame of a sprite, made up of the graphic, shadow and outline layers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    palette: u8,
+    graphic: Option<Layer<GraphicPixel>>,
+    shadow: Option<Layer<u8>>,
+    outline: Option<Layer<bool>>,
+}
+
+impl Frame {
+    /// Create a frame without layers, that uses the given palette number.
+    pub fn new(palette: u8) -> Self {
+        Self {
+            palette,
+            graphic: None,
+            shadow: None,
+            outline: None,
+        }
+    }
+
+    /// Set the graphic layer.
+    pub fn with_graphic(mut self, graphic: Layer<GraphicPixel>) -> Self {
+        self.graphic = Some(graphic);
+        self
+    }
+
+    /// Set the shadow layer.
+    pub fn with_shadow(mut self, shadow: Layer<u8>) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// Set the outline layer.
+    pub fn with_outline(mut self, outline: Layer<bool>) -> Self {
+        self.outline = Some(outline);
+        self
+    }
+
+    /// Get the number of the palette used by the graphic layer. SMP files do not store it, so it
+    /// is 0 for frames read from SMP files.
+    pub fn palette(&self) -> u8 {
+        self.palette
+    }
+
+    /// Get the graphic layer.
+    pub fn graphic(&self) -> Option<&Layer<GraphicPixel>> {
+        self.graphic.as_ref()
+    }
+
+    /// Get the shadow layer.
+    pub fn shadow(&self) -> Option<&Layer<u8>> {
+        self.shadow.as_ref()
+    }
+
+    /// Get the outline layer.
+    pub fn outline(&self) -> Option<&Layer<bool>> {
+        self.outline.as_ref()
+    }
+}
@@ -0,0 +1,307 @@
+//! Reading SMP files, the uncompressed sources of SMX sprites.
+//!
+//! Unlike SMX files, every frame in an SMP file has a table of offsets to the commands of each
+//! row, and every drawn pixel is stored in 4 bytes, in all layers.
+
+use crate::commands::{decode_row, read_outline_table, DRAW_PLAYER_COLOR, EMPTY_ROW};
+use crate::{Error, Frame, GraphicPixel, Layer, Result};
+use byteorder::{ReadBytesExt, LE};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// The signature at the start of SMP files.
+const SIGNATURE: [u8; 4] = *b"SMP$";
+
+/// Layer type of graphic layers.
+const GRAPHIC_LAYER: u32 = 0x02;
+/// Layer type of shadow layers.
+const SHADOW_LAYER: u32 = 0x04;
+/// Layer type of outline layers.
+const OUTLINE_LAYER: u32 = 0x08;
+
+/// An SMP sprite file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SMP {
+    version: u32,
+    num_animations: u32,
+    frames_per_animation: u32,
+    frames: Vec<Frame>,
+}
+
+/// The header of a layer. Offsets are relative to the start of the frame.
+struct LayerHeader {
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    layer_type: u32,
+    outline_table_offset: u32,
+    command_table_offset: u32,
+}
+
+impl LayerHeader {
+    fn read_from(mut input: impl Read) -> Result<Self> {
+        let header = Self {
+            width: input.read_u32::<LE>()?,
+            height: input.read_u32::<LE>()?,
+            hotspot: (input.read_i32::<LE>()?, input.read_i32::<LE>()?),
+            layer_type: input.read_u32::<LE>()?,
+            outline_table_offset: input.read_u32::<LE>()?,
+            command_table_offset: input.read_u32::<LE>()?,
+        };
+        let _flags = input.read_u32::<LE>()?;
+        Ok(header)
+    }
+
+    fn read_layer<P: Clone + Default>(
+        &self,
+        input: &mut Cursor<&[u8]>,
+        frame_offset: u64,
+        mut draw: impl FnMut([u8; 4], u8) -> P,
+    ) -> Result<Layer<P>> {
+        input.seek(SeekFrom::Start(
+            frame_offset + u64::from(self.outline_table_offset),
+        ))?;
+        let outlines = read_outline_table(input.by_ref(), self.height)?;
+        input.seek(SeekFrom::Start(
+            frame_offset + u64::from(self.command_table_offset),
+        ))?;
+        let row_offsets = (0..self.height)
+            .map(|_| input.read_u32::<LE>())
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        let (x, y) = self.hotspot;
+        let mut layer = Layer::new(self.width, self.height).with_hotspot(x, y);
+        let width = layer.width.max(1) as usize;
+        let rows = layer
+            .pixels
+            .chunks_mut(width)
+            .zip(outlines)
+            .zip(row_offsets);
+        for (y, ((row, (left, _)), row_offset)) in rows.enumerate() {
+            if left == EMPTY_ROW {
+                continue;
+            }
+            input.seek(SeekFrom::Start(frame_offset + u64::from(row_offset)))?;
+            decode_row(input, row, left, y as u32, |input, kind| {
+                let mut pixel = [0; 4];
+                input.read_exact(&mut pixel)?;
+                Ok(draw(pixel, kind))
+            })?;
+        }
+        Ok(layer)
+    }
+}
+
+fn read_frame(input: &mut Cursor<&[u8]>, offset: u32) -> Result<Frame> {
+    let offset = u64::from(offset);
+    input.seek(SeekFrom::Start(offset))?;
+    let num_layers = input.read_u32::<LE>()?;
+    let headers = (0..num_layers)
+        .map(|_| LayerHeader::read_from(input.by_ref()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut frame = Frame::new(0);
+    for header in headers {
+        match header.layer_type {
+            GRAPHIC_LAYER => {
+                frame.graphic = Some(header.read_layer(input, offset, |pixel, kind| {
+                    let (index, section) = (pixel[0], pixel[1] & 0b11);
+                    if kind == DRAW_PLAYER_COLOR {
+                        GraphicPixel::PlayerColor { index, section }
+                    } else {
+                        GraphicPixel::Color { index, section }
+                    }
+                })?);
+            }
+            SHADOW_LAYER => {
+                frame.shadow = Some(header.read_layer(input, offset, |pixel, _| pixel[0])?);
+            }
+            OUTLINE_LAYER => {
+                frame.outline = Some(header.read_layer(input, offset, |_, _| true)?);
+            }
+            // Other layers, like the damage masks, are not supported.
+            _ => (),
+        }
+    }
+    Ok(frame)
+}
+
+impl SMP {
+    /// Read an SMP file.
+    pub fn read_from(mut input: impl Read) -> Result<Self> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        let mut input = Cursor::new(&data[..]);
+
+        let mut signature = [0; 4];
+        input.read_exact(&mut signature)?;
+        if signature != SIGNATURE {
+            return Err(Error::InvalidSignatureError(signature));
+        }
+        let version = input.read_u32::<LE>()?;
+        let num_frames = input.read_u32::<LE>()?;
+        let num_animations = input.read_u32::<LE>()?;
+        let frames_per_animation = input.read_u32::<LE>()?;
+        let _checksum = input.read_u32::<LE>()?;
+        let _size = input.read_u32::<LE>()?;
+        let _source_format = input.read_u32::<LE>()?;
+        let mut comment = [0; 32];
+        input.read_exact(&mut comment)?;
+
+        let offsets = (0..num_frames)
+            .map(|_| input.read_u32::<LE>())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let frames = offsets
+            .into_iter()
+            .map(|offset| read_frame(&mut input, offset))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            version,
+            num_animations,
+            frames_per_animation,
+            frames,
+        })
+    }
+
+    /// Get the format version of the file.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Get the number of animations in the file, usually one for each direction the unit can
+    /// face.
+    pub fn num_animations(&self) -> u32 {
+        self.num_animations
+    }
+
+    /// Get the number of frames in each animation.
+    pub fn frames_per_animation(&self) -> u32 {
+        self.frames_per_animation
+    }
+
+    /// Get the frames in the file.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Get the frames in the file, to convert them to another format.
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size of the layer headers of the test frame, which has 3 layers.
+    const HEADERS_LEN: u32 = 4 + 3 * 32;
+
+    /// Append the tables and commands of a layer to `body`, and its header to `headers`.
+    fn layer(
+        body: &mut Vec<u8>,
+        headers: &mut Vec<u32>,
+        layer_type: u32,
+        (width, height): (u32, u32),
+        outlines: &[(u16, u16)],
+        rows: &[&[u8]],
+    ) {
+        let outline_table = HEADERS_LEN + body.len() as u32;
+        for (left, right) in outlines {
+            body.extend_from_slice(&left.to_le_bytes());
+            body.extend_from_slice(&right.to_le_bytes());
+        }
+        let command_table = HEADERS_LEN + body.len() as u32;
+        let mut offset = command_table + 4 * rows.len() as u32;
+        for row in rows {
+            body.extend_from_slice(&offset.to_le_bytes());
+            offset += row.len() as u32;
+        }
+        for row in rows {
+            body.extend_from_slice(row);
+        }
+        headers.extend_from_slice(&[
+            width,
+            height,
+            1,
+            2,
+            layer_type,
+            outline_table,
+            command_table,
+            0,
+        ]);
+    }
+
+    #[test]
+    fn read_smp() -> anyhow::Result<()> {
+        let mut body = vec![];
+        let mut headers = vec![3];
+        layer(
+            &mut body,
+            &mut headers,
+            GRAPHIC_LAYER,
+            (3, 2),
+            &[(1, 0), (EMPTY_ROW, EMPTY_ROW)],
+            &[&[0b01, 7, 1, 0, 0, 0b10, 3, 0, 0, 0, 0b11], &[]],
+        );
+        layer(
+            &mut body,
+            &mut headers,
+            SHADOW_LAYER,
+            (2, 1),
+            &[(0, 0)],
+            &[&[0b0101, 50, 0, 0, 0, 60, 0, 0, 0, 0b11]],
+        );
+        layer(
+            &mut body,
+            &mut headers,
+            OUTLINE_LAYER,
+            (2, 1),
+            &[(0, 1)],
+            &[&[0b01, 0, 0, 0, 0, 0b11]],
+        );
+
+        let mut data = vec![];
+        data.extend_from_slice(b"SMP$");
+        for value in &[11u32, 1, 1, 1, 0, 0, 0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&(64u32 + 4).to_le_bytes());
+        for value in headers {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&body);
+
+        let smp = SMP::read_from(&data[..])?;
+        assert_eq!(smp.version(), 11);
+        assert_eq!(smp.frames().len(), 1);
+        let frame = &smp.frames()[0];
+        let graphic = frame.graphic().unwrap();
+        assert_eq!(graphic.hotspot(), (1, 2));
+        assert_eq!(
+            graphic.pixels(),
+            &[
+                GraphicPixel::Transparent,
+                GraphicPixel::Color {
+                    index: 7,
+                    section: 1
+                },
+                GraphicPixel::PlayerColor {
+                    index: 3,
+                    section: 0
+                },
+                GraphicPixel::Transparent,
+                GraphicPixel::Transparent,
+                GraphicPixel::Transparent,
+            ]
+        );
+        assert_eq!(frame.shadow().unwrap().pixels(), &[50, 60]);
+        assert_eq!(frame.outline().unwrap().pixels(), &[true, false]);
+
+        assert!(matches!(
+            SMP::read_from(&b"SMPX"[..]),
+            Err(Error::InvalidSignatureError(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+//! Reading and writing SMX files, the compressed sprites that the game loads.
+
+use crate::commands::{
+    decode_row, encode_rows, read_outline_table, DRAW, DRAW_PLAYER_COLOR, EMPTY_ROW, SKIP,
+};
+use crate::{Error, Frame, GraphicPixel, Layer, Result};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::convert::TryFrom;
+use std::io::{self, Cursor, Read, Write};
+
+/// The signature at the start of SMX files.
+const SIGNATURE: [u8; 4] = *b"SMPX";
+
+/// The frame has a graphic layer.
+const GRAPHIC_LAYER: u8 = 0x01;
+/// The frame has a shadow layer.
+const SHADOW_LAYER: u8 = 0x02;
+/// The frame has an outline layer.
+const OUTLINE_LAYER: u8 = 0x04;
+/// The pixels of the graphic layer use the 8to5 compression instead of 4plus1.
+const COMPRESSION_8TO5: u8 = 0x08;
+
+/// An SMX sprite file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SMX {
+    version: u16,
+    frames: Vec<Frame>,
+}
+
+/// Reads the pixels of the graphic layer, which are stored separately from the commands.
+///
+/// With 4plus1 compression, every 4 pixels are stored in 5 bytes: their palette indices, and then
+/// a byte with the 2-bit palette section of each pixel. With 8to5 compression, every 2 pixels are
+/// stored in 5 bytes: 20 bits per pixel, for the palette index, palette section and damage
+/// modifiers. The damage modifiers are not kept.
+struct GraphicPixels<'a> {
+    data: &'a [u8],
+    compression_8to5: bool,
+    next: usize,
+}
+
+impl GraphicPixels<'_> {
+    fn next_pixel(&mut self) -> Result<(u8, u8)> {
+        let n = self.next;
+        self.next += 1;
+        let (block, offset) = if self.compression_8to5 {
+            (n / 2, n % 2)
+        } else {
+            (n / 4, n % 4)
+        };
+        let bytes = self
+            .data
+            .get(block * 5..block * 5 + 5)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        if self.compression_8to5 {
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0u64, |value, byte| value << 8 | u64::from(*byte));
+            let pixel = value >> (20 * offset);
+            Ok((pixel as u8, (pixel >> 8) as u8 & 0b11))
+        } else {
+            Ok((bytes[offset], bytes[4] >> (2 * offset) & 0b11))
+        }
+    }
+}
+
+/// The header of a layer.
+struct LayerHeader {
+    width: u32,
+    height: u32,
+    hotspot: (i32, i32),
+    len: u32,
+}
+
+impl LayerHeader {
+    fn read_from(mut input: impl Read) -> Result<Self> {
+        let width = input.read_u16::<LE>()?.into();
+        let height = input.read_u16::<LE>()?.into();
+        let hotspot = (
+            input.read_i16::<LE>()?.into(),
+            input.read_i16::<LE>()?.into(),
+        );
+        let len = input.read_u32::<LE>()?;
+        let _unknown = input.read_u32::<LE>()?;
+        Ok(Self {
+            width,
+            height,
+            hotspot,
+            len,
+        })
+    }
+
+    fn write_to(&self, mut output: impl Write) -> Result<()> {
+        let too_large = || Error::LayerTooLargeError(self.width, self.height);
+        output.write_u16::<LE>(u16::try_from(self.width).map_err(|_| too_large())?)?;
+        output.write_u16::<LE>(u16::try_from(self.height).map_err(|_| too_large())?)?;
+        output.write_i16::<LE>(i16::try_from(self.hotspot.0).map_err(|_| too_large())?)?;
+        output.write_i16::<LE>(i16::try_from(self.hotspot.1).map_err(|_| too_large())?)?;
+        output.write_u32::<LE>(self.len)?;
+        output.write_u32::<LE>(0)?;
+        Ok(())
+    }
+
+    fn layer<P: Clone + Default>(&self) -> Layer<P> {
+        let (x, y) = self.hotspot;
+        Layer::new(self.width, self.height).with_hotspot(x, y)
+    }
+
+    /// Read the layer data that follows the header.
+    fn read_body(&self, mut input: impl Read) -> Result<Vec<u8>> {
+        let mut body = vec![0; self.len as usize];
+        input.read_exact(&mut body)?;
+        Ok(body)
+    }
+}
+
+fn read_graphic_layer(mut input: impl Read, compression_8to5: bool) -> Result<Layer<GraphicPixel>> {
+    let header = LayerHeader::read_from(input.by_ref())?;
+    let body = header.read_body(input)?;
+    let mut body = Cursor::new(&body[..]);
+    let outlines = read_outline_table(&mut body, header.height)?;
+    let commands_len = body.read_u32::<LE>()? as usize;
+    let pixels_len = body.read_u32::<LE>()? as usize;
+    let start = body.position() as usize;
+    let data = body.into_inner();
+    let mut commands = Cursor::new(
+        data.get(start..start + commands_len)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?,
+    );
+    let mut pixels = GraphicPixels {
+        data: data
+            .get(start + commands_len..start + commands_len + pixels_len)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?,
+        compression_8to5,
+        next: 0,
+    };
+
+    let mut layer = header.layer();
+    let width = layer.width.max(1) as usize;
+    for (y, (row, (left, _))) in layer.pixels.chunks_mut(width).zip(outlines).enumerate() {
+        if left == EMPTY_ROW {
+            continue;
+        }
+        decode_row(&mut commands, row, left, y as u32, |_, kind| {
+            let (index, section) = pixels.next_pixel()?;
+            Ok(if kind == DRAW_PLAYER_COLOR {
+                GraphicPixel::PlayerColor { index, section }
+            } else {
+                GraphicPixel::Color { index, section }
+            })
+        })?;
+    }
+    Ok(layer)
+}
+
+/// Read a shadow or outline layer, where the data of the drawn pixels follows their command.
+fn read_layer<P: Clone + Default>(
+    mut input: impl Read,
+    mut draw: impl FnMut(&mut Cursor<&[u8]>) -> Result<P>,
+) -> Result<Layer<P>> {
+    let header = LayerHeader::read_from(input.by_ref())?;
+    let body = header.read_body(input)?;
+    let mut body = Cursor::new(&body[..]);
+    let outlines = read_outline_table(&mut body, header.height)?;
+    let _data_len = body.read_u32::<LE>()?;
+
+    let mut layer = header.layer();
+    let width = layer.width.max(1) as usize;
+    for (y, (row, (left, _))) in layer.pixels.chunks_mut(width).zip(outlines).enumerate() {
+        if left == EMPTY_ROW {
+            continue;
+        }
+        decode_row(&mut body, row, left, y as u32, |commands, _| draw(commands))?;
+    }
+    Ok(layer)
+}
+
+fn write_outline_table(output: &mut Vec<u8>, outlines: &[(u16, u16)]) {
+    for (left, right) in outlines {
+        output.extend_from_slice(&left.to_le_bytes());
+        output.extend_from_slice(&right.to_le_bytes());
+    }
+}
+
+fn write_layer_header<P>(output: &mut Vec<u8>, layer: &Layer<P>, len: usize) -> Result<()> {
+    LayerHeader {
+        width: layer.width,
+        height: layer.height,
+        hotspot: layer.hotspot,
+        len: len as u32,
+    }
+    .write_to(output)
+}
+
+/// Write the graphic layer, using 4plus1 compression for the pixels.
+fn write_graphic_layer(output: &mut Vec<u8>, layer: &Layer<GraphicPixel>) -> Result<()> {
+    let kind = |pixel: &GraphicPixel| match pixel {
+        GraphicPixel::Transparent => SKIP,
+        GraphicPixel::Color { .. } => DRAW,
+        GraphicPixel::PlayerColor { .. } => DRAW_PLAYER_COLOR,
+    };
+    let mut commands = vec![];
+    let mut pixels = vec![];
+    let outlines = encode_rows(layer, kind, &mut commands, |_, pixel| match *pixel {
+        GraphicPixel::Color { index, section } | GraphicPixel::PlayerColor { index, section } => {
+            pixels.push((index, section))
+        }
+        GraphicPixel::Transparent => unreachable!(),
+    });
+
+    let mut pixel_data = vec![];
+    for block in pixels.chunks(4) {
+        let mut sections = 0;
+        for (offset, (index, section)) in block.iter().enumerate() {
+            pixel_data.push(*index);
+            sections |= (section & 0b11) << (2 * offset);
+        }
+        pixel_data.resize(pixel_data.len() + 4 - block.len(), 0);
+        pixel_data.push(sections);
+    }
+
+    let mut body = vec![];
+    write_outline_table(&mut body, &outlines);
+    body.extend_from_slice(&(commands.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(pixel_data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&commands);
+    body.extend_from_slice(&pixel_data);
+    write_layer_header(output, layer, body.len())?;
+    output.extend_from_slice(&body);
+    Ok(())
+}
+
+/// Write a shadow or outline layer, where the data of the drawn pixels follows their command.
+fn write_layer<P>(
+    output: &mut Vec<u8>,
+    layer: &Layer<P>,
+    kind: impl Fn(&P) -> u8,
+    draw: impl FnMut(&mut Vec<u8>, &P),
+) -> Result<()> {
+    let mut commands = vec![];
+    let outlines = encode_rows(layer, kind, &mut commands, draw);
+
+    let mut body = vec![];
+    write_outline_table(&mut body, &outlines);
+    body.extend_from_slice(&(commands.len() as u32).to_le_bytes());
+    body.extend_from_slice(&commands);
+    write_layer_header(output, layer, body.len())?;
+    output.extend_from_slice(&body);
+    Ok(())
+}
+
+fn read_frame(mut input: impl Read) -> Result<Frame> {
+    let frame_type = input.read_u8()?;
+    let palette = input.read_u8()?;
+    let _uncompressed_size = input.read_u32::<LE>()?;
+
+    let mut frame = Frame::new(palette);
+    if frame_type & GRAPHIC_LAYER != 0 {
+        let compression_8to5 = frame_type & COMPRESSION_8TO5 != 0;
+        frame.graphic = Some(read_graphic_layer(input.by_ref(), compression_8to5)?);
+    }
+    if frame_type & SHADOW_LAYER != 0 {
+        frame.shadow = Some(read_layer(input.by_ref(), |data| Ok(data.read_u8()?))?);
+    }
+    if frame_type & OUTLINE_LAYER != 0 {
+        frame.outline = Some(read_layer(input.by_ref(), |_| Ok(true))?);
+    }
+    Ok(frame)
+}
+
+fn write_frame(output: &mut Vec<u8>, frame: &Frame) -> Result<()> {
+    let mut layers = vec![];
+    let mut frame_type = 0;
+    if let Some(graphic) = &frame.graphic {
+        frame_type |= GRAPHIC_LAYER;
+        write_graphic_layer(&mut layers, graphic)?;
+    }
+    if let Some(shadow) = &frame.shadow {
+        frame_type |= SHADOW_LAYER;
+        let kind = |intensity: &u8| if *intensity == 0 { SKIP } else { DRAW };
+        write_layer(&mut layers, shadow, kind, |data, intensity| {
+            data.push(*intensity)
+        })?;
+    }
+    if let Some(outline) = &frame.outline {
+        frame_type |= OUTLINE_LAYER;
+        let kind = |drawn: &bool| if *drawn { DRAW } else { SKIP };
+        write_layer(&mut layers, outline, kind, |_, _| ())?;
+    }
+
+    output.push(frame_type);
+    output.push(frame.palette);
+    output.extend_from_slice(&(layers.len() as u32).to_le_bytes());
+    output.extend_from_slice(&layers);
+    Ok(())
+}
+
+impl SMX {
+    /// Create an SMX file from frames.
+    pub fn from_frames(frames: Vec<Frame>) -> Self {
+        Self { version: 2, frames }
+    }
+
+    /// Read an SMX file.
+    pub fn read_from(mut input: impl Read) -> Result<Self> {
+        let mut signature = [0; 4];
+        input.read_exact(&mut signature)?;
+        if signature != SIGNATURE {
+            return Err(Error::InvalidSignatureError(signature));
+        }
+        let version = input.read_u16::<LE>()?;
+        let num_frames = input.read_u16::<LE>()?;
+        let _size = input.read_u32::<LE>()?;
+        let _uncompressed_size = input.read_u32::<LE>()?;
+        let mut comment = [0; 16];
+        input.read_exact(&mut comment)?;
+
+        let frames = (0..num_frames)
+            .map(|_| read_frame(input.by_ref()))
+            .collect::<Result<_>>()?;
+        Ok(Self { version, frames })
+    }
+
+    /// Write the SMX file. The graphic layers are written with 4plus1 compression.
+    ///
+    /// SMX files store the size of each frame in the SMP file it was compressed from, which is not
+    /// known when writing, so the compressed size is written in its place.
+    pub fn write_to(&self, mut output: impl Write) -> Result<()> {
+        let mut frames = vec![];
+        for frame in &self.frames {
+            write_frame(&mut frames, frame)?;
+        }
+        output.write_all(&SIGNATURE)?;
+        output.write_u16::<LE>(self.version)?;
+        output.write_u16::<LE>(self.frames.len() as u16)?;
+        output.write_u32::<LE>(frames.len() as u32)?;
+        output.write_u32::<LE>(frames.len() as u32)?;
+        output.write_all(&[0; 16])?;
+        output.write_all(&frames)?;
+        Ok(())
+    }
+
+    /// Get the format version of the file.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Get the frames in the file.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Get the frames in the file mutably, to add, remove or edit frames.
+    pub fn frames_mut(&mut self) -> &mut Vec<Frame> {
+        &mut self.frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers() -> anyhow::Result<Frame> {
+        let mut graphic = Layer::new(80, 3).with_hotspot(40, 2);
+        for (i, pixel) in graphic.pixels_mut().iter_mut().enumerate() {
+            *pixel = match i % 80 {
+                0..=4 => GraphicPixel::Transparent,
+                5..=9 => GraphicPixel::PlayerColor {
+                    index: i as u8,
+                    section: 0,
+                },
+                _ => GraphicPixel::Color {
+                    index: (i * 7) as u8,
+                    section: (i % 4) as u8,
+                },
+            };
+        }
+        graphic.pixels_mut()[80..160]
+            .iter_mut()
+            .for_each(|pixel| *pixel = GraphicPixel::Transparent);
+        let shadow = Layer::from_pixels(4, 2, vec![0, 10, 20, 0, 30, 0, 0, 40])?.with_hotspot(1, 0);
+        let outline = Layer::from_pixels(3, 1, vec![true, false, true])?.with_hotspot(-2, 5);
+        Ok(Frame::new(21)
+            .with_graphic(graphic)
+            .with_shadow(shadow)
+            .with_outline(outline))
+    }
+
+    #[test]
+    fn roundtrip() -> anyhow::Result<()> {
+        let frames = vec![
+            layers()?,
+            Frame::new(0),
+            Frame::new(1).with_graphic(Layer::new(2, 2)),
+        ];
+        let smx = SMX::from_frames(frames.clone());
+        let mut bytes = vec![];
+        smx.write_to(&mut bytes)?;
+        assert_eq!(&bytes[..4], b"SMPX");
+
+        let smx = SMX::read_from(&bytes[..])?;
+        assert_eq!(smx.version(), 2);
+        assert_eq!(smx.frames(), &frames[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn compression_8to5() -> anyhow::Result<()> {
+        // Two pixels: index 5 in section 2 with damage modifiers, and index 200 in section 1.
+        let first: u64 = 5 | 2 << 8 | 0x3FF << 10;
+        let second: u64 = 200 | 1 << 8;
+        let value = first | second << 20;
+        let pixel_data = &value.to_le_bytes()[..5];
+        let commands = [0b0000_0101, 0b11];
+
+        let mut body = vec![];
+        write_outline_table(&mut body, &[(1, 0)]);
+        body.extend_from_slice(&(commands.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(pixel_data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&commands);
+        body.extend_from_slice(pixel_data);
+        let mut bytes = vec![GRAPHIC_LAYER | COMPRESSION_8TO5, 3, 0, 0, 0, 0];
+        write_layer_header(&mut bytes, &Layer::<GraphicPixel>::new(3, 1), body.len())?;
+        bytes.extend_from_slice(&body);
+
+        let frame = read_frame(&bytes[..])?;
+        assert_eq!(frame.palette(), 3);
+        assert_eq!(
+            frame.graphic().unwrap().pixels(),
+            &[
+                GraphicPixel::Transparent,
+                GraphicPixel::Color {
+                    index: 5,
+                    section: 2
+                },
+                GraphicPixel::Color {
+                    index: 200,
+                    section: 1
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_handwritten_file() -> anyhow::Result<()> {
+        // Written out by hand from the format description, not with `SMX::write_to`, so the reader
+        // is checked against the format and not only against the writer.
+        #[rustfmt::skip]
+        let bytes: &[u8] = &[
+            // Signature, version 2, 1 frame, sizes and comment.
+            b'S', b'M', b'P', b'X', 0x02, 0x00, 0x01, 0x00,
+            0x50, 0x00, 0x00, 0x00, 0x50, 0x00, 0x00, 0x00,
+            b'h', b'a', b'n', b'd', b'w', b'r', b'i', b't', b't', b'e', b'n', 0, 0, 0, 0, 0,
+            // Frame with graphic and shadow layers, palette 21.
+            0x03, 0x15, 0x00, 0x01, 0x00, 0x00,
+            // Graphic layer: 3×2, hotspot (1, 2), 31 bytes.
+            0x03, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02, 0x00,
+            0x1F, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            // Outline table, and the sizes of the commands and the pixel data.
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x05, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00,
+            // Draw 2, draw 1 in player colour, end; draw 2, end.
+            0x05, 0x02, 0x03, 0x05, 0x03,
+            // 4plus1 pixel blocks: palette indices, then 2-bit palette sections.
+            0x0A, 0x14, 0x1E, 0x28, 0b11_00_10_01,
+            0x32, 0x00, 0x00, 0x00, 0b00_00_00_10,
+            // Shadow layer: 2×1, hotspot (0, 0), 11 bytes.
+            0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Outline table, size of the commands, draw 1 with intensity 64, end.
+            0x00, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00,
+            0x01, 0x40, 0x03,
+        ];
+
+        let smx = SMX::read_from(bytes)?;
+        assert_eq!(smx.version(), 2);
+        assert_eq!(smx.frames().len(), 1);
+        let frame = &smx.frames()[0];
+        assert_eq!(frame.palette(), 21);
+        assert!(frame.outline().is_none());
+
+        let graphic = frame.graphic().unwrap();
+        assert_eq!((graphic.width(), graphic.height()), (3, 2));
+        assert_eq!(graphic.hotspot(), (1, 2));
+        assert_eq!(
+            graphic.pixels(),
+            &[
+                GraphicPixel::Color {
+                    index: 10,
+                    section: 1
+                },
+                GraphicPixel::Color {
+                    index: 20,
+                    section: 2
+                },
+                GraphicPixel::PlayerColor {
+                    index: 30,
+                    section: 0
+                },
+                GraphicPixel::Transparent,
+                GraphicPixel::Color {
+                    index: 40,
+                    section: 3
+                },
+                GraphicPixel::Color {
+                    index: 50,
+                    section: 2
+                },
+            ]
+        );
+
+        let shadow = frame.shadow().unwrap();
+        assert_eq!((shadow.width(), shadow.height()), (2, 1));
+        assert_eq!(shadow.pixels(), &[64, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_files() -> anyhow::Result<()> {
+        assert!(matches!(
+            SMX::read_from(&b"SLP 0000"[..]),
+            Err(Error::InvalidSignatureError(_))
+        ));
+        let frame = Frame::new(0).with_graphic(Layer::new(70000, 0));
+        assert!(matches!(
+            SMX::from_frames(vec![frame]).write_to(&mut vec![]),
+            Err(Error::LayerTooLargeError(70000, 0))
+        ));
+        Ok(())
+    }
+}
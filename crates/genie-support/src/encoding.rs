@@ -0,0 +1,88 @@
+use crate::{DecodeStringError, EncodeStringError};
+use encoding_rs::{UTF_8, WINDOWS_1252};
+use std::borrow::Cow;
+
+pub use encoding_rs::Encoding;
+
+/// The code page that text is stored in when a file format does not say otherwise.
+///
+/// The original games store text in the code page of the system they run on, which is
+/// WINDOWS-1252 for Western languages. WINDOWS-1252 can decode every byte, so text in a different
+/// code page is garbled but its bytes are kept, and it can be decoded again once the real
+/// encoding is known.
+pub const DEFAULT_ENCODING: &Encoding = WINDOWS_1252;
+
+/// Guess the encoding of some text.
+///
+/// Text that starts with a byte order mark uses the encoding of the byte order mark. Otherwise,
+/// text that is valid UTF-8 is assumed to be UTF-8, and everything else is assumed to use the
+/// [`DEFAULT_ENCODING`]. Plain ASCII text is valid in all of them.
+///
+/// ```rust
+/// use genie_support::{detect_encoding, DEFAULT_ENCODING};
+/// use encoding_rs::{UTF_16LE, UTF_8};
+///
+/// assert_eq!(detect_encoding("Sacsahuamán".as_bytes()), UTF_8);
+/// assert_eq!(detect_encoding(b"Sacsahuam\xE1n"), DEFAULT_ENCODING);
+/// assert_eq!(detect_encoding(b"\xFF\xFEa\0"), UTF_16LE);
+/// ```
+pub fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _bom_length)) = Encoding::for_bom(bytes) {
+        encoding
+    } else if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        DEFAULT_ENCODING
+    }
+}
+
+/// Decode a string from the given encoding.
+///
+/// Byte order marks are not stripped. Fails if `bytes` is not valid in `encoding`.
+pub fn decode_str_with_encoding(
+    bytes: &[u8],
+    encoding: &'static Encoding,
+) -> Result<String, DecodeStringError> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(Cow::into_owned)
+        .ok_or(DecodeStringError)
+}
+
+/// Encode a string in the given encoding.
+///
+/// Fails if `string` contains characters that can not be represented in `encoding`. UTF-16 can
+/// only be decoded, so encoding to it always fails.
+pub fn encode_str_with_encoding<'a>(
+    string: &'a str,
+    encoding: &'static Encoding,
+) -> Result<Cow<'a, [u8]>, EncodeStringError> {
+    if encoding.output_encoding() != encoding {
+        return Err(EncodeStringError);
+    }
+    let (bytes, _enc, failed) = encoding.encode(string);
+    if failed {
+        Err(EncodeStringError)
+    } else {
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, UTF_16LE, WINDOWS_1251};
+
+    #[test]
+    fn detect_and_convert() -> anyhow::Result<()> {
+        let encoded = encode_str_with_encoding("保护国王", GBK)?;
+        assert_eq!(detect_encoding(&encoded), DEFAULT_ENCODING);
+        assert_eq!(decode_str_with_encoding(&encoded, GBK)?, "保护国王");
+        assert!(decode_str_with_encoding(&encoded, UTF_8).is_err());
+
+        assert!(encode_str_with_encoding("保护国王", WINDOWS_1251).is_err());
+        assert!(encode_str_with_encoding("text", UTF_16LE).is_err());
+        assert_eq!(detect_encoding(b"text"), UTF_8);
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+/// Where in a file an [`Error`] occurred.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    file_kind: Option<&'static str>,
+    sections: Vec<String>,
+    offset: Option<u64>,
+    version: Option<String>,
+}
+
+impl ErrorContext {
+    /// The kind of file that was being read or written, like "scenario".
+    pub fn file_kind(&self) -> Option<&'static str> {
+        self.file_kind
+    }
+
+    /// The sections of the file that were being read or written, from the outermost to the
+    /// innermost, like `["triggers", "trigger 3"]`.
+    pub fn sections(&self) -> &[String] {
+        &self.sections
+    }
+
+    /// The byte offset in the file at which the error occurred, if it is known.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// The format version of the file, if it is known.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        if let Some(kind) = self.file_kind {
+            write!(f, "in {}", kind)?;
+            separator = ", ";
+        }
+        if !self.sections.is_empty() {
+            write!(f, "{}section {}", separator, self.sections.join(" > "))?;
+            separator = ", ";
+        }
+        if let Some(offset) = self.offset {
+            write!(f, "{}at offset {:#x}", separator, offset)?;
+            separator = ", ";
+        }
+        if let Some(version) = &self.version {
+            write!(f, "{}version {}", separator, version)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error from any of the genie-rs crates, together with the place in the file where it
+/// occurred.
+///
+/// Each crate has its own error types, which describe what went wrong in detail. They can be
+/// converted into this type, so applications that read many kinds of files can report errors in
+/// the same way. The original error is kept as the [`source`](StdError::source) and can be
+/// inspected with [`Error::downcast_ref`].
+///
+/// ```rust
+/// use genie_support::Error;
+/// use std::io;
+///
+/// let err = Error::new("scenario", io::Error::from(io::ErrorKind::UnexpectedEof))
+///     .in_section("trigger 3")
+///     .in_section("triggers")
+///     .at_offset(0x1234)
+///     .with_version("1.21");
+/// assert_eq!(err.context().sections(), ["triggers", "trigger 3"]);
+/// assert_eq!(
+///     err.to_string(),
+///     "unexpected end of file (in scenario, section triggers > trigger 3, at offset 0x1234, version 1.21)"
+/// );
+/// assert!(err.downcast_ref::<io::Error>().is_some());
+/// ```
+#[derive(Debug, thiserror::Error)]
+#[error("{source}{}", DisplayContext(.context))]
+pub struct Error {
+    context: ErrorContext,
+    #[source]
+    source: Box<dyn StdError + Send + Sync + 'static>,
+}
+
+/// Displays an error context in parentheses, or nothing if it is empty.
+struct DisplayContext<'a>(&'a ErrorContext);
+
+impl fmt::Display for DisplayContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self.0 == ErrorContext::default() {
+            Ok(())
+        } else {
+            write!(f, " ({})", self.0)
+        }
+    }
+}
+
+impl Error {
+    /// Wrap an error that occurred while reading or writing a kind of file, like "scenario".
+    pub fn new(
+        file_kind: &'static str,
+        source: impl Into<Box<dyn StdError + Send + Sync + 'static>>,
+    ) -> Self {
+        Self {
+            context: ErrorContext {
+                file_kind: Some(file_kind),
+                ..Default::default()
+            },
+            source: source.into(),
+        }
+    }
+
+    /// Wrap an error without any context.
+    pub fn from_source(source: impl Into<Box<dyn StdError + Send + Sync + 'static>>) -> Self {
+        Self {
+            context: ErrorContext::default(),
+            source: source.into(),
+        }
+    }
+
+    /// Mark that the error occurred inside a file of the given kind, like a scenario inside a
+    /// campaign. The previous file kind becomes the outermost section.
+    pub fn in_file(mut self, file_kind: &'static str) -> Self {
+        if let Some(inner) = self.context.file_kind.replace(file_kind) {
+            if inner != file_kind {
+                self.context.sections.insert(0, inner.to_string());
+            }
+        }
+        self
+    }
+
+    /// Mark that the error occurred inside a section of the file. Errors bubble up from the
+    /// innermost section, so this adds the section in front of the existing ones.
+    pub fn in_section(mut self, section: impl Into<String>) -> Self {
+        self.context.sections.insert(0, section.into());
+        self
+    }
+
+    /// Set the byte offset at which the error occurred, if it was not known yet.
+    pub fn at_offset(mut self, offset: u64) -> Self {
+        self.context.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Set the format version of the file, if it was not known yet.
+    pub fn with_version(mut self, version: impl ToString) -> Self {
+        if self.context.version.is_none() {
+            self.context.version = Some(version.to_string());
+        }
+        self
+    }
+
+    /// Get the place in the file where the error occurred.
+    pub fn context(&self) -> &ErrorContext {
+        &self.context
+    }
+
+    /// Get the original error, if it has the given type.
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        self.source.downcast_ref()
+    }
+
+    /// Get the original error.
+    pub fn into_source(self) -> Box<dyn StdError + Send + Sync + 'static> {
+        self.source
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::from_source(err)
+    }
+}
+
+/// Add context to the error in a `Result`, converting it to an [`Error`].
+pub trait ResultExt<T> {
+    /// Mark that the error occurred inside a section of the file. See [`Error::in_section`].
+    fn in_section(self, section: impl Into<String>) -> Result<T, Error>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn in_section(self, section: impl Into<String>) -> Result<T, Error> {
+        self.map_err(|err| err.into().in_section(section))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_files() {
+        let err = Error::new("scenario", io::Error::from(io::ErrorKind::InvalidData))
+            .in_section("map")
+            .in_file("campaign")
+            .in_section("scenario 2");
+        assert_eq!(err.context().file_kind(), Some("campaign"));
+        assert_eq!(err.context().sections(), ["scenario 2", "scenario", "map"]);
+
+        let err: Result<(), Error> =
+            Err(io::Error::from(io::ErrorKind::InvalidData)).in_section("x");
+        let err = err.unwrap_err();
+        assert_eq!(err.context().file_kind(), None);
+        assert_eq!(err.to_string(), "invalid data (section x)");
+        assert_eq!(
+            Error::from(io::Error::from(io::ErrorKind::InvalidData)).to_string(),
+            "invalid data"
+        );
+    }
+}
@@ -5,6 +5,7 @@ use std::num::TryFromIntError;
 
 /// An ID identifying a unit type.
 #[derive(Debug, Hash, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnitTypeID(u16);
 
 impl From<u16> for UnitTypeID {
@@ -49,6 +50,7 @@ fallible_try_from!(UnitTypeID, u32);
 
 /// An ID identifying a tech.
 #[derive(Debug, Hash, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TechID(u16);
 
 impl From<u16> for TechID {
@@ -76,6 +78,7 @@ fallible_try_from!(TechID, u32);
 
 /// An ID identifying a sprite.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpriteID(u16);
 impl From<u16> for SpriteID {
     fn from(n: u16) -> Self {
@@ -112,6 +115,110 @@ fallible_try_from!(SpriteID, i16);
 fallible_try_from!(SpriteID, i32);
 fallible_try_from!(SpriteID, u32);
 
+/// An ID identifying a player attribute, such as a resource stockpile or the population headroom.
+///
+/// Player attributes are often called resources. Well-known attributes are available as
+/// associated constants.
+///
+/// # Examples
+///
+/// ```
+/// use genie_support::{AttributeID, ResourceID};
+/// assert_eq!(AttributeID::from(3u16), AttributeID::GOLD);
+/// assert_eq!(u16::from(ResourceID::STONE), 2);
+/// ```
+#[derive(Debug, Hash, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributeID(u16);
+
+/// Player attributes are called resources in many places.
+pub type ResourceID = AttributeID;
+
+impl AttributeID {
+    /// The food stockpile.
+    pub const FOOD: Self = Self(0);
+    /// The wood stockpile.
+    pub const WOOD: Self = Self(1);
+    /// The stone stockpile.
+    pub const STONE: Self = Self(2);
+    /// The gold stockpile.
+    pub const GOLD: Self = Self(3);
+    /// The number of additional units that can be created with the current houses.
+    pub const POPULATION_HEADROOM: Self = Self(4);
+    /// The range at which monks can convert units.
+    pub const CONVERSION_RANGE: Self = Self(5);
+    /// The current age: 0 for the Dark Age, up to 3 for the Imperial Age.
+    pub const CURRENT_AGE: Self = Self(6);
+    /// The number of relics held.
+    pub const RELICS_CAPTURED: Self = Self(7);
+    /// The number of trade goods.
+    pub const TRADE_GOODS: Self = Self(9);
+    /// The current population.
+    pub const CURRENT_POPULATION: Self = Self(11);
+    /// The time it takes for corpses to decay.
+    pub const CORPSE_DECAY_TIME: Self = Self(12);
+    /// The number of units owned.
+    pub const TOTAL_UNITS_OWNED: Self = Self(19);
+    /// The number of enemy units killed.
+    pub const UNITS_KILLED: Self = Self(20);
+    /// The number of techs researched.
+    pub const RESEARCH_COUNT: Self = Self(21);
+    /// The percentage of the map that has been explored.
+    pub const MAP_EXPLORED: Self = Self(22);
+    /// Additional population provided without houses.
+    pub const BONUS_POPULATION_CAP: Self = Self(32);
+    /// The faith of monks.
+    pub const FAITH: Self = Self(34);
+    /// The rate at which monks recharge their faith.
+    pub const FAITH_RECHARGING_RATE: Self = Self(35);
+    /// The amount of food in a new farm.
+    pub const FARM_FOOD_AMOUNT: Self = Self(36);
+    /// The fraction of tributes that is lost to tax.
+    pub const TRIBUTE_INEFFICIENCY: Self = Self(46);
+    /// The gold mining productivity multiplier.
+    pub const GOLD_MINING_PRODUCTIVITY: Self = Self(47);
+}
+
+impl From<u8> for AttributeID {
+    #[inline]
+    fn from(n: u8) -> Self {
+        AttributeID(n.into())
+    }
+}
+
+impl From<u16> for AttributeID {
+    #[inline]
+    fn from(n: u16) -> Self {
+        AttributeID(n)
+    }
+}
+
+impl From<AttributeID> for u16 {
+    #[inline]
+    fn from(n: AttributeID) -> Self {
+        n.0
+    }
+}
+
+impl From<AttributeID> for i32 {
+    #[inline]
+    fn from(n: AttributeID) -> Self {
+        n.0.into()
+    }
+}
+
+impl From<AttributeID> for usize {
+    #[inline]
+    fn from(n: AttributeID) -> Self {
+        n.0.into()
+    }
+}
+
+fallible_try_into!(AttributeID, u8);
+fallible_try_into!(AttributeID, i16);
+fallible_try_from!(AttributeID, i16);
+fallible_try_from!(AttributeID, i32);
+
 /// A key in a language file.
 ///
 /// A key may be either a nonnegative integer or an arbitrary string.
@@ -119,7 +226,10 @@ fallible_try_from!(SpriteID, u32);
 /// The original game supports only nonnegative integers.
 /// The HD Edition allows for integers as well as Strings to serve as keys in a
 /// key value file.
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+///
+/// Numeric keys sort before named keys.
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StringKey {
     /// An integer string key.
     Num(u32),
@@ -7,16 +7,31 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+#[cfg(feature = "strings")]
+mod encoding;
+mod error;
 mod ids;
 mod macros;
 mod map_into;
 mod read;
+mod string_source;
 #[cfg(feature = "strings")]
 mod strings;
+mod trace;
+mod version;
+mod versioned;
+mod write;
 
+#[cfg(feature = "strings")]
+pub use encoding::*;
+pub use error::*;
 pub use ids::*;
-pub use macros::*;
 pub use map_into::*;
 pub use read::*;
+pub use string_source::*;
 #[cfg(feature = "strings")]
 pub use strings::*;
+pub use trace::*;
+pub use version::*;
+pub use versioned::*;
+pub use write::*;
@@ -14,6 +14,7 @@
 /// ```
 macro_rules! infallible_try_into {
     ($from:ident, $to:ty) => {
+        #[allow(clippy::infallible_try_from)]
         impl ::std::convert::TryFrom<$from> for $to {
             type Error = ::std::convert::Infallible;
             fn try_from(n: $from) -> ::std::result::Result<Self, Self::Error> {
@@ -90,7 +91,7 @@ macro_rules! fallible_try_from {
 /// ```
 macro_rules! f32_eq {
     ($left:expr, $right:expr) => {
-        f32::abs($left - $right) < std::f32::EPSILON
+        f32::abs($left - $right) < f32::EPSILON
     };
 }
 
@@ -105,6 +106,6 @@ macro_rules! f32_eq {
 /// ```
 macro_rules! f32_neq {
     ($left:expr, $right:expr) => {
-        f32::abs($left - $right) > std::f32::EPSILON
+        f32::abs($left - $right) > f32::EPSILON
     };
 }
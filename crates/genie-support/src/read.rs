@@ -2,6 +2,137 @@ use byteorder::{ReadBytesExt, LE};
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Error, ErrorKind, Read, Result};
 
+/// Extension trait for converting numbers that were read from a file into IDs and other types.
+///
+/// Files can contain any value, so a conversion that fails means that the file is malformed. This
+/// returns an I/O error of kind `InvalidData` in that case, which the genie-rs crates convert into
+/// their own error types, instead of panicking like `.try_into().unwrap()` would.
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::{TryConvertExt, UnitTypeID};
+///
+/// let unit_type: UnitTypeID = 4i32.try_convert().unwrap();
+/// assert_eq!(unit_type, UnitTypeID::from(4));
+/// let error = (-2i32).try_convert::<UnitTypeID>().unwrap_err();
+/// assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+/// ```
+pub trait TryConvertExt: Sized {
+    /// Convert this value using its `TryFrom` implementation, failing with an `InvalidData` error
+    /// if it is out of range for the target type.
+    fn try_convert<T>(self) -> Result<T>
+    where
+        T: TryFrom<Self>,
+        T::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl<U> TryConvertExt for U {}
+
+/// Read a 1-byte integer that uses 0xFF as an "absent" value.
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::read_opt_u8;
+///
+/// assert_eq!(read_opt_u8::<u8, _>(&[0xFF][..]).unwrap(), None);
+/// assert_eq!(read_opt_u8::<u8, _>(&[0x04][..]).unwrap(), Some(4));
+/// ```
+#[inline]
+pub fn read_opt_u8<T, R>(mut input: R) -> Result<Option<T>>
+where
+    T: TryFrom<u8>,
+    T::Error: std::error::Error + Send + Sync + 'static,
+    R: Read,
+{
+    match input.read_u8()? {
+        0xFF => Ok(None),
+        v => v.try_convert().map(Some),
+    }
+}
+
+/// Read a signed 1-byte integer that uses -1 as an "absent" value.
+///
+/// Other negative values are returned as-is, or fail to convert if `T` is unsigned.
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::read_opt_i8;
+///
+/// assert_eq!(read_opt_i8::<u8, _>(&[0xFF][..]).unwrap(), None);
+/// assert_eq!(read_opt_i8::<u8, _>(&[0x02][..]).unwrap(), Some(2));
+/// assert!(read_opt_i8::<u8, _>(&[0xFE][..]).is_err());
+/// ```
+#[inline]
+pub fn read_opt_i8<T, R>(mut input: R) -> Result<Option<T>>
+where
+    T: TryFrom<i8>,
+    T::Error: std::error::Error + Send + Sync + 'static,
+    R: Read,
+{
+    match input.read_i8()? {
+        -1 => Ok(None),
+        v => v.try_convert().map(Some),
+    }
+}
+
+/// Read a signed 2-byte integer that uses -1 as an "absent" value.
+///
+/// Other negative values are returned as-is, or fail to convert if `T` is unsigned.
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::read_opt_i16;
+///
+/// assert_eq!(read_opt_i16::<u16, _>(&[0xFF, 0xFF][..]).unwrap(), None);
+/// assert_eq!(read_opt_i16::<i16, _>(&[0xFE, 0xFF][..]).unwrap(), Some(-2));
+/// assert!(read_opt_i16::<u16, _>(&[0xFE, 0xFF][..]).is_err());
+/// ```
+#[inline]
+pub fn read_opt_i16<T, R>(mut input: R) -> Result<Option<T>>
+where
+    T: TryFrom<i16>,
+    T::Error: std::error::Error + Send + Sync + 'static,
+    R: Read,
+{
+    match input.read_i16::<LE>()? {
+        -1 => Ok(None),
+        v => v.try_convert().map(Some),
+    }
+}
+
+/// Read a signed 4-byte integer that uses -1 as an "absent" value.
+///
+/// Other negative values are returned as-is, or fail to convert if `T` is unsigned.
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::read_opt_i32;
+///
+/// assert_eq!(read_opt_i32::<u32, _>(&[0xFF, 0xFF, 0xFF, 0xFF][..]).unwrap(), None);
+/// assert_eq!(read_opt_i32::<u32, _>(&[0x01, 0x00, 0x00, 0x00][..]).unwrap(), Some(1));
+/// ```
+#[inline]
+pub fn read_opt_i32<T, R>(mut input: R) -> Result<Option<T>>
+where
+    T: TryFrom<i32>,
+    T::Error: std::error::Error + Send + Sync + 'static,
+    R: Read,
+{
+    match input.read_i32::<LE>()? {
+        -1 => Ok(None),
+        v => v.try_convert().map(Some),
+    }
+}
+
 /// Read a 2-byte integer that uses -1 as an "absent" value.
 ///
 /// ## Example
@@ -22,14 +153,10 @@ where
     T::Error: std::error::Error + Send + Sync + 'static,
     R: Read,
 {
-    let opt = match input.read_u16::<LE>()? {
-        0xFFFF => None,
-        v => Some(
-            v.try_into()
-                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
-        ),
-    };
-    Ok(opt)
+    match input.read_u16::<LE>()? {
+        0xFFFF => Ok(None),
+        v => v.try_convert().map(Some),
+    }
 }
 
 /// Read a 4-byte integer that uses -1 as an "absent" value.
@@ -52,16 +179,12 @@ where
     T::Error: std::error::Error + Send + Sync + 'static,
     R: Read,
 {
-    let opt = match input.read_u32::<LE>()? {
-        0xFFFF_FFFF => None,
+    match input.read_u32::<LE>()? {
+        0xFFFF_FFFF => Ok(None),
         // HD Edition uses -2 in some places.
-        0xFFFF_FFFE => None,
-        v => Some(
-            v.try_into()
-                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
-        ),
-    };
-    Ok(opt)
+        0xFFFF_FFFE => Ok(None),
+        v => v.try_convert().map(Some),
+    }
 }
 
 /// Extension trait that adds a `skip()` method to `Read` instances.
@@ -76,3 +199,54 @@ impl<T: Read> ReadSkipExt for T {
         Ok(())
     }
 }
+
+/// Read `count` records of `size` bytes each with a single read, and parse them from memory.
+///
+/// Reading a record field by field costs a call into the reader for every field. That is slow
+/// when the reader is a file handle, a network stream or a decompressor, so long lists of
+/// fixed-size records are better read in bulk. `read` receives each record as a byte slice and
+/// must consume all of it. `size` must not be 0.
+///
+/// Memory is only allocated for the bytes that are actually in the input, so a damaged count does
+/// not cause a huge allocation.
+///
+/// ## Example
+///
+/// ```rust
+/// use byteorder::{ReadBytesExt, LE};
+/// use genie_support::read_records;
+///
+/// let input = [1, 0, 2, 0, 3, 0, 4, 0];
+/// let pairs = read_records(&input[..], 2, 4, |record| -> std::io::Result<_> {
+///     Ok((record.read_u16::<LE>()?, record.read_u16::<LE>()?))
+/// })?;
+/// assert_eq!(pairs, [(1, 2), (3, 4)]);
+/// assert!(read_records(&input[..], 3, 4, |record| record.read_u32::<LE>()).is_err());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read_records<T, E>(
+    input: impl Read,
+    count: usize,
+    size: usize,
+    mut read: impl FnMut(&mut &[u8]) -> std::result::Result<T, E>,
+) -> std::result::Result<Vec<T>, E>
+where
+    E: From<Error>,
+{
+    assert_ne!(size, 0, "records must not be empty");
+    let total = count
+        .checked_mul(size)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "record list is too large"))?;
+    let mut bytes = vec![];
+    input.take(total.try_convert()?).read_to_end(&mut bytes)?;
+    if bytes.len() != total {
+        return Err(Error::from(ErrorKind::UnexpectedEof).into());
+    }
+
+    let mut records = Vec::with_capacity(count);
+    for mut record in bytes.chunks_exact(size) {
+        records.push(read(&mut record)?);
+        debug_assert!(record.is_empty(), "record was not read completely");
+    }
+    Ok(records)
+}
@@ -0,0 +1,50 @@
+use crate::StringKey;
+use std::collections::HashMap;
+
+/// A source of display strings, such as a loaded language file.
+///
+/// Data files only store the keys of display strings; the strings themselves live in language
+/// files. Implementing this trait allows genie-rs types to resolve their display strings.
+///
+/// A slice of string sources can be used to look up strings in several language files in order,
+/// like the game does with its expansion language files.
+///
+/// # Examples
+///
+/// ```
+/// use genie_support::{StringKey, StringSource};
+/// use std::collections::HashMap;
+///
+/// let mut base = HashMap::new();
+/// base.insert(StringKey::from(5083u32), String::from("Archer"));
+/// base.insert(StringKey::from(5084u32), String::from("Crossbowman"));
+/// let mut expansion = HashMap::new();
+/// expansion.insert(StringKey::from(5084u32), String::from("Arbalest"));
+///
+/// let sources = [expansion, base];
+/// assert_eq!(sources[..].get_string(&5083u32.into()), Some("Archer"));
+/// assert_eq!(sources[..].get_string(&5084u32.into()), Some("Arbalest"));
+/// assert_eq!(sources[..].get_string(&5085u32.into()), None);
+/// ```
+pub trait StringSource {
+    /// Get the string for a key, or `None` if this source does not contain the key.
+    fn get_string(&self, key: &StringKey) -> Option<&str>;
+}
+
+impl StringSource for HashMap<StringKey, String> {
+    fn get_string(&self, key: &StringKey) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+impl<S: StringSource> StringSource for [S] {
+    fn get_string(&self, key: &StringKey) -> Option<&str> {
+        self.iter().find_map(|source| source.get_string(key))
+    }
+}
+
+impl<S: StringSource + ?Sized> StringSource for &S {
+    fn get_string(&self, key: &StringKey) -> Option<&str> {
+        (**self).get_string(key)
+    }
+}
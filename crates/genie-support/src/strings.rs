@@ -1,20 +1,21 @@
+use crate::{decode_str_with_encoding, encode_str_with_encoding, Encoding, DEFAULT_ENCODING};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use encoding_rs::WINDOWS_1252;
 use std::io::{self, Read, Write};
 
-/// Failed to decode a string as WINDOWS-1252.
+/// Failed to decode a string.
 ///
-/// This means that the scenario file contained a string that could not be decoded using the
-/// WINDOWS-1252 code page. In the future, genie-scx will support other encodings.
+/// This means that a file contained a string that is not valid in the code page it was decoded
+/// with, which is WINDOWS-1252 unless specified otherwise.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
-#[error("could not decode string as WINDOWS-1252")]
+#[error("could not decode string")]
 pub struct DecodeStringError;
 
-/// Failed to encode a string as WINDOWS-1252.
+/// Failed to encode a string.
 ///
-/// This means that a string could not be encoded using the WINDOWS-1252 code page. In the future, genie-scx will support other encodings.
+/// This means that a string contained characters that can not be represented in the code page
+/// it was encoded with, which is WINDOWS-1252 unless specified otherwise.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
-#[error("could not encode string as WINDOWS-1252")]
+#[error("could not encode string")]
 pub struct EncodeStringError;
 
 /// Failed to read a string.
@@ -39,16 +40,54 @@ pub enum WriteStringError {
     IoError(#[from] std::io::Error),
 }
 
+impl From<DecodeStringError> for io::Error {
+    fn from(err: DecodeStringError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl From<EncodeStringError> for io::Error {
+    fn from(err: EncodeStringError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl From<ReadStringError> for io::Error {
+    fn from(err: ReadStringError) -> Self {
+        match err {
+            ReadStringError::DecodeStringError(inner) => inner.into(),
+            ReadStringError::IoError(inner) => inner,
+        }
+    }
+}
+
+impl From<WriteStringError> for io::Error {
+    fn from(err: WriteStringError) -> Self {
+        match err {
+            WriteStringError::EncodeStringError(inner) => inner.into(),
+            WriteStringError::IoError(inner) => inner,
+        }
+    }
+}
+
 /// Write a string to an output stream, using code page 1252, using a `u16` for the length prefix.
 ///
 /// This writes the length of the string (including NULL terminator) as a little-endian u16,
 /// followed by the encoded bytes, followed by a NULL terminator.
 pub fn write_str<W: Write>(output: &mut W, string: &str) -> Result<(), WriteStringError> {
-    let (bytes, _enc, failed) = WINDOWS_1252.encode(string);
-    if failed {
-        return Err(WriteStringError::EncodeStringError(EncodeStringError));
-    }
-    assert!(bytes.len() < std::i16::MAX as usize);
+    write_str_with_encoding(output, string, DEFAULT_ENCODING)
+}
+
+/// Write a string to an output stream in the given encoding, using a `u16` for the length prefix.
+///
+/// See `write_str`.
+pub fn write_str_with_encoding<W: Write>(
+    output: &mut W,
+    string: &str,
+    encoding: &'static Encoding,
+) -> Result<(), WriteStringError> {
+    let bytes = encode_str_with_encoding(string, encoding)?;
+    assert!(bytes.len() < i16::MAX as usize);
     output.write_i16::<LE>(bytes.len() as i16 + 1)?;
     output.write_all(&bytes)?;
     output.write_u8(0)?;
@@ -60,11 +99,8 @@ pub fn write_str<W: Write>(output: &mut W, string: &str) -> Result<(), WriteStri
 /// This writes the length of the string (including NULL terminator) as a little-endian u177,
 /// followed by the encoded bytes, followed by a NULL terminator.
 pub fn write_i32_str<W: Write>(output: &mut W, string: &str) -> Result<(), WriteStringError> {
-    let (bytes, _enc, failed) = WINDOWS_1252.encode(string);
-    if failed {
-        return Err(WriteStringError::EncodeStringError(EncodeStringError));
-    }
-    assert!(bytes.len() < std::i32::MAX as usize);
+    let bytes = encode_str_with_encoding(string, DEFAULT_ENCODING)?;
+    assert!(bytes.len() < i32::MAX as usize);
     output.write_i32::<LE>(bytes.len() as i32 + 1)?;
     output.write_all(&bytes)?;
     output.write_u8(0)?;
@@ -79,7 +115,7 @@ pub fn write_opt_str<W: Write>(
     option: &Option<String>,
 ) -> Result<(), WriteStringError> {
     if let Some(ref string) = option {
-        write_str(output, &string)
+        write_str(output, string)
     } else {
         output.write_i16::<LE>(0)?;
         Ok(())
@@ -94,25 +130,34 @@ pub fn write_opt_i32_str<W: Write>(
     option: &Option<String>,
 ) -> Result<(), WriteStringError> {
     if let Some(ref string) = option {
-        write_i32_str(output, &string)
+        write_i32_str(output, string)
     } else {
         output.write_i32::<LE>(0)?;
         Ok(())
     }
 }
 
-/// Decode a string using the WINDOWS-1252 code page.
-fn decode_str(bytes: &[u8]) -> Result<String, DecodeStringError> {
-    if bytes.is_empty() {
-        return Ok("".to_string());
-    }
-
-    let (decoded, _enc, failed) = WINDOWS_1252.decode(&bytes);
-    if failed {
-        Err(DecodeStringError)
-    } else {
-        Ok(decoded.to_string())
+/// Write a string to a fixed-size field in the given encoding, padded with NULL bytes.
+///
+/// Strings that fill the whole field are written without a NULL terminator. Fails with an I/O
+/// error of kind `InvalidInput` if the encoded string is longer than `length` bytes.
+pub fn write_fixed_str_with_encoding<W: Write>(
+    output: &mut W,
+    string: &str,
+    length: usize,
+    encoding: &'static Encoding,
+) -> Result<(), WriteStringError> {
+    let bytes = encode_str_with_encoding(string, encoding)?;
+    if bytes.len() > length {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("string must be at most {} bytes long", length),
+        )
+        .into());
     }
+    output.write_all(&bytes)?;
+    output.write_all(&vec![0; length - bytes.len()])?;
+    Ok(())
 }
 
 /// Functions to read various kinds of strings from input streams.
@@ -120,8 +165,18 @@ fn decode_str(bytes: &[u8]) -> Result<String, DecodeStringError> {
 pub trait ReadStringsExt: Read {
     /// Read an optionally null-terminated WINDOWS-1252-encoded string with the given `length` in bytes.
     fn read_str(&mut self, length: usize) -> Result<Option<String>, ReadStringError> {
+        self.read_str_with_encoding(length, DEFAULT_ENCODING)
+    }
+
+    /// Read an optionally null-terminated string in the given encoding, with the given `length`
+    /// in bytes.
+    fn read_str_with_encoding(
+        &mut self,
+        length: usize,
+        encoding: &'static Encoding,
+    ) -> Result<Option<String>, ReadStringError> {
         if length > 0 {
-            let mut bytes = vec![0; length as usize];
+            let mut bytes = vec![0; length];
             self.read_exact(&mut bytes)?;
             if let Some(end) = bytes.iter().position(|&byte| byte == 0) {
                 bytes.truncate(end);
@@ -129,7 +184,7 @@ pub trait ReadStringsExt: Read {
             if bytes.is_empty() {
                 Ok(None)
             } else {
-                Ok(Some(decode_str(&bytes)?))
+                Ok(Some(decode_str_with_encoding(&bytes, encoding)?))
             }
         } else {
             Ok(None)
@@ -167,7 +222,7 @@ pub trait ReadStringsExt: Read {
         let len = self.read_u16::<LE>()? as usize;
         let mut bytes = vec![0; len];
         self.read_exact(&mut bytes[0..len])?;
-        Ok(Some(decode_str(&bytes)?))
+        Ok(Some(decode_str_with_encoding(&bytes, DEFAULT_ENCODING)?))
     }
 }
 
@@ -0,0 +1,220 @@
+use std::io::{BufRead, Read, Result};
+
+/// A reader that counts the bytes that are read through it.
+///
+/// The genie-rs parsers use this to report how large each section of a file is.
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> CountingReader<R> {
+    /// Count the bytes read from `inner`.
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// The number of bytes read so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Get a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped reader. Bytes read directly from it are not
+    /// counted.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.count += amount as u64;
+    }
+}
+
+/// A section of a file that is being parsed.
+///
+/// With the `tracing` feature, this is a `tracing` span at the debug level with the target
+/// `genie`, named "section", with `file`, `section` and `bytes` fields. Subscribers report how
+/// long the span was entered, so this shows where parsing time goes. Without the feature, this
+/// does nothing.
+#[derive(Debug)]
+pub struct TraceSection {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: u64,
+}
+
+impl TraceSection {
+    /// Start a section of a kind of file, like "data file", at the given byte position.
+    #[inline]
+    pub fn new(file: &'static str, section: &'static str, position: u64) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            Self {
+                span: tracing::debug_span!(
+                    target: "genie",
+                    "section",
+                    file,
+                    section,
+                    bytes = tracing::field::Empty
+                ),
+                start: position,
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (file, section, position);
+            Self {}
+        }
+    }
+
+    /// Run `f` inside the section.
+    #[inline]
+    pub fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.in_scope(f)
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            f()
+        }
+    }
+
+    /// Record that the section ended at the given byte position. This should be called once.
+    #[inline]
+    pub fn finish(&self, position: u64) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span
+                .record("bytes", position.saturating_sub(self.start));
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = position;
+        }
+    }
+}
+
+/// Read a section of a kind of file, like "data file", inside a [`TraceSection`].
+///
+/// The number of bytes that `read` consumed from `input` is recorded when it succeeds.
+#[inline]
+pub fn trace_section<R, T, E>(
+    file: &'static str,
+    section: &'static str,
+    input: &mut CountingReader<R>,
+    read: impl FnOnce(&mut CountingReader<R>) -> std::result::Result<T, E>,
+) -> std::result::Result<T, E> {
+    let trace = TraceSection::new(file, section, input.count());
+    let value = trace.in_scope(|| read(input))?;
+    trace.finish(input.count());
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_bytes() -> anyhow::Result<()> {
+        let mut input = CountingReader::new(&[1, 2, 3, 4, 5][..]);
+        let mut buf = [0; 2];
+        let read = trace_section("test", "first", &mut input, |input| {
+            input.read_exact(&mut buf)?;
+            Ok::<_, std::io::Error>(input.count())
+        })?;
+        assert_eq!(read, 2);
+        input.consume(1);
+        assert_eq!(input.count(), 3);
+        assert_eq!(input.fill_buf()?, [4, 5]);
+        Ok(())
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn record_spans() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        /// Collects the section names and byte counts of spans.
+        #[derive(Default)]
+        struct Sections(Mutex<Vec<(String, u64)>>);
+
+        impl Visit for &Sections {
+            fn record_u64(&mut self, _field: &Field, value: u64) {
+                self.0.lock().unwrap().last_mut().unwrap().1 = value;
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "section" {
+                    let name = format!("{:?}", value).trim_matches('"').to_string();
+                    self.0.lock().unwrap().push((name, 0));
+                }
+            }
+        }
+
+        struct Subscriber(Arc<Sections>);
+
+        impl tracing::Subscriber for Subscriber {
+            fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+                metadata.target() == "genie"
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                span.record(&mut &*self.0);
+                Id::from_u64(self.0 .0.lock().unwrap().len() as u64)
+            }
+            fn record(&self, _span: &Id, values: &Record<'_>) {
+                values.record(&mut &*self.0);
+            }
+            fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+            fn event(&self, _event: &Event<'_>) {}
+            fn enter(&self, _span: &Id) {}
+            fn exit(&self, _span: &Id) {}
+        }
+
+        let sections = Arc::new(Sections::default());
+        tracing::subscriber::with_default(Subscriber(Arc::clone(&sections)), || {
+            let mut input = CountingReader::new(&[0; 10][..]);
+            trace_section("test", "header", &mut input, |input| {
+                input.read_exact(&mut [0; 4])
+            })?;
+            trace_section("test", "body", &mut input, |input| {
+                input.read_to_end(&mut vec![]).map(|_| ())
+            })
+        })?;
+        assert_eq!(
+            *sections.0.lock().unwrap(),
+            [("header".to_string(), 4), ("body".to_string(), 6)]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The version number of the game data in a saved or recorded game.
+///
+/// The games use the same number to decide which fields are stored in unit and player data, so
+/// it can be compared against the version in which a field was added, like `version >= 11.58`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveVersion(f32);
+
+impl SaveVersion {
+    /// The save version of Age of Empires II: The Age of Kings 2.0.
+    pub const AOK: Self = Self(11.5);
+    /// The save version of Age of Empires II: The Conquerors 1.0c and UserPatch.
+    pub const AOC: Self = Self(11.76);
+    /// The lowest save version used by Age of Empires II: HD Edition.
+    pub const HD: Self = Self(12.5);
+    /// The lowest save version used by Age of Empires II: Definitive Edition.
+    pub const DE: Self = Self(12.97);
+
+    /// Create a save version from its raw number.
+    pub const fn new(version: f32) -> Self {
+        Self(version)
+    }
+
+    /// Get the raw number of this save version.
+    pub fn as_f32(self) -> f32 {
+        self.0
+    }
+
+    /// Returns whether recorded games of this version use the format introduced by The
+    /// Conquerors (.mgx), rather than the Age of Kings format (.mgl).
+    pub fn is_mgx(self) -> bool {
+        self >= Self::AOC
+    }
+
+    /// Returns whether this version is from HD Edition or later.
+    pub fn is_hd_or_later(self) -> bool {
+        self >= Self::HD
+    }
+
+    /// Returns whether this version is from the Definitive Edition.
+    pub fn is_de(self) -> bool {
+        self >= Self::DE
+    }
+}
+
+impl From<f32> for SaveVersion {
+    fn from(version: f32) -> Self {
+        Self(version)
+    }
+}
+
+impl From<SaveVersion> for f32 {
+    fn from(version: SaveVersion) -> Self {
+        version.0
+    }
+}
+
+impl PartialEq<f32> for SaveVersion {
+    fn eq(&self, other: &f32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<f32> for SaveVersion {
+    fn partial_cmp(&self, other: &f32) -> Option<Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl fmt::Display for SaveVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}", self.0)
+    }
+}
+
+/// A release of Age of Empires II, with queries for the features that files from it can
+/// contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameRelease {
+    /// Age of Empires II: The Age of Kings.
+    AoK,
+    /// Age of Empires II: The Conquerors.
+    AoC,
+    /// The Conquerors with UserPatch 1.4.
+    UserPatch14,
+    /// The Conquerors with UserPatch 1.5.
+    UserPatch15,
+    /// Age of Empires II: HD Edition.
+    HD,
+    /// Age of Empires II: Definitive Edition.
+    DE,
+}
+
+impl GameRelease {
+    /// All known releases, from oldest to newest.
+    pub const ALL: [GameRelease; 6] = [
+        GameRelease::AoK,
+        GameRelease::AoC,
+        GameRelease::UserPatch14,
+        GameRelease::UserPatch15,
+        GameRelease::HD,
+        GameRelease::DE,
+    ];
+
+    /// Detect the release that created a saved or recorded game, from the game version string
+    /// and the save version at the start of its header.
+    ///
+    /// Returns `None` for versions that are not in the registry, like AoE1 or UserPatch 1.3.
+    ///
+    /// ```rust
+    /// use genie_support::{GameRelease, SaveVersion};
+    ///
+    /// assert_eq!(GameRelease::detect(b"VER 9.4\0", SaveVersion::AOC), Some(GameRelease::AoC));
+    /// assert_eq!(
+    ///     GameRelease::detect(b"VER 9.F\0", SaveVersion::AOC),
+    ///     Some(GameRelease::UserPatch15)
+    /// );
+    /// assert_eq!(GameRelease::detect(b"VER 9.4\0", 12.97.into()), Some(GameRelease::DE));
+    /// ```
+    pub fn detect(game_version: &[u8], save_version: SaveVersion) -> Option<Self> {
+        let end = game_version
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(game_version.len());
+        match &game_version[..end] {
+            b"VER 9.3" | b"TRL 9.3" => Some(GameRelease::AoK),
+            b"VER 9.4" if save_version.is_de() => Some(GameRelease::DE),
+            b"VER 9.4" if save_version.is_hd_or_later() => Some(GameRelease::HD),
+            b"VER 9.4" => Some(GameRelease::AoC),
+            b"VER 9.A" | b"VER 9.B" | b"VER 9.C" | b"VER 9.D" => Some(GameRelease::UserPatch14),
+            b"VER 9.E" | b"VER 9.F" => Some(GameRelease::UserPatch15),
+            _ => None,
+        }
+    }
+
+    /// Get the human-readable name of this release.
+    pub fn name(self) -> &'static str {
+        match self {
+            GameRelease::AoK => "Age of Kings",
+            GameRelease::AoC => "The Conquerors",
+            GameRelease::UserPatch14 => "UserPatch 1.4",
+            GameRelease::UserPatch15 => "UserPatch 1.5",
+            GameRelease::HD => "HD Edition",
+            GameRelease::DE => "Definitive Edition",
+        }
+    }
+
+    /// Returns whether this release includes The Conquerors expansion.
+    pub fn has_conquerors(self) -> bool {
+        self != GameRelease::AoK
+    }
+
+    /// Returns whether this release is a version of UserPatch.
+    pub fn is_userpatch(self) -> bool {
+        matches!(self, GameRelease::UserPatch14 | GameRelease::UserPatch15)
+    }
+
+    /// The highest unit type ID that exists in this release, if it is known.
+    pub fn max_unit_type(self) -> Option<i32> {
+        // Stormy Dog is the highest ID in AoC 1.0c. AoK does not use all IDs up to it, but it
+        // does not have any units above it either.
+        const STORMY_DOG: i32 = 862;
+        match self {
+            GameRelease::AoK
+            | GameRelease::AoC
+            | GameRelease::UserPatch14
+            | GameRelease::UserPatch15 => Some(STORMY_DOG),
+            GameRelease::HD | GameRelease::DE => None,
+        }
+    }
+
+    /// The highest terrain ID that exists in this release, if it is known.
+    pub fn max_terrain(self) -> Option<u8> {
+        match self {
+            GameRelease::AoK => Some(31),
+            GameRelease::AoC | GameRelease::UserPatch14 | GameRelease::UserPatch15 => Some(41),
+            GameRelease::HD | GameRelease::DE => None,
+        }
+    }
+
+    /// The highest trigger condition type that exists in this release, if it is known.
+    pub fn max_condition_type(self) -> Option<i32> {
+        match self {
+            // "Units Garrisoned" and "Difficulty Level" were added in AoC.
+            GameRelease::AoK => Some(17),
+            GameRelease::AoC
+            | GameRelease::UserPatch14
+            | GameRelease::UserPatch15
+            | GameRelease::HD => Some(19),
+            GameRelease::DE => None,
+        }
+    }
+
+    /// The highest trigger effect type that exists in this release, if it is known.
+    pub fn max_effect_type(self) -> Option<i32> {
+        match self {
+            // "Damage Object" through "Stop Unit" were added in AoC.
+            GameRelease::AoK => Some(23),
+            GameRelease::AoC | GameRelease::HD => Some(29),
+            // UserPatch adds effects 30 to 35.
+            GameRelease::UserPatch14 | GameRelease::UserPatch15 => Some(35),
+            GameRelease::DE => None,
+        }
+    }
+}
+
+impl fmt::Display for GameRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_releases() {
+        assert_eq!(
+            GameRelease::detect(b"VER 9.3\0", SaveVersion::AOK),
+            Some(GameRelease::AoK)
+        );
+        assert_eq!(
+            GameRelease::detect(b"VER 9.C\0", SaveVersion::AOC),
+            Some(GameRelease::UserPatch14)
+        );
+        assert_eq!(
+            GameRelease::detect(b"VER 9.4\0", SaveVersion::new(12.5)),
+            Some(GameRelease::HD)
+        );
+        assert_eq!(GameRelease::detect(b"VER 9.8\0", SaveVersion::AOC), None);
+        assert_eq!(GameRelease::detect(b"", SaveVersion::AOC), None);
+    }
+
+    #[test]
+    fn compare_save_versions() {
+        let version = SaveVersion::from(11.76);
+        assert_eq!(version, SaveVersion::AOC);
+        assert!(version >= 11.58);
+        assert!(version < SaveVersion::HD);
+        assert!(version.is_mgx());
+        assert!(!SaveVersion::AOK.is_mgx());
+        assert!(!version.is_hd_or_later());
+        assert_eq!(version.to_string(), "11.76");
+    }
+}
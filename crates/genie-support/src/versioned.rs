@@ -0,0 +1,121 @@
+use std::io::{Read, Write};
+
+/// A type that can be read from a byte stream.
+///
+/// The layout of most structures depends on the version of the file they are in, so reading
+/// takes a version argument `V`. This can be any type that describes the layout, like a
+/// `SaveVersion`, a tuple of several values, or `()` for structures that never change.
+///
+/// The genie-rs crates keep their inherent `read_from` methods, whose signatures differ per type.
+/// This trait gives them all the same shape, so generic code, like round-trip tests, can read any
+/// of them. Use [`impl_versioned!`](crate::impl_versioned) to implement it by calling the
+/// inherent methods.
+pub trait ReadableVersioned<V = ()>: Sized {
+    /// The error type returned when reading fails.
+    type Error;
+
+    /// Read a value in the layout used by `version`.
+    fn read_versioned<R: Read>(input: &mut R, version: V) -> Result<Self, Self::Error>;
+}
+
+/// A type that can be written to a byte stream.
+///
+/// This is the counterpart of [`ReadableVersioned`]. Values written with a version can be read
+/// back with the same version.
+pub trait WritableVersioned<V = ()> {
+    /// The error type returned when writing fails.
+    type Error;
+
+    /// Write this value in the layout used by `version`.
+    fn write_versioned<W: Write>(&self, output: &mut W, version: V) -> Result<(), Self::Error>;
+}
+
+/// Implement [`ReadableVersioned`] and [`WritableVersioned`] for types that have inherent
+/// `read_from` and `write_to` methods.
+///
+/// Types without a version argument implement the traits for `()`. Types with one implement them
+/// for the type of the version argument, given after a colon.
+///
+/// ```rust
+/// use genie_support::{impl_versioned, ReadableVersioned, SaveVersion, WritableVersioned};
+/// use std::io::{self, Read, Write};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Speed(f32);
+///
+/// impl Speed {
+///     fn read_from(mut input: impl Read, version: SaveVersion) -> io::Result<Self> {
+///         let mut bytes = [0; 4];
+///         input.read_exact(&mut bytes)?;
+///         Ok(Speed(f32::from_le_bytes(bytes)))
+///     }
+///
+///     fn write_to(&self, mut output: impl Write, version: SaveVersion) -> io::Result<()> {
+///         output.write_all(&self.0.to_le_bytes())
+///     }
+/// }
+///
+/// impl_versioned! {
+///     Speed: SaveVersion => io::Error;
+/// }
+///
+/// let mut bytes = vec![];
+/// Speed(1.5).write_versioned(&mut bytes, SaveVersion::from(11.97))?;
+/// let speed = Speed::read_versioned(&mut &bytes[..], SaveVersion::from(11.97))?;
+/// assert_eq!(speed, Speed(1.5));
+/// # Ok::<(), io::Error>(())
+/// ```
+#[macro_export]
+macro_rules! impl_versioned {
+    ($($ty:ty $(: $version:ty)? => $error:ty;)*) => {
+        $($crate::impl_versioned!(@impl $ty $(, $version)? => $error);)*
+    };
+    (@impl $ty:ty => $error:ty) => {
+        impl $crate::ReadableVersioned for $ty {
+            type Error = $error;
+
+            fn read_versioned<R: ::std::io::Read>(
+                input: &mut R,
+                _version: (),
+            ) -> ::std::result::Result<Self, $error> {
+                <$ty>::read_from(input)
+            }
+        }
+
+        impl $crate::WritableVersioned for $ty {
+            type Error = $error;
+
+            fn write_versioned<W: ::std::io::Write>(
+                &self,
+                output: &mut W,
+                _version: (),
+            ) -> ::std::result::Result<(), $error> {
+                self.write_to(output)
+            }
+        }
+    };
+    (@impl $ty:ty, $version:ty => $error:ty) => {
+        impl $crate::ReadableVersioned<$version> for $ty {
+            type Error = $error;
+
+            fn read_versioned<R: ::std::io::Read>(
+                input: &mut R,
+                version: $version,
+            ) -> ::std::result::Result<Self, $error> {
+                <$ty>::read_from(input, version)
+            }
+        }
+
+        impl $crate::WritableVersioned<$version> for $ty {
+            type Error = $error;
+
+            fn write_versioned<W: ::std::io::Write>(
+                &self,
+                output: &mut W,
+                version: $version,
+            ) -> ::std::result::Result<(), $error> {
+                self.write_to(output, version)
+            }
+        }
+    };
+}
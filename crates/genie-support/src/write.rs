@@ -0,0 +1,196 @@
+use byteorder::{WriteBytesExt, LE};
+use std::convert::{TryFrom, TryInto};
+use std::io::{Error, ErrorKind, Result, Write};
+
+/// Convert a value so it can be written to a file, failing with an `InvalidInput` error if it is
+/// out of range for the target type.
+#[inline]
+fn convert<T, U>(value: T) -> Result<U>
+where
+    U: TryFrom<T>,
+    U::Error: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .try_into()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// Write a 1-byte integer that uses 0xFF as an "absent" value.
+///
+/// This is the counterpart of [`read_opt_u8`](crate::read_opt_u8).
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::write_opt_u8;
+///
+/// let mut output = vec![];
+/// write_opt_u8::<u8, _>(&mut output, None).unwrap();
+/// assert_eq!(output, [0xFF]);
+/// let mut output = vec![];
+/// write_opt_u8(&mut output, Some(4u8)).unwrap();
+/// assert_eq!(output, [0x04]);
+/// ```
+#[inline]
+pub fn write_opt_u8<T, W>(mut output: W, value: Option<T>) -> Result<()>
+where
+    u8: TryFrom<T>,
+    <u8 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+    W: Write,
+{
+    let value = match value {
+        Some(value) => convert(value)?,
+        None => 0xFF,
+    };
+    output.write_u8(value)
+}
+
+/// Write a signed 1-byte integer that uses -1 as an "absent" value.
+///
+/// This is the counterpart of [`read_opt_i8`](crate::read_opt_i8).
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::write_opt_i8;
+///
+/// let mut output = vec![];
+/// write_opt_i8::<u8, _>(&mut output, None).unwrap();
+/// assert_eq!(output, [0xFF]);
+/// let mut output = vec![];
+/// write_opt_i8(&mut output, Some(4u8)).unwrap();
+/// assert_eq!(output, [0x04]);
+/// ```
+#[inline]
+pub fn write_opt_i8<T, W>(mut output: W, value: Option<T>) -> Result<()>
+where
+    i8: TryFrom<T>,
+    <i8 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+    W: Write,
+{
+    let value = match value {
+        Some(value) => convert(value)?,
+        None => -1,
+    };
+    output.write_i8(value)
+}
+
+/// Write a 2-byte integer that uses -1 as an "absent" value.
+///
+/// This is the counterpart of [`read_opt_u16`](crate::read_opt_u16).
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::write_opt_u16;
+///
+/// let mut output = vec![];
+/// write_opt_u16::<u8, _>(&mut output, None).unwrap();
+/// assert_eq!(output, [0xFF, 0xFF]);
+/// let mut output = vec![];
+/// write_opt_u16(&mut output, Some(4u8)).unwrap();
+/// assert_eq!(output, [0x04, 0x00]);
+/// ```
+#[inline]
+pub fn write_opt_u16<T, W>(mut output: W, value: Option<T>) -> Result<()>
+where
+    u16: TryFrom<T>,
+    <u16 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+    W: Write,
+{
+    let value = match value {
+        Some(value) => convert(value)?,
+        None => 0xFFFF,
+    };
+    output.write_u16::<LE>(value)
+}
+
+/// Write a signed 2-byte integer that uses -1 as an "absent" value.
+///
+/// This is the counterpart of [`read_opt_i16`](crate::read_opt_i16).
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::write_opt_i16;
+///
+/// let mut output = vec![];
+/// write_opt_i16::<u8, _>(&mut output, None).unwrap();
+/// assert_eq!(output, [0xFF, 0xFF]);
+/// let mut output = vec![];
+/// write_opt_i16(&mut output, Some(4u8)).unwrap();
+/// assert_eq!(output, [0x04, 0x00]);
+/// ```
+#[inline]
+pub fn write_opt_i16<T, W>(mut output: W, value: Option<T>) -> Result<()>
+where
+    i16: TryFrom<T>,
+    <i16 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+    W: Write,
+{
+    let value = match value {
+        Some(value) => convert(value)?,
+        None => -1,
+    };
+    output.write_i16::<LE>(value)
+}
+
+/// Write a 4-byte integer that uses -1 as an "absent" value.
+///
+/// This is the counterpart of [`read_opt_u32`](crate::read_opt_u32).
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::write_opt_u32;
+///
+/// let mut output = vec![];
+/// write_opt_u32::<u8, _>(&mut output, None).unwrap();
+/// assert_eq!(output, [0xFF, 0xFF, 0xFF, 0xFF]);
+/// let mut output = vec![];
+/// write_opt_u32(&mut output, Some(4u8)).unwrap();
+/// assert_eq!(output, [0x04, 0x00, 0x00, 0x00]);
+/// ```
+#[inline]
+pub fn write_opt_u32<T, W>(mut output: W, value: Option<T>) -> Result<()>
+where
+    u32: TryFrom<T>,
+    <u32 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+    W: Write,
+{
+    let value = match value {
+        Some(value) => convert(value)?,
+        None => 0xFFFF_FFFF,
+    };
+    output.write_u32::<LE>(value)
+}
+
+/// Write a signed 4-byte integer that uses -1 as an "absent" value.
+///
+/// This is the counterpart of [`read_opt_i32`](crate::read_opt_i32).
+///
+/// ## Example
+///
+/// ```rust
+/// use genie_support::write_opt_i32;
+///
+/// let mut output = vec![];
+/// write_opt_i32::<u8, _>(&mut output, None).unwrap();
+/// assert_eq!(output, [0xFF, 0xFF, 0xFF, 0xFF]);
+/// let mut output = vec![];
+/// write_opt_i32(&mut output, Some(4u8)).unwrap();
+/// assert_eq!(output, [0x04, 0x00, 0x00, 0x00]);
+/// ```
+#[inline]
+pub fn write_opt_i32<T, W>(mut output: W, value: Option<T>) -> Result<()>
+where
+    i32: TryFrom<T>,
+    <i32 as TryFrom<T>>::Error: std::error::Error + Send + Sync + 'static,
+    W: Write,
+{
+    let value = match value {
+        Some(value) => convert(value)?,
+        None => -1,
+    };
+    output.write_i32::<LE>(value)
+}
@@ -0,0 +1,334 @@
+//! Round-trip and fixture helpers for testing genie-rs file format readers and writers.
+//!
+//! The genie-rs crates read and write their types with inherent `read_from` and `write_to`
+//! methods, some of which take extra arguments like a format version. The helpers here take the
+//! reading and writing code as closures, so they work with any of those signatures:
+//!
+//! ```rust
+//! use genie_test_utils::assert_round_trip;
+//! use std::io::{self, Read, Write};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Name(String);
+//!
+//! impl Name {
+//!     fn read_from(mut input: impl Read) -> io::Result<Self> {
+//!         let mut len = [0; 1];
+//!         input.read_exact(&mut len)?;
+//!         let mut bytes = vec![0; usize::from(len[0])];
+//!         input.read_exact(&mut bytes)?;
+//!         Ok(Name(String::from_utf8_lossy(&bytes).to_string()))
+//!     }
+//!
+//!     fn write_to(&self, mut output: impl Write) -> io::Result<()> {
+//!         output.write_all(&[self.0.len() as u8])?;
+//!         output.write_all(self.0.as_bytes())
+//!     }
+//! }
+//!
+//! let bytes = assert_round_trip(
+//!     &Name("Cuman".to_string()),
+//!     |name, output| name.write_to(output),
+//!     |input| Name::read_from(input),
+//! );
+//! assert_eq!(bytes, b"\x05Cuman");
+//! ```
+
+#![deny(future_incompatible)]
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(unsafe_code)]
+#![warn(missing_docs)]
+#![warn(unused)]
+
+use genie_support::{ReadableVersioned, WritableVersioned};
+use std::fmt::Debug;
+use std::fs;
+use std::io::Cursor;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// The input that reading closures receive: a cursor over the bytes to read.
+pub type Input<'a> = Cursor<&'a [u8]>;
+
+/// Panic if `input` has bytes left over after reading.
+fn assert_consumed(input: &Input<'_>, what: &str) {
+    let len = input.get_ref().len() as u64;
+    assert!(
+        input.position() == len,
+        "{} read {} of {} bytes",
+        what,
+        input.position(),
+        len
+    );
+}
+
+/// Panic with the offset of the first difference between two byte strings.
+fn assert_same_bytes(actual: &[u8], expected: &[u8]) {
+    if actual == expected {
+        return;
+    }
+    let offset = actual
+        .iter()
+        .zip(expected)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| actual.len().min(expected.len()));
+    panic!(
+        "written bytes differ from the original at offset {:#x} (wrote {} bytes, expected {})",
+        offset,
+        actual.len(),
+        expected.len()
+    );
+}
+
+/// Write `value`, read it back, and check that the result is equal to `value` and that all the
+/// written bytes were read. Returns the written bytes.
+pub fn assert_round_trip<T, E>(
+    value: &T,
+    write: impl FnOnce(&T, &mut Vec<u8>) -> Result<(), E>,
+    read: impl FnOnce(&mut Input<'_>) -> Result<T, E>,
+) -> Vec<u8>
+where
+    T: Debug + PartialEq,
+    E: Debug,
+{
+    let mut bytes = vec![];
+    write(value, &mut bytes).expect("could not write the original value");
+
+    let mut input = Cursor::new(&bytes[..]);
+    let read_back = read(&mut input).expect("could not read the written bytes");
+    assert_consumed(&input, "reading the written value");
+    assert_eq!(&read_back, value, "value changed after writing and reading");
+    bytes
+}
+
+/// Like [`assert_round_trip`], for types that implement [`ReadableVersioned`] and
+/// [`WritableVersioned`]. The value is written and read with the same `version`.
+pub fn assert_versioned_round_trip<T, V>(value: &T, version: V) -> Vec<u8>
+where
+    T: ReadableVersioned<V> + WritableVersioned<V> + Debug + PartialEq,
+    <T as ReadableVersioned<V>>::Error: Debug,
+    <T as WritableVersioned<V>>::Error: Debug,
+    V: Clone,
+{
+    let read_version = version.clone();
+    assert_round_trip(
+        value,
+        |value, output| {
+            value
+                .write_versioned(output, version)
+                .map_err(|err| format!("{:?}", err))
+        },
+        |input| T::read_versioned(input, read_version).map_err(|err| format!("{:?}", err)),
+    )
+}
+
+/// Read `bytes`, write the result, and check that exactly the same bytes were written. Returns
+/// the value that was read.
+///
+/// This is the strictest check, for formats that keep every byte of the original file.
+pub fn assert_bytes_round_trip<T, E>(
+    bytes: &[u8],
+    read: impl FnOnce(&mut Input<'_>) -> Result<T, E>,
+    write: impl FnOnce(&T, &mut Vec<u8>) -> Result<(), E>,
+) -> T
+where
+    E: Debug,
+{
+    let mut input = Cursor::new(bytes);
+    let value = read(&mut input).expect("could not read the original bytes");
+    assert_consumed(&input, "reading the original bytes");
+
+    let mut written = vec![];
+    write(&value, &mut written).expect("could not write the value that was read");
+    assert_same_bytes(&written, bytes);
+    value
+}
+
+/// Read `bytes`, write the result and read it again, and check that both reads give the same
+/// value. Returns the value that was read.
+///
+/// Use this for formats where writing is allowed to normalize the file, for example by choosing a
+/// different compression level or dropping padding.
+pub fn assert_stable_round_trip<T, E>(
+    bytes: &[u8],
+    read: impl Fn(&mut Input<'_>) -> Result<T, E>,
+    write: impl FnOnce(&T, &mut Vec<u8>) -> Result<(), E>,
+) -> T
+where
+    T: Debug + PartialEq,
+    E: Debug,
+{
+    let value = read(&mut Cursor::new(bytes)).expect("could not read the original bytes");
+    assert_round_trip(&value, write, read);
+    value
+}
+
+/// Check that reading every truncated prefix of `bytes` returns an error instead of panicking or
+/// succeeding.
+///
+/// Long inputs are checked at up to `max_lengths` evenly spaced lengths. Readers that allocate
+/// based on a length read from the file may still allocate a lot of memory for some prefixes, so
+/// prefer small inputs.
+pub fn assert_truncation_errors<T, E>(
+    bytes: &[u8],
+    max_lengths: usize,
+    read: impl Fn(&mut Input<'_>) -> Result<T, E>,
+) {
+    let step = (bytes.len() / max_lengths.max(1)).max(1);
+    for len in (0..bytes.len()).step_by(step) {
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            read(&mut Cursor::new(&bytes[..len])).is_err()
+        }));
+        match result {
+            Ok(true) => {}
+            Ok(false) => panic!(
+                "reading {} of {} bytes succeeded, expected an error",
+                len,
+                bytes.len()
+            ),
+            Err(_) => panic!("reading {} of {} bytes panicked", len, bytes.len()),
+        }
+    }
+}
+
+/// List the files in `dir` that have one of the given extensions, sorted by path.
+///
+/// Extensions are compared case-insensitively and without the leading dot, like `"scx"`. This
+/// makes it easy to run a check on every fixture of a format, so new fixtures are tested without
+/// touching the test code.
+pub fn fixtures(dir: impl AsRef<Path>, extensions: &[&str]) -> Vec<PathBuf> {
+    let dir = dir.as_ref();
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("could not list fixtures in {}: {}", dir.display(), err));
+    let mut paths: Vec<PathBuf> = entries
+        .map(|entry| entry.expect("could not read directory entry").path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| {
+                    extensions
+                        .iter()
+                        .any(|wanted| ext.eq_ignore_ascii_case(wanted))
+                })
+                .unwrap_or(false)
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Generate `len` pseudo-random bytes from a seed.
+///
+/// The same seed always gives the same bytes, so failures are reproducible.
+pub fn noise(seed: u64, len: usize) -> Vec<u8> {
+    // xorshift64*; the state must not be zero.
+    let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+    if state == 0 {
+        state = 1;
+    }
+    let mut bytes = Vec::with_capacity(len + 8);
+    while bytes.len() < len {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        bytes.extend_from_slice(&state.wrapping_mul(0x2545_F491_4F6C_DD1D).to_le_bytes());
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+/// Generate `count` copies of `bytes`, each with a few bytes overwritten with noise.
+///
+/// Together with [`assert_truncation_errors`] this is a cheap way to check that a reader does not
+/// panic on damaged files.
+pub fn corruptions(bytes: &[u8], seed: u64, count: usize) -> Vec<Vec<u8>> {
+    if bytes.is_empty() {
+        return vec![vec![]; count];
+    }
+    (0..count as u64)
+        .map(|index| {
+            let random = noise(seed.wrapping_add(index), 16);
+            let mut copy = bytes.to_vec();
+            for pair in random.chunks(4) {
+                let position = usize::from(u16::from_le_bytes([pair[0], pair[1]])) % copy.len();
+                copy[position] = pair[2];
+            }
+            copy
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Read, Write};
+
+    #[derive(Debug, PartialEq)]
+    struct Pair(u8, u8);
+
+    impl Pair {
+        fn read_from(input: &mut impl Read) -> io::Result<Self> {
+            let mut bytes = [0; 2];
+            input.read_exact(&mut bytes)?;
+            Ok(Pair(bytes[0], bytes[1]))
+        }
+
+        fn write_to(&self, output: &mut impl Write) -> io::Result<()> {
+            output.write_all(&[self.0, self.1])
+        }
+    }
+
+    fn read(input: &mut Input<'_>) -> io::Result<Pair> {
+        Pair::read_from(input)
+    }
+
+    fn write(pair: &Pair, output: &mut Vec<u8>) -> io::Result<()> {
+        pair.write_to(output)
+    }
+
+    #[test]
+    fn round_trips() {
+        let bytes = assert_round_trip(&Pair(1, 2), write, read);
+        assert_eq!(bytes, [1, 2]);
+        let pair = assert_bytes_round_trip(&[3, 4], read, write);
+        assert_eq!(pair, Pair(3, 4));
+        assert_eq!(assert_stable_round_trip(&[5, 6], read, write), Pair(5, 6));
+        assert_truncation_errors(&bytes, 10, read);
+    }
+
+    genie_support::impl_versioned! {
+        Pair => io::Error;
+    }
+
+    #[test]
+    fn versioned_round_trip() {
+        assert_eq!(assert_versioned_round_trip(&Pair(7, 8), ()), [7, 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "read 2 of 3 bytes")]
+    fn leftover_bytes() {
+        assert_bytes_round_trip(&[1, 2, 3], read, write);
+    }
+
+    #[test]
+    #[should_panic(expected = "differ from the original at offset 0x1")]
+    fn changed_bytes() {
+        assert_bytes_round_trip(&[1, 2], read, |pair, output| {
+            Pair(pair.0, 0).write_to(output)
+        });
+    }
+
+    #[test]
+    fn generators() {
+        assert_eq!(noise(1, 13), noise(1, 13));
+        assert_ne!(noise(1, 13), noise(2, 13));
+        assert_eq!(noise(7, 13).len(), 13);
+        let damaged = corruptions(&[0; 32], 5, 3);
+        assert_eq!(damaged.len(), 3);
+        assert!(damaged.iter().all(|copy| copy.len() == 32));
+        assert_eq!(damaged, corruptions(&[0; 32], 5, 3));
+        assert_eq!(fixtures("src", &["RS"]), [PathBuf::from("src/lib.rs")]);
+    }
+}
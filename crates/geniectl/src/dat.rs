@@ -0,0 +1,117 @@
+use crate::Status;
+use genie_dat::DatFile;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub enum Command {
+    #[structopt(name = "diff")]
+    /// Compare the civilizations, unit types, techs and tech effects of two data files.
+    Diff(Diff),
+}
+
+#[derive(StructOpt)]
+pub struct Diff {
+    /// Path to the original data file.
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+    /// Path to the modified data file.
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+}
+
+/// Split a data file dump into its entries, as pairs of the first line of the entry and the
+/// remaining lines.
+fn entries(dump: &str) -> Vec<(&str, Vec<&str>)> {
+    dump.split("\n\n")
+        .filter_map(|entry| {
+            let mut lines = entry.lines();
+            let title = lines.next()?;
+            Some((title, lines.collect()))
+        })
+        .collect()
+}
+
+/// Compare two data file dumps entry by entry, and list the changes.
+///
+/// Entries that only exist in one dump are listed in full with a "-" or "+" prefix. For entries
+/// that exist in both, only the lines that changed are listed under the title of the entry.
+fn diff_dumps(old: &str, new: &str) -> Vec<String> {
+    let old_entries = entries(old);
+    let new_entries = entries(new);
+    let new_by_title: HashMap<_, _> = new_entries.iter().map(|(t, l)| (*t, l)).collect();
+    let old_titles: HashSet<_> = old_entries.iter().map(|(title, _)| *title).collect();
+
+    let mut output = vec![];
+    for (title, old_lines) in &old_entries {
+        match new_by_title.get(title) {
+            Some(new_lines) if *new_lines == old_lines => {}
+            Some(new_lines) => {
+                output.push(format!("@ {}", title));
+                for line in old_lines.iter().filter(|line| !new_lines.contains(line)) {
+                    output.push(format!("-{}", line));
+                }
+                for line in new_lines.iter().filter(|line| !old_lines.contains(line)) {
+                    output.push(format!("+{}", line));
+                }
+            }
+            None => {
+                output.push(format!("-{}", title));
+                output.extend(old_lines.iter().map(|line| format!("-{}", line)));
+            }
+        }
+    }
+    for (title, new_lines) in &new_entries {
+        if !old_titles.contains(title) {
+            output.push(format!("+{}", title));
+            output.extend(new_lines.iter().map(|line| format!("+{}", line)));
+        }
+    }
+    output
+}
+
+fn diff(args: Diff) -> anyhow::Result<Status> {
+    let old = DatFile::read_from(File::open(args.old)?)?;
+    let new = DatFile::read_from(File::open(args.new)?)?;
+    let changes = diff_dumps(&old.dump().to_string(), &new.dump().to_string());
+    for line in &changes {
+        println!("{}", line);
+    }
+    Ok(if changes.is_empty() {
+        Status::Ok
+    } else {
+        Status::Problems
+    })
+}
+
+pub fn run(command: Command) -> anyhow::Result<Status> {
+    match command {
+        Command::Diff(args) => diff(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_entries() {
+        let old = "tech 0 \"A\"\n  cost: 10\n  time: 5\n\ntech 1 \"B\"\n  cost: 20\n\n";
+        let new = "tech 0 \"A\"\n  cost: 15\n  time: 5\n\ntech 2 \"C\"\n  cost: 30\n\n";
+        assert_eq!(
+            diff_dumps(old, new),
+            vec![
+                "@ tech 0 \"A\"",
+                "-  cost: 10",
+                "+  cost: 15",
+                "-tech 1 \"B\"",
+                "-  cost: 20",
+                "+tech 2 \"C\"",
+                "+  cost: 30",
+            ]
+        );
+        assert!(diff_dumps(old, old).is_empty());
+    }
+}
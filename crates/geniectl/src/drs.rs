@@ -0,0 +1,56 @@
+use crate::Status;
+use genie_drs::DRSArchive;
+use std::fs::{create_dir_all, File};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub enum Command {
+    #[structopt(name = "extract")]
+    /// Extract the resources in an archive to a directory.
+    Extract(Extract),
+}
+
+#[derive(StructOpt)]
+pub struct Extract {
+    /// Path to the .drs archive.
+    #[structopt(parse(from_os_str))]
+    archive: PathBuf,
+    /// Only extract resources from this table, like "slp" or "wav".
+    #[structopt(long, short = "t")]
+    table: Option<String>,
+    /// Output directory to place the resources in.
+    #[structopt(long, short = "o", parse(from_os_str))]
+    out: PathBuf,
+}
+
+fn extract(args: Extract) -> anyhow::Result<Status> {
+    let mut archive = DRSArchive::new(File::open(args.archive)?)?;
+    create_dir_all(&args.out)?;
+
+    let mut resources = vec![];
+    for table in archive.tables() {
+        let ext = table.resource_ext();
+        if let Some(ref filter) = args.table {
+            if *filter != ext {
+                continue;
+            }
+        }
+        for resource in table.resources() {
+            resources.push((table.resource_type, resource.id, ext.clone()));
+        }
+    }
+
+    for (resource_type, id, ext) in &resources {
+        let mut output = File::create(args.out.join(format!("{}.{}", id, ext)))?;
+        archive.copy_resource(*resource_type, *id, &mut output)?;
+    }
+    println!("extracted {} resources", resources.len());
+    Ok(Status::Ok)
+}
+
+pub fn run(command: Command) -> anyhow::Result<Status> {
+    match command {
+        Command::Extract(args) => extract(args),
+    }
+}
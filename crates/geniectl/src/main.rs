@@ -0,0 +1,52 @@
+//! Command line tool to inspect recorded games, scenarios, data files and DRS archives.
+
+#![deny(future_incompatible)]
+#![deny(nonstandard_style)]
+#![deny(rust_2018_idioms)]
+#![deny(unsafe_code)]
+#![warn(unused)]
+
+mod dat;
+mod drs;
+mod rec;
+mod scx;
+
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(name = "geniectl")]
+enum Cli {
+    #[structopt(name = "rec")]
+    /// Inspect recorded games (.mgl, .mgx, .mgz).
+    Rec(rec::Command),
+    #[structopt(name = "scx")]
+    /// Inspect scenarios (.scn, .scx, .aoe2scenario).
+    Scx(scx::Command),
+    #[structopt(name = "dat")]
+    /// Inspect data files (empires2_x1_p1.dat).
+    Dat(dat::Command),
+    #[structopt(name = "drs")]
+    /// Inspect DRS archives.
+    Drs(drs::Command),
+}
+
+/// The result of a command: whether it succeeded, or found problems that should make the process
+/// exit with a non-zero status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Problems,
+}
+
+fn main() -> anyhow::Result<()> {
+    let status = match Cli::from_args() {
+        Cli::Rec(command) => rec::run(command)?,
+        Cli::Scx(command) => scx::run(command)?,
+        Cli::Dat(command) => dat::run(command)?,
+        Cli::Drs(command) => drs::run(command)?,
+    };
+    if status == Status::Problems {
+        std::process::exit(1);
+    }
+    Ok(())
+}
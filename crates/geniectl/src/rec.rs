@@ -0,0 +1,171 @@
+use crate::Status;
+use genie_rec::actions::Action;
+use genie_rec::RecordedGame;
+use serde_json::json;
+use std::fs::File;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub enum Command {
+    #[structopt(name = "summary")]
+    /// Show the versions, players and duration of a recorded game.
+    Summary(Args),
+    #[structopt(name = "chat")]
+    /// Print the chat messages in a recorded game.
+    Chat(Args),
+    #[structopt(name = "json")]
+    /// Print the players, commands and chat messages in a recorded game as JSON.
+    Json(Args),
+}
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// Path to the recorded game file.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+}
+
+/// Format a game time in milliseconds as "h:mm:ss".
+fn format_time(millis: u64) -> String {
+    let seconds = millis / 1000;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Get the name of a command type from its debug output, like "Move".
+fn command_name(command: &genie_rec::actions::Command) -> String {
+    let debug = format!("{:?}", command);
+    match debug.find('(') {
+        Some(end) => debug[..end].to_string(),
+        None => debug,
+    }
+}
+
+/// Call `f` for each action in the body of a recorded game, with the game time at which it
+/// happened in milliseconds. Returns the duration of the game.
+fn for_each_action(
+    rec: &mut RecordedGame<File>,
+    mut f: impl FnMut(u64, Action),
+) -> anyhow::Result<u64> {
+    let mut time = 0;
+    for action in rec.actions()? {
+        match action? {
+            Action::Time(step) => time += u64::from(step.time),
+            action => f(time, action),
+        }
+    }
+    Ok(time)
+}
+
+fn summary(args: Args) -> anyhow::Result<Status> {
+    let mut rec = RecordedGame::new(File::open(args.file)?)?;
+    let header = rec.header()?;
+
+    let release = rec
+        .release()
+        .map_or("unknown release", |release| release.name());
+    println!(
+        "version: {} {} ({})",
+        rec.game_version().to_string().trim_end_matches('\0'),
+        rec.save_version(),
+        release
+    );
+    println!("players:");
+    // Player 0 is Gaia.
+    for (index, player) in header.players().enumerate().skip(1) {
+        println!(
+            "  {}: {} (civilization {})",
+            index,
+            player.name(),
+            u8::from(player.civilization_id)
+        );
+    }
+
+    let mut commands = 0;
+    let mut chats = 0;
+    let duration = for_each_action(&mut rec, |_, action| match action {
+        Action::Command(_) => commands += 1,
+        Action::Chat(_) => chats += 1,
+        _ => {}
+    })?;
+    println!("duration: {}", format_time(duration));
+    println!("commands: {}", commands);
+    println!("chat messages: {}", chats);
+    Ok(Status::Ok)
+}
+
+fn chat(args: Args) -> anyhow::Result<Status> {
+    let mut rec = RecordedGame::new(File::open(args.file)?)?;
+    for_each_action(&mut rec, |time, action| {
+        if let Action::Chat(chat) = action {
+            println!("[{}] {}", format_time(time), chat.message());
+        }
+    })?;
+    Ok(Status::Ok)
+}
+
+fn json(args: Args) -> anyhow::Result<Status> {
+    let mut rec = RecordedGame::new(File::open(args.file)?)?;
+    let header = rec.header()?;
+    let players = header
+        .players()
+        .map(|player| {
+            json!({
+                "name": player.name(),
+                "civilization_id": u8::from(player.civilization_id),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut actions = vec![];
+    let duration = for_each_action(&mut rec, |time, action| match action {
+        Action::Command(command) => actions.push(json!({
+            "time": time,
+            "type": "command",
+            "command": command_name(&command),
+        })),
+        Action::Chat(chat) => actions.push(json!({
+            "time": time,
+            "type": "chat",
+            "message": chat.message(),
+        })),
+        _ => {}
+    })?;
+
+    let output = json!({
+        "game_version": rec.game_version().to_string().trim_end_matches('\0'),
+        "save_version": rec.save_version().as_f32(),
+        "release": rec.release().map(|release| release.name()),
+        "duration": duration,
+        "players": players,
+        "actions": actions,
+    });
+    serde_json::to_writer_pretty(std::io::stdout(), &output)?;
+    println!();
+    Ok(Status::Ok)
+}
+
+pub fn run(command: Command) -> anyhow::Result<Status> {
+    match command {
+        Command::Summary(args) => summary(args),
+        Command::Chat(args) => chat(args),
+        Command::Json(args) => json(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_time() {
+        assert_eq!(format_time(0), "0:00:00");
+        assert_eq!(format_time(61_999), "0:01:01");
+        assert_eq!(format_time(3_723_000), "1:02:03");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::Status;
+use genie_scx::Scenario;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub enum Command {
+    #[structopt(name = "validate")]
+    /// Check a scenario for triggers that refer to missing objects, players or triggers, objects
+    /// outside the map, and unknown terrain types.
+    Validate(Validate),
+}
+
+#[derive(StructOpt)]
+pub struct Validate {
+    /// Path to the scenario file.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+}
+
+fn validate(args: Validate) -> anyhow::Result<Status> {
+    let scen = Scenario::read_from(BufReader::new(File::open(args.file)?))?;
+    let report = scen.validate();
+    for issue in report.issues() {
+        println!("{:?}", issue);
+    }
+    println!("{} problems", report.issues().len());
+    Ok(if report.is_valid() {
+        Status::Ok
+    } else {
+        Status::Problems
+    })
+}
+
+pub fn run(command: Command) -> anyhow::Result<Status> {
+    match command {
+        Command::Validate(args) => validate(args),
+    }
+}
@@ -28,6 +28,9 @@
 //!     "JASC-PAL\r\n0100\r\n3\r\n0 0 0\r\n0 255 255\r\n255 0 0\r\n".to_string()
 //! );
 //! ```
+//!
+//! Extended palette files with an alpha channel, used by Age of Empires 2: Definitive Edition,
+//! can be read and written using [`ExtendedPalette`].
 
 #![deny(future_incompatible)]
 #![deny(nonstandard_style)]
@@ -36,6 +39,10 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+mod palx;
+
+pub use palx::{ColorAlpha, ExtendedPalette};
+
 use nom::bytes::complete::tag;
 use nom::character::complete::{digit1, one_of};
 use nom::combinator::{map, map_res};
@@ -43,7 +50,7 @@ use nom::multi::many1;
 use nom::IResult;
 use rgb::RGB;
 use std::convert::{TryFrom, TryInto};
-
+use std::fmt;
 use std::io::{Read, Write};
 use std::num::TryFromIntError;
 use std::str::{self, FromStr};
@@ -60,6 +67,7 @@ pub type Color = RGB<u8>;
 /// assert_eq!(pal[PaletteIndex::from(0)], Color { r: 0, g: 0, b: 0 });
 /// ```
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PaletteIndex(u8);
 impl From<u8> for PaletteIndex {
     #[inline]
@@ -308,11 +316,11 @@ impl<'a> IntoIterator for &'a Palette {
     }
 }
 
-impl ToString for Palette {
+impl fmt::Display for Palette {
     #[inline]
-    fn to_string(&self) -> String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = self.to_bytes();
-        String::from_utf8(s).expect("serialization not utf-8, this is a bug")
+        f.write_str(std::str::from_utf8(&s).expect("serialization not utf-8, this is a bug"))
     }
 }
 
@@ -0,0 +1,303 @@
+//! Extended palette files, used by Age of Empires 2: Definitive Edition.
+//!
+//! Extended palette files are JASC palette files with an alpha channel:
+//! ```txt
+//! JASC-PALX
+//! 0100
+//! $num_colors
+//! $ALPHA $default_alpha
+//! $rgba...
+//! ```
+//!
+//! The `$ALPHA` line is optional, and sets the alpha value of colours that only have r, g, b
+//! values. It defaults to 255.
+
+use crate::{parse_number, parse_rgb, whitespace, Color, Palette, PaletteIndex, ReadPaletteError};
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::combinator::opt;
+use nom::sequence::{delimited, preceded};
+use nom::IResult;
+use rgb::RGBA;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// Represents an RGBA colour.
+pub type ColorAlpha = RGBA<u8>;
+
+/// Parsed contents of an extended palette file: the default alpha value, and the colours.
+type Parsed = (Option<u8>, Vec<ColorAlpha>);
+
+/// Parse a header and colours from an input stream. Plain JASC palette files are accepted too.
+fn parse(input: &[u8]) -> IResult<&[u8], Parsed> {
+    let (input, _) = alt((tag(b"JASC-PALX\r\n"), tag(b"JASC-PAL\r\n")))(input)?;
+    let (input, _) = tag(b"0100\r\n")(input)?;
+    let (input, num_colors) = parse_number::<usize>(input)?;
+    let (input, _) = tag(b"\r\n")(input)?;
+    let (input, default_alpha) = opt(delimited(
+        tag(b"$ALPHA"),
+        preceded(whitespace, parse_number),
+        tag(b"\r\n"),
+    ))(input)?;
+
+    let mut colors = Vec::with_capacity(num_colors);
+    let mut input = input;
+    for _ in 0..num_colors {
+        let (remaining, color) = parse_rgb(input)?;
+        let (remaining, alpha) = opt(preceded(whitespace, parse_number))(remaining)?;
+        let alpha = alpha.or(default_alpha).unwrap_or(255);
+        colors.push(RGBA::new(color.r, color.g, color.b, alpha));
+        let (remaining, _) = tag(b"\r\n")(remaining)?;
+        input = remaining;
+    }
+
+    Ok((input, (default_alpha, colors)))
+}
+
+/// A palette with an alpha channel.
+///
+/// ## Example
+/// ```rust
+/// use jascpal::{ColorAlpha, ExtendedPalette};
+/// let pal: ExtendedPalette = "JASC-PALX\r\n0100\r\n2\r\n$ALPHA 192\r\n0 0 0\r\n255 255 255 0\r\n"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(pal.default_alpha(), Some(192));
+/// assert_eq!(pal.colors(), &[
+///     ColorAlpha { r: 0, g: 0, b: 0, a: 192 },
+///     ColorAlpha { r: 255, g: 255, b: 255, a: 0 },
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPalette {
+    default_alpha: Option<u8>,
+    colors: Vec<ColorAlpha>,
+}
+
+impl From<Vec<ColorAlpha>> for ExtendedPalette {
+    /// Create a palette from a vector of colours.
+    #[inline]
+    fn from(colors: Vec<ColorAlpha>) -> Self {
+        ExtendedPalette {
+            default_alpha: None,
+            colors,
+        }
+    }
+}
+
+impl From<Palette> for ExtendedPalette {
+    /// Create an extended palette from a palette, with all colours opaque.
+    #[inline]
+    fn from(palette: Palette) -> Self {
+        Self::from(
+            palette
+                .into_iter()
+                .map(|c| RGBA::new(c.r, c.g, c.b, 255))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+impl Default for ExtendedPalette {
+    /// Create a palette with 256 colours, all opaque black.
+    #[inline]
+    fn default() -> Self {
+        Self::from(Palette::default())
+    }
+}
+
+impl ExtendedPalette {
+    /// Create an empty palette.
+    #[inline]
+    pub fn new() -> Self {
+        Self::from(vec![])
+    }
+
+    /// Read an extended palette from an input stream. Plain JASC palette files can be read too,
+    /// with all colours opaque.
+    #[inline]
+    pub fn read_from(mut input: impl Read) -> Result<Self, ReadPaletteError> {
+        let mut buffer = vec![];
+        input.read_to_end(&mut buffer)?;
+        let (_remaining, (default_alpha, colors)) =
+            parse(&buffer).map_err(|_| ReadPaletteError::ParseError)?;
+        Ok(Self {
+            default_alpha,
+            colors,
+        })
+    }
+
+    /// Write an extended palette to an output stream.
+    ///
+    /// If the palette has a default alpha value, it is written in the `$ALPHA` line, and only
+    /// colours with a different alpha value get a fourth component. Otherwise, only colours that
+    /// are not opaque do.
+    #[inline]
+    pub fn write_to(&self, mut output: impl Write) -> Result<(), std::io::Error> {
+        output.write_all(b"JASC-PALX\r\n0100\r\n")?;
+        output.write_all(format!("{}\r\n", self.colors.len()).as_bytes())?;
+        if let Some(alpha) = self.default_alpha {
+            output.write_all(format!("$ALPHA {}\r\n", alpha).as_bytes())?;
+        }
+        let default_alpha = self.default_alpha.unwrap_or(255);
+        for c in &self.colors {
+            if c.a == default_alpha {
+                output.write_all(format!("{} {} {}\r\n", c.r, c.g, c.b).as_bytes())?;
+            } else {
+                output.write_all(format!("{} {} {} {}\r\n", c.r, c.g, c.b, c.a).as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the alpha value from the `$ALPHA` line, if the palette file has one.
+    #[inline]
+    pub fn default_alpha(&self) -> Option<u8> {
+        self.default_alpha
+    }
+
+    /// Set the alpha value written to the `$ALPHA` line. This does not change the colours.
+    #[inline]
+    pub fn set_default_alpha(&mut self, alpha: Option<u8>) {
+        self.default_alpha = alpha;
+    }
+
+    /// Return the colours in a Vec so it can be manipulated.
+    #[inline]
+    pub fn into_inner(self) -> Vec<ColorAlpha> {
+        self.colors
+    }
+
+    /// Return the colours as a slice.
+    #[inline]
+    pub fn colors(&self) -> &[ColorAlpha] {
+        &self.colors
+    }
+
+    /// Return the colours as a mutable vector.
+    #[inline]
+    pub fn colors_mut(&mut self) -> &mut Vec<ColorAlpha> {
+        &mut self.colors
+    }
+
+    /// Returns the number of colours in this palette.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns `true` if this palette contains 0 colours.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Add a colour at the end of the palette.
+    #[inline]
+    pub fn add(&mut self, color: ColorAlpha) {
+        self.colors.push(color);
+    }
+
+    /// Create a palette without the alpha channel.
+    #[inline]
+    pub fn to_palette(&self) -> Palette {
+        Palette::from(
+            self.colors
+                .iter()
+                .map(|c| Color::new(c.r, c.g, c.b))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Serialize the palette to a byte vector.
+    #[inline]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write_to(&mut bytes)
+            .expect("serialization failed, this is a bug");
+        bytes
+    }
+}
+
+impl std::ops::Index<PaletteIndex> for ExtendedPalette {
+    type Output = ColorAlpha;
+    /// Get the colour at the given index.
+    #[inline]
+    fn index(&self, index: PaletteIndex) -> &Self::Output {
+        let index: usize = index.into();
+        &self.colors[index]
+    }
+}
+
+impl std::ops::IndexMut<PaletteIndex> for ExtendedPalette {
+    /// Get the colour at the given index.
+    #[inline]
+    fn index_mut(&mut self, index: PaletteIndex) -> &mut Self::Output {
+        let index: usize = index.into();
+        &mut self.colors[index]
+    }
+}
+
+impl std::str::FromStr for ExtendedPalette {
+    type Err = ReadPaletteError;
+    /// Parse an extended palette from a UTF-8 string.
+    #[inline]
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::read_from(input.as_bytes())
+    }
+}
+
+impl fmt::Display for ExtendedPalette {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.to_bytes();
+        f.write_str(std::str::from_utf8(&s).expect("serialization not utf-8, this is a bug"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        parse(b"JASC-PALX\r\n0100\r\n2\r\n0 0 0 0\r\n255 255 255\r\n").expect("failed to parse");
+        parse(b"JASC-PALX\r\n0100\r\n1\r\n$ALPHA 64\r\n0 0 0\r\n").expect("failed to parse");
+        assert!(parse(b"JASC-PALX\r\n0100\r\n1\r\n0 0 0 0 0\r\n").is_err());
+    }
+
+    #[test]
+    fn roundtrip() {
+        let source = "JASC-PALX\r\n0100\r\n3\r\n$ALPHA 128\r\n1 2 3\r\n4 5 6 255\r\n7 8 9 0\r\n";
+        let pal: ExtendedPalette = source.parse().expect("failed to parse");
+        assert_eq!(
+            pal.colors(),
+            &[
+                RGBA::new(1, 2, 3, 128),
+                RGBA::new(4, 5, 6, 255),
+                RGBA::new(7, 8, 9, 0)
+            ]
+        );
+        assert_eq!(pal.to_string(), source);
+
+        let mut pal = pal;
+        pal.set_default_alpha(None);
+        assert_eq!(
+            pal.to_string(),
+            "JASC-PALX\r\n0100\r\n3\r\n1 2 3 128\r\n4 5 6\r\n7 8 9 0\r\n"
+        );
+    }
+
+    #[test]
+    fn convert() {
+        let source = "JASC-PAL\r\n0100\r\n2\r\n0 0 0\r\n255 255 255\r\n";
+        let pal: ExtendedPalette = source.parse().expect("failed to parse");
+        assert_eq!(
+            pal,
+            ExtendedPalette::from(source.parse::<Palette>().unwrap())
+        );
+        assert_eq!(pal[PaletteIndex::from(1)], RGBA::new(255, 255, 255, 255));
+        assert_eq!(pal.to_palette().to_string(), source);
+        assert_eq!(ExtendedPalette::default().len(), 256);
+    }
+}
@@ -0,0 +1,74 @@
+//! Exports the frames of an .slp file as PNG images, an animated PNG or a GIF.
+
+use genie::pal::Palette;
+use genie::slp::export::{Animation, ExportOptions};
+use genie::slp::DecodeOptions;
+use genie::SLP;
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Export the frames of an .slp file as images.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "exportslp")]
+struct ExportSlp {
+    /// Path to the .slp file.
+    #[structopt(parse(from_os_str))]
+    slp: PathBuf,
+    /// Path to the JASC palette file to use.
+    #[structopt(parse(from_os_str))]
+    palette: PathBuf,
+    /// Path to place the output. A directory for the `png` format, a file otherwise.
+    #[structopt(long, short = "o", parse(from_os_str))]
+    out: PathBuf,
+    /// Output format: `png` for a PNG image per frame, `apng` or `gif`.
+    #[structopt(long, short = "f", default_value = "png")]
+    format: String,
+    /// The player whose colours are used for player colour pixels.
+    #[structopt(long, short = "p", default_value = "1")]
+    player: u8,
+    /// Do not draw shadows.
+    #[structopt(long)]
+    no_shadows: bool,
+    /// How long each frame is shown in animated images, in milliseconds.
+    #[structopt(long, default_value = "100")]
+    delay: u16,
+}
+
+/// Executes the CLI.
+fn main() -> anyhow::Result<()> {
+    let args = ExportSlp::from_args();
+    let slp = SLP::read_from(File::open(&args.slp)?)?;
+    let palette = Palette::read_from(File::open(&args.palette)?)?;
+
+    let options = ExportOptions::new()
+        .decode(DecodeOptions::new().player(args.player))
+        .shadows(!args.no_shadows)
+        .frame_delay(args.delay);
+    let animation = Animation::from_slp(&slp, palette.colors(), &options)?;
+
+    match args.format.as_str() {
+        "png" => {
+            create_dir_all(&args.out)?;
+            let prefix = args
+                .slp
+                .file_stem()
+                .map(|stem| format!("{}_", stem.to_string_lossy()))
+                .unwrap_or_default();
+            animation.write_png_sequence(&args.out, &prefix)?;
+        }
+        "apng" | "gif" => {
+            let mut output = BufWriter::new(File::create(&args.out)?);
+            if args.format == "apng" {
+                animation.write_apng(&mut output)?;
+            } else {
+                animation.write_gif(&mut output)?;
+            }
+            output.flush()?;
+        }
+        format => anyhow::bail!("unknown output format: {}", format),
+    }
+
+    Ok(())
+}
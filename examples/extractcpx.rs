@@ -43,9 +43,9 @@ fn list(args: List) {
         .map(|entry| entry.filename.to_string())
         .collect::<Vec<String>>();
 
-    for i in 0..campaign.len() {
+    for (i, name) in names.iter().enumerate() {
         let bytes = campaign.by_index_raw(i).expect("missing scenario data");
-        println!("- {} ({})", names[i], format_bytes(bytes.len() as u32));
+        println!("- {} ({})", name, format_bytes(bytes.len() as u32));
     }
 }
 
@@ -62,10 +62,10 @@ fn extract(args: Extract) {
         .map(|entry| entry.filename.to_string())
         .collect::<Vec<String>>();
 
-    for i in 0..campaign.len() {
+    for (i, name) in names.iter().enumerate() {
         let bytes = campaign.by_index_raw(i).expect("missing scenario data");
-        println!("{}", names[i]);
-        std::fs::write(dir.join(&names[i]), bytes).expect("failed to write");
+        println!("{}", name);
+        std::fs::write(dir.join(name), bytes).expect("failed to write");
     }
 }
 
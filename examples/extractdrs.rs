@@ -1,4 +1,6 @@
-use genie_drs::{DRSReader, DRSWriter, ReserveDirectoryStrategy};
+use genie_drs::{
+    DRSArchive, DRSPatch, DRSReader, DRSWriter, RepackOptions, ReserveDirectoryStrategy,
+};
 use std::collections::HashSet;
 use std::fs::{create_dir_all, File};
 use std::io::{self, stdout, Write};
@@ -25,6 +27,18 @@ enum Command {
     #[structopt(name = "add")]
     /// Add a resource to an existing archive.
     Add(Add),
+    #[structopt(name = "diff")]
+    /// Create a patch with the resources that differ between two archives.
+    Diff(Diff),
+    #[structopt(name = "patch")]
+    /// Apply a patch to an archive.
+    Patch(Patch),
+    #[structopt(name = "repack")]
+    /// Rebuild an archive, storing identical resources only once.
+    Repack(Repack),
+    #[structopt(name = "verify")]
+    /// Check an archive for corrupted tables and resources.
+    Verify(Verify),
 }
 
 #[derive(StructOpt)]
@@ -76,6 +90,50 @@ struct Add {
     file: Vec<PathBuf>,
 }
 
+#[derive(Debug, StructOpt)]
+struct Diff {
+    /// Path to the original .drs archive.
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+    /// Path to the modified .drs archive.
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+    /// Path to place the patch file.
+    #[structopt(long, short = "o", parse(from_os_str))]
+    out: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct Patch {
+    /// Path to the .drs archive to patch.
+    #[structopt(parse(from_os_str))]
+    archive: PathBuf,
+    /// Path to the patch file.
+    #[structopt(parse(from_os_str))]
+    patch: PathBuf,
+    /// Path to place the patched .drs archive.
+    #[structopt(long, short = "o", parse(from_os_str))]
+    out: PathBuf,
+}
+#[derive(Debug, StructOpt)]
+struct Repack {
+    /// Path to the .drs archive to repack.
+    #[structopt(parse(from_os_str))]
+    archive: PathBuf,
+    /// Path to place the repacked .drs archive.
+    #[structopt(long, short = "o", parse(from_os_str))]
+    out: PathBuf,
+    /// Keep duplicate resources.
+    #[structopt(long)]
+    no_dedup: bool,
+}
+#[derive(Debug, StructOpt)]
+struct Verify {
+    /// Path to the .drs archive to check.
+    #[structopt(parse(from_os_str))]
+    archive: PathBuf,
+}
+
 fn list(args: List) -> anyhow::Result<()> {
     let mut file = File::open(args.archive)?;
     let drs = DRSReader::new(&mut file)?;
@@ -94,13 +152,10 @@ fn get(args: Get) -> anyhow::Result<()> {
     let drs = DRSReader::new(&mut file)?;
 
     for table in drs.tables() {
-        match table.get_resource(args.resource_id) {
-            Some(ref resource) => {
-                let buf = drs.read_resource(&mut file, table.resource_type, resource.id)?;
-                stdout().write_all(&buf)?;
-                return Ok(());
-            }
-            None => (),
+        if let Some(resource) = table.get_resource(args.resource_id) {
+            let buf = drs.read_resource(&mut file, table.resource_type, resource.id)?;
+            stdout().write_all(&buf)?;
+            return Ok(());
         }
     }
 
@@ -177,9 +232,10 @@ fn add(args: Add) -> anyhow::Result<()> {
     ));
 
     let output = File::create(&temp_out)?;
-    let mut drs_write = DRSWriter::new(
+    let mut drs_write = DRSWriter::with_variant(
         output,
         ReserveDirectoryStrategy::new(tables + new_tables, files + new_files),
+        drs_read.variant(),
     )?;
 
     for t in drs_read.tables() {
@@ -192,7 +248,7 @@ fn add(args: Add) -> anyhow::Result<()> {
     for (i, path) in args.file.iter().enumerate() {
         let mut res_type = [0x20; 4];
         let slice = args.table[i].as_bytes();
-        (&mut res_type[0..slice.len()]).copy_from_slice(slice);
+        res_type[0..slice.len()].copy_from_slice(slice);
         res_type.reverse();
         drs_write.add(res_type, args.id[i], File::open(path)?)?;
     }
@@ -211,6 +267,57 @@ fn add(args: Add) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn diff(args: Diff) -> anyhow::Result<()> {
+    let mut old = DRSArchive::new(File::open(args.old)?)?;
+    let mut new = DRSArchive::new(File::open(args.new)?)?;
+    let patch = DRSPatch::diff(&mut old, &mut new)?;
+    patch.write_to(File::create(args.out)?)?;
+    println!("{} changed resources", patch.changes().len());
+    Ok(())
+}
+
+fn patch(args: Patch) -> anyhow::Result<()> {
+    let mut archive = DRSArchive::new(File::open(args.archive)?)?;
+    let patch = DRSPatch::read_from(File::open(args.patch)?)?;
+    let mut output = DRSWriter::with_variant(
+        File::create(args.out)?,
+        ReserveDirectoryStrategy::default(),
+        archive.variant(),
+    )?;
+    patch.apply(&mut archive, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn repack(args: Repack) -> anyhow::Result<()> {
+    let mut archive = DRSArchive::new(File::open(args.archive)?)?;
+    let (_, report) = RepackOptions::new()
+        .deduplicate(!args.no_dedup)
+        .repack(&mut archive, File::create(args.out)?)?;
+    println!(
+        "{} resources, {} duplicates, {} -> {} bytes",
+        report.resources, report.duplicates, report.original_size, report.repacked_size
+    );
+    Ok(())
+}
+
+fn verify(args: Verify) -> anyhow::Result<()> {
+    let mut archive = DRSArchive::new(File::open(args.archive)?)?;
+    let report = archive.verify()?;
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    println!(
+        "{} resources, {} problems",
+        report.resources,
+        report.issues.len()
+    );
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
     let args = Cli::from_args();
 
@@ -219,5 +326,9 @@ fn main() -> anyhow::Result<()> {
         Command::Get(args) => get(args),
         Command::Extract(args) => extract(args),
         Command::Add(args) => add(args),
+        Command::Diff(args) => diff(args),
+        Command::Patch(args) => patch(args),
+        Command::Repack(args) => repack(args),
+        Command::Verify(args) => verify(args),
     }
 }
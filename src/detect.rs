@@ -0,0 +1,350 @@
+use crate::{Campaign, DatFile, HotkeyInfo, RecordedGame, Scenario, SLP};
+use flate2::read::DeflateDecoder;
+use genie_drs::DRSArchive;
+use genie_support::Error;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// The number of bytes that are inspected to detect the format of a file.
+const SNIFF_LENGTH: u64 = 512;
+
+/// The kinds of files that can be detected by [`open`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// A recorded game file (.mgl, .mgx, .mgz).
+    RecordedGame,
+    /// A scenario file (.scn, .scx, .aoe2scenario).
+    Scenario,
+    /// A campaign file (.cpn, .cpx, .aoe2campaign).
+    Campaign,
+    /// A data file (empires2.dat, empires2_x1_p1.dat).
+    Dat,
+    /// A DRS archive.
+    DRS,
+    /// An SLP sprite file.
+    SLP,
+    /// A hotkey file (.hki).
+    Hotkeys,
+}
+
+impl FileKind {
+    /// Detect the kind of a file from its first bytes.
+    ///
+    /// The start of the file must be given, at least 512 bytes if the file is that large. The
+    /// file is not parsed, so a detected file may still fail to read.
+    ///
+    /// ```rust
+    /// use genie::FileKind;
+    /// let bytes = std::fs::read("crates/genie-drs/test.drs").unwrap();
+    /// assert_eq!(FileKind::detect(&bytes), Some(FileKind::DRS));
+    /// assert_eq!(FileKind::detect(b"not a genie file"), None);
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"Copyright (c) ") {
+            return Some(FileKind::DRS);
+        }
+        if bytes.starts_with(b"VER ") {
+            // Uncompressed data file.
+            return Some(FileKind::Dat);
+        }
+
+        match bytes.get(0..4) {
+            Some(b"2.0N") | Some(b"2.0P") => return Some(FileKind::SLP),
+            // No scenario format uses this version.
+            Some(b"2.00") => return Some(FileKind::Campaign),
+            Some(_) if is_scenario_header(bytes) => return Some(FileKind::Scenario),
+            Some(b"1.00") | Some(b"1.10") => return Some(FileKind::Campaign),
+            _ => {}
+        }
+
+        // Recorded games start with the size of the compressed header, and sometimes the
+        // position of the next header, followed by the compressed game version.
+        for offset in &[8, 4] {
+            if let Some(compressed) = bytes.get(*offset..) {
+                let header = inflate_prefix(compressed, 8);
+                if header.starts_with(b"VER 9.") || header.starts_with(b"TRL 9.") {
+                    return Some(FileKind::RecordedGame);
+                }
+            }
+        }
+
+        // Data files and hotkey files are compressed entirely.
+        let contents = inflate_prefix(bytes, 8);
+        if contents.starts_with(b"VER ") {
+            return Some(FileKind::Dat);
+        }
+        if contents.len() == 8 {
+            let version = f32::from_le_bytes([contents[0], contents[1], contents[2], contents[3]]);
+            let num_groups =
+                u32::from_le_bytes([contents[4], contents[5], contents[6], contents[7]]);
+            if (1.0..=4.0).contains(&version) && num_groups > 0 && num_groups < 256 {
+                return Some(FileKind::Hotkeys);
+            }
+        }
+
+        None
+    }
+
+    /// Get a human-readable name for this kind of file, like "recorded game".
+    pub fn name(self) -> &'static str {
+        match self {
+            FileKind::RecordedGame => "recorded game",
+            FileKind::Scenario => "scenario",
+            FileKind::Campaign => "campaign",
+            FileKind::Dat => "data file",
+            FileKind::DRS => "DRS archive",
+            FileKind::SLP => "SLP sprite",
+            FileKind::Hotkeys => "hotkey file",
+        }
+    }
+}
+
+/// Check if the bytes start with a scenario format version like "1.21", followed by the size and
+/// the version of the scenario header.
+fn is_scenario_header(bytes: &[u8]) -> bool {
+    match bytes.get(0..12) {
+        Some(&[major, b'.', minor, patch, _, _, _, _, version, 0, 0, 0]) => {
+            major.is_ascii_digit()
+                && minor.is_ascii_digit()
+                && patch.is_ascii_digit()
+                && (1..=6).contains(&version)
+        }
+        _ => false,
+    }
+}
+
+/// Decompress the start of a raw deflate stream. Returns fewer bytes if the data is not a valid
+/// deflate stream.
+fn inflate_prefix(compressed: &[u8], length: u64) -> Vec<u8> {
+    let mut contents = vec![];
+    // Any bytes that were decompressed before an error are kept.
+    let _ = DeflateDecoder::new(compressed)
+        .take(length)
+        .read_to_end(&mut contents);
+    contents
+}
+
+/// Read the version string at the start of a file of the given kind, like "1.21" or "VER 9.4".
+fn detect_version(kind: FileKind, bytes: &[u8]) -> Option<String> {
+    let version = match kind {
+        FileKind::Scenario | FileKind::Campaign => bytes.get(0..4)?.to_vec(),
+        FileKind::Dat if bytes.starts_with(b"VER ") => bytes.get(0..8)?.to_vec(),
+        FileKind::Dat => inflate_prefix(bytes, 8),
+        FileKind::RecordedGame => [8, 4]
+            .iter()
+            .filter_map(|offset| bytes.get(*offset..))
+            .map(|compressed| inflate_prefix(compressed, 8))
+            .find(|header| header.starts_with(b"VER ") || header.starts_with(b"TRL "))?,
+        FileKind::DRS | FileKind::SLP | FileKind::Hotkeys => return None,
+    };
+    let end = version
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(version.len());
+    let version = std::str::from_utf8(&version[..end]).ok()?;
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return None;
+    }
+    Some(version.to_string())
+}
+
+/// The error returned by [`open`] when the format of a file could not be detected.
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("unknown file format")]
+pub struct UnknownFormatError;
+
+/// Add the position at which reading stopped to an error.
+fn at_position(err: Error, input: &mut impl Seek) -> Error {
+    match input.stream_position() {
+        Ok(offset) => err.at_offset(offset),
+        Err(_) => err,
+    }
+}
+
+/// A parsed file of any of the formats that can be detected by [`open`].
+///
+/// Recorded games, campaigns and DRS archives keep the file handle to read their contents on
+/// demand. Scenarios and data files are boxed, because they are much larger than the other
+/// variants.
+pub enum GenieFile<R = File>
+where
+    R: Read + Seek,
+{
+    RecordedGame(RecordedGame<R>),
+    Scenario(Box<Scenario>),
+    Campaign(Campaign<R>),
+    Dat(Box<DatFile>),
+    DRS(DRSArchive<R>),
+    SLP(SLP),
+    Hotkeys(HotkeyInfo),
+}
+
+impl<R> GenieFile<R>
+where
+    R: Read + Seek,
+{
+    /// Get the kind of this file.
+    pub fn kind(&self) -> FileKind {
+        match self {
+            GenieFile::RecordedGame(_) => FileKind::RecordedGame,
+            GenieFile::Scenario(_) => FileKind::Scenario,
+            GenieFile::Campaign(_) => FileKind::Campaign,
+            GenieFile::Dat(_) => FileKind::Dat,
+            GenieFile::DRS(_) => FileKind::DRS,
+            GenieFile::SLP(_) => FileKind::SLP,
+            GenieFile::Hotkeys(_) => FileKind::Hotkeys,
+        }
+    }
+}
+
+/// Detect the format of a file and parse it. The file extension is not used.
+///
+/// Errors describe the kind of file that failed to parse and its version, if they are known.
+/// Errors in scenarios, data files, SLP sprites and hotkey files also include the offset at which
+/// reading stopped.
+///
+/// ```rust
+/// # fn main() -> anyhow::Result<()> {
+/// use genie::GenieFile;
+/// match genie::open("crates/genie-scx/test/scenarios/Age of Heroes b1-3-5.scx")? {
+///     GenieFile::Scenario(scen) => println!("{} objects", scen.objects().count()),
+///     other => panic!("unexpected file: {:?}", other.kind()),
+/// }
+/// # Ok(()) }
+/// ```
+pub fn open(path: impl AsRef<Path>) -> Result<GenieFile, Error> {
+    read_from(File::open(path)?)
+}
+
+/// Detect the format of the file in a stream and parse it.
+pub fn read_from<R: Read + Seek>(mut input: R) -> Result<GenieFile<R>, Error> {
+    let start = input.stream_position()?;
+    let mut bytes = vec![];
+    input.by_ref().take(SNIFF_LENGTH).read_to_end(&mut bytes)?;
+    input.seek(SeekFrom::Start(start))?;
+
+    let kind = FileKind::detect(&bytes).ok_or_else(|| Error::from_source(UnknownFormatError))?;
+    parse(kind, input).map_err(|err| {
+        let err = err.in_file(kind.name());
+        match detect_version(kind, &bytes) {
+            Some(version) => err.with_version(version),
+            None => err,
+        }
+    })
+}
+
+/// Parse a file of a known kind.
+fn parse<R: Read + Seek>(kind: FileKind, mut input: R) -> Result<GenieFile<R>, Error> {
+    let file = match kind {
+        FileKind::RecordedGame => GenieFile::RecordedGame(RecordedGame::new(input)?),
+        FileKind::Scenario => GenieFile::Scenario(Box::new(
+            Scenario::read_from(&mut input).map_err(|err| at_position(err.into(), &mut input))?,
+        )),
+        FileKind::Campaign => GenieFile::Campaign(Campaign::from(input)?),
+        FileKind::Dat => GenieFile::Dat(Box::new(
+            DatFile::read_from(&mut input).map_err(|err| at_position(err.into(), &mut input))?,
+        )),
+        FileKind::DRS => GenieFile::DRS(DRSArchive::new(input)?),
+        FileKind::SLP => GenieFile::SLP(
+            SLP::read_from(&mut input)
+                .map_err(|err| at_position(Error::new(kind.name(), err), &mut input))?,
+        ),
+        FileKind::Hotkeys => GenieFile::Hotkeys(
+            HotkeyInfo::from(&mut input).map_err(|err| at_position(err.into(), &mut input))?,
+        ),
+    };
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genie_slp::IndexedFrame;
+    use std::io;
+
+    fn detect(path: &str) -> anyhow::Result<Option<FileKind>> {
+        let mut bytes = vec![];
+        File::open(path)?
+            .take(SNIFF_LENGTH)
+            .read_to_end(&mut bytes)?;
+        Ok(FileKind::detect(&bytes))
+    }
+
+    #[test]
+    fn detect_fixtures() -> anyhow::Result<()> {
+        let fixtures = [
+            ("crates/genie-rec/test/aok.mgl", FileKind::RecordedGame),
+            (
+                "crates/genie-rec/test/missyou_finally_vs_11.mgx",
+                FileKind::RecordedGame,
+            ),
+            (
+                "crates/genie-rec/test/rec.20181208-195117.mgz",
+                FileKind::RecordedGame,
+            ),
+            (
+                "crates/genie-scx/test/scenarios/Bronze Age Art of War.scn",
+                FileKind::Scenario,
+            ),
+            (
+                "crates/genie-scx/test/scenarios/Corlis.aoescn",
+                FileKind::Scenario,
+            ),
+            (
+                "crates/genie-scx/test/scenarios/layertest.aoe2scenario",
+                FileKind::Scenario,
+            ),
+            (
+                "crates/genie-cpx/test/campaigns/10 The First Punic War.aoecpn",
+                FileKind::Campaign,
+            ),
+            (
+                "crates/genie-cpx/test/campaigns/DER FALL VON SACSAHUAMAN - TEIL I.cpx",
+                FileKind::Campaign,
+            ),
+            (
+                "crates/genie-cpx/test/campaigns/acam1.aoe2campaign",
+                FileKind::Campaign,
+            ),
+            ("crates/genie-dat/fixtures/aok.dat", FileKind::Dat),
+            ("crates/genie-drs/test.drs", FileKind::DRS),
+            ("crates/genie-hki/test/files/aoc1.hki", FileKind::Hotkeys),
+            ("crates/genie-hki/test/files/hd0.hki", FileKind::Hotkeys),
+        ];
+        for (path, kind) in &fixtures {
+            assert_eq!(detect(path)?, Some(*kind), "{}", path);
+        }
+        assert_eq!(detect("Cargo.toml")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn open_files() -> anyhow::Result<()> {
+        let mut slp = vec![];
+        SLP::from_frames(&[IndexedFrame::new(2, 2)]).write_to(&mut slp)?;
+        let file = read_from(io::Cursor::new(slp))?;
+        assert_eq!(file.kind(), FileKind::SLP);
+
+        match open("crates/genie-hki/test/files/aoc1.hki")? {
+            GenieFile::Hotkeys(info) => assert_eq!(info.num_groups(), 14),
+            other => panic!("expected a hotkey file, got {:?}", other.kind()),
+        }
+        let err = open("Cargo.toml").err().unwrap();
+        assert!(err.downcast_ref::<UnknownFormatError>().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn error_context() -> anyhow::Result<()> {
+        let mut bytes = std::fs::read("crates/genie-scx/test/scenarios/Corlis.aoescn")?;
+        bytes.truncate(SNIFF_LENGTH as usize);
+        let err = read_from(io::Cursor::new(bytes)).err().unwrap();
+        let context = err.context();
+        assert_eq!(context.file_kind(), Some("scenario"));
+        assert_eq!(context.version(), Some("3.13"));
+        assert!(context.offset().is_some());
+        assert!(err.to_string().contains("(in scenario, at offset "));
+        Ok(())
+    }
+}
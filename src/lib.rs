@@ -1,5 +1,26 @@
 //! Libraries for reading/writing Age of Empires 2 data files.
 //!
+//! ## Opening Files
+//!
+//! [`open`] detects the format of a file from its contents, so applications do not need to
+//! depend on file extensions. It can detect recorded games, scenarios, campaigns, data files,
+//! DRS archives, SLP sprites and hotkey files.
+//!
+//! Errors from all formats are reported as an [`Error`], which says what kind of file failed to
+//! parse, and where in the file the problem is if that is known. The error types of the
+//! individual crates can be converted to it with `?`.
+//!
+//! ```rust
+//! # fn main() -> anyhow::Result<()> {
+//! use genie::{FileKind, GenieFile};
+//! let file = genie::open("./crates/genie-dat/fixtures/aok.dat")?;
+//! assert_eq!(file.kind(), FileKind::Dat);
+//! if let GenieFile::Dat(dat) = file {
+//!     assert_eq!(dat.civilizations.len(), 14);
+//! }
+//! # Ok(()) }
+//! ```
+//!
 //! ## Data Files
 //!
 //! > Supported version range: Age of Empires 2: Age of Kings, Age of Conquerors, HD Edition
@@ -8,17 +29,25 @@
 //! version is detected automatically, based on the amount of terrains included in the file (since
 //! that is hardcoded in each game executable).
 //!
-//! Writing data files is not yet supported, and many of the things that the library reads are not
-//! yet exposed in the public API.
+//! Data files can be written back with `DatFile::write_to`, which compresses them like the games
+//! do. `DatFile::write_to_with` takes a `DatCompression` to pick the compression level, or to write
+//! an uncompressed file for debugging; uncompressed files are detected when reading. Many of the
+//! things that the library reads are not yet exposed in the public API.
 //!
 //! ```rust
 //! # fn main() -> anyhow::Result<()> {
+//! use genie::dat::DatCompression;
 //! use genie::DatFile;
 //! let mut input = std::fs::File::open("./crates/genie-dat/fixtures/aok.dat")?;
 //!
 //! let dat = DatFile::read_from(&mut input)?;
 //! assert_eq!(dat.civilizations.len(), 14);
 //! assert_eq!(dat.civilizations[1].name(), "British");
+//!
+//! let mut output = vec![];
+//! dat.write_to_with(&mut output, DatCompression::Uncompressed)?;
+//! let dat = DatFile::read_from(&output[..])?;
+//! assert_eq!(dat.civilizations.len(), 14);
 //! # Ok(()) }
 //! ```
 //!
@@ -68,12 +97,25 @@
 //!
 //! Hotkey files contain groups of key mappings for different game areas.
 //!
+//! ## Sprite Files
+//!
+//! > Supported version range: Age of Empires 1/2, Star Wars: Galactic Battlegrounds
+//!
+//! SLP files contain the frames of unit and building graphics. genie-slp can decode frames to
+//! RGBA colours using a palette, including player colours, shadows and outlines, and encode
+//! paletted images into new SLP files.
+//!
+//! Definitive Edition uses SMX files instead, and SMP files as their uncompressed sources.
+//! genie-smx can read both, write SMX files, and convert their frames to and from SLP frames.
+//!
 //! ## Palette Files
 //!
 //! > Supported version range: all versions
 //!
 //! Palette files contain the 256-bit colour palettes used in different areas of the game. Each
 //! palette contains up to 256 r, g, b colour values. Both reading and writing is supported.
+//! The extended palette files of the Definitive Edition, which add an alpha channel, are
+//! supported as well.
 
 #![deny(future_incompatible)]
 #![deny(nonstandard_style)]
@@ -82,6 +124,8 @@
 #![warn(unused)]
 #![allow(missing_docs)]
 
+mod detect;
+
 pub use genie_cpx as cpx;
 pub use genie_dat as dat;
 pub use genie_drs as drs;
@@ -89,8 +133,11 @@ pub use genie_hki as hki;
 pub use genie_lang as lang;
 pub use genie_rec as rec;
 pub use genie_scx as scx;
+pub use genie_slp as slp;
+pub use genie_smx as smx;
 pub use jascpal as pal;
 
+pub use detect::{open, read_from, FileKind, GenieFile, UnknownFormatError};
 pub use genie_cpx::Campaign;
 pub use genie_dat::DatFile;
 pub use genie_drs::{DRSReader, DRSWriter};
@@ -98,4 +145,7 @@ pub use genie_hki::HotkeyInfo;
 pub use genie_lang::LangFile;
 pub use genie_rec::RecordedGame;
 pub use genie_scx::Scenario;
+pub use genie_slp::SLP;
+pub use genie_smx::{SMP, SMX};
+pub use genie_support::{Error, ErrorContext};
 pub use jascpal::Palette;